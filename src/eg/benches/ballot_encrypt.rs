@@ -0,0 +1,81 @@
+// Copyright (C) Microsoft Corporation. All rights reserved.
+
+//! Benchmarks encrypting a ballot for the example election manifest.
+//!
+//! Compare `cargo bench -p eg` with `cargo bench -p eg --features parallel`.
+
+#![allow(clippy::unwrap_used)]
+
+use criterion::{criterion_group, criterion_main, Criterion};
+
+use eg::{
+    ballot::BallotEncrypted, contest_selection::ContestSelection, device::Device,
+    election_record::PreVotingData, example_election::ExampleElection,
+    example_election_manifest::example_election_manifest,
+    example_election_parameters::example_election_parameters, hashes::Hashes,
+    hashes_ext::HashesExt, joint_election_public_key::JointElectionPublicKey, vec1::Vec1,
+};
+use util::csprng::Csprng;
+
+fn bench_ballot_encrypt(c: &mut Criterion) {
+    let mut csprng = Csprng::new(b"bench_ballot_encrypt");
+
+    let election_parameters = example_election_parameters();
+    let election_manifest = example_election_manifest();
+
+    let (_, guardian_public_keys) =
+        ExampleElection::generate_guardian_keys(&mut csprng, &election_parameters).unwrap();
+
+    let joint_election_public_key =
+        JointElectionPublicKey::compute(&election_parameters, &guardian_public_keys).unwrap();
+    let hashes = Hashes::compute(&election_parameters, &election_manifest).unwrap();
+    let hashes_ext = HashesExt::compute(
+        &election_parameters,
+        &hashes,
+        &joint_election_public_key,
+        &guardian_public_keys,
+    );
+
+    let device = Device::new(
+        "bench",
+        PreVotingData::new(
+            election_manifest,
+            election_parameters,
+            hashes,
+            hashes_ext,
+            joint_election_public_key,
+        ),
+    );
+
+    let mut ctest_selections = Vec1::new();
+    for contest_ix in device.header.manifest.contests.indices() {
+        let contest = device.header.manifest.contests.get(contest_ix).unwrap();
+        ctest_selections
+            .try_push(ContestSelection::new_pick_random(
+                &mut csprng,
+                contest.selection_limit,
+                contest.options.len(),
+            ))
+            .unwrap();
+    }
+
+    let primary_nonce = [0u8; 32];
+
+    let mut group = c.benchmark_group("ballot_encrypt");
+    group.sample_size(10);
+    group.bench_function("example_manifest", |b| {
+        b.iter(|| {
+            BallotEncrypted::new_from_selections(
+                &device,
+                &mut csprng,
+                &primary_nonce,
+                &ctest_selections,
+            )
+            .unwrap()
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, bench_ballot_encrypt);
+criterion_main!(benches);
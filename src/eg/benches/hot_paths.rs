@@ -0,0 +1,279 @@
+// Copyright (C) Microsoft Corporation. All rights reserved.
+
+//! Benchmarks of the operations which dominate the cost of an election: encrypting a
+//! ballot, verifying proofs, computing the joint key, and decrypting.
+//!
+//! By default everything uses toy parameters, with a 127-bit `p` and a 61-bit `q`, so that
+//! iterating on a change is fast. Their timings are only comparable with each other, since
+//! the cost of modular exponentiation depends on the size of `p`. Set `EG_BENCH_FULL_SIZE=1`
+//! to use the standard parameters as real elections do, encrypt ballots for the whole example
+//! manifest rather than its first contest, and solve discrete logs up to a million.
+
+#![allow(clippy::unwrap_used)]
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+
+use num_bigint::BigUint;
+
+use eg::{
+    ballot::BallotEncrypted,
+    contest_selection::ContestSelection,
+    decryption_share::{combine_decryption_shares, verify_decryption_share, DecryptionShare},
+    device::Device,
+    election_parameters::ElectionParameters,
+    example_election::ExampleElection,
+    example_election_manifest::{
+        example_election_manifest, example_election_manifest_first_contests,
+    },
+    example_election_parameters::example_election_parameters,
+    fixed_parameters::{FixedParameterGenerationParameters, FixedParameters, NumsNumber},
+    guardian::GuardianIndex,
+    guardian_key_proof::verify_guardian_key_proofs,
+    guardian_key_share::GuardianKeyShare,
+    guardian_public_key::GuardianPublicKey,
+    joint_election_public_key::JointElectionPublicKey,
+    vec1::Vec1,
+};
+use util::{csprng::Csprng, prime::BigUintPrime};
+
+fn full_size() -> bool {
+    std::env::var_os("EG_BENCH_FULL_SIZE").is_some()
+}
+
+/// Parameters with the structure of the standard ones, `p = r·q + 1` with `g` generating the
+/// subgroup of order `q`, but far too small to be secure.
+fn toy_fixed_parameters() -> FixedParameters {
+    let biguint = |s: &str| s.parse::<BigUint>().unwrap();
+    let prime = BigUintPrime::new_unchecked_the_caller_guarantees_that_this_number_is_prime;
+
+    // q = 2^61 - 1, and r is the least even number of at least 2^65 for which p is prime.
+    FixedParameters {
+        opt_ElectionGuard_Design_Specification: None,
+        generation_parameters: FixedParameterGenerationParameters {
+            q_bits_total: 61,
+            p_bits_total: 127,
+            p_bits_msb_fixed_1: 0,
+            p_middle_bits_source: NumsNumber::ln_2,
+            p_bits_lsb_fixed_1: 0,
+        },
+        p: prime(biguint("85070591730234615921183884079070707673")),
+        q: prime(biguint("2305843009213693951")),
+        r: biguint("36893488147419103272"),
+        g: biguint("68033170602847395304410337608369475640"),
+    }
+}
+
+/// The example election parameters, with the toy fixed parameters unless full size.
+fn election_parameters() -> ElectionParameters {
+    let mut election_parameters = example_election_parameters();
+    if !full_size() {
+        election_parameters.fixed_parameters = toy_fixed_parameters();
+    }
+    election_parameters
+}
+
+/// The example election, with `n` guardians all of whom are needed to decrypt.
+struct Setup {
+    guardian_public_keys: Vec<GuardianPublicKey>,
+    guardian_key_shares: Vec<GuardianKeyShare>,
+    device: Device,
+}
+
+fn setup(csprng: &mut Csprng, n: u32) -> Setup {
+    let mut election_parameters = election_parameters();
+    election_parameters.varying_parameters.n = GuardianIndex::from_one_based_index(n).unwrap();
+    election_parameters.varying_parameters.k = election_parameters.varying_parameters.n;
+
+    let election_manifest = if full_size() {
+        example_election_manifest()
+    } else {
+        example_election_manifest_first_contests(1)
+    };
+
+    let ExampleElection {
+        guardian_public_keys,
+        guardian_key_shares,
+        pvd,
+        ..
+    } = ExampleElection::generate(csprng, election_parameters, election_manifest).unwrap();
+
+    Setup {
+        guardian_public_keys,
+        guardian_key_shares,
+        device: Device::new("bench", pvd),
+    }
+}
+
+fn random_selections(csprng: &mut Csprng, device: &Device) -> Vec1<ContestSelection> {
+    let contests = &device.header.manifest.contests;
+    let mut ctest_selections = Vec1::new();
+    for contest_ix in contests.indices() {
+        let contest = contests.get(contest_ix).unwrap();
+        ctest_selections
+            .try_push(ContestSelection::new_pick_random(
+                csprng,
+                contest.selection_limit,
+                contest.options.len(),
+            ))
+            .unwrap();
+    }
+    ctest_selections
+}
+
+fn bench_ballot_encrypt(c: &mut Criterion) {
+    let mut csprng = Csprng::new(b"bench_hot_paths_ballot_encrypt");
+    let Setup { device, .. } = setup(&mut csprng, 5);
+    let ctest_selections = random_selections(&mut csprng, &device);
+
+    let mut group = c.benchmark_group("hot_paths");
+    group.sample_size(10);
+    group.bench_function("ballot_encrypt", |b| {
+        b.iter(|| {
+            BallotEncrypted::new_from_selections(
+                &device,
+                &mut csprng,
+                &[0u8; 32],
+                &ctest_selections,
+            )
+            .unwrap()
+        })
+    });
+    group.finish();
+}
+
+fn bench_proof_verify(c: &mut Criterion) {
+    let mut csprng = Csprng::new(b"bench_hot_paths_proof_verify");
+    let Setup {
+        guardian_public_keys,
+        guardian_key_shares,
+        device,
+    } = setup(&mut csprng, 5);
+    let pvd = &device.header;
+
+    let ctest_selections = random_selections(&mut csprng, &device);
+    let ballot =
+        BallotEncrypted::new_from_selections(&device, &mut csprng, &[0u8; 32], &ctest_selections)
+            .unwrap();
+    let contest = ballot.contests.indices().next().unwrap();
+    let contest = ballot.contests.get(contest).unwrap();
+    let ciphertext = &contest.selection[0];
+    let proof = contest.proof_ballot_correctness.indices().next().unwrap();
+    let proof = contest.proof_ballot_correctness.get(proof).unwrap();
+
+    let decryption_share =
+        DecryptionShare::compute(&mut csprng, pvd, &guardian_key_shares[0], ciphertext);
+    let public_key_share = guardian_key_shares[0].public_key_share();
+
+    let mut group = c.benchmark_group("hot_paths");
+    group.bench_function("proof_verify/range", |b| {
+        b.iter(|| assert!(proof.verify(pvd, ciphertext, 1)))
+    });
+    group.bench_function("proof_verify/decryption_share", |b| {
+        b.iter(|| {
+            verify_decryption_share(pvd, public_key_share, ciphertext, &decryption_share).unwrap()
+        })
+    });
+    group.bench_function("proof_verify/schnorr", |b| {
+        b.iter(|| verify_guardian_key_proofs(&pvd.parameters, &guardian_public_keys[0]).unwrap())
+    });
+    group.finish();
+}
+
+fn bench_joint_key(c: &mut Criterion) {
+    let mut csprng = Csprng::new(b"bench_hot_paths_joint_key");
+
+    let mut election_parameters = election_parameters();
+    election_parameters.varying_parameters.n = GuardianIndex::from_one_based_index(10).unwrap();
+    election_parameters.varying_parameters.k = GuardianIndex::from_one_based_index(6).unwrap();
+
+    let (_, guardian_public_keys) =
+        ExampleElection::generate_guardian_keys(&mut csprng, &election_parameters).unwrap();
+
+    let mut group = c.benchmark_group("hot_paths");
+    group.bench_function("joint_key/n=10", |b| {
+        b.iter(|| {
+            JointElectionPublicKey::compute(&election_parameters, &guardian_public_keys).unwrap()
+        })
+    });
+    group.finish();
+}
+
+fn bench_decrypt(c: &mut Criterion) {
+    let mut csprng = Csprng::new(b"bench_hot_paths_decrypt");
+    let Setup {
+        guardian_key_shares,
+        device,
+        ..
+    } = setup(&mut csprng, 5);
+    let pvd = &device.header;
+    let fixed_parameters = &pvd.parameters.fixed_parameters;
+
+    let vote = 3;
+    let nonce = fixed_parameters.q.random_group_elem(&mut csprng);
+    let ciphertext = pvd
+        .public_key
+        .encrypt_with(fixed_parameters, &nonce, vote, false);
+
+    let mut group = c.benchmark_group("hot_paths");
+    group.sample_size(10);
+    group.bench_function("decrypt/full_quorum", |b| {
+        b.iter(|| {
+            let decryption_shares = guardian_key_shares
+                .iter()
+                .map(|key_share| DecryptionShare::compute(&mut csprng, pvd, key_share, &ciphertext))
+                .collect::<Vec<_>>();
+            let t = combine_decryption_shares(pvd, &ciphertext, &decryption_shares).unwrap();
+            assert_eq!(t.discrete_log(pvd, 10), Some(vote as u64));
+        })
+    });
+    group.finish();
+}
+
+/// The discrete log is only reachable from a combined decryption, so this decrypts an
+/// encryption of the largest value in range with a single guardian.
+fn bench_discrete_log(c: &mut Criterion) {
+    let mut csprng = Csprng::new(b"bench_hot_paths_discrete_log");
+    let Setup {
+        guardian_key_shares,
+        device,
+        ..
+    } = setup(&mut csprng, 1);
+    let pvd = &device.header;
+    let fixed_parameters = &pvd.parameters.fixed_parameters;
+
+    let bounds: &[u64] = if full_size() {
+        &[1_000, 100_000, 1_000_000]
+    } else {
+        &[1_000, 10_000]
+    };
+
+    let mut group = c.benchmark_group("hot_paths/discrete_log");
+    group.sample_size(10);
+    for &bound in bounds {
+        // The largest value in range is the worst case for the giant steps.
+        let nonce = fixed_parameters.q.random_group_elem(&mut csprng);
+        let ciphertext =
+            pvd.public_key
+                .encrypt_with(fixed_parameters, &nonce, bound as usize, false);
+        let decryption_shares = guardian_key_shares
+            .iter()
+            .map(|key_share| DecryptionShare::compute(&mut csprng, pvd, key_share, &ciphertext))
+            .collect::<Vec<_>>();
+        let t = combine_decryption_shares(pvd, &ciphertext, &decryption_shares).unwrap();
+
+        group.bench_with_input(BenchmarkId::from_parameter(bound), &bound, |b, &bound| {
+            b.iter(|| assert_eq!(t.discrete_log(pvd, bound), Some(bound)))
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(
+    benches,
+    bench_ballot_encrypt,
+    bench_proof_verify,
+    bench_joint_key,
+    bench_decrypt,
+    bench_discrete_log
+);
+criterion_main!(benches);
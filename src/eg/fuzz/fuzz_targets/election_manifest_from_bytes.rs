@@ -0,0 +1,15 @@
+// Copyright (C) Microsoft Corporation. All rights reserved.
+
+//! Feeds arbitrary bytes to [`ElectionManifest::from_bytes`].
+//!
+//! The manifest is untrusted input, so parsing must only ever return `Err`, never panic.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+use eg::election_manifest::ElectionManifest;
+
+fuzz_target!(|data: &[u8]| {
+    let _ = ElectionManifest::from_bytes(data);
+});
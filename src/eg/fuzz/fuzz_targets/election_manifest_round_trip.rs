@@ -0,0 +1,26 @@
+// Copyright (C) Microsoft Corporation. All rights reserved.
+
+//! For any input that parses as an [`ElectionManifest`], checks that
+//! `ElectionManifest -> canonical bytes -> ElectionManifest` yields an equal manifest,
+//! and that the canonical bytes are a fixed point.
+
+#![no_main]
+#![allow(clippy::unwrap_used)]
+
+use libfuzzer_sys::fuzz_target;
+
+use eg::election_manifest::ElectionManifest;
+
+fuzz_target!(|data: &[u8]| {
+    let Ok(election_manifest) = ElectionManifest::from_bytes(data) else {
+        return;
+    };
+
+    let canonical_bytes = election_manifest.to_canonical_bytes().unwrap();
+
+    let election_manifest_2 = ElectionManifest::from_bytes(&canonical_bytes).unwrap();
+    assert_eq!(election_manifest, election_manifest_2);
+
+    let canonical_bytes_2 = election_manifest_2.to_canonical_bytes().unwrap();
+    assert_eq!(canonical_bytes, canonical_bytes_2);
+});
@@ -0,0 +1,237 @@
+// Copyright (C) Microsoft Corporation. All rights reserved.
+
+#![deny(clippy::unwrap_used)]
+#![deny(clippy::expect_used)]
+#![deny(clippy::panic)]
+#![deny(clippy::manual_assert)]
+
+//! Explanations for artifact files which can't be parsed because they are the wrong kind of
+//! file, or are damaged, rather than because of what they contain.
+//!
+//! Without these, a truncated file or a binary file given where JSON is expected shows up as
+//! a serde error about an unexpected character or end of input.
+//!
+//! Some Windows editors save files with a UTF-8 byte order mark, which JSON doesn't allow.
+//! It carries no information, so it is skipped rather than reported.
+//!
+//! Artifacts are read into memory whole, so reads are capped at a maximum size, lest an
+//! oversized or endless file exhaust memory.
+
+use std::io::Read;
+
+use anyhow::{ensure, Context, Result};
+
+use crate::ballot_packed::PACKED_BALLOT_MAGIC;
+
+/// The UTF-8 encoding of U+FEFF, the byte order mark.
+pub const UTF8_BOM: &[u8] = b"\xEF\xBB\xBF";
+
+/// The largest artifact file, such as an election manifest, read by [`parse_json_artifact`].
+pub const MAX_ARTIFACT_BYTES: u64 = 64 * 1024 * 1024;
+
+/// The largest election record, which contains every ballot, that will be read.
+pub const MAX_ELECTION_RECORD_BYTES: u64 = 1024 * 1024 * 1024;
+
+/// Reads all of `stdioread`, failing without reading further once more than `max_bytes`
+/// bytes have been read.
+pub fn read_to_end_bounded(
+    stdioread: &mut dyn std::io::Read,
+    what: &str,
+    max_bytes: u64,
+) -> Result<Vec<u8>> {
+    let mut bytes = Vec::new();
+    stdioread
+        .take(max_bytes.saturating_add(1))
+        .read_to_end(&mut bytes)
+        .with_context(|| format!("Reading {what}"))?;
+
+    ensure!(
+        bytes.len() as u64 <= max_bytes,
+        "The {what} file exceeds the maximum size of {max_bytes} bytes"
+    );
+
+    Ok(bytes)
+}
+
+/// Returns `bytes` without its leading UTF-8 byte order mark, if it has one.
+pub fn strip_utf8_bom(bytes: &[u8]) -> &[u8] {
+    bytes.strip_prefix(UTF8_BOM).unwrap_or(bytes)
+}
+
+/// Returns an explanation of why `bytes` can't be `what` JSON, if the problem is with the
+/// format of the file rather than its contents.
+///
+/// Returns `None` if `bytes` is well-formed JSON, in which case any parse error is about the
+/// values it contains.
+pub fn diagnose_json(bytes: &[u8], what: &str) -> Option<String> {
+    if bytes.starts_with(PACKED_BALLOT_MAGIC) {
+        return Some(format!("File is a packed binary ballot, not {what} JSON"));
+    }
+
+    if let Err(e) = std::str::from_utf8(bytes) {
+        return Some(format!(
+            "File is not valid UTF-8 at byte {}, so it can't be {what} JSON. Is it a binary file?",
+            e.valid_up_to()
+        ));
+    }
+
+    match serde_json::from_slice::<serde::de::IgnoredAny>(bytes) {
+        Ok(_) => None,
+        Err(e) if e.is_eof() => Some(format!(
+            "File appears truncated: the {what} JSON ends unexpectedly at line {} column {}",
+            e.line(),
+            e.column()
+        )),
+        Err(e) => Some(format!("File is not valid {what} JSON: {e}")),
+    }
+}
+
+/// Reads all of `stdioread`, up to [`MAX_ARTIFACT_BYTES`], and parses it with `parse`,
+/// skipping any leading UTF-8 byte order mark. If that fails, the error is prefixed with the
+/// explanation from [`diagnose_json`], if any.
+pub fn parse_json_artifact<T>(
+    stdioread: &mut dyn std::io::Read,
+    what: &str,
+    parse: impl FnOnce(&mut dyn std::io::Read) -> Result<T>,
+) -> Result<T> {
+    parse_json_artifact_with_limit(stdioread, what, MAX_ARTIFACT_BYTES, parse)
+}
+
+/// As [`parse_json_artifact`], but for an artifact of at most `max_bytes` bytes.
+pub fn parse_json_artifact_with_limit<T>(
+    stdioread: &mut dyn std::io::Read,
+    what: &str,
+    max_bytes: u64,
+    parse: impl FnOnce(&mut dyn std::io::Read) -> Result<T>,
+) -> Result<T> {
+    let bytes = read_to_end_bounded(stdioread, what, max_bytes)?;
+    let bytes = strip_utf8_bom(&bytes);
+
+    parse(&mut &bytes[..]).map_err(|e| match diagnose_json(bytes, what) {
+        Some(diagnosis) => e.context(diagnosis),
+        None => e,
+    })
+}
+
+/// Serializes `value` as canonical JSON and returns a reader over it, so that tests can read
+/// an artifact with its `from_stdioread` functions without writing it to a file.
+///
+/// Canonical here means compact, with the keys of every object in sorted order, so the bytes
+/// don't depend on the order in which a type happens to declare its fields.
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+pub(crate) fn artifact_reader(value: &impl serde::Serialize) -> impl std::io::Read {
+    // `serde_json::Value` keeps object keys sorted.
+    let value = serde_json::to_value(value).unwrap();
+    std::io::Cursor::new(serde_json::to_vec(&value).unwrap())
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod test {
+    use super::*;
+    use crate::{
+        election_manifest::ElectionManifest, example_election_manifest::example_election_manifest,
+    };
+
+    #[test]
+    fn test_diagnose_json() {
+        let mut json = Vec::new();
+        example_election_manifest()
+            .to_stdiowrite_pretty(&mut json)
+            .unwrap();
+
+        let parse = |bytes: &[u8]| {
+            parse_json_artifact(&mut &bytes[..], "election manifest", |r| {
+                ElectionManifest::from_stdioread_validated(r)
+            })
+        };
+
+        parse(&json).unwrap();
+        assert_eq!(diagnose_json(&json, "election manifest"), None);
+
+        let e = format!("{:#}", parse(&json[..json.len() / 2]).unwrap_err());
+        assert!(e.starts_with("File appears truncated"), "{e}");
+        assert!(e.contains("line "), "{e}");
+
+        let e = format!("{:#}", parse(&[0x1f, 0x8b, 0x08, 0x00, 0xff]).unwrap_err());
+        assert!(e.contains("not valid UTF-8 at byte 1"), "{e}");
+
+        let e = format!("{:#}", parse(b"EGPB\x03\x00").unwrap_err());
+        assert!(e.contains("packed binary ballot"), "{e}");
+
+        let e = format!("{:#}", parse(b"{ \"label\": \"x\" ] }").unwrap_err());
+        assert!(
+            e.starts_with("File is not valid election manifest JSON"),
+            "{e}"
+        );
+        assert!(e.contains("column"), "{e}");
+
+        // A byte order mark is skipped.
+        let with_bom = [UTF8_BOM, &json].concat();
+        assert_eq!(parse(&with_bom).unwrap(), parse(&json).unwrap());
+        assert_eq!(
+            ElectionManifest::from_bytes(&with_bom).unwrap(),
+            parse(&json).unwrap()
+        );
+        let e = format!("{:#}", parse(&with_bom[..json.len() / 2]).unwrap_err());
+        assert!(e.starts_with("File appears truncated"), "{e}");
+
+        // Well-formed JSON which isn't a manifest gets just the parse error.
+        let e = format!("{:#}", parse(b"{}").unwrap_err());
+        assert!(!e.starts_with("File"), "{e}");
+    }
+
+    #[test]
+    fn test_read_to_end_bounded() {
+        let json = br#"{ "label": "x" }"#;
+        let len = json.len() as u64;
+
+        assert_eq!(
+            read_to_end_bounded(&mut &json[..], "test", len).unwrap(),
+            json
+        );
+
+        let e = read_to_end_bounded(&mut &json[..], "test", len - 1).unwrap_err();
+        assert!(e.to_string().contains("exceeds the maximum size of"), "{e}");
+
+        // An endless input is cut off at the limit rather than read until memory runs out.
+        let e = read_to_end_bounded(&mut std::io::repeat(b' '), "test", 1024).unwrap_err();
+        assert!(e.to_string().contains("1024 bytes"), "{e}");
+
+        let e = parse_json_artifact_with_limit(
+            &mut std::io::repeat(b'['),
+            "election manifest",
+            4096,
+            |r| ElectionManifest::from_stdioread_validated(r),
+        )
+        .unwrap_err();
+        assert!(
+            e.to_string()
+                .starts_with("The election manifest file exceeds"),
+            "{e}"
+        );
+    }
+
+    #[test]
+    fn test_artifact_reader() {
+        let election_manifest = example_election_manifest();
+        let election_manifest_2 =
+            ElectionManifest::from_stdioread_validated(&mut artifact_reader(&election_manifest))
+                .unwrap();
+        assert_eq!(election_manifest_2, election_manifest);
+
+        #[derive(serde::Serialize)]
+        struct Unsorted {
+            b: u8,
+            a: Vec<Unsorted>,
+        }
+        let value = Unsorted {
+            b: 1,
+            a: vec![Unsorted { b: 2, a: vec![] }],
+        };
+        let mut json = String::new();
+        artifact_reader(&value).read_to_string(&mut json).unwrap();
+        assert_eq!(json, r#"{"a":[{"a":[],"b":2}],"b":1}"#);
+    }
+}
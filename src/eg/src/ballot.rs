@@ -5,18 +5,24 @@
 #![deny(clippy::panic)]
 #![deny(clippy::manual_assert)]
 
-use anyhow::{Context, Result};
+use anyhow::{bail, ensure, Context, Result};
+use num_bigint::BigUint;
 use serde::{Deserialize, Serialize};
 use util::csprng::Csprng;
 
 use crate::{
-    confirmation_code::confirmation_code,
+    ballot_style::{BallotStyle, BallotStyleIndex},
+    confirmation_code::{b_aux, confirmation_code},
     contest_encrypted::ContestEncrypted,
     contest_selection::{ContestSelection, ContestSelectionIndex},
     device::Device,
     election_manifest::ContestIndex,
-    hash::HValue,
+    election_record::PreVotingData,
+    hash::{eg_h, HValue},
+    index::Index,
+    joint_election_public_key::Ciphertext,
     vec1::Vec1,
+    zk::ProofRange,
 };
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
@@ -26,6 +32,101 @@ pub enum BallotState {
     Challenged,
 }
 
+/// A stable identifier for a [`BallotEncrypted`], derived from its contents.
+///
+/// See [`BallotEncrypted::ballot_id`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct BallotId(pub HValue);
+
+impl std::fmt::Display for BallotId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0.to_string_hex_no_prefix_suffix())
+    }
+}
+
+/// Identifies the election key under which a [`BallotEncrypted`] was encrypted, so that a
+/// ballot can be matched to the right [`PreVotingData`](crate::election_record::PreVotingData)
+/// when verifying an archive spanning several elections or key rotations.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BallotKeyVersion {
+    /// The extended base hash `H_E`.
+    pub h_e: HValue,
+
+    /// See [`JointElectionPublicKey::fingerprint`](crate::joint_election_public_key::JointElectionPublicKey::fingerprint).
+    pub public_key_fingerprint: HValue,
+}
+
+impl BallotKeyVersion {
+    /// Returns the key version of ballots encrypted for the election described by `pvd`.
+    pub fn of(pvd: &PreVotingData) -> Self {
+        BallotKeyVersion {
+            h_e: pvd.hashes_ext.h_e,
+            public_key_fingerprint: pvd.public_key.fingerprint(&pvd.parameters),
+        }
+    }
+}
+
+/// An encryption nonce `ξ` revealed by [`BallotEncrypted::disclose_nonces`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DisclosedNonce(
+    #[serde(
+        serialize_with = "util::biguint_serde::biguint_serialize",
+        deserialize_with = "util::biguint_serde::biguint_deserialize"
+    )]
+    pub BigUint,
+);
+
+/// The encryption nonces of one contest of a challenged ballot.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ContestNonceDisclosure {
+    /// One nonce for each ciphertext in [`ContestEncrypted::selection`], in the same order.
+    pub selection: Vec<DisclosedNonce>,
+
+    /// The nonce of the blank indicator, present iff the contest has one.
+    #[serde(
+        rename = "blank_indicator",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub opt_blank_indicator: Option<DisclosedNonce>,
+
+    /// The nonce of the undervote count, present iff the contest has one. It is derived from
+    /// the selection nonces, but is disclosed too so that the count can be checked on its own.
+    #[serde(rename = "undervote", default, skip_serializing_if = "Option::is_none")]
+    pub opt_undervote: Option<DisclosedNonce>,
+}
+
+/// The encryption nonces of a challenged ballot, published so that anyone can re-encrypt the
+/// voter's selections and confirm that the device encrypted what the voter chose.
+///
+/// The nonces decrypt the ballot just as well as the guardians could, so they are only ever
+/// disclosed for ballots which are [`BallotState::Challenged`] and will not be counted.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BallotNonceDisclosure {
+    /// The [`BallotEncrypted::ballot_id`] of the ballot the nonces belong to.
+    pub ballot_id: BallotId,
+
+    /// One entry per contest, in the order of [`BallotEncrypted::contests`].
+    pub contests: Vec1<ContestNonceDisclosure>,
+}
+
+impl BallotNonceDisclosure {
+    /// Reads a `BallotNonceDisclosure` from a `std::io::Read`.
+    pub fn from_stdioread(stdioread: &mut dyn std::io::Read) -> Result<Self> {
+        serde_json::from_reader(stdioread).context("Reading BallotNonceDisclosure")
+    }
+
+    /// Writes a `BallotNonceDisclosure` to a `std::io::Write`.
+    pub fn to_stdiowrite(&self, stdiowrite: &mut dyn std::io::Write) -> Result<()> {
+        let mut ser = serde_json::Serializer::pretty(stdiowrite);
+
+        self.serialize(&mut ser)
+            .map_err(Into::<anyhow::Error>::into)
+            .and_then(|_| ser.into_inner().write_all(b"\n").map_err(Into::into))
+            .context("Writing BallotNonceDisclosure")
+    }
+}
+
 /// An encrypted ballot.
 #[derive(Debug, Serialize, Deserialize)]
 pub struct BallotEncrypted {
@@ -43,6 +144,29 @@ pub struct BallotEncrypted {
 
     /// Device that generated this ballot
     pub device: String,
+
+    /// The style of this ballot, if known. It is bound to the confirmation code through
+    /// `B_aux`.
+    #[serde(
+        rename = "ballot_style",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub opt_ballot_style: Option<BallotStyleIndex>,
+
+    /// The election key this ballot was encrypted under, if recorded.
+    /// Ballots written before this was recorded do not have it.
+    #[serde(
+        rename = "key_version",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub opt_key_version: Option<BallotKeyVersion>,
+
+    /// Non-secret metadata, such as a device id or timestamp, stored in the clear but covered
+    /// by the confirmation code through `B_aux`. See [`b_aux`].
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub associated_data: Vec<(String, String)>,
     // TODO: Have an optional field to store election record data for pre-encrypted ballots
 }
 
@@ -60,6 +184,9 @@ impl BallotEncrypted {
             confirmation_code,
             date: date.to_string(),
             device: device.to_string(),
+            opt_ballot_style: None,
+            opt_key_version: None,
+            associated_data: Vec::new(),
         }
     }
 
@@ -68,47 +195,233 @@ impl BallotEncrypted {
         csprng: &mut Csprng,
         primary_nonce: &[u8],
         ctest_selections: &Vec1<ContestSelection>,
-    ) -> BallotEncrypted {
-        let mut contests = Vec1::with_capacity(ctest_selections.len());
+    ) -> Result<BallotEncrypted> {
+        Self::new_from_selections_with_associated_data(
+            device,
+            csprng,
+            primary_nonce,
+            ctest_selections,
+            Vec::new(),
+        )
+    }
 
-        for i in 1..ctest_selections.len() + 1 {
-            #[allow(clippy::unwrap_used)] //? TODO: Remove temp development code
-            let c_idx = ContestIndex::from_one_based_index(i as u32).unwrap();
+    /// As [`BallotEncrypted::new_from_selections`], but binds `associated_data` to the
+    /// ballot's confirmation code.
+    pub fn new_from_selections_with_associated_data(
+        device: &Device,
+        csprng: &mut Csprng,
+        primary_nonce: &[u8],
+        ctest_selections: &Vec1<ContestSelection>,
+        associated_data: Vec<(String, String)>,
+    ) -> Result<BallotEncrypted> {
+        Self::encrypt(
+            device,
+            csprng,
+            primary_nonce,
+            None,
+            ctest_selections,
+            associated_data,
+        )
+    }
 
-            #[allow(clippy::unwrap_used)] //? TODO: Remove temp development code
-            let s_idx = ContestSelectionIndex::from_one_based_index(i as u32).unwrap();
+    /// As [`BallotEncrypted::new_from_selections_with_associated_data`], for a ballot of the
+    /// specified style.
+    ///
+    /// The contests which are not on the ballot style are encrypted as having no selections,
+    /// with [`ContestEncrypted::new_omitted`], so that every ballot has every contest and
+    /// ballots of different styles can be tallied together. Fails if the style does not
+    /// exist, or any of those contests has a selection.
+    pub fn new_from_selections_for_ballot_style(
+        device: &Device,
+        csprng: &mut Csprng,
+        primary_nonce: &[u8],
+        ballot_style_ix: BallotStyleIndex,
+        ctest_selections: &Vec1<ContestSelection>,
+        associated_data: Vec<(String, String)>,
+    ) -> Result<BallotEncrypted> {
+        let manifest = &device.header.manifest;
+        let ballot_style = manifest
+            .ballot_styles
+            .get(ballot_style_ix)
+            .with_context(|| format!("Ballot style {ballot_style_ix} does not exist"))?;
 
-            #[allow(clippy::unwrap_used)] //? TODO: Remove temp development code
-            contests
-                .try_push(ContestEncrypted::new(
-                    device,
-                    csprng,
-                    primary_nonce,
-                    device.header.manifest.contests.get(c_idx).unwrap(),
-                    ctest_selections.get(s_idx).unwrap(),
-                ))
-                .unwrap();
+        for contest_ix in manifest.contests.indices() {
+            let s_idx =
+                ContestSelectionIndex::from_one_based_index(contest_ix.get_one_based_u32())?;
+            let Some(selection) = ctest_selections.get(s_idx) else {
+                continue;
+            };
+            ensure!(
+                ballot_style.contests.contains(&contest_ix)
+                    || selection.vote.iter().all(|&v| v == 0),
+                "Contest {contest_ix} is not on ballot style {ballot_style_ix}, but has selections"
+            );
         }
 
-        // for (i, selection) in selections.iter().enumerate() {
-        //     contests.push(ContestEncrypted::new(
-        //         device,
-        //         csprng,
-        //         primary_nonce,
-        //         &device.header.manifest.contests.get(i).unwrap(),
-        //         selection,
-        //     ));
-        // }
-        let confirmation_code =
-            confirmation_code(&device.header.hashes_ext.h_e, &contests, &[0u8; 32]);
+        Self::encrypt(
+            device,
+            csprng,
+            primary_nonce,
+            Some((ballot_style_ix, ballot_style)),
+            ctest_selections,
+            associated_data,
+        )
+    }
 
-        BallotEncrypted {
+    fn encrypt(
+        device: &Device,
+        csprng: &mut Csprng,
+        primary_nonce: &[u8],
+        opt_ballot_style: Option<(BallotStyleIndex, &BallotStyle)>,
+        ctest_selections: &Vec1<ContestSelection>,
+        associated_data: Vec<(String, String)>,
+    ) -> Result<BallotEncrypted> {
+        let opt_ballot_style_ix = opt_ballot_style.map(|(ix, _)| ix);
+        let opt_ballot_style = opt_ballot_style.map(|(_, ballot_style)| ballot_style);
+
+        // The selection nonces are derived from the primary nonce, but the proofs need fresh
+        // randomness. Each contest gets its own seed, drawn in contest order, so the result
+        // does not depend on the order in which the contests are actually encrypted.
+        let contest_seeds: Vec<Vec<u8>> = (0..ctest_selections.len())
+            .map(|_| (0..32).map(|_| csprng.next_u8()).collect())
+            .collect();
+
+        #[cfg(feature = "parallel")]
+        let contests = Self::encrypt_contests_parallel(
+            device,
+            &contest_seeds,
+            primary_nonce,
+            opt_ballot_style,
+            ctest_selections,
+        )?;
+
+        #[cfg(not(feature = "parallel"))]
+        let contests = Self::encrypt_contests_sequential(
+            device,
+            &contest_seeds,
+            primary_nonce,
+            opt_ballot_style,
+            ctest_selections,
+        )?;
+
+        let confirmation_code = confirmation_code(
+            &device.header.hashes_ext.h_e,
+            &contests,
+            &b_aux(opt_ballot_style_ix, &associated_data),
+        );
+
+        Ok(BallotEncrypted {
             contests,
             state: BallotState::Uncast,
             confirmation_code,
             date: device.header.parameters.varying_parameters.date.clone(),
             device: device.uuid.clone(),
+            opt_ballot_style: opt_ballot_style_ix,
+            opt_key_version: Some(BallotKeyVersion::of(&device.header)),
+            associated_data,
+        })
+    }
+
+    /// Encrypts contest `i` using its own proof randomness seed, as omitted if it is not on
+    /// the ballot style.
+    fn encrypt_contest(
+        device: &Device,
+        contest_seed: &[u8],
+        primary_nonce: &[u8],
+        opt_ballot_style: Option<&BallotStyle>,
+        ctest_selections: &Vec1<ContestSelection>,
+        i: usize,
+    ) -> Result<ContestEncrypted> {
+        let c_idx = ContestIndex::from_one_based_index(i as u32)?;
+        let s_idx = ContestSelectionIndex::from_one_based_index(i as u32)?;
+
+        let mut csprng = Csprng::new(contest_seed);
+
+        let contest = device
+            .header
+            .manifest
+            .contests
+            .get(c_idx)
+            .with_context(|| format!("Contest {c_idx} is not in the manifest"))?;
+
+        if opt_ballot_style.is_some_and(|ballot_style| !ballot_style.contests.contains(&c_idx)) {
+            return Ok(ContestEncrypted::new_omitted(
+                device,
+                &mut csprng,
+                primary_nonce,
+                contest,
+            ));
         }
+
+        let contest_selection = ctest_selections
+            .get(s_idx)
+            .with_context(|| format!("No selection for contest {c_idx}"))?;
+
+        Ok(ContestEncrypted::new(
+            device,
+            &mut csprng,
+            primary_nonce,
+            contest,
+            contest_selection,
+        ))
+    }
+
+    // With the `parallel` feature this is only used to check the parallel results.
+    #[cfg_attr(feature = "parallel", allow(dead_code))]
+    fn encrypt_contests_sequential(
+        device: &Device,
+        contest_seeds: &[Vec<u8>],
+        primary_nonce: &[u8],
+        opt_ballot_style: Option<&BallotStyle>,
+        ctest_selections: &Vec1<ContestSelection>,
+    ) -> Result<Vec1<ContestEncrypted>> {
+        let mut contests = Vec1::with_capacity(ctest_selections.len());
+
+        for (ix0, contest_seed) in contest_seeds.iter().enumerate() {
+            contests.try_push(Self::encrypt_contest(
+                device,
+                contest_seed,
+                primary_nonce,
+                opt_ballot_style,
+                ctest_selections,
+                ix0 + 1,
+            )?)?;
+        }
+
+        Ok(contests)
+    }
+
+    #[cfg(feature = "parallel")]
+    fn encrypt_contests_parallel(
+        device: &Device,
+        contest_seeds: &[Vec<u8>],
+        primary_nonce: &[u8],
+        opt_ballot_style: Option<&BallotStyle>,
+        ctest_selections: &Vec1<ContestSelection>,
+    ) -> Result<Vec1<ContestEncrypted>> {
+        use rayon::prelude::*;
+
+        let contests_encrypted = contest_seeds
+            .par_iter()
+            .enumerate()
+            .map(|(ix0, contest_seed)| {
+                Self::encrypt_contest(
+                    device,
+                    contest_seed,
+                    primary_nonce,
+                    opt_ballot_style,
+                    ctest_selections,
+                    ix0 + 1,
+                )
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        let mut contests = Vec1::with_capacity(contests_encrypted.len());
+        for contest_encrypted in contests_encrypted {
+            contests.try_push(contest_encrypted)?;
+        }
+
+        Ok(contests)
     }
 
     pub fn contests(&self) -> &Vec1<ContestEncrypted> {
@@ -119,6 +432,59 @@ impl BallotEncrypted {
         &self.confirmation_code
     }
 
+    /// Returns the identifier of this ballot.
+    ///
+    /// This is `H(H(B); 26, contests)`, the hash keyed by the confirmation code of the
+    /// encrypted contests including their proofs, in which every count and integer is
+    /// preceded by its length.
+    /// So it is reproducible from the ballot alone, and two ballots with the same id but
+    /// different ciphertexts or proofs would require a hash collision.
+    /// The state, date, and device are not included, so the id does not change when a
+    /// ballot is cast or challenged.
+    pub fn ballot_id(&self) -> BallotId {
+        let mut w = BallotIdWriter(vec![0x26]);
+
+        w.count(self.contests.len());
+        for contest in self
+            .contests
+            .indices()
+            .filter_map(|ix| self.contests.get(ix))
+        {
+            w.count(contest.selection.len());
+            for ciphertext in &contest.selection {
+                w.ciphertext(ciphertext);
+            }
+
+            let proofs = &contest.proof_ballot_correctness;
+            w.count(proofs.len());
+            for proof in proofs.indices().filter_map(|ix| proofs.get(ix)) {
+                w.proof(proof);
+            }
+            w.proof(&contest.proof_selection_limit);
+
+            match &contest.opt_blank_indicator {
+                None => w.0.push(0),
+                Some(blank_indicator) => {
+                    w.0.push(1);
+                    w.ciphertext(&blank_indicator.ciphertext);
+                    w.proof(&blank_indicator.proof_range);
+                    w.proof(&blank_indicator.proof_only_if_blank);
+                    w.proof(&blank_indicator.proof_if_blank);
+                }
+            }
+            match &contest.opt_undervote {
+                None => w.0.push(0),
+                Some(undervote) => {
+                    w.0.push(1);
+                    w.ciphertext(&undervote.ciphertext);
+                    w.proof(&undervote.proof_range);
+                }
+            }
+        }
+
+        BallotId(eg_h(&self.confirmation_code, &w.0))
+    }
+
     pub fn date(&self) -> &String {
         &self.date
     }
@@ -127,6 +493,246 @@ impl BallotEncrypted {
         &self.device
     }
 
+    pub fn ballot_style(&self) -> Option<BallotStyleIndex> {
+        self.opt_ballot_style
+    }
+
+    /// Relabels the ballot with another style. The style is bound to the confirmation code,
+    /// so the relabeled ballot no longer verifies. Only for tests.
+    #[cfg(test)]
+    pub(crate) fn set_ballot_style(&mut self, ballot_style: BallotStyleIndex) {
+        self.opt_ballot_style = Some(ballot_style);
+    }
+
+    pub fn key_version(&self) -> Option<&BallotKeyVersion> {
+        self.opt_key_version.as_ref()
+    }
+
+    /// Records that the voter cast this ballot, so that it is counted in the tally.
+    ///
+    /// Only an [`BallotState::Uncast`] ballot can be cast.
+    pub fn cast(&mut self) -> Result<()> {
+        self.set_state(BallotState::Cast)
+    }
+
+    /// Records that the voter challenged this ballot, so that it is not counted and its nonces
+    /// may be disclosed with [`BallotEncrypted::disclose_nonces`].
+    ///
+    /// Only an [`BallotState::Uncast`] ballot can be challenged.
+    pub fn challenge(&mut self) -> Result<()> {
+        self.set_state(BallotState::Challenged)
+    }
+
+    fn set_state(&mut self, state: BallotState) -> Result<()> {
+        ensure!(
+            self.state == BallotState::Uncast,
+            "Ballot {} is already {:?}",
+            self.ballot_id(),
+            self.state
+        );
+        self.state = state;
+        Ok(())
+    }
+
+    /// Reveals the nonces with which this ballot was encrypted, for a challenge audit.
+    ///
+    /// Only permitted if the ballot is [`BallotState::Challenged`]; revealing the nonces of
+    /// a ballot which is cast, or might yet be, would reveal the vote. The nonces are only
+    /// known to the device which encrypted the ballot, as they are not written to the
+    /// ballot file.
+    pub fn disclose_nonces(&self) -> Result<BallotNonceDisclosure> {
+        ensure!(
+            self.state == BallotState::Challenged,
+            "Refusing to disclose the nonces of ballot {}, which is {:?} rather than challenged",
+            self.ballot_id(),
+            self.state
+        );
+
+        let disclose = |ciphertext: &Ciphertext, what: String| {
+            ciphertext
+                .nonce
+                .clone()
+                .map(DisclosedNonce)
+                .with_context(|| {
+                    format!("The nonce of {what} is not known. Was the ballot read from a file?")
+                })
+        };
+
+        let mut contests = Vec1::with_capacity(self.contests.len());
+        for contest_ix in self.contests.indices() {
+            let contest = self
+                .contests
+                .get(contest_ix)
+                .with_context(|| format!("Contest {contest_ix} not found"))?;
+
+            let selection = contest
+                .selection
+                .iter()
+                .enumerate()
+                .map(|(ix0, ciphertext)| {
+                    disclose(
+                        ciphertext,
+                        format!("contest {contest_ix} option {}", ix0 + 1),
+                    )
+                })
+                .collect::<Result<Vec<_>>>()?;
+
+            let opt_blank_indicator = contest
+                .opt_blank_indicator
+                .as_ref()
+                .map(|blank_indicator| {
+                    disclose(
+                        &blank_indicator.ciphertext,
+                        format!("contest {contest_ix} blank indicator"),
+                    )
+                })
+                .transpose()?;
+
+            let opt_undervote = contest
+                .opt_undervote
+                .as_ref()
+                .map(|undervote| {
+                    disclose(
+                        &undervote.ciphertext,
+                        format!("contest {contest_ix} undervote count"),
+                    )
+                })
+                .transpose()?;
+
+            contests.try_push(ContestNonceDisclosure {
+                selection,
+                opt_blank_indicator,
+                opt_undervote,
+            })?;
+        }
+
+        Ok(BallotNonceDisclosure {
+            ballot_id: self.ballot_id(),
+            contests,
+        })
+    }
+
+    /// Checks a [`BallotNonceDisclosure`] for this ballot against the plaintext selections the
+    /// voter claims to have made, by encrypting them again with the disclosed nonces and
+    /// comparing the result with the ciphertexts in the ballot.
+    pub fn verify_disclosure(
+        &self,
+        disclosure: &BallotNonceDisclosure,
+        ctest_selections: &Vec1<ContestSelection>,
+        pvd: &PreVotingData,
+    ) -> Result<()> {
+        let fixed_parameters = &pvd.parameters.fixed_parameters;
+        let q: &BigUint = fixed_parameters.q.as_ref();
+
+        ensure!(
+            self.state == BallotState::Challenged,
+            "Ballot {} is {:?}, so its nonces should not have been disclosed",
+            self.ballot_id(),
+            self.state
+        );
+        ensure!(
+            disclosure.ballot_id == self.ballot_id(),
+            "The nonces disclosed are for ballot {}, not {}",
+            disclosure.ballot_id,
+            self.ballot_id()
+        );
+        ensure!(
+            disclosure.contests.len() == self.contests.len()
+                && ctest_selections.len() == self.contests.len(),
+            "The ballot has {} contests, but {} were disclosed and {} selected",
+            self.contests.len(),
+            disclosure.contests.len(),
+            ctest_selections.len()
+        );
+
+        let reencrypt = |nonce: &DisclosedNonce, vote: usize, ciphertext: &Ciphertext| {
+            nonce.0 < *q
+                && pvd
+                    .public_key
+                    .encrypt_with(fixed_parameters, &nonce.0, vote, false)
+                    == *ciphertext
+        };
+
+        for contest_ix in self.contests.indices() {
+            let ix1 = contest_ix.get_one_based_u32();
+            let contest = self
+                .contests
+                .get(contest_ix)
+                .with_context(|| format!("Contest {contest_ix} not found"))?;
+            let contest_nonces = Index::from_one_based_index(ix1)
+                .ok()
+                .and_then(|ix| disclosure.contests.get(ix))
+                .with_context(|| format!("No nonces disclosed for contest {contest_ix}"))?;
+            let ctest_selection = ContestSelectionIndex::from_one_based_index(ix1)
+                .ok()
+                .and_then(|ix| ctest_selections.get(ix))
+                .with_context(|| format!("No selection for contest {contest_ix}"))?;
+
+            ensure!(
+                contest.selection.len() == contest_nonces.selection.len()
+                    && contest.selection.len() == ctest_selection.vote.len(),
+                "Contest {contest_ix} has {} options, but {} nonces were disclosed and {} selected",
+                contest.selection.len(),
+                contest_nonces.selection.len(),
+                ctest_selection.vote.len()
+            );
+
+            for (ix0, ciphertext) in contest.selection.iter().enumerate() {
+                let vote = ctest_selection.vote[ix0] as usize;
+                ensure!(
+                    reencrypt(&contest_nonces.selection[ix0], vote, ciphertext),
+                    "Contest {contest_ix} option {} is not an encryption of {vote}",
+                    ix0 + 1
+                );
+            }
+
+            match (
+                &contest.opt_blank_indicator,
+                &contest_nonces.opt_blank_indicator,
+            ) {
+                (None, None) => {}
+                (Some(blank_indicator), Some(nonce)) => {
+                    let b = usize::from(ctest_selection.vote.iter().all(|&v| v == 0));
+                    ensure!(
+                        reencrypt(nonce, b, &blank_indicator.ciphertext),
+                        "The blank indicator of contest {contest_ix} is not an encryption of {b}"
+                    );
+                }
+                _ => bail!("Contest {contest_ix} blank indicator and disclosed nonces don't match"),
+            }
+
+            match (&contest.opt_undervote, &contest_nonces.opt_undervote) {
+                (None, None) => {}
+                (Some(undervote), Some(nonce)) => {
+                    let selection_limit = pvd
+                        .manifest
+                        .contests
+                        .get(Index::from_one_based_index(ix1)?)
+                        .with_context(|| format!("Contest {contest_ix} not in the manifest"))?
+                        .selection_limit;
+                    let num_selections = ctest_selection
+                        .vote
+                        .iter()
+                        .map(|&v| v as usize)
+                        .sum::<usize>();
+                    let u = selection_limit.saturating_sub(num_selections);
+                    ensure!(
+                        reencrypt(nonce, u, &undervote.ciphertext),
+                        "The undervote count of contest {contest_ix} is not an encryption of {u}"
+                    );
+                }
+                _ => bail!("Contest {contest_ix} undervote count and disclosed nonces don't match"),
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Reads a `BallotEncrypted` from a `std::io::Read`.
+    pub fn from_stdioread(stdioread: &mut dyn std::io::Read) -> Result<Self> {
+        serde_json::from_reader(stdioread).context("Reading BallotEncrypted")
+    }
+
     /// Writes a `BallotEncrypted` to a `std::io::Write`.
     pub fn to_stdiowrite(&self, stdiowrite: &mut dyn std::io::Write) -> Result<()> {
         let mut ser = serde_json::Serializer::pretty(stdiowrite);
@@ -139,3 +745,413 @@ impl BallotEncrypted {
             .context("Error writing serialized voter selection to file")
     }
 }
+
+/// Writes the encrypted contests of a ballot for [`BallotEncrypted::ballot_id`].
+///
+/// Each count, and the length of each integer's minimal big-endian encoding, is written as a
+/// big-endian `u64` before what it counts. So no two different ballots have the same
+/// encoding, without the fixed-width fields of [`crate::ballot_packed`] needing the fixed
+/// parameters.
+struct BallotIdWriter(Vec<u8>);
+
+impl BallotIdWriter {
+    fn count(&mut self, n: usize) {
+        self.0.extend_from_slice(&(n as u64).to_be_bytes());
+    }
+
+    fn biguint(&mut self, u: &BigUint) {
+        let bytes = u.to_bytes_be();
+        self.count(bytes.len());
+        self.0.extend_from_slice(&bytes);
+    }
+
+    fn ciphertext(&mut self, ciphertext: &Ciphertext) {
+        self.biguint(&ciphertext.alpha);
+        self.biguint(&ciphertext.beta);
+    }
+
+    fn proof(&mut self, proof: &ProofRange) {
+        self.count(proof.singles().len());
+        for single in proof.singles() {
+            self.biguint(&single.c);
+            self.biguint(&single.v);
+        }
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod test {
+    use super::*;
+    use crate::{
+        contest_encrypted::ContestEncryptedIndex, example_election::ExampleElection,
+        example_election_manifest::example_election_manifest,
+        example_election_parameters::example_election_parameters, verification::verify_ballot,
+    };
+
+    fn test_device(csprng: &mut Csprng) -> Device {
+        Device::new(
+            "test",
+            ExampleElection::generate(
+                csprng,
+                example_election_parameters(),
+                example_election_manifest(),
+            )
+            .unwrap()
+            .pvd,
+        )
+    }
+
+    #[test]
+    fn test_contest_encryption_order_independent() {
+        let mut csprng = Csprng::new(b"test_contest_encryption_order_independent");
+        let device = test_device(&mut csprng);
+
+        let mut ctest_selections = Vec1::new();
+        for contest_ix in device.header.manifest.contests.indices().take(2) {
+            let contest = device.header.manifest.contests.get(contest_ix).unwrap();
+            ctest_selections
+                .try_push(ContestSelection::new_pick_random(
+                    &mut csprng,
+                    contest.selection_limit,
+                    contest.options.len(),
+                ))
+                .unwrap();
+        }
+
+        let primary_nonce = [0u8; 32];
+        let contest_seeds = vec![b"seed 1".to_vec(), b"seed 2".to_vec()];
+
+        let forward = BallotEncrypted::encrypt_contests_sequential(
+            &device,
+            &contest_seeds,
+            &primary_nonce,
+            None,
+            &ctest_selections,
+        )
+        .unwrap();
+
+        // Encrypting the contests in the opposite order gives identical results.
+        let reverse_1 = BallotEncrypted::encrypt_contest(
+            &device,
+            &contest_seeds[1],
+            &primary_nonce,
+            None,
+            &ctest_selections,
+            2,
+        )
+        .unwrap();
+        let reverse_0 = BallotEncrypted::encrypt_contest(
+            &device,
+            &contest_seeds[0],
+            &primary_nonce,
+            None,
+            &ctest_selections,
+            1,
+        )
+        .unwrap();
+        let to_json = |contest: &ContestEncrypted| serde_json::to_string(contest).unwrap();
+        let forward_1 = forward
+            .get(ContestEncryptedIndex::from_one_based_index(1).unwrap())
+            .unwrap();
+        let forward_2 = forward
+            .get(ContestEncryptedIndex::from_one_based_index(2).unwrap())
+            .unwrap();
+        assert_eq!(to_json(forward_1), to_json(&reverse_0));
+        assert_eq!(to_json(forward_2), to_json(&reverse_1));
+
+        #[cfg(feature = "parallel")]
+        {
+            let parallel = BallotEncrypted::encrypt_contests_parallel(
+                &device,
+                &contest_seeds,
+                &primary_nonce,
+                None,
+                &ctest_selections,
+            )
+            .unwrap();
+            assert_eq!(
+                serde_json::to_string(&forward).unwrap(),
+                serde_json::to_string(&parallel).unwrap()
+            );
+        }
+    }
+
+    #[test]
+    fn test_ballot_id() {
+        let mut csprng = Csprng::new(b"test_ballot_id");
+        let device = test_device(&mut csprng);
+
+        let contest_ix = ContestIndex::from_one_based_index(1).unwrap();
+        let contest = device.header.manifest.contests.get(contest_ix).unwrap();
+        let mut ctest_selections = Vec1::new();
+        ctest_selections
+            .try_push(ContestSelection::new_pick_random(
+                &mut csprng,
+                contest.selection_limit,
+                contest.options.len(),
+            ))
+            .unwrap();
+
+        let ballot_for_nonce = |primary_nonce: &[u8], seed: &[u8]| {
+            let contests = BallotEncrypted::encrypt_contests_sequential(
+                &device,
+                &[seed.to_vec()],
+                primary_nonce,
+                None,
+                &ctest_selections,
+            )
+            .unwrap();
+            let confirmation_code =
+                confirmation_code(&device.header.hashes_ext.h_e, &contests, &[0u8; 32]);
+            BallotEncrypted::new(
+                &contests,
+                BallotState::Uncast,
+                confirmation_code,
+                "date",
+                "device",
+            )
+        };
+
+        // Identical content yields the same id, even if the ballot has since been cast.
+        let ballot_1 = ballot_for_nonce(&[1u8; 32], b"seed");
+        let mut ballot_1_again = ballot_for_nonce(&[1u8; 32], b"seed");
+        ballot_1_again.state = BallotState::Cast;
+        assert_eq!(ballot_1.ballot_id(), ballot_1_again.ballot_id());
+
+        // The id survives a round trip through JSON.
+        let mut buf = Vec::new();
+        ballot_1.to_stdiowrite(&mut buf).unwrap();
+        let ballot_1_read = BallotEncrypted::from_stdioread(&mut buf.as_slice()).unwrap();
+        assert_eq!(ballot_1.ballot_id(), ballot_1_read.ballot_id());
+
+        // Distinct ballots get distinct ids.
+        let ballot_2 = ballot_for_nonce(&[2u8; 32], b"seed");
+        let ballot_3 = ballot_for_nonce(&[1u8; 32], b"other seed");
+        assert_ne!(ballot_1.ballot_id(), ballot_2.ballot_id());
+        assert_ne!(ballot_1.ballot_id(), ballot_3.ballot_id());
+        assert_ne!(ballot_2.ballot_id(), ballot_3.ballot_id());
+
+        // Reusing the confirmation code with different ciphertexts is caught.
+        let mut ballot_tampered = ballot_for_nonce(&[1u8; 32], b"seed");
+        ballot_tampered
+            .contests
+            .get_mut(ContestEncryptedIndex::from_one_based_index(1).unwrap())
+            .unwrap()
+            .selection
+            .swap(0, 1);
+        assert_eq!(
+            ballot_tampered.confirmation_code,
+            ballot_1.confirmation_code
+        );
+        assert_ne!(ballot_1.ballot_id(), ballot_tampered.ballot_id());
+
+        // As is reusing it with different proofs.
+        let mut ballot_tampered = ballot_for_nonce(&[1u8; 32], b"seed");
+        let contest_ix = ContestEncryptedIndex::from_one_based_index(1).unwrap();
+        ballot_tampered
+            .contests
+            .get_mut(contest_ix)
+            .unwrap()
+            .proof_selection_limit = ballot_2
+            .contests
+            .get(contest_ix)
+            .unwrap()
+            .proof_selection_limit
+            .clone();
+        assert_ne!(ballot_1.ballot_id(), ballot_tampered.ballot_id());
+
+        assert_eq!(
+            ballot_1.ballot_id().to_string(),
+            ballot_1.ballot_id().0.to_string_hex_no_prefix_suffix()
+        );
+    }
+
+    #[test]
+    fn test_nonce_disclosure() {
+        let mut csprng = Csprng::new(b"test_nonce_disclosure");
+        let mut device = test_device(&mut csprng);
+
+        // The first contest, with a blank indicator and an undervote count.
+        let contest_ix = ContestIndex::from_one_based_index(1).unwrap();
+        let contest = device.header.manifest.contests.get_mut(contest_ix).unwrap();
+        contest.track_blank = true;
+        contest.track_undervotes = true;
+        let cnt_options = contest.options.len();
+
+        let mut ctest_selections = Vec1::new();
+        ctest_selections
+            .try_push(ContestSelection {
+                vote: vec![0; cnt_options],
+            })
+            .unwrap();
+
+        let contests = BallotEncrypted::encrypt_contests_sequential(
+            &device,
+            &[b"seed".to_vec()],
+            &[3u8; 32],
+            None,
+            &ctest_selections,
+        )
+        .unwrap();
+        let confirmation_code =
+            confirmation_code(&device.header.hashes_ext.h_e, &contests, &[0u8; 32]);
+        let mut ballot = BallotEncrypted::new(
+            &contests,
+            BallotState::Cast,
+            confirmation_code,
+            "date",
+            "device",
+        );
+
+        // A cast ballot keeps its nonces secret.
+        let err = ballot.disclose_nonces().unwrap_err();
+        assert!(err.to_string().starts_with("Refusing"), "{err}");
+
+        ballot.state = BallotState::Challenged;
+        let disclosure = ballot.disclose_nonces().unwrap();
+        let contest_nonces = disclosure
+            .contests
+            .get(Index::from_one_based_index(1).unwrap())
+            .unwrap();
+        assert!(contest_nonces.opt_blank_indicator.is_some());
+
+        // The undervote count is an encryption of the selection limit, as nothing was selected.
+        let undervote = ballot
+            .contests
+            .get(ContestEncryptedIndex::from_one_based_index(1).unwrap())
+            .unwrap()
+            .opt_undervote
+            .as_ref()
+            .unwrap();
+        let nonce = contest_nonces.opt_undervote.as_ref().unwrap();
+        let selection_limit = device
+            .header
+            .manifest
+            .contests
+            .get(contest_ix)
+            .unwrap()
+            .selection_limit;
+        assert_eq!(
+            device.header.public_key.encrypt_with(
+                &device.header.parameters.fixed_parameters,
+                &nonce.0,
+                selection_limit,
+                false
+            ),
+            undervote.ciphertext
+        );
+
+        let mut buf = Vec::new();
+        disclosure.to_stdiowrite(&mut buf).unwrap();
+        let disclosure = BallotNonceDisclosure::from_stdioread(&mut buf.as_slice()).unwrap();
+
+        // The published ballot has no nonces of its own, but can still be checked.
+        let mut buf = Vec::new();
+        ballot.to_stdiowrite(&mut buf).unwrap();
+        let ballot_read = BallotEncrypted::from_stdioread(&mut buf.as_slice()).unwrap();
+        assert!(ballot_read.disclose_nonces().is_err());
+        ballot_read
+            .verify_disclosure(&disclosure, &ctest_selections, &device.header)
+            .unwrap();
+
+        // A different choice than the one encrypted is detected.
+        let mut other_selections = Vec1::new();
+        let mut vote = vec![0; cnt_options];
+        vote[0] = 1;
+        other_selections
+            .try_push(ContestSelection { vote })
+            .unwrap();
+        let err = ballot_read
+            .verify_disclosure(&disclosure, &other_selections, &device.header)
+            .unwrap_err();
+        assert!(err.to_string().contains("option 1"), "{err}");
+
+        // As is a device which publishes an undervote count other than the one computed from
+        // the selections, along with a disclosure for that ballot.
+        let mut ballot_tampered = BallotEncrypted::from_stdioread(&mut buf.as_slice()).unwrap();
+        let contest_tampered = ballot_tampered
+            .contests
+            .get_mut(ContestEncryptedIndex::from_one_based_index(1).unwrap())
+            .unwrap();
+        let undervote = contest_tampered.opt_undervote.as_mut().unwrap();
+        undervote.ciphertext = device.header.public_key.encrypt_with(
+            &device.header.parameters.fixed_parameters,
+            &nonce.0,
+            selection_limit - 1,
+            false,
+        );
+        let mut disclosure_tampered = disclosure.clone();
+        disclosure_tampered.ballot_id = ballot_tampered.ballot_id();
+        let err = ballot_tampered
+            .verify_disclosure(&disclosure_tampered, &ctest_selections, &device.header)
+            .unwrap_err();
+        assert!(err.to_string().contains("undervote count"), "{err}");
+
+        // And a disclosure which leaves out the nonce of the undervote count.
+        let mut disclosure_short = disclosure.clone();
+        disclosure_short
+            .contests
+            .get_mut(Index::from_one_based_index(1).unwrap())
+            .unwrap()
+            .opt_undervote = None;
+        assert!(ballot_read
+            .verify_disclosure(&disclosure_short, &ctest_selections, &device.header)
+            .is_err());
+
+        // As is a disclosure for a ballot which was cast after all.
+        let mut ballot_cast = ballot_read;
+        ballot_cast.state = BallotState::Cast;
+        assert!(ballot_cast
+            .verify_disclosure(&disclosure, &ctest_selections, &device.header)
+            .is_err());
+    }
+
+    #[test]
+    fn test_associated_data() {
+        let mut csprng = Csprng::new(b"test_associated_data");
+        let mut device = test_device(&mut csprng);
+
+        device.header.manifest.contests.truncate(1);
+        let contest_ix = ContestIndex::from_one_based_index(1).unwrap();
+        let contest = device.header.manifest.contests.get(contest_ix).unwrap();
+        let mut ctest_selections = Vec1::new();
+        ctest_selections
+            .try_push(ContestSelection::new_pick_random(
+                &mut csprng,
+                contest.selection_limit,
+                contest.options.len(),
+            ))
+            .unwrap();
+
+        let associated_data = vec![
+            ("device".to_string(), "scanner 7".to_string()),
+            ("time".to_string(), "2023-05-02T09:00".to_string()),
+        ];
+        let mut ballot = BallotEncrypted::new_from_selections_with_associated_data(
+            &device,
+            &mut csprng,
+            &[4u8; 32],
+            &ctest_selections,
+            associated_data.clone(),
+        )
+        .unwrap();
+        verify_ballot(&device.header, &ballot).unwrap();
+
+        // The associated data is kept in the clear.
+        let mut buf = Vec::new();
+        ballot.to_stdiowrite(&mut buf).unwrap();
+        let ballot_read = BallotEncrypted::from_stdioread(&mut buf.as_slice()).unwrap();
+        assert_eq!(ballot_read.associated_data, associated_data);
+        verify_ballot(&device.header, &ballot_read).unwrap();
+
+        // The same contests without it have a different confirmation code.
+        let code_without = confirmation_code(&device.header.hashes_ext.h_e, &ballot.contests, &[]);
+        assert_ne!(code_without, ballot.confirmation_code);
+
+        // Altering it is detected.
+        ballot.associated_data[1].1 = "2023-05-02T17:00".to_string();
+        let err = verify_ballot(&device.header, &ballot).unwrap_err();
+        assert!(err.to_string().contains("associated data"), "{err}");
+    }
+}
@@ -0,0 +1,167 @@
+// Copyright (C) Microsoft Corporation. All rights reserved.
+
+#![deny(clippy::unwrap_used)]
+#![deny(clippy::expect_used)]
+#![deny(clippy::panic)]
+#![deny(clippy::manual_assert)]
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::hash::{eg_h, HValue};
+
+/// A running hash over the confirmation codes of the ballots encrypted by a device, in order.
+///
+/// The chain starts from `H_0 = H(H_E; 25, device)`, each ballot extends it with
+/// `H_j = H(H_{j-1}; 25, H(B_j))`, and it is closed with `H(H_ℓ; 25, "CLOSE")`.
+/// Publishing the closing code commits the device to exactly the ballots it encrypted, in
+/// the order it encrypted them, so that a dropped, inserted, or reordered ballot is detected
+/// by recomputing the chain.
+///
+/// The chain is kept alongside the ballots. It does not feed into their confirmation codes,
+/// and has its own domain separator so that no chain hash can be mistaken for one.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BallotChain {
+    device: String,
+    current: HValue,
+    ballot_count: usize,
+}
+
+impl BallotChain {
+    /// Starts a new chain for the specified device.
+    pub fn new(h_e: &HValue, device: &str) -> Self {
+        let mut v = vec![0x25];
+        v.extend_from_slice(device.as_bytes());
+
+        BallotChain {
+            device: device.to_string(),
+            current: eg_h(h_e, &v),
+            ballot_count: 0,
+        }
+    }
+
+    /// Extends the chain with the confirmation code of the next ballot.
+    pub fn add(&mut self, confirmation_code: &HValue) {
+        let mut v = vec![0x25];
+        v.extend_from_slice(confirmation_code.as_ref());
+
+        self.current = eg_h(&self.current, &v);
+        self.ballot_count += 1;
+    }
+
+    /// The hash of the chain so far.
+    pub fn current(&self) -> &HValue {
+        &self.current
+    }
+
+    /// The number of ballots added to the chain.
+    pub fn ballot_count(&self) -> usize {
+        self.ballot_count
+    }
+
+    /// Returns the closing code of the chain, to be published once the device has
+    /// encrypted its last ballot.
+    pub fn closing_code(&self) -> HValue {
+        let mut v = vec![0x25];
+        v.extend_from_slice(b"CLOSE");
+
+        eg_h(&self.current, &v)
+    }
+
+    /// Returns the record of the closed chain, to be published with the ballots.
+    pub fn closing(&self) -> BallotChainClosing {
+        BallotChainClosing {
+            device: self.device.clone(),
+            ballot_count: self.ballot_count,
+            closing_code: self.closing_code(),
+        }
+    }
+}
+
+/// The published record of a closed [`BallotChain`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BallotChainClosing {
+    /// The device which encrypted the ballots.
+    pub device: String,
+
+    /// The number of ballots in the chain.
+    pub ballot_count: usize,
+
+    /// See [`BallotChain::closing_code`].
+    pub closing_code: HValue,
+}
+
+impl BallotChainClosing {
+    /// Reads a `BallotChainClosing` from a `std::io::Read`.
+    pub fn from_stdioread(stdioread: &mut dyn std::io::Read) -> Result<Self> {
+        serde_json::from_reader(stdioread).context("Reading BallotChainClosing")
+    }
+
+    /// Writes a `BallotChainClosing` to a `std::io::Write`.
+    pub fn to_stdiowrite(&self, stdiowrite: &mut dyn std::io::Write) -> Result<()> {
+        let mut ser = serde_json::Serializer::pretty(stdiowrite);
+
+        self.serialize(&mut ser)
+            .map_err(Into::<anyhow::Error>::into)
+            .and_then(|_| ser.into_inner().write_all(b"\n").map_err(Into::into))
+            .context("Writing BallotChainClosing")
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_ballot_chain() {
+        let h_e = HValue::from([1u8; 32]);
+        let codes = [
+            HValue::from([2u8; 32]),
+            HValue::from([3u8; 32]),
+            HValue::from([4u8; 32]),
+        ];
+
+        let chain_of = |device: &str, codes: &[HValue]| {
+            let mut chain = BallotChain::new(&h_e, device);
+            for code in codes {
+                chain.add(code);
+            }
+            chain
+        };
+
+        let chain = chain_of("device", &codes);
+        assert_eq!(chain.ballot_count(), 3);
+        assert_eq!(chain, chain_of("device", &codes));
+        assert_ne!(chain.closing_code(), *chain.current());
+
+        // Another device, a dropped ballot, or a reordering all change the closing code.
+        let closing_code = chain.closing_code();
+        assert_ne!(
+            chain_of("other device", &codes).closing_code(),
+            closing_code
+        );
+        assert_ne!(chain_of("device", &codes[..2]).closing_code(), closing_code);
+        assert_ne!(
+            chain_of("device", &[codes[1], codes[0], codes[2]]).closing_code(),
+            closing_code
+        );
+
+        // The chain has its own domain separator, not the confirmation code's 24.
+        let mut v = vec![0x24];
+        v.extend_from_slice(b"device");
+        assert_ne!(*chain_of("device", &[]).current(), eg_h(&h_e, &v));
+
+        let closing = chain.closing();
+        assert_eq!(closing.device, "device");
+        assert_eq!(closing.ballot_count, 3);
+        assert_eq!(closing.closing_code, closing_code);
+
+        let mut buf = Vec::new();
+        closing.to_stdiowrite(&mut buf).unwrap();
+        assert_eq!(
+            BallotChainClosing::from_stdioread(&mut buf.as_slice()).unwrap(),
+            closing
+        );
+    }
+}
@@ -0,0 +1,274 @@
+// Copyright (C) Microsoft Corporation. All rights reserved.
+
+#![deny(clippy::unwrap_used)]
+#![deny(clippy::expect_used)]
+#![deny(clippy::panic)]
+#![deny(clippy::manual_assert)]
+
+use anyhow::{ensure, Context, Result};
+use serde::{Deserialize, Serialize};
+use util::csprng::Csprng;
+
+use crate::{
+    ballot::BallotEncrypted, ballot_chain::BallotChain, ballot_style::BallotStyleIndex,
+    contest_selection::ContestSelection, device::Device, election_manifest::ElectionManifest,
+    index::Index, nonce_auditor::NonceAuditor, vec1::Vec1,
+};
+
+/// A plaintext ballot, as read from a file for encryption.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BallotPlaintext {
+    /// The style of this ballot, if known.
+    #[serde(
+        rename = "ballot_style",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub opt_ballot_style: Option<BallotStyleIndex>,
+
+    /// The voter's selections for every contest, in the order defined in the manifest.
+    pub contests: Vec1<ContestSelection>,
+}
+
+impl BallotPlaintext {
+    /// Reads a `BallotPlaintext` from a `std::io::Read`.
+    pub fn from_stdioread(stdioread: &mut dyn std::io::Read) -> Result<Self> {
+        serde_json::from_reader(stdioread).context("Reading BallotPlaintext")
+    }
+
+    /// Verifies that the selections are allowed by the manifest: every contest is present,
+    /// each option is selected at most once, and no contest has more selections than its
+    /// selection limit. Also verifies that the manifest's contests can be encrypted at all,
+    /// with [`ElectionManifest::check_range_proofs_feasible`].
+    pub fn validate(&self, election_manifest: &ElectionManifest) -> Result<()> {
+        election_manifest.check_range_proofs_feasible()?;
+
+        ensure!(
+            self.contests.len() == election_manifest.contests.len(),
+            "Ballot has {} contests, but the manifest has {}",
+            self.contests.len(),
+            election_manifest.contests.len()
+        );
+
+        if let Some(ballot_style_ix) = self.opt_ballot_style {
+            ensure!(
+                election_manifest
+                    .ballot_styles
+                    .get(ballot_style_ix)
+                    .is_some(),
+                "Ballot style {ballot_style_ix} does not exist"
+            );
+        }
+
+        for contest_ix in election_manifest.contests.indices() {
+            let (Some(contest), Some(contest_selection)) = (
+                election_manifest.contests.get(contest_ix),
+                self.contests
+                    .get(Index::from_one_based_index(contest_ix.get_one_based_u32())?),
+            ) else {
+                continue;
+            };
+
+            let vote = &contest_selection.vote;
+
+            ensure!(
+                vote.len() == contest.options.len(),
+                "Contest {contest_ix} has {} selections, but {} options",
+                vote.len(),
+                contest.options.len()
+            );
+
+            ensure!(
+                vote.iter().all(|&v| v <= 1),
+                "Contest {contest_ix} has an option selected more than once"
+            );
+
+            let cnt_selected = vote.iter().filter(|&&v| v == 1).count();
+            ensure!(
+                cnt_selected <= contest.selection_limit,
+                "Contest {contest_ix} has {cnt_selected} selections, which exceeds its selection limit of {}",
+                contest.selection_limit
+            );
+        }
+
+        Ok(())
+    }
+}
+
+/// Encrypts ballots on a device, keeping a [`BallotChain`] of their confirmation codes.
+///
+/// Every ballot is encrypted under the extended base hash `H_E` of the device's election,
+/// as recorded in its [`BallotKeyVersion`](crate::ballot::BallotKeyVersion). Every ballot is
+/// also recorded with a [`NonceAuditor`], so that a nonce reused across the batch is caught
+/// before the ballot is returned.
+pub struct BallotEncryptor<'a> {
+    device: &'a Device,
+    chain: BallotChain,
+    nonce_auditor: NonceAuditor,
+}
+
+impl<'a> BallotEncryptor<'a> {
+    pub fn new(device: &'a Device) -> Self {
+        let chain = BallotChain::new(&device.header.hashes_ext.h_e, &device.uuid);
+        BallotEncryptor {
+            device,
+            chain,
+            nonce_auditor: NonceAuditor::new(),
+        }
+    }
+
+    /// Validates and encrypts a plaintext ballot, and adds it to the chain.
+    ///
+    /// If the ballot has a style, the contests which are not on it must have no selections,
+    /// and are encrypted as omitted. A ballot which fails validation, or reuses a nonce of a
+    /// ballot encrypted earlier, is not added to the chain.
+    pub fn encrypt(
+        &mut self,
+        csprng: &mut Csprng,
+        ballot_plaintext: &BallotPlaintext,
+    ) -> Result<BallotEncrypted> {
+        ballot_plaintext.validate(&self.device.header.manifest)?;
+
+        let primary_nonce: Vec<u8> = (0..32).map(|_| csprng.next_u8()).collect();
+
+        let ballot = match ballot_plaintext.opt_ballot_style {
+            None => BallotEncrypted::new_from_selections(
+                self.device,
+                csprng,
+                &primary_nonce,
+                &ballot_plaintext.contests,
+            )?,
+            Some(ballot_style_ix) => BallotEncrypted::new_from_selections_for_ballot_style(
+                self.device,
+                csprng,
+                &primary_nonce,
+                ballot_style_ix,
+                &ballot_plaintext.contests,
+                Vec::new(),
+            )?,
+        };
+
+        self.nonce_auditor.record_ballot(&ballot)?;
+        self.chain.add(&ballot.confirmation_code);
+
+        #[cfg(feature = "tracing")]
+        tracing::debug!(
+            confirmation_code = %ballot.confirmation_code,
+            ballot_count = self.chain.ballot_count(),
+            "Encrypted ballot"
+        );
+
+        Ok(ballot)
+    }
+
+    /// The chain of the ballots encrypted so far.
+    pub fn chain(&self) -> &BallotChain {
+        &self.chain
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod test {
+    use super::*;
+    use crate::{
+        example_election::ExampleElection, hash::HValue, hashes::Hashes, hashes_ext::HashesExt,
+        joint_election_public_key::JointElectionPublicKey, verification::verify_ballot,
+    };
+
+    #[test]
+    fn test_ballot_encryptor() {
+        let mut csprng = Csprng::new(b"test_ballot_encryptor");
+
+        let ExampleElection {
+            guardian_public_keys,
+            pvd,
+            ..
+        } = ExampleElection::with_first_contests(&mut csprng, 1).unwrap();
+
+        // H_E as computed on its own, rather than as part of the pre-voting data.
+        let election_parameters = &pvd.parameters;
+        let h_e = HashesExt::compute(
+            election_parameters,
+            &Hashes::compute(election_parameters, &pvd.manifest).unwrap(),
+            &JointElectionPublicKey::compute(election_parameters, &guardian_public_keys).unwrap(),
+            &guardian_public_keys,
+        )
+        .h_e;
+
+        let device = Device::new("test", pvd.clone());
+
+        let ballot_plaintext = |json: &str| BallotPlaintext::from_stdioread(&mut json.as_bytes());
+
+        let mut encryptor = BallotEncryptor::new(&device);
+
+        // Too many selections.
+        let ballot_over = ballot_plaintext(r#"{ "contests": [ { "vote": [1, 1] } ] }"#).unwrap();
+        let err = encryptor.encrypt(&mut csprng, &ballot_over).unwrap_err();
+        assert!(err.to_string().contains("selection limit"), "{err:#}");
+
+        // The wrong number of options.
+        let ballot_short = ballot_plaintext(r#"{ "contests": [ { "vote": [1] } ] }"#).unwrap();
+        assert!(encryptor.encrypt(&mut csprng, &ballot_short).is_err());
+
+        // A ballot style which doesn't exist.
+        let ballot_bad_style =
+            ballot_plaintext(r#"{ "ballot_style": 99, "contests": [ { "vote": [0, 1] } ] }"#)
+                .unwrap();
+        assert!(encryptor.encrypt(&mut csprng, &ballot_bad_style).is_err());
+
+        // A contest whose selection limit can't be proven, in a manifest which was never
+        // validated.
+        let mut infeasible = device.header.manifest.clone();
+        infeasible
+            .contests
+            .get_mut(Index::from_one_based_index(1).unwrap())
+            .unwrap()
+            .selection_limit = 3;
+        let ballot = ballot_plaintext(r#"{ "contests": [ { "vote": [1, 1] } ] }"#).unwrap();
+        let err = ballot.validate(&infeasible).unwrap_err();
+        assert!(err.to_string().contains("selection limit of 3"), "{err}");
+
+        assert_eq!(encryptor.chain().ballot_count(), 0);
+
+        let ballot_ok =
+            ballot_plaintext(r#"{ "ballot_style": 1, "contests": [ { "vote": [0, 1] } ] }"#)
+                .unwrap();
+        let ballot = encryptor.encrypt(&mut csprng, &ballot_ok).unwrap();
+        verify_ballot(&device.header, &ballot).unwrap();
+        assert_eq!(ballot.ballot_style().unwrap().get_one_based_u32(), 1);
+
+        let mut chain = BallotChain::new(&device.header.hashes_ext.h_e, "test");
+        chain.add(&ballot.confirmation_code);
+        assert_eq!(encryptor.chain(), &chain);
+
+        // Every ballot of the batch is encrypted under the same H_E.
+        let mut ballots = vec![ballot];
+        ballots.push(encryptor.encrypt(&mut csprng, &ballot_ok).unwrap());
+        for ballot in &ballots {
+            assert_eq!(ballot.key_version().unwrap().h_e, h_e);
+        }
+
+        // A device whose header has some other H_E encrypts under that one instead, and its
+        // ballots don't verify against this election.
+        let mut pvd_other = pvd;
+        pvd_other.hashes_ext.h_e = HValue::from([0xAA; 32]);
+        let device_other = Device::new("test", pvd_other);
+        let ballot_other = BallotEncryptor::new(&device_other)
+            .encrypt(&mut csprng, &ballot_ok)
+            .unwrap();
+        assert_ne!(ballot_other.key_version().unwrap().h_e, h_e);
+        assert!(verify_ballot(&device.header, &ballot_other).is_err());
+
+        // A csprng which repeats itself would reuse the nonces, which is caught.
+        let mut encryptor = BallotEncryptor::new(&device);
+        encryptor
+            .encrypt(&mut Csprng::new(b"repeated"), &ballot_ok)
+            .unwrap();
+        let err = encryptor
+            .encrypt(&mut Csprng::new(b"repeated"), &ballot_ok)
+            .unwrap_err();
+        assert!(err.to_string().contains("nonce reused"), "{err}");
+        assert_eq!(encryptor.chain().ballot_count(), 1);
+    }
+}
@@ -0,0 +1,522 @@
+// Copyright (C) Microsoft Corporation. All rights reserved.
+
+#![deny(clippy::unwrap_used)]
+#![deny(clippy::expect_used)]
+#![deny(clippy::panic)]
+#![deny(clippy::manual_assert)]
+
+//! A compact binary encoding of [`BallotEncrypted`].
+//!
+//! Every value mod p (ciphertext `alpha` and `beta`) is written as a fixed-width big-endian
+//! field of [`FixedParameters::l_p_bytes`] bytes, and every value mod q (proof `c` and `v`)
+//! as [`FixedParameters::l_q_bytes`] bytes, with no delimiters. Counts and lengths are
+//! big-endian `u32`s.
+//!
+//! ```text
+//! "EGPB" version:u8 state:u8 confirmation_code:[u8; 32] ballot_style:u32 (0 = none)
+//! date_len:u32 date device_len:u32 device
+//! has_key_version:u8 [h_e:[u8; 32] public_key_fingerprint:[u8; 32]]   (version 3 and later)
+//! associated_data_count:u32 associated_data_count * (key_len:u32 key value_len:u32 value)
+//!                                                                      (version 4 and later)
+//! contest_count:u32
+//! for each contest:
+//!     option_count:u32 contest_hash:[u8; 32]
+//!     option_count * (alpha beta)
+//!     option_count * proof
+//!     proof (selection limit)
+//!     has_blank_indicator:u8 [alpha beta proof proof proof]   (version 2 and later)
+//!     has_undervote:u8 [alpha beta proof]                     (version 5 and later)
+//! where proof = entry_count:u32 entry_count * (c v)
+//! ```
+
+use anyhow::{bail, ensure, Context, Result};
+use num_bigint::BigUint;
+
+use crate::{
+    ballot::{BallotEncrypted, BallotKeyVersion, BallotState},
+    ballot_style::BallotStyleIndex,
+    contest_encrypted::{BlankIndicator, ContestEncrypted, UndervoteCount},
+    fixed_parameters::FixedParameters,
+    hash::{HValue, HVALUE_BYTE_LEN},
+    joint_election_public_key::Ciphertext,
+    vec1::Vec1,
+    zk::{ProofRange, ProofRangeSingle},
+};
+
+/// Identifies the packed ballot encoding.
+pub const PACKED_BALLOT_MAGIC: &[u8; 4] = b"EGPB";
+
+/// The version of the packed ballot encoding written by [`BallotEncrypted::to_packed_bytes`].
+pub const PACKED_BALLOT_VERSION: u8 = 5;
+
+impl BallotEncrypted {
+    /// Returns the packed binary encoding of the ballot, described in [`crate::ballot_packed`].
+    ///
+    /// Fails if a value is not in range for the fixed parameters, since it would not fit its
+    /// fixed-width field.
+    pub fn to_packed_bytes(&self, fixed_parameters: &FixedParameters) -> Result<Vec<u8>> {
+        let mut w = PackedWriter {
+            fixed_parameters,
+            bytes: Vec::new(),
+        };
+
+        w.bytes.extend_from_slice(PACKED_BALLOT_MAGIC);
+        w.bytes.push(PACKED_BALLOT_VERSION);
+        w.bytes.push(match self.state {
+            BallotState::Uncast => 0,
+            BallotState::Cast => 1,
+            BallotState::Challenged => 2,
+        });
+        w.bytes.extend_from_slice(self.confirmation_code.as_ref());
+        w.u32(self.opt_ballot_style.map_or(0, |ix| ix.get_one_based_u32()));
+        w.string(&self.date)?;
+        w.string(&self.device)?;
+        match &self.opt_key_version {
+            None => w.bytes.push(0),
+            Some(key_version) => {
+                w.bytes.push(1);
+                w.bytes.extend_from_slice(key_version.h_e.as_ref());
+                w.bytes
+                    .extend_from_slice(key_version.public_key_fingerprint.as_ref());
+            }
+        }
+        w.count(self.associated_data.len())?;
+        for (key, value) in &self.associated_data {
+            w.string(key)?;
+            w.string(value)?;
+        }
+
+        w.count(self.contests.len())?;
+        for contest_ix in self.contests.indices() {
+            let Some(contest) = self.contests.get(contest_ix) else {
+                continue;
+            };
+
+            ensure!(
+                contest.proof_ballot_correctness.len() == contest.selection.len(),
+                "Contest {contest_ix} has {} ciphertexts but {} proofs",
+                contest.selection.len(),
+                contest.proof_ballot_correctness.len()
+            );
+
+            w.count(contest.selection.len())?;
+            w.bytes.extend_from_slice(contest.contest_hash.as_ref());
+            for ciphertext in &contest.selection {
+                w.modp(&ciphertext.alpha)
+                    .and_then(|_| w.modp(&ciphertext.beta))
+                    .with_context(|| format!("Contest {contest_ix} ciphertext"))?;
+            }
+            for proof_ix in contest.proof_ballot_correctness.indices() {
+                if let Some(proof) = contest.proof_ballot_correctness.get(proof_ix) {
+                    w.proof(proof)
+                        .with_context(|| format!("Contest {contest_ix} proof {proof_ix}"))?;
+                }
+            }
+            w.proof(&contest.proof_selection_limit)
+                .with_context(|| format!("Contest {contest_ix} selection limit proof"))?;
+            match &contest.opt_blank_indicator {
+                None => w.bytes.push(0),
+                Some(blank_indicator) => {
+                    w.bytes.push(1);
+                    w.blank_indicator(blank_indicator)
+                        .with_context(|| format!("Contest {contest_ix} blank indicator"))?;
+                }
+            }
+            match &contest.opt_undervote {
+                None => w.bytes.push(0),
+                Some(undervote) => {
+                    w.bytes.push(1);
+                    w.undervote(undervote)
+                        .with_context(|| format!("Contest {contest_ix} undervote count"))?;
+                }
+            }
+        }
+
+        Ok(w.bytes)
+    }
+
+    /// Decodes a ballot from the packed binary encoding written by
+    /// [`BallotEncrypted::to_packed_bytes`] with the same fixed parameters.
+    pub fn from_packed_bytes(bytes: &[u8], fixed_parameters: &FixedParameters) -> Result<Self> {
+        let mut r = PackedReader {
+            fixed_parameters,
+            bytes,
+        };
+
+        if !bytes.starts_with(PACKED_BALLOT_MAGIC) {
+            let looks_like_json = bytes.iter().find(|b| !b.is_ascii_whitespace()) == Some(&b'{');
+            if looks_like_json {
+                bail!("Not a packed ballot: the input appears to be a JSON ballot");
+            }
+            bail!(
+                "Not a packed ballot: expected magic number {:?}, found {:?}",
+                PACKED_BALLOT_MAGIC,
+                &bytes[..bytes.len().min(PACKED_BALLOT_MAGIC.len())]
+            );
+        }
+        r.take(PACKED_BALLOT_MAGIC.len(), "magic")?;
+        let version = r.u8("version")?;
+        ensure!(
+            (1..=PACKED_BALLOT_VERSION).contains(&version),
+            "Unsupported packed ballot version {version}"
+        );
+        let state = match r.u8("state")? {
+            0 => BallotState::Uncast,
+            1 => BallotState::Cast,
+            2 => BallotState::Challenged,
+            state => bail!("Invalid ballot state {state}"),
+        };
+        let confirmation_code = r.hvalue("confirmation code")?;
+        let opt_ballot_style = match r.u32("ballot style")? {
+            0 => None,
+            ix1 => Some(BallotStyleIndex::from_one_based_index(ix1)?),
+        };
+        let date = r.string("date")?;
+        let device = r.string("device")?;
+        let opt_key_version = match version {
+            1 | 2 => None,
+            _ => match r.u8("key version flag")? {
+                0 => None,
+                1 => Some(BallotKeyVersion {
+                    h_e: r.hvalue("extended base hash")?,
+                    public_key_fingerprint: r.hvalue("public key fingerprint")?,
+                }),
+                flag => bail!("Invalid key version flag {flag}"),
+            },
+        };
+        let mut associated_data = Vec::new();
+        if 4 <= version {
+            for _ in 0..r.count("associated data count")? {
+                let key = r.string("associated data key")?;
+                let value = r.string("associated data value")?;
+                associated_data.push((key, value));
+            }
+        }
+
+        let cnt_contests = r.count("contest count")?;
+        let mut contests = Vec1::with_capacity(cnt_contests.min(r.bytes.len()));
+        for contest_n in 1..=cnt_contests {
+            let contest = r
+                .contest(version)
+                .with_context(|| format!("Reading contest {contest_n}"))?;
+            contests.try_push(contest)?;
+        }
+
+        ensure!(
+            r.bytes.is_empty(),
+            "{} unexpected bytes after the last contest",
+            r.bytes.len()
+        );
+
+        Ok(BallotEncrypted {
+            contests,
+            confirmation_code,
+            state,
+            date,
+            device,
+            opt_ballot_style,
+            opt_key_version,
+            associated_data,
+        })
+    }
+}
+
+struct PackedWriter<'a> {
+    fixed_parameters: &'a FixedParameters,
+    bytes: Vec<u8>,
+}
+
+impl PackedWriter<'_> {
+    fn u32(&mut self, n: u32) {
+        self.bytes.extend_from_slice(&n.to_be_bytes());
+    }
+
+    fn count(&mut self, n: usize) -> Result<()> {
+        let n = u32::try_from(n).context("Count too large for packed ballot")?;
+        self.u32(n);
+        Ok(())
+    }
+
+    fn string(&mut self, s: &str) -> Result<()> {
+        self.count(s.len())?;
+        self.bytes.extend_from_slice(s.as_bytes());
+        Ok(())
+    }
+
+    fn modp(&mut self, n: &BigUint) -> Result<()> {
+        ensure!(
+            self.fixed_parameters.is_valid_modp(n),
+            "Value is not valid mod p"
+        );
+        self.bytes
+            .extend(self.fixed_parameters.biguint_to_be_bytes_len_p(n));
+        Ok(())
+    }
+
+    fn modq(&mut self, n: &BigUint) -> Result<()> {
+        ensure!(
+            self.fixed_parameters.is_valid_modq(n),
+            "Value is not valid mod q"
+        );
+        self.bytes
+            .extend(self.fixed_parameters.biguint_to_be_bytes_len_q(n));
+        Ok(())
+    }
+
+    fn proof(&mut self, proof: &ProofRange) -> Result<()> {
+        self.count(proof.singles().len())?;
+        for single in proof.singles() {
+            self.modq(&single.c)?;
+            self.modq(&single.v)?;
+        }
+        Ok(())
+    }
+
+    fn blank_indicator(&mut self, blank_indicator: &BlankIndicator) -> Result<()> {
+        self.modp(&blank_indicator.ciphertext.alpha)?;
+        self.modp(&blank_indicator.ciphertext.beta)?;
+        self.proof(&blank_indicator.proof_range)?;
+        self.proof(&blank_indicator.proof_only_if_blank)?;
+        self.proof(&blank_indicator.proof_if_blank)
+    }
+
+    fn undervote(&mut self, undervote: &UndervoteCount) -> Result<()> {
+        self.modp(&undervote.ciphertext.alpha)?;
+        self.modp(&undervote.ciphertext.beta)?;
+        self.proof(&undervote.proof_range)
+    }
+}
+
+struct PackedReader<'a> {
+    fixed_parameters: &'a FixedParameters,
+    bytes: &'a [u8],
+}
+
+impl<'a> PackedReader<'a> {
+    fn take(&mut self, len: usize, what: &str) -> Result<&'a [u8]> {
+        ensure!(
+            len <= self.bytes.len(),
+            "Packed ballot is truncated in the {what}"
+        );
+        let (taken, rest) = self.bytes.split_at(len);
+        self.bytes = rest;
+        Ok(taken)
+    }
+
+    fn u8(&mut self, what: &str) -> Result<u8> {
+        Ok(self.take(1, what)?[0])
+    }
+
+    fn u32(&mut self, what: &str) -> Result<u32> {
+        let mut buf = [0u8; 4];
+        buf.copy_from_slice(self.take(4, what)?);
+        Ok(u32::from_be_bytes(buf))
+    }
+
+    fn count(&mut self, what: &str) -> Result<usize> {
+        Ok(self.u32(what)? as usize)
+    }
+
+    fn string(&mut self, what: &str) -> Result<String> {
+        let len = self.count(what)?;
+        let bytes = self.take(len, what)?;
+        String::from_utf8(bytes.to_vec()).with_context(|| format!("Packed ballot {what}"))
+    }
+
+    fn hvalue(&mut self, what: &str) -> Result<HValue> {
+        let mut hvba = [0u8; HVALUE_BYTE_LEN];
+        hvba.copy_from_slice(self.take(HVALUE_BYTE_LEN, what)?);
+        Ok(HValue(hvba))
+    }
+
+    fn modp(&mut self, what: &str) -> Result<BigUint> {
+        let n = BigUint::from_bytes_be(self.take(self.fixed_parameters.l_p_bytes(), what)?);
+        ensure!(
+            self.fixed_parameters.is_valid_modp(&n),
+            "Packed ballot {what} is not valid mod p"
+        );
+        Ok(n)
+    }
+
+    fn modq(&mut self, what: &str) -> Result<BigUint> {
+        let n = BigUint::from_bytes_be(self.take(self.fixed_parameters.l_q_bytes(), what)?);
+        ensure!(
+            self.fixed_parameters.is_valid_modq(&n),
+            "Packed ballot {what} is not valid mod q"
+        );
+        Ok(n)
+    }
+
+    fn proof(&mut self) -> Result<ProofRange> {
+        let cnt_singles = self.count("proof length")?;
+        let mut singles = Vec::with_capacity(cnt_singles.min(self.bytes.len()));
+        for _ in 0..cnt_singles {
+            let c = self.modq("proof c")?;
+            let v = self.modq("proof v")?;
+            singles.push(ProofRangeSingle { c, v });
+        }
+        Ok(ProofRange::from_singles(singles))
+    }
+
+    fn contest(&mut self, version: u8) -> Result<ContestEncrypted> {
+        let cnt_options = self.count("option count")?;
+        let contest_hash = self.hvalue("contest hash")?;
+
+        let mut selection = Vec::with_capacity(cnt_options.min(self.bytes.len()));
+        for _ in 0..cnt_options {
+            let alpha = self.modp("ciphertext alpha")?;
+            let beta = self.modp("ciphertext beta")?;
+            selection.push(Ciphertext {
+                alpha,
+                beta,
+                nonce: None,
+            });
+        }
+
+        let mut proof_ballot_correctness = Vec1::with_capacity(cnt_options.min(self.bytes.len()));
+        for _ in 0..cnt_options {
+            proof_ballot_correctness.try_push(self.proof()?)?;
+        }
+
+        let proof_selection_limit = self.proof()?;
+
+        let opt_blank_indicator = match version {
+            1 => None,
+            _ => match self.u8("blank indicator flag")? {
+                0 => None,
+                1 => Some(self.blank_indicator()?),
+                flag => bail!("Invalid blank indicator flag {flag}"),
+            },
+        };
+
+        let opt_undervote = match version {
+            1..=4 => None,
+            _ => match self.u8("undervote flag")? {
+                0 => None,
+                1 => Some(self.undervote()?),
+                flag => bail!("Invalid undervote flag {flag}"),
+            },
+        };
+
+        Ok(ContestEncrypted {
+            selection,
+            contest_hash,
+            proof_ballot_correctness,
+            proof_selection_limit,
+            opt_blank_indicator,
+            opt_undervote,
+        })
+    }
+
+    fn blank_indicator(&mut self) -> Result<BlankIndicator> {
+        let alpha = self.modp("blank indicator alpha")?;
+        let beta = self.modp("blank indicator beta")?;
+        Ok(BlankIndicator {
+            ciphertext: Ciphertext {
+                alpha,
+                beta,
+                nonce: None,
+            },
+            proof_range: self.proof()?,
+            proof_only_if_blank: self.proof()?,
+            proof_if_blank: self.proof()?,
+        })
+    }
+
+    fn undervote(&mut self) -> Result<UndervoteCount> {
+        let alpha = self.modp("undervote alpha")?;
+        let beta = self.modp("undervote beta")?;
+        Ok(UndervoteCount {
+            ciphertext: Ciphertext {
+                alpha,
+                beta,
+                nonce: None,
+            },
+            proof_range: self.proof()?,
+        })
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod test {
+    use super::*;
+    use crate::{
+        contest_selection::ContestSelection, device::Device, example_election::ExampleElection,
+    };
+    use util::csprng::Csprng;
+
+    #[test]
+    fn test_packed_round_trip() {
+        let mut csprng = Csprng::new(b"test_packed_round_trip");
+
+        let ExampleElection { pvd, .. } =
+            ExampleElection::with_first_contests(&mut csprng, 2).unwrap();
+        let fixed_parameters = &pvd.parameters.fixed_parameters;
+
+        let device = Device::new("test", pvd.clone());
+
+        let mut ctest_selections = Vec1::new();
+        for contest_ix in pvd.manifest.contests.indices() {
+            let contest = pvd.manifest.contests.get(contest_ix).unwrap();
+            ctest_selections
+                .try_push(ContestSelection::new_pick_random(
+                    &mut csprng,
+                    contest.selection_limit,
+                    contest.options.len(),
+                ))
+                .unwrap();
+        }
+        let mut ballot = BallotEncrypted::new_from_selections_with_associated_data(
+            &device,
+            &mut csprng,
+            &[7u8; 32],
+            &ctest_selections,
+            vec![("device".to_string(), "d1".to_string())],
+        )
+        .unwrap();
+        ballot.set_ballot_style(BallotStyleIndex::from_one_based_index(2).unwrap());
+
+        let packed = ballot.to_packed_bytes(fixed_parameters).unwrap();
+        let ballot_2 = BallotEncrypted::from_packed_bytes(&packed, fixed_parameters).unwrap();
+
+        let mut json = Vec::new();
+        ballot.to_stdiowrite(&mut json).unwrap();
+        let mut json_2 = Vec::new();
+        ballot_2.to_stdiowrite(&mut json_2).unwrap();
+        assert_eq!(json_2, json);
+
+        // Every mod p value takes exactly l_p_bytes.
+        let cnt_ciphertexts: usize = ballot
+            .contests
+            .indices()
+            .map(|ix| ballot.contests.get(ix).unwrap().selection.len())
+            .sum();
+        assert!(packed.len() > cnt_ciphertexts * 2 * fixed_parameters.l_p_bytes());
+
+        // Pretty JSON spends at least two characters per byte on hex.
+        assert!(
+            packed.len() * 2 < json.len(),
+            "{} {}",
+            packed.len(),
+            json.len()
+        );
+
+        // Truncation and trailing garbage are detected.
+        assert!(
+            BallotEncrypted::from_packed_bytes(&packed[..packed.len() - 1], fixed_parameters)
+                .is_err()
+        );
+        let mut extended = packed.clone();
+        extended.push(0);
+        assert!(BallotEncrypted::from_packed_bytes(&extended, fixed_parameters).is_err());
+
+        // A JSON ballot is recognized as such.
+        let e = BallotEncrypted::from_packed_bytes(&json, fixed_parameters).unwrap_err();
+        assert!(e.to_string().contains("JSON ballot"), "{e}");
+
+        let mut wrong_version = packed.clone();
+        wrong_version[PACKED_BALLOT_MAGIC.len()] = PACKED_BALLOT_VERSION + 1;
+        assert!(BallotEncrypted::from_packed_bytes(&wrong_version, fixed_parameters).is_err());
+    }
+}
@@ -19,6 +19,7 @@ pub type BallotStyleIndex = Index<BallotStyle>;
 /// A ballot style.
 /// TODO: write more?
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "strict-parsing", serde(deny_unknown_fields))]
 pub struct BallotStyle {
     /// The label for this ballot style.
     pub label: String,
@@ -0,0 +1,185 @@
+// Copyright (C) Microsoft Corporation. All rights reserved.
+
+#![deny(clippy::unwrap_used)]
+#![deny(clippy::expect_used)]
+#![deny(clippy::panic)]
+#![deny(clippy::manual_assert)]
+
+use std::borrow::Borrow;
+
+use anyhow::{ensure, Context, Result};
+use num_bigint::BigUint;
+use num_traits::One;
+
+use crate::{
+    election_parameters::ElectionParameters, fixed_parameters::FixedParameters,
+    guardian::GuardianIndex, guardian_public_key::GuardianPublicKey,
+    guardian_secret_key::CoefficientCommitments,
+};
+
+/// Computes `Π_j K_i,j^(ℓ^j) mod p` from guardian `i`'s coefficient commitments `K_i,j`.
+///
+/// This is `g^P_i(ℓ)`, the value that guardian `i`'s share for guardian `ℓ` must be the
+/// discrete log of.
+pub fn share_commitment(
+    fixed_parameters: &FixedParameters,
+    coefficient_commitments: &CoefficientCommitments,
+    recipient_i: GuardianIndex,
+) -> BigUint {
+    let p: &BigUint = fixed_parameters.p.borrow();
+    let q: &BigUint = fixed_parameters.q.borrow();
+    let l = BigUint::from(recipient_i.get_one_based_u32());
+
+    let mut l_pow_j = BigUint::one();
+    let mut product = BigUint::one();
+    for k_i_j in &coefficient_commitments.0 {
+        product = product * k_i_j.0.modpow(&l_pow_j, p) % p;
+        l_pow_j = l_pow_j * &l % q;
+    }
+    product
+}
+
+/// The coefficient commitments `K_i,j` of every guardian, indexed by guardian `i` and then
+/// coefficient `j`.
+///
+/// Share verification and key share computation both need the commitments of guardians
+/// other than the one doing the work. Building this once checks that they are all present
+/// and the right shape.
+#[derive(Debug, Clone)]
+pub struct CommitmentMatrix {
+    /// One row per guardian, in order of guardian number.
+    rows: Vec<CoefficientCommitments>,
+}
+
+impl CommitmentMatrix {
+    /// Assembles the matrix from the public keys of all `n` guardians, in any order.
+    ///
+    /// Every guardian must be present exactly once, with exactly `k` commitments.
+    pub fn new(
+        election_parameters: &ElectionParameters,
+        guardian_public_keys: &[GuardianPublicKey],
+    ) -> Result<Self> {
+        let varying_parameters = &election_parameters.varying_parameters;
+        let k = varying_parameters.k.as_quantity();
+
+        let mut rows = Vec::with_capacity(varying_parameters.n.as_quantity());
+        for i in varying_parameters.each_guardian_i() {
+            let mut matching = guardian_public_keys.iter().filter(|gpk| gpk.i == i);
+            let gpk = matching
+                .next()
+                .with_context(|| format!("No public key for guardian {i}"))?;
+            ensure!(
+                matching.next().is_none(),
+                "More than one public key for guardian {i}"
+            );
+
+            let cnt_commitments = gpk.coefficient_commitments.0.len();
+            ensure!(
+                cnt_commitments == k,
+                "Guardian {i} published {cnt_commitments} coefficient commitments, expected {k}"
+            );
+
+            rows.push(gpk.coefficient_commitments.clone());
+        }
+
+        ensure!(
+            guardian_public_keys.len() == rows.len(),
+            "Expected public keys for {} guardians, found {}",
+            rows.len(),
+            guardian_public_keys.len()
+        );
+
+        Ok(CommitmentMatrix { rows })
+    }
+
+    /// Returns the commitments of guardian `i`.
+    pub fn row(&self, i: GuardianIndex) -> Option<&CoefficientCommitments> {
+        self.rows.get(i.get_zero_based_usize())
+    }
+
+    /// Returns the commitment `K_i,j`, where `j` is 0-based as in the spec.
+    pub fn get(&self, i: GuardianIndex, j: usize) -> Option<&BigUint> {
+        self.row(i)?.0.get(j).map(|k_i_j| &k_i_j.0)
+    }
+
+    /// Returns `g^P_i(ℓ)`, which the share sent from guardian `sender_i` to guardian
+    /// `recipient_i` must be the discrete log of. See [`share_commitment`].
+    pub fn expected_share_commitment(
+        &self,
+        fixed_parameters: &FixedParameters,
+        sender_i: GuardianIndex,
+        recipient_i: GuardianIndex,
+    ) -> Result<BigUint> {
+        let row = self
+            .row(sender_i)
+            .with_context(|| format!("No commitments for guardian {sender_i}"))?;
+        Ok(share_commitment(fixed_parameters, row, recipient_i))
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod test {
+    use super::*;
+    use crate::{
+        example_election::ExampleElection, example_election_parameters::example_election_parameters,
+    };
+    use util::csprng::Csprng;
+
+    #[test]
+    fn test_commitment_matrix() {
+        let mut csprng = Csprng::new(b"test_commitment_matrix");
+
+        let election_parameters = example_election_parameters();
+        let fixed_parameters = &election_parameters.fixed_parameters;
+        let p: &BigUint = fixed_parameters.p.borrow();
+        let q: &BigUint = fixed_parameters.q.borrow();
+
+        let (guardian_secret_keys, mut guardian_public_keys) =
+            ExampleElection::generate_guardian_keys(&mut csprng, &election_parameters).unwrap();
+
+        // Order doesn't matter.
+        guardian_public_keys.reverse();
+        let matrix = CommitmentMatrix::new(&election_parameters, &guardian_public_keys).unwrap();
+
+        let sender = &guardian_secret_keys[1];
+        let recipient_i = guardian_secret_keys[3].i;
+        assert_eq!(
+            matrix.get(sender.i, 0),
+            Some(sender.make_public_key().public_key_k_i_0())
+        );
+
+        // P_2(4) = a_2,0 + a_2,1 * 4 + a_2,2 * 16 + ...
+        let l = BigUint::from(recipient_i.get_one_based_u32());
+        let share = sender
+            .secret_coefficients()
+            .0
+            .iter()
+            .rev()
+            .fold(BigUint::from(0u8), |acc, a_j| (acc * &l + &a_j.0) % q);
+        assert_eq!(
+            matrix
+                .expected_share_commitment(fixed_parameters, sender.i, recipient_i)
+                .unwrap(),
+            fixed_parameters.g.modpow(&share, p)
+        );
+
+        // Every guardian must be present exactly once, with exactly k commitments.
+        let err =
+            CommitmentMatrix::new(&election_parameters, &guardian_public_keys[1..]).unwrap_err();
+        assert!(
+            err.to_string().starts_with("No public key for guardian"),
+            "{err}"
+        );
+
+        let mut duplicated = guardian_public_keys.clone();
+        duplicated.push(guardian_public_keys[0].clone());
+        let err = CommitmentMatrix::new(&election_parameters, &duplicated).unwrap_err();
+        assert!(err.to_string().contains("More than one"), "{err}");
+
+        let mut short = guardian_public_keys.clone();
+        short[2].coefficient_commitments.0.pop();
+        let err = CommitmentMatrix::new(&election_parameters, &short).unwrap_err();
+        assert!(err.to_string().contains("coefficient commitments"), "{err}");
+    }
+}
@@ -5,9 +5,13 @@
 #![deny(clippy::panic)]
 #![deny(clippy::manual_assert)]
 
+use anyhow::{anyhow, bail, ensure, Result};
+use num_bigint::BigUint;
+
 use crate::{
+    ballot_style::BallotStyleIndex,
     contest_encrypted::ContestEncrypted,
-    hash::{eg_h, HValue},
+    hash::{eg_h, HValue, HVALUE_BYTE_LEN},
     vec1::Vec1,
 };
 
@@ -26,3 +30,257 @@ pub fn confirmation_code(h_e: &HValue, contests: &Vec1<ContestEncrypted>, b_aux:
     v.extend_from_slice(b_aux);
     eg_h(h_e, &v)
 }
+
+/// Returns the auxiliary data `B_aux` of a ballot of the given style and with the given
+/// associated data, which binds them to the confirmation code.
+///
+/// For a ballot with neither, this is 32 zero bytes, as for ballots which predate them.
+/// Otherwise it is the one-based ballot style index as a big-endian `u32`, or 0 if there is
+/// none, then the count of associated data pairs, followed by each key and value preceded
+/// by its length, all as big-endian `u64`s. The style or count is nonzero, so this is never
+/// 32 zero bytes, and no two different styles and lists have the same encoding. So a ballot
+/// can't be relabeled with another style without changing its confirmation code.
+pub fn b_aux(
+    opt_ballot_style: Option<BallotStyleIndex>,
+    associated_data: &[(String, String)],
+) -> Vec<u8> {
+    if opt_ballot_style.is_none() && associated_data.is_empty() {
+        return vec![0u8; 32];
+    }
+
+    let mut v = Vec::new();
+    v.extend_from_slice(
+        &opt_ballot_style
+            .map_or(0, |ix| ix.get_one_based_u32())
+            .to_be_bytes(),
+    );
+    v.extend_from_slice(&(associated_data.len() as u64).to_be_bytes());
+    for (key, value) in associated_data {
+        for s in [key, value] {
+            v.extend_from_slice(&(s.len() as u64).to_be_bytes());
+            v.extend_from_slice(s.as_bytes());
+        }
+    }
+    v
+}
+
+/// A confirmation code in the form in which it is presented to a voter, e.g. printed
+/// on a receipt.
+///
+/// The human-readable representation uses an alphabet of 31 characters which omits the
+/// easily-confused `0`/`O` and `1`/`I`/`L`. It is followed by a check character, which
+/// detects any single-character error and any transposition of adjacent characters.
+/// Characters are grouped by four, separated by `-`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ConfirmationCode(pub HValue);
+
+impl ConfirmationCode {
+    /// The characters used in the human-readable representation, in order of value.
+    pub const HUMAN_ALPHABET: &'static [u8; 31] = b"23456789ABCDEFGHJKMNPQRSTUVWXYZ";
+
+    /// Count of characters needed to represent a 256-bit value, not including the check character.
+    /// 31^52 > 2^256.
+    const HUMAN_DATA_CHARS: usize = 52;
+
+    const HUMAN_GROUP_LEN: usize = 4;
+
+    /// Returns the human-readable representation.
+    pub fn to_human_string(&self) -> String {
+        let radix = Self::HUMAN_ALPHABET.len() as u32;
+        let mut digits = BigUint::from_bytes_be(&self.0 .0).to_radix_be(radix);
+        debug_assert!(digits.len() <= Self::HUMAN_DATA_CHARS);
+
+        let mut padded = vec![0u8; Self::HUMAN_DATA_CHARS.saturating_sub(digits.len())];
+        padded.append(&mut digits);
+        padded.push(Self::check_digit(&padded));
+
+        let chars: Vec<char> = padded
+            .iter()
+            .map(|&d| Self::HUMAN_ALPHABET[d as usize] as char)
+            .collect();
+
+        chars
+            .chunks(Self::HUMAN_GROUP_LEN)
+            .map(|group| group.iter().collect::<String>())
+            .collect::<Vec<_>>()
+            .join("-")
+    }
+
+    /// Parses the human-readable representation, verifying the check character.
+    /// Separators (`-` and whitespace) are ignored, and lowercase is accepted.
+    pub fn from_human_string(s: &str) -> Result<Self> {
+        let mut digits = s
+            .chars()
+            .filter(|c| *c != '-' && !c.is_whitespace())
+            .map(|c| {
+                let uc = c.to_ascii_uppercase();
+                Self::HUMAN_ALPHABET
+                    .iter()
+                    .position(|&a| a as char == uc)
+                    .map(|d| d as u8)
+                    .ok_or_else(|| anyhow!("Invalid character {c:?} in confirmation code"))
+            })
+            .collect::<Result<Vec<u8>>>()?;
+
+        ensure!(
+            digits.len() == Self::HUMAN_DATA_CHARS + 1,
+            "Confirmation code has {} characters, expected {}",
+            digits.len(),
+            Self::HUMAN_DATA_CHARS + 1
+        );
+
+        #[allow(clippy::unwrap_used)]
+        // `unwrap()` is justified here because the length was checked above.
+        let check_digit = digits.pop().unwrap();
+        ensure!(
+            check_digit == Self::check_digit(&digits),
+            "Confirmation code check character does not match"
+        );
+
+        let radix = Self::HUMAN_ALPHABET.len() as u32;
+        let Some(n) = BigUint::from_radix_be(&digits, radix) else {
+            bail!("Invalid confirmation code");
+        };
+
+        let bytes = n.to_bytes_be();
+        ensure!(
+            bytes.len() <= HVALUE_BYTE_LEN,
+            "Confirmation code value is out of range"
+        );
+
+        let mut hvalue = HValue::default();
+        hvalue.0[HVALUE_BYTE_LEN - bytes.len()..].copy_from_slice(&bytes);
+        Ok(Self(hvalue))
+    }
+
+    /// Weighted sum mod 31 with weights cycling through 1..=30. Since 31 is prime and no
+    /// weight is 0 mod 31, any single substitution changes the sum. Adjacent weights differ
+    /// by 1 or 29, so any transposition of two different adjacent digits changes it too.
+    fn check_digit(digits: &[u8]) -> u8 {
+        let m = Self::HUMAN_ALPHABET.len();
+        let sum = digits.iter().enumerate().fold(0usize, |acc, (ix, &d)| {
+            (acc + (ix % (m - 1) + 1) * d as usize) % m
+        });
+        sum as u8
+    }
+}
+
+impl From<HValue> for ConfirmationCode {
+    #[inline]
+    fn from(value: HValue) -> Self {
+        ConfirmationCode(value)
+    }
+}
+
+impl std::fmt::Display for ConfirmationCode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.to_human_string())
+    }
+}
+
+impl std::str::FromStr for ConfirmationCode {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::from_human_string(s)
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod test {
+    use super::*;
+
+    fn examples() -> Vec<ConfirmationCode> {
+        [
+            [0u8; 32],
+            [0xffu8; 32],
+            core::array::from_fn(|ix| ix as u8),
+            core::array::from_fn(|ix| (ix as u8).wrapping_mul(151)),
+        ]
+        .into_iter()
+        .map(|a| ConfirmationCode(HValue(a)))
+        .collect()
+    }
+
+    #[test]
+    fn test_b_aux() {
+        let pair = |key: &str, value: &str| (key.to_string(), value.to_string());
+
+        assert_eq!(b_aux(None, &[]), vec![0u8; 32]);
+
+        let a = b_aux(None, &[pair("device", "d1"), pair("time", "9:00")]);
+        assert_ne!(
+            a,
+            b_aux(None, &[pair("device", "d1"), pair("time", "9:01")])
+        );
+        assert_ne!(
+            a,
+            b_aux(None, &[pair("time", "9:00"), pair("device", "d1")])
+        );
+
+        // Moving characters between the key and the value changes the encoding.
+        assert_ne!(
+            b_aux(None, &[pair("ab", "c")]),
+            b_aux(None, &[pair("a", "bc")])
+        );
+        assert_ne!(b_aux(None, &[pair("", "")]), b_aux(None, &[]));
+
+        // Two empty pairs would otherwise be 32 zero bytes, the same as no associated data.
+        assert_ne!(b_aux(None, &[pair("", ""), pair("", "")]), b_aux(None, &[]));
+
+        // The ballot style is bound too, with or without associated data.
+        let style = |i: u32| Some(BallotStyleIndex::from_one_based_index(i).unwrap());
+        assert_ne!(b_aux(style(1), &[]), b_aux(None, &[]));
+        assert_ne!(b_aux(style(1), &[]), b_aux(style(2), &[]));
+        assert_ne!(
+            b_aux(style(1), &[pair("", "")]),
+            b_aux(None, &[pair("", "")])
+        );
+    }
+
+    #[test]
+    fn test_round_trip() {
+        for cc in examples() {
+            let s = cc.to_human_string();
+            assert_eq!(s.chars().filter(|&c| c != '-').count(), 53);
+            assert!(!s.contains(['0', 'O', '1', 'I', 'L']));
+
+            assert_eq!(ConfirmationCode::from_human_string(&s).unwrap(), cc);
+            assert_eq!(s.to_lowercase().parse::<ConfirmationCode>().unwrap(), cc);
+            assert_eq!(cc.to_string(), s);
+        }
+    }
+
+    #[test]
+    fn test_detects_errors() {
+        for cc in examples() {
+            let chars: Vec<char> = cc.to_human_string().replace('-', "").chars().collect();
+
+            // Every transposition of adjacent, different characters.
+            for ix in 0..chars.len() - 1 {
+                if chars[ix] != chars[ix + 1] {
+                    let mut bad = chars.clone();
+                    bad.swap(ix, ix + 1);
+                    let bad: String = bad.into_iter().collect();
+                    assert!(ConfirmationCode::from_human_string(&bad).is_err());
+                }
+            }
+
+            // Every single-character substitution.
+            for ix in 0..chars.len() {
+                for &a in ConfirmationCode::HUMAN_ALPHABET.iter() {
+                    if a as char != chars[ix] {
+                        let mut bad = chars.clone();
+                        bad[ix] = a as char;
+                        let bad: String = bad.into_iter().collect();
+                        assert!(ConfirmationCode::from_human_string(&bad).is_err());
+                    }
+                }
+            }
+        }
+
+        assert!(ConfirmationCode::from_human_string("2345").is_err());
+        assert!(ConfirmationCode::from_human_string("O").is_err());
+    }
+}
@@ -5,6 +5,8 @@
 #![deny(clippy::panic)]
 #![deny(clippy::manual_assert)]
 
+use num_bigint::BigUint;
+use num_traits::{One, Zero};
 use serde::{Deserialize, Serialize};
 use util::{csprng::Csprng, prime::BigUintPrime};
 
@@ -19,6 +21,7 @@ use crate::{
     index::Index,
     joint_election_public_key::Ciphertext,
     nonce::encrypted as nonce,
+    tally::ciphertext_accumulate,
     vec1::Vec1,
     zk::ProofRange,
 };
@@ -60,6 +63,249 @@ pub struct ContestEncrypted {
 
     // Proof of satisfying the selection limit.
     pub proof_selection_limit: ProofRange,
+
+    /// Encrypted indicator of whether the contest was left blank, present iff the contest
+    /// has [`Contest::track_blank`] set.
+    #[serde(
+        rename = "blank_indicator",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub opt_blank_indicator: Option<BlankIndicator>,
+
+    /// Encrypted number of undervotes, present iff the contest has
+    /// [`Contest::track_undervotes`] set.
+    #[serde(rename = "undervote", default, skip_serializing_if = "Option::is_none")]
+    pub opt_undervote: Option<UndervoteCount>,
+}
+
+/// An encryption of `b`, which is 1 iff no option of the contest was selected, together with
+/// proofs that it is consistent with the selections.
+///
+/// With `s` the number of selections and `L` the selection limit, the proofs show that
+/// `b` is 0 or 1, that `L*b + s <= L` (so `b = 1` implies `s = 0`), and that `s + b - 1 >= 0`
+/// (so `s = 0` implies `b = 1`).
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct BlankIndicator {
+    /// Encryption of `b`.
+    pub ciphertext: Ciphertext,
+
+    /// Proof that `0 <= b <= 1`.
+    pub proof_range: ProofRange,
+
+    /// Proof that `0 <= L*b + s <= L`.
+    pub proof_only_if_blank: ProofRange,
+
+    /// Proof that `0 <= s + b - 1 <= L`.
+    pub proof_if_blank: ProofRange,
+}
+
+impl BlankIndicator {
+    /// Label used to derive the nonce of the blank indicator from the primary nonce. It can't
+    /// be the label of an option, because labels may not contain NUL characters.
+    const NONCE_LABEL: &'static [u8] = b"\0blank";
+
+    fn new(
+        header: &PreVotingData,
+        csprng: &mut Csprng,
+        primary_nonce: &[u8],
+        contest: &Contest,
+        selection: &[Ciphertext],
+        num_selections: usize,
+    ) -> Self {
+        let fixed_parameters = &header.parameters.fixed_parameters;
+        let q = &fixed_parameters.q;
+        let big_l = contest.selection_limit;
+
+        let b = usize::from(num_selections == 0);
+        let nonce = nonce(
+            header,
+            primary_nonce,
+            contest.label.as_bytes(),
+            Self::NONCE_LABEL,
+        );
+        let ciphertext = header
+            .public_key
+            .encrypt_with(fixed_parameters, &nonce, b, true);
+
+        let sum = ContestEncrypted::sum_selection_vector(fixed_parameters, selection);
+        let (ct_only_if, ct_if) = Self::derived_ciphertexts(header, big_l, &ciphertext, &sum);
+
+        let proof_range = ProofRange::new(header, csprng, q, &ciphertext, b, 1);
+        let proof_only_if_blank = ProofRange::new(
+            header,
+            csprng,
+            q,
+            &ct_only_if,
+            big_l * b + num_selections,
+            big_l,
+        );
+        let proof_if_blank =
+            ProofRange::new(header, csprng, q, &ct_if, num_selections + b - 1, big_l);
+
+        // The nonce is kept, as for the selections, so that it can be disclosed if the ballot
+        // is challenged. It is never serialized.
+        Self {
+            ciphertext,
+            proof_range,
+            proof_only_if_blank,
+            proof_if_blank,
+        }
+    }
+
+    /// Verifies the proofs against the contest's selection ciphertexts.
+    pub fn verify(
+        &self,
+        header: &PreVotingData,
+        contest: &Contest,
+        selection: &[Ciphertext],
+    ) -> bool {
+        let fixed_parameters = &header.parameters.fixed_parameters;
+        let big_l = contest.selection_limit;
+
+        let mut sum = Ciphertext {
+            alpha: BigUint::one(),
+            beta: BigUint::one(),
+            nonce: None,
+        };
+        for ciphertext in selection {
+            ciphertext_accumulate(fixed_parameters, &mut sum, ciphertext);
+        }
+
+        let (ct_only_if, ct_if) = Self::derived_ciphertexts(header, big_l, &self.ciphertext, &sum);
+
+        self.proof_range.verify(header, &self.ciphertext, 1)
+            && self.proof_only_if_blank.verify(header, &ct_only_if, big_l)
+            && self.proof_if_blank.verify(header, &ct_if, big_l)
+    }
+
+    /// Computes the encryptions of `L*b + s` and `s + b - 1` from the encryptions of `b` and
+    /// `s`. Their nonces are computed too if both input nonces are known.
+    fn derived_ciphertexts(
+        header: &PreVotingData,
+        big_l: usize,
+        ct_b: &Ciphertext,
+        ct_s: &Ciphertext,
+    ) -> (Ciphertext, Ciphertext) {
+        let fixed_parameters = &header.parameters.fixed_parameters;
+        let p = fixed_parameters.p.as_ref();
+        let q = fixed_parameters.q.as_ref();
+        let big_l_biguint = BigUint::from(big_l);
+
+        let opt_nonces = ct_b.nonce.as_ref().zip(ct_s.nonce.as_ref());
+
+        let ct_only_if = Ciphertext {
+            alpha: (ct_b.alpha.modpow(&big_l_biguint, p) * &ct_s.alpha) % p,
+            beta: (ct_b.beta.modpow(&big_l_biguint, p) * &ct_s.beta) % p,
+            nonce: opt_nonces.map(|(r_b, r_s)| (&big_l_biguint * r_b + r_s) % q),
+        };
+
+        // Dividing beta by K subtracts one from the encrypted value. K is in the subgroup of
+        // order q, so its inverse is K^(q-1), which is much cheaper to compute than K^(p-2).
+        let k_inv = header
+            .public_key
+            .joint_election_public_key
+            .modpow(&(q - 1u8), p);
+        let ct_if = Ciphertext {
+            alpha: (&ct_b.alpha * &ct_s.alpha) % p,
+            beta: (&ct_b.beta * &ct_s.beta % p) * k_inv % p,
+            nonce: opt_nonces.map(|(r_b, r_s)| (r_b + r_s) % q),
+        };
+
+        (ct_only_if, ct_if)
+    }
+}
+
+/// An encryption of `L - s`, the number of undervotes, with `s` the number of selections and
+/// `L` the selection limit, together with a proof that it is in the range `0..=L`.
+///
+/// The ciphertext is computed homomorphically from the encrypted selections, so it does not
+/// need a nonce of its own and is not covered by the contest hash. It is checked against the
+/// selections when the proof is verified.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct UndervoteCount {
+    /// Encryption of `L - s`.
+    pub ciphertext: Ciphertext,
+
+    /// Proof that `0 <= L - s <= L`.
+    pub proof_range: ProofRange,
+}
+
+impl UndervoteCount {
+    fn new(
+        header: &PreVotingData,
+        csprng: &mut Csprng,
+        contest: &Contest,
+        selection: &[Ciphertext],
+        num_selections: usize,
+    ) -> Self {
+        let fixed_parameters = &header.parameters.fixed_parameters;
+        let big_l = contest.selection_limit;
+
+        let sum = ContestEncrypted::sum_selection_vector(fixed_parameters, selection);
+        let ciphertext = Self::ciphertext_from_sum(header, big_l, &sum);
+        let proof_range = ProofRange::new(
+            header,
+            csprng,
+            &fixed_parameters.q,
+            &ciphertext,
+            big_l.saturating_sub(num_selections),
+            big_l,
+        );
+
+        Self {
+            ciphertext,
+            proof_range,
+        }
+    }
+
+    /// Verifies that the ciphertext is the one computed from the contest's selection
+    /// ciphertexts, and its range proof.
+    pub fn verify(
+        &self,
+        header: &PreVotingData,
+        contest: &Contest,
+        selection: &[Ciphertext],
+    ) -> bool {
+        let fixed_parameters = &header.parameters.fixed_parameters;
+        let big_l = contest.selection_limit;
+
+        let mut sum = Ciphertext {
+            alpha: BigUint::one(),
+            beta: BigUint::one(),
+            nonce: None,
+        };
+        for ciphertext in selection {
+            ciphertext_accumulate(fixed_parameters, &mut sum, ciphertext);
+        }
+        let expected = Self::ciphertext_from_sum(header, big_l, &sum);
+
+        self.ciphertext.alpha == expected.alpha
+            && self.ciphertext.beta == expected.beta
+            && self.proof_range.verify(header, &self.ciphertext, big_l)
+    }
+
+    /// Computes the encryption of `L - s` from the encryption of `s`, by negating it and
+    /// adding `L`. Its nonce is computed too if that of `s` is known.
+    fn ciphertext_from_sum(header: &PreVotingData, big_l: usize, ct_s: &Ciphertext) -> Ciphertext {
+        let fixed_parameters = &header.parameters.fixed_parameters;
+        let p = fixed_parameters.p.as_ref();
+        let q = fixed_parameters.q.as_ref();
+
+        // Both parts of the ciphertext are in the subgroup of order q, so they are inverted
+        // by raising them to q - 1.
+        let q_minus_1 = q - 1u8;
+        let k_l = header
+            .public_key
+            .joint_election_public_key
+            .modpow(&BigUint::from(big_l), p);
+
+        Ciphertext {
+            alpha: ct_s.alpha.modpow(&q_minus_1, p),
+            beta: ct_s.beta.modpow(&q_minus_1, p) * k_l % p,
+            nonce: ct_s.nonce.as_ref().map(|r_s| (q - r_s % q) % q),
+        }
+    }
 }
 
 impl ContestEncrypted {
@@ -85,7 +331,7 @@ impl ContestEncrypted {
             vote.push(header.public_key.encrypt_with(
                 &header.parameters.fixed_parameters,
                 &nonce,
-                pt_vote.vote[j] as usize,
+                pt_vote.vote[j - 1] as usize,
                 true,
             ));
         }
@@ -100,8 +346,74 @@ impl ContestEncrypted {
         pt_vote: &ContestSelection,
     ) -> ContestEncrypted {
         let selection = Self::encrypt_selection(&device.header, primary_nonce, contest, pt_vote);
-        let contest_hash = contest_hash::contest_hash(&device.header, &contest.label, &selection);
+        Self::with_selection(
+            device,
+            csprng,
+            primary_nonce,
+            contest,
+            pt_vote,
+            selection,
+            true,
+        )
+    }
+
+    /// Encrypts a contest which is not on the voter's ballot style as having no selections.
+    ///
+    /// Every option is encrypted with the nonce zero, which gives the ciphertext `(1, 1)`, and
+    /// there is no blank indicator or undervote count, even if the contest tracks them, since
+    /// the voter could not have left blank or undervoted a contest they were never offered.
+    /// Anyone can then check with [`ContestEncrypted::is_omitted`] that the contest adds
+    /// nothing to the tally, without learning anything about the ballot that its style
+    /// doesn't already reveal.
+    pub fn new_omitted(
+        device: &Device,
+        csprng: &mut Csprng,
+        primary_nonce: &[u8],
+        contest: &Contest,
+    ) -> ContestEncrypted {
+        let header = &device.header;
+        let pt_vote = ContestSelection {
+            vote: vec![0; contest.options.len()],
+        };
+        let ciphertext_zero = header.public_key.encrypt_with(
+            &header.parameters.fixed_parameters,
+            &BigUint::zero(),
+            0,
+            true,
+        );
+        let selection = vec![ciphertext_zero; contest.options.len()];
+        Self::with_selection(
+            device,
+            csprng,
+            primary_nonce,
+            contest,
+            &pt_vote,
+            selection,
+            false,
+        )
+    }
 
+    /// Returns true iff every option is encrypted as `(1, 1)` and there is no blank indicator
+    /// or undervote count, as by [`ContestEncrypted::new_omitted`].
+    pub fn is_omitted(&self) -> bool {
+        self.selection
+            .iter()
+            .all(|ciphertext| ciphertext.alpha.is_one() && ciphertext.beta.is_one())
+            && self.opt_blank_indicator.is_none()
+            && self.opt_undervote.is_none()
+    }
+
+    /// Completes the encrypted contest from the encryptions of `pt_vote`, adding the proofs,
+    /// and, if `track` is set, the blank indicator and undervote count the contest tracks.
+    fn with_selection(
+        device: &Device,
+        csprng: &mut Csprng,
+        primary_nonce: &[u8],
+        contest: &Contest,
+        pt_vote: &ContestSelection,
+        selection: Vec<Ciphertext>,
+        track: bool,
+    ) -> ContestEncrypted {
         let mut proof_ballot_correctness = Vec1::new();
         for (i, sel) in selection.iter().enumerate() {
             #[allow(clippy::unwrap_used)] //? TODO: Remove temp development code
@@ -126,14 +438,65 @@ impl ContestEncrypted {
             num_selections as usize,
             contest.selection_limit,
         );
+        let opt_blank_indicator = (track && contest.track_blank).then(|| {
+            BlankIndicator::new(
+                &device.header,
+                csprng,
+                primary_nonce,
+                contest,
+                &selection,
+                num_selections as usize,
+            )
+        });
+
+        let opt_undervote = (track && contest.track_undervotes).then(|| {
+            UndervoteCount::new(
+                &device.header,
+                csprng,
+                contest,
+                &selection,
+                num_selections as usize,
+            )
+        });
+
+        let contest_hash = contest_hash::contest_hash(
+            &device.header,
+            &contest.label,
+            &Self::hashed_ciphertexts(&selection, &opt_blank_indicator),
+        );
+
         ContestEncrypted {
             selection,
             contest_hash,
             proof_ballot_correctness,
             proof_selection_limit,
+            opt_blank_indicator,
+            opt_undervote,
         }
     }
 
+    /// Computes the contest hash of this encrypted contest.
+    pub fn compute_contest_hash(&self, header: &PreVotingData, contest: &Contest) -> HValue {
+        contest_hash::contest_hash(
+            header,
+            &contest.label,
+            &Self::hashed_ciphertexts(&self.selection, &self.opt_blank_indicator),
+        )
+    }
+
+    /// The ciphertexts covered by the contest hash: those of the selection, followed by the
+    /// blank indicator if any.
+    fn hashed_ciphertexts(
+        selection: &[Ciphertext],
+        opt_blank_indicator: &Option<BlankIndicator>,
+    ) -> Vec<Ciphertext> {
+        selection
+            .iter()
+            .chain(opt_blank_indicator.iter().map(|bi| &bi.ciphertext))
+            .cloned()
+            .collect()
+    }
+
     pub fn get_proof_ballot_correctness(&self) -> &Vec1<ProofRange> {
         &self.proof_ballot_correctness
     }
@@ -189,3 +552,41 @@ impl ContestEncrypted {
         sum_ct
     }
 }
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod test {
+    use super::*;
+    use crate::{election_manifest::ContestIndex, example_election::ExampleElection};
+
+    #[test]
+    fn test_encrypt_selection() {
+        let mut csprng = Csprng::new(b"test_encrypt_selection");
+        let pvd = ExampleElection::with_first_contests(&mut csprng, 1)
+            .unwrap()
+            .pvd;
+        let fixed_parameters = &pvd.parameters.fixed_parameters;
+        let contest = pvd
+            .manifest
+            .contests
+            .get(ContestIndex::from_one_based_index(1).unwrap())
+            .unwrap();
+
+        // Only the last option is selected, so each option must be paired with its own vote.
+        let mut vote = vec![0; contest.options.len()];
+        *vote.last_mut().unwrap() = 1;
+        let pt_vote = ContestSelection { vote: vote.clone() };
+
+        let selection = ContestEncrypted::encrypt_selection(&pvd, &[0; 32], contest, &pt_vote);
+        assert_eq!(selection.len(), vote.len());
+        for (ciphertext, &v) in selection.iter().zip(&vote) {
+            let nonce = ciphertext.nonce.as_ref().unwrap();
+            assert_eq!(
+                ciphertext.beta,
+                pvd.public_key
+                    .joint_election_public_key
+                    .modpow(&(nonce + v), fixed_parameters.p.as_ref())
+            );
+        }
+    }
+}
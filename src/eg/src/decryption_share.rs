@@ -0,0 +1,662 @@
+// Copyright (C) Microsoft Corporation. All rights reserved.
+
+#![deny(clippy::unwrap_used)]
+#![deny(clippy::expect_used)]
+#![deny(clippy::panic)]
+#![deny(clippy::manual_assert)]
+
+use std::{borrow::Borrow, collections::BTreeSet};
+
+use anyhow::{ensure, Context, Result};
+use num_bigint::BigUint;
+use num_traits::One;
+use serde::{Deserialize, Serialize};
+
+use util::csprng::Csprng;
+
+use crate::{
+    discrete_log::discrete_log_bounded,
+    election_record::PreVotingData,
+    fixed_parameters::FixedParameters,
+    guardian::GuardianIndex,
+    guardian_key_share::{GuardianKeyShare, GuardianPublicKeyShare},
+    guardian_public_key::GuardianPublicKey,
+    hash::eg_h,
+    joint_election_public_key::Ciphertext,
+    zk::{Record, TranscriptValue},
+};
+
+#[cfg(feature = "debug-transcript")]
+use crate::transcript::TranscriptSink;
+
+/// The domain separator of the challenge of a [`ProofDecryptionShare`].
+const CHALLENGE_DOMAIN: u8 = 0x30;
+
+/// Proof that a [`DecryptionShare`] was computed with the guardian's key share `P(i)`.
+///
+/// This is a Chaum-Pedersen proof that `log_g(g^P(i)) = log_A(M_i)`, where `g^P(i)` is the
+/// guardian's [`GuardianPublicKeyShare`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProofDecryptionShare {
+    #[serde(
+        serialize_with = "util::biguint_serde::biguint_serialize",
+        deserialize_with = "util::biguint_serde::biguint_deserialize"
+    )]
+    pub c: BigUint,
+    #[serde(
+        serialize_with = "util::biguint_serde::biguint_serialize",
+        deserialize_with = "util::biguint_serde::biguint_deserialize"
+    )]
+    pub v: BigUint,
+}
+
+/// A guardian's share of the decryption of a single [`Ciphertext`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DecryptionShare {
+    /// Guardian number, 1 <= i <= n.
+    pub i: GuardianIndex,
+
+    /// The partial decryption `M_i = A^P(i) mod p`.
+    #[serde(
+        serialize_with = "util::biguint_serde::biguint_serialize",
+        deserialize_with = "util::biguint_serde::biguint_deserialize"
+    )]
+    pub m_i: BigUint,
+
+    /// Proof of correct computation of `m_i`.
+    pub proof: ProofDecryptionShare,
+}
+
+impl DecryptionShare {
+    /// Computes guardian `i`'s decryption share of `ciphertext` with its key share, along with
+    /// the proof of its correctness.
+    pub fn compute(
+        csprng: &mut Csprng,
+        pvd: &PreVotingData,
+        guardian_key_share: &GuardianKeyShare,
+        ciphertext: &Ciphertext,
+    ) -> Self {
+        let fixed_parameters = &pvd.parameters.fixed_parameters;
+        let p: &BigUint = fixed_parameters.p.borrow();
+        let q = &fixed_parameters.q;
+
+        let p_i = guardian_key_share.secret_p_i();
+        let k_i = &guardian_key_share.public_key_share().k_i;
+
+        let m_i = ciphertext.alpha.modpow(p_i, p);
+
+        let u = q.random_group_elem(csprng);
+        let a = fixed_parameters.g.modpow(&u, p);
+        let b = ciphertext.alpha.modpow(&u, p);
+
+        let c = challenge(pvd, guardian_key_share.i, k_i, ciphertext, &m_i, &a, &b);
+        let v = q.subtract_group_elem(&u, &q.multiply_group_elem(&c, p_i));
+
+        DecryptionShare {
+            i: guardian_key_share.i,
+            m_i,
+            proof: ProofDecryptionShare { c, v },
+        }
+    }
+}
+
+/// Computes the challenge for a [`ProofDecryptionShare`].
+///
+/// The guardian number and its public key share `g^P(i)` are both hashed, so a proof made
+/// for one guardian can not be presented as though it came from another.
+fn challenge(
+    pvd: &PreVotingData,
+    i: GuardianIndex,
+    k_i: &BigUint,
+    ciphertext: &Ciphertext,
+    m_i: &BigUint,
+    a: &BigUint,
+    b: &BigUint,
+) -> BigUint {
+    let fixed_parameters = &pvd.parameters.fixed_parameters;
+
+    let mut v = vec![CHALLENGE_DOMAIN];
+    v.extend_from_slice(&i.get_one_based_u32().to_be_bytes());
+    for x in [k_i, &ciphertext.alpha, &ciphertext.beta, m_i, a, b] {
+        v.extend_from_slice(&fixed_parameters.biguint_to_be_bytes_len_p(x));
+    }
+
+    let c = eg_h(&pvd.hashes_ext.h_e, &v);
+    BigUint::from_bytes_be(c.0.as_slice()) % fixed_parameters.q.as_ref()
+}
+
+/// Verifies a [`DecryptionShare`] of `ciphertext` against the public key share of the
+/// guardian which claims to have produced it.
+///
+/// The proof is checked against `g^P(i)`, which is computed from every guardian's published
+/// commitments, so a share will be rejected if it was made with any other key.
+pub fn verify_decryption_share(
+    pvd: &PreVotingData,
+    guardian_public_key_share: &GuardianPublicKeyShare,
+    ciphertext: &Ciphertext,
+    decryption_share: &DecryptionShare,
+) -> Result<()> {
+    verify_decryption_share_impl(
+        pvd,
+        guardian_public_key_share,
+        ciphertext,
+        decryption_share,
+        &mut |_label, _value| {},
+    )
+}
+
+/// Same as [`verify_decryption_share`], but records every hashed element and challenge value
+/// in `transcript`.
+#[cfg(feature = "debug-transcript")]
+pub fn verify_decryption_share_with_transcript(
+    pvd: &PreVotingData,
+    guardian_public_key_share: &GuardianPublicKeyShare,
+    ciphertext: &Ciphertext,
+    decryption_share: &DecryptionShare,
+    transcript: &mut TranscriptSink,
+) -> Result<()> {
+    verify_decryption_share_impl(
+        pvd,
+        guardian_public_key_share,
+        ciphertext,
+        decryption_share,
+        &mut |label, value| transcript.record(label, value),
+    )
+}
+
+fn verify_decryption_share_impl(
+    pvd: &PreVotingData,
+    guardian_public_key_share: &GuardianPublicKeyShare,
+    ciphertext: &Ciphertext,
+    decryption_share: &DecryptionShare,
+    record: &mut Record,
+) -> Result<()> {
+    use TranscriptValue::{BigUint as Int, Bytes};
+
+    let fixed_parameters = &pvd.parameters.fixed_parameters;
+    let p: &BigUint = fixed_parameters.p.borrow();
+
+    let i = decryption_share.i;
+
+    ensure!(
+        guardian_public_key_share.i == i,
+        "Decryption share from guardian {i} can't be verified with the public key share of guardian {}",
+        guardian_public_key_share.i
+    );
+
+    let ProofDecryptionShare { c, v } = &decryption_share.proof;
+    let m_i = &decryption_share.m_i;
+
+    ensure!(
+        fixed_parameters.is_valid_nonzero_modp(m_i),
+        "Decryption share from guardian {i} is not a valid nonzero mod p value"
+    );
+    ensure!(
+        fixed_parameters.is_valid_modq(c) && fixed_parameters.is_valid_modq(v),
+        "Decryption share proof from guardian {i} has a response out of range"
+    );
+
+    let k_i = &guardian_public_key_share.k_i;
+
+    record(format_args!("domain"), Bytes(&[CHALLENGE_DOMAIN]));
+    record(format_args!("H_E"), Bytes(pvd.hashes_ext.h_e.0.as_slice()));
+    record(
+        format_args!("i"),
+        Bytes(&i.get_one_based_u32().to_be_bytes()),
+    );
+    record(format_args!("K_i"), Int(k_i));
+    record(format_args!("alpha"), Int(&ciphertext.alpha));
+    record(format_args!("beta"), Int(&ciphertext.beta));
+    record(format_args!("M_i"), Int(m_i));
+    record(format_args!("c"), Int(c));
+    record(format_args!("v"), Int(v));
+
+    let a = (fixed_parameters.g.modpow(v, p) * k_i.modpow(c, p)) % p;
+    let b = (ciphertext.alpha.modpow(v, p) * m_i.modpow(c, p)) % p;
+    record(format_args!("a"), Int(&a));
+    record(format_args!("b"), Int(&b));
+
+    let c_computed = challenge(pvd, i, k_i, ciphertext, m_i, &a, &b);
+    record(format_args!("c_computed"), Int(&c_computed));
+
+    ensure!(
+        *c == c_computed,
+        "Decryption share proof from guardian {i} does not verify against its public key share"
+    );
+
+    Ok(())
+}
+
+/// The combined decryption `T = K^m mod p` of a ciphertext encrypting `m`.
+///
+/// This marks the boundary of the secret-dependent part of decryption. Each guardian's
+/// [`DecryptionShare`] is computed with its key share, but `T` is computed only from the
+/// ciphertext and the published shares, so it is public. Finding `m` by the discrete log is
+/// not constant time, so the solver is private to this crate and
+/// [`CombinedDecryption::discrete_log`] is the only public way to reach it. The field is
+/// private so that nothing else can be passed in its place.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CombinedDecryption(BigUint);
+
+impl CombinedDecryption {
+    /// Returns `T`.
+    pub fn as_biguint(&self) -> &BigUint {
+        &self.0
+    }
+
+    /// Finds `m` such that `T = K^m mod p` and `0 <= m <= bound`, or `None` if there is none.
+    pub fn discrete_log(&self, pvd: &PreVotingData, bound: u64) -> Option<u64> {
+        discrete_log_bounded(
+            &pvd.public_key.joint_election_public_key,
+            pvd.parameters.fixed_parameters.p.as_ref(),
+            &self.0,
+            bound,
+        )
+    }
+}
+
+/// Computes the Lagrange coefficient `w_i = Π_{ℓ≠i} ℓ/(ℓ-i) mod q` of guardian `i` for
+/// interpolating at 0 from the guardians in `guardians`.
+pub fn lagrange_coefficient(
+    fixed_parameters: &FixedParameters,
+    i: GuardianIndex,
+    guardians: &[GuardianIndex],
+) -> BigUint {
+    let q: &BigUint = fixed_parameters.q.borrow();
+    let i = BigUint::from(i.get_one_based_u32());
+
+    guardians
+        .iter()
+        .map(|l| BigUint::from(l.get_one_based_u32()))
+        .filter(|l| *l != i)
+        .fold(BigUint::one(), |w, l| {
+            // `q` is prime, so `x^(q - 2) = x^-1 mod q`.
+            let l_minus_i_inv = ((q + &l - &i) % q).modpow(&(q - 2u8), q);
+            w * l * l_minus_i_inv % q
+        })
+}
+
+/// Combines the decryption shares of `ciphertext` from a quorum of guardians, returning
+/// `T = B / Π_i M_i^w_i mod p`, where `w_i` are the Lagrange coefficients of the guardians
+/// present.
+///
+/// For an encryption of `m`, `T = K^m`, from which `m` can be found by a discrete log. There
+/// must be shares from at least `k` distinct guardians. The shares are not verified here, see
+/// [`verify_decryption_share`].
+pub fn combine_decryption_shares(
+    pvd: &PreVotingData,
+    ciphertext: &Ciphertext,
+    decryption_shares: &[DecryptionShare],
+) -> Result<CombinedDecryption> {
+    let fixed_parameters = &pvd.parameters.fixed_parameters;
+    let varying_parameters = &pvd.parameters.varying_parameters;
+    let p: &BigUint = fixed_parameters.p.borrow();
+
+    let mut seen = BTreeSet::new();
+    for share in decryption_shares {
+        let i = share.i;
+        ensure!(
+            i <= varying_parameters.n,
+            "Decryption share from guardian {i}, but there are only n={} guardians",
+            varying_parameters.n
+        );
+        ensure!(
+            seen.insert(i),
+            "More than one decryption share from guardian {i}"
+        );
+    }
+    ensure!(
+        varying_parameters.k.as_quantity() <= seen.len(),
+        "Decryption requires shares from at least k={} guardians, found {}",
+        varying_parameters.k,
+        seen.len()
+    );
+
+    let guardians: Vec<GuardianIndex> = seen.into_iter().collect();
+
+    let m = decryption_shares.iter().fold(BigUint::one(), |acc, share| {
+        let w_i = lagrange_coefficient(fixed_parameters, share.i, &guardians);
+        (acc * share.m_i.modpow(&w_i, p)) % p
+    });
+
+    // `p` is prime, so `M^(p - 2) = M^-1 mod p`.
+    let m_inv = m.modpow(&(p - 2u8), p);
+
+    #[cfg(feature = "tracing")]
+    tracing::trace!(guardians = guardians.len(), "Combined decryption shares");
+
+    Ok(CombinedDecryption((&ciphertext.beta * m_inv) % p))
+}
+
+/// Checks the assembled key material end-to-end by encrypting zero to the joint election public
+/// key, decrypting it with the key shares of the guardians present, and checking that the
+/// result is zero. The guardians present may be any `k` or more of the `n`.
+///
+/// Every decryption share is also verified against the guardian's public key share, as
+/// computed from `guardian_public_keys`. Running this after the key ceremony catches a broken
+/// joint key or share exchange before any real ballots are encrypted.
+pub fn verify_decryption_end_to_end(
+    csprng: &mut Csprng,
+    pvd: &PreVotingData,
+    guardian_public_keys: &[GuardianPublicKey],
+    guardian_key_shares: &[GuardianKeyShare],
+) -> Result<()> {
+    let fixed_parameters = &pvd.parameters.fixed_parameters;
+
+    let public_key_shares =
+        GuardianPublicKeyShare::compute_all(&pvd.parameters, guardian_public_keys)?;
+
+    let nonce = fixed_parameters.q.random_group_elem(csprng);
+    let ciphertext = pvd
+        .public_key
+        .encrypt_with(fixed_parameters, &nonce, 0, false);
+
+    let mut decryption_shares = Vec::with_capacity(guardian_key_shares.len());
+    for guardian_key_share in guardian_key_shares {
+        let i = guardian_key_share.i;
+        let public_key_share = public_key_shares
+            .iter()
+            .find(|public_key_share| public_key_share.i == i)
+            .with_context(|| format!("Guardian {i} is not one of the guardians"))?;
+
+        let decryption_share =
+            DecryptionShare::compute(csprng, pvd, guardian_key_share, &ciphertext);
+
+        verify_decryption_share(pvd, public_key_share, &ciphertext, &decryption_share)?;
+
+        decryption_shares.push(decryption_share);
+    }
+
+    let t = combine_decryption_shares(pvd, &ciphertext, &decryption_shares)
+        .context("End-to-end decryption check")?;
+
+    ensure!(
+        t.as_biguint().is_one(),
+        "End-to-end decryption check failed: an encryption of zero did not decrypt to zero"
+    );
+
+    Ok(())
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod test {
+    use super::*;
+    use crate::{
+        example_election::ExampleElection, example_election_manifest::example_election_manifest,
+        example_election_parameters::example_election_parameters,
+    };
+    use num_traits::Zero;
+
+    #[test]
+    fn test_verify_decryption_share() {
+        let mut csprng = Csprng::new(b"test_verify_decryption_share");
+
+        let election_parameters = example_election_parameters();
+        let election_manifest = example_election_manifest();
+
+        let ExampleElection {
+            guardian_secret_keys,
+            guardian_public_keys,
+            guardian_key_shares,
+            pvd,
+        } = ExampleElection::generate(&mut csprng, election_parameters, election_manifest).unwrap();
+
+        let public_key_shares =
+            GuardianPublicKeyShare::compute_all(&pvd.parameters, &guardian_public_keys).unwrap();
+
+        let fixed_parameters = &pvd.parameters.fixed_parameters;
+        let nonce = fixed_parameters.q.random_group_elem(&mut csprng);
+        let ciphertext = pvd
+            .public_key
+            .encrypt_with(fixed_parameters, &nonce, 1, false);
+
+        let shares = guardian_key_shares
+            .iter()
+            .map(|key_share| DecryptionShare::compute(&mut csprng, &pvd, key_share, &ciphertext))
+            .collect::<Vec<_>>();
+
+        // Every share verifies against its own guardian's public key share.
+        for (share, public_key_share) in shares.iter().zip(public_key_shares.iter()) {
+            verify_decryption_share(&pvd, public_key_share, &ciphertext, share).unwrap();
+        }
+
+        // Swapping the key shares of guardians 1 and 2 is caught.
+        verify_decryption_share(&pvd, &public_key_shares[1], &ciphertext, &shares[0]).unwrap_err();
+        verify_decryption_share(&pvd, &public_key_shares[0], &ciphertext, &shares[1]).unwrap_err();
+
+        // Even if the substituted key share claims to be guardian 1, the proof does not verify.
+        let mut substituted = public_key_shares[1].clone();
+        substituted.i = public_key_shares[0].i;
+        let err = verify_decryption_share(&pvd, &substituted, &ciphertext, &shares[0]).unwrap_err();
+        assert!(err.to_string().contains("does not verify"));
+
+        // A share made with the guardian's own secret key, rather than its key share, is
+        // caught.
+        let mut own_key_only = shares[0].clone();
+        own_key_only.m_i = ciphertext.alpha.modpow(
+            guardian_secret_keys[0].secret_s(),
+            fixed_parameters.p.as_ref(),
+        );
+        verify_decryption_share(&pvd, &public_key_shares[0], &ciphertext, &own_key_only)
+            .unwrap_err();
+
+        // A share which is zero or not reduced mod p is rejected.
+        let p: &BigUint = fixed_parameters.p.as_ref();
+        for m_i in [BigUint::zero(), p.clone(), &shares[0].m_i + p] {
+            let mut out_of_range = shares[0].clone();
+            out_of_range.m_i = m_i;
+            let err =
+                verify_decryption_share(&pvd, &public_key_shares[0], &ciphertext, &out_of_range)
+                    .unwrap_err();
+            assert!(
+                err.to_string().contains("not a valid nonzero mod p value"),
+                "{err}"
+            );
+        }
+
+        // A share relabeled as coming from guardian 2 does not verify against guardian 2's key
+        // share.
+        let mut relabeled_share = shares[0].clone();
+        relabeled_share.i = public_key_shares[1].i;
+        verify_decryption_share(&pvd, &public_key_shares[1], &ciphertext, &relabeled_share)
+            .unwrap_err();
+    }
+
+    #[test]
+    fn test_combine_decryption_shares() {
+        let mut csprng = Csprng::new(b"test_combine_decryption_shares");
+
+        let election_parameters = example_election_parameters();
+        let election_manifest = example_election_manifest();
+
+        let ExampleElection {
+            guardian_key_shares,
+            pvd,
+            ..
+        } = ExampleElection::generate(&mut csprng, election_parameters, election_manifest).unwrap();
+
+        // The combined shares of an encryption of 1 give K, from any k or more guardians.
+        let fixed_parameters = &pvd.parameters.fixed_parameters;
+        let nonce = fixed_parameters.q.random_group_elem(&mut csprng);
+        let ciphertext = pvd
+            .public_key
+            .encrypt_with(fixed_parameters, &nonce, 1, false);
+        let shares = guardian_key_shares
+            .iter()
+            .map(|key_share| DecryptionShare::compute(&mut csprng, &pvd, key_share, &ciphertext))
+            .collect::<Vec<_>>();
+        let k = &pvd.public_key.joint_election_public_key;
+
+        for present in [
+            &shares[..],
+            &shares[..3],
+            &shares[2..],
+            &[shares[4].clone(), shares[0].clone(), shares[2].clone()][..],
+        ] {
+            assert_eq!(
+                combine_decryption_shares(&pvd, &ciphertext, present)
+                    .unwrap()
+                    .as_biguint(),
+                k
+            );
+        }
+
+        // Fewer than k guardians can't decrypt.
+        let err = combine_decryption_shares(&pvd, &ciphertext, &shares[..2]).unwrap_err();
+        assert!(err.to_string().contains("at least k=3"), "{err}");
+
+        // Nor can k shares from fewer than k distinct guardians.
+        let repeated = [shares[0].clone(), shares[1].clone(), shares[1].clone()];
+        let err = combine_decryption_shares(&pvd, &ciphertext, &repeated).unwrap_err();
+        assert!(err.to_string().contains("More than one"), "{err}");
+
+        // Decryption needs only the published shares, not the key shares which made them.
+        let published = serde_json::to_string(&shares[1..4]).unwrap();
+        drop(guardian_key_shares);
+        let shares: Vec<DecryptionShare> = serde_json::from_str(&published).unwrap();
+        let t = combine_decryption_shares(&pvd, &ciphertext, &shares).unwrap();
+        assert_eq!(t.discrete_log(&pvd, 1), Some(1));
+    }
+
+    #[test]
+    fn test_verify_decryption_end_to_end() {
+        let mut csprng = Csprng::new(b"test_verify_decryption_end_to_end");
+
+        let election_parameters = example_election_parameters();
+        let election_manifest = example_election_manifest();
+
+        let ExampleElection {
+            guardian_public_keys,
+            guardian_key_shares,
+            pvd,
+            ..
+        } = ExampleElection::generate(&mut csprng, election_parameters, election_manifest).unwrap();
+
+        verify_decryption_end_to_end(
+            &mut csprng,
+            &pvd,
+            &guardian_public_keys,
+            &guardian_key_shares,
+        )
+        .unwrap();
+
+        // Any k=3 or more of the n=5 guardians are enough, but no fewer.
+        for present in [
+            &guardian_key_shares[..3],
+            &guardian_key_shares[2..],
+            &[
+                guardian_key_shares[4].clone(),
+                guardian_key_shares[1].clone(),
+                guardian_key_shares[3].clone(),
+            ][..],
+            &guardian_key_shares[1..],
+        ] {
+            verify_decryption_end_to_end(&mut csprng, &pvd, &guardian_public_keys, present)
+                .unwrap();
+        }
+        let err = verify_decryption_end_to_end(
+            &mut csprng,
+            &pvd,
+            &guardian_public_keys,
+            &guardian_key_shares[3..],
+        )
+        .unwrap_err();
+        assert!(format!("{err:#}").contains("at least k=3"), "{err:#}");
+
+        // A joint key which doesn't match the guardians' keys is caught.
+        let fixed_parameters = &pvd.parameters.fixed_parameters;
+        let mut broken_pvd = pvd.clone();
+        broken_pvd.public_key.joint_election_public_key =
+            (&pvd.public_key.joint_election_public_key * &fixed_parameters.g)
+                % fixed_parameters.p.as_ref();
+        let err = verify_decryption_end_to_end(
+            &mut csprng,
+            &broken_pvd,
+            &guardian_public_keys,
+            &guardian_key_shares,
+        )
+        .unwrap_err();
+        assert!(err.to_string().contains("did not decrypt to zero"));
+    }
+
+    #[cfg(feature = "debug-transcript")]
+    #[test]
+    fn test_verify_decryption_share_with_transcript() {
+        let mut csprng = Csprng::new(b"test_verify_decryption_share_with_transcript");
+
+        let ExampleElection {
+            guardian_public_keys,
+            guardian_key_shares,
+            pvd,
+            ..
+        } = ExampleElection::with_first_contests(&mut csprng, 1).unwrap();
+
+        let public_key_share = GuardianPublicKeyShare::compute(
+            &pvd.parameters,
+            &guardian_public_keys,
+            guardian_key_shares[0].i,
+        )
+        .unwrap();
+
+        let fixed_parameters = &pvd.parameters.fixed_parameters;
+        let nonce = fixed_parameters.q.random_group_elem(&mut csprng);
+        let ciphertext = pvd
+            .public_key
+            .encrypt_with(fixed_parameters, &nonce, 1, false);
+        let share =
+            DecryptionShare::compute(&mut csprng, &pvd, &guardian_key_shares[0], &ciphertext);
+
+        let mut transcript = TranscriptSink::new();
+        verify_decryption_share_with_transcript(
+            &pvd,
+            &public_key_share,
+            &ciphertext,
+            &share,
+            &mut transcript,
+        )
+        .unwrap();
+
+        let labels: Vec<&str> = transcript
+            .entries
+            .iter()
+            .map(|entry| entry.label.as_str())
+            .collect();
+        assert_eq!(
+            labels,
+            [
+                "domain",
+                "H_E",
+                "i",
+                "K_i",
+                "alpha",
+                "beta",
+                "M_i",
+                "c",
+                "v",
+                "a",
+                "b",
+                "c_computed"
+            ]
+        );
+        assert_eq!(transcript.entries[0].value, "30");
+        assert_eq!(transcript.entries[2].value, "00000001");
+        assert_eq!(transcript.entries[7].value, transcript.entries[11].value);
+
+        // A share which doesn't verify records the challenge which it should have had.
+        let mut share_bad = share.clone();
+        share_bad.proof.v = fixed_parameters
+            .q
+            .add_group_elem(&share.proof.v, &BigUint::one());
+        let mut transcript = TranscriptSink::new();
+        assert!(verify_decryption_share_with_transcript(
+            &pvd,
+            &public_key_share,
+            &ciphertext,
+            &share_bad,
+            &mut transcript,
+        )
+        .is_err());
+        assert_ne!(transcript.entries[7].value, transcript.entries[11].value);
+    }
+}
@@ -8,6 +8,7 @@
 use std::collections::HashMap;
 
 use num_bigint::BigUint;
+use num_traits::One;
 
 pub struct DiscreteLog(pub HashMap<BigUint, u32>);
 
@@ -47,7 +48,49 @@ impl DiscreteLog {
     }
 }
 
+/// Finds `x` such that `base^x = y mod modulus` and `0 <= x <= bound`.
+/// Returns `None` if there is no such `x`.
+///
+/// Uses a Baby-step giant-step algorithm with a table sized to the bound, so the work is
+/// proportional to `sqrt(bound)`. Callers should pass the tightest bound they know.
+///
+/// This is not constant time: it returns as soon as a match is found. So `y` must be a public
+/// value. To decrypt, use
+/// [`CombinedDecryption::discrete_log`](crate::decryption_share::CombinedDecryption::discrete_log).
+pub(crate) fn discrete_log_bounded(
+    base: &BigUint,
+    modulus: &BigUint,
+    y: &BigUint,
+    bound: u64,
+) -> Option<u64> {
+    // Smallest `m` such that `m * m > bound`.
+    let m = bound.isqrt() + 1;
+
+    // Baby steps: `y * base^j` for `0 <= j <= m`. Later entries overwrite earlier ones, so
+    // each maps to the largest `j`, which gives the smallest `x` below.
+    let mut table = HashMap::new();
+    let mut baby = y % modulus;
+    for j in 0..=m {
+        table.insert(baby.clone(), j);
+        baby = (baby * base) % modulus;
+    }
+
+    // Giant steps: `base^(i * m)` for `1 <= i <= m`. A match means `x = i * m - j`.
+    // This avoids computing the inverse of `base`, which is expensive for a large modulus.
+    let base_to_m = base.modpow(&BigUint::from(m), modulus);
+    let mut giant = BigUint::one();
+    for i in 1..=m {
+        giant = (giant * &base_to_m) % modulus;
+        if let Some(j) = table.get(&giant) {
+            let x = i * m - j;
+            return (x <= bound).then_some(x);
+        }
+    }
+    None
+}
+
 #[cfg(test)]
+#[allow(clippy::unwrap_used)]
 mod test {
     use std::borrow::Borrow;
 
@@ -85,4 +128,22 @@ mod test {
             );
         }
     }
+
+    #[test]
+    fn test_dlog_bounded() {
+        let mut csprng = Csprng::new(b"test_dlog_bounded");
+        let fixed_parameters = &STANDARD_PARAMETERS;
+        let p = fixed_parameters.p.as_ref();
+        let h = csprng.next_biguint_lt(fixed_parameters.p.borrow());
+
+        for bound in [0u64, 1, 2, 15, 16, 17, 1000] {
+            for x in [0, bound / 2, bound] {
+                let y = h.modpow(&BigUint::from(x), p);
+                assert_eq!(discrete_log_bounded(&h, p, &y, bound), Some(x));
+            }
+
+            let y = h.modpow(&BigUint::from(bound + 1), p);
+            assert_eq!(discrete_log_bounded(&h, p, &y, bound), None);
+        }
+    }
 }
@@ -5,17 +5,30 @@
 #![deny(clippy::panic)]
 #![deny(clippy::manual_assert)]
 
-use std::io::Cursor;
+use std::collections::BTreeSet;
+use std::io::{Cursor, Write};
 
-use anyhow::{Context, Result};
+use anyhow::{ensure, Context, Result};
 use serde::{Deserialize, Serialize};
 
+use crate::artifact_format::strip_utf8_bom;
 use crate::ballot_style::BallotStyle;
+use crate::hash::{EgHWriter, HValue};
 use crate::index::Index;
 use crate::vec1::Vec1;
 
+/// The default maximum number of options a [`Contest`] may have.
+///
+/// Every option gets a ciphertext and a range proof on every ballot, so this bounds the
+/// work an oversized manifest can cause during encryption and verification.
+pub const MAX_OPTIONS_PER_CONTEST: usize = 1000;
+
+/// The election manifest hash `H_M`.
+pub type ManifestHash = HValue;
+
 /// The election manifest.
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "strict-parsing", serde(deny_unknown_fields))]
 pub struct ElectionManifest {
     /// A descriptive label for this election.
     pub label: String,
@@ -30,6 +43,10 @@ pub struct ElectionManifest {
     //
     /// All the [`BallotStyle`]s of the election.
     pub ballot_styles: Vec1<BallotStyle>,
+
+    /// The [`GeopoliticalUnit`]s which contests may refer to as their electoral district.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub geopolitical_units: Vec<GeopoliticalUnit>,
 }
 
 impl ElectionManifest {
@@ -43,13 +60,179 @@ impl ElectionManifest {
         Ok(self_)
     }
 
+    /// Reads an [`ElectionManifest`] from a byte sequence and validates it.
+    /// It can be either the canonical or pretty JSON representation, and may start with a
+    /// UTF-8 byte order mark.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        let self_: Self = serde_json::from_slice(strip_utf8_bom(bytes))
+            .context("Error parsing ElectionManifest bytes")?;
+
+        self_.validate()?;
+
+        Ok(self_)
+    }
+
     /// Validates that the [`ElectionManifest`] is well-formed.
     /// Useful after deserialization.
     pub fn validate(&self) -> Result<()> {
-        // We currently have no validation rules for this type.
+        self.validate_with_max_options_per_contest(MAX_OPTIONS_PER_CONTEST)
+    }
+
+    /// Same as [`ElectionManifest::validate`], but with a specified maximum number of options
+    /// per contest instead of [`MAX_OPTIONS_PER_CONTEST`].
+    pub fn validate_with_max_options_per_contest(
+        &self,
+        max_options_per_contest: usize,
+    ) -> Result<()> {
+        validate_label("Election manifest", &self.label)?;
+
+        ensure!(
+            !self.contests.is_empty(),
+            "Election manifest has no contests"
+        );
+
+        let mut geopolitical_unit_ids = BTreeSet::new();
+        for geopolitical_unit in &self.geopolitical_units {
+            let id = &geopolitical_unit.id;
+            ensure!(
+                !id.trim().is_empty(),
+                "Geopolitical unit {:?} has a blank id",
+                geopolitical_unit.label
+            );
+            validate_label(
+                &format!("Geopolitical unit {id:?}"),
+                &geopolitical_unit.label,
+            )?;
+            ensure!(
+                geopolitical_unit_ids.insert(id.as_str()),
+                "More than one geopolitical unit has the id {id:?}"
+            );
+        }
+
+        for contest_ix in self.contests.indices() {
+            if let Some(contest) = self.contests.get(contest_ix) {
+                contest.validate(contest_ix, max_options_per_contest)?;
+
+                if let Some(district_id) = &contest.opt_electoral_district_id {
+                    ensure!(
+                        geopolitical_unit_ids.contains(district_id.as_str()),
+                        "Contest {contest_ix} {:?} refers to electoral district {district_id:?}, which is not a geopolitical unit of the election",
+                        contest.label
+                    );
+                }
+            }
+        }
+
+        for ballot_style_ix in self.ballot_styles.indices() {
+            let Some(ballot_style) = self.ballot_styles.get(ballot_style_ix) else {
+                continue;
+            };
+
+            validate_label(
+                &format!("Ballot style {ballot_style_ix}"),
+                &ballot_style.label,
+            )?;
+
+            for &contest_ix in &ballot_style.contests {
+                ensure!(
+                    self.contests.get(contest_ix).is_some(),
+                    "Ballot style {ballot_style_ix} {:?} refers to contest {contest_ix}, which does not exist",
+                    ballot_style.label
+                );
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Returns the index of the contest with the specified label.
+    ///
+    /// Labels are compared exactly. It is an error if no contest, or more than one contest,
+    /// has the label.
+    pub fn contest_ix_by_label(&self, label: &str) -> Result<ContestIndex> {
+        let mut matching = self.contests.indices().filter(|&contest_ix| {
+            self.contests
+                .get(contest_ix)
+                .is_some_and(|contest| contest.label == label)
+        });
+
+        let contest_ix = matching
+            .next()
+            .with_context(|| format!("No contest has the label {label:?}"))?;
+        ensure!(
+            matching.next().is_none(),
+            "More than one contest has the label {label:?}"
+        );
+
+        Ok(contest_ix)
+    }
+
+    /// Returns the number of ciphertexts in each encrypted ballot, i.e., the total over every
+    /// contest of its options and placeholders. See [`Contest::ballot_slots`].
+    pub fn total_ballot_slots(&self) -> usize {
+        self.contests
+            .indices()
+            .filter_map(|contest_ix| self.contests.get(contest_ix))
+            .map(|contest| contest.options.len() + contest.placeholder_count())
+            .sum()
+    }
+
+    /// Summarizes the size of each contest, as a sanity check of the scale of the manifest.
+    pub fn stats(&self) -> ManifestStats {
+        let contests = self
+            .contests
+            .indices()
+            .filter_map(|contest_ix| {
+                let contest = self.contests.get(contest_ix)?;
+                Some(ContestStats {
+                    contest_ix,
+                    label: contest.label.clone(),
+                    cnt_options: contest.options.len(),
+                    selection_limit: contest.selection_limit,
+                    cnt_placeholders: contest.placeholder_count(),
+                    cnt_ballot_slots: contest.ballot_slots().count(),
+                })
+            })
+            .collect();
+
+        ManifestStats {
+            contests,
+            cnt_ballot_styles: self.ballot_styles.len(),
+        }
+    }
+
+    /// Verifies that every contest's range proof can be constructed, as by
+    /// [`Contest::check_range_proof_feasible`], so that a misconfigured contest is reported
+    /// before any ballot is encrypted.
+    pub fn check_range_proofs_feasible(&self) -> Result<()> {
+        for contest_ix in self.contests.indices() {
+            if let Some(contest) = self.contests.get(contest_ix) {
+                contest.check_range_proof_feasible(contest_ix)?;
+            }
+        }
         Ok(())
     }
 
+    /// Returns a copy of the manifest with the presentation metadata which does not contribute
+    /// to the manifest hash, such as [`Contest::kind`] and [`ContestOption::color`], reset to
+    /// the defaults. Labels, the order of contests and options, and selection limits are kept,
+    /// so the redacted manifest has the same [`hash`](Self::hash) and may be posted in its place.
+    pub fn redacted(&self) -> ElectionManifest {
+        let mut election_manifest = self.clone();
+        for contest_ix in self.contests.indices() {
+            if let Some(contest) = election_manifest.contests.get_mut(contest_ix) {
+                contest.kind = ContestKind::default();
+                for option_ix in contest.options.indices() {
+                    if let Some(option) = contest.options.get_mut(option_ix) {
+                        option.color = None;
+                        option.image_uri = None;
+                    }
+                }
+            }
+        }
+        election_manifest
+    }
+
     /// Writes an [`ElectionManifest`] to a [`std::io::Write`] as canonical bytes.
     /// This uses a more compact JSON format.
     pub fn to_stdiowrite_canonical(&self, stdiowrite: &mut dyn std::io::Write) -> Result<()> {
@@ -65,6 +248,43 @@ impl ElectionManifest {
         Ok(buf.into_inner())
     }
 
+    /// Computes the election manifest hash `H_M` from the parameter base hash `H_P`.
+    ///
+    /// H_M = H(H_P; 01, manifest)
+    ///
+    /// Presentation metadata such as [`Contest::kind`] and [`ContestOption::color`] is omitted
+    /// from the hashed manifest.
+    ///
+    /// The hashed bytes are the canonical bytes of the [`redacted`](Self::redacted) manifest,
+    /// but they are serialized from a borrowed view of `self` and streamed into the hash
+    /// function, so neither a redacted copy nor the serialized bytes are held in memory.
+    pub fn hash(&self, h_p: &HValue) -> Result<ManifestHash> {
+        let mut eg_h_writer = EgHWriter::new(h_p);
+
+        eg_h_writer
+            .write_all(&[0x01])
+            .context("Hashing ElectionManifest")?;
+        serde_json::ser::to_writer(&mut eg_h_writer, &HashedManifest::from(self))
+            .context("Hashing ElectionManifest")?;
+
+        Ok(eg_h_writer.finalize())
+    }
+
+    /// Verifies that the manifest hash `H_M` computed from the parameter base hash `H_P` is
+    /// the `expected` one, e.g. as announced by the election. The hashes are compared in
+    /// constant time.
+    ///
+    /// A mismatch means the manifest is not the one the hash was computed from, e.g. it was
+    /// altered in transit.
+    pub fn verify_hash(&self, h_p: &HValue, expected: &ManifestHash) -> Result<()> {
+        let h_m = self.hash(h_p)?;
+        ensure!(
+            h_m.ct_eq(expected),
+            "Election manifest hash {h_m} does not match the expected {expected}"
+        );
+        Ok(())
+    }
+
     /// Writes an [`ElectionManifest`] to a [`std::io::Write`] as pretty JSON.
     pub fn to_stdiowrite_pretty(&self, stdiowrite: &mut dyn std::io::Write) -> Result<()> {
         let mut ser = serde_json::Serializer::pretty(stdiowrite);
@@ -76,28 +296,490 @@ impl ElectionManifest {
     }
 }
 
+/// Checks that a label is not blank and has no control characters other than line breaks.
+///
+/// Labels may span several lines, e.g. a candidate's name followed by their party.
+pub(crate) fn validate_label(what: &str, label: &str) -> Result<()> {
+    ensure!(!label.trim().is_empty(), "{what} has a blank label");
+    ensure!(
+        !label.chars().any(|c| c.is_control() && c != '\n'),
+        "{what} label {label:?} contains a control character"
+    );
+    Ok(())
+}
+
+/// The size of each contest of an [`ElectionManifest`], as returned by
+/// [`ElectionManifest::stats`]. Displays as a table with a row per contest.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ManifestStats {
+    /// One entry per contest, in the order defined in the manifest.
+    pub contests: Vec<ContestStats>,
+
+    /// The number of ballot styles.
+    pub cnt_ballot_styles: usize,
+}
+
+/// The size of a single [`Contest`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ContestStats {
+    pub contest_ix: ContestIndex,
+    pub label: String,
+    pub cnt_options: usize,
+    pub selection_limit: usize,
+    pub cnt_placeholders: usize,
+
+    /// The number of ciphertexts the contest contributes to each encrypted ballot.
+    /// See [`Contest::ballot_slots`].
+    pub cnt_ballot_slots: usize,
+}
+
+impl ManifestStats {
+    /// The total number of options over every contest.
+    pub fn total_options(&self) -> usize {
+        self.contests.iter().map(|c| c.cnt_options).sum()
+    }
+
+    /// The total number of ciphertexts in each encrypted ballot.
+    pub fn total_ballot_slots(&self) -> usize {
+        self.contests.iter().map(|c| c.cnt_ballot_slots).sum()
+    }
+}
+
+impl std::fmt::Display for ManifestStats {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(
+            f,
+            "{:>7} {:>7} {:>5} {:>12} {:>5}  label",
+            "contest", "options", "limit", "placeholders", "slots"
+        )?;
+        for c in &self.contests {
+            writeln!(
+                f,
+                "{:>7} {:>7} {:>5} {:>12} {:>5}  {:?}",
+                c.contest_ix.get_one_based_u32(),
+                c.cnt_options,
+                c.selection_limit,
+                c.cnt_placeholders,
+                c.cnt_ballot_slots,
+                c.label
+            )?;
+        }
+        write!(
+            f,
+            "{:>7} {:>7} {:>5} {:>12} {:>5}  ({} ballot styles)",
+            "total",
+            self.total_options(),
+            "",
+            self.contests
+                .iter()
+                .map(|c| c.cnt_placeholders)
+                .sum::<usize>(),
+            self.total_ballot_slots(),
+            self.cnt_ballot_styles
+        )
+    }
+}
+
+/// A borrowed view of an [`ElectionManifest`] which serializes exactly as its
+/// [`redacted`](ElectionManifest::redacted) form does. The field order and `serde` attributes
+/// must be kept in step with [`ElectionManifest`], [`Contest`], and [`ContestOption`].
+#[derive(Serialize)]
+struct HashedManifest<'a> {
+    label: &'a str,
+    #[serde(serialize_with = "serialize_hashed_contests")]
+    contests: &'a Vec1<Contest>,
+    ballot_styles: &'a Vec1<BallotStyle>,
+    #[serde(skip_serializing_if = "<[GeopoliticalUnit]>::is_empty")]
+    geopolitical_units: &'a [GeopoliticalUnit],
+}
+
+impl<'a> From<&'a ElectionManifest> for HashedManifest<'a> {
+    fn from(election_manifest: &'a ElectionManifest) -> Self {
+        HashedManifest {
+            label: &election_manifest.label,
+            contests: &election_manifest.contests,
+            ballot_styles: &election_manifest.ballot_styles,
+            geopolitical_units: &election_manifest.geopolitical_units,
+        }
+    }
+}
+
+/// A [`Contest`] without its [`kind`](Contest::kind).
+#[derive(Serialize)]
+struct HashedContest<'a> {
+    label: &'a str,
+    selection_limit: usize,
+    #[serde(skip_serializing_if = "VoteVariation::is_n_of_m")]
+    vote_variation: VoteVariation,
+    #[serde(serialize_with = "serialize_hashed_options")]
+    options: &'a Vec1<ContestOption>,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    track_blank: bool,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    track_undervotes: bool,
+    #[serde(
+        rename = "electoral_district_id",
+        skip_serializing_if = "Option::is_none"
+    )]
+    opt_electoral_district_id: Option<&'a String>,
+}
+
+impl<'a> From<&'a Contest> for HashedContest<'a> {
+    fn from(contest: &'a Contest) -> Self {
+        HashedContest {
+            label: &contest.label,
+            selection_limit: contest.selection_limit,
+            vote_variation: contest.vote_variation,
+            options: &contest.options,
+            track_blank: contest.track_blank,
+            track_undervotes: contest.track_undervotes,
+            opt_electoral_district_id: contest.opt_electoral_district_id.as_ref(),
+        }
+    }
+}
+
+/// A [`ContestOption`] without its [`color`](ContestOption::color) and
+/// [`image_uri`](ContestOption::image_uri).
+#[derive(Serialize)]
+struct HashedContestOption<'a> {
+    label: &'a str,
+}
+
+fn serialize_hashed_contests<S: serde::Serializer>(
+    contests: &&Vec1<Contest>,
+    serializer: S,
+) -> std::result::Result<S::Ok, S::Error> {
+    serializer.collect_seq(
+        contests
+            .indices()
+            .filter_map(|contest_ix| contests.get(contest_ix))
+            .map(HashedContest::from),
+    )
+}
+
+fn serialize_hashed_options<S: serde::Serializer>(
+    options: &&Vec1<ContestOption>,
+    serializer: S,
+) -> std::result::Result<S::Ok, S::Error> {
+    serializer.collect_seq(
+        options
+            .indices()
+            .filter_map(|option_ix| options.get(option_ix))
+            .map(|option| HashedContestOption {
+                label: &option.label,
+            }),
+    )
+}
+
+/// A geopolitical unit, such as a county, city, or precinct, in which contests are held.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "strict-parsing", serde(deny_unknown_fields))]
+pub struct GeopoliticalUnit {
+    /// Identifies the unit within the manifest, e.g. `"county-12"`.
+    pub id: String,
+
+    /// The label for this `GeopoliticalUnit`.
+    pub label: String,
+}
+
+/// Returns true iff `s` is a `#` followed by exactly six hex digits.
+fn is_rrggbb(s: &str) -> bool {
+    s.strip_prefix('#')
+        .is_some_and(|hex| hex.len() == 6 && hex.chars().all(|c| c.is_ascii_hexdigit()))
+}
+
 /// A contest.
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "strict-parsing", serde(deny_unknown_fields))]
 pub struct Contest {
     /// The label for this `Contest`.
     pub label: String,
 
     /// The maximum count of [`ContestOption`]s that a voter may select.
+    /// It must be consistent with [`Contest::vote_variation`].
     pub selection_limit: usize, //? TODO NonZeroU32,
 
+    /// How voters may mark their selections.
+    #[serde(default, skip_serializing_if = "VoteVariation::is_n_of_m")]
+    pub vote_variation: VoteVariation,
+
     /// The candidates/options.
     /// The order of options matches the virtual ballot.
     pub options: Vec1<ContestOption>,
+
+    /// The kind of question this contest asks.
+    /// This is only used to present results, and does not affect the manifest hash.
+    #[serde(default, skip_serializing_if = "ContestKind::is_candidate")]
+    pub kind: ContestKind,
+
+    /// Whether ballots carry an encrypted indicator of leaving this contest blank, so that
+    /// the number of blank ballots can be tallied and decrypted.
+    /// Unlike [`Contest::kind`], this affects the manifest hash when set.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub track_blank: bool,
+
+    /// Whether ballots carry an encryption of the contest's undervotes, the number of
+    /// selections the voter could have made but didn't, so that their total can be tallied
+    /// and decrypted. Like [`Contest::track_blank`], this affects the manifest hash when set.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub track_undervotes: bool,
+
+    /// The id of the [`GeopoliticalUnit`] in which this contest is held. Only voters in that
+    /// district should be given ballot styles which include the contest.
+    #[serde(
+        rename = "electoral_district_id",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub opt_electoral_district_id: Option<String>,
+}
+
+/// The kind of question a [`Contest`] asks.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ContestKind {
+    /// An election between candidates.
+    #[default]
+    Candidate,
+
+    /// A yes/no question on a measure. The first option is in favor.
+    Referendum,
+
+    /// Whether to retain an official, e.g. a judge. The first option is to retain.
+    Retention,
+
+    /// A yes/no question on an amendment. The first option is in favor.
+    Amendment,
+}
+
+/// How voters may mark their selections in a [`Contest`].
+///
+/// Every variation allows at most one vote per option. Ranked and score voting, which need
+/// more, can't be encoded on a ballot.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum VoteVariation {
+    /// Select exactly one option. The selection limit must be 1.
+    OneOfM,
+
+    /// Select up to the selection limit of the options.
+    #[default]
+    NOfM,
+
+    /// Select any number of the options. The selection limit must be the number of options.
+    Approval,
+}
+
+impl VoteVariation {
+    pub fn is_n_of_m(&self) -> bool {
+        *self == VoteVariation::NOfM
+    }
+
+    /// Checks that `selection_limit` means something for this variation, in a contest with
+    /// `cnt_options` options.
+    ///
+    /// The limit is assumed to already be in the range 1 to `cnt_options`.
+    pub fn validate_selection_limit(
+        &self,
+        selection_limit: usize,
+        cnt_options: usize,
+    ) -> Result<()> {
+        match self {
+            VoteVariation::OneOfM => ensure!(
+                selection_limit == 1,
+                "A one-of-m contest must have a selection limit of 1, not {selection_limit}"
+            ),
+            VoteVariation::NOfM => {}
+            VoteVariation::Approval => ensure!(
+                selection_limit == cnt_options,
+                "An approval contest must have a selection limit of {cnt_options}, not {selection_limit}"
+            ),
+        }
+        Ok(())
+    }
+}
+
+impl ContestKind {
+    pub fn is_candidate(&self) -> bool {
+        *self == ContestKind::Candidate
+    }
+
+    /// Returns a description of the outcome of a yes/no contest of this kind, given the
+    /// totals of its options, or `None` if this kind has no such outcome.
+    ///
+    /// The first option is taken to be "yes" and the second "no". A tie is not a "yes".
+    pub fn outcome(&self, option_totals: &[u64]) -> Option<&'static str> {
+        let (&yes, &no) = (option_totals.first()?, option_totals.get(1)?);
+        let (passed, failed) = match self {
+            ContestKind::Candidate => return None,
+            ContestKind::Referendum | ContestKind::Amendment => ("PASSED", "FAILED"),
+            ContestKind::Retention => ("RETAINED", "REMOVED"),
+        };
+        Some(if yes > no { passed } else { failed })
+    }
+}
+
+impl Contest {
+    /// Validates that the contest, at index `contest_ix` in its manifest, is well-formed.
+    pub(crate) fn validate(
+        &self,
+        contest_ix: ContestIndex,
+        max_options_per_contest: usize,
+    ) -> Result<()> {
+        validate_label(&format!("Contest {contest_ix}"), &self.label)?;
+
+        ensure!(
+            !self.options.is_empty(),
+            "Contest {contest_ix} {:?} has no options",
+            self.label
+        );
+
+        ensure!(
+            self.options.len() <= max_options_per_contest,
+            "Contest {contest_ix} {:?} has {} options, which exceeds the maximum of {max_options_per_contest}",
+            self.label,
+            self.options.len()
+        );
+
+        ensure!(
+            1 <= self.selection_limit && self.selection_limit <= self.options.len(),
+            "Contest {contest_ix} {:?} has a selection limit of {}, which is not in the range 1 to its {} options",
+            self.label,
+            self.selection_limit,
+            self.options.len()
+        );
+
+        self.vote_variation
+            .validate_selection_limit(self.selection_limit, self.options.len())
+            .with_context(|| format!("Contest {contest_ix} {:?}", self.label))?;
+
+        for option_ix in self.options.indices() {
+            let Some(option) = self.options.get(option_ix) else {
+                continue;
+            };
+
+            validate_label(
+                &format!("Contest {contest_ix} option {option_ix}"),
+                &option.label,
+            )?;
+
+            if let Some(color) = &option.color {
+                ensure!(
+                    is_rrggbb(color),
+                    "Contest {contest_ix} option {option_ix} color {color:?} is not of the form #RRGGBB"
+                );
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Returns the number of placeholder selections appended to this contest on the ballot.
+    ///
+    /// The 2.0 spec proves the selection limit with a range proof over the sum of the option
+    /// ciphertexts, so no placeholder selections are needed and this is always zero.
+    pub fn placeholder_count(&self) -> usize {
+        0
+    }
+
+    /// Verifies that the range proof of the contest's selection limit can be constructed, i.e.,
+    /// that the limit can be reached with the contest's options and placeholders.
+    ///
+    /// [`Contest::validate`] implies this, but encryption checks it as well, as it may be given
+    /// a manifest which was never validated.
+    pub fn check_range_proof_feasible(&self, contest_ix: ContestIndex) -> Result<()> {
+        let cnt_slots = self.options.len() + self.placeholder_count();
+        ensure!(
+            1 <= self.selection_limit && self.selection_limit <= cnt_slots,
+            "Contest {contest_ix} {:?} has a selection limit of {}, but its {} options and {} placeholders can't be selected that many times",
+            self.label,
+            self.selection_limit,
+            self.options.len(),
+            self.placeholder_count()
+        );
+        Ok(())
+    }
+
+    /// Returns the option at the 1-based `sequence_order`, i.e., its position in the contest
+    /// as given by its [`ContestOptionIndex`], or `None` if there is no such option.
+    ///
+    /// An option is identified by its position rather than its label, so two options may have
+    /// the same label and still be distinct.
+    pub fn option_by_sequence(&self, sequence_order: u32) -> Option<&ContestOption> {
+        let option_ix = ContestOptionIndex::from_one_based_index_const(sequence_order)?;
+        self.options.get(option_ix)
+    }
+
+    /// Returns the index of the option with the specified label.
+    ///
+    /// Labels are compared exactly, including any line breaks. It is an error if no option,
+    /// or more than one option, has the label.
+    pub fn option_ix_by_label(&self, label: &str) -> Result<ContestOptionIndex> {
+        let mut matching = self.options.indices().filter(|&option_ix| {
+            self.options
+                .get(option_ix)
+                .is_some_and(|option| option.label == label)
+        });
+
+        let option_ix = matching.next().with_context(|| {
+            format!(
+                "Contest {:?} has no option with the label {label:?}",
+                self.label
+            )
+        })?;
+        ensure!(
+            matching.next().is_none(),
+            "Contest {:?} has more than one option with the label {label:?}",
+            self.label
+        );
+
+        Ok(option_ix)
+    }
+
+    /// Returns an iterator over every ciphertext slot of this contest in canonical order: first
+    /// each [`ContestOption`] in the order defined, then each placeholder.
+    ///
+    /// Encryption, tallying, and verification should all use this ordering.
+    pub fn ballot_slots(&self) -> impl Iterator<Item = SlotKind> {
+        self.options
+            .indices()
+            .map(SlotKind::Option)
+            .chain((1..=self.placeholder_count()).map(SlotKind::Placeholder))
+    }
 }
 
 /// A 1-based index of a [`Contest`] in the order it is defined in the [`ElectionManifest`].
 pub type ContestIndex = Index<Contest>;
 
+/// The kind of a ciphertext slot in an encrypted contest, as returned by
+/// [`Contest::ballot_slots`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SlotKind {
+    /// The slot for a [`ContestOption`].
+    Option(ContestOptionIndex),
+
+    /// A placeholder slot. The value is 1-based.
+    Placeholder(usize),
+}
+
 /// An option in a contest.
+///
+/// Within its contest, an option is identified by its [`ContestOptionIndex`], so labels need
+/// not be unique.
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "strict-parsing", serde(deny_unknown_fields))]
 pub struct ContestOption {
     /// The label for this `ContestOption`.
     pub label: String,
+
+    /// A color with which to show this option, e.g. the candidate's party color, as `#RRGGBB`.
+    /// This is only used to present the option, and does not affect the manifest hash.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub color: Option<String>,
+
+    /// A reference to an image for this option, e.g. a candidate photo or party logo.
+    /// This is only used to present the option, and does not affect the manifest hash.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub image_uri: Option<String>,
     /*
     /// The maximum count of votes that a voter can apply to this option.
     /// In the traditional election style, will use `Some(1)` to indicate that a voter may select the option 0 or 1 times.
@@ -121,7 +803,11 @@ pub type ContestOptionIndex = Index<ContestOption>;
 #[allow(clippy::unwrap_used)]
 pub mod test {
     use super::*;
-    use crate::example_election_manifest::example_election_manifest;
+    use crate::{
+        election_manifest_builder::ElectionManifestBuilder,
+        example_election_manifest::example_election_manifest,
+    };
+    use hex_literal::hex;
 
     #[test]
     fn test_election_manifest() -> Result<()> {
@@ -145,11 +831,637 @@ pub mod test {
             assert_ne!(canonical_bytes[canonical_bytes.len() - 1], 0x00);
 
             let election_manifest_from_canonical_bytes =
-                ElectionManifest::from_stdioread_validated(&mut Cursor::new(canonical_bytes))?;
+                ElectionManifest::from_stdioread_validated(&mut Cursor::new(&canonical_bytes))?;
 
             assert_eq!(election_manifest, election_manifest_from_canonical_bytes);
+
+            let election_manifest_from_bytes = ElectionManifest::from_bytes(&canonical_bytes)?;
+            assert_eq!(election_manifest, election_manifest_from_bytes);
         }
 
         Ok(())
     }
+
+    #[test]
+    fn test_hash_streams_canonical_bytes() -> Result<()> {
+        use crate::hash::eg_h;
+
+        let election_manifest = example_election_manifest();
+
+        let mut streamed = Vec::new();
+        election_manifest.to_stdiowrite_canonical(&mut streamed)?;
+        assert_eq!(streamed, election_manifest.to_canonical_bytes()?);
+
+        // Same result as hashing the fully-buffered bytes.
+        let h_p = HValue::from([0xA5; 32]);
+        let mut v = vec![0x01];
+        v.append(&mut election_manifest.redacted().to_canonical_bytes()?);
+        assert_eq!(election_manifest.hash(&h_p)?, eg_h(&h_p, &v));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_unknown_field() -> Result<()> {
+        let mut election_manifest = example_election_manifest();
+        election_manifest.geopolitical_units.push(GeopoliticalUnit {
+            id: "county-1".into(),
+            label: "County 1".into(),
+        });
+
+        let json = String::from_utf8(election_manifest.to_canonical_bytes()?).unwrap();
+        let misspelled = json.replacen(
+            "\"selection_limit\":",
+            "\"selecton_limit\":0,\"selection_limit\":",
+            1,
+        );
+        assert_ne!(misspelled, json);
+
+        let result = ElectionManifest::from_stdioread_validated(&mut misspelled.as_bytes());
+        if cfg!(feature = "strict-parsing") {
+            let err = format!("{:#}", result.unwrap_err());
+            assert!(err.contains("selecton_limit"), "{err}");
+        } else {
+            assert_eq!(result?, election_manifest);
+        }
+
+        // A bogus field in each of the other kinds of object in a manifest.
+        let value: serde_json::Value = serde_json::from_str(&json)?;
+        for pointer in [
+            "",
+            "/contests/0/options/0",
+            "/ballot_styles/0",
+            "/geopolitical_units/0",
+        ] {
+            let mut value = value.clone();
+            value
+                .pointer_mut(pointer)
+                .and_then(serde_json::Value::as_object_mut)
+                .unwrap()
+                .insert("bogus".into(), 1.into());
+            let bogus = serde_json::to_vec(&value)?;
+
+            let result = ElectionManifest::from_stdioread_validated(&mut bogus.as_slice());
+            if cfg!(feature = "strict-parsing") {
+                let err = format!("{:#}", result.unwrap_err());
+                assert!(err.contains("unknown field `bogus`"), "{pointer}: {err}");
+            } else {
+                assert_eq!(result?, election_manifest, "{pointer}");
+            }
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_max_options_per_contest() {
+        let mut election_manifest = example_election_manifest();
+        election_manifest.validate().unwrap();
+
+        // The contest with the most options.
+        let contest_ix = election_manifest
+            .contests
+            .indices()
+            .max_by_key(|&ix| election_manifest.contests.get(ix).unwrap().options.len())
+            .unwrap();
+        let contest = election_manifest.contests.get_mut(contest_ix).unwrap();
+        let cnt_options = contest.options.len();
+        let label = contest.label.clone();
+
+        election_manifest
+            .validate_with_max_options_per_contest(cnt_options)
+            .unwrap();
+
+        let err = election_manifest
+            .validate_with_max_options_per_contest(cnt_options - 1)
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains(&label), "{err}");
+
+        let contest = election_manifest.contests.get_mut(contest_ix).unwrap();
+        while contest.options.len() <= MAX_OPTIONS_PER_CONTEST {
+            contest
+                .options
+                .try_push(ContestOption {
+                    label: "Write-in".into(),
+                    color: None,
+                    image_uri: None,
+                })
+                .unwrap();
+        }
+        assert!(election_manifest.validate().is_err());
+    }
+
+    #[test]
+    fn test_example_manifest_validates() -> Result<()> {
+        let election_manifest = example_election_manifest();
+        election_manifest.validate()?;
+
+        // The canonical bytes, and so the hash, don't depend on anything but the manifest.
+        let canonical_bytes = election_manifest.to_canonical_bytes()?;
+        assert_eq!(
+            example_election_manifest().to_canonical_bytes()?,
+            canonical_bytes
+        );
+        assert_eq!(
+            ElectionManifest::from_bytes(&canonical_bytes)?.to_canonical_bytes()?,
+            canonical_bytes
+        );
+
+        let h_p = HValue::from([0x5A; 32]);
+        assert_eq!(
+            election_manifest.hash(&h_p)?,
+            example_election_manifest().hash(&h_p)?
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_validate_structure() {
+        let election_manifest = example_election_manifest();
+        let contest_ix = ContestIndex::from_one_based_index(1).unwrap();
+
+        let mut m = election_manifest.clone();
+        m.contests.get_mut(contest_ix).unwrap().selection_limit = 0;
+        assert!(m.validate().is_err());
+
+        let mut m = election_manifest.clone();
+        let contest = m.contests.get_mut(contest_ix).unwrap();
+        contest.selection_limit = contest.options.len() + 1;
+        let err = m.validate().unwrap_err().to_string();
+        assert!(err.contains("selection limit"), "{err}");
+
+        let mut m = election_manifest.clone();
+        m.contests.get_mut(contest_ix).unwrap().label = " \n ".into();
+        assert!(m.validate().is_err());
+
+        let mut m = election_manifest.clone();
+        m.contests.get_mut(contest_ix).unwrap().label = "Contest\u{0}".into();
+        assert!(m.validate().is_err());
+
+        let mut m = election_manifest.clone();
+        let ballot_style_ix = m.ballot_styles.indices().next().unwrap();
+        m.ballot_styles
+            .get_mut(ballot_style_ix)
+            .unwrap()
+            .contests
+            .insert(ContestIndex::from_one_based_index(1000).unwrap());
+        let err = m.validate().unwrap_err().to_string();
+        assert!(err.contains("does not exist"), "{err}");
+    }
+
+    #[test]
+    fn test_contest_kind() -> Result<()> {
+        let election_manifest = example_election_manifest();
+        let h_p = HValue::from([0x5A; 32]);
+
+        let mut m = election_manifest.clone();
+        for contest_ix in m.contests.indices() {
+            m.contests.get_mut(contest_ix).unwrap().kind = ContestKind::Candidate;
+        }
+        assert_ne!(m, election_manifest);
+        assert_eq!(m.hash(&h_p)?, election_manifest.hash(&h_p)?);
+
+        // The kind of a candidate contest is not written, so existing manifests still parse.
+        let json = String::from_utf8(election_manifest.to_canonical_bytes()?).unwrap();
+        assert_eq!(json.matches("\"kind\"").count(), 5);
+        assert_eq!(
+            ElectionManifest::from_bytes(json.as_bytes())?,
+            election_manifest
+        );
+
+        assert_eq!(ContestKind::Candidate.outcome(&[2, 1]), None);
+        assert_eq!(ContestKind::Referendum.outcome(&[2, 1]), Some("PASSED"));
+        assert_eq!(ContestKind::Amendment.outcome(&[1, 1]), Some("FAILED"));
+        assert_eq!(ContestKind::Retention.outcome(&[2, 1]), Some("RETAINED"));
+        assert_eq!(ContestKind::Retention.outcome(&[0, 1]), Some("REMOVED"));
+        assert_eq!(ContestKind::Retention.outcome(&[1]), None);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_vote_variation() -> Result<()> {
+        let election_manifest = example_election_manifest();
+        let h_p = HValue::from([0x5A; 32]);
+
+        // The Grand Council contest, which has a selection limit of 3.
+        let contest_ix = ContestIndex::from_one_based_index(5).unwrap();
+        let cnt_options = election_manifest
+            .contests
+            .get(contest_ix)
+            .unwrap()
+            .options
+            .len();
+        assert!(cnt_options > 3);
+
+        for (vote_variation, valid_limits) in [
+            (VoteVariation::OneOfM, vec![1]),
+            (VoteVariation::NOfM, (1..=cnt_options).collect()),
+            (VoteVariation::Approval, vec![cnt_options]),
+        ] {
+            for selection_limit in 1..=cnt_options {
+                let mut m = election_manifest.clone();
+                let contest = m.contests.get_mut(contest_ix).unwrap();
+                contest.vote_variation = vote_variation;
+                contest.selection_limit = selection_limit;
+                assert_eq!(
+                    m.validate().is_ok(),
+                    valid_limits.contains(&selection_limit),
+                    "{vote_variation:?} {selection_limit}"
+                );
+            }
+        }
+
+        let mut m = election_manifest.clone();
+        m.contests.get_mut(contest_ix).unwrap().vote_variation = VoteVariation::Approval;
+        let err = format!("{:#}", m.validate().unwrap_err());
+        assert!(err.contains("approval contest"), "{err}");
+
+        // The default is not written, so existing manifests still parse and hash the same.
+        let json = String::from_utf8(election_manifest.to_canonical_bytes()?).unwrap();
+        assert!(!json.contains("vote_variation"));
+
+        let mut m = election_manifest.clone();
+        let contest = m.contests.get_mut(contest_ix).unwrap();
+        contest.vote_variation = VoteVariation::OneOfM;
+        contest.selection_limit = 1;
+        let json = String::from_utf8(m.to_canonical_bytes()?).unwrap();
+        assert!(json.contains("\"vote_variation\":\"one_of_m\""), "{json}");
+        assert_eq!(ElectionManifest::from_bytes(json.as_bytes())?, m);
+        assert_ne!(m.hash(&h_p)?, election_manifest.hash(&h_p)?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_option_presentation() -> Result<()> {
+        let election_manifest = example_election_manifest();
+        let h_p = HValue::from([0x5A; 32]);
+
+        let contest_ix = election_manifest.contests.indices().next().unwrap();
+        let option_ix = ContestOptionIndex::from_one_based_index(1).unwrap();
+        let with_option = |color: Option<&str>, image_uri: Option<&str>| {
+            let mut m = election_manifest.clone();
+            let contest = m.contests.get_mut(contest_ix).unwrap();
+            let option = contest.options.get_mut(option_ix).unwrap();
+            option.color = color.map(Into::into);
+            option.image_uri = image_uri.map(Into::into);
+            m
+        };
+
+        let m = with_option(Some("#1a2B3c"), Some("https://example.com/thunderoak.png"));
+        m.validate()?;
+        assert_ne!(m, election_manifest);
+        assert_eq!(m.hash(&h_p)?, election_manifest.hash(&h_p)?);
+        assert_eq!(ElectionManifest::from_bytes(&m.to_canonical_bytes()?)?, m);
+
+        // Neither is written when absent.
+        let json = String::from_utf8(election_manifest.to_canonical_bytes()?).unwrap();
+        assert!(!json.contains("\"color\""));
+        assert!(!json.contains("\"image_uri\""));
+
+        for bad_color in [
+            "",
+            "red",
+            "1a2b3c",
+            "#1a2b3",
+            "#1a2b3c4",
+            "#1a2b3g",
+            "#１２３４５６",
+        ] {
+            let err = with_option(Some(bad_color), None)
+                .validate()
+                .unwrap_err()
+                .to_string();
+            assert!(err.contains("#RRGGBB"), "{err}");
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_redacted() -> Result<()> {
+        let mut election_manifest = example_election_manifest();
+        let h_p = HValue::from([0xA5; 32]);
+
+        let contest_ix = election_manifest.contests.indices().next().unwrap();
+        let contest = election_manifest.contests.get_mut(contest_ix).unwrap();
+        contest.kind = ContestKind::Referendum;
+        // Fields which are hashed only when set.
+        contest.vote_variation = VoteVariation::Approval;
+        contest.track_blank = true;
+        contest.track_undervotes = true;
+        contest.opt_electoral_district_id = Some("county-1".into());
+        let option_ix = contest.options.indices().next().unwrap();
+        let option = contest.options.get_mut(option_ix).unwrap();
+        option.color = Some("#00AA55".into());
+        option.image_uri = Some("https://example.com/option.png".into());
+        election_manifest.geopolitical_units.push(GeopoliticalUnit {
+            id: "county-1".into(),
+            label: "County 1".into(),
+        });
+
+        let redacted = election_manifest.redacted();
+        assert_ne!(redacted, election_manifest);
+        assert_eq!(redacted.hash(&h_p)?, election_manifest.hash(&h_p)?);
+
+        // The hash is of the redacted manifest's canonical bytes.
+        let mut v = vec![0x01];
+        v.append(&mut redacted.to_canonical_bytes()?);
+        assert_eq!(election_manifest.hash(&h_p)?, crate::hash::eg_h(&h_p, &v));
+        assert_eq!(redacted.redacted(), redacted);
+
+        let contest = redacted.contests.get(contest_ix).unwrap();
+        assert_eq!(contest.kind, ContestKind::Candidate);
+        let option = contest.options.get(option_ix).unwrap();
+        assert_eq!((&option.color, &option.image_uri), (&None, &None));
+
+        // Everything which contributes to the hash is kept.
+        for contest_ix in election_manifest.contests.indices() {
+            let (full, redacted) = (
+                election_manifest.contests.get(contest_ix).unwrap(),
+                redacted.contests.get(contest_ix).unwrap(),
+            );
+            assert_eq!(full.label, redacted.label);
+            assert_eq!(full.selection_limit, redacted.selection_limit);
+            assert_eq!(full.options.len(), redacted.options.len());
+        }
+        assert_eq!(election_manifest.ballot_styles, redacted.ballot_styles);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_stats() {
+        let election_manifest = example_election_manifest();
+        let stats = election_manifest.stats();
+
+        assert_eq!(stats.contests.len(), election_manifest.contests.len());
+        assert_eq!(
+            stats.cnt_ballot_styles,
+            election_manifest.ballot_styles.len()
+        );
+        for (contest_stats, contest_ix) in stats
+            .contests
+            .iter()
+            .zip(election_manifest.contests.indices())
+        {
+            let contest = election_manifest.contests.get(contest_ix).unwrap();
+            assert_eq!(contest_stats.contest_ix, contest_ix);
+            assert_eq!(contest_stats.label, contest.label);
+            assert_eq!(contest_stats.cnt_options, contest.options.len());
+            assert_eq!(contest_stats.selection_limit, contest.selection_limit);
+            assert_eq!(
+                contest_stats.cnt_ballot_slots,
+                contest.options.len() + contest.placeholder_count()
+            );
+        }
+        assert_eq!(
+            stats.total_ballot_slots(),
+            election_manifest.total_ballot_slots()
+        );
+
+        // A header, a row per contest, and the totals.
+        let table = stats.to_string();
+        assert_eq!(table.lines().count(), stats.contests.len() + 2);
+        let first = &stats.contests[0];
+        assert!(table.contains(&format!("{:?}", first.label)), "{table}");
+        assert!(table
+            .lines()
+            .last()
+            .unwrap()
+            .contains(&stats.total_options().to_string()));
+    }
+
+    #[test]
+    fn test_verify_hash() -> Result<()> {
+        let election_manifest = example_election_manifest();
+        let h_p = HValue::from([0x3C; 32]);
+        let h_m = election_manifest.hash(&h_p)?;
+
+        election_manifest.verify_hash(&h_p, &h_m)?;
+
+        // Under other parameters.
+        assert!(election_manifest
+            .verify_hash(&HValue::from([0x3D; 32]), &h_m)
+            .is_err());
+
+        // An altered manifest.
+        let mut altered = election_manifest.clone();
+        altered.label.push('!');
+        let err = altered.verify_hash(&h_p, &h_m).unwrap_err().to_string();
+        assert!(err.contains("does not match"), "{err}");
+
+        // Presentation metadata isn't covered by the hash.
+        election_manifest.redacted().verify_hash(&h_p, &h_m)?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_manifest_hash() -> Result<()> {
+        let election_manifest = example_election_manifest();
+
+        // H_P for the standard parameters.
+        let h_p = HValue::from(hex!(
+            "BAD5EEBFE2C98C9031BA8C36E7E4FB76DAC20665FD3621DF33F3F666BEC9AC0D"
+        ));
+
+        let expected_h_m = HValue::from(hex!(
+            "2FE7EA3C2E3C42F88647B4727254F960F1BB7B0D00A6A60C21D2F8984F5090B7"
+        ));
+
+        assert_eq!(election_manifest.hash(&h_p)?, expected_h_m);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_ballot_slots() {
+        let election_manifest = example_election_manifest();
+
+        for contest in election_manifest.contests.indices() {
+            let contest = election_manifest.contests.get(contest).unwrap();
+            let slots: Vec<SlotKind> = contest.ballot_slots().collect();
+
+            assert_eq!(
+                slots.len(),
+                contest.options.len() + contest.placeholder_count()
+            );
+
+            // Options come first, in order, followed by the placeholders.
+            for (ix0, slot) in slots.iter().enumerate() {
+                if ix0 < contest.options.len() {
+                    let expected =
+                        ContestOptionIndex::from_one_based_index(ix0 as u32 + 1).unwrap();
+                    assert_eq!(*slot, SlotKind::Option(expected));
+                } else {
+                    assert_eq!(
+                        *slot,
+                        SlotKind::Placeholder(ix0 + 1 - contest.options.len())
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_total_ballot_slots() {
+        let mut election_manifest = example_election_manifest();
+
+        // President 2, the Ministers 4, 3, and 3, Grand Council 11, the Amendment and three
+        // Referenda 2 each, Librarian 2, and Retention 2. There are no placeholders.
+        assert_eq!(election_manifest.contests.len(), 11);
+        assert_eq!(
+            election_manifest.total_ballot_slots(),
+            2 + 4 + 3 + 3 + 11 + 4 * 2 + 2 + 2
+        );
+
+        election_manifest.contests.truncate(1);
+        assert_eq!(election_manifest.total_ballot_slots(), 2);
+    }
+
+    #[test]
+    fn test_range_proof_feasible() {
+        let mut election_manifest = example_election_manifest();
+        election_manifest.check_range_proofs_feasible().unwrap();
+
+        // More selections than options, which validation would also reject.
+        let contest_ix = election_manifest.contests.indices().nth(1).unwrap();
+        let contest = election_manifest.contests.get_mut(contest_ix).unwrap();
+        contest.selection_limit = contest.options.len() + 1;
+        let label = contest.label.clone();
+
+        let err = election_manifest
+            .check_range_proofs_feasible()
+            .unwrap_err()
+            .to_string();
+        assert!(err.starts_with("Contest 2"), "{err}");
+        assert!(err.contains(&format!("{label:?}")), "{err}");
+        assert!(election_manifest.validate().is_err());
+
+        let contest = election_manifest.contests.get_mut(contest_ix).unwrap();
+        contest.selection_limit = 0;
+        contest.check_range_proof_feasible(contest_ix).unwrap_err();
+    }
+
+    #[test]
+    fn test_option_by_sequence() {
+        let mut election_manifest = example_election_manifest();
+        let contest_ix = election_manifest.contests.indices().next().unwrap();
+
+        // Repeat the first option's label at a third position.
+        let contest = election_manifest.contests.get_mut(contest_ix).unwrap();
+        let first_option = contest.option_by_sequence(1).unwrap().clone();
+        contest.options.try_push(first_option.clone()).unwrap();
+        election_manifest.validate().unwrap();
+
+        let contest = election_manifest.contests.get(contest_ix).unwrap();
+        assert_eq!(contest.option_by_sequence(1), Some(&first_option));
+        assert_eq!(contest.option_by_sequence(3), Some(&first_option));
+        assert_ne!(contest.option_by_sequence(2), Some(&first_option));
+        assert_eq!(contest.option_by_sequence(0), None);
+        assert_eq!(contest.option_by_sequence(4), None);
+
+        // Both are distinct ballot slots, but can't be looked up by label.
+        assert_eq!(
+            contest.ballot_slots().collect::<Vec<_>>(),
+            (1..=3)
+                .map(|i| SlotKind::Option(ContestOptionIndex::from_one_based_index(i).unwrap()))
+                .collect::<Vec<_>>()
+        );
+        let err = contest.option_ix_by_label(&first_option.label).unwrap_err();
+        assert!(err.to_string().contains("more than one"), "{err}");
+    }
+
+    #[test]
+    fn test_lookup_by_label() {
+        let mut election_manifest = example_election_manifest();
+
+        let contest_ix = election_manifest
+            .contest_ix_by_label("Minister of Arcane Sciences")
+            .unwrap();
+        assert_eq!(contest_ix.get_one_based_u32(), 2);
+
+        let contest = election_manifest.contests.get(contest_ix).unwrap();
+        let option_ix = contest
+            .option_ix_by_label("Archímedes Darkstone\n(Ætherwïng)")
+            .unwrap();
+        assert_eq!(option_ix.get_one_based_u32(), 2);
+
+        // Labels must match exactly.
+        assert!(contest.option_ix_by_label("Archímedes Darkstone").is_err());
+        assert!(election_manifest
+            .contest_ix_by_label("minister of arcane sciences")
+            .is_err());
+
+        // Ambiguous labels.
+        let contest = election_manifest.contests.get(contest_ix).unwrap().clone();
+        election_manifest.contests.try_push(contest).unwrap();
+        let err = election_manifest
+            .contest_ix_by_label("Minister of Arcane Sciences")
+            .unwrap_err();
+        assert!(err.to_string().contains("More than one"));
+    }
+
+    #[test]
+    fn test_electoral_district() {
+        let builder = || {
+            ElectionManifestBuilder::new("Election")
+                .add_geopolitical_unit("county-1", "Ëlderglen County")
+                .add_contest("Mayor", 1)
+                .electoral_district("county-1")
+                .add_option("Åsh")
+                .add_ballot_style("Countywide", [1])
+        };
+
+        let election_manifest = builder().build().unwrap();
+        let contest = election_manifest
+            .contests
+            .get(ContestIndex::from_one_based_index(1).unwrap())
+            .unwrap();
+        assert_eq!(
+            contest.opt_electoral_district_id.as_deref(),
+            Some("county-1")
+        );
+
+        let json = String::from_utf8(election_manifest.to_canonical_bytes().unwrap()).unwrap();
+        assert!(
+            json.contains(r#""electoral_district_id":"county-1""#),
+            "{json}"
+        );
+        assert_eq!(
+            ElectionManifest::from_bytes(json.as_bytes()).unwrap(),
+            election_manifest
+        );
+
+        // A contest must refer to a unit which exists.
+        let mut missing = election_manifest.clone();
+        missing.geopolitical_units.clear();
+        let e = missing.validate().unwrap_err().to_string();
+        assert!(e.contains(r#"electoral district "county-1""#), "{e}");
+
+        let e = builder()
+            .electoral_district("county-2")
+            .build()
+            .unwrap_err()
+            .to_string();
+        assert!(e.contains("not a geopolitical unit"), "{e}");
+
+        // Unit ids must be unique.
+        let e = builder()
+            .add_geopolitical_unit("county-1", "Ëlderglen Township")
+            .build()
+            .unwrap_err()
+            .to_string();
+        assert!(e.contains("More than one geopolitical unit"), "{e}");
+
+        // Manifests without districts are unchanged.
+        let json =
+            String::from_utf8(example_election_manifest().to_canonical_bytes().unwrap()).unwrap();
+        assert!(!json.contains("geopolitical_units"));
+        assert!(!json.contains("electoral_district_id"));
+    }
 }
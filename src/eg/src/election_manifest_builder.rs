@@ -0,0 +1,327 @@
+// Copyright (C) Microsoft Corporation. All rights reserved.
+
+#![deny(clippy::unwrap_used)]
+#![deny(clippy::expect_used)]
+#![deny(clippy::panic)]
+#![deny(clippy::manual_assert)]
+
+use std::collections::BTreeSet;
+
+use anyhow::{anyhow, Result};
+
+use crate::{
+    ballot_style::BallotStyle,
+    election_manifest::{
+        validate_label, Contest, ContestIndex, ContestKind, ContestOption, ElectionManifest,
+        GeopoliticalUnit, VoteVariation, MAX_OPTIONS_PER_CONTEST,
+    },
+    vec1::Vec1,
+};
+
+/// Builds an [`ElectionManifest`] one contest, option, and ballot style at a time.
+///
+/// Options, [`kind`](Self::kind), and [`track_blank`](Self::track_blank) apply to the contest
+/// most recently added. Each step is checked as it is taken, and each contest is checked once
+/// its options are complete. The first problem found is returned by [`build`](Self::build),
+/// and steps after it are ignored.
+///
+/// ```
+/// # use eg::{election_manifest::ContestKind, election_manifest_builder::ElectionManifestBuilder};
+/// let election_manifest = ElectionManifestBuilder::new("General Election")
+///     .add_contest("Mayor", 1)
+///     .add_option("Åsh")
+///     .add_option("Bïrch")
+///     .add_contest("Library Levy", 1)
+///     .kind(ContestKind::Referendum)
+///     .add_option("Yes")
+///     .add_option("No")
+///     .add_ballot_style("Citywide", [1, 2])
+///     .build()
+///     .unwrap();
+/// assert_eq!(election_manifest.contests.len(), 2);
+/// ```
+pub struct ElectionManifestBuilder {
+    label: String,
+    contests: Vec1<Contest>,
+    ballot_styles: Vec1<BallotStyle>,
+    geopolitical_units: Vec<GeopoliticalUnit>,
+    opt_error: Option<anyhow::Error>,
+}
+
+impl ElectionManifestBuilder {
+    /// Starts a manifest with the specified label, and no contests or ballot styles.
+    pub fn new(label: impl Into<String>) -> Self {
+        let label = label.into();
+        let opt_error = validate_label("Election manifest", &label).err();
+        ElectionManifestBuilder {
+            label,
+            contests: Vec1::new(),
+            ballot_styles: Vec1::new(),
+            geopolitical_units: Vec::new(),
+            opt_error,
+        }
+    }
+
+    /// Adds a contest with no options yet. Add them with [`add_option`](Self::add_option).
+    pub fn add_contest(mut self, label: impl Into<String>, selection_limit: usize) -> Self {
+        self.finish_contest();
+
+        let contest = Contest {
+            label: label.into(),
+            selection_limit,
+            vote_variation: VoteVariation::default(),
+            options: Vec1::new(),
+            kind: ContestKind::default(),
+            track_blank: false,
+            track_undervotes: false,
+            opt_electoral_district_id: None,
+        };
+
+        self.step(|self_| {
+            let contest_ix = ContestIndex::from_one_based_index(self_.contests.len() as u32 + 1)?;
+            validate_label(&format!("Contest {contest_ix}"), &contest.label)?;
+            self_.contests.try_push(contest)
+        })
+    }
+
+    /// Adds an option to the most recently added contest.
+    pub fn add_option(self, label: impl Into<String>) -> Self {
+        let option = ContestOption {
+            label: label.into(),
+            color: None,
+            image_uri: None,
+        };
+
+        self.step(|self_| {
+            let (contest_ix, contest) = self_.current_contest()?;
+            validate_label(
+                &format!("Contest {contest_ix} option {}", contest.options.len() + 1),
+                &option.label,
+            )?;
+            contest.options.try_push(option)
+        })
+    }
+
+    /// Sets the kind of the most recently added contest. The default is
+    /// [`ContestKind::Candidate`].
+    pub fn kind(self, kind: ContestKind) -> Self {
+        self.step(|self_| {
+            self_.current_contest()?.1.kind = kind;
+            Ok(())
+        })
+    }
+
+    /// Sets the vote variation of the most recently added contest. The default is
+    /// [`VoteVariation::NOfM`].
+    pub fn vote_variation(self, vote_variation: VoteVariation) -> Self {
+        self.step(|self_| {
+            self_.current_contest()?.1.vote_variation = vote_variation;
+            Ok(())
+        })
+    }
+
+    /// Tracks the number of ballots which leave the most recently added contest blank.
+    pub fn track_blank(self) -> Self {
+        self.step(|self_| {
+            self_.current_contest()?.1.track_blank = true;
+            Ok(())
+        })
+    }
+
+    /// Tracks the total number of undervotes in the most recently added contest.
+    pub fn track_undervotes(self) -> Self {
+        self.step(|self_| {
+            self_.current_contest()?.1.track_undervotes = true;
+            Ok(())
+        })
+    }
+
+    /// Sets the electoral district of the most recently added contest, by the id of a
+    /// geopolitical unit. The unit need not have been added yet.
+    pub fn electoral_district(self, id: impl Into<String>) -> Self {
+        let id = id.into();
+        self.step(|self_| {
+            self_.current_contest()?.1.opt_electoral_district_id = Some(id);
+            Ok(())
+        })
+    }
+
+    /// Adds a geopolitical unit, which contests may name as their electoral district.
+    pub fn add_geopolitical_unit(
+        mut self,
+        id: impl Into<String>,
+        label: impl Into<String>,
+    ) -> Self {
+        self.geopolitical_units.push(GeopoliticalUnit {
+            id: id.into(),
+            label: label.into(),
+        });
+        self
+    }
+
+    /// Adds a ballot style with the specified contests, by their 1-based indices in the order
+    /// they were added. The contests need not have been added yet.
+    pub fn add_ballot_style(
+        self,
+        label: impl Into<String>,
+        contests: impl IntoIterator<Item = u32>,
+    ) -> Self {
+        let label = label.into();
+        let contests = contests.into_iter().collect::<Vec<_>>();
+
+        self.step(|self_| {
+            let ballot_style_ix = self_.ballot_styles.len() + 1;
+            validate_label(&format!("Ballot style {ballot_style_ix}"), &label)?;
+
+            let contests = contests
+                .into_iter()
+                .map(ContestIndex::from_one_based_index)
+                .collect::<Result<BTreeSet<_>>>()?;
+
+            self_
+                .ballot_styles
+                .try_push(BallotStyle { label, contests })
+        })
+    }
+
+    /// Returns the finished manifest, or the first problem found while building it.
+    ///
+    /// The manifest is validated as a whole with [`ElectionManifest::validate`], which also
+    /// checks that every ballot style refers only to contests which exist.
+    pub fn build(mut self) -> Result<ElectionManifest> {
+        self.finish_contest();
+
+        if let Some(error) = self.opt_error {
+            return Err(error);
+        }
+
+        let election_manifest = ElectionManifest {
+            label: self.label,
+            contests: self.contests,
+            ballot_styles: self.ballot_styles,
+            geopolitical_units: self.geopolitical_units,
+        };
+        election_manifest.validate()?;
+
+        Ok(election_manifest)
+    }
+
+    /// Performs a step, unless an earlier step has failed. The first failure is recorded.
+    fn step(mut self, f: impl FnOnce(&mut Self) -> Result<()>) -> Self {
+        if self.opt_error.is_none() {
+            self.opt_error = f(&mut self).err();
+        }
+        self
+    }
+
+    /// Validates the most recently added contest, which now has all its options.
+    fn finish_contest(&mut self) {
+        if self.opt_error.is_some() {
+            return;
+        }
+        if let Some(contest_ix) = self.contests.indices().last() {
+            if let Some(contest) = self.contests.get(contest_ix) {
+                self.opt_error = contest.validate(contest_ix, MAX_OPTIONS_PER_CONTEST).err();
+            }
+        }
+    }
+
+    /// Returns the most recently added contest.
+    fn current_contest(&mut self) -> Result<(ContestIndex, &mut Contest)> {
+        let contest_ix = self
+            .contests
+            .indices()
+            .last()
+            .ok_or_else(|| anyhow!("No contest has been added yet"))?;
+        let contest = self
+            .contests
+            .get_mut(contest_ix)
+            .ok_or_else(|| anyhow!("Contest {contest_ix} does not exist"))?;
+        Ok((contest_ix, contest))
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod test {
+    use super::*;
+    use crate::example_election_manifest::example_election_manifest;
+
+    #[test]
+    fn test_builder() {
+        // The example manifest is written with the builder.
+        example_election_manifest().validate().unwrap();
+
+        let err = |builder: ElectionManifestBuilder| builder.build().unwrap_err().to_string();
+
+        let e = err(ElectionManifestBuilder::new("Election").add_option("Åsh"));
+        assert!(e.contains("No contest"), "{e}");
+
+        let e = err(ElectionManifestBuilder::new(" "));
+        assert!(e.contains("blank label"), "{e}");
+
+        // Checked when the next contest is added.
+        let e = err(ElectionManifestBuilder::new("Election")
+            .add_contest("Mayor", 2)
+            .add_option("Åsh")
+            .add_contest("Sheriff", 1)
+            .add_option("Bïrch"));
+        assert!(e.contains("selection limit of 2"), "{e}");
+
+        // And at the end.
+        let e = err(ElectionManifestBuilder::new("Election").add_contest("Mayor", 1));
+        assert!(e.contains("has no options"), "{e}");
+
+        // The first error is kept.
+        let e = err(ElectionManifestBuilder::new("Election")
+            .add_contest("Mayor", 1)
+            .add_option("Åsh\u{7}")
+            .add_contest("", 1));
+        assert!(e.contains("control character"), "{e}");
+
+        let e = err(ElectionManifestBuilder::new("Election")
+            .add_contest("Mayor", 1)
+            .add_option("Åsh")
+            .add_ballot_style("Citywide", [1, 2]));
+        assert!(e.contains("does not exist"), "{e}");
+
+        let e = err(ElectionManifestBuilder::new("Election")
+            .add_contest("Mayor", 1)
+            .add_option("Åsh")
+            .add_ballot_style("Citywide", [0]));
+        assert!(!e.is_empty());
+
+        let election_manifest = ElectionManifestBuilder::new("Election")
+            .add_contest("Mayor", 1)
+            .add_option("Åsh")
+            .add_option("Bïrch")
+            .track_blank()
+            .track_undervotes()
+            .add_contest("Levy", 1)
+            .kind(ContestKind::Referendum)
+            .add_option("Yes")
+            .add_option("No")
+            .add_ballot_style("Citywide", [1, 2])
+            .add_ballot_style("Outskirts", [2])
+            .build()
+            .unwrap();
+
+        let mayor = election_manifest
+            .contests
+            .get(ContestIndex::from_one_based_index(1).unwrap())
+            .unwrap();
+        assert!(mayor.track_blank);
+        assert!(mayor.track_undervotes);
+        assert_eq!(mayor.kind, ContestKind::Candidate);
+        assert_eq!(mayor.options.len(), 2);
+
+        let levy = election_manifest
+            .contests
+            .get(ContestIndex::from_one_based_index(2).unwrap())
+            .unwrap();
+        assert!(!levy.track_blank);
+        assert!(!levy.track_undervotes);
+        assert_eq!(levy.kind, ContestKind::Referendum);
+        assert_eq!(election_manifest.ballot_styles.len(), 2);
+    }
+}
@@ -14,6 +14,7 @@ use util::csprng::Csprng;
 use crate::{fixed_parameters::FixedParameters, varying_parameters::VaryingParameters};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "strict-parsing", serde(deny_unknown_fields))]
 pub struct ElectionParameters {
     /// The fixed ElectionGuard parameters that apply to all elections.
     pub fixed_parameters: FixedParameters,
@@ -22,6 +23,24 @@ pub struct ElectionParameters {
     pub varying_parameters: VaryingParameters,
 }
 
+/// A one-line summary, e.g. `ElectionParameters{v2.0, n=5, k=3, date=2023-05-02}`.
+/// Unlike `Debug`, this does not write out `p`, `q`, `r`, or `g`.
+impl std::fmt::Display for ElectionParameters {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let varying_parameters = &self.varying_parameters;
+        f.write_str("ElectionParameters{")?;
+        match &self.fixed_parameters.opt_ElectionGuard_Design_Specification {
+            Some(version) => write!(f, "{version}")?,
+            None => f.write_str("unversioned")?,
+        }
+        write!(
+            f,
+            ", n={}, k={}, date={}}}",
+            varying_parameters.n, varying_parameters.k, varying_parameters.date
+        )
+    }
+}
+
 impl ElectionParameters {
     /// Reads a `ElectionParameters` from a `std::io::Read` and validates it.
     pub fn from_stdioread_validated(
@@ -37,15 +56,41 @@ impl ElectionParameters {
     }
 
     /// Verifies that the `ElectionParameters` meet some basic validity requirements.
+    ///
+    /// This includes primality tests of `p` and `q`, see [`FixedParameters::validate`].
     pub fn validate(&self, csprng: &mut Csprng) -> Result<()> {
         self.fixed_parameters.validate(csprng)?;
         self.varying_parameters.validate()?;
         Ok(())
     }
 
+    /// Same as [`ElectionParameters::validate`], but with the specified number of rounds of
+    /// primality testing. See [`FixedParameters::validate_with_rounds`].
+    pub fn validate_with_rounds(&self, csprng: &mut Csprng, rounds: usize) -> Result<()> {
+        self.fixed_parameters.validate_with_rounds(csprng, rounds)?;
+        self.varying_parameters.validate()?;
+        Ok(())
+    }
+
+    /// Same as [`ElectionParameters::validate`], but without the primality tests of `p` and
+    /// `q`, so no [`Csprng`] is needed. See [`FixedParameters::validate_structural`].
+    pub fn validate_structural(&self) -> Result<()> {
+        self.fixed_parameters.validate_structural()?;
+        self.varying_parameters.validate()?;
+        Ok(())
+    }
+
     /// Reads an `ElectionParameters` from a byte sequence.
+    ///
+    /// The fixed parameters must be exactly the standard parameters for the ElectionGuard
+    /// Design Specification version named in the file.
     pub fn from_bytes(bytes: &[u8]) -> Result<ElectionParameters> {
-        serde_json::from_slice(bytes).with_context(|| "Error parsing ElectionParameters bytes")
+        let self_: Self = serde_json::from_slice(bytes)
+            .with_context(|| "Error parsing ElectionParameters bytes")?;
+
+        self_.fixed_parameters.matches_standard()?;
+
+        Ok(self_)
     }
 
     /// Returns a pretty JSON `String` representation of the `ElectionParameters`.
@@ -78,3 +123,121 @@ impl ElectionParameters {
             .context("Writing ElectionParameters")
     }
 }
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod test {
+    use super::*;
+    use crate::{
+        example_election_parameters::example_election_parameters,
+        fixed_parameters::ElectionGuardDesignSpecificationVersion, guardian::GuardianIndex,
+    };
+
+    #[test]
+    fn test_display() {
+        let election_parameters = example_election_parameters();
+        assert_eq!(
+            election_parameters.to_string(),
+            "ElectionParameters{v2.0, n=5, k=3, date=2023-05-02}"
+        );
+        assert_eq!(
+            election_parameters.varying_parameters.to_string(),
+            "VaryingParameters{n=5, k=3, date=2023-05-02, ballot_chaining=Prohibited}"
+        );
+    }
+
+    #[test]
+    fn test_from_bytes_matches_standard() {
+        let election_parameters = example_election_parameters();
+
+        let bytes = election_parameters.to_json_pretty().into_bytes();
+        let election_parameters_2 = ElectionParameters::from_bytes(&bytes).unwrap();
+        assert_eq!(
+            election_parameters.fixed_parameters,
+            election_parameters_2.fixed_parameters
+        );
+
+        // A different g.
+        let mut election_parameters_bad_g = election_parameters.clone();
+        election_parameters_bad_g.fixed_parameters.g += 1_u8;
+        let bytes = election_parameters_bad_g.to_json_pretty().into_bytes();
+        let err = ElectionParameters::from_bytes(&bytes).unwrap_err();
+        assert!(err.to_string().contains("g does not match"));
+
+        // A version for which there are no standard parameters.
+        let mut election_parameters_other = election_parameters.clone();
+        election_parameters_other
+            .fixed_parameters
+            .opt_ElectionGuard_Design_Specification = Some(
+            ElectionGuardDesignSpecificationVersion::Other("Toy parameters".to_string()),
+        );
+        let bytes = election_parameters_other.to_json_pretty().into_bytes();
+        assert!(ElectionParameters::from_bytes(&bytes).is_err());
+
+        // No version at all.
+        let mut election_parameters_none = election_parameters;
+        election_parameters_none
+            .fixed_parameters
+            .opt_ElectionGuard_Design_Specification = None;
+        let bytes = election_parameters_none.to_json_pretty().into_bytes();
+        assert!(ElectionParameters::from_bytes(&bytes).is_err());
+    }
+
+    #[test]
+    fn test_validate_structural() {
+        let election_parameters = example_election_parameters();
+        election_parameters.validate_structural().unwrap();
+
+        // A g outside the subgroup of order q.
+        let mut election_parameters_bad_g = election_parameters.clone();
+        election_parameters_bad_g.fixed_parameters.g = BigUint::from(2_u8);
+        let err = election_parameters_bad_g.validate_structural().unwrap_err();
+        assert!(err.to_string().contains("g^q = 1 mod p"));
+
+        // An inconsistent cofactor.
+        let mut election_parameters_bad_r = election_parameters.clone();
+        election_parameters_bad_r.fixed_parameters.r += 1_u8;
+        assert!(election_parameters_bad_r.validate_structural().is_err());
+
+        // An invalid guardian threshold.
+        let mut election_parameters_bad_k = election_parameters;
+        election_parameters_bad_k.varying_parameters.k = GuardianIndex::from_one_based_index(
+            election_parameters_bad_k
+                .varying_parameters
+                .n
+                .get_one_based_u32()
+                + 1,
+        )
+        .unwrap();
+        assert!(election_parameters_bad_k.validate_structural().is_err());
+    }
+
+    #[test]
+    fn test_unknown_field() {
+        let election_parameters = example_election_parameters();
+        let value: serde_json::Value =
+            serde_json::from_str(&election_parameters.to_json_pretty()).unwrap();
+
+        for pointer in ["", "/fixed_parameters", "/varying_parameters"] {
+            let mut value = value.clone();
+            value
+                .pointer_mut(pointer)
+                .and_then(serde_json::Value::as_object_mut)
+                .unwrap()
+                .insert("bogus".into(), 1.into());
+            let json = serde_json::to_vec(&value).unwrap();
+
+            let result = ElectionParameters::from_bytes(&json);
+            if cfg!(feature = "strict-parsing") {
+                let err = format!("{:#}", result.unwrap_err());
+                assert!(err.contains("unknown field `bogus`"), "{pointer}: {err}");
+            } else {
+                assert_eq!(
+                    result.unwrap().to_json_pretty(),
+                    election_parameters.to_json_pretty(),
+                    "{pointer}"
+                );
+            }
+        }
+    }
+}
@@ -5,18 +5,27 @@
 #![deny(clippy::panic)]
 #![deny(clippy::manual_assert)]
 
-use std::collections::HashMap;
-
-use anyhow::{anyhow, Context, Result};
+use anyhow::{anyhow, ensure, Context, Result};
 use serde::{Deserialize, Serialize};
 
 use crate::{
-    ballot::BallotEncrypted, election_manifest::ElectionManifest,
-    election_parameters::ElectionParameters, hashes::Hashes, hashes_ext::HashesExt,
+    artifact_format::{parse_json_artifact_with_limit, MAX_ELECTION_RECORD_BYTES},
+    ballot::BallotEncrypted,
+    election_manifest::ElectionManifest,
+    election_parameters::ElectionParameters,
+    guardian_public_key::GuardianPublicKey,
+    hashes::Hashes,
+    hashes_ext::HashesExt,
     joint_election_public_key::JointElectionPublicKey,
+    tally::Tally,
 };
 
 /// The header of the election record, generated before the election begins.
+///
+/// This bundles everything needed to encrypt or verify a ballot, so functions which need the
+/// election context take a `&PreVotingData` rather than its parts separately. Use
+/// [`PreVotingData::compute`] to derive the hashes and joint key from the same inputs, or
+/// [`PreVotingData::validate`] to check a header that was read from a file.
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct PreVotingData {
     /// The election manifest.
@@ -34,27 +43,153 @@ pub struct PreVotingData {
     /// The joint election public key.
     pub public_key: JointElectionPublicKey,
 }
-#[allow(dead_code)]
-/// The body of the election record, generated after the election is complete.
+
+/// The election record.
 #[derive(Debug)]
-pub struct ElectionRecordBody {
-    /// Every encrypted ballot prepared in the election (whether cast or challenged)
-    all_ballots: Vec<BallotEncrypted>,
+pub struct ElectionRecord {
+    /// The header of the election record, generated before the election begins.
+    pub prevoting: PreVotingData,
 
-    /// Every challenged ballot
-    // challenged_ballots: Vec<BallotSelections>,
+    /// The public keys of all the guardians.
+    pub guardian_public_keys: Vec<GuardianPublicKey>,
 
-    /// Tally of all cast ballots
+    /// Every encrypted ballot prepared in the election (whether cast or challenged).
+    pub ballots: Vec<BallotEncrypted>,
 
-    /// Ordered lists of ballots encrypted by each device
-    ballots_by_device: HashMap<String, String>,
+    /// The encrypted tally, once it has been computed.
+    pub opt_tally: Option<Tally>,
 }
-#[allow(dead_code)]
-/// The election record.
-#[derive(Debug)]
-pub struct ElectionRecord {
-    prevoting: PreVotingData,
-    body: ElectionRecordBody,
+
+/// The representation of an [`ElectionRecord`] as a single JSON object.
+///
+/// The hashes are not included, since they are recomputed when the bundle is read.
+#[derive(Serialize, Deserialize)]
+struct ElectionRecordBundle {
+    parameters: ElectionParameters,
+    manifest: ElectionManifest,
+    guardians: Vec<GuardianPublicKey>,
+    joint_key: JointElectionPublicKey,
+    ballots: Vec<BallotEncrypted>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    tally: Option<Tally>,
+}
+
+impl ElectionRecord {
+    /// Reads an `ElectionRecord` from a bundle, i.e. a single JSON object with `parameters`,
+    /// `manifest`, `guardians`, `joint_key`, `ballots`, and optionally `tally` fields.
+    ///
+    /// Each part is validated as it would be if it were loaded from its own file, see
+    /// [`ElectionRecord::from_parts`]. Rather than testing `p` and `q` for primality, the
+    /// fixed parameters must be the standard ones. The ballots are not verified.
+    pub fn from_bundle(stdioread: &mut dyn std::io::Read) -> Result<Self> {
+        let bundle: ElectionRecordBundle = parse_json_artifact_with_limit(
+            stdioread,
+            "election record bundle",
+            MAX_ELECTION_RECORD_BYTES,
+            |r| serde_json::from_reader(r).context("Reading ElectionRecord bundle"),
+        )?;
+
+        Self::from_parts(
+            bundle.parameters,
+            bundle.manifest,
+            bundle.guardians,
+            &bundle.joint_key,
+            bundle.ballots,
+            bundle.tally,
+        )
+    }
+
+    /// Assembles an `ElectionRecord` from its published parts, which have been read but not
+    /// validated.
+    ///
+    /// Every part is validated as its `from_stdioread_validated` would, the hashes are
+    /// recomputed, and the joint election public key is checked against the guardian public
+    /// keys. The primality tests of `p` and `q` need a csprng, so instead the fixed parameters
+    /// must be exactly the standard parameters, see
+    /// [`FixedParameters::matches_standard`](crate::fixed_parameters::FixedParameters::matches_standard),
+    /// besides passing [`ElectionParameters::validate_structural`].
+    pub(crate) fn from_parts(
+        parameters: ElectionParameters,
+        manifest: ElectionManifest,
+        guardian_public_keys: Vec<GuardianPublicKey>,
+        joint_key: &JointElectionPublicKey,
+        ballots: Vec<BallotEncrypted>,
+        opt_tally: Option<Tally>,
+    ) -> Result<Self> {
+        parameters
+            .validate_structural()
+            .and_then(|()| parameters.fixed_parameters.matches_standard())
+            .context("Validating ElectionRecord parameters")?;
+        manifest
+            .validate()
+            .context("Validating ElectionRecord manifest")?;
+        for gpk in &guardian_public_keys {
+            gpk.validate(&parameters)
+                .with_context(|| format!("Validating public key of guardian {}", gpk.i))?;
+        }
+        joint_key
+            .validate(&parameters)
+            .context("Validating ElectionRecord joint_key")?;
+        if let Some(tally) = &opt_tally {
+            tally
+                .validate(&parameters.fixed_parameters, &manifest)
+                .context("Validating ElectionRecord tally")?;
+        }
+
+        let prevoting = PreVotingData::compute(manifest, parameters, &guardian_public_keys)?;
+
+        ensure!(
+            prevoting.public_key.joint_election_public_key == joint_key.joint_election_public_key,
+            "ElectionRecord joint_key does not match the guardian public keys"
+        );
+
+        #[cfg(feature = "tracing")]
+        tracing::debug!(
+            guardians = guardian_public_keys.len(),
+            ballots = ballots.len(),
+            tally = opt_tally.is_some(),
+            "Read election record"
+        );
+
+        Ok(ElectionRecord {
+            prevoting,
+            guardian_public_keys,
+            ballots,
+            opt_tally,
+        })
+    }
+
+    /// Writes the `ElectionRecord` as a bundle, which can be read by
+    /// [`ElectionRecord::from_bundle`].
+    pub fn to_bundle(&self, stdiowrite: &mut dyn std::io::Write) -> Result<()> {
+        #[derive(Serialize)]
+        struct ElectionRecordBundleRef<'a> {
+            parameters: &'a ElectionParameters,
+            manifest: &'a ElectionManifest,
+            guardians: &'a [GuardianPublicKey],
+            joint_key: &'a JointElectionPublicKey,
+            ballots: &'a [BallotEncrypted],
+            #[serde(skip_serializing_if = "Option::is_none")]
+            tally: &'a Option<Tally>,
+        }
+
+        let bundle = ElectionRecordBundleRef {
+            parameters: &self.prevoting.parameters,
+            manifest: &self.prevoting.manifest,
+            guardians: &self.guardian_public_keys,
+            joint_key: &self.prevoting.public_key,
+            ballots: &self.ballots,
+            tally: &self.opt_tally,
+        };
+
+        let mut ser = serde_json::Serializer::pretty(stdiowrite);
+
+        bundle
+            .serialize(&mut ser)
+            .map_err(Into::<anyhow::Error>::into)
+            .and_then(|_| ser.into_inner().write_all(b"\n").map_err(Into::into))
+            .context("Writing ElectionRecord bundle")
+    }
 }
 
 impl PreVotingData {
@@ -74,6 +209,111 @@ impl PreVotingData {
         }
     }
 
+    /// Computes the joint election public key and all the hashes from the guardians' public
+    /// keys, the manifest and the parameters.
+    pub fn compute(
+        manifest: ElectionManifest,
+        parameters: ElectionParameters,
+        guardian_public_keys: &[GuardianPublicKey],
+    ) -> Result<PreVotingData> {
+        let public_key = JointElectionPublicKey::compute(&parameters, guardian_public_keys)?;
+
+        let hashes = Hashes::compute(&parameters, &manifest)?;
+
+        let hashes_ext =
+            HashesExt::compute(&parameters, &hashes, &public_key, guardian_public_keys);
+
+        #[cfg(feature = "tracing")]
+        tracing::debug!(h_e = %hashes_ext.h_e, "Computed pre-voting data");
+
+        Ok(PreVotingData {
+            manifest,
+            parameters,
+            hashes,
+            hashes_ext,
+            public_key,
+        })
+    }
+
+    /// Verifies that `H_P`, `H_M`, and `H_B` were computed from this manifest and these
+    /// parameters, and that the joint election public key conforms to the parameters.
+    ///
+    /// `H_E` and the joint key also depend on the guardians' public keys, see
+    /// [`PreVotingData::validate_with_guardian_public_keys`].
+    pub fn validate(&self) -> Result<()> {
+        self.manifest.validate()?;
+        self.public_key.validate(&self.parameters)?;
+
+        let hashes = Hashes::compute(&self.parameters, &self.manifest)?;
+        ensure!(
+            self.hashes.h_p == hashes.h_p,
+            "H_P does not match the election parameters"
+        );
+        ensure!(
+            self.hashes.h_m == hashes.h_m,
+            "H_M does not match the election manifest"
+        );
+        ensure!(
+            self.hashes.h_b == hashes.h_b,
+            "H_B does not match the election parameters and manifest"
+        );
+
+        Ok(())
+    }
+
+    /// Verifies everything that [`PreVotingData::validate`] does, and also that the joint
+    /// election public key and `H_E` were computed from the specified guardian public keys.
+    pub fn validate_with_guardian_public_keys(
+        &self,
+        guardian_public_keys: &[GuardianPublicKey],
+    ) -> Result<()> {
+        self.validate()?;
+
+        let public_key = JointElectionPublicKey::compute(&self.parameters, guardian_public_keys)?;
+        ensure!(
+            self.public_key.joint_election_public_key == public_key.joint_election_public_key,
+            "The joint election public key does not match the guardian public keys"
+        );
+
+        let hashes_ext = HashesExt::compute(
+            &self.parameters,
+            &self.hashes,
+            &self.public_key,
+            guardian_public_keys,
+        );
+        ensure!(
+            self.hashes_ext.h_e == hashes_ext.h_e,
+            "H_E does not match the guardian public keys"
+        );
+
+        Ok(())
+    }
+
+    /// The election manifest.
+    pub fn manifest(&self) -> &ElectionManifest {
+        &self.manifest
+    }
+
+    /// The election parameters.
+    pub fn parameters(&self) -> &ElectionParameters {
+        &self.parameters
+    }
+
+    /// The hashes `H_P`, `H_M`, and `H_B`.
+    pub fn hashes(&self) -> &Hashes {
+        &self.hashes
+    }
+
+    /// The extended base hash `H_E`.
+    pub fn hashes_ext(&self) -> &HashesExt {
+        &self.hashes_ext
+    }
+
+    /// The joint election public key.
+    pub fn public_key(&self) -> &JointElectionPublicKey {
+        &self.public_key
+    }
+
     pub fn set_manifest(&mut self, manifest: ElectionManifest) {
         self.manifest = manifest;
     }
@@ -123,3 +363,177 @@ impl PreVotingData {
             .context("Error writing election record header file")
     }
 }
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod test {
+    use super::*;
+    use crate::{
+        contest_selection::ContestSelection, device::Device, example_election::ExampleElection,
+        example_election_manifest::example_election_manifest,
+        example_election_parameters::example_election_parameters,
+        guardian_secret_key::GuardianSecretKey, vec1::Vec1,
+    };
+    use util::csprng::Csprng;
+
+    #[test]
+    fn test_validate() {
+        let mut csprng = Csprng::new(b"test_validate");
+
+        let election_parameters = example_election_parameters();
+        let election_manifest = example_election_manifest();
+
+        let ExampleElection {
+            guardian_public_keys,
+            pvd,
+            ..
+        } = ExampleElection::generate(&mut csprng, election_parameters, election_manifest).unwrap();
+        pvd.validate_with_guardian_public_keys(&guardian_public_keys)
+            .unwrap();
+
+        // A manifest which doesn't match the hashes.
+        let mut manifest = pvd.manifest().clone();
+        manifest.label.push_str(" (amended)");
+        let mut pvd_2 = pvd.clone();
+        pvd_2.set_manifest(manifest);
+        let err = pvd_2.validate().unwrap_err();
+        assert!(err.to_string().contains("H_M"));
+
+        // Guardian keys which don't match the joint key.
+        let mut guardian_public_keys_2 = guardian_public_keys.clone();
+        guardian_public_keys_2[0] = GuardianSecretKey::generate(
+            &mut csprng,
+            pvd.parameters(),
+            guardian_public_keys[0].i,
+            None,
+        )
+        .unwrap()
+        .make_public_key();
+        pvd.validate().unwrap();
+        let err = pvd
+            .validate_with_guardian_public_keys(&guardian_public_keys_2)
+            .unwrap_err();
+        assert!(err.to_string().contains("joint election public key"));
+    }
+
+    #[test]
+    fn test_bundle() {
+        let mut csprng = Csprng::new(b"test_bundle");
+
+        let ExampleElection {
+            guardian_public_keys,
+            pvd: prevoting,
+            ..
+        } = ExampleElection::with_first_contests(&mut csprng, 2).unwrap();
+
+        let device = Device::new("test", prevoting.clone());
+        let mut ctest_selections = Vec1::new();
+        for contest_ix in prevoting.manifest.contests.indices() {
+            let contest = prevoting.manifest.contests.get(contest_ix).unwrap();
+            ctest_selections
+                .try_push(ContestSelection::new_pick_random(
+                    &mut csprng,
+                    contest.selection_limit,
+                    contest.options.len(),
+                ))
+                .unwrap();
+        }
+        let mut ballot = BallotEncrypted::new_from_selections(
+            &device,
+            &mut csprng,
+            &[0u8; 32],
+            &ctest_selections,
+        )
+        .unwrap();
+        ballot.cast().unwrap();
+
+        let tally = Tally::from_ballots(
+            &prevoting.parameters.fixed_parameters,
+            &prevoting.manifest,
+            [&ballot],
+        )
+        .unwrap();
+
+        let record = ElectionRecord {
+            prevoting,
+            guardian_public_keys,
+            ballots: vec![ballot],
+            opt_tally: Some(tally),
+        };
+
+        let mut buf = Vec::new();
+        record.to_bundle(&mut buf).unwrap();
+
+        let record_2 = ElectionRecord::from_bundle(&mut buf.as_slice()).unwrap();
+        assert!(record_2.prevoting.hashes == record.prevoting.hashes);
+        assert!(record_2.prevoting.hashes_ext == record.prevoting.hashes_ext);
+        assert_eq!(record_2.ballots.len(), 1);
+        assert_eq!(
+            record_2.ballots[0].confirmation_code,
+            record.ballots[0].confirmation_code
+        );
+        assert!(record_2.opt_tally.is_some());
+
+        let mut buf_2 = Vec::new();
+        record_2.to_bundle(&mut buf_2).unwrap();
+        assert_eq!(buf_2, buf);
+
+        // A joint key which doesn't match the guardian public keys.
+        let mut json: serde_json::Value = serde_json::from_slice(&buf).unwrap();
+        json["joint_key"]["joint_election_public_key"] =
+            json["guardians"][0]["coefficient_commitments"][0].clone();
+        let buf_3 = serde_json::to_vec(&json).unwrap();
+        let err = ElectionRecord::from_bundle(&mut buf_3.as_slice()).unwrap_err();
+        assert!(err.to_string().contains("joint_key"), "{err:#}");
+
+        // A bundle cut short is reported as such.
+        let err = ElectionRecord::from_bundle(&mut &buf[..buf.len() / 2]).unwrap_err();
+        assert!(
+            format!("{err:#}").contains("the election record bundle JSON ends unexpectedly"),
+            "{err:#}"
+        );
+
+        // Each part is validated as it would be if it were read from its own file.
+        let read_modified = |modify: &dyn Fn(&mut serde_json::Value)| {
+            let mut json: serde_json::Value = serde_json::from_slice(&buf).unwrap();
+            modify(&mut json);
+            let buf = serde_json::to_vec(&json).unwrap();
+            format!(
+                "{:#}",
+                ElectionRecord::from_bundle(&mut buf.as_slice()).unwrap_err()
+            )
+        };
+
+        // Fewer than `k` commitments.
+        let err = read_modified(&|json| {
+            json["guardians"][1]["coefficient_commitments"]
+                .as_array_mut()
+                .unwrap()
+                .pop();
+        });
+        assert!(err.contains("public key of guardian 2"), "{err}");
+
+        let err = read_modified(&|json| {
+            json["tally"]["contests"].as_array_mut().unwrap().pop();
+        });
+        assert!(err.contains("ElectionRecord tally"), "{err}");
+
+        let err = read_modified(&|json| json["manifest"]["label"] = "".into());
+        assert!(err.contains("ElectionRecord manifest"), "{err}");
+
+        // A generator of the same subgroup, which passes the structural checks, but is not
+        // the standard one.
+        let err = read_modified(&|json| {
+            let mut parameters: ElectionParameters =
+                serde_json::from_value(json["parameters"].clone()).unwrap();
+            let fixed_parameters = &mut parameters.fixed_parameters;
+            fixed_parameters.g = fixed_parameters
+                .g
+                .modpow(&2_u8.into(), fixed_parameters.p.as_ref());
+            fixed_parameters.validate_structural().unwrap();
+            json["parameters"] = serde_json::to_value(&parameters).unwrap();
+        });
+        assert!(err.contains("ElectionRecord parameters"), "{err}");
+        assert!(err.contains("standard parameters"), "{err}");
+    }
+}
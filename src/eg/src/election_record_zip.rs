@@ -0,0 +1,591 @@
+// Copyright (C) Microsoft Corporation. All rights reserved.
+
+#![deny(clippy::unwrap_used)]
+#![deny(clippy::expect_used)]
+#![deny(clippy::panic)]
+#![deny(clippy::manual_assert)]
+
+//! An [`ElectionRecord`] as a single ZIP file, for distribution.
+//!
+//! The artifacts are stored in the same layout as in the artifacts directory:
+//!
+//! ```text
+//! election_parameters.json
+//! election_manifest_pretty.json
+//! public/guardian_<i>.public_key.json
+//! public/joint_election_public_key.json
+//! record/ballots/ballot.<n>.json       (n counts from 000001, in record order)
+//! record/tally.json                    (if there is a tally)
+//! contents.json
+//! ```
+//!
+//! `contents.json` gives the extended base hash `H_E` of the election and lists every other
+//! file with its SHA-256 hash, so that a damaged file, or one which went missing or was added
+//! along the way, is detected when the ZIP is read. It is not signed, so it does not stop
+//! anyone from altering the record and rewriting it to match. That is what verification is
+//! for: `H_E` is recomputed from the parameters, manifest, and guardian keys in the ZIP and
+//! must match the one in `contents.json`, which the reader should compare with the `H_E`
+//! the election published, and every ballot and the tally are checked against it.
+//!
+//! Entries are stored uncompressed, which every unzip tool can read. The ZIP format itself is
+//! handled by the `zip` crate.
+
+use std::collections::BTreeMap;
+use std::sync::atomic::AtomicBool;
+
+use anyhow::{bail, ensure, Context, Result};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::{
+    artifact_format::{read_to_end_bounded, MAX_ELECTION_RECORD_BYTES},
+    ballot::BallotEncrypted,
+    election_manifest::ElectionManifest,
+    election_parameters::ElectionParameters,
+    election_record::ElectionRecord,
+    guardian_public_key::GuardianPublicKey,
+    hash::HValue,
+    joint_election_public_key::JointElectionPublicKey,
+    tally::Tally,
+    verification::{verify_election_record, RecordContents, VerificationReport},
+};
+
+const PATH_CONTENTS: &str = "contents.json";
+const PATH_PARAMETERS: &str = "election_parameters.json";
+const PATH_MANIFEST: &str = "election_manifest_pretty.json";
+const PATH_JOINT_KEY: &str = "public/joint_election_public_key.json";
+const PATH_TALLY: &str = "record/tally.json";
+const PREFIX_GUARDIAN: &str = "public/guardian_";
+const SUFFIX_GUARDIAN: &str = ".public_key.json";
+const PREFIX_BALLOT: &str = "record/ballots/ballot.";
+
+/// The list of files in the ZIP, written to [`PATH_CONTENTS`].
+#[derive(Serialize, Deserialize)]
+struct ZipContents {
+    /// The extended base hash of the election whose record this is.
+    h_e: HValue,
+
+    files: Vec<ZipContentsEntry>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct ZipContentsEntry {
+    path: String,
+    sha256: HValue,
+}
+
+fn sha256(data: &[u8]) -> HValue {
+    HValue(Sha256::digest(data).into())
+}
+
+fn to_json_pretty<T: Serialize>(value: &T, what: &str) -> Result<Vec<u8>> {
+    let mut v = serde_json::to_vec_pretty(value).with_context(|| format!("Writing {what}"))?;
+    v.push(b'\n');
+    Ok(v)
+}
+
+/// The path of the `n`th ballot, counting from 1.
+fn ballot_path(n: usize) -> String {
+    format!("{PREFIX_BALLOT}{n:06}.json")
+}
+
+/// Returns `n` if `path` is that of the `n`th ballot.
+///
+/// The number is at least six digits, but may be more, so ballots must be put in order by
+/// their numbers rather than their paths.
+fn ballot_number(path: &str) -> Option<usize> {
+    let digits = path.strip_prefix(PREFIX_BALLOT)?.strip_suffix(".json")?;
+    if digits.len() < 6 || !digits.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    digits.parse().ok()
+}
+
+impl ElectionRecord {
+    /// Writes the `ElectionRecord` as a ZIP file, which can be read by
+    /// [`ElectionRecord::read_zip`]. See [`crate::election_record_zip`] for the layout.
+    pub fn write_zip(&self, stdiowrite: &mut dyn std::io::Write) -> Result<()> {
+        let mut files: Vec<(String, Vec<u8>)> = Vec::new();
+
+        let mut v = Vec::new();
+        self.prevoting.parameters.to_stdiowrite(&mut v)?;
+        files.push((PATH_PARAMETERS.to_string(), v));
+
+        let mut v = Vec::new();
+        self.prevoting.manifest.to_stdiowrite_pretty(&mut v)?;
+        files.push((PATH_MANIFEST.to_string(), v));
+
+        for gpk in &self.guardian_public_keys {
+            let mut v = Vec::new();
+            gpk.to_stdiowrite(&mut v)?;
+            files.push((format!("{PREFIX_GUARDIAN}{}{SUFFIX_GUARDIAN}", gpk.i), v));
+        }
+
+        let mut v = Vec::new();
+        self.prevoting.public_key.to_stdiowrite(&mut v)?;
+        files.push((PATH_JOINT_KEY.to_string(), v));
+
+        for (ix0, ballot) in self.ballots.iter().enumerate() {
+            let mut v = Vec::new();
+            ballot.to_stdiowrite(&mut v)?;
+            files.push((ballot_path(ix0 + 1), v));
+        }
+
+        if let Some(tally) = &self.opt_tally {
+            let mut v = Vec::new();
+            tally.to_stdiowrite(&mut v)?;
+            files.push((PATH_TALLY.to_string(), v));
+        }
+
+        let contents = ZipContents {
+            h_e: self.prevoting.hashes_ext.h_e,
+            files: files
+                .iter()
+                .map(|(path, data)| ZipContentsEntry {
+                    path: path.clone(),
+                    sha256: sha256(data),
+                })
+                .collect(),
+        };
+        files.push((
+            PATH_CONTENTS.to_string(),
+            to_json_pretty(&contents, "ZIP contents")?,
+        ));
+
+        let zip = zip_stored::write(&files)?;
+        stdiowrite
+            .write_all(&zip)
+            .context("Writing ElectionRecord ZIP")
+    }
+
+    /// Reads an `ElectionRecord` from a ZIP file written by [`ElectionRecord::write_zip`].
+    ///
+    /// Every file must be listed in `contents.json` with the right hash. Each part of the
+    /// record is then validated as by [`ElectionRecord::from_bundle`], which requires the
+    /// standard fixed parameters, and `H_E` recomputed from them must be the one in
+    /// `contents.json`. The ballots and tally are not verified.
+    pub fn read_zip(stdioread: &mut dyn std::io::Read) -> Result<Self> {
+        let zip = read_to_end_bounded(stdioread, "ElectionRecord ZIP", MAX_ELECTION_RECORD_BYTES)?;
+
+        let mut files = zip_stored::read(&zip)?;
+
+        let contents_bytes = files
+            .remove(PATH_CONTENTS)
+            .with_context(|| format!("ZIP has no {PATH_CONTENTS}"))?;
+        let contents: ZipContents = serde_json::from_slice(&contents_bytes)
+            .with_context(|| format!("Reading {PATH_CONTENTS}"))?;
+
+        ensure!(
+            contents.files.len() == files.len(),
+            "{PATH_CONTENTS} lists {} files, but the ZIP has {}",
+            contents.files.len(),
+            files.len()
+        );
+        for entry in &contents.files {
+            let data = files.get(&entry.path).with_context(|| {
+                format!("{} is listed in {PATH_CONTENTS} but missing", entry.path)
+            })?;
+            ensure!(
+                sha256(data) == entry.sha256,
+                "{} does not match its hash in {PATH_CONTENTS}",
+                entry.path
+            );
+        }
+
+        let mut take = |path: &str| {
+            files
+                .remove(path)
+                .with_context(|| format!("ZIP has no {path}"))
+        };
+
+        // The parts are validated together by `from_parts`, since most of them are validated
+        // against the parameters. The fixed parameters must be the standard ones.
+        let parameters = ElectionParameters::from_bytes(&take(PATH_PARAMETERS)?)
+            .with_context(|| format!("Reading {PATH_PARAMETERS}"))?;
+        let manifest =
+            ElectionManifest::from_stdioread_validated(&mut take(PATH_MANIFEST)?.as_slice())
+                .with_context(|| format!("Reading {PATH_MANIFEST}"))?;
+        let joint_key =
+            JointElectionPublicKey::from_stdioread(&mut take(PATH_JOINT_KEY)?.as_slice())
+                .with_context(|| format!("Reading {PATH_JOINT_KEY}"))?;
+        let opt_tally = files
+            .remove(PATH_TALLY)
+            .map(|data| Tally::from_stdioread(&mut data.as_slice()))
+            .transpose()
+            .with_context(|| format!("Reading {PATH_TALLY}"))?;
+
+        // The remaining files are guardian keys and ballots.
+        let mut guardian_public_keys = Vec::new();
+        let mut numbered_ballots = Vec::new();
+        for (path, data) in files {
+            if path.starts_with(PREFIX_GUARDIAN) && path.ends_with(SUFFIX_GUARDIAN) {
+                let gpk = GuardianPublicKey::from_stdioread(&mut data.as_slice())
+                    .with_context(|| format!("Reading {path}"))?;
+                guardian_public_keys.push(gpk);
+            } else if let Some(n) = ballot_number(&path) {
+                let ballot = BallotEncrypted::from_stdioread(&mut data.as_slice())
+                    .with_context(|| format!("Reading {path}"))?;
+                numbered_ballots.push((n, ballot));
+            } else {
+                bail!("Unexpected file in ZIP: {path}");
+            }
+        }
+
+        guardian_public_keys.sort_by_key(|gpk| gpk.i);
+
+        numbered_ballots.sort_by_key(|&(n, _)| n);
+        for (&(n, _), expected_n) in numbered_ballots.iter().zip(1..) {
+            ensure!(
+                n == expected_n,
+                "Ballots are not numbered consecutively from 1: expected {}, found {}",
+                ballot_path(expected_n),
+                ballot_path(n)
+            );
+        }
+        let ballots = numbered_ballots
+            .into_iter()
+            .map(|(_, ballot)| ballot)
+            .collect();
+
+        let record = ElectionRecord::from_parts(
+            parameters,
+            manifest,
+            guardian_public_keys,
+            &joint_key,
+            ballots,
+            opt_tally,
+        )?;
+
+        ensure!(
+            record.prevoting.hashes_ext.h_e == contents.h_e,
+            "{PATH_CONTENTS} is for the election with H_E {}, but the record is of {}",
+            contents.h_e,
+            record.prevoting.hashes_ext.h_e
+        );
+
+        Ok(record)
+    }
+
+    /// Reads an `ElectionRecord` from a ZIP file as [`ElectionRecord::read_zip`] does, then
+    /// verifies the joint election public key, ballots, and tally with
+    /// [`verify_election_record`].
+    pub fn verify_zip(stdioread: &mut dyn std::io::Read) -> Result<VerificationReport> {
+        let record = Self::read_zip(stdioread)?;
+
+        let contents = RecordContents {
+            header: &record.prevoting,
+            opt_guardian_public_keys: Some(&record.guardian_public_keys),
+            opt_ballots: Some(&record.ballots),
+            opt_tally: record.opt_tally.as_ref(),
+            ..RecordContents::new(&record.prevoting)
+        };
+
+        verify_election_record(&contents, &AtomicBool::new(false))
+    }
+}
+
+/// Writing and reading ZIP files whose entries are stored without compression, with the
+/// `zip` crate.
+mod zip_stored {
+    use std::io::{Cursor, Read, Write};
+
+    use zip::{write::SimpleFileOptions, CompressionMethod, DateTime, ZipArchive, ZipWriter};
+
+    use super::*;
+
+    /// Returns a ZIP file containing `files`, in order.
+    ///
+    /// No timestamps are recorded, so that the same files always give the same ZIP.
+    pub(super) fn write(files: &[(String, Vec<u8>)]) -> Result<Vec<u8>> {
+        let options = SimpleFileOptions::default()
+            .compression_method(CompressionMethod::Stored)
+            .last_modified_time(DateTime::default())
+            .large_file(false);
+
+        let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+        for (path, data) in files {
+            zip.start_file(path.as_str(), options)
+                .and_then(|()| zip.write_all(data).map_err(Into::into))
+                .with_context(|| format!("Writing {path} to ZIP"))?;
+        }
+
+        Ok(zip.finish().context("Writing ZIP")?.into_inner())
+    }
+
+    /// Returns the files in `zip`, by path. Fails unless every file is stored without
+    /// compression and matches its CRC-32.
+    ///
+    /// If a path appears more than once, the `zip` crate keeps only the last entry with it.
+    /// That is the one which must match its hash in `contents.json`.
+    pub(super) fn read(zip: &[u8]) -> Result<BTreeMap<String, Vec<u8>>> {
+        let mut archive = ZipArchive::new(Cursor::new(zip)).context("Not a ZIP file")?;
+
+        let mut files = BTreeMap::new();
+        for ix in 0..archive.len() {
+            let mut file = archive
+                .by_index(ix)
+                .with_context(|| format!("Reading ZIP entry {ix}"))?;
+            let path = file.name().to_string();
+
+            ensure!(
+                file.compression() == CompressionMethod::Stored,
+                "{path} is compressed, which is not supported"
+            );
+
+            // Stored data is no larger than the ZIP, which is already in memory.
+            let mut data = Vec::new();
+            file.read_to_end(&mut data)
+                .with_context(|| format!("{path} is damaged"))?;
+
+            files.insert(path, data);
+        }
+
+        Ok(files)
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod test {
+    use super::*;
+    use crate::{
+        contest_selection::ContestSelection, device::Device, election_manifest::ContestIndex,
+        example_election::ExampleElection, vec1::Vec1,
+    };
+    use util::csprng::Csprng;
+
+    fn small_record() -> ElectionRecord {
+        let mut csprng = Csprng::new(b"election_record_zip");
+
+        let ExampleElection {
+            guardian_public_keys,
+            pvd: prevoting,
+            ..
+        } = ExampleElection::with_first_contests(&mut csprng, 1).unwrap();
+
+        let device = Device::new("test", prevoting.clone());
+        let ballots = (0..2u8)
+            .map(|n| {
+                let contest_ix = ContestIndex::from_one_based_index(1).unwrap();
+                let contest = prevoting.manifest.contests.get(contest_ix).unwrap();
+                let mut ctest_selections = Vec1::new();
+                ctest_selections
+                    .try_push(ContestSelection::new_pick_random(
+                        &mut csprng,
+                        contest.selection_limit,
+                        contest.options.len(),
+                    ))
+                    .unwrap();
+                let mut ballot = BallotEncrypted::new_from_selections(
+                    &device,
+                    &mut csprng,
+                    &[n; 32],
+                    &ctest_selections,
+                )
+                .unwrap();
+                ballot.cast().unwrap();
+                ballot
+            })
+            .collect::<Vec<_>>();
+
+        let tally = Tally::from_ballots(
+            &prevoting.parameters.fixed_parameters,
+            &prevoting.manifest,
+            &ballots,
+        )
+        .unwrap();
+
+        ElectionRecord {
+            prevoting,
+            guardian_public_keys,
+            ballots,
+            opt_tally: Some(tally),
+        }
+    }
+
+    #[test]
+    fn test_zip_round_trip() {
+        let record = small_record();
+
+        let mut zip = Vec::new();
+        record.write_zip(&mut zip).unwrap();
+
+        let record_2 = ElectionRecord::read_zip(&mut zip.as_slice()).unwrap();
+        assert!(record_2.prevoting.hashes_ext == record.prevoting.hashes_ext);
+        assert_eq!(record_2.guardian_public_keys.len(), 5);
+        assert_eq!(record_2.ballots.len(), 2);
+        for (ballot, ballot_2) in record.ballots.iter().zip(&record_2.ballots) {
+            assert_eq!(ballot.ballot_id(), ballot_2.ballot_id());
+        }
+        assert!(record_2.opt_tally == record.opt_tally);
+
+        // The same record always gives the same ZIP.
+        let mut zip_2 = Vec::new();
+        record_2.write_zip(&mut zip_2).unwrap();
+        assert_eq!(zip_2, zip);
+
+        let report = ElectionRecord::verify_zip(&mut zip.as_slice()).unwrap();
+        assert!(report.is_success(), "{:?}", report.failures);
+    }
+
+    #[test]
+    fn test_zip_tampering() {
+        let mut zip = Vec::new();
+        small_record().write_zip(&mut zip).unwrap();
+        let files = zip_stored::read(&zip).unwrap();
+        assert!(files.contains_key(PATH_CONTENTS));
+        assert!(files.contains_key("public/guardian_5.public_key.json"));
+        assert!(files.contains_key("record/ballots/ballot.000002.json"));
+
+        let rezip = |files: &BTreeMap<String, Vec<u8>>| {
+            let files = files
+                .iter()
+                .map(|(path, data)| (path.clone(), data.clone()))
+                .collect::<Vec<_>>();
+            zip_stored::write(&files).unwrap()
+        };
+        let read_err =
+            |zip: &[u8]| format!("{:#}", ElectionRecord::read_zip(&mut &zip[..]).unwrap_err());
+
+        // A byte changed in place is caught by the CRC.
+        let mut damaged = zip.clone();
+        damaged[100] ^= 1;
+        let e = read_err(&damaged);
+        assert!(e.contains("is damaged: Invalid checksum"), "{e}");
+
+        // A file altered consistently with its CRC is caught by contents.json.
+        let mut altered = files.clone();
+        let ballot = altered
+            .get_mut("record/ballots/ballot.000001.json")
+            .unwrap();
+        let pos = ballot.windows(4).position(|w| w == b"Cast").unwrap();
+        ballot.drain(pos..pos + 2);
+        let e = read_err(&rezip(&altered));
+        assert!(e.contains("does not match its hash"), "{e}");
+
+        // As are files removed or added.
+        let mut removed = files.clone();
+        removed.remove("record/ballots/ballot.000002.json");
+        let e = read_err(&rezip(&removed));
+        assert!(e.contains("lists 11 files, but the ZIP has 10"), "{e}");
+
+        let mut added = files.clone();
+        added.insert("extra.txt".to_string(), b"extra".to_vec());
+        let e = read_err(&rezip(&added));
+        assert!(e.contains("ZIP has 12"), "{e}");
+
+        let e = read_err(b"not a zip file");
+        assert!(e.contains("Not a ZIP file"), "{e}");
+
+        // Renaming files in contents.json to match is not enough to reorder the ballots.
+        let rename = |files: &mut BTreeMap<String, Vec<u8>>, from: &str, to: &str| {
+            let data = files.remove(from).unwrap();
+            files.insert(to.to_string(), data);
+            let contents = files.get_mut(PATH_CONTENTS).unwrap();
+            *contents = String::from_utf8(contents.clone())
+                .unwrap()
+                .replace(from, to)
+                .into_bytes();
+        };
+        let mut gap = files.clone();
+        rename(&mut gap, &ballot_path(2), &ballot_path(3));
+        let e = read_err(&rezip(&gap));
+        assert!(
+            e.contains("expected record/ballots/ballot.000002.json"),
+            "{e}"
+        );
+
+        let mut swapped = files.clone();
+        rename(&mut swapped, &ballot_path(1), "temp");
+        rename(&mut swapped, &ballot_path(2), &ballot_path(1));
+        rename(&mut swapped, "temp", &ballot_path(2));
+        let record = ElectionRecord::read_zip(&mut rezip(&swapped).as_slice()).unwrap();
+        let report = ElectionRecord::verify_zip(&mut rezip(&swapped).as_slice()).unwrap();
+        assert_eq!(record.ballots.len(), 2);
+        assert!(report.is_success(), "{:?}", report.failures);
+
+        // The contents must be for the election whose record it is.
+        let mut other_election = files.clone();
+        let contents = other_election.get_mut(PATH_CONTENTS).unwrap();
+        let mut json: serde_json::Value = serde_json::from_slice(contents).unwrap();
+        json["h_e"] = serde_json::to_value(HValue([0; 32])).unwrap();
+        *contents = serde_json::to_vec(&json).unwrap();
+        let e = read_err(&rezip(&other_election));
+        assert!(e.contains("is for the election with H_E"), "{e}");
+
+        // Parameters which pass the structural checks, with a generator of the same subgroup,
+        // but which are not the standard ones, listed in contents.json with their new hash.
+        let mut nonstandard = files.clone();
+        let mut parameters = ElectionParameters::from_bytes(&files[PATH_PARAMETERS]).unwrap();
+        let fixed_parameters = &mut parameters.fixed_parameters;
+        fixed_parameters.g = fixed_parameters
+            .g
+            .modpow(&2_u8.into(), fixed_parameters.p.as_ref());
+        fixed_parameters.validate_structural().unwrap();
+        let mut v = Vec::new();
+        parameters.to_stdiowrite(&mut v).unwrap();
+        let contents = nonstandard.get_mut(PATH_CONTENTS).unwrap();
+        let mut zip_contents: ZipContents = serde_json::from_slice(contents).unwrap();
+        for entry in &mut zip_contents.files {
+            if entry.path == PATH_PARAMETERS {
+                entry.sha256 = sha256(&v);
+            }
+        }
+        *contents = serde_json::to_vec(&zip_contents).unwrap();
+        nonstandard.insert(PATH_PARAMETERS.to_string(), v);
+        let e = read_err(&rezip(&nonstandard));
+        assert!(e.contains(&format!("Reading {PATH_PARAMETERS}")), "{e}");
+        assert!(
+            e.contains("g does not match the standard parameters"),
+            "{e}"
+        );
+        assert!(ElectionRecord::verify_zip(&mut rezip(&nonstandard).as_slice()).is_err());
+    }
+
+    #[test]
+    fn test_zip_malformed() {
+        let mut zip = Vec::new();
+        small_record().write_zip(&mut zip).unwrap();
+
+        // The ZIP cut short anywhere, including in its central directory.
+        for len in (0..zip.len()).step_by(97).chain(zip.len() - 30..zip.len()) {
+            assert!(
+                ElectionRecord::read_zip(&mut &zip[..len]).is_err(),
+                "{len} of {}",
+                zip.len()
+            );
+        }
+
+        // Every entry of the central directory pointed at the data of the first file.
+        let sig_central_header = 0x0201_4b50_u32.to_le_bytes();
+        let central_header_positions = zip
+            .windows(4)
+            .enumerate()
+            .filter(|(_, w)| *w == sig_central_header)
+            .map(|(pos, _)| pos)
+            .collect::<Vec<_>>();
+        assert_eq!(central_header_positions.len(), 12);
+        let mut overlapping = zip.clone();
+        for pos in central_header_positions {
+            overlapping[pos + 42..pos + 46].copy_from_slice(&0u32.to_le_bytes());
+        }
+        assert!(ElectionRecord::read_zip(&mut overlapping.as_slice()).is_err());
+    }
+
+    #[test]
+    fn test_ballot_number() {
+        assert_eq!(ballot_number(&ballot_path(1)), Some(1));
+        assert_eq!(ballot_number(&ballot_path(999_999)), Some(999_999));
+        assert_eq!(ballot_number(&ballot_path(1_000_000)), Some(1_000_000));
+        assert_eq!(ballot_number("record/ballots/ballot.1.json"), None);
+        assert_eq!(ballot_number("record/ballots/ballot.00000x.json"), None);
+        assert_eq!(ballot_number("record/ballots/ballot.+00001.json"), None);
+
+        // Past a million, ballots sort by number, not by path.
+        let mut paths = [ballot_path(1_000_000), ballot_path(999_999)];
+        paths.sort();
+        assert_eq!(paths[0], ballot_path(1_000_000));
+        paths.sort_by_key(|path| ballot_number(path));
+        assert_eq!(paths[0], ballot_path(999_999));
+    }
+}
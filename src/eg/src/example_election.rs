@@ -0,0 +1,211 @@
+// Copyright (C) Microsoft Corporation. All rights reserved.
+
+#![deny(clippy::unwrap_used)]
+#![deny(clippy::expect_used)]
+#![deny(clippy::panic)]
+#![deny(clippy::manual_assert)]
+
+use anyhow::Result;
+
+use util::csprng::Csprng;
+
+use crate::{
+    ballot::BallotEncrypted,
+    ballot_encryptor::{BallotEncryptor, BallotPlaintext},
+    device::Device,
+    election_manifest::ElectionManifest,
+    election_parameters::ElectionParameters,
+    election_record::PreVotingData,
+    example_election_manifest::example_election_manifest_first_contests,
+    example_election_parameters::example_election_parameters,
+    guardian_key_share::GuardianKeyShare,
+    guardian_public_key::GuardianPublicKey,
+    guardian_secret_key::GuardianSecretKey,
+};
+
+/// An example election with keys and key shares for every guardian, as tests and benchmarks
+/// need.
+///
+/// Everything else is computed from the keys, so that ballots can be encrypted to the joint
+/// election public key and decrypted again.
+#[derive(Debug, Clone)]
+pub struct ExampleElection {
+    /// The secret key of every guardian, in order of guardian number.
+    pub guardian_secret_keys: Vec<GuardianSecretKey>,
+
+    /// The public key of every guardian, in order of guardian number.
+    pub guardian_public_keys: Vec<GuardianPublicKey>,
+
+    /// The key share of every guardian, in order of guardian number, as if the guardians had
+    /// exchanged shares.
+    pub guardian_key_shares: Vec<GuardianKeyShare>,
+
+    /// The pre-voting data computed from the manifest, parameters, and public keys.
+    pub pvd: PreVotingData,
+}
+
+impl ExampleElection {
+    /// Generates a secret key for each of the `n` guardians of `election_parameters`, and
+    /// computes the pre-voting data of an election with `election_manifest`.
+    pub fn generate(
+        csprng: &mut Csprng,
+        election_parameters: ElectionParameters,
+        election_manifest: ElectionManifest,
+    ) -> Result<Self> {
+        let (guardian_secret_keys, guardian_public_keys) =
+            Self::generate_guardian_keys(csprng, &election_parameters)?;
+        let guardian_key_shares = GuardianKeyShare::compute_all_from_secret_keys(
+            &election_parameters,
+            &guardian_secret_keys,
+        );
+
+        let pvd = PreVotingData::compute(
+            election_manifest,
+            election_parameters,
+            &guardian_public_keys,
+        )?;
+
+        Ok(ExampleElection {
+            guardian_secret_keys,
+            guardian_public_keys,
+            guardian_key_shares,
+            pvd,
+        })
+    }
+
+    /// Generates a secret key for each of the `n` guardians of `election_parameters`, and
+    /// returns them in order of guardian number along with their public keys.
+    ///
+    /// For when just the keys are needed, without the rest of an election.
+    pub fn generate_guardian_keys(
+        csprng: &mut Csprng,
+        election_parameters: &ElectionParameters,
+    ) -> Result<(Vec<GuardianSecretKey>, Vec<GuardianPublicKey>)> {
+        let guardian_secret_keys = election_parameters
+            .varying_parameters
+            .each_guardian_i()
+            .map(|i| GuardianSecretKey::generate(csprng, election_parameters, i, None))
+            .collect::<Result<Vec<_>>>()?;
+        let guardian_public_keys = guardian_secret_keys
+            .iter()
+            .map(GuardianSecretKey::make_public_key)
+            .collect();
+        Ok((guardian_secret_keys, guardian_public_keys))
+    }
+
+    /// [`generate`](Self::generate) with the example election parameters and the first
+    /// `num_contests` contests of the example manifest, see
+    /// [`example_election_manifest_first_contests`].
+    ///
+    /// Every contest adds ciphertexts and proofs to each ballot, which are slow to compute and
+    /// verify, so tests which need only a contest or two use this to stay quick.
+    pub fn with_first_contests(csprng: &mut Csprng, num_contests: usize) -> Result<Self> {
+        Self::generate(
+            csprng,
+            example_election_parameters(),
+            example_election_manifest_first_contests(num_contests),
+        )
+    }
+
+    /// Encrypts a ballot for each of `ballot_plaintexts`, given as the JSON of a
+    /// [`BallotPlaintext`], in order, with a [`BallotEncryptor`] on a device of this election.
+    /// The ballots are cast, so that they are counted when tallied.
+    pub fn encrypt_ballots(
+        &self,
+        csprng: &mut Csprng,
+        ballot_plaintexts: &[&str],
+    ) -> Result<Vec<BallotEncrypted>> {
+        let device = Device::new("example", self.pvd.clone());
+        let mut encryptor = BallotEncryptor::new(&device);
+        ballot_plaintexts
+            .iter()
+            .map(|json| {
+                let ballot_plaintext = BallotPlaintext::from_stdioread(&mut json.as_bytes())?;
+                let mut ballot = encryptor.encrypt(csprng, &ballot_plaintext)?;
+                ballot.cast()?;
+                Ok(ballot)
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod test {
+    use super::*;
+    use crate::{ballot_style::BallotStyleIndex, verification::verify_ballot};
+
+    #[test]
+    fn test_example_election() {
+        let mut csprng = Csprng::new(b"test_example_election");
+
+        let example_election = ExampleElection::with_first_contests(&mut csprng, 1).unwrap();
+        let pvd = &example_election.pvd;
+
+        assert_eq!(pvd.manifest.contests.len(), 1);
+        for ballot_style_ix in pvd.manifest.ballot_styles.indices() {
+            let ballot_style = pvd.manifest.ballot_styles.get(ballot_style_ix).unwrap();
+            assert!(ballot_style
+                .contests
+                .iter()
+                .all(|contest_ix| contest_ix.get_one_based_usize() == 1));
+        }
+        pvd.manifest.validate().unwrap();
+
+        let n = pvd.parameters.varying_parameters.n.as_quantity();
+        assert_eq!(example_election.guardian_secret_keys.len(), n);
+        assert_eq!(example_election.guardian_key_shares.len(), n);
+        for (i, ((secret_key, public_key), key_share)) in
+            pvd.parameters.varying_parameters.each_guardian_i().zip(
+                example_election
+                    .guardian_secret_keys
+                    .iter()
+                    .zip(&example_election.guardian_public_keys)
+                    .zip(&example_election.guardian_key_shares),
+            )
+        {
+            assert_eq!(secret_key.i, i);
+            assert_eq!(public_key.i, i);
+            assert_eq!(key_share.i, i);
+            assert_eq!(
+                secret_key.coefficient_commitments,
+                public_key.coefficient_commitments
+            );
+        }
+
+        pvd.validate_with_guardian_public_keys(&example_election.guardian_public_keys)
+            .unwrap();
+    }
+
+    #[test]
+    fn test_encrypt_ballots() {
+        let mut csprng = Csprng::new(b"test_encrypt_ballots");
+
+        let example_election = ExampleElection::with_first_contests(&mut csprng, 1).unwrap();
+        let pvd = &example_election.pvd;
+
+        let ballots = example_election
+            .encrypt_ballots(
+                &mut csprng,
+                &[
+                    r#"{ "contests": [ { "vote": [1, 0] } ] }"#,
+                    r#"{ "ballot_style": 2, "contests": [ { "vote": [0, 1] } ] }"#,
+                ],
+            )
+            .unwrap();
+        assert_eq!(ballots.len(), 2);
+        assert_eq!(ballots[0].opt_ballot_style, None);
+        assert_eq!(
+            ballots[1].opt_ballot_style,
+            Some(BallotStyleIndex::from_one_based_index(2).unwrap())
+        );
+        for ballot in &ballots {
+            verify_ballot(pvd, ballot).unwrap();
+        }
+
+        // A plaintext which doesn't match the manifest is an error.
+        assert!(example_election
+            .encrypt_ballots(&mut csprng, &[r#"{ "contests": [ { "vote": [1, 1] } ] }"#])
+            .is_err());
+    }
+}
@@ -9,222 +9,406 @@
 // the point that we can prove this at compile time, and implement `From` instead.
 #![allow(clippy::unwrap_used)]
 
-use std::collections::BTreeSet;
+use std::collections::{BTreeSet, HashSet};
+
+use util::csprng::Csprng;
 
 use crate::{
     ballot_style::BallotStyle,
-    election_manifest::{Contest, ContestIndex, ContestOption, ElectionManifest},
+    election_manifest::{
+        Contest, ContestIndex, ContestKind, ContestOption, ElectionManifest, VoteVariation,
+        MAX_OPTIONS_PER_CONTEST,
+    },
+    election_manifest_builder::ElectionManifestBuilder,
     vec1::Vec1,
 };
 
 pub fn example_election_manifest() -> ElectionManifest {
-    let referendum_options: Vec1<ContestOption> = [
-        ContestOption {
-            label: "Prō".to_string(),
-        },
-        ContestOption {
-            label: "Ĉontrá".to_string(),
-        },
-    ]
-    .try_into()
-    .unwrap();
-
-    let contests = [
-        // Contest index 1:
-        Contest {
-            label: "For President and Vice President of The United Realms of Imaginaria"
-                .to_string(),
-            selection_limit: 1,
-            options: [
-                ContestOption {
-                    label:
-                        "Thündéroak, Vâlêriana D.\nËverbright, Ålistair R. Jr.\n(Ætherwïng)"
-                            .to_string(),
-                },
-                ContestOption {
-                    label: "Stârførge, Cássánder A.\nMøonfire, Célestïa L.\n(Crystâlheärt)".to_string(),
-                },
-            ].try_into().unwrap(),
-        },
-        // Contest index 2:
-        Contest {
-            label: "Minister of Arcane Sciences".to_string(),
-            selection_limit: 1,
-            options: [
-                ContestOption {
-                    label: "Élyria Moonshadow\n(Crystâlheärt)".to_string(),
-                },
-                ContestOption {
-                    label: "Archímedes Darkstone\n(Ætherwïng)".to_string(),
-                },
-                ContestOption {
-                    label: "Seraphína Stormbinder\n(Independent)".to_string(),
-                },
-                ContestOption {
-                    label: "Gávrïel Runëbørne\n(Stärsky)".to_string(),
-                },
-            ].try_into().unwrap(),
-        },
-        // Contest index 3:
-        Contest {
-            label: "Minister of Elemental Resources".to_string(),
-            selection_limit: 1,
-            options: [
-                ContestOption {
-                    label: "Tïtus Stormforge\n(Ætherwïng)".to_string(),
-                },
-                ContestOption {
-                    label: "Fæ Willowgrove\n(Crystâlheärt)".to_string(),
-                },
-                ContestOption {
-                    label: "Tèrra Stonebinder\n(Independent)".to_string(),
-                },
-            ].try_into().unwrap(),
-        },
-        // Contest index 4:
-        Contest {
-            label: "Minister of Dance".to_string(),
-            selection_limit: 1,
-            options: [
-                ContestOption {
-                    label: "Äeliana Sunsong\n(Crystâlheärt)".to_string(),
-                },
-                ContestOption {
-                    label: "Thâlia Shadowdance\n(Ætherwïng)".to_string(),
-                },
-                ContestOption {
-                    label: "Jasper Moonstep\n(Stärsky)".to_string(),
-                },
-            ].try_into().unwrap(),
-        },
-        // Contest index 5:
-        Contest {
-            label: "Gränd Cøuncil of Arcáne and Technomägical Affairs".to_string(),
-            selection_limit: 3,
-            options: [
-                ContestOption {
-                    label: "Ìgnatius Gearsøul\n(Crystâlheärt)".to_string(),
-                },
-                ContestOption {
-                    label: "Èlena Wîndwhisper\n(Technocrat)".to_string(),
-                },
-                ContestOption {
-                    label: "Bërnard Månesworn\n(Ætherwïng)".to_string(),
-                },
-                ContestOption {
-                    label: "Èmeline Glîmmerwillow\n(Ætherwïng)".to_string(),
-                },
-                ContestOption {
-                    label: "Nikólai Thunderstrîde\n(Independent)".to_string(),
-                },
-                ContestOption {
-                    label: "Lïliana Fîrestone\n(Pęacemaker)".to_string(),
-                },
-                ContestOption {
-                    label: "Émeric Crystálgaze\n(Førestmíst)".to_string(),
-                },
-                ContestOption {
-                    label: "Séraphine Lùmenwing\n(Stärsky)".to_string(),
-                },
-                ContestOption {
-                    label: "Rãfael Stëamheart\n(Ætherwïng)".to_string(),
-                },
-                ContestOption {
-                    label: "Océane Tidecaller\n(Pęacemaker)".to_string(),
-                },
-                ContestOption {
-                    label: "Elysêa Shadowbinder\n(Independent)".to_string(),
-                },
-            ].try_into().unwrap(),
-        },
-        // Contest index 6:
-        Contest {
-            label: "Proposed Amendment No. 1\nEqual Representation for Technological and Magical Profeſsions".to_string(),
-            selection_limit: 1,
-            options: [
-                ContestOption {
-                    label: "For".to_string(),
-                },
-                ContestOption {
-                    label: "Against".to_string(),
-                },
-            ].try_into().unwrap(),
-        },
-        // Contest index 7:
-        Contest {
-            label: "Privacy Protection in Techno-Magical Communications Act".to_string(),
-            selection_limit: 1,
-            options: referendum_options.clone(),
-        },
-        // Contest index 8:
-        Contest {
-            label: "Public Transport Modernization and Enchantment Proposal".to_string(),
-            selection_limit: 1,
-            options: referendum_options.clone(),
-        },
-        // Contest index 9:
-        Contest {
-            label: "Renewable Ætherwind Infrastructure Initiative".to_string(),
-            selection_limit: 1,
-            options: referendum_options,
-        },
-        // Contest index 10:
-        Contest {
-            label: "For Librarian-in-Chief of Smoothstone County".to_string(),
-            selection_limit: 1,
-            options: [
-                ContestOption {
-                    label: "Élise Planetes".to_string(),
-                },
-                ContestOption {
-                    label: "Théodoric Inkdrifter".to_string(),
-                },
-            ].try_into().unwrap(),
-        },
-        // Contest index 11:
-        Contest {
-            label: "Silvërspîre County Register of Deeds Sébastian Moonglôw to be retained"
-                .to_string(),
-            selection_limit: 1,
-            options: [
-                ContestOption {
-                    label: "Retain".to_string(),
-                },
-                ContestOption {
-                    label: "Remove".to_string(),
-                },
-            ].try_into().unwrap(),
-        },
-    ].try_into().unwrap();
-
-    let ballot_styles = [
-        // Ballot style index 1:
-        BallotStyle {
-            label: "Smoothstone County Ballot".to_string(),
-            contests: BTreeSet::from(
-                [
-                    1u32, 2, 3, 4, 5, 6, 7, 8, 9, 10, // missing 11
-                ]
-                .map(|ix1| ContestIndex::from_one_based_index(ix1).unwrap()),
-            ),
-        },
-        // Ballot style index 2:
-        BallotStyle {
-            label: "Silvërspîre County Ballot".to_string(),
-            contests: BTreeSet::from(
-                [
-                    1u32, 2, 3, 4, 5, 6, 7, 8, 9, 11, // missing 10
-                ]
-                .map(|ix1| ContestIndex::from_one_based_index(ix1).unwrap()),
-            ),
-        },
-    ]
-    .try_into()
-    .unwrap();
+    let referendum_options = ["Prō", "Ĉontrá"];
+
+    let mut builder =
+        ElectionManifestBuilder::new("General Election - The United Realms of Imaginaria");
+
+    // Contest index 1:
+    builder = builder
+        .add_contest(
+            "For President and Vice President of The United Realms of Imaginaria",
+            1,
+        )
+        .add_option("Thündéroak, Vâlêriana D.\nËverbright, Ålistair R. Jr.\n(Ætherwïng)")
+        .add_option("Stârførge, Cássánder A.\nMøonfire, Célestïa L.\n(Crystâlheärt)");
+
+    // Contest index 2:
+    builder = builder
+        .add_contest("Minister of Arcane Sciences", 1)
+        .add_option("Élyria Moonshadow\n(Crystâlheärt)")
+        .add_option("Archímedes Darkstone\n(Ætherwïng)")
+        .add_option("Seraphína Stormbinder\n(Independent)")
+        .add_option("Gávrïel Runëbørne\n(Stärsky)");
+
+    // Contest index 3:
+    builder = builder
+        .add_contest("Minister of Elemental Resources", 1)
+        .add_option("Tïtus Stormforge\n(Ætherwïng)")
+        .add_option("Fæ Willowgrove\n(Crystâlheärt)")
+        .add_option("Tèrra Stonebinder\n(Independent)");
+
+    // Contest index 4:
+    builder = builder
+        .add_contest("Minister of Dance", 1)
+        .add_option("Äeliana Sunsong\n(Crystâlheärt)")
+        .add_option("Thâlia Shadowdance\n(Ætherwïng)")
+        .add_option("Jasper Moonstep\n(Stärsky)");
+
+    // Contest index 5:
+    builder = builder
+        .add_contest("Gränd Cøuncil of Arcáne and Technomägical Affairs", 3)
+        .add_option("Ìgnatius Gearsøul\n(Crystâlheärt)")
+        .add_option("Èlena Wîndwhisper\n(Technocrat)")
+        .add_option("Bërnard Månesworn\n(Ætherwïng)")
+        .add_option("Èmeline Glîmmerwillow\n(Ætherwïng)")
+        .add_option("Nikólai Thunderstrîde\n(Independent)")
+        .add_option("Lïliana Fîrestone\n(Pęacemaker)")
+        .add_option("Émeric Crystálgaze\n(Førestmíst)")
+        .add_option("Séraphine Lùmenwing\n(Stärsky)")
+        .add_option("Rãfael Stëamheart\n(Ætherwïng)")
+        .add_option("Océane Tidecaller\n(Pęacemaker)")
+        .add_option("Elysêa Shadowbinder\n(Independent)");
+
+    // Contest index 6:
+    builder = builder
+        .add_contest(
+            "Proposed Amendment No. 1\nEqual Representation for Technological and Magical Profeſsions",
+            1,
+        )
+        .kind(ContestKind::Amendment)
+        .add_option("For")
+        .add_option("Against");
+
+    // Contest index 7:
+    builder = builder
+        .add_contest("Privacy Protection in Techno-Magical Communications Act", 1)
+        .kind(ContestKind::Referendum);
+    for label in referendum_options {
+        builder = builder.add_option(label);
+    }
+
+    // Contest index 8:
+    builder = builder
+        .add_contest("Public Transport Modernization and Enchantment Proposal", 1)
+        .kind(ContestKind::Referendum);
+    for label in referendum_options {
+        builder = builder.add_option(label);
+    }
+
+    // Contest index 9:
+    builder = builder
+        .add_contest("Renewable Ætherwind Infrastructure Initiative", 1)
+        .kind(ContestKind::Referendum);
+    for label in referendum_options {
+        builder = builder.add_option(label);
+    }
+
+    // Contest index 10:
+    builder = builder
+        .add_contest("For Librarian-in-Chief of Smoothstone County", 1)
+        .add_option("Élise Planetes")
+        .add_option("Théodoric Inkdrifter");
+
+    // Contest index 11:
+    builder = builder
+        .add_contest(
+            "Silvërspîre County Register of Deeds Sébastian Moonglôw to be retained",
+            1,
+        )
+        .kind(ContestKind::Retention)
+        .add_option("Retain")
+        .add_option("Remove");
+
+    builder
+        // Ballot style index 1, missing 11:
+        .add_ballot_style("Smoothstone County Ballot", [1, 2, 3, 4, 5, 6, 7, 8, 9, 10])
+        // Ballot style index 2, missing 10:
+        .add_ballot_style("Silvërspîre County Ballot", [1, 2, 3, 4, 5, 6, 7, 8, 9, 11])
+        .build()
+        .unwrap()
+}
+
+/// The [`example_election_manifest`] with only its first `num_contests` contests, and every
+/// ballot style limited to those.
+///
+/// Encrypting or decrypting a ballot of this is much quicker than of the whole manifest.
+pub fn example_election_manifest_first_contests(num_contests: usize) -> ElectionManifest {
+    let mut election_manifest = example_election_manifest();
+    election_manifest.contests.truncate(num_contests);
+    for ballot_style_ix in election_manifest.ballot_styles.indices() {
+        // `unwrap()` is justified here because the index comes from the same collection.
+        let ballot_style = election_manifest
+            .ballot_styles
+            .get_mut(ballot_style_ix)
+            .unwrap();
+        ballot_style
+            .contests
+            .retain(|contest_ix| contest_ix.get_one_based_usize() <= num_contests);
+    }
+    election_manifest
+}
+
+const SEEDED_GIVEN_NAMES: &[&str] = &[
+    "Ålistair",
+    "Béatrix",
+    "Cássánder",
+    "Dörothéa",
+    "Élowen",
+    "Fínnegan",
+    "Gwëndolyn",
+    "Hålvard",
+    "Ïsolde",
+    "Jörmund",
+    "Kæliope",
+    "Lûcian",
+    "Mîrabel",
+    "Nïcodemus",
+    "Øphelia",
+    "Pérsival",
+    "Quìlla",
+    "Rüdiger",
+    "Séraphine",
+    "Thëodric",
+];
+
+const SEEDED_SURNAMES: &[&str] = &[
+    "Thündéroak",
+    "Ëverbright",
+    "Stârførge",
+    "Møonfire",
+    "Ashgrôve",
+    "Brîarwood",
+    "Cøldbrook",
+    "Dawnwhîsper",
+    "Émberfall",
+    "Frøstvale",
+    "Glîmmerdale",
+    "Hollowbrôok",
+    "Ïronwood",
+    "Lûmenhart",
+    "Mîstvale",
+    "Nîghtshade",
+    "Ravenswîng",
+    "Starwhîsper",
+    "Wîllowmere",
+    "Zéphyrine",
+];
+
+const SEEDED_PARTIES: &[&str] = &[
+    "Ætherwïng",
+    "Crystâlheärt",
+    "Ēmerald Ĉircle",
+    "Sôlar Ålliance",
+    "Lünar Çoncord",
+    "Verdant Wäy",
+];
+
+const SEEDED_OFFICES: &[&str] = &[
+    "Mâyor",
+    "Hîgh Chancellor",
+    "Sheriff",
+    "Ârchmage",
+    "Keeper of the Sëals",
+    "Trêasurer",
+    "Harbormaster",
+    "Ĉouncil Member",
+    "Royal Cartøgrapher",
+    "Guardian of the Grôve",
+];
+
+const SEEDED_PLACES: &[&str] = &[
+    "Smoothstone County",
+    "Silvërspîre County",
+    "Ëlderglen",
+    "Frøstvale Township",
+    "the Çrystal Coast",
+    "Mîstvale Valley",
+    "Émberfall City",
+    "Hollowbrôok",
+];
+
+/// Builds an example election manifest of the requested size, with labels procedurally
+/// generated from `seed` in the style of [`example_election_manifest`].
+///
+/// The same arguments always give the same manifest. `num_contests` is raised to at least 1
+/// and `options_per_contest` is kept within `1..=`[`MAX_OPTIONS_PER_CONTEST`], so the result
+/// always passes [`ElectionManifest::validate`].
+///
+/// The first ballot style has every contest. Up to two more have the first contest and a
+/// random selection of the others.
+pub fn example_election_manifest_seeded(
+    seed: &[u8],
+    num_contests: usize,
+    options_per_contest: usize,
+) -> ElectionManifest {
+    let mut csprng = Csprng::new(seed);
+    let num_contests = num_contests.max(1);
+    let options_per_contest = options_per_contest.clamp(1, MAX_OPTIONS_PER_CONTEST);
+
+    let mut contest_labels = HashSet::new();
+    let mut contests = Vec1::with_capacity(num_contests);
+    for _ in 0..num_contests {
+        let office = pick(&mut csprng, SEEDED_OFFICES);
+        let place = pick(&mut csprng, SEEDED_PLACES);
+        let label = unique_label(&mut contest_labels, format!("{office} of {place}"));
+
+        let mut option_labels = HashSet::new();
+        let mut options = Vec1::with_capacity(options_per_contest);
+        for _ in 0..options_per_contest {
+            let surname = pick(&mut csprng, SEEDED_SURNAMES);
+            let given_name = pick(&mut csprng, SEEDED_GIVEN_NAMES);
+            let initial = char::from(b'A' + csprng.next_u8() % 26);
+            let party = pick(&mut csprng, SEEDED_PARTIES);
+            let label = unique_label(
+                &mut option_labels,
+                format!("{surname}, {given_name} {initial}.\n({party})"),
+            );
+
+            // `unwrap()` is justified here because there are at most `MAX_OPTIONS_PER_CONTEST`.
+            options
+                .try_push(ContestOption {
+                    label,
+                    color: None,
+                    image_uri: None,
+                })
+                .unwrap();
+        }
+
+        // Mostly vote-for-one, with the occasional vote-for-several.
+        let selection_limit = if csprng.next_u8() < 192 {
+            1
+        } else {
+            1 + csprng.next_u32() as usize % options_per_contest.min(3)
+        };
+
+        // `unwrap()` is justified here because there are far fewer than `u32::MAX` contests.
+        contests
+            .try_push(Contest {
+                label,
+                selection_limit,
+                vote_variation: VoteVariation::NOfM,
+                options,
+                kind: ContestKind::Candidate,
+                track_blank: false,
+                track_undervotes: false,
+                opt_electoral_district_id: None,
+            })
+            .unwrap();
+    }
+
+    let all_contests: BTreeSet<ContestIndex> = contests.indices().collect();
+    let mut ballot_style_labels = HashSet::new();
+    let mut ballot_styles = Vec1::new();
+    for ballot_style_ix0 in 0..num_contests.min(3) {
+        let contests = if ballot_style_ix0 == 0 {
+            all_contests.clone()
+        } else {
+            all_contests
+                .iter()
+                .enumerate()
+                .filter(|&(ix0, _)| ix0 == 0 || csprng.next_bool())
+                .map(|(_, &contest_ix)| contest_ix)
+                .collect()
+        };
+        let place = pick(&mut csprng, SEEDED_PLACES);
+
+        // `unwrap()` is justified here because there are at most three ballot styles.
+        ballot_styles
+            .try_push(BallotStyle {
+                label: unique_label(&mut ballot_style_labels, format!("{place} Ballot")),
+                contests,
+            })
+            .unwrap();
+    }
 
     ElectionManifest {
-        label: "General Election - The United Realms of Imaginaria".to_string(),
+        label: "Seeded Election - The United Realms of Imaginaria".to_string(),
         contests,
         ballot_styles,
+        geopolitical_units: Vec::new(),
+    }
+}
+
+/// Returns a uniformly random element of `items`, which must not be empty.
+fn pick<'a>(csprng: &mut Csprng, items: &[&'a str]) -> &'a str {
+    items[csprng.next_u32() as usize % items.len()]
+}
+
+/// Returns `label`, or if it has already been used, `label` with the smallest number appended
+/// which makes it unique. The result is recorded in `used`.
+fn unique_label(used: &mut HashSet<String>, label: String) -> String {
+    let mut unique = label.clone();
+    let mut n = 2;
+    while used.contains(&unique) {
+        unique = format!("{label} ({n})");
+        n += 1;
+    }
+    used.insert(unique.clone());
+    unique
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_example_election_manifest_seeded() {
+        for (num_contests, options_per_contest) in [
+            (0, 0),
+            (1, 1),
+            (1, 2),
+            (10, 4),
+            (25, 7),
+            (2, MAX_OPTIONS_PER_CONTEST + 1),
+        ] {
+            let election_manifest =
+                example_election_manifest_seeded(b"seed", num_contests, options_per_contest);
+            election_manifest.validate().unwrap();
+
+            assert_eq!(election_manifest.contests.len(), num_contests.max(1));
+            for contest_ix in election_manifest.contests.indices() {
+                let contest = election_manifest.contests.get(contest_ix).unwrap();
+                assert_eq!(
+                    contest.options.len(),
+                    options_per_contest.clamp(1, MAX_OPTIONS_PER_CONTEST)
+                );
+
+                // Labels are unique, so they can be used for lookups.
+                assert_eq!(
+                    election_manifest
+                        .contest_ix_by_label(&contest.label)
+                        .unwrap(),
+                    contest_ix
+                );
+                for option_ix in contest.options.indices() {
+                    let option = contest.options.get(option_ix).unwrap();
+                    assert_eq!(
+                        contest.option_ix_by_label(&option.label).unwrap(),
+                        option_ix
+                    );
+                }
+            }
+        }
+
+        let election_manifest = example_election_manifest_seeded(b"seed", 10, 4);
+        assert_eq!(
+            election_manifest,
+            example_election_manifest_seeded(b"seed", 10, 4)
+        );
+        assert_ne!(
+            election_manifest,
+            example_election_manifest_seeded(b"other seed", 10, 4)
+        );
+
+        // The labels exercise non-ASCII text.
+        let json = String::from_utf8(election_manifest.to_canonical_bytes().unwrap()).unwrap();
+        assert!(!json.is_ascii());
     }
 }
@@ -7,7 +7,7 @@
 
 use std::borrow::Borrow;
 
-use anyhow::{ensure, Result};
+use anyhow::{bail, ensure, Context, Result};
 use num_bigint::BigUint;
 use num_traits::{One, Zero};
 use serde::{Deserialize, Serialize};
@@ -15,9 +15,11 @@ use serde::{Deserialize, Serialize};
 use util::{
     csprng::Csprng,
     integer_util::{cnt_bits_repr, to_be_bytes_left_pad},
-    prime::{is_prime, BigUintPrime},
+    prime::{is_prime_with_rounds, BigUintPrime},
 };
 
+use crate::standard_parameters::standard_parameters_for_version;
+
 // "Nothing up my sleeve" numbers for use in fixed parameters.
 #[allow(non_camel_case_types)]
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
@@ -72,8 +74,27 @@ pub enum ElectionGuardDesignSpecificationVersion {
     Other(String),
 }
 
+impl std::fmt::Display for ElectionGuardDesignSpecificationVersion {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Official(OfficialVersion {
+                version: [major, minor],
+                release,
+            }) => {
+                write!(f, "v{major}.{minor}")?;
+                if *release == OfficialReleaseKind::Prerelease {
+                    write!(f, " (prerelease)")?;
+                }
+                Ok(())
+            }
+            Self::Other(s) => write!(f, "{s}"),
+        }
+    }
+}
+
 #[allow(non_snake_case)]
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "strict-parsing", serde(deny_unknown_fields))]
 pub struct FixedParameters {
     /// Version of the ElectionGuard Design Specification to which these parameters conform.
     /// E.g., `Some([2, 0])` for v2.0 and `Some([1, 55])` for v1.55.
@@ -108,6 +129,10 @@ pub struct FixedParameters {
     pub g: BigUint,
 }
 
+/// The number of Miller-Rabin rounds [`FixedParameters::validate`] uses to test `p` and `q`
+/// for primality, the same as for any other primality test.
+pub use util::prime::DEFAULT_PRIMALITY_TEST_ROUNDS;
+
 impl FixedParameters {
     /// The length of the byte array representation of p.
     pub fn l_p_bytes(&self) -> usize {
@@ -126,6 +151,20 @@ impl FixedParameters {
         n.borrow() < self.p.borrow()
     }
 
+    /// Returns `true` iff `n` is in `Z_p^*`, i.e., `0 < n < p`. Values read from a file which
+    /// represent group elements must be, so that each element has only one encoding.
+    pub fn is_valid_nonzero_modp<T: Borrow<BigUint>>(&self, n: &T) -> bool {
+        let n: &BigUint = n.borrow();
+        !n.is_zero() && self.is_valid_modp(n)
+    }
+
+    /// Returns `true` iff `n` is in the order-`q` subgroup of `Z_p^*`, i.e., `0 < n < p` and
+    /// `n^q = 1 mod p`. This takes a modular exponentiation, unlike [`Self::is_valid_modp`].
+    pub fn is_in_subgroup<T: Borrow<BigUint>>(&self, n: &T) -> bool {
+        let n: &BigUint = n.borrow();
+        self.is_valid_nonzero_modp(n) && n.modpow(self.q.borrow(), self.p.borrow()).is_one()
+    }
+
     /// Returns `true` iff `n` is a valid result of `mod q`.
     pub fn is_valid_modq<T: Borrow<BigUint>>(&self, n: &T) -> bool {
         n.borrow() < self.q.borrow()
@@ -137,13 +176,29 @@ impl FixedParameters {
     }
 
     /// Converts a `BigUint` to a big-endian byte array of the correct length for `mod q`.
+    ///
+    /// This is 32 bytes for the standard 256-bit `q`. Scalars such as challenges, responses,
+    /// and key shares should always be written this way when hashed or sealed, since a value
+    /// with leading zero bytes would otherwise be shorter, and hash differently from another
+    /// implementation's.
     pub fn biguint_to_be_bytes_len_q(&self, u: &BigUint) -> Vec<u8> {
         to_be_bytes_left_pad(&u, self.l_q_bytes())
     }
 
     /// Verifies that the `FixedParameters` meet some basic validity requirements.
-    #[allow(clippy::nonminimal_bool)]
+    ///
+    /// This tests `p` and `q` for primality, which is probabilistic and relatively expensive,
+    /// and then performs every check of [`FixedParameters::validate_structural`].
+    ///
+    /// [`DEFAULT_PRIMALITY_TEST_ROUNDS`] rounds of testing are used.
     pub fn validate(&self, csprng: &mut Csprng) -> Result<()> {
+        self.validate_with_rounds(csprng, DEFAULT_PRIMALITY_TEST_ROUNDS)
+    }
+
+    /// Same as [`FixedParameters::validate`], but with the specified number of Miller-Rabin
+    /// rounds, which must be at least 1. See [`DEFAULT_PRIMALITY_TEST_ROUNDS`] for how the
+    /// rounds relate to the probability of accepting a composite.
+    pub fn validate_with_rounds(&self, csprng: &mut Csprng, rounds: usize) -> Result<()> {
         let q: &BigUint = self.q.borrow();
         let p: &BigUint = self.p.borrow();
 
@@ -152,17 +207,42 @@ impl FixedParameters {
         //TODO verify p_middle_bits_source
         //TODO verify p_bits_lsb_fixed_1
 
+        #[cfg(feature = "tracing")]
+        tracing::debug!("Testing p and q for primality");
+
         // p is a prime of the requested number of bits
-        ensure!(is_prime(p, csprng), "Fixed parameters: p is not prime");
+        ensure!(
+            is_prime_with_rounds(p, rounds, csprng).context("Fixed parameters")?,
+            "Fixed parameters: p is not prime"
+        );
+
+        // q is a prime of the requested number of bits
+        ensure!(
+            is_prime_with_rounds(q, rounds, csprng).context("Fixed parameters")?,
+            "Fixed parameters: q is not prime"
+        );
+
+        self.validate_structural()
+    }
+
+    /// Verifies the relationships between the `FixedParameters` without testing `p` or `q`
+    /// for primality. This needs no [`Csprng`] and is cheap enough for a quick sanity check
+    /// of parameters from a trusted source.
+    ///
+    /// Checks that:
+    /// - `p` and `q` have the number of bits given in the generation parameters,
+    /// - `q < p - 1`, `r·q + 1 = p`, and `q` does not divide `r`,
+    /// - `1 < g < p`, and `g` generates the subgroup of order `q`, i.e. `g^q = 1 mod p`.
+    #[allow(clippy::nonminimal_bool)]
+    pub fn validate_structural(&self) -> Result<()> {
+        let q: &BigUint = self.q.borrow();
+        let p: &BigUint = self.p.borrow();
 
         ensure!(
             cnt_bits_repr(p) == self.generation_parameters.p_bits_total,
             "Fixed parameters: p wrong number of bits"
         );
 
-        // q is a prime of the requested number of bits
-        ensure!(is_prime(q, csprng), "Fixed parameters: q is not prime");
-
         ensure!(
             cnt_bits_repr(q) == self.generation_parameters.q_bits_total,
             "Fixed parameters: q wrong number of bits"
@@ -174,10 +254,11 @@ impl FixedParameters {
             "Fixed parameters failed check: q < (p - 1)"
         );
 
-        // r = (p − 1)/q
+        // r = (p − 1)/q, exactly. Comparing with the quotient alone would accept an r when q
+        // does not divide p − 1.
         ensure!(
-            self.r == ((p - 1_u8) / q),
-            "Fixed parameters failed check: r = (p − 1)/q"
+            &self.r * q + 1_u8 == *p,
+            "Fixed parameters failed check: r = (p − 1)/q, i.e. r·q + 1 = p"
         );
 
         // q is not a divisor of r = (p − 1)/q
@@ -195,6 +276,206 @@ impl FixedParameters {
         );
         ensure!(g < p, "Fixed parameters failed check: g < p");
 
+        // g is in the subgroup of order q
+        ensure!(
+            g.modpow(q, p).is_one(),
+            "Fixed parameters failed check: g^q = 1 mod p"
+        );
+
+        Ok(())
+    }
+
+    /// Returns true iff `other` describes the same group as `self`. This agrees with `==`, but
+    /// compares the version and generation parameters first, so that parameters for a
+    /// different version are rejected without comparing `p` and the other large integers.
+    pub fn same_group_as(&self, other: &FixedParameters) -> bool {
+        self.opt_ElectionGuard_Design_Specification == other.opt_ElectionGuard_Design_Specification
+            && self.generation_parameters == other.generation_parameters
+            && self.q == other.q
+            && self.g == other.g
+            && self.p == other.p
+            && self.r == other.r
+    }
+
+    /// Verifies that the `FixedParameters` are exactly the standard parameters for the
+    /// ElectionGuard Design Specification version they claim to conform to.
+    pub fn matches_standard(&self) -> Result<()> {
+        let Some(version) = &self.opt_ElectionGuard_Design_Specification else {
+            bail!("Fixed parameters do not specify an ElectionGuard Design Specification version");
+        };
+
+        let Some(standard) = standard_parameters_for_version(version) else {
+            bail!("No standard fixed parameters are known for ElectionGuard Design Specification {version}");
+        };
+
+        if self.same_group_as(&standard) {
+            return Ok(());
+        }
+
+        ensure!(
+            self.generation_parameters == standard.generation_parameters,
+            "Fixed parameters: generation parameters do not match the standard parameters for {version}"
+        );
+
+        for (name, value, standard_value) in [
+            ("p", self.p.borrow(), standard.p.borrow()),
+            ("q", self.q.borrow(), standard.q.borrow()),
+            ("r", &self.r, &standard.r),
+            ("g", &self.g, &standard.g),
+        ] {
+            ensure!(
+                value == standard_value,
+                "Fixed parameters: {name} does not match the standard parameters for {version}"
+            );
+        }
+
         Ok(())
     }
 }
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod test {
+    use super::*;
+    use crate::example_election_parameters::example_election_parameters;
+
+    #[test]
+    fn test_is_valid_nonzero_modp() {
+        let fixed_parameters = example_election_parameters().fixed_parameters;
+        let p: &BigUint = fixed_parameters.p.borrow();
+
+        for n in [BigUint::one(), p - 1_u8] {
+            assert!(fixed_parameters.is_valid_nonzero_modp(&n));
+        }
+        for n in [BigUint::zero(), p.clone(), p + 1_u8] {
+            assert!(!fixed_parameters.is_valid_nonzero_modp(&n));
+        }
+    }
+
+    #[test]
+    fn test_biguint_to_be_bytes_len_q() {
+        let fixed_parameters = example_election_parameters().fixed_parameters;
+        let q: &BigUint = fixed_parameters.q.borrow();
+        let l_q_bytes = fixed_parameters.l_q_bytes();
+        assert_eq!(l_q_bytes, 32);
+
+        let small = fixed_parameters.biguint_to_be_bytes_len_q(&BigUint::from(0x1234u32));
+        assert_eq!(small.len(), l_q_bytes);
+        assert!(small[..l_q_bytes - 2].iter().all(|&b| b == 0));
+        assert_eq!(small[l_q_bytes - 2..], [0x12, 0x34]);
+
+        let zero = fixed_parameters.biguint_to_be_bytes_len_q(&BigUint::from(0u8));
+        assert_eq!(zero, vec![0u8; l_q_bytes]);
+
+        let near_q = q - 1u8;
+        let bytes = fixed_parameters.biguint_to_be_bytes_len_q(&near_q);
+        assert_eq!(bytes, near_q.to_bytes_be());
+        assert_eq!(BigUint::from_bytes_be(&bytes), near_q);
+
+        // A value mod p is longer.
+        assert_eq!(
+            fixed_parameters.biguint_to_be_bytes_len_p(&near_q).len(),
+            fixed_parameters.l_p_bytes()
+        );
+    }
+
+    #[test]
+    fn test_validate_r() {
+        let fixed_parameters = example_election_parameters().fixed_parameters;
+        fixed_parameters.validate_structural().unwrap();
+
+        let mut fixed_parameters_bad_r = fixed_parameters.clone();
+        fixed_parameters_bad_r.r -= 1_u8;
+        let err = fixed_parameters_bad_r.validate_structural().unwrap_err();
+        assert!(err.to_string().contains("r·q + 1 = p"));
+
+        // Toy parameters where q does not divide p − 1, but r is the truncated quotient.
+        let toy_parameters = FixedParameters {
+            opt_ElectionGuard_Design_Specification: None,
+            generation_parameters: FixedParameterGenerationParameters {
+                q_bits_total: 3,
+                p_bits_total: 5,
+                p_bits_msb_fixed_1: 0,
+                p_middle_bits_source: NumsNumber::ln_2,
+                p_bits_lsb_fixed_1: 0,
+            },
+            p: BigUintPrime::new_unchecked_the_caller_guarantees_that_this_number_is_prime(
+                BigUint::from(23_u8),
+            ),
+            q: BigUintPrime::new_unchecked_the_caller_guarantees_that_this_number_is_prime(
+                BigUint::from(7_u8),
+            ),
+            r: BigUint::from(3_u8),
+            g: BigUint::from(2_u8),
+        };
+        let err = toy_parameters.validate_structural().unwrap_err();
+        assert!(err.to_string().contains("r·q + 1 = p"));
+    }
+
+    #[test]
+    fn test_composite_p_rejected() {
+        let mut csprng = Csprng::new(b"test_composite_p_rejected");
+
+        let fixed_parameters = example_election_parameters().fixed_parameters;
+        let err = fixed_parameters
+            .validate_with_rounds(&mut csprng, 0)
+            .unwrap_err();
+        assert!(
+            format!("{err:#}").contains("At least one round of primality testing"),
+            "{err:#}"
+        );
+
+        let q: &BigUint = fixed_parameters.q.borrow();
+        for composite in [
+            // A strong pseudoprime to bases 2, 3, 5, and 7, i.e., 151·751·28351.
+            BigUint::from(3_215_031_751_u64),
+            // The square of a prime too large for trial division.
+            q * q,
+        ] {
+            let mut fixed_parameters_bad_p = fixed_parameters.clone();
+            fixed_parameters_bad_p.p =
+                BigUintPrime::new_unchecked_the_caller_guarantees_that_this_number_is_prime(
+                    composite,
+                );
+
+            for rounds in [16, DEFAULT_PRIMALITY_TEST_ROUNDS] {
+                let err = fixed_parameters_bad_p
+                    .validate_with_rounds(&mut csprng, rounds)
+                    .unwrap_err();
+                assert!(err.to_string().contains("p is not prime"), "{err}");
+            }
+        }
+    }
+
+    #[test]
+    fn test_same_group_as() {
+        let fixed_parameters = example_election_parameters().fixed_parameters;
+
+        let mut other_version = fixed_parameters.clone();
+        other_version.opt_ElectionGuard_Design_Specification = None;
+
+        let mut other_generation = fixed_parameters.clone();
+        other_generation.generation_parameters.p_bits_total += 1;
+
+        let mut other_g = fixed_parameters.clone();
+        other_g.g += 1u8;
+
+        for other in [
+            &fixed_parameters,
+            &other_version,
+            &other_generation,
+            &other_g,
+        ] {
+            assert_eq!(
+                fixed_parameters.same_group_as(other),
+                fixed_parameters == *other
+            );
+            assert_eq!(
+                other.same_group_as(&fixed_parameters),
+                *other == fixed_parameters
+            );
+        }
+        assert!(fixed_parameters.same_group_as(&fixed_parameters.clone()));
+        assert!(!fixed_parameters.same_group_as(&other_g));
+    }
+}
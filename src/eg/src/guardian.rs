@@ -5,6 +5,8 @@
 #![deny(clippy::panic)]
 #![deny(clippy::manual_assert)]
 
+use anyhow::{ensure, Result};
+
 use crate::index::Index;
 
 #[doc(hidden)]
@@ -22,6 +24,31 @@ pub struct GuardianIndexTag;
 ///
 pub type GuardianIndex = Index<GuardianIndexTag>;
 
+impl GuardianIndex {
+    /// Converts guardian `i` to its 0-based position among the guardians `1..=n`, e.g. for
+    /// indexing into a `Vec` with an element per guardian.
+    ///
+    /// It is an error if `i` is greater than `n`.
+    pub fn to_zero_based(&self, n: GuardianIndex) -> Result<usize> {
+        ensure!(
+            *self <= n,
+            "Guardian number i={self} is not in the range 1 <= i <= n={n}"
+        );
+        Ok(self.get_zero_based_usize())
+    }
+
+    /// Converts a 0-based position among the guardians `1..=n` to the guardian index `i`.
+    ///
+    /// It is an error if the position is not less than `n`.
+    pub fn from_zero_based(ix0: usize, n: GuardianIndex) -> Result<GuardianIndex> {
+        ensure!(
+            ix0 < n.as_quantity(),
+            "Guardian position {ix0} is not in the range 0 <= position < n={n}"
+        );
+        GuardianIndex::from_one_based_index(ix0 as u32 + 1)
+    }
+}
+
 // use std::{borrow::Borrow, rc::Rc};
 
 // use num_bigint::BigUint;
@@ -450,3 +477,35 @@ pub type GuardianIndex = Index<GuardianIndexTag>;
 //         unimplemented!()
 //     }
 // }
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_zero_based() {
+        let ix = |i: u32| GuardianIndex::from_one_based_index(i).unwrap();
+        let n = ix(5);
+
+        assert_eq!(ix(1).to_zero_based(n).unwrap(), 0);
+        assert_eq!(ix(5).to_zero_based(n).unwrap(), 4);
+        let err = ix(6).to_zero_based(n).unwrap_err();
+        assert!(err.to_string().contains("1 <= i <= n=5"), "{err}");
+
+        assert_eq!(GuardianIndex::from_zero_based(0, n).unwrap(), ix(1));
+        assert_eq!(GuardianIndex::from_zero_based(4, n).unwrap(), ix(5));
+        GuardianIndex::from_zero_based(5, n).unwrap_err();
+        GuardianIndex::from_zero_based(usize::MAX, n).unwrap_err();
+
+        for i in 1..=5 {
+            let ix0 = ix(i).to_zero_based(n).unwrap();
+            assert_eq!(GuardianIndex::from_zero_based(ix0, n).unwrap(), ix(i));
+        }
+
+        // With a single guardian.
+        let n = ix(1);
+        assert_eq!(ix(1).to_zero_based(n).unwrap(), 0);
+        GuardianIndex::from_zero_based(1, n).unwrap_err();
+    }
+}
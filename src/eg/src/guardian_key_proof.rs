@@ -0,0 +1,377 @@
+// Copyright (C) Microsoft Corporation. All rights reserved.
+
+#![deny(clippy::unwrap_used)]
+#![deny(clippy::expect_used)]
+#![deny(clippy::panic)]
+#![deny(clippy::manual_assert)]
+
+//! Proofs that a guardian knows the secret coefficient behind each of its published
+//! coefficient commitments.
+//!
+//! Each is a Schnorr proof for `K_i,j = g^a_i,j`: the guardian picks `u_i,j`, computes
+//! `h_i,j = g^u_i,j`, the challenge `c_i,j = H(H_P; 10, i, j, K_i,j, h_i,j) mod q` and the
+//! response `v_i,j = u_i,j - c_i,j·a_i,j mod q`. Only `c_i,j` and `v_i,j` are published, since
+//! `h_i,j = g^v_i,j · K_i,j^c_i,j` can be recomputed by a verifier.
+//!
+//! In the challenge, `i` and `j` are big-endian `u32`s, and `K_i,j` and `h_i,j` are written
+//! as fixed-width fields of [`FixedParameters::l_p_bytes`] bytes.
+
+use std::borrow::Borrow;
+
+use anyhow::{ensure, Result};
+use num_bigint::BigUint;
+use serde::{Deserialize, Serialize};
+
+use util::csprng::Csprng;
+
+use crate::{
+    election_parameters::ElectionParameters,
+    fixed_parameters::FixedParameters,
+    guardian::GuardianIndex,
+    guardian_public_key::GuardianPublicKey,
+    guardian_secret_key::{CoefficientCommitments, SecretCoefficients},
+    hash::{eg_h, HValue},
+    hashes::Hashes,
+    zk::{Record, TranscriptValue},
+};
+
+#[cfg(feature = "debug-transcript")]
+use crate::transcript::TranscriptSink;
+
+/// The domain separator of the challenge.
+const CHALLENGE_DOMAIN: u8 = 0x10;
+
+/// Proof of knowledge of the secret coefficient `a_i,j` of a single commitment `K_i,j`.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CoefficientProof {
+    #[serde(
+        serialize_with = "util::biguint_serde::biguint_serialize",
+        deserialize_with = "util::biguint_serde::biguint_deserialize"
+    )]
+    pub c: BigUint,
+    #[serde(
+        serialize_with = "util::biguint_serde::biguint_serialize",
+        deserialize_with = "util::biguint_serde::biguint_deserialize"
+    )]
+    pub v: BigUint,
+}
+
+/// A guardian's [`CoefficientProof`]s, one for each coefficient commitment, in order of `j`.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CoefficientProofs(pub Vec<CoefficientProof>);
+
+impl CoefficientProofs {
+    /// Generates a proof for each of guardian `i`'s secret coefficients.
+    pub fn generate(
+        csprng: &mut Csprng,
+        election_parameters: &ElectionParameters,
+        i: GuardianIndex,
+        secret_coefficients: &SecretCoefficients,
+        coefficient_commitments: &CoefficientCommitments,
+    ) -> Self {
+        let fixed_parameters = &election_parameters.fixed_parameters;
+        let p: &BigUint = fixed_parameters.p.borrow();
+        let q = &fixed_parameters.q;
+        let h_p = Hashes::compute_h_p(election_parameters);
+
+        let proofs = secret_coefficients
+            .0
+            .iter()
+            .zip(&coefficient_commitments.0)
+            .enumerate()
+            .map(|(j, (a_i_j, k_i_j))| {
+                let u = q.random_group_elem(csprng);
+                let h = fixed_parameters.g.modpow(&u, p);
+                let c = challenge(fixed_parameters, &h_p, i, j, &k_i_j.0, &h);
+                let v = q.subtract_group_elem(&u, &q.multiply_group_elem(&c, &a_i_j.0));
+                CoefficientProof { c, v }
+            })
+            .collect();
+
+        CoefficientProofs(proofs)
+    }
+
+    /// Returns true iff there are no proofs, as for keys generated before proofs were
+    /// published.
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+/// Computes the challenge `c_i,j` for a [`CoefficientProof`].
+fn challenge(
+    fixed_parameters: &FixedParameters,
+    h_p: &HValue,
+    i: GuardianIndex,
+    j: usize,
+    k_i_j: &BigUint,
+    h_i_j: &BigUint,
+) -> BigUint {
+    let mut v = vec![CHALLENGE_DOMAIN];
+    v.extend_from_slice(&i.get_one_based_u32().to_be_bytes());
+    v.extend_from_slice(&(j as u32).to_be_bytes());
+    for x in [k_i_j, h_i_j] {
+        v.extend_from_slice(&fixed_parameters.biguint_to_be_bytes_len_p(x));
+    }
+
+    let c = eg_h(h_p, &v);
+    BigUint::from_bytes_be(c.0.as_slice()) % fixed_parameters.q.as_ref()
+}
+
+/// Verifies guardian `i`'s proof of knowledge of the secret coefficient behind the
+/// commitment `K_i,j`.
+pub fn verify_schnorr_proof(
+    fixed_parameters: &FixedParameters,
+    h_p: &HValue,
+    i: GuardianIndex,
+    j: usize,
+    k_i_j: &BigUint,
+    proof: &CoefficientProof,
+) -> Result<()> {
+    verify_schnorr_proof_impl(
+        fixed_parameters,
+        h_p,
+        i,
+        j,
+        k_i_j,
+        proof,
+        &mut |_label, _value| {},
+    )
+}
+
+/// Same as [`verify_schnorr_proof`], but records every hashed element and challenge value in
+/// `transcript`.
+#[cfg(feature = "debug-transcript")]
+pub fn verify_schnorr_proof_with_transcript(
+    fixed_parameters: &FixedParameters,
+    h_p: &HValue,
+    i: GuardianIndex,
+    j: usize,
+    k_i_j: &BigUint,
+    proof: &CoefficientProof,
+    transcript: &mut TranscriptSink,
+) -> Result<()> {
+    verify_schnorr_proof_impl(
+        fixed_parameters,
+        h_p,
+        i,
+        j,
+        k_i_j,
+        proof,
+        &mut |label, value| transcript.record(label, value),
+    )
+}
+
+fn verify_schnorr_proof_impl(
+    fixed_parameters: &FixedParameters,
+    h_p: &HValue,
+    i: GuardianIndex,
+    j: usize,
+    k_i_j: &BigUint,
+    proof: &CoefficientProof,
+    record: &mut Record,
+) -> Result<()> {
+    use TranscriptValue::{BigUint as Int, Bytes};
+
+    let p: &BigUint = fixed_parameters.p.borrow();
+    let CoefficientProof { c, v } = proof;
+
+    ensure!(
+        fixed_parameters.is_valid_nonzero_modp(k_i_j),
+        "Guardian {i} coefficient commitment {j} is not a valid nonzero mod p value"
+    );
+    ensure!(
+        fixed_parameters.is_valid_modq(c) && fixed_parameters.is_valid_modq(v),
+        "Guardian {i} proof for coefficient {j} has a response out of range"
+    );
+
+    record(format_args!("domain"), Bytes(&[CHALLENGE_DOMAIN]));
+    record(format_args!("H_P"), Bytes(h_p.0.as_slice()));
+    record(
+        format_args!("i"),
+        Bytes(&i.get_one_based_u32().to_be_bytes()),
+    );
+    record(format_args!("j"), Bytes(&(j as u32).to_be_bytes()));
+    record(format_args!("K_i,j"), Int(k_i_j));
+    record(format_args!("c"), Int(c));
+    record(format_args!("v"), Int(v));
+
+    let h = fixed_parameters.g.modpow(v, p) * k_i_j.modpow(c, p) % p;
+    record(format_args!("h_i,j"), Int(&h));
+
+    let c_computed = challenge(fixed_parameters, h_p, i, j, k_i_j, &h);
+    record(format_args!("c_computed"), Int(&c_computed));
+
+    ensure!(
+        *c == c_computed,
+        "Guardian {i} proof of knowledge for coefficient {j} does not verify"
+    );
+
+    Ok(())
+}
+
+/// Verifies the proofs of knowledge for every coefficient commitment of a guardian's public
+/// key. A key without proofs fails.
+pub fn verify_guardian_key_proofs(
+    election_parameters: &ElectionParameters,
+    guardian_public_key: &GuardianPublicKey,
+) -> Result<()> {
+    let fixed_parameters = &election_parameters.fixed_parameters;
+    let i = guardian_public_key.i;
+    let commitments = &guardian_public_key.coefficient_commitments.0;
+    let proofs = &guardian_public_key.coefficient_proofs.0;
+
+    ensure!(
+        !proofs.is_empty(),
+        "Guardian {i} public key has no proofs of knowledge"
+    );
+    ensure!(
+        proofs.len() == commitments.len(),
+        "Guardian {i} public key has {} proofs for {} coefficient commitments",
+        proofs.len(),
+        commitments.len()
+    );
+
+    let h_p = Hashes::compute_h_p(election_parameters);
+    for (j, (k_i_j, proof)) in commitments.iter().zip(proofs).enumerate() {
+        verify_schnorr_proof(fixed_parameters, &h_p, i, j, &k_i_j.0, proof)?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod test {
+    use super::*;
+    use crate::{
+        example_election::ExampleElection, example_election_parameters::example_election_parameters,
+    };
+
+    #[test]
+    fn test_guardian_key_proofs() {
+        let mut csprng = Csprng::new(b"test_guardian_key_proofs");
+
+        let election_parameters = example_election_parameters();
+        let varying_parameters = &election_parameters.varying_parameters;
+
+        let (_, guardian_public_keys) =
+            ExampleElection::generate_guardian_keys(&mut csprng, &election_parameters).unwrap();
+
+        for guardian_public_key in &guardian_public_keys {
+            assert_eq!(
+                guardian_public_key.coefficient_proofs.0.len(),
+                varying_parameters.k.as_quantity()
+            );
+            verify_guardian_key_proofs(&election_parameters, guardian_public_key).unwrap();
+        }
+
+        // A proof is bound to its guardian and coefficient.
+        let mut swapped = guardian_public_keys[0].clone();
+        swapped.coefficient_proofs.0.swap(0, 1);
+        let err = verify_guardian_key_proofs(&election_parameters, &swapped).unwrap_err();
+        assert!(err.to_string().contains("does not verify"), "{err}");
+
+        let mut other_guardian = guardian_public_keys[1].clone();
+        other_guardian.i = guardian_public_keys[0].i;
+        verify_guardian_key_proofs(&election_parameters, &other_guardian).unwrap_err();
+
+        // As well as to the commitment.
+        let mut altered = guardian_public_keys[0].clone();
+        altered.coefficient_commitments.0[1] =
+            guardian_public_keys[1].coefficient_commitments.0[1].clone();
+        verify_guardian_key_proofs(&election_parameters, &altered).unwrap_err();
+
+        // Keys without proofs fail.
+        let mut no_proofs = guardian_public_keys[0].clone();
+        no_proofs.coefficient_proofs = CoefficientProofs::default();
+        let err = verify_guardian_key_proofs(&election_parameters, &no_proofs).unwrap_err();
+        assert!(err.to_string().contains("no proofs"), "{err}");
+
+        // Proofs survive a round trip, and keys written without them can still be read.
+        let json = guardian_public_keys[0].to_json();
+        let public_key = GuardianPublicKey::from_stdioread(&mut json.as_bytes()).unwrap();
+        verify_guardian_key_proofs(&election_parameters, &public_key).unwrap();
+
+        let json = no_proofs.to_json();
+        assert!(!json.contains("coefficient_proofs"), "{json}");
+        let public_key = GuardianPublicKey::from_stdioread(&mut json.as_bytes()).unwrap();
+        assert!(public_key.coefficient_proofs.is_empty());
+    }
+
+    #[cfg(feature = "debug-transcript")]
+    #[test]
+    fn test_verify_schnorr_proof_with_transcript() {
+        let mut csprng = Csprng::new(b"test_verify_schnorr_proof_with_transcript");
+
+        let election_parameters = example_election_parameters();
+        let fixed_parameters = &election_parameters.fixed_parameters;
+        let h_p = Hashes::compute_h_p(&election_parameters);
+
+        let i = GuardianIndex::from_one_based_index(2).unwrap();
+        let guardian_public_key = crate::guardian_secret_key::GuardianSecretKey::generate(
+            &mut csprng,
+            &election_parameters,
+            i,
+            None,
+        )
+        .unwrap()
+        .make_public_key();
+        let k_i_1 = &guardian_public_key.coefficient_commitments.0[1].0;
+        let proof = &guardian_public_key.coefficient_proofs.0[1];
+
+        let mut transcript = TranscriptSink::new();
+        verify_schnorr_proof_with_transcript(
+            fixed_parameters,
+            &h_p,
+            i,
+            1,
+            k_i_1,
+            proof,
+            &mut transcript,
+        )
+        .unwrap();
+
+        let labels: Vec<&str> = transcript
+            .entries
+            .iter()
+            .map(|entry| entry.label.as_str())
+            .collect();
+        assert_eq!(
+            labels,
+            [
+                "domain",
+                "H_P",
+                "i",
+                "j",
+                "K_i,j",
+                "c",
+                "v",
+                "h_i,j",
+                "c_computed"
+            ]
+        );
+        assert_eq!(transcript.entries[0].value, "10");
+        assert_eq!(
+            transcript.entries[1].value,
+            h_p.to_string_hex_no_prefix_suffix()
+        );
+        assert_eq!(transcript.entries[2].value, "00000002");
+        assert_eq!(transcript.entries[3].value, "00000001");
+        assert_eq!(transcript.entries[5].value, transcript.entries[8].value);
+
+        // A proof presented for another coefficient records a different challenge.
+        let mut transcript = TranscriptSink::new();
+        assert!(verify_schnorr_proof_with_transcript(
+            fixed_parameters,
+            &h_p,
+            i,
+            0,
+            k_i_1,
+            proof,
+            &mut transcript,
+        )
+        .is_err());
+        assert_ne!(transcript.entries[5].value, transcript.entries[8].value);
+    }
+}
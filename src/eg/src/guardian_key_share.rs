@@ -0,0 +1,299 @@
+// Copyright (C) Microsoft Corporation. All rights reserved.
+
+#![deny(clippy::unwrap_used)]
+#![deny(clippy::expect_used)]
+#![deny(clippy::panic)]
+#![deny(clippy::manual_assert)]
+
+use std::borrow::Borrow;
+
+use anyhow::{ensure, Context, Result};
+use num_bigint::BigUint;
+use num_traits::{One, Zero};
+
+use crate::{
+    commitment_matrix::{share_commitment, CommitmentMatrix},
+    election_parameters::ElectionParameters,
+    guardian::GuardianIndex,
+    guardian_public_key::GuardianPublicKey,
+    guardian_secret_key::GuardianSecretKey,
+    share_distribution::{evaluate_polynomial, verify_share, ShareDistribution},
+};
+
+/// Guardian `ℓ`'s share `P(ℓ) = Σ_i P_i(ℓ) mod q` of the joint secret key `s = P(0)`.
+///
+/// `P` has degree `k - 1`, so the key shares of any `k` guardians determine `s` by Lagrange
+/// interpolation. This is what a guardian decrypts with, so that a quorum of `k` guardians
+/// can decrypt without the others.
+#[derive(Debug, Clone)]
+pub struct GuardianKeyShare {
+    /// Guardian number, 1 <= i <= n.
+    pub i: GuardianIndex,
+
+    /// `P(i)`.
+    secret_p_i: BigUint,
+
+    /// `g^P(i) mod p`.
+    public_key_share: GuardianPublicKeyShare,
+}
+
+impl GuardianKeyShare {
+    /// Computes the key share of `guardian_secret_key`'s guardian from its own polynomial
+    /// and the shares sent to it by every other guardian in `share_distribution`.
+    ///
+    /// Each received share is opened and verified against its sender's coefficient
+    /// commitments, so a guardian which sent a bad share is identified here.
+    pub fn compute(
+        election_parameters: &ElectionParameters,
+        guardian_secret_key: &GuardianSecretKey,
+        guardian_public_keys: &[GuardianPublicKey],
+        share_distribution: &ShareDistribution,
+    ) -> Result<Self> {
+        let fixed_parameters = &election_parameters.fixed_parameters;
+        let q: &BigUint = fixed_parameters.q.borrow();
+        let l = guardian_secret_key.i;
+
+        let mut p_l = evaluate_polynomial(fixed_parameters, guardian_secret_key, l);
+        for i in election_parameters
+            .varying_parameters
+            .each_guardian_i()
+            .filter(|&i| i != l)
+        {
+            let sender = guardian_public_keys
+                .iter()
+                .find(|k| k.i == i)
+                .with_context(|| format!("No public key for guardian {i}"))?;
+            let sealed_share = share_distribution
+                .get(i, l)
+                .with_context(|| format!("No share from guardian {i} to guardian {l}"))?;
+
+            let share = sealed_share.open(election_parameters, i, guardian_secret_key)?;
+            verify_share(fixed_parameters, sender, l, &share)?;
+
+            p_l = (p_l + share) % q;
+        }
+
+        Ok(Self::from_secret_p_i(election_parameters, l, p_l))
+    }
+
+    /// Computes every guardian's key share directly from the secret keys of all of them.
+    ///
+    /// Only possible for whoever holds every secret key, as when generating an example
+    /// election. In a real election, each guardian uses [`compute`](Self::compute).
+    pub fn compute_all_from_secret_keys(
+        election_parameters: &ElectionParameters,
+        guardian_secret_keys: &[GuardianSecretKey],
+    ) -> Vec<Self> {
+        let fixed_parameters = &election_parameters.fixed_parameters;
+        let q: &BigUint = fixed_parameters.q.borrow();
+
+        guardian_secret_keys
+            .iter()
+            .map(|recipient| {
+                let p_l = guardian_secret_keys
+                    .iter()
+                    .map(|sender| evaluate_polynomial(fixed_parameters, sender, recipient.i))
+                    .fold(BigUint::zero(), |acc, share| (acc + share) % q);
+                Self::from_secret_p_i(election_parameters, recipient.i, p_l)
+            })
+            .collect()
+    }
+
+    fn from_secret_p_i(
+        election_parameters: &ElectionParameters,
+        i: GuardianIndex,
+        secret_p_i: BigUint,
+    ) -> Self {
+        let fixed_parameters = &election_parameters.fixed_parameters;
+        let k_i = fixed_parameters
+            .g
+            .modpow(&secret_p_i, fixed_parameters.p.borrow());
+        GuardianKeyShare {
+            i,
+            secret_p_i,
+            public_key_share: GuardianPublicKeyShare { i, k_i },
+        }
+    }
+
+    /// Returns `P(i)`.
+    pub fn secret_p_i(&self) -> &BigUint {
+        &self.secret_p_i
+    }
+
+    /// Returns the corresponding [`GuardianPublicKeyShare`].
+    pub fn public_key_share(&self) -> &GuardianPublicKeyShare {
+        &self.public_key_share
+    }
+}
+
+/// The public counterpart `g^P(i) mod p` of guardian `i`'s [`GuardianKeyShare`].
+///
+/// Anyone can compute it from the published coefficient commitments, as
+/// `Π_j Π_m K_j,m^(i^m) mod p`. Decryption shares are verified against it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GuardianPublicKeyShare {
+    /// Guardian number, 1 <= i <= n.
+    pub i: GuardianIndex,
+
+    /// `g^P(i) mod p`.
+    pub k_i: BigUint,
+}
+
+impl GuardianPublicKeyShare {
+    /// Computes the public key share of every guardian from all `n` guardians' public keys,
+    /// in order of guardian number.
+    pub fn compute_all(
+        election_parameters: &ElectionParameters,
+        guardian_public_keys: &[GuardianPublicKey],
+    ) -> Result<Vec<Self>> {
+        let commitment_matrix = CommitmentMatrix::new(election_parameters, guardian_public_keys)?;
+
+        election_parameters
+            .varying_parameters
+            .each_guardian_i()
+            .map(|l| Self::from_commitment_matrix(election_parameters, &commitment_matrix, l))
+            .collect()
+    }
+
+    /// Computes the public key share of guardian `i` from all `n` guardians' public keys.
+    pub fn compute(
+        election_parameters: &ElectionParameters,
+        guardian_public_keys: &[GuardianPublicKey],
+        i: GuardianIndex,
+    ) -> Result<Self> {
+        let n = election_parameters.varying_parameters.n;
+        ensure!(i <= n, "Guardian {i} is not one of the n={n} guardians");
+
+        let commitment_matrix = CommitmentMatrix::new(election_parameters, guardian_public_keys)?;
+        Self::from_commitment_matrix(election_parameters, &commitment_matrix, i)
+    }
+
+    fn from_commitment_matrix(
+        election_parameters: &ElectionParameters,
+        commitment_matrix: &CommitmentMatrix,
+        l: GuardianIndex,
+    ) -> Result<Self> {
+        let fixed_parameters = &election_parameters.fixed_parameters;
+        let p: &BigUint = fixed_parameters.p.borrow();
+
+        let mut k_l = BigUint::one();
+        for i in election_parameters.varying_parameters.each_guardian_i() {
+            let row = commitment_matrix
+                .row(i)
+                .with_context(|| format!("No commitments for guardian {i}"))?;
+            k_l = k_l * share_commitment(fixed_parameters, row, l) % p;
+        }
+
+        Ok(GuardianPublicKeyShare { i: l, k_i: k_l })
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod test {
+    use super::*;
+    use crate::{
+        decryption_share::lagrange_coefficient, example_election::ExampleElection,
+        example_election_parameters::example_election_parameters,
+    };
+    use util::csprng::Csprng;
+
+    #[test]
+    fn test_guardian_key_share() {
+        let mut csprng = Csprng::new(b"test_guardian_key_share");
+
+        let election_parameters = example_election_parameters();
+        let fixed_parameters = &election_parameters.fixed_parameters;
+        let q: &BigUint = fixed_parameters.q.borrow();
+
+        let (guardian_secret_keys, guardian_public_keys) =
+            ExampleElection::generate_guardian_keys(&mut csprng, &election_parameters).unwrap();
+
+        let mut share_distribution = ShareDistribution::new();
+        for sender in &guardian_secret_keys {
+            share_distribution
+                .add_shares_from(
+                    &mut csprng,
+                    &election_parameters,
+                    sender,
+                    &guardian_public_keys,
+                )
+                .unwrap();
+        }
+
+        let key_shares = guardian_secret_keys
+            .iter()
+            .map(|secret_key| {
+                GuardianKeyShare::compute(
+                    &election_parameters,
+                    secret_key,
+                    &guardian_public_keys,
+                    &share_distribution,
+                )
+                .unwrap()
+            })
+            .collect::<Vec<_>>();
+
+        // The same as from all the secret keys at once.
+        let from_secret_keys = GuardianKeyShare::compute_all_from_secret_keys(
+            &election_parameters,
+            &guardian_secret_keys,
+        );
+        for (key_share, other) in key_shares.iter().zip(&from_secret_keys) {
+            assert_eq!(key_share.i, other.i);
+            assert_eq!(key_share.secret_p_i(), other.secret_p_i());
+        }
+
+        // The public key shares computed from only the public keys match.
+        let public_key_shares =
+            GuardianPublicKeyShare::compute_all(&election_parameters, &guardian_public_keys)
+                .unwrap();
+        for (key_share, public_key_share) in key_shares.iter().zip(&public_key_shares) {
+            assert_eq!(key_share.public_key_share(), public_key_share);
+        }
+        assert_eq!(
+            GuardianPublicKeyShare::compute(
+                &election_parameters,
+                &guardian_public_keys,
+                key_shares[2].i
+            )
+            .unwrap(),
+            public_key_shares[2]
+        );
+
+        // The key shares of guardians 1, 3, and 5 interpolate to the joint secret key.
+        let s = guardian_secret_keys
+            .iter()
+            .fold(BigUint::zero(), |acc, secret_key| {
+                (acc + secret_key.secret_s()) % q
+            });
+        let present = [&key_shares[0], &key_shares[2], &key_shares[4]];
+        let guardians = present
+            .iter()
+            .map(|key_share| key_share.i)
+            .collect::<Vec<_>>();
+        let interpolated = present.iter().fold(BigUint::zero(), |acc, key_share| {
+            let w_l = lagrange_coefficient(fixed_parameters, key_share.i, &guardians);
+            (acc + w_l * key_share.secret_p_i()) % q
+        });
+        assert_eq!(interpolated, s);
+
+        // A missing share is reported.
+        let mut incomplete = share_distribution.clone();
+        incomplete
+            .shares
+            .retain(|s| !(s.sender_i == key_shares[0].i && s.recipient_i == key_shares[1].i));
+        let err = GuardianKeyShare::compute(
+            &election_parameters,
+            &guardian_secret_keys[1],
+            &guardian_public_keys,
+            &incomplete,
+        )
+        .unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("No share from guardian 1 to guardian 2"),
+            "{err}"
+        );
+    }
+}
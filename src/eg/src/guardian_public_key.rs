@@ -5,7 +5,7 @@
 #![deny(clippy::panic)]
 #![deny(clippy::manual_assert)]
 
-use anyhow::{Context, Result};
+use anyhow::{ensure, Context, Result};
 use num_bigint::BigUint;
 use serde::{Deserialize, Serialize};
 
@@ -13,14 +13,16 @@ use util::integer_util::to_be_bytes_left_pad;
 
 use crate::{
     election_parameters::ElectionParameters,
-    fixed_parameters::FixedParameters,
+    fixed_parameters::{ElectionGuardDesignSpecificationVersion, FixedParameters},
     guardian::GuardianIndex,
+    guardian_key_proof::CoefficientProofs,
     guardian_public_key_info::{validate_guardian_public_key_info, GuardianPublicKeyInfo},
     guardian_secret_key::CoefficientCommitments,
 };
 
 /// Public key for a guardian.
 #[derive(Clone, Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "strict-parsing", serde(deny_unknown_fields))]
 pub struct GuardianPublicKey {
     /// Guardian number, 1 <= i <= n.
     pub i: GuardianIndex,
@@ -29,8 +31,38 @@ pub struct GuardianPublicKey {
     #[serde(rename = "name", skip_serializing_if = "Option::is_none")]
     pub opt_name: Option<String>,
 
+    /// The version of the ElectionGuard Design Specification named in the election parameters
+    /// under which the key was generated, if it was recorded. Keys generated under
+    /// different versions can't be combined, and a key without it can't be combined with
+    /// keys or parameters which have one.
+    #[serde(
+        rename = "parameters_version",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub opt_parameters_version: Option<ElectionGuardDesignSpecificationVersion>,
+
     /// "Published" polynomial coefficient commitments.
     pub coefficient_commitments: CoefficientCommitments,
+
+    /// Proofs of knowledge of the secret coefficient behind each commitment. Keys generated
+    /// before these were published have none.
+    #[serde(default, skip_serializing_if = "CoefficientProofs::is_empty")]
+    pub coefficient_proofs: CoefficientProofs,
+}
+
+/// A one-line summary, e.g. `GuardianPublicKey{i=2, name=Some("Guardian 2"), commitments=3}`.
+/// Unlike `Debug`, this does not write out the commitments themselves.
+impl std::fmt::Display for GuardianPublicKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "GuardianPublicKey{{i={}, name={:?}, commitments={}}}",
+            self.i,
+            self.opt_name,
+            self.coefficient_commitments.0.len()
+        )
+    }
 }
 
 impl GuardianPublicKeyInfo for GuardianPublicKey {
@@ -48,19 +80,63 @@ impl GuardianPublicKeyInfo for GuardianPublicKey {
 }
 
 impl GuardianPublicKey {
+    /// Assembles the public key of guardian `i` from its published coefficient commitments,
+    /// without its secret key.
+    ///
+    /// The key is validated as by [`GuardianPublicKey::validate`], and every commitment must
+    /// also be in the order-`q` subgroup. It has no [`CoefficientProofs`], so it can be used
+    /// to compute the joint election public key, but not to check the guardian's proofs of
+    /// knowledge.
+    pub fn from_commitments(
+        i: GuardianIndex,
+        opt_name: Option<String>,
+        coefficient_commitments: CoefficientCommitments,
+        election_parameters: &ElectionParameters,
+    ) -> Result<Self> {
+        let fixed_parameters = &election_parameters.fixed_parameters;
+
+        let self_ = GuardianPublicKey {
+            i,
+            opt_name,
+            opt_parameters_version: fixed_parameters
+                .opt_ElectionGuard_Design_Specification
+                .clone(),
+            coefficient_commitments,
+            coefficient_proofs: CoefficientProofs::default(),
+        };
+
+        self_.validate(election_parameters)?;
+
+        for (j, commitment) in self_.coefficient_commitments.0.iter().enumerate() {
+            ensure!(
+                fixed_parameters.is_in_subgroup(&commitment.0),
+                "Guardian {i} coefficient commitment {j} is not in the subgroup of order q"
+            );
+        }
+
+        Ok(self_)
+    }
+
     /// Reads a `GuardianPublicKey` from a `std::io::Read` and validates it.
     pub fn from_stdioread_validated(
         stdioread: &mut dyn std::io::Read,
         election_parameters: &ElectionParameters,
     ) -> Result<Self> {
-        let self_: Self =
-            serde_json::from_reader(stdioread).context("Reading GuardianPublicKey")?;
+        let self_ = Self::from_stdioread(stdioread)?;
 
         self_.validate(election_parameters)?;
 
         Ok(self_)
     }
 
+    /// Reads a `GuardianPublicKey` from a `std::io::Read` without validating it.
+    ///
+    /// The caller is responsible for calling [`GuardianPublicKey::validate`] before using it.
+    /// This allows many keys to be parsed first and validated later as a batch.
+    pub fn from_stdioread(stdioread: &mut dyn std::io::Read) -> Result<Self> {
+        serde_json::from_reader(stdioread).context("Reading GuardianPublicKey")
+    }
+
     /// Verifies that the `GuardianPublicKey` is well-formed
     /// and conforms to the election parameters.
     /// Useful after deserialization.
@@ -83,11 +159,16 @@ impl GuardianPublicKey {
     /// Returns a pretty JSON `String` representation of the `GuardianPublicKey`.
     /// The final line will end with a newline.
     pub fn to_json(&self) -> String {
-        // `unwrap()` is justified here because why would JSON serialization fail?
+        let mut v = Vec::new();
+
+        // `unwrap()` is justified here because writing to a `Vec` can't fail, and
+        // why would JSON serialization fail?
         #[allow(clippy::unwrap_used)]
-        let mut s = serde_json::to_string_pretty(self).unwrap();
-        s.push('\n');
-        s
+        self.to_stdiowrite(&mut v).unwrap();
+
+        // `unwrap()` is justified here because `serde_json` only writes valid UTF-8.
+        #[allow(clippy::unwrap_used)]
+        String::from_utf8(v).unwrap()
     }
 
     /// Writes a `GuardianPublicKey` to a `std::io::Write`.
@@ -104,14 +185,214 @@ impl GuardianPublicKey {
 #[cfg(test)]
 #[allow(clippy::unwrap_used)]
 mod test {
-    //use super::*;
+    use super::*;
     use crate::{
+        example_election::ExampleElection,
         example_election_parameters::example_election_parameters,
-        guardian_secret_key::GuardianSecretKey,
+        guardian_secret_key::GuardianSecretKey, joint_election_public_key::JointElectionPublicKey,
     };
     use std::borrow::Borrow;
     use util::csprng::Csprng;
 
+    #[test]
+    fn test_multi_line_name() {
+        let mut csprng = Csprng::new(b"test_multi_line_name");
+
+        let election_parameters = example_election_parameters();
+
+        let i = election_parameters
+            .varying_parameters
+            .each_guardian_i()
+            .next()
+            .unwrap();
+
+        let name = "Guardian 1\nGuardian 2".to_string();
+
+        assert!(GuardianSecretKey::generate(
+            &mut csprng,
+            &election_parameters,
+            i,
+            Some(name.clone())
+        )
+        .is_err());
+
+        // A name that was not checked when the key was made is caught on deserialization.
+        let mut public_key =
+            GuardianSecretKey::generate(&mut csprng, &election_parameters, i, None)
+                .unwrap()
+                .make_public_key();
+        public_key.opt_name = Some(name);
+        let json = public_key.to_json();
+
+        assert!(GuardianPublicKey::from_stdioread_validated(
+            &mut json.as_bytes(),
+            &election_parameters
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_from_commitments() {
+        let mut csprng = Csprng::new(b"test_from_commitments");
+
+        let election_parameters = example_election_parameters();
+        let fixed_parameters = &election_parameters.fixed_parameters;
+
+        let (_, guardian_public_keys) =
+            ExampleElection::generate_guardian_keys(&mut csprng, &election_parameters).unwrap();
+
+        // Only the published commitments are used.
+        let from_commitments = guardian_public_keys
+            .iter()
+            .map(|public_key| {
+                GuardianPublicKey::from_commitments(
+                    public_key.i,
+                    Some(format!("Guardian {}", public_key.i)),
+                    public_key.coefficient_commitments.clone(),
+                    &election_parameters,
+                )
+                .unwrap()
+            })
+            .collect::<Vec<_>>();
+
+        for (public_key, public_key_2) in guardian_public_keys.iter().zip(&from_commitments) {
+            public_key_2.validate(&election_parameters).unwrap();
+            assert_eq!(public_key_2.i, public_key.i);
+            assert_eq!(
+                public_key_2.opt_parameters_version,
+                public_key.opt_parameters_version
+            );
+            assert_eq!(
+                public_key_2.public_key_k_i_0(),
+                public_key.public_key_k_i_0()
+            );
+            assert!(public_key_2.coefficient_proofs.is_empty());
+        }
+
+        let joint_key =
+            JointElectionPublicKey::compute(&election_parameters, &guardian_public_keys).unwrap();
+        let joint_key_2 =
+            JointElectionPublicKey::compute(&election_parameters, &from_commitments).unwrap();
+        assert!(joint_key.equals(&joint_key_2, fixed_parameters));
+
+        let public_key = &guardian_public_keys[0];
+        let from = |commitments: CoefficientCommitments| {
+            GuardianPublicKey::from_commitments(
+                public_key.i,
+                None,
+                commitments,
+                &election_parameters,
+            )
+        };
+
+        // Too few commitments.
+        let mut commitments = public_key.coefficient_commitments.clone();
+        commitments.0.pop();
+        assert!(from(commitments).is_err());
+
+        // A commitment which is a valid value mod p, but not in the subgroup.
+        let mut commitments = public_key.coefficient_commitments.clone();
+        commitments.0[1].0 = fixed_parameters.p.as_ref() - 1u8;
+        let err = from(commitments).unwrap_err();
+        assert!(err.to_string().contains("subgroup"), "{err}");
+    }
+
+    #[test]
+    fn test_from_stdioread_then_validate() {
+        let mut csprng = Csprng::new(b"test_from_stdioread_then_validate");
+
+        let election_parameters = example_election_parameters();
+
+        let i = election_parameters
+            .varying_parameters
+            .each_guardian_i()
+            .next()
+            .unwrap();
+        let public_key = GuardianSecretKey::generate(&mut csprng, &election_parameters, i, None)
+            .unwrap()
+            .make_public_key();
+
+        let json = public_key.to_json();
+
+        // Parsing alone doesn't check the key against the election parameters.
+        let mut invalid_public_key = public_key.clone();
+        invalid_public_key.coefficient_commitments.0[0].0 =
+            election_parameters.fixed_parameters.p.as_ref().clone();
+        let invalid_json = invalid_public_key.to_json();
+
+        let invalid_public_key =
+            GuardianPublicKey::from_stdioread(&mut invalid_json.as_bytes()).unwrap();
+        assert!(invalid_public_key.validate(&election_parameters).is_err());
+
+        // Nor is zero, or p + 1, a valid commitment.
+        let p: &BigUint = election_parameters.fixed_parameters.p.as_ref();
+        for k in [BigUint::from(0_u8), p + 1_u8] {
+            let mut invalid_public_key = public_key.clone();
+            invalid_public_key.coefficient_commitments.0[0].0 = k;
+            assert!(invalid_public_key.validate(&election_parameters).is_err());
+        }
+        assert!(GuardianPublicKey::from_stdioread_validated(
+            &mut invalid_json.as_bytes(),
+            &election_parameters
+        )
+        .is_err());
+
+        let public_key_2 = GuardianPublicKey::from_stdioread(&mut json.as_bytes()).unwrap();
+        public_key_2.validate(&election_parameters).unwrap();
+        assert_eq!(public_key_2.to_json(), json);
+    }
+
+    #[test]
+    fn test_to_stdiowrite_round_trip() {
+        let mut csprng = Csprng::new(b"test_to_stdiowrite_round_trip");
+
+        let election_parameters = example_election_parameters();
+
+        let i = election_parameters
+            .varying_parameters
+            .each_guardian_i()
+            .next()
+            .unwrap();
+        let secret_key = GuardianSecretKey::generate(
+            &mut csprng,
+            &election_parameters,
+            i,
+            Some("Guardian 1".to_string()),
+        )
+        .unwrap();
+        let public_key = secret_key.make_public_key();
+
+        let mut secret_key_bytes = Vec::new();
+        secret_key.to_stdiowrite(&mut secret_key_bytes).unwrap();
+        assert!(secret_key_bytes.ends_with(b"}\n"));
+
+        let secret_key_2 = GuardianSecretKey::from_stdioread_validated(
+            &mut secret_key_bytes.as_slice(),
+            &election_parameters,
+        )
+        .unwrap();
+        let mut secret_key_bytes_2 = Vec::new();
+        secret_key_2.to_stdiowrite(&mut secret_key_bytes_2).unwrap();
+        assert_eq!(secret_key_bytes_2, secret_key_bytes);
+
+        let mut public_key_bytes = Vec::new();
+        public_key.to_stdiowrite(&mut public_key_bytes).unwrap();
+        assert!(public_key_bytes.ends_with(b"}\n"));
+        assert_eq!(public_key_bytes, public_key.to_json().as_bytes());
+
+        let public_key_2 = GuardianPublicKey::from_stdioread_validated(
+            &mut public_key_bytes.as_slice(),
+            &election_parameters,
+        )
+        .unwrap();
+        assert_eq!(public_key_2.to_json().as_bytes(), public_key_bytes);
+
+        assert_eq!(
+            public_key.to_string(),
+            r#"GuardianPublicKey{i=1, name=Some("Guardian 1"), commitments=3}"#
+        );
+    }
+
     #[test]
     fn test_key_generation() {
         let mut csprng = Csprng::new(b"test_key_generation");
@@ -122,15 +403,8 @@ mod test {
 
         let k = varying_parameters.k.as_quantity();
 
-        let guardian_secret_keys = varying_parameters
-            .each_guardian_i()
-            .map(|i| GuardianSecretKey::generate(&mut csprng, &election_parameters, i, None))
-            .collect::<Vec<_>>();
-
-        let guardian_public_keys = guardian_secret_keys
-            .iter()
-            .map(|secret_key| secret_key.make_public_key())
-            .collect::<Vec<_>>();
+        let (guardian_secret_keys, guardian_public_keys) =
+            ExampleElection::generate_guardian_keys(&mut csprng, &election_parameters).unwrap();
 
         for guardian_secret_key in guardian_secret_keys.iter() {
             assert_eq!(guardian_secret_key.secret_coefficients.0.len(), k);
@@ -166,4 +440,34 @@ mod test {
             );
         }
     }
+
+    #[test]
+    fn test_unknown_field() {
+        let mut csprng = Csprng::new(b"test_unknown_field");
+
+        let election_parameters = example_election_parameters();
+        let i = election_parameters
+            .varying_parameters
+            .each_guardian_i()
+            .next()
+            .unwrap();
+        let public_key = GuardianSecretKey::generate(&mut csprng, &election_parameters, i, None)
+            .unwrap()
+            .make_public_key();
+
+        let mut value: serde_json::Value = serde_json::from_str(&public_key.to_json()).unwrap();
+        value
+            .as_object_mut()
+            .unwrap()
+            .insert("bogus".into(), 1.into());
+        let json = serde_json::to_vec(&value).unwrap();
+
+        let result = GuardianPublicKey::from_stdioread(&mut json.as_slice());
+        if cfg!(feature = "strict-parsing") {
+            let err = format!("{:#}", result.unwrap_err());
+            assert!(err.contains("unknown field `bogus`"), "{err}");
+        } else {
+            assert_eq!(result.unwrap().to_json(), public_key.to_json());
+        }
+    }
 }
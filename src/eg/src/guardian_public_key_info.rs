@@ -6,6 +6,7 @@
 #![deny(clippy::manual_assert)]
 
 use anyhow::{ensure, Context, Result};
+use unicode_normalization::{is_nfc, UnicodeNormalization};
 
 use crate::{
     election_parameters::ElectionParameters, guardian::GuardianIndex,
@@ -25,6 +26,42 @@ pub trait GuardianPublicKeyInfo {
     fn coefficient_commitments(&self) -> &CoefficientCommitments;
 }
 
+/// Maximum length of a guardian name, in `char`s.
+pub const GUARDIAN_NAME_MAX_CHARS: usize = 128;
+
+/// Verifies that a guardian name is acceptable: it must not be empty or longer than
+/// [`GUARDIAN_NAME_MAX_CHARS`], must not contain any control characters (including line
+/// breaks), and must be in Unicode Normalization Form C.
+pub fn validate_guardian_name(name: &str) -> Result<()> {
+    ensure!(!name.is_empty(), "Guardian name must not be empty");
+
+    let cnt_chars = name.chars().count();
+    ensure!(
+        cnt_chars <= GUARDIAN_NAME_MAX_CHARS,
+        "Guardian name is {cnt_chars} characters, which exceeds the maximum of {GUARDIAN_NAME_MAX_CHARS}"
+    );
+
+    ensure!(
+        !name.chars().any(char::is_control),
+        "Guardian name must not contain a line break or other control character: {name:?}"
+    );
+
+    ensure!(
+        is_nfc(name),
+        "Guardian name is not in Unicode Normalization Form C: {name:?}"
+    );
+
+    Ok(())
+}
+
+/// Converts a guardian name to Unicode Normalization Form C and verifies that it is
+/// acceptable according to [`validate_guardian_name`].
+pub fn normalize_guardian_name(name: &str) -> Result<String> {
+    let name: String = name.nfc().collect();
+    validate_guardian_name(&name)?;
+    Ok(name)
+}
+
 /// Verifies that the thing implementing `GuardianPublicKeyInfo` is well-formed and conforms
 /// to the election parameters.
 /// Useful after deserialization.
@@ -43,10 +80,7 @@ pub(crate) fn validate_guardian_public_key_info(
     );
 
     if let Some(name) = &gpki.opt_name() {
-        ensure!(
-            !name.contains('\n'),
-            "Guardian name must not contain a newline"
-        );
+        validate_guardian_name(name)?;
     }
 
     let coefficient_commitments_len = gpki.coefficient_commitments().0.len();
@@ -63,3 +97,30 @@ pub(crate) fn validate_guardian_public_key_info(
 
     Ok(())
 }
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_guardian_name() {
+        assert_eq!(normalize_guardian_name("Guardian 1").unwrap(), "Guardian 1");
+
+        // "e" followed by a combining acute accent composes to a single "é".
+        let decomposed = "Jose\u{301}";
+        assert!(validate_guardian_name(decomposed).is_err());
+        let normalized = normalize_guardian_name(decomposed).unwrap();
+        assert_eq!(normalized, "Jos\u{e9}");
+        assert!(validate_guardian_name(&normalized).is_ok());
+
+        assert!(normalize_guardian_name("Guardian 1\nGuardian 2").is_err());
+        assert!(normalize_guardian_name("Guardian 1\r").is_err());
+        assert!(normalize_guardian_name("Guardian\t1").is_err());
+        assert!(normalize_guardian_name("").is_err());
+
+        let long_name = "x".repeat(GUARDIAN_NAME_MAX_CHARS);
+        assert!(normalize_guardian_name(&long_name).is_ok());
+        assert!(normalize_guardian_name(&(long_name + "x")).is_err());
+    }
+}
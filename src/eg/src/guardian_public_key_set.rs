@@ -0,0 +1,159 @@
+// Copyright (C) Microsoft Corporation. All rights reserved.
+
+#![deny(clippy::unwrap_used)]
+#![deny(clippy::expect_used)]
+#![deny(clippy::panic)]
+#![deny(clippy::manual_assert)]
+
+use std::collections::BTreeMap;
+
+use anyhow::{ensure, Result};
+
+use crate::{guardian::GuardianIndex, guardian_public_key::GuardianPublicKey};
+
+/// A set of guardian public keys, at most one per guardian number, such as those found in
+/// one participant's copy of the key ceremony artifacts.
+///
+/// Unlike the keys passed to
+/// [`JointElectionPublicKey::compute`](crate::joint_election_public_key::JointElectionPublicKey::compute),
+/// the set need not be complete.
+#[derive(Debug, Clone, Default)]
+pub struct GuardianPublicKeySet {
+    keys: BTreeMap<GuardianIndex, GuardianPublicKey>,
+}
+
+impl GuardianPublicKeySet {
+    /// Collects the keys into a set. Fails if two keys are for the same guardian.
+    pub fn new(guardian_public_keys: impl IntoIterator<Item = GuardianPublicKey>) -> Result<Self> {
+        let mut keys = BTreeMap::new();
+        for gpk in guardian_public_keys {
+            let i = gpk.i;
+            ensure!(
+                keys.insert(i, gpk).is_none(),
+                "More than one public key for guardian {i}"
+            );
+        }
+        Ok(GuardianPublicKeySet { keys })
+    }
+
+    /// Returns the number of keys in the set.
+    pub fn len(&self) -> usize {
+        self.keys.len()
+    }
+
+    /// Returns true if the set has no keys.
+    pub fn is_empty(&self) -> bool {
+        self.keys.is_empty()
+    }
+
+    /// Returns the key of guardian `i`, if present.
+    pub fn get(&self, i: GuardianIndex) -> Option<&GuardianPublicKey> {
+        self.keys.get(&i)
+    }
+
+    /// Iterates over the guardian numbers present, in increasing order.
+    pub fn indices(&self) -> impl Iterator<Item = GuardianIndex> + '_ {
+        self.keys.keys().copied()
+    }
+
+    /// Compares this set of keys (`a`) with `other` (`b`).
+    ///
+    /// Two keys for the same guardian match if they have the same coefficient commitments.
+    /// The names are not compared, as they don't affect any computation.
+    pub fn diff(&self, other: &GuardianPublicKeySet) -> KeySetDiff {
+        let mut diff = KeySetDiff::default();
+
+        for (&i, gpk_a) in &self.keys {
+            match other.keys.get(&i) {
+                None => diff.only_in_a.push(i),
+                Some(gpk_b) if gpk_a.coefficient_commitments == gpk_b.coefficient_commitments => {
+                    diff.matching.push(i)
+                }
+                Some(_) => diff.differing.push(i),
+            }
+        }
+
+        diff.only_in_b = other
+            .indices()
+            .filter(|i| !self.keys.contains_key(i))
+            .collect();
+
+        diff
+    }
+}
+
+/// The result of [`GuardianPublicKeySet::diff`]. Each list is in increasing order.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct KeySetDiff {
+    /// Guardians with a key in `a` but not in `b`.
+    pub only_in_a: Vec<GuardianIndex>,
+
+    /// Guardians with a key in `b` but not in `a`.
+    pub only_in_b: Vec<GuardianIndex>,
+
+    /// Guardians with a key in both sets, but different coefficient commitments.
+    pub differing: Vec<GuardianIndex>,
+
+    /// Guardians with the same key in both sets.
+    pub matching: Vec<GuardianIndex>,
+}
+
+impl KeySetDiff {
+    /// Returns true if both sets have the same keys for the same guardians.
+    pub fn is_identical(&self) -> bool {
+        self.only_in_a.is_empty() && self.only_in_b.is_empty() && self.differing.is_empty()
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod test {
+    use super::*;
+    use crate::{
+        example_election::ExampleElection,
+        example_election_parameters::example_election_parameters,
+        guardian_secret_key::GuardianSecretKey,
+    };
+    use util::csprng::Csprng;
+
+    #[test]
+    fn test_diff() {
+        let mut csprng = Csprng::new(b"test_diff");
+
+        let election_parameters = example_election_parameters();
+        let (_, mut guardian_public_keys) =
+            ExampleElection::generate_guardian_keys(&mut csprng, &election_parameters).unwrap();
+        let ix = |i: u32| GuardianIndex::from_one_based_index(i).unwrap();
+
+        let a = GuardianPublicKeySet::new(guardian_public_keys[..4].to_vec()).unwrap();
+        assert!(a.diff(&a).is_identical());
+
+        // b lacks guardian 1, has guardian 5, a different key for guardian 3, and a
+        // different name for guardian 4.
+        guardian_public_keys[2] =
+            GuardianSecretKey::generate(&mut csprng, &election_parameters, ix(3), None)
+                .unwrap()
+                .make_public_key();
+        guardian_public_keys[3].opt_name = Some("Guardian 4".to_string());
+        let b = GuardianPublicKeySet::new(guardian_public_keys[1..].to_vec()).unwrap();
+
+        let diff = a.diff(&b);
+        assert!(!diff.is_identical());
+        assert_eq!(
+            diff,
+            KeySetDiff {
+                only_in_a: vec![ix(1)],
+                only_in_b: vec![ix(5)],
+                differing: vec![ix(3)],
+                matching: vec![ix(2), ix(4)],
+            }
+        );
+
+        let err = GuardianPublicKeySet::new(vec![
+            guardian_public_keys[0].clone(),
+            guardian_public_keys[0].clone(),
+        ])
+        .unwrap_err();
+        assert!(err.to_string().contains("More than one"), "{err}");
+    }
+}
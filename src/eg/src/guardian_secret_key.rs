@@ -15,10 +15,13 @@ use util::csprng::Csprng;
 
 use crate::{
     election_parameters::ElectionParameters,
-    fixed_parameters::FixedParameters,
+    fixed_parameters::{ElectionGuardDesignSpecificationVersion, FixedParameters},
     guardian::GuardianIndex,
+    guardian_key_proof::CoefficientProofs,
     guardian_public_key::GuardianPublicKey,
-    guardian_public_key_info::{validate_guardian_public_key_info, GuardianPublicKeyInfo},
+    guardian_public_key_info::{
+        normalize_guardian_name, validate_guardian_public_key_info, GuardianPublicKeyInfo,
+    },
 };
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -59,7 +62,7 @@ impl SecretCoefficients {
     }
 }
 
-#[derive(Clone, Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub struct CoefficientCommitment(
     #[serde(
         serialize_with = "util::biguint_serde::biguint_serialize",
@@ -73,8 +76,10 @@ impl CoefficientCommitment {
     /// to the election parameters. Useful after deserialization.
     pub(crate) fn validate(&self, election_parameters: &ElectionParameters) -> Result<()> {
         ensure!(
-            election_parameters.fixed_parameters.is_valid_modp(&self.0),
-            "Coefficient commitment is not a valid mod p value"
+            election_parameters
+                .fixed_parameters
+                .is_valid_nonzero_modp(&self.0),
+            "Coefficient commitment is not a valid nonzero mod p value"
         );
 
         Ok(())
@@ -86,7 +91,7 @@ impl CoefficientCommitment {
     }
 }
 
-#[derive(Clone, Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub struct CoefficientCommitments(pub Vec<CoefficientCommitment>);
 
 impl CoefficientCommitments {
@@ -112,6 +117,7 @@ impl CoefficientCommitments {
 
 /// Secret key for a guardian.
 #[derive(Clone, Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "strict-parsing", serde(deny_unknown_fields))]
 pub struct GuardianSecretKey {
     /// Guardian number, 1 <= i <= [`crate::varying_parameters::VaryingParameters::n`].
     pub i: GuardianIndex,
@@ -120,11 +126,26 @@ pub struct GuardianSecretKey {
     #[serde(rename = "name", skip_serializing_if = "Option::is_none")]
     pub opt_name: Option<String>,
 
+    /// The version of the ElectionGuard Design Specification named in the election parameters
+    /// under which the key was generated, if it was recorded. Keys generated under
+    /// different versions can't be combined.
+    #[serde(
+        rename = "parameters_version",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub opt_parameters_version: Option<ElectionGuardDesignSpecificationVersion>,
+
     /// Secret polynomial coefficients.
     pub secret_coefficients: SecretCoefficients,
 
     /// "Published" polynomial coefficient commitments.
     pub coefficient_commitments: CoefficientCommitments,
+
+    /// Proofs of knowledge of the secret coefficient behind each commitment. Keys generated
+    /// before these were published have none.
+    #[serde(default, skip_serializing_if = "CoefficientProofs::is_empty")]
+    pub coefficient_proofs: CoefficientProofs,
 }
 
 impl GuardianPublicKeyInfo for GuardianSecretKey {
@@ -142,12 +163,20 @@ impl GuardianPublicKeyInfo for GuardianSecretKey {
 }
 
 impl GuardianSecretKey {
+    /// Generates a new secret key for guardian `i`.
+    ///
+    /// The name, if any, is normalized with [`normalize_guardian_name`], and an error is
+    /// returned if it is not acceptable.
     pub fn generate(
         csprng: &mut Csprng,
         election_parameters: &ElectionParameters,
         i: GuardianIndex,
         opt_name: Option<String>,
-    ) -> Self {
+    ) -> Result<Self> {
+        let opt_name = opt_name
+            .map(|name| normalize_guardian_name(&name))
+            .transpose()?;
+
         let secret_coefficients = SecretCoefficients::generate(csprng, election_parameters);
         assert_ne!(secret_coefficients.0.len(), 0);
 
@@ -157,12 +186,25 @@ impl GuardianSecretKey {
         );
         assert_ne!(secret_coefficients.0.len(), 0);
 
-        GuardianSecretKey {
+        let coefficient_proofs = CoefficientProofs::generate(
+            csprng,
+            election_parameters,
+            i,
+            &secret_coefficients,
+            &coefficient_commitments,
+        );
+
+        Ok(GuardianSecretKey {
             secret_coefficients,
             coefficient_commitments,
+            coefficient_proofs,
             i,
             opt_name,
-        }
+            opt_parameters_version: election_parameters
+                .fixed_parameters
+                .opt_ElectionGuard_Design_Specification
+                .clone(),
+        })
     }
 
     /// Reads a `GuardianSecretKey` from a `std::io::Read` and validates it.
@@ -170,19 +212,60 @@ impl GuardianSecretKey {
         stdioread: &mut dyn std::io::Read,
         election_parameters: &ElectionParameters,
     ) -> Result<Self> {
-        let self_: Self =
-            serde_json::from_reader(stdioread).context("Reading GuardianSecretKey")?;
+        let self_ = Self::from_stdioread(stdioread)?;
 
         self_.validate(election_parameters)?;
 
         Ok(self_)
     }
 
+    /// Reads a `GuardianSecretKey` from a `std::io::Read` without validating it.
+    ///
+    /// The caller is responsible for calling [`GuardianSecretKey::validate`] before using it.
+    pub fn from_stdioread(stdioread: &mut dyn std::io::Read) -> Result<Self> {
+        serde_json::from_reader(stdioread).context("Reading GuardianSecretKey")
+    }
+
     /// Verifies that the `GuardianSecretKey` is well-formed
     /// and conforms to the election parameters.
     /// Useful after deserialization.
+    ///
+    /// In addition to the checks made on the public part, this verifies that every secret
+    /// coefficient matches its commitment, so a damaged key file is detected.
     pub fn validate(&self, election_parameters: &ElectionParameters) -> Result<()> {
-        validate_guardian_public_key_info(self, election_parameters)
+        validate_guardian_public_key_info(self, election_parameters)?;
+
+        let fixed_parameters = &election_parameters.fixed_parameters;
+        let i = self.i;
+
+        ensure!(
+            self.secret_coefficients.0.len() == self.coefficient_commitments.0.len(),
+            "Guardian {i} secret key has {} secret coefficients but {} commitments",
+            self.secret_coefficients.0.len(),
+            self.coefficient_commitments.0.len()
+        );
+
+        for (j, (secret_coefficient, coefficient_commitment)) in self
+            .secret_coefficients
+            .0
+            .iter()
+            .zip(self.coefficient_commitments.0.iter())
+            .enumerate()
+        {
+            ensure!(
+                fixed_parameters.is_valid_modq(&secret_coefficient.0),
+                "Guardian {i} secret coefficient {j} is not a valid mod q value"
+            );
+            ensure!(
+                fixed_parameters
+                    .g
+                    .modpow(&secret_coefficient.0, fixed_parameters.p.as_ref())
+                    == coefficient_commitment.0,
+                "Guardian {i} secret coefficient {j} does not match its commitment"
+            );
+        }
+
+        Ok(())
     }
 
     pub fn secret_coefficients(&self) -> &SecretCoefficients {
@@ -197,8 +280,55 @@ impl GuardianSecretKey {
         GuardianPublicKey {
             i: self.i,
             opt_name: self.opt_name.clone(),
+            opt_parameters_version: self.opt_parameters_version.clone(),
             coefficient_commitments: self.coefficient_commitments.clone(),
+            coefficient_proofs: self.coefficient_proofs.clone(),
+        }
+    }
+
+    /// Verifies that `public_key` is the public key of this guardian, by recomputing the
+    /// coefficient commitments from the secret coefficients.
+    ///
+    /// Useful when restoring a guardian's keys from separate backups, to detect a swapped or
+    /// corrupted file before it is used for decryption.
+    pub fn verify_matches_public(
+        &self,
+        public_key: &GuardianPublicKey,
+        election_parameters: &ElectionParameters,
+    ) -> Result<()> {
+        let i = self.i;
+
+        ensure!(
+            public_key.i == i,
+            "Public key is for guardian {}, but the secret key is for guardian {i}",
+            public_key.i
+        );
+
+        let coefficient_commitments = CoefficientCommitments::new(
+            &election_parameters.fixed_parameters,
+            &self.secret_coefficients,
+        );
+
+        ensure!(
+            coefficient_commitments.0.len() == public_key.coefficient_commitments.0.len(),
+            "Guardian {i} public key has {} coefficient commitments, but the secret key has {}",
+            public_key.coefficient_commitments.0.len(),
+            coefficient_commitments.0.len()
+        );
+
+        for (j, (expected, actual)) in coefficient_commitments
+            .0
+            .iter()
+            .zip(public_key.coefficient_commitments.0.iter())
+            .enumerate()
+        {
+            ensure!(
+                expected.0 == actual.0,
+                "Guardian {i} public key coefficient commitment {j} does not match the secret key"
+            );
         }
+
+        Ok(())
     }
 
     /// Writes a `GuardianSecretKey` to a `std::io::Write`.
@@ -211,3 +341,229 @@ impl GuardianSecretKey {
             .context("Writing GuardianSecretKey")
     }
 }
+
+/// The PEM tag of a [`GuardianSecretKey`].
+#[cfg(feature = "pem")]
+pub const GUARDIAN_SECRET_KEY_PEM_TAG: &str = "ELECTIONGUARD GUARDIAN SECRET KEY";
+
+/// The PEM header recording the parameter base hash `H_P` of the election parameters for which
+/// the key was generated.
+#[cfg(feature = "pem")]
+const PEM_HEADER_H_P: &str = "Parameter-Base-Hash";
+
+#[cfg(feature = "pem")]
+impl GuardianSecretKey {
+    /// Returns the `GuardianSecretKey` as a PEM block containing its JSON representation.
+    ///
+    /// The parameter base hash `H_P` is recorded in a header, so that [`GuardianSecretKey::from_pem`]
+    /// can refuse to load the key for a different set of election parameters.
+    pub fn to_pem(&self, election_parameters: &ElectionParameters) -> Result<String> {
+        let h_p = crate::hashes::Hashes::compute_h_p(election_parameters);
+
+        let mut json = Vec::new();
+        self.to_stdiowrite(&mut json)?;
+
+        let mut pem = pem::Pem::new(GUARDIAN_SECRET_KEY_PEM_TAG, json);
+        pem.headers_mut()
+            .add(PEM_HEADER_H_P, &h_p.to_string())
+            .context("Writing GuardianSecretKey PEM header")?;
+
+        Ok(pem::encode(&pem))
+    }
+
+    /// Reads a `GuardianSecretKey` from a PEM block written by [`GuardianSecretKey::to_pem`],
+    /// and validates it.
+    pub fn from_pem(pem_str: &str, election_parameters: &ElectionParameters) -> Result<Self> {
+        let pem = pem::parse(pem_str).context("Reading GuardianSecretKey PEM")?;
+
+        ensure!(
+            pem.tag() == GUARDIAN_SECRET_KEY_PEM_TAG,
+            "Expected a PEM block tagged {GUARDIAN_SECRET_KEY_PEM_TAG:?}, found {:?}",
+            pem.tag()
+        );
+
+        let Some(h_p_str) = pem.headers().get(PEM_HEADER_H_P) else {
+            anyhow::bail!("GuardianSecretKey PEM has no {PEM_HEADER_H_P} header");
+        };
+        let h_p: crate::hash::HValue = h_p_str
+            .parse()
+            .with_context(|| format!("Reading GuardianSecretKey PEM {PEM_HEADER_H_P} header"))?;
+
+        ensure!(
+            h_p == crate::hashes::Hashes::compute_h_p(election_parameters),
+            "GuardianSecretKey PEM was written for different election parameters"
+        );
+
+        Self::from_stdioread_validated(&mut pem.contents(), election_parameters)
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod test {
+    use super::*;
+    use crate::example_election_parameters::example_election_parameters;
+
+    #[test]
+    fn test_validate_detects_mismatched_secret_coefficient() {
+        let mut csprng = Csprng::new(b"test_validate_detects_mismatched_secret_coefficient");
+
+        let election_parameters = example_election_parameters();
+
+        let i = election_parameters
+            .varying_parameters
+            .each_guardian_i()
+            .next()
+            .unwrap();
+        let mut secret_key =
+            GuardianSecretKey::generate(&mut csprng, &election_parameters, i, None).unwrap();
+        secret_key.validate(&election_parameters).unwrap();
+
+        secret_key.secret_coefficients.0[1].0 += 1u8;
+        let err = secret_key.validate(&election_parameters).unwrap_err();
+        assert!(err.to_string().contains("does not match its commitment"));
+    }
+
+    #[test]
+    fn test_verify_matches_public() {
+        let mut csprng = Csprng::new(b"test_verify_matches_public");
+
+        let election_parameters = example_election_parameters();
+
+        let mut each_guardian_i = election_parameters.varying_parameters.each_guardian_i();
+        let i_1 = each_guardian_i.next().unwrap();
+        let i_2 = each_guardian_i.next().unwrap();
+
+        let secret_key_1 =
+            GuardianSecretKey::generate(&mut csprng, &election_parameters, i_1, None).unwrap();
+        let public_key_1 = secret_key_1.make_public_key();
+        secret_key_1
+            .verify_matches_public(&public_key_1, &election_parameters)
+            .unwrap();
+
+        // Another guardian's public key.
+        let public_key_2 =
+            GuardianSecretKey::generate(&mut csprng, &election_parameters, i_2, None)
+                .unwrap()
+                .make_public_key();
+        assert!(secret_key_1
+            .verify_matches_public(&public_key_2, &election_parameters)
+            .is_err());
+
+        // A different key for the same guardian, e.g. from an older backup.
+        let public_key_1b =
+            GuardianSecretKey::generate(&mut csprng, &election_parameters, i_1, None)
+                .unwrap()
+                .make_public_key();
+        let err = secret_key_1
+            .verify_matches_public(&public_key_1b, &election_parameters)
+            .unwrap_err();
+        assert!(err.to_string().contains("does not match the secret key"));
+
+        let mut public_key_1c = public_key_1.clone();
+        public_key_1c.coefficient_commitments.0.pop();
+        assert!(secret_key_1
+            .verify_matches_public(&public_key_1c, &election_parameters)
+            .is_err());
+    }
+
+    #[cfg(feature = "pem")]
+    #[test]
+    fn test_pem_round_trip() {
+        let mut csprng = Csprng::new(b"test_pem_round_trip");
+
+        let election_parameters = example_election_parameters();
+
+        let i = election_parameters
+            .varying_parameters
+            .each_guardian_i()
+            .next()
+            .unwrap();
+        let secret_key = GuardianSecretKey::generate(
+            &mut csprng,
+            &election_parameters,
+            i,
+            Some("Guardian 1".to_string()),
+        )
+        .unwrap();
+
+        let pem_str = secret_key.to_pem(&election_parameters).unwrap();
+        assert!(pem_str.starts_with("-----BEGIN ELECTIONGUARD GUARDIAN SECRET KEY-----"));
+
+        let secret_key_2 = GuardianSecretKey::from_pem(&pem_str, &election_parameters).unwrap();
+        assert_eq!(secret_key_2.i, secret_key.i);
+        assert_eq!(secret_key_2.opt_name, secret_key.opt_name);
+        assert_eq!(secret_key_2.secret_s(), secret_key.secret_s());
+        assert_eq!(secret_key_2.to_pem(&election_parameters).unwrap(), pem_str);
+
+        // A corrupted body is rejected.
+        let lines: Vec<&str> = pem_str.lines().collect();
+        let body_ix = lines.len() / 2;
+        let corrupted_line: String = lines[body_ix]
+            .chars()
+            .enumerate()
+            .map(|(ix, c)| {
+                if ix == 3 {
+                    if c == 'A' {
+                        'B'
+                    } else {
+                        'A'
+                    }
+                } else {
+                    c
+                }
+            })
+            .collect();
+        let mut corrupted_lines = lines.clone();
+        corrupted_lines[body_ix] = &corrupted_line;
+        let corrupted_pem_str = corrupted_lines.join("\n");
+        assert!(GuardianSecretKey::from_pem(&corrupted_pem_str, &election_parameters).is_err());
+
+        // A truncated body is rejected.
+        let mut truncated_lines = lines.clone();
+        truncated_lines.remove(body_ix);
+        let truncated_pem_str = truncated_lines.join("\n");
+        assert!(GuardianSecretKey::from_pem(&truncated_pem_str, &election_parameters).is_err());
+
+        // The key is bound to the election parameters.
+        let mut other_election_parameters = election_parameters.clone();
+        other_election_parameters.fixed_parameters.g = (&election_parameters.fixed_parameters.g
+            * 2u8)
+            % election_parameters.fixed_parameters.p.as_ref();
+        let err = GuardianSecretKey::from_pem(&pem_str, &other_election_parameters).unwrap_err();
+        assert!(err.to_string().contains("different election parameters"));
+    }
+
+    #[test]
+    fn test_unknown_field() {
+        let mut csprng = Csprng::new(b"test_unknown_field");
+
+        let election_parameters = example_election_parameters();
+        let i = election_parameters
+            .varying_parameters
+            .each_guardian_i()
+            .next()
+            .unwrap();
+        let secret_key =
+            GuardianSecretKey::generate(&mut csprng, &election_parameters, i, None).unwrap();
+
+        let mut json = Vec::new();
+        secret_key.to_stdiowrite(&mut json).unwrap();
+        let mut value: serde_json::Value = serde_json::from_slice(&json).unwrap();
+        value
+            .as_object_mut()
+            .unwrap()
+            .insert("bogus".into(), 1.into());
+        let json_bogus = serde_json::to_vec(&value).unwrap();
+
+        let result = GuardianSecretKey::from_stdioread(&mut json_bogus.as_slice());
+        if cfg!(feature = "strict-parsing") {
+            let err = format!("{:#}", result.unwrap_err());
+            assert!(err.contains("unknown field `bogus`"), "{err}");
+        } else {
+            let mut json_2 = Vec::new();
+            result.unwrap().to_stdiowrite(&mut json_2).unwrap();
+            assert_eq!(json_2, json);
+        }
+    }
+}
@@ -118,6 +118,13 @@ impl HValue {
             .context("Error writing HValue file")
     }
 
+    /// Compares two values in constant time, i.e., without returning early at the first
+    /// byte which differs. Use this instead of `==` when a value is checked against one which
+    /// an attacker may be able to probe.
+    pub fn ct_eq(&self, other: &HValue) -> bool {
+        subtle::ConstantTimeEq::ct_eq(&self.0[..], &other.0[..]).into()
+    }
+
     pub fn to_string_hex_no_prefix_suffix(&self) -> String {
         #[allow(clippy::unwrap_used)] //? TODO: Remove temp development code
         let s = serde_json::to_string_pretty(self).unwrap();
@@ -261,6 +268,39 @@ pub fn eg_h(key: &HValue, data: &dyn AsRef<[u8]>) -> HValue {
     AsRef::<[u8; 32]>::as_ref(&hmac_sha256.chain(data).finalize_fixed()).into()
 }
 
+/// Computes the ElectionGuard "H" function incrementally, for data which is written to it
+/// as a [`std::io::Write`] rather than supplied as a single slice.
+///
+/// Writing `data` and then calling [`EgHWriter::finalize`] gives the same result as
+/// `eg_h(key, &data)`.
+pub struct EgHWriter(HmacSha256);
+
+impl EgHWriter {
+    pub fn new(key: &HValue) -> Self {
+        // `unwrap()` is justified here because `HmacSha256::new_from_slice()` seems
+        // to only fail on slice of incorrect size.
+        #[allow(clippy::unwrap_used)]
+        let hmac_sha256 = HmacSha256::new_from_slice(key.as_ref()).unwrap();
+
+        Self(hmac_sha256)
+    }
+
+    pub fn finalize(self) -> HValue {
+        AsRef::<[u8; 32]>::as_ref(&self.0.finalize_fixed()).into()
+    }
+}
+
+impl std::io::Write for EgHWriter {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        Update::update(&mut self.0, buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
 #[cfg(test)]
 #[allow(clippy::unwrap_used)]
 mod test_eg_h {
@@ -290,6 +330,19 @@ mod test_eg_h {
         assert_eq!(h2, h);
     }
 
+    #[test]
+    fn test_hvalue_ct_eq() {
+        let h: HValue = std::array::from_fn(|ix| ix as u8).into();
+        assert!(h.ct_eq(&HValue::from(std::array::from_fn(|ix| ix as u8))));
+
+        for ix in [0, 17, 31] {
+            let mut other = h;
+            other.0[ix] ^= 0x80;
+            assert!(!h.ct_eq(&other));
+            assert!(!other.ct_eq(&h));
+        }
+    }
+
     #[test]
     fn test_evaluate_h() {
         let key: HValue = HValue::default();
@@ -304,6 +357,22 @@ mod test_eg_h {
 
         assert_eq!(actual, expected);
     }
+
+    #[test]
+    fn test_eg_h_writer() {
+        use std::io::Write;
+
+        let key: HValue = std::array::from_fn(|ix| ix as u8).into();
+        let data: Vec<u8> = (0..1000).map(|n| n as u8).collect();
+
+        let mut writer = EgHWriter::new(&key);
+        for chunk in data.chunks(7) {
+            writer.write_all(chunk).unwrap();
+        }
+        assert_eq!(writer.finalize(), eg_h(&key, &data));
+
+        assert_eq!(EgHWriter::new(&key).finalize(), eg_h(&key, &[0u8; 0]));
+    }
 }
 
 // ElectionGuard "H" function (for WebAssembly)
@@ -7,7 +7,7 @@
 
 use std::borrow::Borrow;
 
-use anyhow::{anyhow, Context, Result};
+use anyhow::{anyhow, ensure, Context, Result};
 use serde::{Deserialize, Serialize};
 
 use crate::{
@@ -33,41 +33,13 @@ impl Hashes {
         election_parameters: &ElectionParameters,
         election_manifest: &ElectionManifest,
     ) -> Result<Self> {
-        // H_V = 322E302E30 ∥ b(0, 27)
-        let h_v: HValue = [
-            0x32, 0x2E, 0x30, 0x2E, 0x30, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
-            0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
-            0x00, 0x00, 0x00, 0x00,
-        ]
-        .into();
-
         // Computation of the parameter base hash H_P.
-        let h_p = {
-            // H_P = H(HV ; 00, p, q, g)
-
-            let mut v_pqg = vec![0x00];
 
-            for biguint in [
-                election_parameters.fixed_parameters.p.borrow(),
-                election_parameters.fixed_parameters.q.borrow(),
-                &election_parameters.fixed_parameters.g,
-            ] {
-                v_pqg.append(&mut biguint.to_bytes_be());
-            }
-
-            eg_h(&h_v, &v_pqg)
-        };
+        let h_p = Self::compute_h_p(election_parameters);
 
         // Computation of the election manifest hash H_M.
 
-        let h_m = {
-            let mut v = vec![0x01];
-
-            let mut v_manifest_bytes = election_manifest.to_canonical_bytes()?;
-            v.append(&mut v_manifest_bytes);
-
-            eg_h(&h_p, &v)
-        };
+        let h_m = election_manifest.hash(&h_p)?;
 
         // Computation of the election base hash H_B.
 
@@ -96,6 +68,57 @@ impl Hashes {
         Ok(Self { h_p, h_m, h_b })
     }
 
+    /// Computes the parameter base hash `H_P` from the fixed parameters.
+    pub fn compute_h_p(election_parameters: &ElectionParameters) -> HValue {
+        // H_V = 322E302E30 ∥ b(0, 27)
+        let h_v: HValue = [
+            0x32, 0x2E, 0x30, 0x2E, 0x30, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+            0x00, 0x00, 0x00, 0x00,
+        ]
+        .into();
+
+        // H_P = H(HV ; 00, p, q, g)
+
+        let mut v_pqg = vec![0x00];
+
+        for biguint in [
+            election_parameters.fixed_parameters.p.borrow(),
+            election_parameters.fixed_parameters.q.borrow(),
+            &election_parameters.fixed_parameters.g,
+        ] {
+            v_pqg.append(&mut biguint.to_bytes_be());
+        }
+
+        eg_h(&h_v, &v_pqg)
+    }
+
+    /// Checks that these hashes, e.g. as cached in a file by an earlier step, are the ones
+    /// computed from the specified parameters and manifest. Fails if either has changed since
+    /// the hashes were computed.
+    pub fn check_current(
+        &self,
+        election_parameters: &ElectionParameters,
+        election_manifest: &ElectionManifest,
+    ) -> Result<()> {
+        let current = Self::compute(election_parameters, election_manifest)?;
+
+        ensure!(
+            self.h_p == current.h_p,
+            "H_P does not match the election parameters"
+        );
+        ensure!(
+            self.h_m == current.h_m,
+            "H_M does not match the election manifest"
+        );
+        ensure!(
+            self.h_b == current.h_b,
+            "H_B does not match the election parameters and manifest"
+        );
+
+        Ok(())
+    }
+
     /// Reads a `Hashes` from a `std::io::Read` and validates it.
     pub fn from_stdioread_validated(stdioread: &mut dyn std::io::Read) -> Result<Self> {
         let self_: Self =
@@ -198,4 +221,27 @@ mod test {
 
         Ok(())
     }
+
+    #[test]
+    fn test_check_current() {
+        let election_parameters = example_election_parameters();
+        let mut election_manifest = example_election_manifest();
+
+        let hashes = Hashes::compute(&election_parameters, &election_manifest).unwrap();
+        hashes
+            .check_current(&election_parameters, &election_manifest)
+            .unwrap();
+
+        // Changing the manifest invalidates the cached hashes.
+        election_manifest.label.push_str(" (amended)");
+        let err = hashes
+            .check_current(&election_parameters, &election_manifest)
+            .unwrap_err();
+        assert!(err.to_string().contains("H_M"), "{err}");
+
+        let hashes = Hashes::compute(&election_parameters, &election_manifest).unwrap();
+        hashes
+            .check_current(&election_parameters, &election_manifest)
+            .unwrap();
+    }
 }
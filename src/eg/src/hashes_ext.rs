@@ -153,7 +153,9 @@ mod test {
 
         let guardian_secret_keys = varying_parameters
             .each_guardian_i()
-            .map(|i| GuardianSecretKey::generate(&mut csprng, &election_parameters, i, None))
+            .map(|i| {
+                GuardianSecretKey::generate(&mut csprng, &election_parameters, i, None).unwrap()
+            })
             .collect::<Vec<_>>();
 
         let guardian_public_keys = guardian_secret_keys
@@ -58,6 +58,14 @@ impl<T> std::cmp::Ord for Index<T> {
     }
 }
 
+// Hash trait must be implmented manually because of the [`PhantomData`](std::marker::PhantomData).
+// Consistent with `PartialEq`, which compares only the index value.
+impl<T> std::hash::Hash for Index<T> {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.0.hash(state);
+    }
+}
+
 // Verify that on the target platform `usize` is large enough to hold `i32::MAX`.
 // If someone needs to target a 16-bit platform with this code, we will have to deal
 // with `Vec` index type being too small to represent the spec commitment.
@@ -216,7 +224,7 @@ mod test_index {
 
     // Checks on size and traits.
     assert_eq_size!(FooIndex, u32);
-    assert_impl_all!(BarIndex: Clone, Copy, Send, Sync);
+    assert_impl_all!(BarIndex: Clone, Copy, Send, Sync, Eq, Ord, std::hash::Hash);
 
     #[test]
     fn test_range() {
@@ -256,6 +264,31 @@ mod test_index {
         );
     }
 
+    #[test]
+    fn test_map_keys() {
+        use std::collections::{BTreeMap, HashMap};
+
+        let ixs: Vec<FooIndex> = [5, 1, 3, 2, 4]
+            .into_iter()
+            .map(|ix1| FooIndex::from_one_based_index(ix1).unwrap())
+            .collect();
+
+        // A `BTreeMap` iterates in index order, regardless of insertion order.
+        let btree_map: BTreeMap<FooIndex, u32> = ixs
+            .iter()
+            .map(|&ix| (ix, ix.get_one_based_u32() * 10))
+            .collect();
+        let keys: Vec<u32> = btree_map.keys().map(|ix| ix.get_one_based_u32()).collect();
+        assert_eq!(keys, vec![1, 2, 3, 4, 5]);
+        let values: Vec<u32> = btree_map.values().copied().collect();
+        assert_eq!(values, vec![10, 20, 30, 40, 50]);
+
+        let hash_map: HashMap<FooIndex, u32> = btree_map.clone().into_iter().collect();
+        for (ix, value) in &btree_map {
+            assert_eq!(hash_map.get(ix), Some(value));
+        }
+    }
+
     #[test]
     fn test_00() {
         // Verify below lower limit
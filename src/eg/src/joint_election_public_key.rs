@@ -11,8 +11,12 @@ use num_traits::One;
 use serde::{Deserialize, Serialize};
 
 use crate::{
-    election_parameters::ElectionParameters, fixed_parameters::FixedParameters,
-    guardian_public_key::GuardianPublicKey, index::Index,
+    election_parameters::ElectionParameters,
+    fixed_parameters::FixedParameters,
+    guardian_public_key::GuardianPublicKey,
+    hash::{eg_h, HValue},
+    hashes::Hashes,
+    index::Index,
 };
 
 /// The joint election public key.
@@ -45,6 +49,19 @@ pub struct Ciphertext {
     pub nonce: Option<BigUint>,
 }
 
+impl Ciphertext {
+    /// Verifies that `alpha` and `beta` are valid nonzero mod p values.
+    /// Useful after deserialization.
+    pub fn validate(&self, fixed_parameters: &FixedParameters) -> Result<()> {
+        ensure!(
+            fixed_parameters.is_valid_nonzero_modp(&self.alpha)
+                && fixed_parameters.is_valid_nonzero_modp(&self.beta),
+            "Ciphertext is not a pair of valid nonzero mod p values"
+        );
+        Ok(())
+    }
+}
+
 /// Does not match nonces if either nonce is None.
 impl PartialEq for Ciphertext {
     fn eq(&self, other: &Self) -> bool {
@@ -57,24 +74,61 @@ impl PartialEq for Ciphertext {
     }
 }
 
+/// Encrypts a single selection `bit` with exponential ElGamal under `joint_public_key`, i.e.
+/// `(g^ξ, K^(ξ + bit))` for the nonce `ξ`.
+///
+/// This is the step which [`JointElectionPublicKey::encrypt_with`] performs for each option of
+/// a ballot, on its own. It generates no proof that the ciphertext encrypts 0 or 1, and the
+/// nonce is not stored in the returned ciphertext.
+pub fn encrypt_selection(
+    bit: bool,
+    nonce: &BigUint,
+    joint_public_key: &JointElectionPublicKey,
+    fixed_parameters: &FixedParameters,
+) -> Ciphertext {
+    joint_public_key.encrypt_with(fixed_parameters, nonce, bit as usize, false)
+}
+
 impl JointElectionPublicKey {
     pub fn compute(
         election_parameters: &ElectionParameters,
         guardian_public_keys: &[GuardianPublicKey],
     ) -> Result<Self> {
-        let fixed_parameters = &election_parameters.fixed_parameters;
-        let varying_parameters = &election_parameters.varying_parameters;
-        let n = varying_parameters.n.get_one_based_usize();
-
         // Validate every supplied guardian public key.
         for guardian_public_key in guardian_public_keys {
             guardian_public_key.validate(election_parameters)?;
         }
 
+        Self::compute_unchecked_the_caller_guarantees_that_the_keys_are_valid(
+            election_parameters,
+            guardian_public_keys,
+        )
+    }
+
+    /// Same as [`JointElectionPublicKey::compute`], but without calling
+    /// [`GuardianPublicKey::validate`] on each key.
+    ///
+    /// The caller must already have validated every key against `election_parameters`, e.g.
+    /// when they were loaded. A key which is not a valid value mod p would produce a joint key
+    /// which is not in the group. That every guardian is represented exactly once, under the
+    /// same parameters version, is still checked.
+    pub fn compute_unchecked_the_caller_guarantees_that_the_keys_are_valid(
+        election_parameters: &ElectionParameters,
+        guardian_public_keys: &[GuardianPublicKey],
+    ) -> Result<Self> {
+        let fixed_parameters = &election_parameters.fixed_parameters;
+        let varying_parameters = &election_parameters.varying_parameters;
+        let n = varying_parameters.n.get_one_based_usize();
+
         // Verify that every guardian is represented exactly once.
         let mut seen = vec![false; n];
         for guardian_public_key in guardian_public_keys {
-            let seen_ix = guardian_public_key.i.get_zero_based_usize();
+            let i = guardian_public_key.i;
+
+            // Check the range before indexing into `seen`, so that a malformed key can't
+            // cause an out-of-bounds access. `compute` has already rejected such a key when
+            // validating it, but the keys given here directly may not have been validated.
+            let seen_ix = i.to_zero_based(varying_parameters.n)?;
 
             ensure!(
                 !seen[seen_ix],
@@ -85,26 +139,53 @@ impl JointElectionPublicKey {
             seen[seen_ix] = true;
         }
 
-        let missing_guardian_ixs: Vec<usize> = seen
+        // Listed in increasing order of guardian number, so the message is deterministic.
+        let missing_guardians: Vec<String> = seen
             .iter()
             .enumerate()
             .filter(|&(_ix, &seen)| !seen)
-            .map(|(ix, _)| ix)
+            .map(|(ix, _)| (ix + 1).to_string())
             .collect();
 
-        if !missing_guardian_ixs.is_empty() {
-            //? TODO Consider using `.intersperse(", ")` when it's stable.
-            // https://github.com/rust-lang/rust/issues/79524
-            let iter = missing_guardian_ixs.iter().enumerate().map(|(n, ix)| {
-                let guardian_i = ix + 1;
-                if 0 == n {
-                    format!("{guardian_i}")
-                } else {
-                    format!(", {guardian_i}")
-                }
-            });
+        if !missing_guardians.is_empty() {
+            bail!(
+                "Guardian(s) {} are not represented in the guardian public keys",
+                missing_guardians.join(", ")
+            );
+        }
 
-            bail!("Guardian(s) {iter:?} are not represented in the guardian public keys");
+        // Keys generated under different versions of the parameters must never be combined.
+        // Once there is a version to compare against, every key must record its own, lest the
+        // check be bypassed by deleting the field.
+        let opt_first_versioned = guardian_public_keys
+            .iter()
+            .find_map(|gpk| Some((gpk.i, gpk.opt_parameters_version.as_ref()?)));
+        let opt_parameters_version = fixed_parameters
+            .opt_ElectionGuard_Design_Specification
+            .as_ref();
+        if opt_first_versioned.is_some() || opt_parameters_version.is_some() {
+            for gpk in guardian_public_keys {
+                let i = gpk.i;
+                let Some(version) = &gpk.opt_parameters_version else {
+                    bail!(
+                        "Guardian {i}'s key does not record the version of the parameters it was generated under"
+                    );
+                };
+                if let Some((first_i, first_version)) = opt_first_versioned {
+                    ensure!(
+                        version == first_version,
+                        "Guardian {i}'s key was generated under {version} parameters, but guardian {first_i}'s under {first_version}"
+                    );
+                }
+            }
+            if let (Some((_, first_version)), Some(version)) =
+                (opt_first_versioned, opt_parameters_version)
+            {
+                ensure!(
+                    first_version == version,
+                    "Guardian keys were generated under {first_version} parameters, but the election parameters are {version}"
+                );
+            }
         }
 
         let joint_election_public_key = guardian_public_keys.iter().fold(
@@ -154,26 +235,55 @@ impl JointElectionPublicKey {
         stdioread: &mut dyn std::io::Read,
         election_parameters: &ElectionParameters,
     ) -> Result<Self> {
-        let self_: Self =
-            serde_json::from_reader(stdioread).context("Reading JointElectionPublicKey")?;
+        let self_ = Self::from_stdioread(stdioread)?;
 
         self_.validate(election_parameters)?;
 
         Ok(self_)
     }
 
+    /// Reads a `JointElectionPublicKey` from a `std::io::Read` without validating it.
+    ///
+    /// The caller is responsible for calling [`JointElectionPublicKey::validate`] before using it.
+    pub fn from_stdioread(stdioread: &mut dyn std::io::Read) -> Result<Self> {
+        serde_json::from_reader(stdioread).context("Reading JointElectionPublicKey")
+    }
+
     /// Verifies that the `JointElectionPublicKey` conforms to the election parameters.
     /// Useful after deserialization.
+    ///
+    /// Only the reduced representation, `0 < K < p`, is accepted.
     pub fn validate(&self, election_parameters: &ElectionParameters) -> Result<()> {
         ensure!(
             election_parameters
                 .fixed_parameters
-                .is_valid_modp(&self.joint_election_public_key),
-            "JointElectionPublicKey is not valid mod p"
+                .is_valid_nonzero_modp(&self.joint_election_public_key),
+            "JointElectionPublicKey is not a valid nonzero mod p value"
         );
         Ok(())
     }
 
+    /// Returns true iff the two keys represent the same element mod p.
+    ///
+    /// Keys which have passed [`JointElectionPublicKey::validate`] are already reduced, in which
+    /// case this is the same as comparing them directly.
+    pub fn equals(&self, other: &Self, fixed_parameters: &FixedParameters) -> bool {
+        let p = fixed_parameters.p.as_ref();
+        &self.joint_election_public_key % p == &other.joint_election_public_key % p
+    }
+
+    /// Returns a short value which identifies this key, for recording alongside the things
+    /// encrypted with it.
+    ///
+    /// It is `H(H_P; 27, K)`, with `K` in its fixed-length representation. Keying the hash
+    /// with the parameter base hash `H_P` binds it to the group the key is an element of.
+    pub fn fingerprint(&self, election_parameters: &ElectionParameters) -> HValue {
+        let mut v = vec![0x27];
+        v.extend_from_slice(&self.to_be_bytes_len_p(&election_parameters.fixed_parameters));
+
+        eg_h(&Hashes::compute_h_p(election_parameters), &v)
+    }
+
     /// Returns the `JointElectionPublicKey` as a big-endian byte array of the correct length for `mod p`.
     pub fn to_be_bytes_len_p(&self, fixed_parameters: &FixedParameters) -> Vec<u8> {
         fixed_parameters.biguint_to_be_bytes_len_p(&self.joint_election_public_key)
@@ -196,3 +306,371 @@ impl AsRef<BigUint> for JointElectionPublicKey {
         &self.joint_election_public_key
     }
 }
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod test {
+    use super::*;
+    use crate::{
+        example_election::ExampleElection,
+        example_election_parameters::example_election_parameters, guardian::GuardianIndex,
+        guardian_secret_key::GuardianSecretKey,
+    };
+    use num_traits::Zero;
+    use util::csprng::Csprng;
+
+    #[test]
+    fn test_guardian_i_out_of_range() {
+        let mut csprng = Csprng::new(b"test_guardian_i_out_of_range");
+
+        let election_parameters = example_election_parameters();
+        let varying_parameters = &election_parameters.varying_parameters;
+
+        let mut guardian_public_keys = varying_parameters
+            .each_guardian_i()
+            .map(|i| {
+                GuardianSecretKey::generate(&mut csprng, &election_parameters, i, None)
+                    .unwrap()
+                    .make_public_key()
+            })
+            .collect::<Vec<_>>();
+
+        assert!(
+            JointElectionPublicKey::compute(&election_parameters, &guardian_public_keys).is_ok()
+        );
+
+        // A key claiming to be guardian i = n + 1.
+        let i_bad =
+            GuardianIndex::from_one_based_index(varying_parameters.n.get_one_based_u32() + 1)
+                .unwrap();
+        guardian_public_keys.push(
+            GuardianSecretKey::generate(&mut csprng, &election_parameters, i_bad, None)
+                .unwrap()
+                .make_public_key(),
+        );
+
+        // `compute` rejects the key when validating it.
+        let err = JointElectionPublicKey::compute(&election_parameters, &guardian_public_keys)
+            .unwrap_err();
+        assert!(err.to_string().contains(&format!("i={i_bad}")), "{err}");
+
+        // Without validation, the range is checked before the key is used as an index.
+        let err =
+            JointElectionPublicKey::compute_unchecked_the_caller_guarantees_that_the_keys_are_valid(
+                &election_parameters,
+                &guardian_public_keys,
+            )
+            .unwrap_err();
+        assert!(
+            err.to_string()
+                .contains(&format!("i={i_bad} is not in the range 1 <= i <= n=")),
+            "{err}"
+        );
+    }
+
+    #[test]
+    fn test_encrypt_selection() {
+        let mut csprng = Csprng::new(b"test_encrypt_selection");
+
+        let election_parameters = example_election_parameters();
+        let fixed_parameters = &election_parameters.fixed_parameters;
+        let p = fixed_parameters.p.as_ref();
+        let q = &fixed_parameters.q;
+
+        let (guardian_secret_keys, guardian_public_keys) =
+            ExampleElection::generate_guardian_keys(&mut csprng, &election_parameters).unwrap();
+        let joint_public_key =
+            JointElectionPublicKey::compute(&election_parameters, &guardian_public_keys).unwrap();
+
+        // The joint secret key is the sum of the guardians' secret keys.
+        let s = guardian_secret_keys
+            .iter()
+            .fold(BigUint::zero(), |acc, secret_key| {
+                (acc + secret_key.secret_s()) % q.as_ref()
+            });
+
+        // Decrypts to K^m, from which m is found by comparison since it is 0 or 1.
+        let decrypt = |ciphertext: &Ciphertext| {
+            let a_s = ciphertext.alpha.modpow(&s, p);
+            let k_m = &ciphertext.beta * a_s.modpow(&(p - 2u8), p) % p;
+            if k_m.is_one() {
+                0
+            } else {
+                assert_eq!(k_m, joint_public_key.joint_election_public_key);
+                1
+            }
+        };
+
+        for bit in [false, true] {
+            let nonce = q.random_group_elem(&mut csprng);
+            let ciphertext = encrypt_selection(bit, &nonce, &joint_public_key, fixed_parameters);
+            assert_eq!(ciphertext.nonce, None);
+            assert_eq!(decrypt(&ciphertext), bit as u8);
+            assert_eq!(
+                ciphertext,
+                joint_public_key.encrypt_with(fixed_parameters, &nonce, bit as usize, true)
+            );
+        }
+    }
+
+    #[test]
+    fn test_compute_unchecked() {
+        let mut csprng = Csprng::new(b"test_compute_unchecked");
+
+        let election_parameters = example_election_parameters();
+
+        let (_, mut guardian_public_keys) =
+            ExampleElection::generate_guardian_keys(&mut csprng, &election_parameters).unwrap();
+
+        let checked =
+            JointElectionPublicKey::compute(&election_parameters, &guardian_public_keys).unwrap();
+        let unchecked =
+            JointElectionPublicKey::compute_unchecked_the_caller_guarantees_that_the_keys_are_valid(
+                &election_parameters,
+                &guardian_public_keys,
+            )
+            .unwrap();
+        assert_eq!(
+            checked.joint_election_public_key,
+            unchecked.joint_election_public_key
+        );
+
+        // The set of guardians is still checked.
+        assert!(
+            JointElectionPublicKey::compute_unchecked_the_caller_guarantees_that_the_keys_are_valid(
+                &election_parameters,
+                &guardian_public_keys[1..],
+            )
+            .is_err()
+        );
+
+        // But not the keys themselves.
+        guardian_public_keys[0].coefficient_commitments.0[0].0 =
+            election_parameters.fixed_parameters.p.as_ref().clone();
+        assert!(
+            JointElectionPublicKey::compute(&election_parameters, &guardian_public_keys).is_err()
+        );
+        assert!(
+            JointElectionPublicKey::compute_unchecked_the_caller_guarantees_that_the_keys_are_valid(
+                &election_parameters,
+                &guardian_public_keys,
+            )
+            .is_ok()
+        );
+    }
+
+    #[test]
+    fn test_missing_guardians() {
+        let mut csprng = Csprng::new(b"test_missing_guardians");
+
+        let election_parameters = example_election_parameters();
+
+        let guardian_public_keys = election_parameters
+            .varying_parameters
+            .each_guardian_i()
+            .filter(|i| ![2, 5].contains(&i.get_one_based_u32()))
+            .map(|i| {
+                GuardianSecretKey::generate(&mut csprng, &election_parameters, i, None)
+                    .unwrap()
+                    .make_public_key()
+            })
+            .collect::<Vec<_>>();
+
+        let err = JointElectionPublicKey::compute(&election_parameters, &guardian_public_keys)
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Guardian(s) 2, 5 are not represented in the guardian public keys"
+        );
+    }
+
+    #[test]
+    fn test_parameters_version_mismatch() {
+        use crate::fixed_parameters::{
+            ElectionGuardDesignSpecificationVersion, OfficialReleaseKind, OfficialVersion,
+        };
+
+        let mut csprng = Csprng::new(b"test_parameters_version_mismatch");
+
+        let election_parameters = example_election_parameters();
+        let mut election_parameters_v1_54 = election_parameters.clone();
+        let v1_54 = ElectionGuardDesignSpecificationVersion::Official(OfficialVersion {
+            version: [1, 54],
+            release: OfficialReleaseKind::Release,
+        });
+        election_parameters_v1_54
+            .fixed_parameters
+            .opt_ElectionGuard_Design_Specification = Some(v1_54);
+
+        // Guardian 2's key was generated under v1.54 parameters, the rest under v2.0.
+        let mut guardian_public_keys = election_parameters
+            .varying_parameters
+            .each_guardian_i()
+            .map(|i| {
+                let election_parameters = if i.get_one_based_u32() == 2 {
+                    &election_parameters_v1_54
+                } else {
+                    &election_parameters
+                };
+                GuardianSecretKey::generate(&mut csprng, election_parameters, i, None)
+                    .unwrap()
+                    .make_public_key()
+            })
+            .collect::<Vec<_>>();
+
+        let err = JointElectionPublicKey::compute(&election_parameters, &guardian_public_keys)
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Guardian 2's key was generated under v1.54 parameters, but guardian 1's under v2.0"
+        );
+
+        // Keys which all agree with each other must still agree with the election parameters.
+        guardian_public_keys[1].opt_parameters_version =
+            guardian_public_keys[0].opt_parameters_version.clone();
+        let err =
+            JointElectionPublicKey::compute(&election_parameters_v1_54, &guardian_public_keys)
+                .unwrap_err();
+        assert!(
+            err.to_string().contains("election parameters are v1.54"),
+            "{err}"
+        );
+
+        // A key which doesn't record a version can't be compared, so it is rejected, whether
+        // the other keys or only the election parameters record one.
+        guardian_public_keys[1].opt_parameters_version = None;
+        let err = JointElectionPublicKey::compute(&election_parameters, &guardian_public_keys)
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Guardian 2's key does not record the version of the parameters it was generated under"
+        );
+        for guardian_public_key in &mut guardian_public_keys {
+            guardian_public_key.opt_parameters_version = None;
+        }
+        let err = JointElectionPublicKey::compute(&election_parameters, &guardian_public_keys)
+            .unwrap_err();
+        assert!(
+            err.to_string().contains("Guardian 1's key does not record"),
+            "{err}"
+        );
+
+        // Only if there is no version anywhere is there nothing to check.
+        let mut election_parameters_unversioned = election_parameters.clone();
+        election_parameters_unversioned
+            .fixed_parameters
+            .opt_ElectionGuard_Design_Specification = None;
+        JointElectionPublicKey::compute(&election_parameters_unversioned, &guardian_public_keys)
+            .unwrap();
+    }
+
+    #[test]
+    fn test_fingerprint() {
+        let mut csprng = Csprng::new(b"test_fingerprint");
+
+        let election_parameters = example_election_parameters();
+        let fixed_parameters = &election_parameters.fixed_parameters;
+
+        let key = |csprng: &mut Csprng| JointElectionPublicKey {
+            joint_election_public_key: fixed_parameters.g.modpow(
+                &fixed_parameters.q.random_group_elem(csprng),
+                fixed_parameters.p.as_ref(),
+            ),
+        };
+        let key_1 = key(&mut csprng);
+        let key_2 = key(&mut csprng);
+
+        let fingerprint = key_1.fingerprint(&election_parameters);
+        assert_eq!(fingerprint, key_1.clone().fingerprint(&election_parameters));
+        assert_ne!(fingerprint, key_2.fingerprint(&election_parameters));
+
+        // It is keyed with H_P and domain separated, rather than a bare hash of the key.
+        let mut v = vec![0x27];
+        v.extend_from_slice(&key_1.to_be_bytes_len_p(fixed_parameters));
+        assert_eq!(
+            fingerprint,
+            eg_h(&Hashes::compute_h_p(&election_parameters), &v)
+        );
+        assert_ne!(
+            fingerprint,
+            eg_h(
+                &HValue::default(),
+                &key_1.to_be_bytes_len_p(fixed_parameters)
+            )
+        );
+    }
+
+    #[test]
+    fn test_non_reduced_key() {
+        let mut csprng = Csprng::new(b"test_non_reduced_key");
+
+        let election_parameters = example_election_parameters();
+        let fixed_parameters = &election_parameters.fixed_parameters;
+
+        let (_, guardian_public_keys) =
+            ExampleElection::generate_guardian_keys(&mut csprng, &election_parameters).unwrap();
+
+        let joint_election_public_key =
+            JointElectionPublicKey::compute(&election_parameters, &guardian_public_keys).unwrap();
+
+        let mut json = Vec::new();
+        joint_election_public_key.to_stdiowrite(&mut json).unwrap();
+        let joint_election_public_key_2 = JointElectionPublicKey::from_stdioread_validated(
+            &mut json.as_slice(),
+            &election_parameters,
+        )
+        .unwrap();
+        assert!(joint_election_public_key.equals(&joint_election_public_key_2, fixed_parameters));
+
+        // The same element of the group, encoded as K + p.
+        let non_reduced = JointElectionPublicKey {
+            joint_election_public_key: &joint_election_public_key.joint_election_public_key
+                + fixed_parameters.p.as_ref(),
+        };
+        assert!(joint_election_public_key.equals(&non_reduced, fixed_parameters));
+        assert!(non_reduced.equals(&joint_election_public_key, fixed_parameters));
+        assert_ne!(
+            non_reduced.joint_election_public_key,
+            joint_election_public_key.joint_election_public_key
+        );
+
+        // Parsing alone doesn't check the key against the election parameters.
+        assert!(non_reduced.validate(&election_parameters).is_err());
+        let mut json = Vec::new();
+        non_reduced.to_stdiowrite(&mut json).unwrap();
+        assert!(JointElectionPublicKey::from_stdioread(&mut json.as_slice()).is_ok());
+        assert!(JointElectionPublicKey::from_stdioread_validated(
+            &mut json.as_slice(),
+            &election_parameters
+        )
+        .is_err());
+
+        let zero = JointElectionPublicKey {
+            joint_election_public_key: BigUint::zero(),
+        };
+        assert!(zero.validate(&election_parameters).is_err());
+        assert!(!zero.equals(&joint_election_public_key, fixed_parameters));
+    }
+
+    #[test]
+    fn test_ciphertext_validate() {
+        let election_parameters = example_election_parameters();
+        let fixed_parameters = &election_parameters.fixed_parameters;
+        let p = fixed_parameters.p.as_ref();
+
+        let ciphertext = |alpha: BigUint, beta: BigUint| Ciphertext {
+            alpha,
+            beta,
+            nonce: None,
+        };
+        let one = || BigUint::from(1u8);
+
+        assert!(ciphertext(one(), one()).validate(fixed_parameters).is_ok());
+        for bad in [BigUint::zero(), p.clone(), p + 1u8] {
+            assert!(ciphertext(bad.clone(), one())
+                .validate(fixed_parameters)
+                .is_err());
+            assert!(ciphertext(one(), bad).validate(fixed_parameters).is_err());
+        }
+    }
+}
@@ -59,22 +59,36 @@
 #![deny(clippy::panic)]
 #![deny(clippy::manual_assert)]
 
+pub mod artifact_format;
 pub mod ballot;
+pub mod ballot_chain;
+pub mod ballot_encryptor;
+pub mod ballot_packed;
 pub mod ballot_style;
+pub mod commitment_matrix;
 pub mod confirmation_code;
 pub mod contest_encrypted;
 pub mod contest_hash;
 pub mod contest_selection;
+pub mod decryption_share;
 pub mod device;
+pub mod discrete_log;
 pub mod election_manifest;
+pub mod election_manifest_builder;
 pub mod election_parameters;
 pub mod election_record;
+#[cfg(feature = "zip")]
+pub mod election_record_zip;
+pub mod example_election;
 pub mod example_election_manifest;
 pub mod example_election_parameters;
 pub mod fixed_parameters;
 pub mod guardian;
+pub mod guardian_key_proof;
+pub mod guardian_key_share;
 pub mod guardian_public_key;
 pub mod guardian_public_key_info;
+pub mod guardian_public_key_set;
 pub mod guardian_secret_key;
 pub mod hash;
 pub mod hashes;
@@ -82,7 +96,16 @@ pub mod hashes_ext;
 pub mod index;
 pub mod joint_election_public_key;
 pub mod nonce;
+pub mod nonce_auditor;
+pub mod reencryption;
+pub mod share_distribution;
 pub mod standard_parameters;
+pub mod tally;
+#[cfg(feature = "signing")]
+pub mod tally_signature;
+#[cfg(feature = "debug-transcript")]
+pub mod transcript;
 pub mod varying_parameters;
 pub mod vec1;
+pub mod verification;
 pub mod zk;
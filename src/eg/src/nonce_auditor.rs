@@ -0,0 +1,116 @@
+// Copyright (C) Microsoft Corporation. All rights reserved.
+
+#![deny(clippy::unwrap_used)]
+#![deny(clippy::expect_used)]
+#![deny(clippy::panic)]
+#![deny(clippy::manual_assert)]
+
+use std::collections::HashMap;
+
+use anyhow::{bail, Result};
+use num_bigint::BigUint;
+
+use crate::{ballot::BallotEncrypted, joint_election_public_key::Ciphertext};
+
+/// Checks that no encryption nonce is used more than once in an election.
+///
+/// Two ciphertexts encrypted with the same nonce `ξ` share the same `α = g^ξ mod p`, and the
+/// ratio of their `β` values reveals the difference of their plaintexts. With nonces derived
+/// from a fresh primary nonce per ballot this should never happen, so a duplicate indicates
+/// an implementation bug.
+///
+/// Only the `α` values are recorded, never the nonces themselves, so the auditor can be used
+/// by a verifier as well as during encryption.
+#[derive(Debug, Default)]
+pub struct NonceAuditor {
+    /// Every `α` seen so far, with a description of where it was first seen.
+    seen: HashMap<BigUint, String>,
+}
+
+impl NonceAuditor {
+    /// Creates a new, empty `NonceAuditor`.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the number of ciphertexts recorded.
+    pub fn len(&self) -> usize {
+        self.seen.len()
+    }
+
+    /// Returns true if no ciphertexts have been recorded.
+    pub fn is_empty(&self) -> bool {
+        self.seen.is_empty()
+    }
+
+    /// Records the nonce commitment `α` of a ciphertext.
+    /// Returns an error if a ciphertext with the same `α` was already recorded.
+    pub fn record_ciphertext(&mut self, ciphertext: &Ciphertext, location: String) -> Result<()> {
+        if let Some(first_location) = self.seen.get(&ciphertext.alpha) {
+            bail!("Encryption nonce reused: {location} has the same nonce as {first_location}");
+        }
+
+        self.seen.insert(ciphertext.alpha.clone(), location);
+
+        Ok(())
+    }
+
+    /// Records every selection ciphertext of an encrypted ballot.
+    /// Returns an error if any of them reuses a nonce already recorded.
+    pub fn record_ballot(&mut self, ballot: &BallotEncrypted) -> Result<()> {
+        for contest_ix in ballot.contests.indices() {
+            let Some(contest) = ballot.contests.get(contest_ix) else {
+                continue;
+            };
+
+            for (option_ix0, ciphertext) in contest.selection.iter().enumerate() {
+                let location = format!(
+                    "ballot {} contest {contest_ix} option {}",
+                    ballot.confirmation_code,
+                    option_ix0 + 1
+                );
+                self.record_ciphertext(ciphertext, location)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod test {
+    use super::*;
+    use crate::example_election_parameters::example_election_parameters;
+    use crate::joint_election_public_key::JointElectionPublicKey;
+
+    #[test]
+    fn test_duplicate_nonce_detected() {
+        let fixed_parameters = example_election_parameters().fixed_parameters;
+
+        let public_key = JointElectionPublicKey {
+            joint_election_public_key: fixed_parameters.g.clone(),
+        };
+
+        let encrypt = |nonce: u32, vote| {
+            public_key.encrypt_with(&fixed_parameters, &BigUint::from(nonce), vote, false)
+        };
+
+        let mut auditor = NonceAuditor::new();
+        auditor
+            .record_ciphertext(&encrypt(11, 0), "first".into())
+            .unwrap();
+        auditor
+            .record_ciphertext(&encrypt(12, 1), "second".into())
+            .unwrap();
+        assert_eq!(auditor.len(), 2);
+
+        // Same nonce, different vote.
+        let err = auditor
+            .record_ciphertext(&encrypt(11, 1), "third".into())
+            .unwrap_err();
+        assert!(err.to_string().contains("third"));
+        assert!(err.to_string().contains("first"));
+        assert_eq!(auditor.len(), 2);
+    }
+}
@@ -0,0 +1,996 @@
+// Copyright (C) Microsoft Corporation. All rights reserved.
+
+#![deny(clippy::unwrap_used)]
+#![deny(clippy::expect_used)]
+#![deny(clippy::panic)]
+#![deny(clippy::manual_assert)]
+
+//! Re-encryption of an encrypted tally under a new joint election public key `K'`, for when
+//! the guardians change between an interim and a final tally.
+//!
+//! Moving a ciphertext to another key needs the old secret key, so, like decryption, it is
+//! done by the old guardians with their key shares `P(i)`, and any `k` of them suffice. For a
+//! ciphertext `(A, B)`, guardian `i` picks a nonce `r_i` and publishes the share
+//! `(α_i, β_i) = (g^r_i, K'^r_i · A^-P(i))`. With `w_i` the Lagrange coefficients of the
+//! guardians present, `(Π α_i^w_i, B · Π β_i^w_i)` is `(g^r', K'^r' · K^m)` with
+//! `r' = Σ w_i r_i`, an encryption under `K'`. Each `A^-P(i)` is masked by `K'^r_i`, so unlike
+//! decryption shares the shares reveal nothing about `m`. That requires `K'` to be an element
+//! of the order-`q` subgroup other than 1, so any other `K'` is rejected.
+//!
+//! Each share comes with a proof that it was made with the guardian's key share `P(i)`, which
+//! is what ensures that the plaintext is unchanged.
+//!
+//! The plaintext remains encoded as `K^m` with the original key `K`. So once the new
+//! guardians have combined their decryption shares, the discrete log is taken to base `K`,
+//! i.e. with the original [`PreVotingData`].
+
+use std::{borrow::Borrow, collections::BTreeSet};
+
+use anyhow::{ensure, Context, Result};
+use num_bigint::BigUint;
+use num_traits::One;
+use serde::{Deserialize, Serialize};
+
+use util::csprng::Csprng;
+
+use crate::{
+    decryption_share::lagrange_coefficient,
+    election_record::PreVotingData,
+    guardian::GuardianIndex,
+    guardian_key_share::{GuardianKeyShare, GuardianPublicKeyShare},
+    guardian_public_key::GuardianPublicKey,
+    hash::eg_h,
+    joint_election_public_key::{Ciphertext, JointElectionPublicKey},
+    tally::{ContestTally, ContestTallyPart, Tally},
+    vec1::Vec1,
+    zk::{Record, TranscriptValue},
+};
+
+#[cfg(feature = "debug-transcript")]
+use crate::transcript::TranscriptSink;
+
+/// The domain separator of the challenge of a [`ProofReencryptionShare`].
+const CHALLENGE_DOMAIN: u8 = 0x31;
+
+/// Proof that a [`ReencryptionShare`] was computed with the guardian's key share `P(i)`, whose
+/// public counterpart is the guardian's [`GuardianPublicKeyShare`] `g^P(i)`, and a nonce `r_i`
+/// with `α_i = g^r_i`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProofReencryptionShare {
+    #[serde(
+        serialize_with = "util::biguint_serde::biguint_serialize",
+        deserialize_with = "util::biguint_serde::biguint_deserialize"
+    )]
+    pub c: BigUint,
+
+    /// Response for `P(i)`.
+    #[serde(
+        serialize_with = "util::biguint_serde::biguint_serialize",
+        deserialize_with = "util::biguint_serde::biguint_deserialize"
+    )]
+    pub v_s: BigUint,
+
+    /// Response for `r_i`.
+    #[serde(
+        serialize_with = "util::biguint_serde::biguint_serialize",
+        deserialize_with = "util::biguint_serde::biguint_deserialize"
+    )]
+    pub v_r: BigUint,
+}
+
+/// A guardian's share of the re-encryption of a single [`Ciphertext`] under a new key.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReencryptionShare {
+    /// Guardian number, 1 <= i <= n.
+    pub i: GuardianIndex,
+
+    /// `α_i = g^r_i mod p`.
+    #[serde(
+        serialize_with = "util::biguint_serde::biguint_serialize",
+        deserialize_with = "util::biguint_serde::biguint_deserialize"
+    )]
+    pub alpha_i: BigUint,
+
+    /// `β_i = K'^r_i · A^-P(i) mod p`.
+    #[serde(
+        serialize_with = "util::biguint_serde::biguint_serialize",
+        deserialize_with = "util::biguint_serde::biguint_deserialize"
+    )]
+    pub beta_i: BigUint,
+
+    /// Proof of correct computation of `alpha_i` and `beta_i`.
+    pub proof: ProofReencryptionShare,
+}
+
+/// Checks that `new_public_key` is an element of the order-`q` subgroup other than 1.
+///
+/// Each share's `A^-P(i)` is masked by `K'^r_i`, which hides nothing if `K'` is 1, and too
+/// little if `K'` has small order. The re-encrypted totals could then be read without the new
+/// guardians.
+fn validate_new_public_key(
+    pvd: &PreVotingData,
+    new_public_key: &JointElectionPublicKey,
+) -> Result<()> {
+    let fixed_parameters = &pvd.parameters.fixed_parameters;
+    let k_new = &new_public_key.joint_election_public_key;
+    ensure!(
+        fixed_parameters.is_in_subgroup(k_new) && !k_new.is_one(),
+        "The new joint election public key is not an element of the order-q subgroup other than 1"
+    );
+    Ok(())
+}
+
+/// Returns `x^-1 mod p`. `p` is prime, so this is `x^(p - 2)`.
+fn inv_modp(x: &BigUint, p: &BigUint) -> BigUint {
+    x.modpow(&(p - 2u8), p)
+}
+
+impl ReencryptionShare {
+    /// Computes guardian `i`'s share of the re-encryption of `ciphertext` under
+    /// `new_public_key` with its key share, along with the proof of its correctness.
+    ///
+    /// Errors if `ciphertext` is not a pair of valid nonzero mod p values, as `A` must be
+    /// invertible, or if `new_public_key` is not an element of the order-`q` subgroup other
+    /// than 1.
+    pub fn compute(
+        csprng: &mut Csprng,
+        pvd: &PreVotingData,
+        guardian_key_share: &GuardianKeyShare,
+        new_public_key: &JointElectionPublicKey,
+        ciphertext: &Ciphertext,
+    ) -> Result<Self> {
+        let fixed_parameters = &pvd.parameters.fixed_parameters;
+        let p: &BigUint = fixed_parameters.p.borrow();
+        let q = &fixed_parameters.q;
+        let g = &fixed_parameters.g;
+        let k_new = &new_public_key.joint_election_public_key;
+
+        ciphertext
+            .validate(fixed_parameters)
+            .context("Re-encrypting ciphertext")?;
+        validate_new_public_key(pvd, new_public_key)?;
+
+        let p_i = guardian_key_share.secret_p_i();
+        let k_i = &guardian_key_share.public_key_share().k_i;
+        let a_inv = inv_modp(&ciphertext.alpha, p);
+
+        let r_i = q.random_group_elem(csprng);
+        let alpha_i = g.modpow(&r_i, p);
+        let beta_i = (k_new.modpow(&r_i, p) * a_inv.modpow(p_i, p)) % p;
+
+        let u_s = q.random_group_elem(csprng);
+        let u_r = q.random_group_elem(csprng);
+        let a_s = g.modpow(&u_s, p);
+        let a_r = g.modpow(&u_r, p);
+        let a_beta = (k_new.modpow(&u_r, p) * a_inv.modpow(&u_s, p)) % p;
+
+        let i = guardian_key_share.i;
+        let c = challenge(
+            pvd,
+            i,
+            k_i,
+            k_new,
+            ciphertext,
+            [&alpha_i, &beta_i, &a_s, &a_r, &a_beta],
+        );
+        let v_s = q.subtract_group_elem(&u_s, &q.multiply_group_elem(&c, p_i));
+        let v_r = q.subtract_group_elem(&u_r, &q.multiply_group_elem(&c, &r_i));
+
+        Ok(ReencryptionShare {
+            i,
+            alpha_i,
+            beta_i,
+            proof: ProofReencryptionShare { c, v_s, v_r },
+        })
+    }
+}
+
+/// Computes the challenge for a [`ProofReencryptionShare`].
+///
+/// The domain separator differs from that of decryption shares, so that neither kind of
+/// proof can be presented as the other.
+fn challenge(
+    pvd: &PreVotingData,
+    i: GuardianIndex,
+    k_i: &BigUint,
+    k_new: &BigUint,
+    ciphertext: &Ciphertext,
+    share_and_commitments: [&BigUint; 5],
+) -> BigUint {
+    let fixed_parameters = &pvd.parameters.fixed_parameters;
+
+    let mut v = vec![CHALLENGE_DOMAIN];
+    v.extend_from_slice(&i.get_one_based_u32().to_be_bytes());
+    for x in [k_i, k_new, &ciphertext.alpha, &ciphertext.beta]
+        .into_iter()
+        .chain(share_and_commitments)
+    {
+        v.extend_from_slice(&fixed_parameters.biguint_to_be_bytes_len_p(x));
+    }
+
+    let c = eg_h(&pvd.hashes_ext.h_e, &v);
+    BigUint::from_bytes_be(c.0.as_slice()) % fixed_parameters.q.as_ref()
+}
+
+/// Verifies a [`ReencryptionShare`] of `ciphertext` under `new_public_key` against the
+/// public key share of the guardian which claims to have produced it.
+pub fn verify_reencryption_share(
+    pvd: &PreVotingData,
+    guardian_public_key_share: &GuardianPublicKeyShare,
+    new_public_key: &JointElectionPublicKey,
+    ciphertext: &Ciphertext,
+    share: &ReencryptionShare,
+) -> Result<()> {
+    verify_reencryption_share_impl(
+        pvd,
+        guardian_public_key_share,
+        new_public_key,
+        ciphertext,
+        share,
+        &mut |_label, _value| {},
+    )
+}
+
+/// Same as [`verify_reencryption_share`], but records every hashed element and challenge
+/// value in `transcript`.
+#[cfg(feature = "debug-transcript")]
+pub fn verify_reencryption_share_with_transcript(
+    pvd: &PreVotingData,
+    guardian_public_key_share: &GuardianPublicKeyShare,
+    new_public_key: &JointElectionPublicKey,
+    ciphertext: &Ciphertext,
+    share: &ReencryptionShare,
+    transcript: &mut TranscriptSink,
+) -> Result<()> {
+    verify_reencryption_share_impl(
+        pvd,
+        guardian_public_key_share,
+        new_public_key,
+        ciphertext,
+        share,
+        &mut |label, value| transcript.record(label, value),
+    )
+}
+
+fn verify_reencryption_share_impl(
+    pvd: &PreVotingData,
+    guardian_public_key_share: &GuardianPublicKeyShare,
+    new_public_key: &JointElectionPublicKey,
+    ciphertext: &Ciphertext,
+    share: &ReencryptionShare,
+    record: &mut Record,
+) -> Result<()> {
+    use TranscriptValue::{BigUint as Int, Bytes};
+
+    let fixed_parameters = &pvd.parameters.fixed_parameters;
+    let p: &BigUint = fixed_parameters.p.borrow();
+    let g = &fixed_parameters.g;
+    let k_new = &new_public_key.joint_election_public_key;
+
+    let i = share.i;
+    ensure!(
+        guardian_public_key_share.i == i,
+        "Re-encryption share from guardian {i} can't be verified with the key share of guardian {}",
+        guardian_public_key_share.i
+    );
+    ciphertext
+        .validate(fixed_parameters)
+        .context("Verifying re-encryption share")?;
+    validate_new_public_key(pvd, new_public_key)?;
+
+    let ProofReencryptionShare { c, v_s, v_r } = &share.proof;
+    ensure!(
+        fixed_parameters.is_valid_nonzero_modp(&share.alpha_i)
+            && fixed_parameters.is_valid_nonzero_modp(&share.beta_i),
+        "Re-encryption share from guardian {i} is not a valid nonzero mod p value"
+    );
+    ensure!(
+        [c, v_s, v_r]
+            .into_iter()
+            .all(|x| fixed_parameters.is_valid_modq(x)),
+        "Re-encryption share proof from guardian {i} has a response out of range"
+    );
+
+    let k_i = &guardian_public_key_share.k_i;
+    let a_inv = inv_modp(&ciphertext.alpha, p);
+
+    let a_s = (g.modpow(v_s, p) * k_i.modpow(c, p)) % p;
+    let a_r = (g.modpow(v_r, p) * share.alpha_i.modpow(c, p)) % p;
+    let a_beta = (k_new.modpow(v_r, p) * a_inv.modpow(v_s, p) % p * share.beta_i.modpow(c, p)) % p;
+
+    record(format_args!("domain"), Bytes(&[CHALLENGE_DOMAIN]));
+    record(format_args!("H_E"), Bytes(pvd.hashes_ext.h_e.0.as_slice()));
+    record(
+        format_args!("i"),
+        Bytes(&i.get_one_based_u32().to_be_bytes()),
+    );
+    record(format_args!("K_i"), Int(k_i));
+    record(format_args!("K'"), Int(k_new));
+    record(format_args!("alpha"), Int(&ciphertext.alpha));
+    record(format_args!("beta"), Int(&ciphertext.beta));
+    record(format_args!("alpha_i"), Int(&share.alpha_i));
+    record(format_args!("beta_i"), Int(&share.beta_i));
+    record(format_args!("c"), Int(c));
+    record(format_args!("v_s"), Int(v_s));
+    record(format_args!("v_r"), Int(v_r));
+    record(format_args!("a_s"), Int(&a_s));
+    record(format_args!("a_r"), Int(&a_r));
+    record(format_args!("a_beta"), Int(&a_beta));
+
+    let c_computed = challenge(
+        pvd,
+        i,
+        k_i,
+        k_new,
+        ciphertext,
+        [&share.alpha_i, &share.beta_i, &a_s, &a_r, &a_beta],
+    );
+    record(format_args!("c_computed"), Int(&c_computed));
+
+    ensure!(
+        *c == c_computed,
+        "Re-encryption share proof from guardian {i} does not verify against its public key share"
+    );
+
+    Ok(())
+}
+
+/// Verifies the [`ReencryptionShare`]s of `ciphertext` from a quorum of guardians and
+/// combines them into the re-encrypted ciphertext `(Π α_i^w_i, B · Π β_i^w_i)`, where `w_i`
+/// are the Lagrange coefficients of the guardians present.
+///
+/// As with decryption, there must be shares from at least `k` distinct guardians. Each is
+/// verified against the guardian's entry in `guardian_public_key_shares`.
+pub fn combine_reencryption_shares(
+    pvd: &PreVotingData,
+    guardian_public_key_shares: &[GuardianPublicKeyShare],
+    new_public_key: &JointElectionPublicKey,
+    ciphertext: &Ciphertext,
+    shares: &[ReencryptionShare],
+) -> Result<Ciphertext> {
+    let fixed_parameters = &pvd.parameters.fixed_parameters;
+    let varying_parameters = &pvd.parameters.varying_parameters;
+    let p: &BigUint = fixed_parameters.p.borrow();
+
+    validate_new_public_key(pvd, new_public_key)?;
+
+    let mut seen = BTreeSet::new();
+    for share in shares {
+        let i = share.i;
+        ensure!(
+            i <= varying_parameters.n,
+            "Re-encryption share from guardian {i}, but there are only n={} guardians",
+            varying_parameters.n
+        );
+        ensure!(
+            seen.insert(i),
+            "More than one re-encryption share from guardian {i}"
+        );
+    }
+    ensure!(
+        varying_parameters.k.as_quantity() <= seen.len(),
+        "Re-encryption requires shares from at least k={} guardians, found {}",
+        varying_parameters.k,
+        seen.len()
+    );
+
+    let guardians: Vec<GuardianIndex> = seen.into_iter().collect();
+
+    let mut alpha = BigUint::one();
+    let mut beta = ciphertext.beta.clone();
+    for share in shares {
+        let guardian_public_key_share = guardian_public_key_shares
+            .iter()
+            .find(|public_key_share| public_key_share.i == share.i)
+            .with_context(|| format!("No public key share for guardian {}", share.i))?;
+        verify_reencryption_share(
+            pvd,
+            guardian_public_key_share,
+            new_public_key,
+            ciphertext,
+            share,
+        )?;
+
+        let w_i = lagrange_coefficient(fixed_parameters, share.i, &guardians);
+        alpha = alpha * share.alpha_i.modpow(&w_i, p) % p;
+        beta = beta * share.beta_i.modpow(&w_i, p) % p;
+    }
+
+    Ok(Ciphertext {
+        alpha,
+        beta,
+        nonce: None,
+    })
+}
+
+/// A guardian's [`ReencryptionShare`]s of every ciphertext of a [`ContestTally`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ContestReencryptionShares {
+    /// The share of each option's total, in the order defined in the manifest.
+    pub selection: Vec<ReencryptionShare>,
+
+    /// The share of the blank count, if it was tracked.
+    #[serde(rename = "blank", default, skip_serializing_if = "Option::is_none")]
+    pub opt_blank: Option<ReencryptionShare>,
+
+    /// The share of the undervote count, if it was tracked.
+    #[serde(rename = "undervote", default, skip_serializing_if = "Option::is_none")]
+    pub opt_undervote: Option<ReencryptionShare>,
+}
+
+impl ContestReencryptionShares {
+    /// Computes the guardian's shares of the re-encryption of the ciphertexts of
+    /// `contest_tally` under `new_public_key`.
+    pub fn compute(
+        csprng: &mut Csprng,
+        pvd: &PreVotingData,
+        guardian_key_share: &GuardianKeyShare,
+        new_public_key: &JointElectionPublicKey,
+        contest_tally: &ContestTally,
+    ) -> Result<Self> {
+        let mut share_of = |ciphertext: &Ciphertext| {
+            ReencryptionShare::compute(csprng, pvd, guardian_key_share, new_public_key, ciphertext)
+        };
+        Ok(ContestReencryptionShares {
+            selection: contest_tally
+                .selection
+                .iter()
+                .map(&mut share_of)
+                .collect::<Result<_>>()?,
+            opt_blank: contest_tally
+                .opt_blank
+                .as_ref()
+                .map(&mut share_of)
+                .transpose()?,
+            opt_undervote: contest_tally
+                .opt_undervote
+                .as_ref()
+                .map(share_of)
+                .transpose()?,
+        })
+    }
+
+    /// Collects each guardian's share of the specified ciphertext.
+    pub fn shares_of(
+        reencryption_shares: &[&ContestReencryptionShares],
+        part: ContestTallyPart,
+    ) -> Result<Vec<ReencryptionShare>> {
+        reencryption_shares
+            .iter()
+            .map(|shares| {
+                match part {
+                    ContestTallyPart::Option(option_ix) => shares.selection.get(option_ix),
+                    ContestTallyPart::Blank => shares.opt_blank.as_ref(),
+                    ContestTallyPart::Undervote => shares.opt_undervote.as_ref(),
+                }
+                .cloned()
+                .context("Missing a re-encryption share")
+            })
+            .collect()
+    }
+}
+
+/// A guardian's [`ReencryptionShare`]s of every ciphertext of a [`Tally`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TallyReencryptionShares {
+    /// The shares of each contest, in the order defined in the manifest.
+    pub contests: Vec<ContestReencryptionShares>,
+}
+
+impl TallyReencryptionShares {
+    /// Computes the guardian's shares of the re-encryption of `tally` under `new_public_key`.
+    pub fn compute(
+        csprng: &mut Csprng,
+        pvd: &PreVotingData,
+        guardian_key_share: &GuardianKeyShare,
+        new_public_key: &JointElectionPublicKey,
+        tally: &Tally,
+    ) -> Result<Self> {
+        let contests = tally
+            .contests
+            .indices()
+            .filter_map(|contest_ix| tally.contests.get(contest_ix))
+            .map(|contest_tally| {
+                ContestReencryptionShares::compute(
+                    csprng,
+                    pvd,
+                    guardian_key_share,
+                    new_public_key,
+                    contest_tally,
+                )
+            })
+            .collect::<Result<_>>()?;
+        Ok(TallyReencryptionShares { contests })
+    }
+}
+
+impl Tally {
+    /// Re-encrypts the tally under `new_public_key`, using a [`TallyReencryptionShares`] from
+    /// each of at least `k` guardians of the election described by `pvd`.
+    ///
+    /// Every share is verified against its guardian's public key share, as computed from
+    /// `guardian_public_keys`, which proves that the re-encrypted tally has the same plaintext.
+    /// The result is determined by the shares, so a verifier can check a published re-encrypted
+    /// tally by calling this again with the same shares.
+    ///
+    /// This can't be done from nonces alone: moving a ciphertext to another key requires the
+    /// secret key it was encrypted under. See [`crate::reencryption`] for how the result is
+    /// decrypted.
+    pub fn reencrypt_under(
+        &self,
+        pvd: &PreVotingData,
+        guardian_public_keys: &[GuardianPublicKey],
+        new_public_key: &JointElectionPublicKey,
+        shares: &[TallyReencryptionShares],
+    ) -> Result<Tally> {
+        let guardian_public_key_shares =
+            GuardianPublicKeyShare::compute_all(&pvd.parameters, guardian_public_keys)?;
+
+        let mut contests = Vec1::with_capacity(self.contests.len());
+        for (contest_ix0, contest_ix) in self.contests.indices().enumerate() {
+            let contest_tally = self
+                .contests
+                .get(contest_ix)
+                .with_context(|| format!("Tally has no contest {contest_ix}"))?;
+            let contest_shares = shares
+                .iter()
+                .map(|tally_shares| tally_shares.contests.get(contest_ix0))
+                .collect::<Option<Vec<_>>>()
+                .with_context(|| {
+                    format!("Missing re-encryption shares for contest {contest_ix}")
+                })?;
+
+            let reencrypt = |ciphertext: &Ciphertext, part: ContestTallyPart| {
+                let shares = ContestReencryptionShares::shares_of(&contest_shares, part)?;
+                combine_reencryption_shares(
+                    pvd,
+                    &guardian_public_key_shares,
+                    new_public_key,
+                    ciphertext,
+                    &shares,
+                )
+                .with_context(|| format!("Re-encrypting contest {contest_ix}"))
+            };
+
+            let selection = contest_tally
+                .selection
+                .iter()
+                .enumerate()
+                .map(|(option_ix, ciphertext)| {
+                    reencrypt(ciphertext, ContestTallyPart::Option(option_ix))
+                })
+                .collect::<Result<Vec<_>>>()?;
+            let opt_blank = contest_tally
+                .opt_blank
+                .as_ref()
+                .map(|ciphertext| reencrypt(ciphertext, ContestTallyPart::Blank))
+                .transpose()?;
+            let opt_undervote = contest_tally
+                .opt_undervote
+                .as_ref()
+                .map(|ciphertext| reencrypt(ciphertext, ContestTallyPart::Undervote))
+                .transpose()?;
+
+            contests.try_push(ContestTally {
+                selection,
+                selection_limit: contest_tally.selection_limit,
+                opt_blank,
+                opt_undervote,
+            })?;
+        }
+
+        Ok(Tally {
+            contests,
+            ballot_count: self.ballot_count,
+        })
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod test {
+    use super::*;
+    use crate::{
+        decryption_share::{combine_decryption_shares, DecryptionShare},
+        election_manifest::ContestIndex,
+        example_election::ExampleElection,
+        guardian_key_share::GuardianKeyShare,
+    };
+
+    #[test]
+    fn test_reencrypt_tally() {
+        let mut csprng = Csprng::new(b"test_reencrypt_tally");
+
+        let example_election = ExampleElection::with_first_contests(&mut csprng, 1).unwrap();
+        let ExampleElection {
+            guardian_key_shares: old_key_shares,
+            guardian_public_keys: old_public_keys,
+            pvd,
+            ..
+        } = &example_election;
+        let fixed_parameters = &pvd.parameters.fixed_parameters;
+        let contest_1 = ContestIndex::from_one_based_index(1).unwrap();
+
+        let (new_secret_keys, new_public_keys) =
+            ExampleElection::generate_guardian_keys(&mut csprng, &pvd.parameters).unwrap();
+        let pvd_new = PreVotingData::compute(
+            pvd.manifest.clone(),
+            pvd.parameters.clone(),
+            &new_public_keys,
+        )
+        .unwrap();
+
+        let expected_totals = [1, 2];
+        let ballots = example_election
+            .encrypt_ballots(
+                &mut csprng,
+                &[
+                    r#"{ "contests": [ { "vote": [1, 0] } ] }"#,
+                    r#"{ "contests": [ { "vote": [0, 1] } ] }"#,
+                    r#"{ "contests": [ { "vote": [0, 1] } ] }"#,
+                ],
+            )
+            .unwrap();
+        let tally = Tally::from_ballots(fixed_parameters, &pvd.manifest, &ballots).unwrap();
+
+        let new_public_key = &pvd_new.public_key;
+        let new_key_shares =
+            GuardianKeyShare::compute_all_from_secret_keys(&pvd_new.parameters, &new_secret_keys);
+        let shares = old_key_shares
+            .iter()
+            .map(|key_share| {
+                TallyReencryptionShares::compute(
+                    &mut csprng,
+                    pvd,
+                    key_share,
+                    new_public_key,
+                    &tally,
+                )
+                .unwrap()
+            })
+            .collect::<Vec<_>>();
+        let reencrypted = tally
+            .reencrypt_under(pvd, old_public_keys, new_public_key, &shares)
+            .unwrap();
+
+        // The new guardians decrypt the re-encrypted totals, but the plaintext is still
+        // encoded with the old joint key.
+        let old_contest_tally = tally.contest(contest_1).unwrap();
+        let contest_tally = reencrypted.contest(contest_1).unwrap();
+        for (option_ix, ciphertext) in contest_tally.selection.iter().enumerate() {
+            assert_ne!(ciphertext, &old_contest_tally.selection[option_ix]);
+
+            let decryption_shares = new_key_shares
+                .iter()
+                .map(|key_share| {
+                    DecryptionShare::compute(&mut csprng, &pvd_new, key_share, ciphertext)
+                })
+                .collect::<Vec<_>>();
+            let combined =
+                combine_decryption_shares(&pvd_new, ciphertext, &decryption_shares).unwrap();
+            assert_eq!(
+                combined.discrete_log(pvd, 3),
+                Some(expected_totals[option_ix])
+            );
+        }
+
+        // A share which doesn't preserve the plaintext is rejected.
+        let mut tampered = shares.clone();
+        let share = &mut tampered[1].contests[0].selection[0];
+        share.beta_i =
+            share.beta_i.clone() * fixed_parameters.g.clone() % fixed_parameters.p.as_ref();
+        let err = tally
+            .reencrypt_under(pvd, old_public_keys, new_public_key, &tampered)
+            .unwrap_err();
+        assert!(format!("{err:#}").contains("does not verify"), "{err:#}");
+
+        // As are shares made for a different new key.
+        let err = tally
+            .reencrypt_under(pvd, old_public_keys, &pvd.public_key, &shares)
+            .unwrap_err();
+        assert!(format!("{err:#}").contains("does not verify"), "{err:#}");
+
+        // And at least k guardians must contribute, each only once.
+        let err = tally
+            .reencrypt_under(pvd, old_public_keys, new_public_key, &shares[3..])
+            .unwrap_err();
+        assert!(format!("{err:#}").contains("at least k=3"), "{err:#}");
+        let duplicated = [&shares[..2], &shares[..2]].concat();
+        let err = tally
+            .reencrypt_under(pvd, old_public_keys, new_public_key, &duplicated)
+            .unwrap_err();
+        assert!(format!("{err:#}").contains("More than one"), "{err:#}");
+    }
+
+    #[test]
+    fn test_reencrypt_with_absent_guardians() {
+        let mut csprng = Csprng::new(b"test_reencrypt_with_absent_guardians");
+
+        let example_election = ExampleElection::with_first_contests(&mut csprng, 1).unwrap();
+        let ExampleElection {
+            guardian_key_shares: old_key_shares,
+            guardian_public_keys: old_public_keys,
+            pvd,
+            ..
+        } = &example_election;
+        let varying_parameters = &pvd.parameters.varying_parameters;
+        assert_eq!(varying_parameters.n.as_quantity(), 5);
+        assert_eq!(varying_parameters.k.as_quantity(), 3);
+        let contest_1 = ContestIndex::from_one_based_index(1).unwrap();
+
+        let (new_secret_keys, new_public_keys) =
+            ExampleElection::generate_guardian_keys(&mut csprng, &pvd.parameters).unwrap();
+        let pvd_new = PreVotingData::compute(
+            pvd.manifest.clone(),
+            pvd.parameters.clone(),
+            &new_public_keys,
+        )
+        .unwrap();
+        let new_public_key = &pvd_new.public_key;
+        let new_key_shares =
+            GuardianKeyShare::compute_all_from_secret_keys(&pvd_new.parameters, &new_secret_keys);
+
+        let ballots = example_election
+            .encrypt_ballots(
+                &mut csprng,
+                &[
+                    r#"{ "contests": [ { "vote": [0, 1] } ] }"#,
+                    r#"{ "contests": [ { "vote": [1, 0] } ] }"#,
+                    r#"{ "contests": [ { "vote": [1, 0] } ] }"#,
+                ],
+            )
+            .unwrap();
+        let tally =
+            Tally::from_ballots(&pvd.parameters.fixed_parameters, &pvd.manifest, &ballots).unwrap();
+
+        // Each old guardian computes its shares, but only those present contribute them.
+        let shares = old_key_shares
+            .iter()
+            .map(|key_share| {
+                TallyReencryptionShares::compute(
+                    &mut csprng,
+                    pvd,
+                    key_share,
+                    new_public_key,
+                    &tally,
+                )
+                .unwrap()
+            })
+            .collect::<Vec<_>>();
+
+        for present in [
+            [1, 2, 3, 4, 5].as_slice(),
+            &[1, 3, 5],
+            &[3, 4, 5],
+            &[5, 2, 4, 1],
+        ] {
+            let present_shares = present
+                .iter()
+                .map(|&ix| shares[ix - 1].clone())
+                .collect::<Vec<_>>();
+            let reencrypted = tally
+                .reencrypt_under(pvd, old_public_keys, new_public_key, &present_shares)
+                .unwrap();
+
+            let contest_tally = reencrypted.contest(contest_1).unwrap();
+            let totals = contest_tally
+                .selection
+                .iter()
+                .map(|ciphertext| {
+                    let decryption_shares = new_key_shares
+                        .iter()
+                        .map(|key_share| {
+                            DecryptionShare::compute(&mut csprng, &pvd_new, key_share, ciphertext)
+                        })
+                        .collect::<Vec<_>>();
+                    combine_decryption_shares(&pvd_new, ciphertext, &decryption_shares)
+                        .unwrap()
+                        .discrete_log(pvd, 3)
+                })
+                .collect::<Vec<_>>();
+            assert_eq!(totals, [Some(2), Some(1)], "present: {present:?}");
+        }
+    }
+
+    #[test]
+    fn test_reencryption_share_invalid_ciphertext() {
+        let mut csprng = Csprng::new(b"test_reencryption_share_invalid_ciphertext");
+
+        let ExampleElection {
+            guardian_key_shares,
+            pvd,
+            ..
+        } = ExampleElection::with_first_contests(&mut csprng, 1).unwrap();
+        let fixed_parameters = &pvd.parameters.fixed_parameters;
+
+        let nonce = fixed_parameters.q.random_group_elem(&mut csprng);
+        let ciphertext = pvd
+            .public_key
+            .encrypt_with(fixed_parameters, &nonce, 1, false);
+        let share = ReencryptionShare::compute(
+            &mut csprng,
+            &pvd,
+            &guardian_key_shares[0],
+            &pvd.public_key,
+            &ciphertext,
+        )
+        .unwrap();
+
+        // `A` has no inverse if it is 0, or p, which is 0 mod p.
+        for alpha in [BigUint::from(0u8), fixed_parameters.p.as_ref().clone()] {
+            let invalid = Ciphertext {
+                alpha,
+                ..ciphertext.clone()
+            };
+            ReencryptionShare::compute(
+                &mut csprng,
+                &pvd,
+                &guardian_key_shares[0],
+                &pvd.public_key,
+                &invalid,
+            )
+            .unwrap_err();
+            verify_reencryption_share(
+                &pvd,
+                guardian_key_shares[0].public_key_share(),
+                &pvd.public_key,
+                &invalid,
+                &share,
+            )
+            .unwrap_err();
+        }
+    }
+
+    #[test]
+    fn test_reencrypt_under_invalid_key() {
+        let mut csprng = Csprng::new(b"test_reencrypt_under_invalid_key");
+
+        let example_election = ExampleElection::with_first_contests(&mut csprng, 1).unwrap();
+        let ExampleElection {
+            guardian_key_shares,
+            guardian_public_keys,
+            pvd,
+            ..
+        } = &example_election;
+        let fixed_parameters = &pvd.parameters.fixed_parameters;
+
+        let ballots = example_election
+            .encrypt_ballots(&mut csprng, &[r#"{ "contests": [ { "vote": [1, 0] } ] }"#])
+            .unwrap();
+        let tally = Tally::from_ballots(fixed_parameters, &pvd.manifest, &ballots).unwrap();
+        let ciphertext = &tally
+            .contest(ContestIndex::from_one_based_index(1).unwrap())
+            .unwrap()
+            .selection[0];
+
+        let shares = guardian_key_shares
+            .iter()
+            .map(|key_share| {
+                TallyReencryptionShares::compute(
+                    &mut csprng,
+                    pvd,
+                    key_share,
+                    &pvd.public_key,
+                    &tally,
+                )
+                .unwrap()
+            })
+            .collect::<Vec<_>>();
+
+        // With `K' = 1`, a share would be an inverted decryption share. `p - 1` has order 2.
+        let p_minus_1 = fixed_parameters.p.as_ref() - 1_u8;
+        for k_new in [BigUint::one(), p_minus_1] {
+            let new_public_key = JointElectionPublicKey {
+                joint_election_public_key: k_new,
+            };
+
+            let err = ReencryptionShare::compute(
+                &mut csprng,
+                pvd,
+                &guardian_key_shares[0],
+                &new_public_key,
+                ciphertext,
+            )
+            .unwrap_err();
+            assert!(format!("{err:#}").contains("order-q subgroup"), "{err:#}");
+
+            // Nor are shares for such a key, however they were made, verified or combined.
+            let err = tally
+                .reencrypt_under(pvd, guardian_public_keys, &new_public_key, &shares)
+                .unwrap_err();
+            assert!(format!("{err:#}").contains("order-q subgroup"), "{err:#}");
+            let err = verify_reencryption_share(
+                pvd,
+                guardian_key_shares[0].public_key_share(),
+                &new_public_key,
+                ciphertext,
+                &shares[0].contests[0].selection[0],
+            )
+            .unwrap_err();
+            assert!(format!("{err:#}").contains("order-q subgroup"), "{err:#}");
+        }
+    }
+
+    #[cfg(feature = "debug-transcript")]
+    #[test]
+    fn test_verify_reencryption_share_with_transcript() {
+        let mut csprng = Csprng::new(b"test_verify_reencryption_share_with_transcript");
+
+        let ExampleElection {
+            guardian_key_shares,
+            pvd,
+            ..
+        } = ExampleElection::with_first_contests(&mut csprng, 1).unwrap();
+        let fixed_parameters = &pvd.parameters.fixed_parameters;
+
+        // The new key doesn't matter to the transcript, so the ciphertext is re-encrypted
+        // under the key it is already encrypted under.
+        let nonce = fixed_parameters.q.random_group_elem(&mut csprng);
+        let ciphertext = pvd
+            .public_key
+            .encrypt_with(fixed_parameters, &nonce, 1, false);
+        let share = ReencryptionShare::compute(
+            &mut csprng,
+            &pvd,
+            &guardian_key_shares[0],
+            &pvd.public_key,
+            &ciphertext,
+        )
+        .unwrap();
+
+        let mut transcript = TranscriptSink::new();
+        verify_reencryption_share_with_transcript(
+            &pvd,
+            guardian_key_shares[0].public_key_share(),
+            &pvd.public_key,
+            &ciphertext,
+            &share,
+            &mut transcript,
+        )
+        .unwrap();
+
+        let labels: Vec<&str> = transcript
+            .entries
+            .iter()
+            .map(|entry| entry.label.as_str())
+            .collect();
+        assert_eq!(
+            labels,
+            [
+                "domain",
+                "H_E",
+                "i",
+                "K_i",
+                "K'",
+                "alpha",
+                "beta",
+                "alpha_i",
+                "beta_i",
+                "c",
+                "v_s",
+                "v_r",
+                "a_s",
+                "a_r",
+                "a_beta",
+                "c_computed"
+            ]
+        );
+        assert_eq!(transcript.entries[0].value, "31");
+        assert_eq!(transcript.entries[9].value, transcript.entries[15].value);
+
+        // The share doesn't verify against another guardian's key.
+        let mut transcript = TranscriptSink::new();
+        let mut other_public_key_share = guardian_key_shares[1].public_key_share().clone();
+        other_public_key_share.i = share.i;
+        assert!(verify_reencryption_share_with_transcript(
+            &pvd,
+            &other_public_key_share,
+            &pvd.public_key,
+            &ciphertext,
+            &share,
+            &mut transcript,
+        )
+        .is_err());
+        assert_ne!(transcript.entries[9].value, transcript.entries[15].value);
+    }
+}
@@ -0,0 +1,483 @@
+// Copyright (C) Microsoft Corporation. All rights reserved.
+
+#![deny(clippy::unwrap_used)]
+#![deny(clippy::expect_used)]
+#![deny(clippy::panic)]
+#![deny(clippy::manual_assert)]
+
+use std::{borrow::Borrow, collections::HashSet};
+
+use anyhow::{anyhow, ensure, Context, Result};
+use num_bigint::BigUint;
+use num_traits::Zero;
+use serde::{Deserialize, Serialize};
+
+use util::csprng::Csprng;
+
+use crate::{
+    commitment_matrix::share_commitment,
+    election_parameters::ElectionParameters,
+    fixed_parameters::FixedParameters,
+    guardian::GuardianIndex,
+    guardian_public_key::GuardianPublicKey,
+    guardian_secret_key::GuardianSecretKey,
+    hash::{eg_h, HValue, HVALUE_BYTE_LEN},
+    hashes::Hashes,
+};
+
+/// Guardian `i`'s secret polynomial evaluated at `ℓ`, `P_i(ℓ)`, encrypted so that only
+/// guardian `ℓ` can read it.
+///
+/// This is hashed ElGamal encryption to the recipient's public key `K_ℓ,0`, with a MAC
+/// so that a share which has been tampered with is detected when it is opened.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SealedShare {
+    /// `α = g^ξ mod p`, for a fresh nonce `ξ`.
+    #[serde(
+        serialize_with = "util::biguint_serde::biguint_serialize",
+        deserialize_with = "util::biguint_serde::biguint_deserialize"
+    )]
+    pub c0: BigUint,
+
+    /// `P_i(ℓ)`, as 32 big-endian bytes, XORed with the encryption key.
+    pub c1: HValue,
+
+    /// The MAC of `c0` and `c1`.
+    pub c2: HValue,
+}
+
+impl SealedShare {
+    /// Evaluates `sender`'s secret polynomial at the recipient's index and seals the result
+    /// to the recipient's public key.
+    pub fn seal(
+        csprng: &mut Csprng,
+        election_parameters: &ElectionParameters,
+        sender: &GuardianSecretKey,
+        recipient: &GuardianPublicKey,
+    ) -> Result<Self> {
+        let fixed_parameters = &election_parameters.fixed_parameters;
+        let p: &BigUint = fixed_parameters.p.borrow();
+        let q: &BigUint = fixed_parameters.q.borrow();
+
+        ensure!(
+            fixed_parameters.l_q_bytes() <= HVALUE_BYTE_LEN,
+            "Shares mod q can't be sealed when q is longer than {HVALUE_BYTE_LEN} bytes"
+        );
+
+        let share = evaluate_polynomial(fixed_parameters, sender, recipient.i);
+
+        let nonce = csprng.next_biguint_lt(q);
+        let c0 = fixed_parameters.g.modpow(&nonce, p);
+        let beta = recipient.public_key_k_i_0().modpow(&nonce, p);
+
+        let (k1, k2) = share_keys(
+            election_parameters,
+            sender.i,
+            recipient.i,
+            recipient.public_key_k_i_0(),
+            &c0,
+            &beta,
+        );
+
+        let mut c1 = [0u8; HVALUE_BYTE_LEN];
+        let share_bytes = fixed_parameters.biguint_to_be_bytes_len_q(&share);
+        c1[HVALUE_BYTE_LEN - share_bytes.len()..].copy_from_slice(&share_bytes);
+        for (b, k) in c1.iter_mut().zip(k1.0.iter()) {
+            *b ^= k;
+        }
+        let c1 = HValue(c1);
+
+        let c2 = share_mac(fixed_parameters, &k2, &c0, &c1);
+
+        Ok(SealedShare { c0, c1, c2 })
+    }
+
+    /// Opens a share sealed by guardian `sender_i` to `recipient`, returning `P_i(ℓ)`.
+    ///
+    /// Fails if the MAC does not match, i.e., the share was not sealed for this recipient by
+    /// this sender, or has been modified.
+    pub fn open(
+        &self,
+        election_parameters: &ElectionParameters,
+        sender_i: GuardianIndex,
+        recipient: &GuardianSecretKey,
+    ) -> Result<BigUint> {
+        let fixed_parameters = &election_parameters.fixed_parameters;
+        let p: &BigUint = fixed_parameters.p.borrow();
+
+        ensure!(
+            fixed_parameters.is_valid_nonzero_modp(&self.c0),
+            "Share from guardian {sender_i} to guardian {} is not a valid nonzero mod p value",
+            recipient.i
+        );
+
+        let k_l = &recipient.coefficient_commitments.0[0].0;
+        let beta = self.c0.modpow(recipient.secret_s(), p);
+
+        let (k1, k2) = share_keys(
+            election_parameters,
+            sender_i,
+            recipient.i,
+            k_l,
+            &self.c0,
+            &beta,
+        );
+
+        ensure!(
+            share_mac(fixed_parameters, &k2, &self.c0, &self.c1).ct_eq(&self.c2),
+            "Share from guardian {sender_i} to guardian {} fails its MAC",
+            recipient.i
+        );
+
+        let mut share = self.c1.0;
+        for (b, k) in share.iter_mut().zip(k1.0.iter()) {
+            *b ^= k;
+        }
+        let share = BigUint::from_bytes_be(&share);
+
+        ensure!(
+            fixed_parameters.is_valid_modq(&share),
+            "Share from guardian {sender_i} to guardian {} is not a valid mod q value",
+            recipient.i
+        );
+
+        Ok(share)
+    }
+}
+
+/// Computes `P_i(ℓ) = Σ a_i,j ℓ^j mod q`.
+pub(crate) fn evaluate_polynomial(
+    fixed_parameters: &FixedParameters,
+    sender: &GuardianSecretKey,
+    recipient_i: GuardianIndex,
+) -> BigUint {
+    let q: &BigUint = fixed_parameters.q.borrow();
+    let l = BigUint::from(recipient_i.get_one_based_u32());
+
+    // Horner's method, from the highest coefficient down.
+    sender
+        .secret_coefficients
+        .0
+        .iter()
+        .rev()
+        .fold(BigUint::zero(), |acc, a_j| (acc * &l + &a_j.0) % q)
+}
+
+/// Derives the encryption and MAC keys for a share sent from guardian `i` to guardian `ℓ`.
+fn share_keys(
+    election_parameters: &ElectionParameters,
+    i: GuardianIndex,
+    l: GuardianIndex,
+    k_l: &BigUint,
+    alpha: &BigUint,
+    beta: &BigUint,
+) -> (HValue, HValue) {
+    let fixed_parameters = &election_parameters.fixed_parameters;
+    let h_p = Hashes::compute_h_p(election_parameters);
+
+    let i = i.get_one_based_u32().to_be_bytes();
+    let l = l.get_one_based_u32().to_be_bytes();
+
+    let mut v = vec![0x11];
+    v.extend_from_slice(&i);
+    v.extend_from_slice(&l);
+    for x in [k_l, alpha, beta] {
+        v.extend_from_slice(&fixed_parameters.biguint_to_be_bytes_len_p(x));
+    }
+    let k_i_l = eg_h(&h_p, &v);
+
+    let derive = |n: u8| {
+        let mut v = vec![n];
+        v.extend_from_slice(b"share_enc_keys");
+        v.push(0x00);
+        v.extend_from_slice(b"share_encrypt");
+        v.extend_from_slice(&i);
+        v.extend_from_slice(&l);
+        v.extend_from_slice(&[0x02, 0x00]);
+        eg_h(&k_i_l, &v)
+    };
+
+    (derive(0x01), derive(0x02))
+}
+
+fn share_mac(fixed_parameters: &FixedParameters, k2: &HValue, c0: &BigUint, c1: &HValue) -> HValue {
+    let mut v = fixed_parameters.biguint_to_be_bytes_len_p(c0);
+    v.extend_from_slice(c1.as_ref());
+    eg_h(k2, &v)
+}
+
+/// Verifies that a share opened by guardian `recipient_i` is consistent with the sender's
+/// published coefficient commitments, i.e., `g^P_i(ℓ) = Π_j K_i,j^(ℓ^j) mod p`.
+pub fn verify_share(
+    fixed_parameters: &FixedParameters,
+    sender: &GuardianPublicKey,
+    recipient_i: GuardianIndex,
+    share: &BigUint,
+) -> Result<()> {
+    let lhs = fixed_parameters
+        .g
+        .modpow(share, fixed_parameters.p.borrow());
+    let rhs = share_commitment(
+        fixed_parameters,
+        &sender.coefficient_commitments,
+        recipient_i,
+    );
+
+    ensure!(
+        lhs == rhs,
+        "Share from guardian {} to guardian {recipient_i} does not match the sender's commitments",
+        sender.i
+    );
+
+    Ok(())
+}
+
+/// One entry of a [`ShareDistribution`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DistributedShare {
+    pub sender_i: GuardianIndex,
+    pub recipient_i: GuardianIndex,
+    pub sealed_share: SealedShare,
+}
+
+/// The published record of the share exchange in the key generation ceremony.
+///
+/// Maps each ordered pair of distinct guardians `(i, ℓ)` to the share that `i` sealed for `ℓ`.
+/// Anyone can check with [`validate_complete`](Self::validate_complete) that every guardian
+/// sent a share to every other guardian, and each recipient can check with
+/// [`verify_received`](Self::verify_received) that the shares it was sent are correct.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ShareDistribution {
+    pub shares: Vec<DistributedShare>,
+}
+
+impl ShareDistribution {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Seals a share from `sender` to every other guardian, and adds them.
+    pub fn add_shares_from(
+        &mut self,
+        csprng: &mut Csprng,
+        election_parameters: &ElectionParameters,
+        sender: &GuardianSecretKey,
+        guardian_public_keys: &[GuardianPublicKey],
+    ) -> Result<()> {
+        for recipient in guardian_public_keys {
+            if recipient.i != sender.i {
+                let sealed_share =
+                    SealedShare::seal(csprng, election_parameters, sender, recipient)?;
+                self.shares.push(DistributedShare {
+                    sender_i: sender.i,
+                    recipient_i: recipient.i,
+                    sealed_share,
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the share sent from guardian `sender_i` to guardian `recipient_i`, if any.
+    pub fn get(&self, sender_i: GuardianIndex, recipient_i: GuardianIndex) -> Option<&SealedShare> {
+        self.shares
+            .iter()
+            .find(|s| s.sender_i == sender_i && s.recipient_i == recipient_i)
+            .map(|s| &s.sealed_share)
+    }
+
+    /// Verifies that there is exactly one share for every ordered pair of distinct guardians
+    /// `1 <= i, ℓ <= n`, and nothing else.
+    pub fn validate_complete(&self, n: GuardianIndex) -> Result<()> {
+        let n = n.get_one_based_u32();
+
+        let mut seen = HashSet::new();
+        for s in &self.shares {
+            let (i, l) = (s.sender_i, s.recipient_i);
+            ensure!(
+                i.get_one_based_u32() <= n && l.get_one_based_u32() <= n,
+                "Share from guardian {i} to guardian {l} is for a guardian outside 1..={n}"
+            );
+            ensure!(i != l, "Guardian {i} has a share addressed to itself");
+            ensure!(
+                seen.insert((i, l)),
+                "Share from guardian {i} to guardian {l} is present more than once"
+            );
+        }
+
+        for i in 1..=n {
+            for l in (1..=n).filter(|&l| l != i) {
+                let pair = (
+                    GuardianIndex::from_one_based_index(i)?,
+                    GuardianIndex::from_one_based_index(l)?,
+                );
+                ensure!(
+                    seen.contains(&pair),
+                    "Share from guardian {i} to guardian {l} is missing"
+                );
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Opens every share sent to `recipient` and verifies it against the sender's coefficient
+    /// commitments.
+    pub fn verify_received(
+        &self,
+        election_parameters: &ElectionParameters,
+        recipient: &GuardianSecretKey,
+        guardian_public_keys: &[GuardianPublicKey],
+    ) -> Result<()> {
+        let fixed_parameters = &election_parameters.fixed_parameters;
+
+        for s in self.shares.iter().filter(|s| s.recipient_i == recipient.i) {
+            let sender = guardian_public_keys
+                .iter()
+                .find(|k| k.i == s.sender_i)
+                .ok_or_else(|| anyhow!("No public key for guardian {}", s.sender_i))?;
+
+            let share = s
+                .sealed_share
+                .open(election_parameters, s.sender_i, recipient)?;
+            verify_share(fixed_parameters, sender, recipient.i, &share)?;
+        }
+
+        Ok(())
+    }
+
+    /// Reads a `ShareDistribution` from a `std::io::Read` and validates that it is complete.
+    pub fn from_stdioread_validated(
+        stdioread: &mut dyn std::io::Read,
+        election_parameters: &ElectionParameters,
+    ) -> Result<Self> {
+        let self_ = Self::from_stdioread(stdioread)?;
+
+        self_.validate_complete(election_parameters.varying_parameters.n)?;
+
+        Ok(self_)
+    }
+
+    /// Reads a `ShareDistribution` from a `std::io::Read` without validating it.
+    pub fn from_stdioread(stdioread: &mut dyn std::io::Read) -> Result<Self> {
+        serde_json::from_reader(stdioread).context("Reading ShareDistribution")
+    }
+
+    /// Writes a `ShareDistribution` to a `std::io::Write`.
+    pub fn to_stdiowrite(&self, stdiowrite: &mut dyn std::io::Write) -> Result<()> {
+        let mut ser = serde_json::Serializer::pretty(stdiowrite);
+
+        self.serialize(&mut ser)
+            .map_err(Into::<anyhow::Error>::into)
+            .and_then(|_| ser.into_inner().write_all(b"\n").map_err(Into::into))
+            .context("Writing ShareDistribution")
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod test {
+    use super::*;
+    use crate::{
+        example_election::ExampleElection, example_election_parameters::example_election_parameters,
+    };
+
+    #[test]
+    fn test_share_distribution() {
+        let mut csprng = Csprng::new(b"test_share_distribution");
+
+        let election_parameters = example_election_parameters();
+        let n = election_parameters.varying_parameters.n;
+
+        let (guardian_secret_keys, guardian_public_keys) =
+            ExampleElection::generate_guardian_keys(&mut csprng, &election_parameters).unwrap();
+
+        let mut share_distribution = ShareDistribution::new();
+        for sender in &guardian_secret_keys {
+            share_distribution
+                .add_shares_from(
+                    &mut csprng,
+                    &election_parameters,
+                    sender,
+                    &guardian_public_keys,
+                )
+                .unwrap();
+        }
+        share_distribution.validate_complete(n).unwrap();
+
+        for recipient in &guardian_secret_keys {
+            share_distribution
+                .verify_received(&election_parameters, recipient, &guardian_public_keys)
+                .unwrap();
+        }
+
+        // Round trip.
+        let mut json = Vec::new();
+        share_distribution.to_stdiowrite(&mut json).unwrap();
+        let share_distribution_2 =
+            ShareDistribution::from_stdioread_validated(&mut json.as_slice(), &election_parameters)
+                .unwrap();
+        assert_eq!(share_distribution, share_distribution_2);
+
+        let i1 = GuardianIndex::from_one_based_index(1).unwrap();
+        let i2 = GuardianIndex::from_one_based_index(2).unwrap();
+
+        // Only the intended recipient can open a share.
+        let sealed_share = share_distribution.get(i1, i2).unwrap();
+        assert!(sealed_share
+            .open(&election_parameters, i1, &guardian_secret_keys[1])
+            .is_ok());
+        assert!(sealed_share
+            .open(&election_parameters, i1, &guardian_secret_keys[2])
+            .is_err());
+
+        // A share whose c0 isn't reduced mod p.
+        let mut unreduced = sealed_share.clone();
+        unreduced.c0 += election_parameters.fixed_parameters.p.as_ref();
+        let e = unreduced
+            .open(&election_parameters, i1, &guardian_secret_keys[1])
+            .unwrap_err()
+            .to_string();
+        assert!(e.contains("not a valid nonzero mod p value"), "{e}");
+
+        // A share whose MAC has been altered.
+        let mut tampered = sealed_share.clone();
+        tampered.c2.0[31] ^= 1;
+        let e = tampered
+            .open(&election_parameters, i1, &guardian_secret_keys[1])
+            .unwrap_err()
+            .to_string();
+        assert!(e.contains("fails its MAC"), "{e}");
+
+        // A share which doesn't match the sender's commitments.
+        let share = sealed_share
+            .open(&election_parameters, i1, &guardian_secret_keys[1])
+            .unwrap();
+        let fixed_parameters = &election_parameters.fixed_parameters;
+        verify_share(fixed_parameters, &guardian_public_keys[0], i2, &share).unwrap();
+        assert!(verify_share(
+            fixed_parameters,
+            &guardian_public_keys[0],
+            i2,
+            &(share + 1u8)
+        )
+        .is_err());
+
+        // Missing and duplicated pairs.
+        let mut incomplete = share_distribution.clone();
+        let removed = incomplete.shares.pop().unwrap();
+        let e = incomplete.validate_complete(n).unwrap_err().to_string();
+        assert!(e.contains("missing"), "{e}");
+
+        incomplete.shares.push(incomplete.shares[0].clone());
+        let e = incomplete.validate_complete(n).unwrap_err().to_string();
+        assert!(e.contains("more than once"), "{e}");
+
+        let mut to_self = removed;
+        to_self.recipient_i = to_self.sender_i;
+        incomplete.shares.pop();
+        incomplete.shares.push(to_self);
+        let e = incomplete.validate_complete(n).unwrap_err().to_string();
+        assert!(e.contains("itself"), "{e}");
+    }
+}
@@ -21,6 +21,20 @@ lazy_static! {
     pub static ref STANDARD_PARAMETERS: FixedParameters = make_standard_parameters_MSR_ElectionGuard_Design_Specification_v2_0();
 }
 
+/// Returns the standard parameters for the specified version of the ElectionGuard Design
+/// Specification, or `None` if there are none known for that version.
+pub fn standard_parameters_for_version(
+    version: &ElectionGuardDesignSpecificationVersion,
+) -> Option<FixedParameters> {
+    match version {
+        ElectionGuardDesignSpecificationVersion::Official(OfficialVersion {
+            version: [2, 0],
+            release: OfficialReleaseKind::Release,
+        }) => Some((*STANDARD_PARAMETERS).clone()),
+        _ => None,
+    }
+}
+
 /// Standard parameters, "MSR ElectionGuard Design Specification 2.0 of 2023-08-16"
 #[allow(non_snake_case)]
 pub fn make_standard_parameters_MSR_ElectionGuard_Design_Specification_v2_0() -> FixedParameters {
@@ -0,0 +1,2196 @@
+// Copyright (C) Microsoft Corporation. All rights reserved.
+
+#![deny(clippy::unwrap_used)]
+#![deny(clippy::expect_used)]
+#![deny(clippy::panic)]
+#![deny(clippy::manual_assert)]
+
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+use anyhow::{bail, ensure, Context, Result};
+use num_bigint::BigUint;
+use num_traits::One;
+use serde::{Deserialize, Serialize};
+
+use util::csprng::Csprng;
+
+use crate::{
+    ballot::{BallotEncrypted, BallotState},
+    ballot_style::BallotStyleIndex,
+    decryption_share::{combine_decryption_shares, verify_decryption_share, DecryptionShare},
+    election_manifest::{ContestIndex, ContestOptionIndex, ElectionManifest},
+    election_record::PreVotingData,
+    fixed_parameters::FixedParameters,
+    guardian::GuardianIndex,
+    guardian_key_share::{GuardianKeyShare, GuardianPublicKeyShare},
+    hash::{eg_h, HValue},
+    index::Index,
+    joint_election_public_key::Ciphertext,
+    vec1::Vec1,
+};
+
+/// The encrypted tally of a single contest.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ContestTally {
+    /// The encrypted total for each option, in the order defined in the manifest.
+    pub selection: Vec<Ciphertext>,
+
+    /// The contest's selection limit from the manifest.
+    /// No ballot can add more than this to the undervote count.
+    pub selection_limit: usize,
+
+    /// The encrypted number of ballots which left this contest blank, if the contest has
+    /// [`Contest::track_blank`](crate::election_manifest::Contest::track_blank) set.
+    #[serde(rename = "blank", default, skip_serializing_if = "Option::is_none")]
+    pub opt_blank: Option<Ciphertext>,
+
+    /// The encrypted total number of undervotes, if the contest has
+    /// [`Contest::track_undervotes`](crate::election_manifest::Contest::track_undervotes) set.
+    #[serde(rename = "undervote", default, skip_serializing_if = "Option::is_none")]
+    pub opt_undervote: Option<Ciphertext>,
+}
+
+/// Identifies one of the ciphertexts of a [`ContestTally`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ContestTallyPart {
+    /// The total of the option with this 0-based index.
+    Option(usize),
+
+    /// The blank count.
+    Blank,
+
+    /// The undervote count.
+    Undervote,
+}
+
+impl std::fmt::Display for ContestTallyPart {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ContestTallyPart::Option(option_ix) => write!(f, "Option {}", option_ix + 1),
+            ContestTallyPart::Blank => write!(f, "Blank count"),
+            ContestTallyPart::Undervote => write!(f, "Undervote count"),
+        }
+    }
+}
+
+impl ContestTally {
+    /// Returns the largest total any option of this contest can have after `ballot_count`
+    /// ballots. This bounds the discrete log needed to decrypt each option's total, and the
+    /// blank count.
+    ///
+    /// Every option ciphertext of a ballot is proven to encrypt 0 or 1, so this is just
+    /// `ballot_count`, whatever the selection limit.
+    pub fn dlog_bound(&self, ballot_count: usize) -> u64 {
+        ballot_count as u64
+    }
+
+    /// Returns the largest undervote count this contest can have after `ballot_count` ballots,
+    /// which is the selection limit times `ballot_count`.
+    pub fn undervote_dlog_bound(&self, ballot_count: usize) -> u64 {
+        (self.selection_limit as u64).saturating_mul(ballot_count as u64)
+    }
+}
+
+/// The homomorphic tally of a set of encrypted ballots.
+///
+/// Each option's ciphertext is the product of the corresponding ciphertexts of every ballot,
+/// which is an encryption of the sum of the votes.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Tally {
+    /// The tally of every contest, in the order defined in the manifest.
+    pub contests: Vec1<ContestTally>,
+
+    /// The number of ballots included.
+    pub ballot_count: usize,
+}
+
+/// The decrypted results of a single contest.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ContestResult {
+    /// The total for each option, in the order defined in the manifest.
+    pub option_totals: Vec<u64>,
+
+    /// The number of ballots which left the contest blank, if it was tracked.
+    #[serde(
+        rename = "blank_count",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub opt_blank_count: Option<u64>,
+
+    /// The total number of undervotes, if it was tracked.
+    #[serde(
+        rename = "undervote_count",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub opt_undervote_count: Option<u64>,
+}
+
+/// One guardian's decryption shares of every ciphertext of a [`ContestTally`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ContestDecryptionShares {
+    /// The share of each option's total, in the order defined in the manifest.
+    pub selection: Vec<DecryptionShare>,
+
+    /// The share of the blank count, if it was tracked.
+    #[serde(rename = "blank", default, skip_serializing_if = "Option::is_none")]
+    pub opt_blank: Option<DecryptionShare>,
+
+    /// The share of the undervote count, if it was tracked.
+    #[serde(rename = "undervote", default, skip_serializing_if = "Option::is_none")]
+    pub opt_undervote: Option<DecryptionShare>,
+}
+
+impl ContestDecryptionShares {
+    /// Computes the guardian's decryption shares of the ciphertexts of `contest_tally`, with
+    /// its key share.
+    pub fn compute(
+        csprng: &mut Csprng,
+        pvd: &PreVotingData,
+        guardian_key_share: &GuardianKeyShare,
+        contest_tally: &ContestTally,
+    ) -> Self {
+        let mut share_of = |ciphertext: &Ciphertext| {
+            DecryptionShare::compute(csprng, pvd, guardian_key_share, ciphertext)
+        };
+        ContestDecryptionShares {
+            selection: contest_tally.selection.iter().map(&mut share_of).collect(),
+            opt_blank: contest_tally.opt_blank.as_ref().map(&mut share_of),
+            opt_undervote: contest_tally.opt_undervote.as_ref().map(share_of),
+        }
+    }
+
+    /// Collects every guardian's share of the specified ciphertext.
+    pub fn shares_of(
+        decryption_shares: &[ContestDecryptionShares],
+        part: ContestTallyPart,
+    ) -> Result<Vec<DecryptionShare>> {
+        decryption_shares
+            .iter()
+            .map(|shares| {
+                match part {
+                    ContestTallyPart::Option(option_ix) => shares.selection.get(option_ix),
+                    ContestTallyPart::Blank => shares.opt_blank.as_ref(),
+                    ContestTallyPart::Undervote => shares.opt_undervote.as_ref(),
+                }
+                .cloned()
+                .context("Missing a decryption share")
+            })
+            .collect()
+    }
+}
+
+/// A guardian's [`ContestDecryptionShares`] for every contest of a [`Tally`].
+///
+/// This is what a guardian publishes so that decryption can be coordinated from another
+/// machine, which collects one from each guardian taking part.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "strict-parsing", serde(deny_unknown_fields))]
+pub struct DecryptionShareSet {
+    /// Guardian number, 1 <= i <= n.
+    pub i: GuardianIndex,
+
+    /// The shares of each contest, in the order defined in the manifest.
+    pub contests: Vec<ContestDecryptionShares>,
+}
+
+impl DecryptionShareSet {
+    /// Computes the guardian's decryption shares of every contest of `tally`.
+    pub fn compute(
+        csprng: &mut Csprng,
+        pvd: &PreVotingData,
+        guardian_key_share: &GuardianKeyShare,
+        tally: &Tally,
+    ) -> Self {
+        let contests = tally
+            .contests
+            .indices()
+            .filter_map(|contest_ix| tally.contests.get(contest_ix))
+            .map(|contest_tally| {
+                ContestDecryptionShares::compute(csprng, pvd, guardian_key_share, contest_tally)
+            })
+            .collect();
+        DecryptionShareSet {
+            i: guardian_key_share.i,
+            contests,
+        }
+    }
+
+    /// Verifies that the set has a share of every ciphertext of `tally`, all from the
+    /// guardian with `guardian_public_key_share`, and that every share's proof is valid.
+    pub fn validate(
+        &self,
+        pvd: &PreVotingData,
+        guardian_public_key_share: &GuardianPublicKeyShare,
+        tally: &Tally,
+    ) -> Result<()> {
+        let i = self.i;
+        ensure!(
+            guardian_public_key_share.i == i,
+            "Shares of guardian {i} can't be validated with the public key share of guardian {}",
+            guardian_public_key_share.i
+        );
+        ensure!(
+            self.contests.len() == tally.contests.len(),
+            "Decryption shares of guardian {i} cover {} contests, but the tally has {}",
+            self.contests.len(),
+            tally.contests.len()
+        );
+
+        for ((contest_tally, shares), contest_ix1) in tally
+            .contests
+            .indices()
+            .filter_map(|contest_ix| tally.contests.get(contest_ix))
+            .zip(&self.contests)
+            .zip(1..)
+        {
+            ensure!(
+                shares.selection.len() == contest_tally.selection.len()
+                    && shares.opt_blank.is_some() == contest_tally.opt_blank.is_some()
+                    && shares.opt_undervote.is_some() == contest_tally.opt_undervote.is_some(),
+                "Shares of guardian {i} don't match the ciphertexts of contest {contest_ix1}"
+            );
+
+            let pairs = contest_tally
+                .selection
+                .iter()
+                .zip(&shares.selection)
+                .chain(contest_tally.opt_blank.iter().zip(&shares.opt_blank))
+                .chain(
+                    contest_tally
+                        .opt_undervote
+                        .iter()
+                        .zip(&shares.opt_undervote),
+                );
+            for (ciphertext, share) in pairs {
+                ensure!(
+                    share.i == i,
+                    "Decryption shares of guardian {i} include a share from guardian {}",
+                    share.i
+                );
+                verify_decryption_share(pvd, guardian_public_key_share, ciphertext, share)
+                    .with_context(|| format!("Contest {contest_ix1}"))?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Reads a `DecryptionShareSet` from a `std::io::Read` and validates it against the
+    /// guardian's public key share and the tally it is for.
+    pub fn from_stdioread_validated(
+        stdioread: &mut dyn std::io::Read,
+        pvd: &PreVotingData,
+        guardian_public_key_share: &GuardianPublicKeyShare,
+        tally: &Tally,
+    ) -> Result<Self> {
+        let self_ = Self::from_stdioread(stdioread)?;
+
+        self_.validate(pvd, guardian_public_key_share, tally)?;
+
+        Ok(self_)
+    }
+
+    /// Reads a `DecryptionShareSet` from a `std::io::Read` without validating it.
+    pub fn from_stdioread(stdioread: &mut dyn std::io::Read) -> Result<Self> {
+        serde_json::from_reader(stdioread).context("Reading DecryptionShareSet")
+    }
+
+    /// Writes a `DecryptionShareSet` to a `std::io::Write`.
+    pub fn to_stdiowrite(&self, stdiowrite: &mut dyn std::io::Write) -> Result<()> {
+        let mut ser = serde_json::Serializer::pretty(stdiowrite);
+
+        self.serialize(&mut ser)
+            .map_err(Into::<anyhow::Error>::into)
+            .and_then(|_| ser.into_inner().write_all(b"\n").map_err(Into::into))
+            .context("Writing DecryptionShareSet")
+    }
+}
+
+/// The decrypted results of a single contest of a [`Tally`], along with the guardians'
+/// decryption shares, so that the contest can be published and verified on its own.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DecryptedContest {
+    /// The contest which was decrypted.
+    pub contest_ix: ContestIndex,
+
+    /// The decrypted results.
+    pub result: ContestResult,
+
+    /// The decryption shares of each guardian.
+    pub decryption_shares: Vec<ContestDecryptionShares>,
+}
+
+/// The decrypted results of a [`Tally`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DecryptedTally {
+    /// The results of every contest, in the order defined in the manifest.
+    pub contests: Vec1<ContestResult>,
+
+    /// The number of ballots included.
+    pub ballot_count: usize,
+}
+
+impl DecryptedTally {
+    /// Reads a `DecryptedTally` from a `std::io::Read`.
+    pub fn from_stdioread(stdioread: &mut dyn std::io::Read) -> Result<Self> {
+        serde_json::from_reader(stdioread).context("Reading DecryptedTally")
+    }
+
+    /// Writes a `DecryptedTally` to a `std::io::Write`.
+    pub fn to_stdiowrite(&self, stdiowrite: &mut dyn std::io::Write) -> Result<()> {
+        let mut ser = serde_json::Serializer::pretty(stdiowrite);
+
+        self.serialize(&mut ser)
+            .map_err(Into::<anyhow::Error>::into)
+            .and_then(|_| ser.into_inner().write_all(b"\n").map_err(Into::into))
+            .context("Writing DecryptedTally")
+    }
+
+    /// Returns the canonical byte sequence representation of the `DecryptedTally`.
+    /// This uses a more compact JSON format.
+    pub fn to_canonical_bytes(&self) -> Result<Vec<u8>> {
+        serde_json::to_vec(self).context("Writing DecryptedTally canonical")
+    }
+
+    /// Returns the decrypted total of an option, identified by the labels of its contest and
+    /// itself in the manifest.
+    pub fn count_for(
+        &self,
+        election_manifest: &ElectionManifest,
+        contest_label: &str,
+        option_label: &str,
+    ) -> Result<u64> {
+        let (contest_ix, option_ix) =
+            resolve_option_labels(election_manifest, contest_label, option_label)?;
+
+        self.contests
+            .get(Index::from_one_based_index(contest_ix.get_one_based_u32())?)
+            .and_then(|contest_result| {
+                contest_result
+                    .option_totals
+                    .get(option_ix.get_zero_based_usize())
+            })
+            .copied()
+            .with_context(|| {
+                format!("Decrypted tally has no total for contest {contest_ix} option {option_ix}")
+            })
+    }
+
+    /// Returns a human-readable summary of the results, with the total of each option and,
+    /// for yes/no questions, the outcome as described by
+    /// [`ContestKind::outcome`](crate::election_manifest::ContestKind::outcome).
+    pub fn summary(&self, election_manifest: &ElectionManifest) -> Result<String> {
+        use std::fmt::Write;
+
+        ensure!(
+            self.contests.len() == election_manifest.contests.len(),
+            "Decrypted tally has {} contests, but the manifest has {}",
+            self.contests.len(),
+            election_manifest.contests.len()
+        );
+
+        let mut s = String::new();
+        writeln!(s, "{}", election_manifest.label)?;
+        writeln!(s, "Ballots: {}", self.ballot_count)?;
+
+        for contest_ix in election_manifest.contests.indices() {
+            let (Some(contest), Some(contest_result)) = (
+                election_manifest.contests.get(contest_ix),
+                self.contests
+                    .get(Index::from_one_based_index(contest_ix.get_one_based_u32())?),
+            ) else {
+                continue;
+            };
+
+            ensure!(
+                contest_result.option_totals.len() == contest.options.len(),
+                "Decrypted tally has {} options for contest {contest_ix}, but the manifest has {}",
+                contest_result.option_totals.len(),
+                contest.options.len()
+            );
+
+            writeln!(s)?;
+            writeln!(s, "{}", contest.label.replace('\n', " "))?;
+
+            for (option_ix, total) in contest
+                .options
+                .indices()
+                .zip(contest_result.option_totals.iter())
+            {
+                if let Some(option) = contest.options.get(option_ix) {
+                    writeln!(s, "    {total:>8}  {}", option.label.replace('\n', " "))?;
+                }
+            }
+
+            if let Some(blank_count) = contest_result.opt_blank_count {
+                writeln!(s, "    {blank_count:>8}  (blank)")?;
+            }
+
+            if let Some(undervote_count) = contest_result.opt_undervote_count {
+                writeln!(s, "    {undervote_count:>8}  (undervotes)")?;
+            }
+
+            if let Some(outcome) = contest.kind.outcome(&contest_result.option_totals) {
+                writeln!(s, "    {outcome}")?;
+            }
+        }
+
+        Ok(s)
+    }
+}
+
+/// Resolves the labels of a contest and one of its options to their indices in the manifest.
+fn resolve_option_labels(
+    election_manifest: &ElectionManifest,
+    contest_label: &str,
+    option_label: &str,
+) -> Result<(ContestIndex, ContestOptionIndex)> {
+    let contest_ix = election_manifest.contest_ix_by_label(contest_label)?;
+
+    // `unwrap()` is justified here because `contest_ix` was just found in the manifest.
+    #[allow(clippy::unwrap_used)]
+    let contest = election_manifest.contests.get(contest_ix).unwrap();
+
+    Ok((contest_ix, contest.option_ix_by_label(option_label)?))
+}
+
+/// Detects an encrypted ballot being submitted more than once.
+///
+/// Every legitimate ballot is encrypted with fresh nonces, so two ballots with exactly the same
+/// ciphertexts can only be a replay. The nonces alone make an accidental match negligibly
+/// unlikely; this is a belt-and-suspenders check that stops a resubmitted ballot from being
+/// counted twice.
+#[derive(Debug, Default)]
+pub struct BallotReplayGuard {
+    /// The confirmation code of the first ballot seen with each digest of ciphertexts.
+    seen: HashMap<HValue, HValue>,
+}
+
+impl BallotReplayGuard {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records the ballot, or returns an error if a ballot with exactly the same ciphertexts
+    /// has already been recorded.
+    pub fn check(
+        &mut self,
+        fixed_parameters: &FixedParameters,
+        ballot: &BallotEncrypted,
+    ) -> Result<()> {
+        let digest = Self::ciphertexts_digest(fixed_parameters, ballot);
+        if let Some(first_confirmation_code) = self.seen.get(&digest) {
+            bail!(
+                "Ballot {} has the same ciphertexts as ballot {first_confirmation_code}, and is a replay",
+                ballot.confirmation_code
+            );
+        }
+        self.seen.insert(digest, ballot.confirmation_code);
+        Ok(())
+    }
+
+    /// Hashes every ciphertext of the ballot, including blank indicators, in order.
+    fn ciphertexts_digest(fixed_parameters: &FixedParameters, ballot: &BallotEncrypted) -> HValue {
+        let mut v = Vec::new();
+        for contest_ix in ballot.contests.indices() {
+            let Some(contest_encrypted) = ballot.contests.get(contest_ix) else {
+                continue;
+            };
+            let blank = contest_encrypted
+                .opt_blank_indicator
+                .as_ref()
+                .map(|blank_indicator| &blank_indicator.ciphertext);
+            for ciphertext in contest_encrypted.selection.iter().chain(blank) {
+                v.append(&mut fixed_parameters.biguint_to_be_bytes_len_p(&ciphertext.alpha));
+                v.append(&mut fixed_parameters.biguint_to_be_bytes_len_p(&ciphertext.beta));
+            }
+        }
+        eg_h(&HValue::default(), &v)
+    }
+}
+
+/// The encryption of zero with nonce zero, i.e., the identity for homomorphic addition.
+fn ciphertext_zero() -> Ciphertext {
+    Ciphertext {
+        alpha: BigUint::one(),
+        beta: BigUint::one(),
+        nonce: None,
+    }
+}
+
+/// Homomorphically adds `b` into `a`.
+pub(crate) fn ciphertext_accumulate(
+    fixed_parameters: &FixedParameters,
+    a: &mut Ciphertext,
+    b: &Ciphertext,
+) {
+    let p = fixed_parameters.p.as_ref();
+    a.alpha = (&a.alpha * &b.alpha) % p;
+    a.beta = (&a.beta * &b.beta) % p;
+}
+
+impl Tally {
+    /// Creates an empty tally with the shape of the contests in the manifest.
+    pub fn new(election_manifest: &ElectionManifest) -> Self {
+        let mut contests = Vec1::with_capacity(election_manifest.contests.len());
+        for contest_ix in election_manifest.contests.indices() {
+            let Some(contest) = election_manifest.contests.get(contest_ix) else {
+                continue;
+            };
+
+            // `unwrap()` is justified here because `contests` has the same length as the
+            // manifest's contests, which is known to be valid.
+            #[allow(clippy::unwrap_used)]
+            contests
+                .try_push(ContestTally {
+                    selection: vec![ciphertext_zero(); contest.options.len()],
+                    selection_limit: contest.selection_limit,
+                    opt_blank: contest.track_blank.then(ciphertext_zero),
+                    opt_undervote: contest.track_undervotes.then(ciphertext_zero),
+                })
+                .unwrap();
+        }
+
+        Tally {
+            contests,
+            ballot_count: 0,
+        }
+    }
+
+    /// Returns the encrypted total of an option, identified by the labels of its contest and
+    /// itself in the manifest.
+    pub fn encrypted_count_for(
+        &self,
+        election_manifest: &ElectionManifest,
+        contest_label: &str,
+        option_label: &str,
+    ) -> Result<&Ciphertext> {
+        let (contest_ix, option_ix) =
+            resolve_option_labels(election_manifest, contest_label, option_label)?;
+
+        self.contests
+            .get(Index::from_one_based_index(contest_ix.get_one_based_u32())?)
+            .and_then(|contest_tally| {
+                contest_tally
+                    .selection
+                    .get(option_ix.get_zero_based_usize())
+            })
+            .with_context(|| {
+                format!("Tally has no ciphertext for contest {contest_ix} option {option_ix}")
+            })
+    }
+
+    /// Tallies the specified ballots.
+    ///
+    /// Only ballots which are [`BallotState::Cast`] are counted. The others are still checked
+    /// for replays, but are left out.
+    /// Fails if any ballot is a replay of an earlier one, as detected by [`BallotReplayGuard`].
+    pub fn from_ballots<'a, I>(
+        fixed_parameters: &FixedParameters,
+        election_manifest: &ElectionManifest,
+        ballots: I,
+    ) -> Result<Self>
+    where
+        I: IntoIterator<Item = &'a BallotEncrypted>,
+    {
+        let mut tally = Self::new(election_manifest);
+        let mut replay_guard = BallotReplayGuard::new();
+        for ballot in ballots {
+            replay_guard.check(fixed_parameters, ballot)?;
+            if ballot.state == BallotState::Cast {
+                tally.add_ballot(fixed_parameters, ballot)?;
+            }
+        }
+        Ok(tally)
+    }
+
+    /// Homomorphically adds an encrypted ballot to the tally.
+    ///
+    /// Fails unless the ballot is [`BallotState::Cast`]. A challenged ballot may have had its
+    /// nonces disclosed, and an uncast one may yet be challenged.
+    pub fn add_ballot(
+        &mut self,
+        fixed_parameters: &FixedParameters,
+        ballot: &BallotEncrypted,
+    ) -> Result<()> {
+        ensure!(
+            ballot.state == BallotState::Cast,
+            "Ballot {} is {:?}, so it is not counted",
+            ballot.confirmation_code,
+            ballot.state
+        );
+        ensure!(
+            ballot.contests.len() == self.contests.len(),
+            "Ballot {} has {} contests, but the tally has {}",
+            ballot.confirmation_code,
+            ballot.contests.len(),
+            self.contests.len()
+        );
+
+        // Check the shape of every contest before modifying anything. A contest omitted by the
+        // ballot style has no blank indicator or undervote count, and adds nothing to them.
+        for (contest_tally, contest_encrypted) in self.contests_zip(&ballot.contests) {
+            ensure!(
+                contest_encrypted.selection.len() == contest_tally.selection.len(),
+                "Ballot {} has a contest with {} options, but the tally has {}",
+                ballot.confirmation_code,
+                contest_encrypted.selection.len(),
+                contest_tally.selection.len()
+            );
+            if contest_encrypted.is_omitted() {
+                continue;
+            }
+            ensure!(
+                contest_encrypted.opt_blank_indicator.is_some()
+                    == contest_tally.opt_blank.is_some(),
+                "Ballot {} has a contest whose blank indicator does not match the tally",
+                ballot.confirmation_code
+            );
+            ensure!(
+                contest_encrypted.opt_undervote.is_some() == contest_tally.opt_undervote.is_some(),
+                "Ballot {} has a contest whose undervote count does not match the tally",
+                ballot.confirmation_code
+            );
+        }
+
+        for contest_ix in self.contests.indices() {
+            let (Some(contest_tally), Some(contest_encrypted)) = (
+                self.contests.get_mut(contest_ix),
+                ballot
+                    .contests
+                    .get(Index::from_one_based_index(contest_ix.get_one_based_u32())?),
+            ) else {
+                continue;
+            };
+
+            for (total, ciphertext) in contest_tally
+                .selection
+                .iter_mut()
+                .zip(contest_encrypted.selection.iter())
+            {
+                ciphertext_accumulate(fixed_parameters, total, ciphertext);
+            }
+
+            if let (Some(total), Some(blank_indicator)) = (
+                contest_tally.opt_blank.as_mut(),
+                contest_encrypted.opt_blank_indicator.as_ref(),
+            ) {
+                ciphertext_accumulate(fixed_parameters, total, &blank_indicator.ciphertext);
+            }
+
+            if let (Some(total), Some(undervote)) = (
+                contest_tally.opt_undervote.as_mut(),
+                contest_encrypted.opt_undervote.as_ref(),
+            ) {
+                ciphertext_accumulate(fixed_parameters, total, &undervote.ciphertext);
+            }
+        }
+
+        self.ballot_count += 1;
+
+        Ok(())
+    }
+
+    /// Homomorphically adds another tally into this one.
+    pub fn add_tally(&mut self, fixed_parameters: &FixedParameters, other: &Tally) -> Result<()> {
+        ensure!(
+            other.contests.len() == self.contests.len(),
+            "Can't add a tally of {} contests to a tally of {}",
+            other.contests.len(),
+            self.contests.len()
+        );
+
+        for (a, b) in self.contests_zip(&other.contests) {
+            ensure!(
+                a.selection.len() == b.selection.len(),
+                "Can't add tallies of contests with different numbers of options"
+            );
+            ensure!(
+                a.selection_limit == b.selection_limit,
+                "Can't add tallies of contests with different selection limits"
+            );
+            ensure!(
+                a.opt_blank.is_some() == b.opt_blank.is_some(),
+                "Can't add a tally of a contest with blank tracking to one without"
+            );
+            ensure!(
+                a.opt_undervote.is_some() == b.opt_undervote.is_some(),
+                "Can't add a tally of a contest with undervote tracking to one without"
+            );
+        }
+
+        for contest_ix in self.contests.indices() {
+            let (Some(a), Some(b)) = (
+                self.contests.get_mut(contest_ix),
+                other.contests.get(contest_ix),
+            ) else {
+                continue;
+            };
+
+            for (total, ciphertext) in a.selection.iter_mut().zip(b.selection.iter()) {
+                ciphertext_accumulate(fixed_parameters, total, ciphertext);
+            }
+            if let (Some(total), Some(ciphertext)) = (a.opt_blank.as_mut(), b.opt_blank.as_ref()) {
+                ciphertext_accumulate(fixed_parameters, total, ciphertext);
+            }
+            if let (Some(total), Some(ciphertext)) =
+                (a.opt_undervote.as_mut(), b.opt_undervote.as_ref())
+            {
+                ciphertext_accumulate(fixed_parameters, total, ciphertext);
+            }
+        }
+
+        self.ballot_count += other.ballot_count;
+
+        Ok(())
+    }
+
+    /// Returns the discrete log bound of the option totals of the specified contest, as given
+    /// by [`ContestTally::dlog_bound`] for the number of ballots in this tally.
+    ///
+    /// Decrypting with the tightest bound is fastest, and lets a total which is out of range
+    /// be detected early.
+    pub fn dlog_bound(&self, contest_ix: ContestIndex) -> Option<u64> {
+        self.contests
+            .get(Index::from_one_based_index(contest_ix.get_one_based_u32()).ok()?)
+            .map(|contest_tally| contest_tally.dlog_bound(self.ballot_count))
+    }
+
+    /// Returns the tally of the specified contest.
+    pub fn contest(&self, contest_ix: ContestIndex) -> Result<&ContestTally> {
+        self.contests
+            .get(Index::from_one_based_index(contest_ix.get_one_based_u32())?)
+            .with_context(|| format!("Tally has no contest {contest_ix}"))
+    }
+
+    /// Decrypts just the specified contest, using a [`ContestDecryptionShares`] from each of
+    /// at least `k` guardians.
+    ///
+    /// This allows results to be published one contest at a time, while others are still
+    /// being adjudicated. The shares are not verified here, see
+    /// [`verify_decrypted_contests`](crate::verification::verify_decrypted_contests).
+    pub fn decrypt_contest(
+        &self,
+        pvd: &PreVotingData,
+        contest_ix: ContestIndex,
+        decryption_shares: &[ContestDecryptionShares],
+    ) -> Result<DecryptedContest> {
+        let contest_tally = self.contest(contest_ix)?;
+
+        let decrypt = |ciphertext: &Ciphertext, part: ContestTallyPart, bound: u64| {
+            let shares = ContestDecryptionShares::shares_of(decryption_shares, part)?;
+            let t = combine_decryption_shares(pvd, ciphertext, &shares)?;
+            t.discrete_log(pvd, bound)
+                .with_context(|| format!("Decrypted total is not in the range 0 to {bound}"))
+        };
+
+        let selection_bound = contest_tally.dlog_bound(self.ballot_count);
+        let option_totals = contest_tally
+            .selection
+            .iter()
+            .enumerate()
+            .map(|(option_ix, ciphertext)| {
+                decrypt(
+                    ciphertext,
+                    ContestTallyPart::Option(option_ix),
+                    selection_bound,
+                )
+                .with_context(|| format!("Contest {contest_ix} option {}", option_ix + 1))
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        let opt_blank_count = contest_tally
+            .opt_blank
+            .as_ref()
+            .map(|ciphertext| {
+                decrypt(ciphertext, ContestTallyPart::Blank, selection_bound)
+                    .with_context(|| format!("Contest {contest_ix} blank count"))
+            })
+            .transpose()?;
+
+        let opt_undervote_count = contest_tally
+            .opt_undervote
+            .as_ref()
+            .map(|ciphertext| {
+                decrypt(
+                    ciphertext,
+                    ContestTallyPart::Undervote,
+                    contest_tally.undervote_dlog_bound(self.ballot_count),
+                )
+                .with_context(|| format!("Contest {contest_ix} undervote count"))
+            })
+            .transpose()?;
+
+        Ok(DecryptedContest {
+            contest_ix,
+            result: ContestResult {
+                option_totals,
+                opt_blank_count,
+                opt_undervote_count,
+            },
+            decryption_shares: decryption_shares.to_vec(),
+        })
+    }
+
+    /// Decrypts every contest of the tally, with a [`DecryptionShareSet`] from each of at least
+    /// `k` guardians.
+    ///
+    /// The shares are not verified here, see [`DecryptionShareSet::validate`]. Errors if the
+    /// sets are from fewer than `k` distinct guardians, or more than one is from the same one.
+    pub fn decrypt(
+        &self,
+        pvd: &PreVotingData,
+        decryption_share_sets: &[DecryptionShareSet],
+    ) -> Result<DecryptedTally> {
+        let varying_parameters = &pvd.parameters.varying_parameters;
+        let mut guardians = BTreeSet::new();
+        for share_set in decryption_share_sets {
+            let i = share_set.i;
+            ensure!(
+                i <= varying_parameters.n,
+                "Decryption shares from guardian {i}, but there are only n={} guardians",
+                varying_parameters.n
+            );
+            ensure!(
+                guardians.insert(i),
+                "More than one set of decryption shares from guardian {i}"
+            );
+        }
+        ensure!(
+            varying_parameters.k.as_quantity() <= guardians.len(),
+            "Decrypting the tally requires the decryption shares of at least k={} guardians, found {}",
+            varying_parameters.k,
+            guardians.len()
+        );
+
+        let mut contests = Vec1::with_capacity(self.contests.len());
+        for contest_ix in self.contests.indices() {
+            let contest_shares = decryption_share_sets
+                .iter()
+                .map(|share_set| {
+                    share_set
+                        .contests
+                        .get(contest_ix.get_zero_based_usize())
+                        .cloned()
+                        .with_context(|| {
+                            format!(
+                                "Decryption shares of guardian {} do not cover contest {contest_ix}",
+                                share_set.i
+                            )
+                        })
+                })
+                .collect::<Result<Vec<_>>>()?;
+
+            let contest_ix = ContestIndex::from_one_based_index(contest_ix.get_one_based_u32())?;
+            let decrypted_contest = self.decrypt_contest(pvd, contest_ix, &contest_shares)?;
+            contests.try_push(decrypted_contest.result)?;
+        }
+
+        Ok(DecryptedTally {
+            contests,
+            ballot_count: self.ballot_count,
+        })
+    }
+
+    /// Tallies the specified ballots separately, partitioned by the key returned by
+    /// `key_fn` for each ballot.
+    ///
+    /// Each sub-tally can be decrypted and published independently. Adding them all together
+    /// gives the same result as [`Tally::from_ballots`]. As there, only cast ballots are
+    /// counted, and a ballot which replays one in any partition is rejected.
+    pub fn partition_by<'a, I, K, F>(
+        fixed_parameters: &FixedParameters,
+        election_manifest: &ElectionManifest,
+        ballots: I,
+        key_fn: F,
+    ) -> Result<BTreeMap<K, Tally>>
+    where
+        I: IntoIterator<Item = &'a BallotEncrypted>,
+        K: Ord,
+        F: Fn(&BallotEncrypted) -> K,
+    {
+        let mut tallies = BTreeMap::new();
+        let mut replay_guard = BallotReplayGuard::new();
+        for ballot in ballots {
+            replay_guard.check(fixed_parameters, ballot)?;
+            if ballot.state != BallotState::Cast {
+                continue;
+            }
+            tallies
+                .entry(key_fn(ballot))
+                .or_insert_with(|| Tally::new(election_manifest))
+                .add_ballot(fixed_parameters, ballot)?;
+        }
+        Ok(tallies)
+    }
+
+    /// Tallies the specified ballots separately for each ballot style.
+    /// Ballots with no recorded ballot style are tallied under `None`.
+    pub fn by_ballot_style<'a, I>(
+        fixed_parameters: &FixedParameters,
+        election_manifest: &ElectionManifest,
+        ballots: I,
+    ) -> Result<BTreeMap<Option<BallotStyleIndex>, Tally>>
+    where
+        I: IntoIterator<Item = &'a BallotEncrypted>,
+    {
+        Self::partition_by(
+            fixed_parameters,
+            election_manifest,
+            ballots,
+            BallotEncrypted::ballot_style,
+        )
+    }
+
+    /// Validates that the tally has the shape of the contests in the manifest, so that
+    /// ballots of the election can be added to it, and that its ciphertexts are pairs of valid
+    /// nonzero mod p values.
+    pub fn validate(
+        &self,
+        fixed_parameters: &FixedParameters,
+        election_manifest: &ElectionManifest,
+    ) -> Result<()> {
+        let expected = Self::new(election_manifest);
+        ensure!(
+            self.contests.len() == expected.contests.len(),
+            "Tally has {} contests, but the manifest has {}",
+            self.contests.len(),
+            expected.contests.len()
+        );
+
+        for ((expected_contest, contest_tally), contest_ix1) in
+            expected.contests_zip(&self.contests).zip(1..)
+        {
+            ensure!(
+                contest_tally.selection.len() == expected_contest.selection.len()
+                    && contest_tally.selection_limit == expected_contest.selection_limit
+                    && contest_tally.opt_blank.is_some() == expected_contest.opt_blank.is_some()
+                    && contest_tally.opt_undervote.is_some()
+                        == expected_contest.opt_undervote.is_some(),
+                "Tally of contest {contest_ix1} does not match the contest in the manifest"
+            );
+
+            for ciphertext in contest_tally
+                .selection
+                .iter()
+                .chain(&contest_tally.opt_blank)
+                .chain(&contest_tally.opt_undervote)
+            {
+                ciphertext
+                    .validate(fixed_parameters)
+                    .with_context(|| format!("Tally of contest {contest_ix1}"))?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Reads a `Tally` from a `std::io::Read` and validates it against the election.
+    pub fn from_stdioread_validated(
+        stdioread: &mut dyn std::io::Read,
+        fixed_parameters: &FixedParameters,
+        election_manifest: &ElectionManifest,
+    ) -> Result<Self> {
+        let self_ = Self::from_stdioread(stdioread)?;
+
+        self_.validate(fixed_parameters, election_manifest)?;
+
+        Ok(self_)
+    }
+
+    /// Reads a `Tally` from a `std::io::Read` without validating it.
+    pub fn from_stdioread(stdioread: &mut dyn std::io::Read) -> Result<Self> {
+        serde_json::from_reader(stdioread).context("Reading Tally")
+    }
+
+    /// Writes a `Tally` to a `std::io::Write`.
+    pub fn to_stdiowrite(&self, stdiowrite: &mut dyn std::io::Write) -> Result<()> {
+        let mut ser = serde_json::Serializer::pretty(stdiowrite);
+
+        self.serialize(&mut ser)
+            .map_err(Into::<anyhow::Error>::into)
+            .and_then(|_| ser.into_inner().write_all(b"\n").map_err(Into::into))
+            .context("Writing Tally")
+    }
+
+    /// Iterates over pairs of this tally's contests and the corresponding element of `other`.
+    fn contests_zip<'a, T>(
+        &'a self,
+        other: &'a Vec1<T>,
+    ) -> impl Iterator<Item = (&'a ContestTally, &'a T)> + 'a {
+        self.contests.indices().filter_map(move |contest_ix| {
+            let other_ix = Index::from_one_based_index(contest_ix.get_one_based_u32()).ok()?;
+            Some((self.contests.get(contest_ix)?, other.get(other_ix)?))
+        })
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod test {
+    use super::*;
+    use crate::{
+        contest_selection::ContestSelection,
+        device::Device,
+        example_election::ExampleElection,
+        example_election_manifest::{
+            example_election_manifest, example_election_manifest_first_contests,
+        },
+        example_election_parameters::example_election_parameters,
+        verification::verify_decrypted_contests,
+    };
+
+    #[test]
+    fn test_partition_sums_to_total() {
+        let mut csprng = Csprng::new(b"test_partition_sums_to_total");
+
+        let example_election = ExampleElection::with_first_contests(&mut csprng, 2).unwrap();
+        let manifest = &example_election.pvd.manifest;
+        let fixed_parameters = &example_election.pvd.parameters.fixed_parameters;
+
+        let ballot_style_1 = BallotStyleIndex::from_one_based_index(1).unwrap();
+        let ballot_style_2 = BallotStyleIndex::from_one_based_index(2).unwrap();
+
+        // The last ballot has no ballot style.
+        let ballots = example_election
+            .encrypt_ballots(
+                &mut csprng,
+                &[
+                    r#"{ "ballot_style": 1, "contests": [ { "vote": [1, 0] }, { "vote": [0, 1, 0, 0] } ] }"#,
+                    r#"{ "ballot_style": 1, "contests": [ { "vote": [0, 1] }, { "vote": [0, 0, 0, 1] } ] }"#,
+                    r#"{ "ballot_style": 2, "contests": [ { "vote": [0, 1] }, { "vote": [1, 0, 0, 0] } ] }"#,
+                    r#"{ "ballot_style": 2, "contests": [ { "vote": [0, 0] }, { "vote": [0, 1, 0, 0] } ] }"#,
+                    r#"{ "contests": [ { "vote": [1, 0] }, { "vote": [0, 0, 1, 0] } ] }"#,
+                ],
+            )
+            .unwrap();
+
+        let total = Tally::from_ballots(fixed_parameters, manifest, &ballots).unwrap();
+        assert_eq!(total.ballot_count, ballots.len());
+
+        let by_style = Tally::by_ballot_style(fixed_parameters, manifest, &ballots).unwrap();
+        assert_eq!(by_style.len(), 3);
+        assert_eq!(by_style[&Some(ballot_style_1)].ballot_count, 2);
+        assert_eq!(by_style[&Some(ballot_style_2)].ballot_count, 2);
+        assert_eq!(by_style[&None].ballot_count, 1);
+
+        let mut sum = Tally::new(manifest);
+        for sub_tally in by_style.values() {
+            sum.add_tally(fixed_parameters, sub_tally).unwrap();
+        }
+        assert_eq!(sum, total);
+
+        // A ballot that doesn't match the shape of the tally is rejected.
+        let mut other_manifest = manifest.clone();
+        other_manifest.contests.truncate(1);
+        let mut other_tally = Tally::new(&other_manifest);
+        assert!(other_tally
+            .add_ballot(fixed_parameters, &ballots[0])
+            .is_err());
+        assert_eq!(other_tally.ballot_count, 0);
+    }
+
+    #[test]
+    fn test_only_cast_ballots_counted() {
+        use crate::verification::verify_tally;
+
+        let mut csprng = Csprng::new(b"test_only_cast_ballots_counted");
+
+        let example_election = ExampleElection::with_first_contests(&mut csprng, 1).unwrap();
+        let pvd = &example_election.pvd;
+        let manifest = &pvd.manifest;
+        let fixed_parameters = &pvd.parameters.fixed_parameters;
+
+        let mut ballots = example_election
+            .encrypt_ballots(
+                &mut csprng,
+                &[
+                    r#"{ "contests": [ { "vote": [1, 0] } ] }"#,
+                    r#"{ "contests": [ { "vote": [0, 1] } ] }"#,
+                ],
+            )
+            .unwrap();
+        let tally_cast = Tally::from_ballots(fixed_parameters, manifest, &ballots).unwrap();
+        assert_eq!(tally_cast.ballot_count, 2);
+
+        // A challenged ballot, whose nonces are disclosed, and one which is not yet cast.
+        let device = Device::new("test", pvd.clone());
+        let ctest_selections: Vec1<ContestSelection> =
+            [ContestSelection { vote: vec![1, 0] }].try_into().unwrap();
+        let mut challenged =
+            BallotEncrypted::new_from_selections(&device, &mut csprng, &[1; 32], &ctest_selections)
+                .unwrap();
+        challenged.challenge().unwrap();
+        challenged.disclose_nonces().unwrap();
+        assert!(challenged.cast().is_err());
+        let uncast =
+            BallotEncrypted::new_from_selections(&device, &mut csprng, &[2; 32], &ctest_selections)
+                .unwrap();
+
+        let mut tally = tally_cast.clone();
+        for ballot in [&challenged, &uncast] {
+            let e = tally.add_ballot(fixed_parameters, ballot).unwrap_err();
+            assert!(e.to_string().contains("not counted"), "{e}");
+        }
+        assert_eq!(tally, tally_cast);
+
+        // They are left out of the tally, its partitions, and the verifier's sum.
+        ballots.push(challenged);
+        ballots.push(uncast);
+        let tally = Tally::from_ballots(fixed_parameters, manifest, &ballots).unwrap();
+        assert_eq!(tally, tally_cast);
+        let by_style = Tally::by_ballot_style(fixed_parameters, manifest, &ballots).unwrap();
+        assert_eq!(by_style.len(), 1);
+        assert_eq!(by_style[&None], tally_cast);
+        verify_tally(pvd, &ballots, &tally_cast).unwrap();
+    }
+
+    #[test]
+    fn test_ballot_style_omits_contest() {
+        use crate::{ballot_style::BallotStyle, verification::verify_ballot};
+
+        let mut csprng = Csprng::new(b"test_ballot_style_omits_contest");
+
+        let election_parameters = example_election_parameters();
+
+        // Two contests, with a second ballot style which omits the second one.
+        let contest_1 = ContestIndex::from_one_based_index(1).unwrap();
+        let contest_2 = ContestIndex::from_one_based_index(2).unwrap();
+        let mut election_manifest = example_election_manifest_first_contests(2);
+        // The second contest tracks blanks and undervotes, which it must not count for the
+        // ballots that omit it.
+        let contest_2_mut = election_manifest.contests.get_mut(contest_2).unwrap();
+        contest_2_mut.track_blank = true;
+        contest_2_mut.track_undervotes = true;
+        let contest_2_limit = contest_2_mut.selection_limit as u64;
+        election_manifest.ballot_styles = [
+            BallotStyle {
+                label: "Both".to_string(),
+                contests: BTreeSet::from([contest_1, contest_2]),
+            },
+            BallotStyle {
+                label: "First only".to_string(),
+                contests: BTreeSet::from([contest_1]),
+            },
+        ]
+        .try_into()
+        .unwrap();
+        election_manifest.validate().unwrap();
+
+        let ExampleElection {
+            guardian_key_shares,
+            pvd,
+            ..
+        } = ExampleElection::generate(&mut csprng, election_parameters, election_manifest).unwrap();
+
+        let device = Device::new("test", pvd);
+        let pvd = &device.header;
+        let manifest = &pvd.manifest;
+        let fixed_parameters = &pvd.parameters.fixed_parameters;
+
+        // Every ballot selects the first option of each contest on its style.
+        let selections = |contest_ixs: &[ContestIndex]| {
+            let mut ctest_selections = Vec1::new();
+            for contest_ix in manifest.contests.indices() {
+                let mut vote = vec![0; manifest.contests.get(contest_ix).unwrap().options.len()];
+                if contest_ixs.contains(&contest_ix) {
+                    vote[0] = 1;
+                }
+                ctest_selections
+                    .try_push(ContestSelection { vote })
+                    .unwrap();
+            }
+            ctest_selections
+        };
+        let style_both = BallotStyleIndex::from_one_based_index(1).unwrap();
+        let style_first = BallotStyleIndex::from_one_based_index(2).unwrap();
+
+        let mut ballots = Vec::new();
+        for (ballot_ix, ballot_style_ix) in [style_both, style_first, style_first]
+            .into_iter()
+            .enumerate()
+        {
+            let contest_ixs = if ballot_style_ix == style_both {
+                vec![contest_1, contest_2]
+            } else {
+                vec![contest_1]
+            };
+            let mut ballot = BallotEncrypted::new_from_selections_for_ballot_style(
+                &device,
+                &mut csprng,
+                &[ballot_ix as u8; 32],
+                ballot_style_ix,
+                &selections(&contest_ixs),
+                Vec::new(),
+            )
+            .unwrap();
+            ballot.cast().unwrap();
+            assert_eq!(ballot.ballot_style(), Some(ballot_style_ix));
+            verify_ballot(pvd, &ballot).unwrap();
+
+            let contest_encrypted_2 = ballot
+                .contests
+                .get(Index::from_one_based_index(2).unwrap())
+                .unwrap();
+            assert_eq!(
+                contest_encrypted_2.is_omitted(),
+                ballot_style_ix == style_first
+            );
+            assert_eq!(
+                contest_encrypted_2.opt_blank_indicator.is_some(),
+                ballot_style_ix == style_both
+            );
+            assert_eq!(
+                contest_encrypted_2.opt_undervote.is_some(),
+                ballot_style_ix == style_both
+            );
+            ballots.push(ballot);
+        }
+
+        // A selection in an omitted contest is rejected.
+        let e = BallotEncrypted::new_from_selections_for_ballot_style(
+            &device,
+            &mut csprng,
+            &[9u8; 32],
+            style_first,
+            &selections(&[contest_1, contest_2]),
+            Vec::new(),
+        )
+        .unwrap_err();
+        assert!(e.to_string().contains("not on ballot style 2"), "{e}");
+
+        // As is a ballot which claims a style omitting a contest it has really encrypted.
+        let mut relabeled = BallotEncrypted::new_from_selections(
+            &device,
+            &mut csprng,
+            &[10u8; 32],
+            &selections(&[contest_1]),
+        )
+        .unwrap();
+        verify_ballot(pvd, &relabeled).unwrap();
+        relabeled.set_ballot_style(style_first);
+        let e = verify_ballot(pvd, &relabeled).unwrap_err();
+        assert!(e.to_string().contains("not encrypted as omitted"), "{e}");
+
+        // Removing the style of a ballot, which omits nothing from it, is caught too, since the
+        // style is bound to the confirmation code.
+        let mut buf = Vec::new();
+        ballots[0].to_stdiowrite(&mut buf).unwrap();
+        let mut unlabeled = BallotEncrypted::from_stdioread(&mut buf.as_slice()).unwrap();
+        unlabeled.opt_ballot_style = None;
+        let e = verify_ballot(pvd, &unlabeled).unwrap_err();
+        assert!(e.to_string().contains("Confirmation code"), "{e}");
+
+        // The ballots of both styles are tallied together, and each contest counts only the
+        // ballots which included it.
+        let tally = Tally::from_ballots(fixed_parameters, manifest, &ballots).unwrap();
+        assert_eq!(tally.ballot_count, 3);
+
+        for (contest_ix, expected_first_option) in [(contest_1, 3), (contest_2, 1)] {
+            let decryption_shares = guardian_key_shares
+                .iter()
+                .map(|key_share| {
+                    ContestDecryptionShares::compute(
+                        &mut csprng,
+                        pvd,
+                        key_share,
+                        tally.contest(contest_ix).unwrap(),
+                    )
+                })
+                .collect::<Vec<_>>();
+            let result = tally
+                .decrypt_contest(pvd, contest_ix, &decryption_shares)
+                .unwrap()
+                .result;
+            assert_eq!(result.option_totals[0], expected_first_option);
+            assert!(result.option_totals[1..].iter().all(|&total| total == 0));
+
+            // Only the one ballot with the second contest counts towards its undervotes, and
+            // none of them left it blank.
+            if contest_ix == contest_2 {
+                assert_eq!(result.opt_blank_count, Some(0));
+                assert_eq!(result.opt_undervote_count, Some(contest_2_limit - 1));
+            }
+        }
+    }
+
+    #[test]
+    fn test_replay_rejected() {
+        let mut csprng = Csprng::new(b"test_replay_rejected");
+
+        let ExampleElection { pvd, .. } =
+            ExampleElection::with_first_contests(&mut csprng, 1).unwrap();
+
+        let device = Device::new("test", pvd);
+        let manifest = &device.header.manifest;
+        let fixed_parameters = &device.header.parameters.fixed_parameters;
+
+        // Two ballots with the same votes, but encrypted with different nonces.
+        let mut ballots = Vec::new();
+        for ballot_ix in 0..2 {
+            let ctest_selections: Vec1<ContestSelection> =
+                [ContestSelection { vote: vec![1, 0] }].try_into().unwrap();
+            let mut ballot = BallotEncrypted::new_from_selections(
+                &device,
+                &mut csprng,
+                &[ballot_ix as u8; 32],
+                &ctest_selections,
+            )
+            .unwrap();
+            ballot.cast().unwrap();
+            ballots.push(ballot);
+        }
+        Tally::from_ballots(fixed_parameters, manifest, &ballots).unwrap();
+
+        // The first ballot submitted again, with its confirmation code changed to disguise it.
+        let mut json = Vec::new();
+        ballots[0].to_stdiowrite(&mut json).unwrap();
+        let mut replayed = BallotEncrypted::from_stdioread(&mut json.as_slice()).unwrap();
+        replayed.confirmation_code = HValue::from([0x42; 32]);
+        let original_confirmation_code = ballots[0].confirmation_code;
+        ballots.push(replayed);
+
+        let err = Tally::from_ballots(fixed_parameters, manifest, &ballots)
+            .unwrap_err()
+            .to_string();
+        assert!(
+            err.contains(&original_confirmation_code.to_string()),
+            "{err}"
+        );
+        assert!(err.contains("replay"), "{err}");
+
+        // The guard applies across partitions too.
+        assert!(
+            Tally::partition_by(fixed_parameters, manifest, &ballots, |ballot| {
+                ballot.confirmation_code
+            })
+            .is_err()
+        );
+    }
+
+    #[test]
+    fn test_dlog_bounds_per_contest() {
+        let mut csprng = Csprng::new(b"test_dlog_bounds_per_contest");
+
+        let election_parameters = example_election_parameters();
+
+        // Two small contests with different selection limits.
+        let mut election_manifest = example_election_manifest_first_contests(2);
+        let contest_1 = ContestIndex::from_one_based_index(1).unwrap();
+        let contest_2 = ContestIndex::from_one_based_index(2).unwrap();
+        election_manifest
+            .contests
+            .get_mut(contest_1)
+            .unwrap()
+            .selection_limit = 1;
+        election_manifest
+            .contests
+            .get_mut(contest_2)
+            .unwrap()
+            .selection_limit = 2;
+
+        let example_election =
+            ExampleElection::generate(&mut csprng, election_parameters, election_manifest).unwrap();
+        let guardian_key_shares = &example_election.guardian_key_shares;
+        let pvd = &example_election.pvd;
+        let manifest = &pvd.manifest;
+        let fixed_parameters = &pvd.parameters.fixed_parameters;
+
+        let mut tally = Tally::new(manifest);
+        assert_eq!(tally.dlog_bound(contest_1), Some(0));
+        assert_eq!(tally.dlog_bound(contest_2), Some(0));
+
+        let ballots = example_election
+            .encrypt_ballots(
+                &mut csprng,
+                &[
+                    r#"{ "contests": [ { "vote": [1, 0] }, { "vote": [1, 1, 0, 0] } ] }"#,
+                    r#"{ "contests": [ { "vote": [0, 1] }, { "vote": [0, 1, 1, 0] } ] }"#,
+                    r#"{ "contests": [ { "vote": [1, 0] }, { "vote": [0, 0, 0, 0] } ] }"#,
+                ],
+            )
+            .unwrap();
+        for ballot in &ballots {
+            tally.add_ballot(fixed_parameters, ballot).unwrap();
+        }
+        let expected_totals: [&[u64]; 2] = [&[2, 1], &[1, 2, 1, 0]];
+
+        // Whatever the selection limit, no option can have more votes than there are ballots,
+        // but each ballot can undervote contest 2 twice.
+        assert_eq!(tally.dlog_bound(contest_1), Some(3));
+        assert_eq!(tally.dlog_bound(contest_2), Some(3));
+        let contest_tally_2 = tally.contest(contest_2).unwrap();
+        assert_eq!(contest_tally_2.undervote_dlog_bound(tally.ballot_count), 6);
+        assert_eq!(
+            tally.dlog_bound(ContestIndex::from_one_based_index(3).unwrap()),
+            None
+        );
+
+        // Every option's total decrypts within its contest's bound.
+        for (contest_ix, expected_totals) in tally.contests.indices().zip(expected_totals.iter()) {
+            let bound = tally
+                .dlog_bound(
+                    ContestIndex::from_one_based_index(contest_ix.get_one_based_u32()).unwrap(),
+                )
+                .unwrap();
+            let contest_tally = tally.contests.get(contest_ix).unwrap();
+
+            for (ciphertext, &expected_total) in
+                contest_tally.selection.iter().zip(expected_totals.iter())
+            {
+                let shares = guardian_key_shares
+                    .iter()
+                    .map(|key_share| {
+                        DecryptionShare::compute(&mut csprng, pvd, key_share, ciphertext)
+                    })
+                    .collect::<Vec<_>>();
+                let t = combine_decryption_shares(pvd, ciphertext, &shares).unwrap();
+
+                assert_eq!(t.discrete_log(pvd, bound), Some(expected_total));
+            }
+        }
+    }
+
+    #[test]
+    fn test_blank_tracking() {
+        use crate::verification::verify_ballot;
+
+        let mut csprng = Csprng::new(b"test_blank_tracking");
+
+        let election_parameters = example_election_parameters();
+
+        // Track blanks in the first of two contests.
+        let mut election_manifest = example_election_manifest_first_contests(2);
+        let contest_1 = ContestIndex::from_one_based_index(1).unwrap();
+        let contest = election_manifest.contests.get_mut(contest_1).unwrap();
+        contest.track_blank = true;
+        contest.selection_limit = 2;
+        assert_eq!(contest.options.len(), 2);
+
+        let ExampleElection {
+            guardian_key_shares,
+            pvd,
+            ..
+        } = ExampleElection::generate(&mut csprng, election_parameters, election_manifest).unwrap();
+
+        let device = Device::new("test", pvd);
+        let pvd = &device.header;
+        let manifest = &pvd.manifest;
+        let fixed_parameters = &pvd.parameters.fixed_parameters;
+        let contest_2 = manifest
+            .contests
+            .get(ContestIndex::from_one_based_index(2).unwrap())
+            .unwrap();
+
+        let votes_1: [Vec<u8>; 4] = [vec![0, 0], vec![1, 1], vec![0, 0], vec![1, 0]];
+        let ballots = votes_1
+            .iter()
+            .enumerate()
+            .map(|(ballot_ix, vote)| {
+                let mut ctest_selections = Vec1::new();
+                ctest_selections
+                    .try_push(ContestSelection { vote: vote.clone() })
+                    .unwrap();
+                ctest_selections
+                    .try_push(ContestSelection::new_pick_random(
+                        &mut csprng,
+                        contest_2.selection_limit,
+                        contest_2.options.len(),
+                    ))
+                    .unwrap();
+
+                let primary_nonce = [ballot_ix as u8; 32];
+                let mut ballot = BallotEncrypted::new_from_selections(
+                    &device,
+                    &mut csprng,
+                    &primary_nonce,
+                    &ctest_selections,
+                )
+                .unwrap();
+                ballot.cast().unwrap();
+                ballot
+            })
+            .collect::<Vec<_>>();
+
+        for ballot in &ballots {
+            let contests = &ballot.contests;
+            assert!(contests
+                .get(Index::from_one_based_index(1).unwrap())
+                .unwrap()
+                .opt_blank_indicator
+                .is_some());
+            assert!(contests
+                .get(Index::from_one_based_index(2).unwrap())
+                .unwrap()
+                .opt_blank_indicator
+                .is_none());
+            verify_ballot(pvd, ballot).unwrap();
+        }
+
+        // The blank indicator survives the packed encoding.
+        let packed = ballots[0].to_packed_bytes(fixed_parameters).unwrap();
+        let unpacked = BallotEncrypted::from_packed_bytes(&packed, fixed_parameters).unwrap();
+        verify_ballot(pvd, &unpacked).unwrap();
+
+        // The indicator of a non-blank contest does not verify for a blank one.
+        let contest_encrypted = |ballot_ix: usize| {
+            ballots[ballot_ix]
+                .contests
+                .get(Index::from_one_based_index(1).unwrap())
+                .unwrap()
+        };
+        let contest = manifest.contests.get(contest_1).unwrap();
+        let blank_indicator_nonblank = contest_encrypted(1).opt_blank_indicator.as_ref().unwrap();
+        assert!(blank_indicator_nonblank.verify(pvd, contest, &contest_encrypted(1).selection));
+        assert!(!blank_indicator_nonblank.verify(pvd, contest, &contest_encrypted(0).selection));
+
+        let tally = Tally::from_ballots(fixed_parameters, manifest, &ballots).unwrap();
+        assert!(tally
+            .contests
+            .get(Index::from_one_based_index(2).unwrap())
+            .unwrap()
+            .opt_blank
+            .is_none());
+
+        let blank_ciphertext = tally
+            .contests
+            .get(Index::from_one_based_index(1).unwrap())
+            .unwrap()
+            .opt_blank
+            .as_ref()
+            .unwrap();
+        let shares = guardian_key_shares
+            .iter()
+            .map(|key_share| {
+                DecryptionShare::compute(&mut csprng, pvd, key_share, blank_ciphertext)
+            })
+            .collect::<Vec<_>>();
+        let t = combine_decryption_shares(pvd, blank_ciphertext, &shares).unwrap();
+        let blank_count = t.discrete_log(pvd, tally.ballot_count as u64);
+        assert_eq!(blank_count, Some(2));
+
+        // A tally without blank tracking can't be combined with this one.
+        let mut other_manifest = manifest.clone();
+        other_manifest
+            .contests
+            .get_mut(contest_1)
+            .unwrap()
+            .track_blank = false;
+        let mut other_tally = Tally::new(&other_manifest);
+        assert!(other_tally.add_tally(fixed_parameters, &tally).is_err());
+        assert!(other_tally
+            .add_ballot(fixed_parameters, &ballots[0])
+            .is_err());
+    }
+
+    #[test]
+    fn test_undervote_tracking() {
+        use crate::verification::verify_ballot;
+
+        let mut csprng = Csprng::new(b"test_undervote_tracking");
+
+        let election_parameters = example_election_parameters();
+
+        // Just the first contest, as a "vote for 2" of 2 options, tracking undervotes.
+        let mut election_manifest = example_election_manifest_first_contests(1);
+        let contest_1 = ContestIndex::from_one_based_index(1).unwrap();
+        let contest = election_manifest.contests.get_mut(contest_1).unwrap();
+        contest.track_undervotes = true;
+        contest.selection_limit = 2;
+        assert_eq!(contest.options.len(), 2);
+
+        let ExampleElection {
+            guardian_key_shares,
+            guardian_public_keys,
+            pvd,
+            ..
+        } = ExampleElection::generate(&mut csprng, election_parameters, election_manifest).unwrap();
+
+        let device = Device::new("test", pvd);
+        let pvd = &device.header;
+        let manifest = &pvd.manifest;
+        let fixed_parameters = &pvd.parameters.fixed_parameters;
+
+        // Undervotes of 2, 0, 1, and 2.
+        let votes: [Vec<u8>; 4] = [vec![0, 0], vec![1, 1], vec![0, 1], vec![0, 0]];
+        let ballots = votes
+            .iter()
+            .enumerate()
+            .map(|(ballot_ix, vote)| {
+                let mut ctest_selections = Vec1::new();
+                ctest_selections
+                    .try_push(ContestSelection { vote: vote.clone() })
+                    .unwrap();
+                let primary_nonce = [ballot_ix as u8; 32];
+                let mut ballot = BallotEncrypted::new_from_selections(
+                    &device,
+                    &mut csprng,
+                    &primary_nonce,
+                    &ctest_selections,
+                )
+                .unwrap();
+                ballot.cast().unwrap();
+                ballot
+            })
+            .collect::<Vec<_>>();
+
+        for ballot in &ballots {
+            verify_ballot(pvd, ballot).unwrap();
+        }
+
+        // The undervote count survives the packed encoding.
+        let packed = ballots[0].to_packed_bytes(fixed_parameters).unwrap();
+        let unpacked = BallotEncrypted::from_packed_bytes(&packed, fixed_parameters).unwrap();
+        verify_ballot(pvd, &unpacked).unwrap();
+
+        // The undervote count of one ballot does not verify on another, even though its range
+        // proof is valid.
+        let copy_of = |ballot: &BallotEncrypted| {
+            let packed = ballot.to_packed_bytes(fixed_parameters).unwrap();
+            BallotEncrypted::from_packed_bytes(&packed, fixed_parameters).unwrap()
+        };
+        let mut swapped = copy_of(&ballots[1]);
+        let contest_encrypted = swapped
+            .contests
+            .get_mut(Index::from_one_based_index(1).unwrap());
+        contest_encrypted.unwrap().opt_undervote = ballots[0]
+            .contests
+            .get(Index::from_one_based_index(1).unwrap())
+            .unwrap()
+            .opt_undervote
+            .clone();
+        let e = verify_ballot(pvd, &swapped).unwrap_err();
+        assert!(e.to_string().contains("undervote count is invalid"), "{e}");
+
+        let mut missing = copy_of(&ballots[1]);
+        let contest_encrypted = missing
+            .contests
+            .get_mut(Index::from_one_based_index(1).unwrap());
+        contest_encrypted.unwrap().opt_undervote = None;
+        assert!(verify_ballot(pvd, &missing).is_err());
+
+        let tally = Tally::from_ballots(fixed_parameters, manifest, &ballots).unwrap();
+
+        let decryption_shares = guardian_key_shares
+            .iter()
+            .map(|key_share| {
+                ContestDecryptionShares::compute(
+                    &mut csprng,
+                    pvd,
+                    key_share,
+                    tally.contest(contest_1).unwrap(),
+                )
+            })
+            .collect::<Vec<_>>();
+        let decrypted_contest = tally
+            .decrypt_contest(pvd, contest_1, &decryption_shares)
+            .unwrap();
+        assert_eq!(decrypted_contest.result.option_totals, vec![1, 2]);
+        assert_eq!(decrypted_contest.result.opt_undervote_count, Some(5));
+        assert_eq!(decrypted_contest.result.opt_blank_count, None);
+
+        let decrypted_contests = [decrypted_contest];
+        verify_decrypted_contests(pvd, &guardian_public_keys, &tally, &decrypted_contests).unwrap();
+
+        let mut wrong = decrypted_contests.clone();
+        wrong[0].result.opt_undervote_count = Some(4);
+        let e = verify_decrypted_contests(pvd, &guardian_public_keys, &tally, &wrong).unwrap_err();
+        assert!(format!("{e:#}").contains("Undervote count"), "{e:#}");
+
+        // A tally without undervote tracking can't be combined with this one.
+        let mut other_manifest = manifest.clone();
+        other_manifest
+            .contests
+            .get_mut(contest_1)
+            .unwrap()
+            .track_undervotes = false;
+        let mut other_tally = Tally::new(&other_manifest);
+        assert!(other_tally.add_tally(fixed_parameters, &tally).is_err());
+        assert!(other_tally
+            .add_ballot(fixed_parameters, &ballots[0])
+            .is_err());
+    }
+
+    #[test]
+    fn test_summary() {
+        let election_manifest = example_election_manifest();
+
+        let mut contests = Vec1::new();
+        for contest_ix in election_manifest.contests.indices() {
+            let contest = election_manifest.contests.get(contest_ix).unwrap();
+            let mut option_totals = vec![0; contest.options.len()];
+            option_totals[0] = 3;
+            option_totals[1] = 5;
+            contests
+                .try_push(ContestResult {
+                    option_totals,
+                    opt_blank_count: None,
+                    opt_undervote_count: None,
+                })
+                .unwrap();
+        }
+        let decrypted_tally = DecryptedTally {
+            contests,
+            ballot_count: 8,
+        };
+
+        let summary = decrypted_tally.summary(&election_manifest).unwrap();
+        assert!(summary.contains("Ballots: 8"));
+        assert!(!summary.contains("(blank)"));
+
+        let mut blank_tally = decrypted_tally.clone();
+        blank_tally
+            .contests
+            .get_mut(Index::from_one_based_index(1).unwrap())
+            .unwrap()
+            .opt_blank_count = Some(4);
+        let blank_summary = blank_tally.summary(&election_manifest).unwrap();
+        assert!(blank_summary.contains("       4  (blank)\n"));
+        assert!(summary.contains("Sébastian Moonglôw to be retained\n"));
+        assert!(summary.contains("    REMOVED\n"));
+        assert!(summary.contains("    FAILED\n"));
+        assert!(!summary.contains("PASSED"));
+        assert!(!summary.contains("RETAINED"));
+
+        let mut truncated_tally = decrypted_tally.clone();
+        truncated_tally.contests.truncate(1);
+        assert!(truncated_tally.summary(&election_manifest).is_err());
+    }
+
+    #[test]
+    fn test_count_for() {
+        let election_manifest = example_election_manifest();
+        let contest_label = "Minister of Arcane Sciences";
+        let option_label = "Seraphína Stormbinder\n(Independent)";
+
+        let mut tally = Tally::new(&election_manifest);
+        let marked = Ciphertext {
+            alpha: BigUint::from(2u8),
+            beta: BigUint::from(3u8),
+            nonce: None,
+        };
+        tally
+            .contests
+            .get_mut(Index::from_one_based_index(2).unwrap())
+            .unwrap()
+            .selection[2] = marked.clone();
+
+        let ciphertext = tally
+            .encrypted_count_for(&election_manifest, contest_label, option_label)
+            .unwrap();
+        assert_eq!(ciphertext.alpha, marked.alpha);
+        assert_eq!(ciphertext.beta, marked.beta);
+
+        let mut contests = Vec1::new();
+        for contest_ix in election_manifest.contests.indices() {
+            let contest = election_manifest.contests.get(contest_ix).unwrap();
+            contests
+                .try_push(ContestResult {
+                    option_totals: (0..contest.options.len() as u64).collect(),
+                    opt_blank_count: None,
+                    opt_undervote_count: None,
+                })
+                .unwrap();
+        }
+        let decrypted_tally = DecryptedTally {
+            contests,
+            ballot_count: 8,
+        };
+        assert_eq!(
+            decrypted_tally
+                .count_for(&election_manifest, contest_label, option_label)
+                .unwrap(),
+            2
+        );
+
+        assert!(tally
+            .encrypted_count_for(&election_manifest, "No such contest", option_label)
+            .is_err());
+        assert!(decrypted_tally
+            .count_for(&election_manifest, contest_label, "No such option")
+            .is_err());
+
+        // A tally which doesn't match the manifest.
+        let mut truncated_tally = decrypted_tally.clone();
+        truncated_tally.contests.truncate(1);
+        assert!(truncated_tally
+            .count_for(&election_manifest, contest_label, option_label)
+            .is_err());
+    }
+
+    #[test]
+    fn test_decrypt_one_contest() {
+        let mut csprng = Csprng::new(b"test_decrypt_one_contest");
+
+        let example_election = ExampleElection::with_first_contests(&mut csprng, 2).unwrap();
+        let ExampleElection {
+            guardian_key_shares,
+            guardian_public_keys,
+            pvd,
+            ..
+        } = &example_election;
+
+        let contest_2 = ContestIndex::from_one_based_index(2).unwrap();
+        let expected_totals = vec![1, 0, 2, 0];
+
+        let ballots = example_election
+            .encrypt_ballots(
+                &mut csprng,
+                &[
+                    r#"{ "contests": [ { "vote": [1, 0] }, { "vote": [0, 0, 1, 0] } ] }"#,
+                    r#"{ "contests": [ { "vote": [0, 1] }, { "vote": [0, 0, 1, 0] } ] }"#,
+                    r#"{ "contests": [ { "vote": [1, 0] }, { "vote": [1, 0, 0, 0] } ] }"#,
+                ],
+            )
+            .unwrap();
+        let tally =
+            Tally::from_ballots(&pvd.parameters.fixed_parameters, &pvd.manifest, &ballots).unwrap();
+
+        // Only contest 2 is decrypted.
+        let contest_tally = tally.contest(contest_2).unwrap();
+        let decryption_shares = guardian_key_shares
+            .iter()
+            .map(|key_share| {
+                ContestDecryptionShares::compute(&mut csprng, pvd, key_share, contest_tally)
+            })
+            .collect::<Vec<_>>();
+        let decrypted_contest = tally
+            .decrypt_contest(pvd, contest_2, &decryption_shares)
+            .unwrap();
+        assert_eq!(decrypted_contest.result.option_totals, expected_totals);
+
+        verify_decrypted_contests(
+            pvd,
+            guardian_public_keys,
+            &tally,
+            std::slice::from_ref(&decrypted_contest),
+        )
+        .unwrap();
+
+        // The shares of any k=3 guardians are enough, but no fewer.
+        assert_eq!(
+            tally
+                .decrypt_contest(pvd, contest_2, &decryption_shares[2..])
+                .unwrap()
+                .result,
+            decrypted_contest.result
+        );
+        tally
+            .decrypt_contest(pvd, contest_2, &decryption_shares[3..])
+            .unwrap_err();
+
+        // Publishing a different total is caught.
+        let mut altered = decrypted_contest.clone();
+        altered.result.option_totals[0] += 1;
+        verify_decrypted_contests(pvd, guardian_public_keys, &tally, &[altered]).unwrap_err();
+
+        // So is publishing the same contest twice.
+        verify_decrypted_contests(
+            pvd,
+            guardian_public_keys,
+            &tally,
+            &[decrypted_contest.clone(), decrypted_contest],
+        )
+        .unwrap_err();
+    }
+
+    #[test]
+    fn test_decryption_share_set_io() {
+        let mut csprng = Csprng::new(b"test_decryption_share_set_io");
+
+        let ExampleElection {
+            guardian_key_shares,
+            pvd,
+            ..
+        } = ExampleElection::with_first_contests(&mut csprng, 1).unwrap();
+
+        let device = Device::new("test", pvd);
+        let pvd = &device.header;
+        let manifest = &pvd.manifest;
+
+        let mut tally = Tally::new(manifest);
+        let contest = manifest
+            .contests
+            .get(Index::from_one_based_index(1).unwrap())
+            .unwrap();
+        let contest_selection = ContestSelection::new_pick_random(
+            &mut csprng,
+            contest.selection_limit,
+            contest.options.len(),
+        );
+        let expected_totals = contest_selection
+            .vote
+            .iter()
+            .map(|&vote| vote as u64)
+            .collect::<Vec<_>>();
+        let mut ctest_selections = Vec1::new();
+        ctest_selections.try_push(contest_selection).unwrap();
+        let mut ballot =
+            BallotEncrypted::new_from_selections(&device, &mut csprng, &[0; 32], &ctest_selections)
+                .unwrap();
+        ballot.cast().unwrap();
+        tally
+            .add_ballot(&pvd.parameters.fixed_parameters, &ballot)
+            .unwrap();
+
+        // Each guardian writes its shares, and the coordinator reads and decrypts with them.
+        let share_sets = guardian_key_shares
+            .iter()
+            .map(|key_share| {
+                let share_set = DecryptionShareSet::compute(&mut csprng, pvd, key_share, &tally);
+                let mut bytes = Vec::new();
+                share_set.to_stdiowrite(&mut bytes).unwrap();
+                assert!(bytes.ends_with(b"}\n"));
+
+                let share_set_2 = DecryptionShareSet::from_stdioread_validated(
+                    &mut bytes.as_slice(),
+                    pvd,
+                    key_share.public_key_share(),
+                    &tally,
+                )
+                .unwrap();
+                assert_eq!(share_set_2, share_set);
+                share_set_2
+            })
+            .collect::<Vec<_>>();
+
+        // A share set with a bogus field.
+        let mut bytes = Vec::new();
+        share_sets[0].to_stdiowrite(&mut bytes).unwrap();
+        let mut value: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+        value
+            .as_object_mut()
+            .unwrap()
+            .insert("bogus".into(), 1.into());
+        let bytes = serde_json::to_vec(&value).unwrap();
+        let result = DecryptionShareSet::from_stdioread(&mut bytes.as_slice());
+        if cfg!(feature = "strict-parsing") {
+            let err = format!("{:#}", result.unwrap_err());
+            assert!(err.contains("unknown field `bogus`"), "{err}");
+        } else {
+            assert_eq!(result.unwrap(), share_sets[0]);
+        }
+
+        let contest_shares = share_sets
+            .iter()
+            .map(|share_set| share_set.contests[0].clone())
+            .collect::<Vec<_>>();
+        let contest_1 = ContestIndex::from_one_based_index(1).unwrap();
+        let decrypted_contest = tally
+            .decrypt_contest(pvd, contest_1, &contest_shares)
+            .unwrap();
+        assert_eq!(decrypted_contest.result.option_totals, expected_totals);
+
+        let decrypted_tally = tally.decrypt(pvd, &share_sets).unwrap();
+        assert_eq!(decrypted_tally.ballot_count, 1);
+        assert_eq!(decrypted_tally.contests.len(), 1);
+        assert_eq!(
+            decrypted_tally
+                .contests
+                .get(Index::from_one_based_index(1).unwrap()),
+            Some(&decrypted_contest.result)
+        );
+        let mut json = Vec::new();
+        decrypted_tally.to_stdiowrite(&mut json).unwrap();
+        assert_eq!(
+            DecryptedTally::from_stdioread(&mut json.as_slice()).unwrap(),
+            decrypted_tally
+        );
+
+        // Shares are rejected when loaded with another guardian's key.
+        let err = share_sets[0]
+            .validate(pvd, guardian_key_shares[1].public_key_share(), &tally)
+            .unwrap_err();
+        assert!(
+            err.to_string().contains("public key share of guardian"),
+            "{err}"
+        );
+
+        // As is a share with an invalid proof.
+        let mut tampered = share_sets[0].clone();
+        tampered.contests[0].selection[0].m_i += 1u8;
+        let err = tampered
+            .validate(pvd, guardian_key_shares[0].public_key_share(), &tally)
+            .unwrap_err();
+        assert!(format!("{err:#}").contains("does not verify"), "{err:#}");
+
+        // And a set missing a contest's shares.
+        let mut short = share_sets[0].clone();
+        short.contests[0].selection.pop();
+        short
+            .validate(pvd, guardian_key_shares[0].public_key_share(), &tally)
+            .unwrap_err();
+    }
+
+    /// Decrypts the option totals of every contest of `tally` with only the guardians in
+    /// `present`.
+    fn decrypt_with_present(
+        csprng: &mut Csprng,
+        pvd: &PreVotingData,
+        tally: &Tally,
+        guardian_key_shares: &[GuardianKeyShare],
+        present: &[GuardianIndex],
+    ) -> Result<Vec<Vec<u64>>> {
+        let share_sets = present
+            .iter()
+            .map(|&i| {
+                let key_share = guardian_key_shares
+                    .iter()
+                    .find(|key_share| key_share.i == i)
+                    .with_context(|| format!("No key share for guardian {i}"))?;
+                Ok(DecryptionShareSet::compute(csprng, pvd, key_share, tally))
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        let decrypted_tally = tally.decrypt(pvd, &share_sets)?;
+        Ok(decrypted_tally
+            .contests
+            .indices()
+            .filter_map(|contest_ix| decrypted_tally.contests.get(contest_ix))
+            .map(|contest_result| contest_result.option_totals.clone())
+            .collect())
+    }
+
+    #[test]
+    fn test_decrypt_with_absent_guardians() {
+        let mut csprng = Csprng::new(b"test_decrypt_with_absent_guardians");
+
+        let example_election = ExampleElection::with_first_contests(&mut csprng, 1).unwrap();
+        let guardian_key_shares = &example_election.guardian_key_shares;
+        let pvd = &example_election.pvd;
+        let varying_parameters = &pvd.parameters.varying_parameters;
+        assert_eq!(varying_parameters.n.as_quantity(), 5);
+        assert_eq!(varying_parameters.k.as_quantity(), 3);
+
+        let ballots = example_election
+            .encrypt_ballots(
+                &mut csprng,
+                &[
+                    r#"{ "contests": [ { "vote": [1, 0] } ] }"#,
+                    r#"{ "contests": [ { "vote": [0, 1] } ] }"#,
+                    r#"{ "contests": [ { "vote": [0, 1] } ] }"#,
+                ],
+            )
+            .unwrap();
+        let tally =
+            Tally::from_ballots(&pvd.parameters.fixed_parameters, &pvd.manifest, &ballots).unwrap();
+
+        // The decryption with every guardian present.
+        let contest_1 = ContestIndex::from_one_based_index(1).unwrap();
+        let decryption_shares = guardian_key_shares
+            .iter()
+            .map(|key_share| {
+                ContestDecryptionShares::compute(
+                    &mut csprng,
+                    pvd,
+                    key_share,
+                    tally.contest(contest_1).unwrap(),
+                )
+            })
+            .collect::<Vec<_>>();
+        let expected = vec![
+            tally
+                .decrypt_contest(pvd, contest_1, &decryption_shares)
+                .unwrap()
+                .result
+                .option_totals,
+        ];
+
+        let guardians = |ixs: &[u32]| {
+            ixs.iter()
+                .map(|&ix| GuardianIndex::from_one_based_index(ix).unwrap())
+                .collect::<Vec<_>>()
+        };
+        for present in [
+            guardians(&[1, 2, 3, 4, 5]),
+            guardians(&[1, 3, 5]),
+            guardians(&[3, 4, 5]),
+            guardians(&[1, 2, 4, 5]),
+            guardians(&[5, 2, 3, 1]),
+        ] {
+            let results =
+                decrypt_with_present(&mut csprng, pvd, &tally, guardian_key_shares, &present)
+                    .unwrap();
+            assert_eq!(results, expected, "present: {present:?}");
+        }
+
+        // Fewer than k guardians can't decrypt.
+        let present = guardians(&[2, 4]);
+        let err = decrypt_with_present(&mut csprng, pvd, &tally, guardian_key_shares, &present)
+            .unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("requires the decryption shares of at least k=3 guardians, found 2"),
+            "{err:#}"
+        );
+
+        // Nor can they by giving their shares twice.
+        let present = guardians(&[2, 4, 2]);
+        let err = decrypt_with_present(&mut csprng, pvd, &tally, guardian_key_shares, &present)
+            .unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("More than one set of decryption shares from guardian 2"),
+            "{err:#}"
+        );
+    }
+
+    #[test]
+    fn test_tally_round_trip() {
+        let election_parameters = example_election_parameters();
+        let fixed_parameters = &election_parameters.fixed_parameters;
+        let mut election_manifest = example_election_manifest();
+        let tally = Tally::new(&election_manifest);
+
+        let mut json = Vec::new();
+        tally.to_stdiowrite(&mut json).unwrap();
+        let tally_2 = Tally::from_stdioread_validated(
+            &mut json.as_slice(),
+            fixed_parameters,
+            &election_manifest,
+        )
+        .unwrap();
+        assert_eq!(tally_2, tally);
+
+        // A ciphertext which isn't reduced mod p.
+        let mut non_reduced = tally.clone();
+        non_reduced
+            .contests
+            .get_mut(Index::from_one_based_index(1).unwrap())
+            .unwrap()
+            .selection[0]
+            .beta += fixed_parameters.p.as_ref();
+        let err = non_reduced
+            .validate(fixed_parameters, &election_manifest)
+            .unwrap_err();
+        assert!(
+            format!("{err:#}").contains(
+                "Tally of contest 1: Ciphertext is not a pair of valid nonzero mod p values"
+            ),
+            "{err:#}"
+        );
+        let mut json_non_reduced = Vec::new();
+        non_reduced.to_stdiowrite(&mut json_non_reduced).unwrap();
+        assert!(Tally::from_stdioread(&mut json_non_reduced.as_slice()).is_ok());
+        assert!(Tally::from_stdioread_validated(
+            &mut json_non_reduced.as_slice(),
+            fixed_parameters,
+            &election_manifest
+        )
+        .is_err());
+
+        // A tally of a different election.
+        election_manifest.contests.truncate(1);
+        assert!(Tally::from_stdioread_validated(
+            &mut json.as_slice(),
+            fixed_parameters,
+            &election_manifest
+        )
+        .is_err());
+        assert!(tally
+            .validate(fixed_parameters, &election_manifest)
+            .is_err());
+    }
+}
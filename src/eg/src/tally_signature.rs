@@ -0,0 +1,251 @@
+// Copyright (C) Microsoft Corporation. All rights reserved.
+
+#![deny(clippy::unwrap_used)]
+#![deny(clippy::expect_used)]
+#![deny(clippy::panic)]
+#![deny(clippy::manual_assert)]
+
+//! Signatures by an election authority certifying the decrypted results.
+//!
+//! These don't add to the verifiability of the tally, which comes from the decryption proofs.
+//! They record which authority vouched for the published results of which election.
+
+use anyhow::{ensure, Context, Result};
+use ed25519_dalek::{Signature, Signer, SigningKey, VerifyingKey};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::{hashes_ext::HashesExt, tally::DecryptedTally};
+
+/// Domain separation prefix for the signed message.
+const TALLY_SIGNATURE_DOMAIN: &[u8] = b"ElectionGuard decrypted tally signature\0";
+
+/// An Ed25519 signature over a [`DecryptedTally`] and the extended base hash `H_E` of its
+/// election.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TallySignature(pub Signature);
+
+impl TallySignature {
+    /// Reads a `TallySignature` from a `std::io::Read`.
+    pub fn from_stdioread(stdioread: &mut dyn std::io::Read) -> Result<Self> {
+        serde_json::from_reader(stdioread).context("Reading TallySignature")
+    }
+
+    /// Writes a `TallySignature` to a `std::io::Write`.
+    pub fn to_stdiowrite(&self, stdiowrite: &mut dyn std::io::Write) -> Result<()> {
+        let mut ser = serde_json::Serializer::pretty(stdiowrite);
+
+        self.serialize(&mut ser)
+            .map_err(Into::<anyhow::Error>::into)
+            .and_then(|_| ser.into_inner().write_all(b"\n").map_err(Into::into))
+            .context("Writing TallySignature")
+    }
+}
+
+impl std::str::FromStr for TallySignature {
+    type Err = anyhow::Error;
+
+    /// Parses the 128 hex digits written by `Display`.
+    fn from_str(s: &str) -> Result<Self> {
+        ensure!(
+            s.len() == 2 * Signature::BYTE_SIZE,
+            "Tally signature should be {} hex digits, found {}",
+            2 * Signature::BYTE_SIZE,
+            s.len()
+        );
+
+        let mut bytes = [0u8; Signature::BYTE_SIZE];
+        for (ix, b) in bytes.iter_mut().enumerate() {
+            let digits = s
+                .get(2 * ix..2 * ix + 2)
+                .context("Tally signature is not ASCII")?;
+            *b = u8::from_str_radix(digits, 16)
+                .with_context(|| format!("Tally signature has a bad hex digit pair: {digits:?}"))?;
+        }
+
+        Ok(TallySignature(Signature::from_bytes(&bytes)))
+    }
+}
+
+impl std::fmt::Display for TallySignature {
+    /// Writes the signature bytes as uppercase hex.
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for b in self.0.to_bytes() {
+            write!(f, "{b:02X}")?;
+        }
+        Ok(())
+    }
+}
+
+impl Serialize for TallySignature {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        self.to_string().serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for TallySignature {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        use serde::de::Error;
+
+        let s = String::deserialize(deserializer)?;
+
+        s.parse().map_err(D::Error::custom)
+    }
+}
+
+/// Returns the message which is signed: the domain prefix, `H_E`, and the canonical bytes of
+/// the decrypted tally.
+fn tally_signature_message(
+    hashes_ext: &HashesExt,
+    decrypted_tally: &DecryptedTally,
+) -> Result<Vec<u8>> {
+    let mut v = TALLY_SIGNATURE_DOMAIN.to_vec();
+    v.extend_from_slice(hashes_ext.h_e.as_ref());
+    v.append(&mut decrypted_tally.to_canonical_bytes()?);
+    Ok(v)
+}
+
+/// Signs the decrypted tally of the election with extended base hash `H_E`.
+pub fn sign_tally(
+    hashes_ext: &HashesExt,
+    decrypted_tally: &DecryptedTally,
+    signing_key: &SigningKey,
+) -> Result<TallySignature> {
+    let message = tally_signature_message(hashes_ext, decrypted_tally)?;
+    Ok(TallySignature(signing_key.sign(&message)))
+}
+
+/// Verifies a signature made by [`sign_tally`].
+///
+/// Fails if the results, the election, or the signer differ from those that were signed.
+/// The verification is strict, so weak verifying keys and signatures which are not
+/// canonical are rejected too.
+pub fn verify_tally_signature(
+    hashes_ext: &HashesExt,
+    decrypted_tally: &DecryptedTally,
+    verifying_key: &VerifyingKey,
+    tally_signature: &TallySignature,
+) -> Result<()> {
+    let message = tally_signature_message(hashes_ext, decrypted_tally)?;
+    verifying_key
+        .verify_strict(&message, &tally_signature.0)
+        .context("Decrypted tally signature does not verify")
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod test {
+    use super::*;
+    use crate::{hash::HValue, tally::ContestResult, vec1::Vec1};
+    use ed25519_dalek::Verifier;
+    use util::csprng::Csprng;
+
+    fn generate_signing_key(csprng: &mut Csprng) -> SigningKey {
+        let mut secret_key = [0u8; 32];
+        for b in secret_key.iter_mut() {
+            *b = csprng.next_u8();
+        }
+        SigningKey::from_bytes(&secret_key)
+    }
+
+    #[test]
+    fn test_tally_signature() {
+        let mut csprng = Csprng::new(b"test_tally_signature");
+
+        let hashes_ext = HashesExt {
+            h_e: HValue::from([0x11; 32]),
+        };
+
+        let mut contests = Vec1::new();
+        for option_totals in [vec![3, 1, 0], vec![2, 2]] {
+            contests
+                .try_push(ContestResult {
+                    option_totals,
+                    opt_blank_count: None,
+                    opt_undervote_count: None,
+                })
+                .unwrap();
+        }
+        let decrypted_tally = DecryptedTally {
+            contests,
+            ballot_count: 4,
+        };
+
+        let signing_key = generate_signing_key(&mut csprng);
+        let verifying_key = signing_key.verifying_key();
+
+        let signature = sign_tally(&hashes_ext, &decrypted_tally, &signing_key).unwrap();
+        verify_tally_signature(&hashes_ext, &decrypted_tally, &verifying_key, &signature).unwrap();
+
+        // Round trip.
+        let mut json = Vec::new();
+        signature.to_stdiowrite(&mut json).unwrap();
+        let signature_2 = TallySignature::from_stdioread(&mut json.as_slice()).unwrap();
+        assert_eq!(signature, signature_2);
+        verify_tally_signature(&hashes_ext, &decrypted_tally, &verifying_key, &signature_2)
+            .unwrap();
+
+        assert!(TallySignature::from_stdioread(&mut &b"\"00\""[..]).is_err());
+        let not_hex = format!("\"{}\"", "G".repeat(128));
+        assert!(TallySignature::from_stdioread(&mut not_hex.as_bytes()).is_err());
+
+        // Changed results.
+        let mut tampered_tally = decrypted_tally.clone();
+        let contest_ix = tampered_tally.contests.indices().next().unwrap();
+        tampered_tally
+            .contests
+            .get_mut(contest_ix)
+            .unwrap()
+            .option_totals[0] += 1;
+        assert!(
+            verify_tally_signature(&hashes_ext, &tampered_tally, &verifying_key, &signature)
+                .is_err()
+        );
+
+        // A different election.
+        let other_hashes_ext = HashesExt {
+            h_e: HValue::from([0x22; 32]),
+        };
+        assert!(verify_tally_signature(
+            &other_hashes_ext,
+            &decrypted_tally,
+            &verifying_key,
+            &signature
+        )
+        .is_err());
+
+        // A different signer.
+        let other_verifying_key = generate_signing_key(&mut csprng).verifying_key();
+        assert!(verify_tally_signature(
+            &hashes_ext,
+            &decrypted_tally,
+            &other_verifying_key,
+            &signature
+        )
+        .is_err());
+
+        // With the identity as the verifying key, and as `R` with `s = 0`, a signature
+        // verifies for every message unless weak keys are rejected.
+        let mut identity = [0u8; 32];
+        identity[0] = 1;
+        let weak_verifying_key = VerifyingKey::from_bytes(&identity).unwrap();
+        let mut forged = [0u8; 64];
+        forged[..32].copy_from_slice(&identity);
+        let forged = TallySignature(Signature::from_bytes(&forged));
+        let message = tally_signature_message(&hashes_ext, &decrypted_tally).unwrap();
+        assert!(weak_verifying_key.is_weak());
+        weak_verifying_key.verify(&message, &forged.0).unwrap();
+        assert!(verify_tally_signature(
+            &hashes_ext,
+            &decrypted_tally,
+            &weak_verifying_key,
+            &forged
+        )
+        .is_err());
+    }
+}
@@ -0,0 +1,110 @@
+// Copyright (C) Microsoft Corporation. All rights reserved.
+
+#![deny(clippy::unwrap_used)]
+#![deny(clippy::expect_used)]
+#![deny(clippy::panic)]
+#![deny(clippy::manual_assert)]
+
+//! Proof verification transcripts, for debugging interoperability problems.
+//!
+//! Only available with the `debug-transcript` feature.
+//!
+//! When a proof fails to verify, a [`TranscriptSink`] passed to a `..._with_transcript`
+//! verifier records the domain separator, hash key, and every element that was hashed, along
+//! with the intermediate and final challenge values. The result can be diffed line-by-line
+//! against the transcript of another implementation to find the first value on which they
+//! disagree.
+
+use anyhow::{Context, Result};
+use num_bigint::BigUint;
+use serde::{Deserialize, Serialize};
+
+use crate::zk::TranscriptValue;
+
+/// One labeled value recorded during proof verification.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TranscriptEntry {
+    /// What the value is, e.g. `"alpha"` or `"a[2]"`.
+    pub label: String,
+
+    /// The value, as uppercase hex without a prefix.
+    pub value: String,
+}
+
+/// Collects [`TranscriptEntry`]s in the order they are recorded.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TranscriptSink {
+    pub entries: Vec<TranscriptEntry>,
+}
+
+impl TranscriptSink {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records a `BigUint` value.
+    pub fn record_biguint(&mut self, label: impl Into<String>, value: &BigUint) {
+        self.entries.push(TranscriptEntry {
+            label: label.into(),
+            value: format!("{value:X}"),
+        });
+    }
+
+    /// Records a byte sequence.
+    pub fn record_bytes(&mut self, label: impl Into<String>, value: &[u8]) {
+        let value = value.iter().map(|b| format!("{b:02X}")).collect();
+        self.entries.push(TranscriptEntry {
+            label: label.into(),
+            value,
+        });
+    }
+
+    /// Records a value passed to the `record` function of a verifier.
+    pub(crate) fn record(&mut self, label: std::fmt::Arguments, value: TranscriptValue) {
+        match value {
+            TranscriptValue::BigUint(value) => self.record_biguint(label.to_string(), value),
+            TranscriptValue::Bytes(value) => self.record_bytes(label.to_string(), value),
+        }
+    }
+
+    /// Writes the transcript to a `std::io::Write` as pretty JSON.
+    pub fn to_stdiowrite(&self, stdiowrite: &mut dyn std::io::Write) -> Result<()> {
+        let mut ser = serde_json::Serializer::pretty(stdiowrite);
+
+        self.serialize(&mut ser)
+            .map_err(Into::<anyhow::Error>::into)
+            .and_then(|_| ser.into_inner().write_all(b"\n").map_err(Into::into))
+            .context("Writing TranscriptSink")
+    }
+}
+
+/// One entry per line, as `label: VALUE`, for easy diffing.
+impl std::fmt::Display for TranscriptSink {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for entry in &self.entries {
+            writeln!(f, "{}: {}", entry.label, entry.value)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_transcript_sink() {
+        let mut sink = TranscriptSink::new();
+        sink.record_biguint("x", &BigUint::from(0xABCDu32));
+        sink.record_bytes("y", &[0x00, 0x1F]);
+
+        assert_eq!(sink.entries.len(), 2);
+        assert_eq!(sink.to_string(), "x: ABCD\ny: 001F\n");
+
+        let mut buf = Vec::new();
+        sink.to_stdiowrite(&mut buf).unwrap();
+        let sink2: TranscriptSink = serde_json::from_slice(&buf).unwrap();
+        assert_eq!(sink.entries, sink2.entries);
+    }
+}
@@ -18,8 +18,18 @@ pub enum BallotChaining {
     Required,
 }
 
+/// The largest number of guardians [`VaryingParameters::n`] supported.
+///
+/// Real elections have a handful of guardians. Each guardian sends a key share to every
+/// other, so the share distribution has `n * (n - 1)` shares, which is about a million at
+/// this limit. Anything larger is much more likely a mistake than a real key ceremony.
+pub const MAX_GUARDIANS: u32 = 1000;
+
 /// The parameters for a specific election.
+///
+/// Deserialization fails unless the parameters pass [`VaryingParameters::validate`].
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(try_from = "VaryingParametersUnchecked")]
 pub struct VaryingParameters {
     /// Number of guardians.
     pub n: GuardianIndex,
@@ -37,6 +47,45 @@ pub struct VaryingParameters {
     pub ballot_chaining: BallotChaining,
 }
 
+/// [`VaryingParameters`] as read from a file, before they are validated.
+#[derive(Deserialize)]
+#[cfg_attr(feature = "strict-parsing", serde(deny_unknown_fields))]
+struct VaryingParametersUnchecked {
+    n: GuardianIndex,
+    k: GuardianIndex,
+    date: String,
+    info: String,
+    ballot_chaining: BallotChaining,
+}
+
+impl TryFrom<VaryingParametersUnchecked> for VaryingParameters {
+    type Error = anyhow::Error;
+
+    fn try_from(unchecked: VaryingParametersUnchecked) -> Result<Self> {
+        let varying_parameters = VaryingParameters {
+            n: unchecked.n,
+            k: unchecked.k,
+            date: unchecked.date,
+            info: unchecked.info,
+            ballot_chaining: unchecked.ballot_chaining,
+        };
+        varying_parameters.validate()?;
+        Ok(varying_parameters)
+    }
+}
+
+/// A one-line summary of the parameters. The jurisdictional information is omitted, as it
+/// may be long.
+impl std::fmt::Display for VaryingParameters {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "VaryingParameters{{n={}, k={}, date={}, ballot_chaining={:?}}}",
+            self.n, self.k, self.date, self.ballot_chaining
+        )
+    }
+}
+
 impl VaryingParameters {
     /// Verifies the `VaryingParameters` meet some basic validity requirements.
     #[allow(clippy::nonminimal_bool)]
@@ -56,6 +105,12 @@ impl VaryingParameters {
         // `k` must be less than or equal to `n`
         ensure!(self.k <= self.n, "Varying parameters failed check: k <= n");
 
+        // `n` must be supported
+        ensure!(
+            self.n.get_one_based_u32() <= MAX_GUARDIANS,
+            "Varying parameters failed check: n <= {MAX_GUARDIANS}"
+        );
+
         Ok(())
     }
 
@@ -67,8 +122,105 @@ impl VaryingParameters {
         (1..=self.n.get_one_based_u32()).contains(&i)
     }
 
+    /// Returns warnings about the security implications of the number of guardians and the
+    /// quorum threshold, see [`threshold_warnings`].
+    pub fn threshold_warnings(&self) -> Vec<&'static str> {
+        threshold_warnings(self.n, self.k)
+    }
+
     /// Iterates over the valid guardian numbers, 1 <= i <= [`VaryingParameters::n`].
     pub fn each_guardian_i(&self) -> impl Iterator<Item = GuardianIndex> {
         GuardianIndex::iter_range_inclusive(GuardianIndex::MIN, self.n)
     }
 }
+
+/// Returns warnings about the security implications of having `n` guardians with a quorum
+/// threshold of `k`. Any `k` guardians together can decrypt, and decryption is impossible if
+/// more than `n - k` guardians are absent.
+///
+/// The result is empty if `1 < k < n` and a majority of guardians is needed to decrypt.
+pub fn threshold_warnings(n: GuardianIndex, k: GuardianIndex) -> Vec<&'static str> {
+    let n = n.get_one_based_u32();
+    let k = k.get_one_based_u32();
+
+    let mut warnings = Vec::new();
+
+    if n < k {
+        warnings.push("The quorum threshold is larger than the number of guardians, so decryption is impossible.");
+        return warnings;
+    }
+
+    if k == 1 {
+        warnings.push("Any single guardian can decrypt alone, so there is no protection against a dishonest or compromised guardian.");
+    }
+
+    if k == n {
+        warnings.push("Every guardian is needed to decrypt, so the loss of any one guardian's key makes decryption impossible.");
+    }
+
+    if 1 < k && 2 * k <= n {
+        warnings.push("A minority of the guardians is enough to decrypt.");
+    }
+
+    warnings
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod test {
+    use super::*;
+
+    fn warnings(n: u32, k: u32) -> Vec<&'static str> {
+        threshold_warnings(
+            GuardianIndex::from_one_based_index(n).unwrap(),
+            GuardianIndex::from_one_based_index(k).unwrap(),
+        )
+    }
+
+    #[test]
+    fn test_threshold_warnings() {
+        assert!(warnings(5, 3).is_empty());
+        assert!(warnings(3, 2).is_empty());
+
+        assert_eq!(warnings(5, 1).len(), 1);
+        assert!(warnings(5, 1)[0].contains("single guardian"));
+
+        assert_eq!(warnings(5, 5).len(), 1);
+        assert!(warnings(5, 5)[0].contains("Every guardian"));
+
+        // k = 1 = n is degenerate both ways.
+        assert_eq!(warnings(1, 1).len(), 2);
+
+        assert_eq!(warnings(4, 2).len(), 1);
+        assert!(warnings(4, 2)[0].contains("minority"));
+
+        assert_eq!(warnings(3, 4).len(), 1);
+        assert!(warnings(3, 4)[0].contains("impossible"));
+    }
+
+    #[test]
+    fn test_deserialize_bounds() {
+        let parse = |n: u32, k: u32| {
+            serde_json::from_value::<VaryingParameters>(serde_json::json!({
+                "n": n,
+                "k": k,
+                "date": "2023-05-02",
+                "info": "",
+                "ballot_chaining": "Prohibited",
+            }))
+        };
+
+        let varying_parameters = parse(5, 3).unwrap();
+        assert_eq!(varying_parameters.n.get_one_based_u32(), 5);
+        parse(MAX_GUARDIANS, MAX_GUARDIANS).unwrap();
+
+        let e = parse(0, 0).unwrap_err().to_string();
+        assert!(e.contains("out of range"), "{e}");
+        let e = parse(5, 0).unwrap_err().to_string();
+        assert!(e.contains("out of range"), "{e}");
+        let e = parse(3, 5).unwrap_err().to_string();
+        assert!(e.contains("k <= n"), "{e}");
+        let e = parse(MAX_GUARDIANS + 1, 3).unwrap_err().to_string();
+        assert!(e.contains(&format!("n <= {MAX_GUARDIANS}")), "{e}");
+    }
+}
@@ -0,0 +1,1609 @@
+// Copyright (C) Microsoft Corporation. All rights reserved.
+
+#![deny(clippy::unwrap_used)]
+#![deny(clippy::expect_used)]
+#![deny(clippy::panic)]
+#![deny(clippy::manual_assert)]
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+use anyhow::{anyhow, bail, ensure, Context, Result};
+use num_bigint::BigUint;
+use num_traits::One;
+
+use crate::{
+    ballot::{BallotEncrypted, BallotKeyVersion},
+    confirmation_code::{b_aux, confirmation_code},
+    decryption_share::{combine_decryption_shares, verify_decryption_share},
+    election_manifest::{ContestIndex, ContestOptionIndex},
+    election_record::PreVotingData,
+    guardian_key_share::GuardianPublicKeyShare,
+    guardian_public_key::GuardianPublicKey,
+    hash::HValue,
+    index::Index,
+    joint_election_public_key::{Ciphertext, JointElectionPublicKey},
+    nonce_auditor::NonceAuditor,
+    tally::{
+        ciphertext_accumulate, ContestDecryptionShares, ContestTallyPart, DecryptedContest,
+        DecryptedTally, DecryptionShareSet, Tally,
+    },
+};
+
+/// Checks that the election record header is well-formed. It is always applicable.
+pub const CHECK_HEADER: &str = "Election record header";
+
+/// Checks that the joint election public key is the product of the guardians' public keys.
+pub const CHECK_JOINT_KEY: &str = "Joint election public key";
+
+/// Checks the proofs, contest hashes, and confirmation code of every ballot, and that no
+/// encryption nonce is used twice.
+pub const CHECK_BALLOTS: &str = "Ballot correctness";
+
+/// Checks that the tally is the homomorphic sum of the ballots.
+pub const CHECK_TALLY: &str = "Tally aggregation";
+
+/// Checks every guardian's decryption shares of the tally, and that the decrypted tally is
+/// what they decrypt to.
+pub const CHECK_DECRYPTION: &str = "Tally decryption";
+
+/// The parts of an election record to be verified by [`verify_election_record`].
+///
+/// A record is built up over the course of an election, so a record for an election which is
+/// still in progress will be missing its later parts. A part is missing if it is `None`, and
+/// the checks which need it are skipped. Start from [`RecordContents::new`] and set the parts
+/// which are present.
+#[derive(Debug, Clone, Copy)]
+pub struct RecordContents<'a> {
+    /// The election record header. Every check needs it, starting with [`CHECK_HEADER`].
+    pub header: &'a PreVotingData,
+
+    /// The guardian public keys. Needed by [`CHECK_JOINT_KEY`] and [`CHECK_DECRYPTION`].
+    pub opt_guardian_public_keys: Option<&'a [GuardianPublicKey]>,
+
+    /// The cast ballots, which may be none at all. Needed by [`CHECK_BALLOTS`] and
+    /// [`CHECK_TALLY`], so a tally published without its ballots is not checked.
+    pub opt_ballots: Option<&'a [BallotEncrypted]>,
+
+    /// The encrypted tally. Needed by [`CHECK_TALLY`] and [`CHECK_DECRYPTION`].
+    pub opt_tally: Option<&'a Tally>,
+
+    /// The decryption shares of the guardians who took part in decrypting the tally. Needed
+    /// by [`CHECK_DECRYPTION`].
+    pub opt_decryption_shares: Option<&'a [DecryptionShareSet]>,
+
+    /// The decrypted tally. Needed by [`CHECK_DECRYPTION`].
+    pub opt_decrypted_tally: Option<&'a DecryptedTally>,
+}
+
+impl<'a> RecordContents<'a> {
+    /// The contents of a record which has only its header.
+    pub fn new(header: &'a PreVotingData) -> Self {
+        RecordContents {
+            header,
+            opt_guardian_public_keys: None,
+            opt_ballots: None,
+            opt_tally: None,
+            opt_decryption_shares: None,
+            opt_decrypted_tally: None,
+        }
+    }
+
+    /// Returns true iff the record includes the guardian public keys.
+    pub fn has_guardian_public_keys(&self) -> bool {
+        self.opt_guardian_public_keys.is_some()
+    }
+
+    /// Returns true iff the record includes the ballots, even if there are none.
+    pub fn has_ballots(&self) -> bool {
+        self.opt_ballots.is_some()
+    }
+
+    /// Returns true iff the record includes the encrypted tally.
+    pub fn has_tally(&self) -> bool {
+        self.opt_tally.is_some()
+    }
+
+    /// Returns true iff the record includes the decryption shares and the decrypted tally.
+    pub fn has_decryption(&self) -> bool {
+        self.opt_decryption_shares.is_some() && self.opt_decrypted_tally.is_some()
+    }
+}
+
+/// The result of a single check.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CheckStatus {
+    Passed,
+    Failed,
+
+    /// The parts of the record needed for the check are not present.
+    Skipped,
+
+    /// Verification was cancelled before the check finished, and it had found no failure so
+    /// far.
+    Incomplete,
+}
+
+/// The outcome of verifying an election record.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VerificationReport {
+    /// The status of every check, in the order they were performed.
+    pub checks: Vec<(&'static str, CheckStatus)>,
+
+    /// The number of ballots supplied for verification.
+    pub ballots_total: usize,
+
+    /// The number of ballots which were checked, whether or not they passed.
+    pub ballots_processed: usize,
+
+    /// A description of every check which failed.
+    pub failures: Vec<String>,
+
+    /// For each ballot which failed, identified by its confirmation code, everything that
+    /// was wrong with it.
+    pub ballot_failures: Vec<(HValue, Vec<BallotFailure>)>,
+
+    /// True if verification was cancelled before every ballot was checked.
+    pub incomplete: bool,
+}
+
+impl VerificationReport {
+    /// Returns the names of the checks which can be performed on a record with the specified
+    /// contents. The remaining checks will be reported as [`CheckStatus::Skipped`].
+    pub fn applicable_checks(available: &RecordContents) -> Vec<&'static str> {
+        let mut checks = vec![CHECK_HEADER];
+        if available.has_guardian_public_keys() {
+            checks.push(CHECK_JOINT_KEY);
+        }
+        if available.has_ballots() {
+            checks.push(CHECK_BALLOTS);
+        }
+        // A tally is checked against the ballots, so it can't be checked without them. If
+        // there are no ballots it is still checked, since it must be empty.
+        if available.has_ballots() && available.has_tally() {
+            checks.push(CHECK_TALLY);
+        }
+        if available.has_guardian_public_keys()
+            && available.has_tally()
+            && available.has_decryption()
+        {
+            checks.push(CHECK_DECRYPTION);
+        }
+        checks
+    }
+
+    /// Returns the status of the named check, if it is known.
+    pub fn status(&self, check: &str) -> Option<CheckStatus> {
+        self.checks
+            .iter()
+            .find(|(name, _)| *name == check)
+            .map(|&(_, status)| status)
+    }
+
+    /// Returns true iff every ballot was checked and no check failed.
+    ///
+    /// Skipped checks do not count against success.
+    pub fn is_success(&self) -> bool {
+        !self.incomplete && self.failures.is_empty()
+    }
+}
+
+/// Verifies an election record against its header.
+///
+/// The record may be partial. Checks which need parts of the record that are not present are
+/// marked [`CheckStatus::Skipped`], so that an election still in progress does not appear to
+/// have failed verification. See [`RecordContents`] for the parts each check needs.
+///
+/// `cancel` is checked before each ballot. If it is set, verification stops and the report
+/// is marked `incomplete`, with the results for the ballots processed so far. This allows a
+/// long-running verification to be interrupted, e.g. from a Ctrl-C handler.
+///
+/// An error is returned only if the header itself is invalid; problems with individual
+/// ballots and the tally are recorded in the report.
+pub fn verify_election_record(
+    record: &RecordContents,
+    cancel: &AtomicBool,
+) -> Result<VerificationReport> {
+    let &RecordContents {
+        header: pvd,
+        opt_guardian_public_keys,
+        opt_ballots,
+        opt_tally,
+        opt_decryption_shares,
+        opt_decrypted_tally,
+    } = record;
+    let guardian_public_keys = opt_guardian_public_keys.unwrap_or_default();
+    let ballots = opt_ballots.unwrap_or_default();
+
+    pvd.validate()
+        .context("Validating the election record header")?;
+
+    let applicable_checks = VerificationReport::applicable_checks(record);
+
+    let mut report = VerificationReport {
+        checks: vec![(CHECK_HEADER, CheckStatus::Passed)],
+        ballots_total: ballots.len(),
+        ballots_processed: 0,
+        failures: Vec::new(),
+        ballot_failures: Vec::new(),
+        incomplete: false,
+    };
+
+    if applicable_checks.contains(&CHECK_JOINT_KEY) {
+        let status = match verify_joint_election_public_key(pvd, guardian_public_keys) {
+            Ok(()) => CheckStatus::Passed,
+            Err(e) => {
+                report
+                    .failures
+                    .push(format!("Joint election public key: {e:#}"));
+                CheckStatus::Failed
+            }
+        };
+        report.checks.push((CHECK_JOINT_KEY, status));
+    } else {
+        report.checks.push((CHECK_JOINT_KEY, CheckStatus::Skipped));
+    }
+
+    if applicable_checks.contains(&CHECK_BALLOTS) {
+        let cnt_failures_before = report.failures.len();
+        let mut nonce_auditor = NonceAuditor::new();
+        for ballot in ballots {
+            if cancel.load(Ordering::Relaxed) {
+                report.incomplete = true;
+                break;
+            }
+
+            let mut ballot_failures = check_ballot(pvd, ballot);
+            if let Err(e) = nonce_auditor.record_ballot(ballot) {
+                ballot_failures.push(BallotFailure::NonceReused(e.to_string()));
+            }
+            if !ballot_failures.is_empty() {
+                let descriptions: Vec<String> =
+                    ballot_failures.iter().map(|f| f.to_string()).collect();
+                report.failures.push(format!(
+                    "Ballot {}: {}",
+                    ballot.confirmation_code,
+                    descriptions.join("; ")
+                ));
+                report
+                    .ballot_failures
+                    .push((ballot.confirmation_code, ballot_failures));
+            }
+
+            report.ballots_processed += 1;
+        }
+
+        let status = if report.failures.len() != cnt_failures_before {
+            CheckStatus::Failed
+        } else if report.incomplete {
+            CheckStatus::Incomplete
+        } else {
+            CheckStatus::Passed
+        };
+        report.checks.push((CHECK_BALLOTS, status));
+    } else {
+        report.checks.push((CHECK_BALLOTS, CheckStatus::Skipped));
+    }
+
+    match opt_tally {
+        Some(tally) if applicable_checks.contains(&CHECK_TALLY) && !report.incomplete => {
+            let status = match verify_tally(pvd, ballots, tally) {
+                Ok(()) => CheckStatus::Passed,
+                Err(e) => {
+                    report.failures.push(format!("Tally: {e:#}"));
+                    CheckStatus::Failed
+                }
+            };
+            report.checks.push((CHECK_TALLY, status));
+        }
+        Some(_) if report.incomplete => report.checks.push((CHECK_TALLY, CheckStatus::Incomplete)),
+        _ => report.checks.push((CHECK_TALLY, CheckStatus::Skipped)),
+    }
+
+    match (opt_tally, opt_decryption_shares, opt_decrypted_tally) {
+        (Some(tally), Some(decryption_shares), Some(decrypted_tally))
+            if applicable_checks.contains(&CHECK_DECRYPTION) =>
+        {
+            let status = match verify_decrypted_tally(
+                pvd,
+                guardian_public_keys,
+                tally,
+                decryption_shares,
+                decrypted_tally,
+            ) {
+                Ok(()) => CheckStatus::Passed,
+                Err(e) => {
+                    report.failures.push(format!("Tally decryption: {e:#}"));
+                    CheckStatus::Failed
+                }
+            };
+            report.checks.push((CHECK_DECRYPTION, status));
+        }
+        _ => report.checks.push((CHECK_DECRYPTION, CheckStatus::Skipped)),
+    }
+
+    Ok(report)
+}
+
+/// Verifies that the joint election public key of the header is the product mod `p` of the
+/// guardians' public keys, i.e., of their commitments `K_i,0`.
+pub fn verify_joint_election_public_key(
+    pvd: &PreVotingData,
+    guardian_public_keys: &[GuardianPublicKey],
+) -> Result<()> {
+    let expected = JointElectionPublicKey::compute(&pvd.parameters, guardian_public_keys)?;
+
+    ensure!(
+        pvd.public_key
+            .equals(&expected, &pvd.parameters.fixed_parameters),
+        "Does not match the product of the guardian public keys"
+    );
+
+    Ok(())
+}
+
+/// Verifies that the tally is exactly the homomorphic sum of the cast ballots, as computed by
+/// [`Tally::from_ballots`]. Challenged and uncast ballots are not counted.
+pub fn verify_tally(pvd: &PreVotingData, ballots: &[BallotEncrypted], tally: &Tally) -> Result<()> {
+    let expected = Tally::from_ballots(&pvd.parameters.fixed_parameters, &pvd.manifest, ballots)?;
+
+    ensure!(
+        tally.ballot_count == expected.ballot_count,
+        "Includes {} ballots, but there are {}",
+        tally.ballot_count,
+        expected.ballot_count
+    );
+    ensure!(*tally == expected, "Does not match the sum of the ballots");
+
+    Ok(())
+}
+
+/// Verifies the contests of `tally` which have been decrypted so far.
+///
+/// Every decryption share is checked against the public key share of its guardian, and each
+/// published total must be what the combined shares decrypt to. Contests which are not in
+/// `decrypted_contests` are not checked, so results can be verified as they are released.
+pub fn verify_decrypted_contests(
+    pvd: &PreVotingData,
+    guardian_public_keys: &[GuardianPublicKey],
+    tally: &Tally,
+    decrypted_contests: &[DecryptedContest],
+) -> Result<()> {
+    let public_key_shares =
+        GuardianPublicKeyShare::compute_all(&pvd.parameters, guardian_public_keys)?;
+
+    let mut seen = BTreeSet::new();
+    for decrypted_contest in decrypted_contests {
+        let contest_ix = decrypted_contest.contest_ix;
+        ensure!(
+            seen.insert(contest_ix),
+            "Contest {contest_ix} is decrypted more than once"
+        );
+        verify_decrypted_contest(pvd, &public_key_shares, tally, decrypted_contest)
+            .with_context(|| format!("Decrypted contest {contest_ix}"))?;
+    }
+    Ok(())
+}
+
+/// Verifies that `decrypted_tally` is the decryption of every contest of `tally` with the
+/// guardians' `decryption_share_sets`, with [`verify_decrypted_contests`].
+pub fn verify_decrypted_tally(
+    pvd: &PreVotingData,
+    guardian_public_keys: &[GuardianPublicKey],
+    tally: &Tally,
+    decryption_share_sets: &[DecryptionShareSet],
+    decrypted_tally: &DecryptedTally,
+) -> Result<()> {
+    ensure!(
+        decrypted_tally.ballot_count == tally.ballot_count,
+        "Includes {} ballots, but the tally has {}",
+        decrypted_tally.ballot_count,
+        tally.ballot_count
+    );
+    ensure!(
+        decrypted_tally.contests.len() == tally.contests.len(),
+        "Has {} contests, but the tally has {}",
+        decrypted_tally.contests.len(),
+        tally.contests.len()
+    );
+
+    let decrypted_contests = tally
+        .contests
+        .indices()
+        .map(|contest_ix| {
+            let ix1 = contest_ix.get_one_based_u32();
+            let contest_ix = ContestIndex::from_one_based_index(ix1)?;
+            let result = decrypted_tally
+                .contests
+                .get(Index::from_one_based_index(ix1)?)
+                .cloned()
+                .with_context(|| format!("No result for contest {contest_ix}"))?;
+            let decryption_shares = decryption_share_sets
+                .iter()
+                .map(|share_set| {
+                    share_set
+                        .contests
+                        .get(contest_ix.get_zero_based_usize())
+                        .cloned()
+                        .with_context(|| {
+                            format!(
+                                "Decryption shares of guardian {} do not cover contest {contest_ix}",
+                                share_set.i
+                            )
+                        })
+                })
+                .collect::<Result<Vec<_>>>()?;
+            Ok(DecryptedContest {
+                contest_ix,
+                result,
+                decryption_shares,
+            })
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    verify_decrypted_contests(pvd, guardian_public_keys, tally, &decrypted_contests)
+}
+
+fn verify_decrypted_contest(
+    pvd: &PreVotingData,
+    public_key_shares: &[GuardianPublicKeyShare],
+    tally: &Tally,
+    decrypted_contest: &DecryptedContest,
+) -> Result<()> {
+    let fixed_parameters = &pvd.parameters.fixed_parameters;
+    let k = &pvd.public_key.joint_election_public_key;
+    let contest_tally = tally.contest(decrypted_contest.contest_ix)?;
+    let result = &decrypted_contest.result;
+    let decryption_shares = &decrypted_contest.decryption_shares;
+
+    ensure!(
+        result.option_totals.len() == contest_tally.selection.len(),
+        "Has {} option totals, but the tally has {} options",
+        result.option_totals.len(),
+        contest_tally.selection.len()
+    );
+    ensure!(
+        result.opt_blank_count.is_some() == contest_tally.opt_blank.is_some(),
+        "Blank count is present in only one of the result and the tally"
+    );
+    ensure!(
+        result.opt_undervote_count.is_some() == contest_tally.opt_undervote.is_some(),
+        "Undervote count is present in only one of the result and the tally"
+    );
+
+    let check = |ciphertext: &Ciphertext, part: ContestTallyPart, total: u64| {
+        let shares = ContestDecryptionShares::shares_of(decryption_shares, part)?;
+        for share in &shares {
+            let public_key_share = public_key_shares
+                .iter()
+                .find(|public_key_share| public_key_share.i == share.i)
+                .with_context(|| format!("No public key share for guardian {}", share.i))?;
+            verify_decryption_share(pvd, public_key_share, ciphertext, share)?;
+        }
+
+        let t = combine_decryption_shares(pvd, ciphertext, &shares)?;
+        ensure!(
+            *t.as_biguint() == k.modpow(&BigUint::from(total), fixed_parameters.p.as_ref()),
+            "Total {total} does not match the decryption shares"
+        );
+        Ok(())
+    };
+
+    for (option_ix, (ciphertext, &total)) in contest_tally
+        .selection
+        .iter()
+        .zip(result.option_totals.iter())
+        .enumerate()
+    {
+        let part = ContestTallyPart::Option(option_ix);
+        check(ciphertext, part, total).with_context(|| part.to_string())?;
+    }
+    let tracked = [
+        (
+            ContestTallyPart::Blank,
+            &contest_tally.opt_blank,
+            result.opt_blank_count,
+        ),
+        (
+            ContestTallyPart::Undervote,
+            &contest_tally.opt_undervote,
+            result.opt_undervote_count,
+        ),
+    ];
+    for (part, opt_ciphertext, opt_count) in tracked {
+        if let (Some(ciphertext), Some(count)) = (opt_ciphertext, opt_count) {
+            check(ciphertext, part, count).with_context(|| part.to_string())?;
+        }
+    }
+
+    Ok(())
+}
+
+/// The election record headers for several elections, or for one election whose key has
+/// been rotated, keyed by their extended base hash `H_E`.
+///
+/// Each ballot is verified against the header for the key it records having been encrypted
+/// under, see [`BallotEncrypted::key_version`].
+#[derive(Debug, Clone, Default)]
+pub struct PreVotingDataMap(BTreeMap<HValue, PreVotingData>);
+
+impl PreVotingDataMap {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a header, replacing and returning any header with the same `H_E`.
+    pub fn insert(&mut self, pvd: PreVotingData) -> Option<PreVotingData> {
+        self.0.insert(pvd.hashes_ext.h_e, pvd)
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Returns the header for the key `ballot` was encrypted under.
+    ///
+    /// A ballot which does not record its key can only be matched if there is just one header.
+    pub fn for_ballot(&self, ballot: &BallotEncrypted) -> Result<&PreVotingData> {
+        match ballot.key_version() {
+            Some(key_version) => self.0.get(&key_version.h_e).ok_or_else(|| {
+                anyhow!(
+                    "No election record header has the extended base hash {}",
+                    key_version.h_e
+                )
+            }),
+            None => {
+                let mut pvds = self.0.values();
+                match (pvds.next(), pvds.next()) {
+                    (Some(pvd), None) => Ok(pvd),
+                    (None, _) => bail!("There are no election record headers"),
+                    (Some(_), Some(_)) => bail!(
+                        "Ballot does not record its key, and there are {} election record headers",
+                        self.0.len()
+                    ),
+                }
+            }
+        }
+    }
+
+    /// Verifies `ballot` against the header for the key it was encrypted under.
+    pub fn verify_ballot(&self, ballot: &BallotEncrypted) -> Result<()> {
+        verify_ballot(self.for_ballot(ballot)?, ballot)
+    }
+}
+
+/// Why a ballot failed verification, as found by [`check_ballot`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BallotFailure {
+    /// The ballot records a different election key than the one it is verified against.
+    WrongKey,
+
+    /// The ballot, or one of its contests, does not have the structure the manifest calls
+    /// for, e.g. it has the wrong number of ciphertexts.
+    Malformed(String),
+
+    /// The contest hash does not match the contest's ciphertexts.
+    ContestHash(ContestIndex),
+
+    /// The disjunctive proof that the option's ciphertext encrypts 0 or 1 is invalid.
+    SelectionProof(ContestIndex, ContestOptionIndex),
+
+    /// The range proof that the contest's selections don't exceed its limit is invalid.
+    SelectionLimitProof(ContestIndex),
+
+    /// The proofs of the contest's blank indicator are invalid.
+    BlankIndicatorProof(ContestIndex),
+
+    /// The contest's undervote count or its range proof is invalid.
+    UndervoteProof(ContestIndex),
+
+    /// The confirmation code does not match the contest hashes, ballot style, and associated
+    /// data.
+    ConfirmationCode,
+
+    /// A ciphertext was encrypted with the same nonce as one of an earlier ballot, or of the
+    /// same ballot. Found by [`verify_election_record`], not [`check_ballot`].
+    NonceReused(String),
+}
+
+impl std::fmt::Display for BallotFailure {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        use BallotFailure::*;
+        match self {
+            WrongKey => write!(f, "Was encrypted under a different election key"),
+            Malformed(description) => write!(f, "{description}"),
+            ContestHash(contest_ix) => {
+                write!(
+                    f,
+                    "Contest {contest_ix} hash does not match its ciphertexts"
+                )
+            }
+            SelectionProof(contest_ix, option_ix) => write!(
+                f,
+                "Contest {contest_ix} option {option_ix} proof of ballot correctness is invalid"
+            ),
+            SelectionLimitProof(contest_ix) => write!(
+                f,
+                "Contest {contest_ix} proof of satisfying the selection limit is invalid"
+            ),
+            BlankIndicatorProof(contest_ix) => {
+                write!(f, "Contest {contest_ix} blank indicator proofs are invalid")
+            }
+            UndervoteProof(contest_ix) => {
+                write!(f, "Contest {contest_ix} undervote count is invalid")
+            }
+            ConfirmationCode => write!(
+                f,
+                "Confirmation code does not match the contest hashes, ballot style, and associated data"
+            ),
+            NonceReused(description) => write!(f, "{description}"),
+        }
+    }
+}
+
+/// Verifies the contest hashes, proofs, and confirmation code of a single ballot.
+///
+/// If the ballot records the key it was encrypted under, that must be the key of `pvd`.
+///
+/// Fails with the first problem found. Use [`check_ballot`] to find all of them.
+pub fn verify_ballot(pvd: &PreVotingData, ballot: &BallotEncrypted) -> Result<()> {
+    match check_ballot(pvd, ballot).into_iter().next() {
+        None => Ok(()),
+        Some(failure) => Err(anyhow!("{failure}")),
+    }
+}
+
+/// Checks everything that [`verify_ballot`] does, and returns every problem found.
+///
+/// Every contest is checked, and every proof within it, unless the ballot or contest is
+/// [`BallotFailure::Malformed`], in which case there is nothing further to check.
+pub fn check_ballot(pvd: &PreVotingData, ballot: &BallotEncrypted) -> Vec<BallotFailure> {
+    use BallotFailure::*;
+
+    let manifest = &pvd.manifest;
+    let fixed_parameters = &pvd.parameters.fixed_parameters;
+
+    if let Some(key_version) = ballot.key_version() {
+        if *key_version != BallotKeyVersion::of(pvd) {
+            return vec![WrongKey];
+        }
+    }
+
+    if ballot.contests.len() != manifest.contests.len() {
+        return vec![Malformed(format!(
+            "Has {} contests, but the manifest has {}",
+            ballot.contests.len(),
+            manifest.contests.len()
+        ))];
+    }
+
+    let opt_ballot_style = match ballot.ballot_style() {
+        None => None,
+        Some(ballot_style_ix) => match manifest.ballot_styles.get(ballot_style_ix) {
+            Some(ballot_style) => Some(ballot_style),
+            None => {
+                return vec![Malformed(format!(
+                    "Has ballot style {ballot_style_ix}, which does not exist"
+                ))];
+            }
+        },
+    };
+
+    let mut failures = Vec::new();
+
+    for contest_ix in manifest.contests.indices() {
+        let (Some(contest), Some(contest_encrypted)) = (
+            manifest.contests.get(contest_ix),
+            Index::from_one_based_index(contest_ix.get_one_based_u32())
+                .ok()
+                .and_then(|ix| ballot.contests.get(ix)),
+        ) else {
+            continue;
+        };
+
+        let selection = &contest_encrypted.selection;
+
+        // A contest which is not on the ballot style must add nothing to the tally.
+        let omitted = opt_ballot_style
+            .is_some_and(|ballot_style| !ballot_style.contests.contains(&contest_ix));
+        if omitted && !contest_encrypted.is_omitted() {
+            failures.push(Malformed(format!(
+                "Contest {contest_ix} is not on the ballot style, but is not encrypted as omitted"
+            )));
+            continue;
+        }
+
+        if selection.len() != contest.options.len()
+            || contest_encrypted.proof_ballot_correctness.len() != selection.len()
+        {
+            failures.push(Malformed(format!(
+                "Contest {contest_ix} has {} ciphertexts and {} proofs, but {} options",
+                selection.len(),
+                contest_encrypted.proof_ballot_correctness.len(),
+                contest.options.len()
+            )));
+            continue;
+        }
+
+        if let Some(option_ix) =
+            contest
+                .options
+                .indices()
+                .zip(selection.iter())
+                .find_map(|(option_ix, ciphertext)| {
+                    ciphertext
+                        .validate(fixed_parameters)
+                        .err()
+                        .map(|_| option_ix)
+                })
+        {
+            failures.push(Malformed(format!(
+                "Contest {contest_ix} option {option_ix} ciphertext is not a pair of valid nonzero mod p values"
+            )));
+            continue;
+        }
+        let other_ciphertexts = [
+            contest_encrypted
+                .opt_blank_indicator
+                .as_ref()
+                .map(|blank_indicator| ("blank indicator", &blank_indicator.ciphertext)),
+            contest_encrypted
+                .opt_undervote
+                .as_ref()
+                .map(|undervote| ("undervote count", &undervote.ciphertext)),
+        ];
+        if let Some((what, _)) = other_ciphertexts
+            .into_iter()
+            .flatten()
+            .find(|(_, ciphertext)| ciphertext.validate(fixed_parameters).is_err())
+        {
+            failures.push(Malformed(format!(
+                "Contest {contest_ix} {what} ciphertext is not a pair of valid nonzero mod p values"
+            )));
+            continue;
+        }
+
+        if contest_encrypted.contest_hash != contest_encrypted.compute_contest_hash(pvd, contest) {
+            failures.push(ContestHash(contest_ix));
+        }
+
+        for (option_ix, ciphertext) in contest.options.indices().zip(selection.iter()) {
+            let proof_ok = Index::from_one_based_index(option_ix.get_one_based_u32())
+                .ok()
+                .and_then(|proof_ix| contest_encrypted.proof_ballot_correctness.get(proof_ix))
+                .is_some_and(|proof| proof.verify(pvd, ciphertext, 1));
+            if !proof_ok {
+                failures.push(SelectionProof(contest_ix, option_ix));
+            }
+        }
+
+        let mut combined = Ciphertext {
+            alpha: BigUint::one(),
+            beta: BigUint::one(),
+            nonce: None,
+        };
+        for ciphertext in selection {
+            ciphertext_accumulate(fixed_parameters, &mut combined, ciphertext);
+        }
+        if !contest_encrypted
+            .proof_selection_limit
+            .verify(pvd, &combined, contest.selection_limit)
+        {
+            failures.push(SelectionLimitProof(contest_ix));
+        }
+
+        match (
+            contest.track_blank && !omitted,
+            &contest_encrypted.opt_blank_indicator,
+        ) {
+            (false, None) => {}
+            (true, Some(blank_indicator)) => {
+                if !blank_indicator.verify(pvd, contest, selection) {
+                    failures.push(BlankIndicatorProof(contest_ix));
+                }
+            }
+            (true, None) => failures.push(Malformed(format!(
+                "Contest {contest_ix} is missing its blank indicator"
+            ))),
+            (false, Some(_)) => failures.push(Malformed(format!(
+                "Contest {contest_ix} has an unexpected blank indicator"
+            ))),
+        }
+
+        match (
+            contest.track_undervotes && !omitted,
+            &contest_encrypted.opt_undervote,
+        ) {
+            (false, None) => {}
+            (true, Some(undervote)) => {
+                if !undervote.verify(pvd, contest, selection) {
+                    failures.push(UndervoteProof(contest_ix));
+                }
+            }
+            (true, None) => failures.push(Malformed(format!(
+                "Contest {contest_ix} is missing its undervote count"
+            ))),
+            (false, Some(_)) => failures.push(Malformed(format!(
+                "Contest {contest_ix} has an unexpected undervote count"
+            ))),
+        }
+    }
+
+    if ballot.confirmation_code
+        != confirmation_code(
+            &pvd.hashes_ext.h_e,
+            &ballot.contests,
+            &b_aux(ballot.ballot_style(), &ballot.associated_data),
+        )
+    {
+        failures.push(ConfirmationCode);
+    }
+
+    failures
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod test {
+    use super::*;
+    use crate::{
+        contest_selection::ContestSelection,
+        device::Device,
+        example_election::ExampleElection,
+        example_election_manifest::{
+            example_election_manifest, example_election_manifest_first_contests,
+        },
+        example_election_parameters::example_election_parameters,
+        vec1::Vec1,
+    };
+    use util::csprng::Csprng;
+
+    fn ballots(csprng: &mut Csprng, cnt_ballots: usize) -> (PreVotingData, Vec<BallotEncrypted>) {
+        let (pvd, _, ballots) = election(csprng, cnt_ballots);
+        (pvd, ballots)
+    }
+
+    /// A ballot for each of the first two contests of the example manifest.
+    const BALLOT_PLAINTEXTS: [&str; 3] = [
+        r#"{ "contests": [ { "vote": [1, 0] }, { "vote": [0, 1, 0, 0] } ] }"#,
+        r#"{ "contests": [ { "vote": [0, 1] }, { "vote": [0, 0, 0, 1] } ] }"#,
+        r#"{ "contests": [ { "vote": [0, 0] }, { "vote": [1, 0, 0, 0] } ] }"#,
+    ];
+
+    /// An example election with the first `cnt_ballots` of [`BALLOT_PLAINTEXTS`] encrypted.
+    fn election(
+        csprng: &mut Csprng,
+        cnt_ballots: usize,
+    ) -> (PreVotingData, Vec<GuardianPublicKey>, Vec<BallotEncrypted>) {
+        let example_election = ExampleElection::with_first_contests(csprng, 2).unwrap();
+        let ballots = example_election
+            .encrypt_ballots(csprng, &BALLOT_PLAINTEXTS[..cnt_ballots])
+            .unwrap();
+
+        let ExampleElection {
+            guardian_public_keys,
+            pvd,
+            ..
+        } = example_election;
+        (pvd, guardian_public_keys, ballots)
+    }
+
+    #[test]
+    fn test_verify_election_record() {
+        let mut csprng = Csprng::new(b"test_verify_election_record");
+
+        let (pvd, mut ballots) = ballots(&mut csprng, 3);
+        let verify = |ballots: &[BallotEncrypted], cancel: bool| {
+            let record = RecordContents {
+                opt_ballots: Some(ballots),
+                ..RecordContents::new(&pvd)
+            };
+            verify_election_record(&record, &AtomicBool::new(cancel)).unwrap()
+        };
+
+        let report = verify(&ballots, false);
+        assert!(report.is_success(), "{report:?}");
+        assert_eq!(report.ballots_processed, 3);
+
+        // Cancelled before the first ballot.
+        let report = verify(&ballots, true);
+        assert!(report.incomplete);
+        assert!(!report.is_success());
+        assert_eq!(report.ballots_total, 3);
+        assert_eq!(report.ballots_processed, 0);
+        assert_eq!(report.status(CHECK_BALLOTS), Some(CheckStatus::Incomplete));
+
+        // Swap the ciphertexts of two options of the second ballot.
+        let contest_ix = Index::from_one_based_index(1).unwrap();
+        ballots[1]
+            .contests
+            .get_mut(contest_ix)
+            .unwrap()
+            .selection
+            .swap(0, 1);
+
+        let report = verify(&ballots, false);
+        assert!(!report.incomplete);
+        assert_eq!(report.ballots_processed, 3);
+        assert_eq!(report.failures.len(), 1, "{report:?}");
+        assert!(report.failures[0].contains(&ballots[1].confirmation_code.to_string()));
+
+        // A ballot recorded twice reuses every nonce of the first copy, though each copy is
+        // correct on its own.
+        let mut buf = Vec::new();
+        ballots[0].to_stdiowrite(&mut buf).unwrap();
+        ballots.push(BallotEncrypted::from_stdioread(&mut buf.as_slice()).unwrap());
+        let report = verify(&ballots, false);
+        assert_eq!(report.status(CHECK_BALLOTS), Some(CheckStatus::Failed));
+        assert_eq!(report.failures.len(), 2, "{report:?}");
+        assert!(
+            matches!(
+                report.ballot_failures[1].1[..],
+                [BallotFailure::NonceReused(_)]
+            ),
+            "{report:?}"
+        );
+        assert!(report.failures[1].contains("nonce reused"), "{report:?}");
+    }
+
+    #[test]
+    fn test_ballot_failures() {
+        let mut csprng = Csprng::new(b"test_ballot_failures");
+
+        let (pvd, mut ballots) = ballots(&mut csprng, 2);
+        assert_eq!(check_ballot(&pvd, &ballots[0]), vec![]);
+
+        // Give the second option of the first contest of the second ballot the proof from the
+        // first ballot, which is for a different ciphertext. Nothing else is affected.
+        let contest_ix: ContestIndex = Index::from_one_based_index(1).unwrap();
+        let option_ix: ContestOptionIndex = Index::from_one_based_index(2).unwrap();
+        let proof_ix = Index::from_one_based_index(2).unwrap();
+        let proof = ballots[0]
+            .contests
+            .get(Index::from_one_based_index(1).unwrap())
+            .unwrap()
+            .proof_ballot_correctness
+            .get(proof_ix)
+            .unwrap()
+            .clone();
+        *ballots[1]
+            .contests
+            .get_mut(Index::from_one_based_index(1).unwrap())
+            .unwrap()
+            .proof_ballot_correctness
+            .get_mut(proof_ix)
+            .unwrap() = proof;
+
+        let expected = vec![BallotFailure::SelectionProof(contest_ix, option_ix)];
+        assert_eq!(check_ballot(&pvd, &ballots[1]), expected);
+
+        // A ciphertext encoded as `alpha + p` is malformed, even though it is the same group
+        // element.
+        let p = pvd.parameters.fixed_parameters.p.as_ref();
+        let alpha = &mut ballots[0]
+            .contests
+            .get_mut(Index::from_one_based_index(1).unwrap())
+            .unwrap()
+            .selection
+            .first_mut()
+            .unwrap()
+            .alpha;
+        *alpha += p;
+        assert_eq!(
+            check_ballot(&pvd, &ballots[0]),
+            vec![BallotFailure::Malformed(
+                "Contest 1 option 1 ciphertext is not a pair of valid nonzero mod p values"
+                    .to_string()
+            )]
+        );
+        let alpha = &mut ballots[0]
+            .contests
+            .get_mut(Index::from_one_based_index(1).unwrap())
+            .unwrap()
+            .selection
+            .first_mut()
+            .unwrap()
+            .alpha;
+        *alpha -= p;
+        assert_eq!(check_ballot(&pvd, &ballots[0]), vec![]);
+
+        let record = RecordContents {
+            opt_ballots: Some(&ballots),
+            ..RecordContents::new(&pvd)
+        };
+        let report = verify_election_record(&record, &AtomicBool::new(false)).unwrap();
+        assert_eq!(report.status(CHECK_BALLOTS), Some(CheckStatus::Failed));
+        assert_eq!(
+            report.ballot_failures,
+            vec![(ballots[1].confirmation_code, expected)]
+        );
+        assert!(
+            report.failures[0]
+                .ends_with("Contest 1 option 2 proof of ballot correctness is invalid"),
+            "{report:?}"
+        );
+    }
+
+    #[test]
+    fn test_blank_indicator_malformed() {
+        let mut csprng = Csprng::new(b"test_blank_indicator_malformed");
+
+        let mut election_manifest = example_election_manifest_first_contests(1);
+        let contest_1 = ContestIndex::from_one_based_index(1).unwrap();
+        election_manifest
+            .contests
+            .get_mut(contest_1)
+            .unwrap()
+            .track_blank = true;
+        let example_election = ExampleElection::generate(
+            &mut csprng,
+            example_election_parameters(),
+            election_manifest,
+        )
+        .unwrap();
+        let mut ballot = example_election
+            .encrypt_ballots(&mut csprng, &[r#"{ "contests": [ { "vote": [0, 1] } ] }"#])
+            .unwrap()
+            .remove(0);
+        let pvd = &example_election.pvd;
+        assert_eq!(check_ballot(pvd, &ballot), vec![]);
+
+        // Neither `alpha + p`, which is the same group element, nor anything else outside
+        // `1..p` is accepted for the blank indicator's ciphertext.
+        let p = pvd.parameters.fixed_parameters.p.as_ref();
+        let alpha_orig = ballot
+            .contests
+            .get(Index::from_one_based_index(1).unwrap())
+            .unwrap()
+            .opt_blank_indicator
+            .as_ref()
+            .unwrap()
+            .ciphertext
+            .alpha
+            .clone();
+        for alpha in [
+            &alpha_orig + p,
+            p.clone(),
+            p + BigUint::one(),
+            BigUint::from(0_u8),
+        ] {
+            ballot
+                .contests
+                .get_mut(Index::from_one_based_index(1).unwrap())
+                .unwrap()
+                .opt_blank_indicator
+                .as_mut()
+                .unwrap()
+                .ciphertext
+                .alpha = alpha;
+            assert_eq!(
+                check_ballot(pvd, &ballot),
+                vec![BallotFailure::Malformed(
+                    "Contest 1 blank indicator ciphertext is not a pair of valid nonzero mod p values"
+                        .to_string()
+                )]
+            );
+        }
+
+        ballot
+            .contests
+            .get_mut(Index::from_one_based_index(1).unwrap())
+            .unwrap()
+            .opt_blank_indicator
+            .as_mut()
+            .unwrap()
+            .ciphertext
+            .alpha = alpha_orig;
+        assert_eq!(check_ballot(pvd, &ballot), vec![]);
+    }
+
+    #[test]
+    fn test_partial_record() {
+        let mut csprng = Csprng::new(b"test_partial_record");
+
+        let (pvd, ballots) = ballots(&mut csprng, 2);
+        let cancel = AtomicBool::new(false);
+
+        // Voting is still in progress, so there is no tally yet.
+        let ballots_only = RecordContents {
+            opt_ballots: Some(&ballots),
+            ..RecordContents::new(&pvd)
+        };
+        assert_eq!(
+            VerificationReport::applicable_checks(&ballots_only),
+            [CHECK_HEADER, CHECK_BALLOTS]
+        );
+
+        let report = verify_election_record(&ballots_only, &cancel).unwrap();
+        assert!(report.is_success(), "{report:?}");
+        assert_eq!(report.status(CHECK_HEADER), Some(CheckStatus::Passed));
+        assert_eq!(report.status(CHECK_BALLOTS), Some(CheckStatus::Passed));
+        assert_eq!(report.status(CHECK_TALLY), Some(CheckStatus::Skipped));
+
+        // Nothing but the header.
+        let header_only = RecordContents::new(&pvd);
+        assert_eq!(
+            VerificationReport::applicable_checks(&header_only),
+            [CHECK_HEADER]
+        );
+        let report = verify_election_record(&header_only, &cancel).unwrap();
+        assert!(report.is_success(), "{report:?}");
+        assert_eq!(report.status(CHECK_BALLOTS), Some(CheckStatus::Skipped));
+
+        // Once the tally is present, it is checked.
+        let fixed_parameters = &pvd.parameters.fixed_parameters;
+        let tally = Tally::from_ballots(fixed_parameters, &pvd.manifest, &ballots).unwrap();
+        let with_tally = RecordContents {
+            opt_tally: Some(&tally),
+            ..ballots_only
+        };
+        let report = verify_election_record(&with_tally, &cancel).unwrap();
+        assert!(report.is_success(), "{report:?}");
+        assert_eq!(report.status(CHECK_TALLY), Some(CheckStatus::Passed));
+
+        let tally_of_one =
+            Tally::from_ballots(fixed_parameters, &pvd.manifest, &ballots[..1]).unwrap();
+        let with_tally_of_one = RecordContents {
+            opt_tally: Some(&tally_of_one),
+            ..ballots_only
+        };
+        let report = verify_election_record(&with_tally_of_one, &cancel).unwrap();
+        assert!(!report.is_success());
+        assert_eq!(report.status(CHECK_BALLOTS), Some(CheckStatus::Passed));
+        assert_eq!(report.status(CHECK_TALLY), Some(CheckStatus::Failed));
+
+        // Without the ballots, the tally can't be checked.
+        let tally_only = RecordContents {
+            opt_tally: Some(&tally),
+            ..header_only
+        };
+        assert_eq!(
+            VerificationReport::applicable_checks(&tally_only),
+            [CHECK_HEADER]
+        );
+        let report = verify_election_record(&tally_only, &cancel).unwrap();
+        assert!(report.is_success(), "{report:?}");
+        assert_eq!(report.status(CHECK_TALLY), Some(CheckStatus::Skipped));
+
+        // But if there are no ballots at all, the tally must be empty.
+        let no_ballots = RecordContents {
+            opt_ballots: Some(&[]),
+            ..tally_only
+        };
+        assert_eq!(
+            VerificationReport::applicable_checks(&no_ballots),
+            [CHECK_HEADER, CHECK_BALLOTS, CHECK_TALLY]
+        );
+        let report = verify_election_record(&no_ballots, &cancel).unwrap();
+        assert_eq!(report.status(CHECK_TALLY), Some(CheckStatus::Failed));
+
+        let empty_tally = Tally::new(&pvd.manifest);
+        let report = verify_election_record(
+            &RecordContents {
+                opt_tally: Some(&empty_tally),
+                ..no_ballots
+            },
+            &cancel,
+        )
+        .unwrap();
+        assert!(report.is_success(), "{report:?}");
+        assert_eq!(report.status(CHECK_TALLY), Some(CheckStatus::Passed));
+
+        // Cancelled before any ballot was checked, neither the ballots nor the tally passed.
+        let report = verify_election_record(&with_tally, &AtomicBool::new(true)).unwrap();
+        assert!(!report.is_success());
+        assert_eq!(report.status(CHECK_BALLOTS), Some(CheckStatus::Incomplete));
+        assert_eq!(report.status(CHECK_TALLY), Some(CheckStatus::Incomplete));
+    }
+
+    #[test]
+    fn test_joint_key_check() {
+        let mut csprng = Csprng::new(b"test_joint_key_check");
+
+        let (mut pvd, guardian_public_keys, ballots) = election(&mut csprng, 1);
+        let cancel = AtomicBool::new(false);
+
+        let with_keys = RecordContents {
+            opt_guardian_public_keys: Some(&guardian_public_keys),
+            opt_ballots: Some(&ballots),
+            ..RecordContents::new(&pvd)
+        };
+        assert_eq!(
+            VerificationReport::applicable_checks(&with_keys),
+            [CHECK_HEADER, CHECK_JOINT_KEY, CHECK_BALLOTS]
+        );
+
+        let report = verify_election_record(&with_keys, &cancel).unwrap();
+        assert!(report.is_success(), "{report:?}");
+        assert_eq!(report.status(CHECK_JOINT_KEY), Some(CheckStatus::Passed));
+
+        // Without the guardian public keys, the check can't be performed.
+        let without_keys = RecordContents {
+            opt_guardian_public_keys: None,
+            ..with_keys
+        };
+        let report = verify_election_record(&without_keys, &cancel).unwrap();
+        assert_eq!(report.status(CHECK_JOINT_KEY), Some(CheckStatus::Skipped));
+
+        // The keys are compared mod p, so the same key, unreduced, still matches.
+        let p = pvd.parameters.fixed_parameters.p.as_ref().clone();
+        let mut unreduced = pvd.clone();
+        unreduced.public_key.joint_election_public_key += &p;
+        verify_joint_election_public_key(&unreduced, &guardian_public_keys).unwrap();
+
+        // A published joint key which isn't the product of the guardian public keys.
+        let k = &mut pvd.public_key.joint_election_public_key;
+        *k = &*k * &pvd.parameters.fixed_parameters.g % &p;
+
+        let tampered = RecordContents {
+            opt_guardian_public_keys: Some(&guardian_public_keys),
+            ..RecordContents::new(&pvd)
+        };
+        let report = verify_election_record(&tampered, &cancel).unwrap();
+        assert!(!report.is_success());
+        assert_eq!(report.status(CHECK_JOINT_KEY), Some(CheckStatus::Failed));
+        assert_eq!(report.failures.len(), 1, "{report:?}");
+        assert!(
+            report.failures[0].starts_with("Joint election public key"),
+            "{report:?}"
+        );
+    }
+
+    #[test]
+    fn test_decryption_check() {
+        let mut csprng = Csprng::new(b"test_decryption_check");
+
+        let example_election = ExampleElection::with_first_contests(&mut csprng, 1).unwrap();
+        let ballots = example_election
+            .encrypt_ballots(
+                &mut csprng,
+                &[
+                    r#"{ "contests": [ { "vote": [1, 0] } ] }"#,
+                    r#"{ "contests": [ { "vote": [0, 1] } ] }"#,
+                ],
+            )
+            .unwrap();
+        let ExampleElection {
+            guardian_public_keys,
+            guardian_key_shares,
+            pvd,
+            ..
+        } = example_election;
+
+        let tally =
+            Tally::from_ballots(&pvd.parameters.fixed_parameters, &pvd.manifest, &ballots).unwrap();
+        let decryption_share_sets = guardian_key_shares
+            .iter()
+            .map(|key_share| DecryptionShareSet::compute(&mut csprng, &pvd, key_share, &tally))
+            .collect::<Vec<_>>();
+        let decrypted_tally = tally.decrypt(&pvd, &decryption_share_sets).unwrap();
+
+        let cancel = AtomicBool::new(false);
+        let complete = RecordContents {
+            opt_guardian_public_keys: Some(&guardian_public_keys),
+            opt_ballots: Some(&ballots),
+            opt_tally: Some(&tally),
+            opt_decryption_shares: Some(&decryption_share_sets),
+            opt_decrypted_tally: Some(&decrypted_tally),
+            ..RecordContents::new(&pvd)
+        };
+        assert_eq!(
+            VerificationReport::applicable_checks(&complete),
+            [
+                CHECK_HEADER,
+                CHECK_JOINT_KEY,
+                CHECK_BALLOTS,
+                CHECK_TALLY,
+                CHECK_DECRYPTION
+            ]
+        );
+        let report = verify_election_record(&complete, &cancel).unwrap();
+        assert!(report.is_success(), "{report:?}");
+        assert_eq!(report.status(CHECK_DECRYPTION), Some(CheckStatus::Passed));
+
+        // Until the tally is decrypted, the check can't be performed.
+        let not_decrypted = RecordContents {
+            opt_decrypted_tally: None,
+            ..complete
+        };
+        let report = verify_election_record(&not_decrypted, &cancel).unwrap();
+        assert!(report.is_success(), "{report:?}");
+        assert_eq!(report.status(CHECK_DECRYPTION), Some(CheckStatus::Skipped));
+
+        // A published total which isn't what the shares decrypt to.
+        let mut decrypted_tally_wrong = decrypted_tally.clone();
+        decrypted_tally_wrong
+            .contests
+            .get_mut(Index::from_one_based_index(1).unwrap())
+            .unwrap()
+            .option_totals[0] += 1;
+        let report = verify_election_record(
+            &RecordContents {
+                opt_decrypted_tally: Some(&decrypted_tally_wrong),
+                ..complete
+            },
+            &cancel,
+        )
+        .unwrap();
+        assert_eq!(report.status(CHECK_DECRYPTION), Some(CheckStatus::Failed));
+        assert_eq!(report.failures.len(), 1, "{report:?}");
+        assert!(
+            report.failures[0].starts_with("Tally decryption"),
+            "{report:?}"
+        );
+
+        // As are the shares of fewer than k guardians.
+        let k = pvd.parameters.varying_parameters.k.as_quantity();
+        let report = verify_election_record(
+            &RecordContents {
+                opt_decryption_shares: Some(&decryption_share_sets[..k - 1]),
+                ..complete
+            },
+            &cancel,
+        )
+        .unwrap();
+        assert_eq!(report.status(CHECK_DECRYPTION), Some(CheckStatus::Failed));
+    }
+
+    #[test]
+    fn test_pre_voting_data_map() {
+        let mut csprng = Csprng::new(b"test_pre_voting_data_map");
+
+        // The same election with its key rotated: the guardians generated new keys.
+        let (pvd_1, mut ballots_1) = ballots(&mut csprng, 2);
+        let (pvd_2, ballots_2) = ballots(&mut csprng, 2);
+        assert_ne!(pvd_1.hashes_ext.h_e, pvd_2.hashes_ext.h_e);
+
+        let mut pvd_map = PreVotingDataMap::new();
+        assert!(pvd_map.verify_ballot(&ballots_1[0]).is_err());
+        pvd_map.insert(pvd_1.clone());
+        pvd_map.insert(pvd_2.clone());
+        assert_eq!(pvd_map.len(), 2);
+
+        for (pvd, ballot) in std::iter::repeat(&pvd_1)
+            .zip(&ballots_1)
+            .chain(std::iter::repeat(&pvd_2).zip(&ballots_2))
+        {
+            assert_eq!(ballot.key_version(), Some(&BallotKeyVersion::of(pvd)));
+            pvd_map.verify_ballot(ballot).unwrap();
+        }
+
+        // Verifying against the other key fails up front.
+        let e = verify_ballot(&pvd_2, &ballots_1[0])
+            .unwrap_err()
+            .to_string();
+        assert!(e.contains("different election key"), "{e}");
+
+        // A ballot claiming a key that is not in the map.
+        let mut key_version = BallotKeyVersion::of(&pvd_1);
+        key_version.h_e = HValue::default();
+        ballots_1[0].opt_key_version = Some(key_version);
+        assert!(pvd_map.verify_ballot(&ballots_1[0]).is_err());
+
+        // A ballot which doesn't record its key is ambiguous unless there is just one header.
+        ballots_1[1].opt_key_version = None;
+        assert!(pvd_map.verify_ballot(&ballots_1[1]).is_err());
+        let mut pvd_map_1 = PreVotingDataMap::new();
+        pvd_map_1.insert(pvd_1);
+        pvd_map_1.verify_ballot(&ballots_1[1]).unwrap();
+    }
+
+    #[test]
+    fn test_selection_limit_equals_option_count() {
+        use crate::{
+            ballot_style::BallotStyle,
+            decryption_share::{combine_decryption_shares, DecryptionShare},
+            election_manifest::{
+                Contest, ContestIndex, ContestKind, ContestOption, ElectionManifest, VoteVariation,
+            },
+        };
+        use std::collections::BTreeSet;
+
+        let mut csprng = Csprng::new(b"test_selection_limit_equals_option_count");
+
+        let election_parameters = example_election_parameters();
+
+        // A 3-option contest where a voter may select everything, tracking blanks so that the
+        // blank indicator's proofs are exercised at the boundary too, and the Grand Council
+        // contest, where the limit of 3 is much smaller than the number of options.
+        let example_manifest = example_election_manifest();
+        let grand_council = example_manifest
+            .contests
+            .get(ContestIndex::from_one_based_index(5).unwrap())
+            .unwrap()
+            .clone();
+        assert_eq!(grand_council.selection_limit, 3);
+        let select_all = Contest {
+            label: "Thrée Seats on the Ëlderglen Board".to_string(),
+            selection_limit: 3,
+            vote_variation: VoteVariation::Approval,
+            options: ["Åsh", "Bïrch", "Çedar"]
+                .map(|label| ContestOption {
+                    label: label.to_string(),
+                    color: None,
+                    image_uri: None,
+                })
+                .try_into()
+                .unwrap(),
+            kind: ContestKind::Candidate,
+            track_blank: true,
+            track_undervotes: false,
+            opt_electoral_district_id: None,
+        };
+        let election_manifest = ElectionManifest {
+            label: example_manifest.label.clone(),
+            contests: [select_all, grand_council].try_into().unwrap(),
+            ballot_styles: [BallotStyle {
+                label: "Ëlderglen Ballot".to_string(),
+                contests: BTreeSet::from(
+                    [1, 2].map(|ix1| ContestIndex::from_one_based_index(ix1).unwrap()),
+                ),
+            }]
+            .try_into()
+            .unwrap(),
+            geopolitical_units: Vec::new(),
+        };
+        election_manifest.validate().unwrap();
+
+        // No placeholders are needed, however large the limit.
+        for contest_ix in election_manifest.contests.indices() {
+            let contest = election_manifest.contests.get(contest_ix).unwrap();
+            assert_eq!(contest.placeholder_count(), 0);
+            assert_eq!(contest.ballot_slots().count(), contest.options.len());
+        }
+
+        let ExampleElection {
+            guardian_key_shares,
+            pvd,
+            ..
+        } = ExampleElection::generate(&mut csprng, election_parameters, election_manifest).unwrap();
+        let device = Device::new("test", pvd.clone());
+
+        let fixed_parameters = &pvd.parameters.fixed_parameters;
+        let contest_1 = Index::from_one_based_index(1).unwrap();
+        let contest_2 = Index::from_one_based_index(2).unwrap();
+
+        // Decrypts a ciphertext known to encrypt at most 4.
+        let decrypt = |csprng: &mut Csprng, ciphertext: &Ciphertext| {
+            let decryption_shares = guardian_key_shares
+                .iter()
+                .map(|key_share| DecryptionShare::compute(csprng, &pvd, key_share, ciphertext))
+                .collect::<Vec<_>>();
+            combine_decryption_shares(&pvd, ciphertext, &decryption_shares)
+                .unwrap()
+                .discrete_log(&pvd, 4)
+                .unwrap()
+        };
+        let sum = |ballot: &BallotEncrypted, contest_ix| {
+            let mut combined = Ciphertext {
+                alpha: BigUint::one(),
+                beta: BigUint::one(),
+                nonce: None,
+            };
+            for ciphertext in &ballot.contests.get(contest_ix).unwrap().selection {
+                ciphertext_accumulate(fixed_parameters, &mut combined, ciphertext);
+            }
+            combined
+        };
+        let encrypt = |csprng: &mut Csprng, select_all_vote: Vec<u8>, grand_council_vote| {
+            let primary_nonce = csprng.next_u8();
+            let ctest_selections: Vec1<ContestSelection> = [
+                ContestSelection {
+                    vote: select_all_vote,
+                },
+                ContestSelection {
+                    vote: grand_council_vote,
+                },
+            ]
+            .try_into()
+            .unwrap();
+            BallotEncrypted::new_from_selections(
+                &device,
+                csprng,
+                &[primary_nonce; 32],
+                &ctest_selections,
+            )
+            .unwrap()
+        };
+        let grand_council_vote = |cnt_selected: usize| {
+            let mut vote = vec![0; 11];
+            for v in vote.iter_mut().step_by(3).take(cnt_selected) {
+                *v = 1;
+            }
+            assert_eq!(vote.iter().filter(|&&v| v == 1).count(), cnt_selected);
+            vote
+        };
+
+        // Every possible selection in the 3-option contest, from none up to every option,
+        // which is the limit. The Grand Council contest goes up to its limit alongside.
+        for selected_bits in 0..8_u8 {
+            let select_all_vote = (0..3).map(|j| (selected_bits >> j) & 1).collect::<Vec<_>>();
+            let cnt_selected = selected_bits.count_ones() as u64;
+            let ballot = encrypt(
+                &mut csprng,
+                select_all_vote,
+                grand_council_vote(cnt_selected as usize),
+            );
+            assert_eq!(check_ballot(&pvd, &ballot), vec![], "{selected_bits:03b}");
+
+            for contest_ix in [contest_1, contest_2] {
+                let combined = sum(&ballot, contest_ix);
+                assert_eq!(
+                    decrypt(&mut csprng, &combined),
+                    cnt_selected,
+                    "{selected_bits:03b}"
+                );
+            }
+
+            // The blank indicator is set only when nothing is selected, and a full contest is
+            // not mistaken for a blank one.
+            let blank_indicator = ballot
+                .contests
+                .get(contest_1)
+                .unwrap()
+                .opt_blank_indicator
+                .as_ref()
+                .unwrap();
+            assert_eq!(
+                decrypt(&mut csprng, &blank_indicator.ciphertext),
+                u64::from(cnt_selected == 0),
+                "{selected_bits:03b}"
+            );
+        }
+
+        // The selection limit proof of a full contest is for the range up to the limit, and
+        // doesn't verify against a limit one smaller.
+        let ballot = encrypt(&mut csprng, vec![1, 1, 1], grand_council_vote(3));
+        for contest_ix in [contest_1, contest_2] {
+            let combined = sum(&ballot, contest_ix);
+            let proof_selection_limit = &ballot
+                .contests
+                .get(contest_ix)
+                .unwrap()
+                .proof_selection_limit;
+            assert!(proof_selection_limit.verify(&pvd, &combined, 3));
+            assert!(!proof_selection_limit.verify(&pvd, &combined, 2));
+        }
+
+        // The encryptor can't make a proof for more than the limit, so a ballot with one
+        // selection past it is spliced together from two honest ones: a fourth selection in
+        // the Grand Council contest, with its valid proof of being 0 or 1, and the hashes
+        // recomputed to match. Only the selection limit proof catches it.
+        let mut ballot = encrypt(&mut csprng, vec![1, 1, 1], grand_council_vote(3));
+        let mut one_more = vec![0; 11];
+        one_more[10] = 1;
+        let other_ballot = encrypt(&mut csprng, vec![0, 0, 0], one_more);
+        let option_ix = Index::from_one_based_index(11).unwrap();
+        let other_contest = other_ballot.contests.get(contest_2).unwrap();
+        let contest_encrypted = ballot.contests.get_mut(contest_2).unwrap();
+        assert_eq!(decrypt(&mut csprng, &contest_encrypted.selection[10]), 0);
+        contest_encrypted.selection[10] = other_contest.selection[10].clone();
+        *contest_encrypted
+            .proof_ballot_correctness
+            .get_mut(option_ix)
+            .unwrap() = other_contest
+            .proof_ballot_correctness
+            .get(option_ix)
+            .unwrap()
+            .clone();
+        let contest_ix_2 = ContestIndex::from_one_based_index(2).unwrap();
+        contest_encrypted.contest_hash = contest_encrypted
+            .compute_contest_hash(&pvd, pvd.manifest.contests.get(contest_ix_2).unwrap());
+        ballot.confirmation_code = confirmation_code(
+            &pvd.hashes_ext.h_e,
+            &ballot.contests,
+            &b_aux(ballot.ballot_style(), &ballot.associated_data),
+        );
+        assert_eq!(decrypt(&mut csprng, &sum(&ballot, contest_2)), 4);
+        assert_eq!(
+            check_ballot(&pvd, &ballot),
+            vec![BallotFailure::SelectionLimitProof(contest_ix_2)]
+        );
+    }
+}
@@ -11,10 +11,23 @@ use num_bigint::BigUint;
 use serde::{Deserialize, Serialize};
 use util::{csprng::Csprng, prime::BigUintPrime};
 
+#[cfg(feature = "debug-transcript")]
+use crate::transcript::TranscriptSink;
 use crate::{
     election_record::PreVotingData, hash::eg_h, index::Index, joint_election_public_key::Ciphertext,
 };
 
+/// A value passed to the `record` function of a verifier, which records it in a transcript
+/// with the `debug-transcript` feature, and ignores it otherwise.
+#[cfg_attr(not(feature = "debug-transcript"), allow(dead_code))]
+pub(crate) enum TranscriptValue<'a> {
+    BigUint(&'a BigUint),
+    Bytes(&'a [u8]),
+}
+
+/// The function through which a verifier records each [`TranscriptValue`] with its label.
+pub(crate) type Record<'a> = dyn FnMut(std::fmt::Arguments, TranscriptValue) + 'a;
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ProofRangeSingle {
     #[serde(
@@ -36,33 +49,44 @@ pub type ProofRangeIndex = Index<ProofRange>;
 pub struct ProofRange(Vec<ProofRangeSingle>);
 
 impl ProofRange {
+    /// The domain separator of the challenge.
+    const CHALLENGE_DOMAIN: u8 = 0x21;
+
+    /// Makes a proof from its components, e.g. when decoding a ballot.
+    pub(crate) fn from_singles(singles: Vec<ProofRangeSingle>) -> Self {
+        Self(singles)
+    }
+
+    /// The components of the proof, one for each possible value `0 <= j <= L`.
+    pub(crate) fn singles(&self) -> &[ProofRangeSingle] {
+        &self.0
+    }
+
     pub fn challenge(
         pvd: &PreVotingData,
         ct: &Ciphertext,
         a: &[BigUint],
         b: &[BigUint],
     ) -> BigUint {
-        let mut v = vec![0x21];
-
-        v.extend_from_slice(
-            pvd.public_key
-                .joint_election_public_key
-                .to_bytes_be()
-                .as_slice(),
-        );
-        v.extend_from_slice(ct.alpha.to_bytes_be().as_slice());
-        v.extend_from_slice(ct.beta.to_bytes_be().as_slice());
+        let fixed_parameters = &pvd.parameters.fixed_parameters;
+        let mut v = vec![Self::CHALLENGE_DOMAIN];
 
-        a.iter().for_each(|a_i| {
-            v.extend_from_slice(a_i.to_bytes_be().as_slice());
-        });
-        b.iter().for_each(|b_i| {
-            v.extend_from_slice(b_i.to_bytes_be().as_slice());
-        });
+        // Every hashed value is mod p, so each is written as a fixed-width field.
+        for x in [
+            &pvd.public_key.joint_election_public_key,
+            &ct.alpha,
+            &ct.beta,
+        ]
+        .into_iter()
+        .chain(a)
+        .chain(b)
+        {
+            v.extend_from_slice(&fixed_parameters.biguint_to_be_bytes_len_p(x));
+        }
 
         // Equation 25
         let c = eg_h(&pvd.hashes_ext.h_e, &v);
-        BigUint::from_bytes_be(c.0.as_slice()) % pvd.parameters.fixed_parameters.q.as_ref()
+        BigUint::from_bytes_be(c.0.as_slice()) % fixed_parameters.q.as_ref()
     }
 
     pub fn new(
@@ -136,6 +160,53 @@ impl ProofRange {
 
     /// Verification 4
     pub fn verify(&self, pvd: &PreVotingData, ct: &Ciphertext, big_l: usize) -> bool {
+        self.verify_impl(pvd, ct, big_l, &mut |_label, _value| {})
+    }
+
+    /// Same as [`ProofRange::verify`], but records every hashed element and challenge
+    /// value in `transcript`.
+    #[cfg(feature = "debug-transcript")]
+    pub fn verify_with_transcript(
+        &self,
+        pvd: &PreVotingData,
+        ct: &Ciphertext,
+        big_l: usize,
+        transcript: &mut TranscriptSink,
+    ) -> bool {
+        self.verify_impl(pvd, ct, big_l, &mut |label, value| {
+            transcript.record(label, value)
+        })
+    }
+
+    fn verify_impl(
+        &self,
+        pvd: &PreVotingData,
+        ct: &Ciphertext,
+        big_l: usize,
+        record: &mut Record,
+    ) -> bool {
+        use TranscriptValue::{BigUint as Int, Bytes};
+
+        // A proof read from a file may have the wrong number of entries.
+        if self.0.len() != big_l + 1 {
+            return false;
+        }
+
+        // The labels are only formatted if `record` uses them, so that verifying without a
+        // transcript doesn't allocate a string for every value.
+        record(format_args!("domain"), Bytes(&[Self::CHALLENGE_DOMAIN]));
+        record(format_args!("H_E"), Bytes(pvd.hashes_ext.h_e.0.as_slice()));
+        record(
+            format_args!("K"),
+            Int(&pvd.public_key.joint_election_public_key),
+        );
+        record(format_args!("alpha"), Int(&ct.alpha));
+        record(format_args!("beta"), Int(&ct.beta));
+        for (j, proof) in self.0.iter().enumerate() {
+            record(format_args!("c[{j}]"), Int(&proof.c));
+            record(format_args!("v[{j}]"), Int(&proof.v));
+        }
+
         let a = (0..big_l + 1)
             .map(|j| {
                 (pvd.parameters
@@ -169,7 +240,15 @@ impl ProofRange {
             })
             .collect::<Vec<_>>();
 
+        for (j, a_j) in a.iter().enumerate() {
+            record(format_args!("a[{j}]"), Int(a_j));
+        }
+        for (j, b_j) in b.iter().enumerate() {
+            record(format_args!("b[{j}]"), Int(b_j));
+        }
+
         let c = Self::challenge(pvd, ct, &a, &b);
+        record(format_args!("c"), Int(&c));
 
         let mut rhs = BigUint::from(0u8);
         for e in self.0.iter() {
@@ -177,6 +256,7 @@ impl ProofRange {
         }
 
         rhs %= pvd.parameters.fixed_parameters.q.as_ref();
+        record(format_args!("sum c[j] mod q"), Int(&rhs));
 
         c == rhs
 
@@ -191,170 +271,107 @@ impl ProofRange {
     }
 }
 
-/*
-#[derive(Debug, Clone)]
-pub struct ProofGuardian {
-    pub c: Vec<BigUint>,
-    pub v: Vec<BigUint>,
-    pub capital_k: Vec<BigUint>,
-}
-impl struct ProofCorrectDecryption {}
-Serialize for ProofGuardian
-impl Serialize for ProofGuardian {
-    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
-    where
-        S: serde::ser::Serializer,
-    {
-        (
-            self.c
-                .iter()
-                .map(|x| x.to_str_radix(16))
-                .collect::<Vec<String>>(),
-            self.v
-                .iter()
-                .map(|x| x.to_str_radix(16))
-                .collect::<Vec<String>>(),
-            self.capital_k
-                .iter()
-                .map(|x| x.to_str_radix(16))
-                .collect::<Vec<String>>(),
-        )
-            .serialize(serializer)
-    }
-}
-// Deserialize for ProofGuardian
-impl<'de> Deserialize<'de> for ProofGuardian {
-    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
-    where
-        D: serde::de::Deserializer<'de>,
-    {
-        match <(Vec<String>, Vec<String>, Vec<String>)>::deserialize(deserializer) {
-            Ok((c, v, capital_k)) => Ok(Self {
-                c: c.iter()
-                    .map(|x| BigUint::from_str_radix(x, 16).unwrap())
-                    .collect(),
-                v: v.iter()
-                    .map(|x| BigUint::from_str_radix(x, 16).unwrap())
-                    .collect(),
-                capital_k: capital_k
-                    .iter()
-                    .map(|x| BigUint::from_str_radix(x, 16).unwrap())
-                    .collect(),
-            }),
-            Err(e) => return Err(e),
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod test {
+    use super::*;
+    use crate::example_election::ExampleElection;
+
+    #[test]
+    fn test_range_proof_challenge_fixed_width() {
+        let mut csprng = Csprng::new(b"test_range_proof_challenge_fixed_width");
+
+        let pvd = ExampleElection::with_first_contests(&mut csprng, 1)
+            .unwrap()
+            .pvd;
+        let fixed_parameters = &pvd.parameters.fixed_parameters;
+        let l_p_bytes = fixed_parameters.l_p_bytes();
+
+        // Values far shorter than p are padded to its length, so that they hash the same as
+        // in any other implementation.
+        let ct = Ciphertext {
+            alpha: BigUint::from(2u8),
+            beta: BigUint::from(0x0102u16),
+            nonce: None,
+        };
+        let a = [BigUint::from(3u8)];
+        let b = [BigUint::from(4u8)];
+
+        let mut v = vec![ProofRange::CHALLENGE_DOMAIN];
+        for x in [
+            &pvd.public_key.joint_election_public_key,
+            &ct.alpha,
+            &ct.beta,
+            &a[0],
+            &b[0],
+        ] {
+            let bytes = x.to_bytes_be();
+            v.extend(std::iter::repeat_n(0, l_p_bytes - bytes.len()));
+            v.extend_from_slice(&bytes);
         }
+        assert_eq!(v.len(), 1 + 5 * l_p_bytes);
+
+        let c = eg_h(&pvd.hashes_ext.h_e, &v);
+        assert_eq!(
+            ProofRange::challenge(&pvd, &ct, &a, &b),
+            BigUint::from_bytes_be(c.0.as_slice()) % fixed_parameters.q.as_ref()
+        );
     }
-}
-impl ProofGuardian {
-    pub fn from_json(json: &str) -> Self {
-        serde_json::from_str(json).unwrap()
-    }
-    pub fn to_json(&self) -> String {
-        serde_json::to_string(self).unwrap()
-    }
-    pub fn challenge(
-        fixed_parameters: &FixedParameters,
-        h_p: HValue,
-        i: usize,
-        j: usize,
-        capital_k_i_j: &BigUint,
-        h_i_j: &BigUint,
-    ) -> BigUint {
-        let mut v = vec![0x10];
-        v.extend_from_slice(i.to_be_bytes().as_slice());
-        v.extend_from_slice(j.to_be_bytes().as_slice());
-        v.extend_from_slice(capital_k_i_j.to_bytes_be().as_slice());
-        v.extend_from_slice(h_i_j.to_bytes_be().as_slice());
-        // Equation 11
-        let c = eg_h(&h_p, &v);
-        BigUint::from_bytes_be(c.0.as_slice()) % fixed_parameters.q.as_ref()
-    }
-    pub fn new(
-        csprng: &mut Csprng,
-        fixed_parameters: &FixedParameters,
-        h_p: HValue,
-        zmulq: Rc<ZMulPrime>,
-        i: u16,
-        k: u16,
-        capital_k_i: &[BigUint],
-        a_i: &[BigUint],
-    ) -> Self {
-        let u = (0..k)
-            .map(|_| ZMulPrimeElem::new_pick_random(zmulq.clone(), csprng))
-            .collect::<Vec<ZMulPrimeElem>>();
-        let h = u
+
+    #[cfg(feature = "debug-transcript")]
+    #[test]
+    fn test_verify_with_transcript() {
+        let mut csprng = Csprng::new(b"test_verify_with_transcript");
+
+        let pvd = ExampleElection::with_first_contests(&mut csprng, 1)
+            .unwrap()
+            .pvd;
+        let fixed_parameters = &pvd.parameters.fixed_parameters;
+
+        let nonce = fixed_parameters.q.random_group_elem(&mut csprng);
+        let ct = pvd
+            .public_key
+            .encrypt_with(fixed_parameters, &nonce, 1, true);
+        let proof = ProofRange::new(&pvd, &mut csprng, &fixed_parameters.q, &ct, 1, 2);
+        assert!(proof.verify(&pvd, &ct, 2));
+
+        let mut transcript = TranscriptSink::new();
+        assert!(proof.verify_with_transcript(&pvd, &ct, 2, &mut transcript));
+
+        let labels: Vec<&str> = transcript
+            .entries
             .iter()
-            .map(|u_j| {
-                fixed_parameters
-                    .g
-                    .modpow(&u_j.elem, fixed_parameters.p.borrow())
-            })
-            .collect::<Vec<BigUint>>();
-        let mut c = <Vec<ZMulPrimeElem>>::new();
-        let mut v = <Vec<ZMulPrimeElem>>::new();
-        for j in 0..k {
-            match ZMulPrimeElem::try_new(
-                zmulq.clone(),
-                Self::challenge(
-                    fixed_parameters,
-                    h_p,
-                    i as usize,
-                    j as usize,
-                    &capital_k_i[j as usize],
-                    &h[j as usize],
-                ),
-            ) {
-                Some(x) => c.push(x),
-                None => panic!("Challenge is not in ZmulPrime"),
-            };
-            v.push(&u[j as usize] - &(&c[j as usize] * &a_i[j as usize]));
-        }
-        ProofGuardian {
-            c: c.iter().map(|x| x.elem.clone()).collect(),
-            v: v.iter().map(|x| x.elem.clone()).collect(),
-            capital_k: capital_k_i.to_vec(),
-        }
-    }
-    /// Verification 2
-    pub fn verify(&self, fixed_parameters: &FixedParameters, h_p: HValue, i: u16, k: u16) -> bool {
-        // 2.1
-        let h = (0..k)
-            .map(|j| {
-                let j = j as usize;
-                fixed_parameters
-                    .g
-                    .modpow(&self.v[j], fixed_parameters.p.borrow())
-                    * self.capital_k[j].modpow(&self.c[j], fixed_parameters.p.borrow())
-                    % fixed_parameters.p.as_ref()
-            })
-            .collect::<Vec<BigUint>>();
-        // let zmulp = Rc::new(ZMulPrime::new(fixed_parameters.p.clone()));
-        // let zmulq = Rc::new(ZMulPrime::new(fixed_parameters.q.clone()));
-        let mut verified = true;
-        let zero = BigUint::from(0u8);
-        // let one = BigUint::from(1u8);
-        for j in 0..k {
-            let j = j as usize;
-            // 2.A
-            verified &=
-                (zero <= self.capital_k[j]) & (self.capital_k[j] < *fixed_parameters.p.borrow());
-            verified &= self.capital_k[j]
-                .modpow(fixed_parameters.q.borrow(), fixed_parameters.p.borrow())
-                == One::one();
-            // 2.B
-            verified &= (zero <= self.v[j]) & (self.v[j] < *fixed_parameters.q.borrow());
-            // 2.C
-            verified &= self.c[j]
-                == Self::challenge(
-                    fixed_parameters,
-                    h_p,
-                    i as usize,
-                    j,
-                    &self.capital_k[j],
-                    &h[j],
-                );
-        }
-        verified
+            .map(|entry| entry.label.as_str())
+            .collect();
+        assert_eq!(
+            labels,
+            [
+                "domain",
+                "H_E",
+                "K",
+                "alpha",
+                "beta",
+                "c[0]",
+                "v[0]",
+                "c[1]",
+                "v[1]",
+                "c[2]",
+                "v[2]",
+                "a[0]",
+                "a[1]",
+                "a[2]",
+                "b[0]",
+                "b[1]",
+                "b[2]",
+                "c",
+                "sum c[j] mod q"
+            ]
+        );
+        assert_eq!(transcript.entries[0].value, "21");
+        assert_eq!(
+            transcript.entries[1].value,
+            pvd.hashes_ext.h_e.to_string_hex_no_prefix_suffix()
+        );
+        assert_eq!(transcript.entries[17].value, transcript.entries[18].value);
     }
 }
-*/
@@ -0,0 +1,104 @@
+// Copyright (C) Microsoft Corporation. All rights reserved.
+
+//! Checks the crate against the known-answer test vectors in `tests/vectors/`.
+
+#![allow(clippy::unwrap_used)]
+#![allow(clippy::expect_used)]
+
+use std::{path::PathBuf, str::FromStr};
+
+use base64::{engine::general_purpose, Engine as _};
+use serde::Deserialize;
+
+use eg::{
+    example_election_manifest::example_election_manifest,
+    example_election_parameters::example_election_parameters,
+    hash::{eg_h, eg_h_js, HValue},
+    hashes::Hashes,
+};
+
+fn read_vectors<T: for<'de> Deserialize<'de>>(file_name: &str) -> T {
+    let path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("vectors")
+        .join(file_name);
+    let bytes = std::fs::read(&path).unwrap_or_else(|e| panic!("{}: {e}", path.display()));
+    serde_json::from_slice(&bytes).unwrap_or_else(|e| panic!("{}: {e}", path.display()))
+}
+
+fn hex_decode(s: &str) -> Vec<u8> {
+    assert!(s.len().is_multiple_of(2), "odd length hex string: {s:?}");
+    (0..s.len())
+        .step_by(2)
+        .map(|ix| u8::from_str_radix(&s[ix..ix + 2], 16).unwrap())
+        .collect()
+}
+
+#[derive(Deserialize)]
+struct HashVectors {
+    hmac_sha256: Vec<HmacSha256Vector>,
+}
+
+#[derive(Deserialize)]
+struct HmacSha256Vector {
+    source: String,
+    key: String,
+    data: String,
+    h: String,
+}
+
+#[test]
+fn test_vectors_hash() {
+    let vectors: HashVectors = read_vectors("hash.json");
+    assert!(!vectors.hmac_sha256.is_empty());
+
+    for v in vectors.hmac_sha256 {
+        let key = hex_decode(&v.key);
+        let data = hex_decode(&v.data);
+        let expected = hex_decode(&v.h);
+
+        let actual = general_purpose::URL_SAFE_NO_PAD
+            .decode(eg_h_js(&key, &data))
+            .unwrap();
+        assert_eq!(actual, expected, "{}", v.source);
+
+        // `eg_h` only accepts keys which are themselves hash values.
+        if let Ok(key) = <[u8; 32]>::try_from(key.as_slice()) {
+            let actual = eg_h(&HValue::from(key), &data);
+            assert_eq!(actual.as_ref(), expected.as_slice(), "{}", v.source);
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct HashesVectors {
+    standard_parameters: StandardParametersVectors,
+    example_election_manifest: ExampleElectionManifestVectors,
+}
+
+#[derive(Deserialize)]
+struct StandardParametersVectors {
+    h_p: String,
+}
+
+#[derive(Deserialize)]
+struct ExampleElectionManifestVectors {
+    h_m: String,
+}
+
+#[test]
+fn test_vectors_hashes() {
+    let vectors: HashesVectors = read_vectors("hashes.json");
+
+    let election_parameters = example_election_parameters();
+    let election_manifest = example_election_manifest();
+
+    let hashes = Hashes::compute(&election_parameters, &election_manifest).unwrap();
+
+    let expected_h_p = HValue::from_str(&vectors.standard_parameters.h_p).unwrap();
+    assert_eq!(hashes.h_p, expected_h_p);
+
+    let expected_h_m = HValue::from_str(&vectors.example_election_manifest.h_m).unwrap();
+    assert_eq!(hashes.h_m, expected_h_m);
+    assert_eq!(election_manifest.hash(&hashes.h_p).unwrap(), expected_h_m);
+}
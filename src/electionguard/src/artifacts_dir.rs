@@ -9,8 +9,10 @@ use std::fs::{File, OpenOptions};
 use std::path::{Path, PathBuf};
 
 use anyhow::{bail, Context, Result};
+use eg::ballot::BallotId;
 use eg::guardian::GuardianIndex;
 use eg::hash::HValue;
+use tracing::{debug, info};
 
 /// Provides access to files in the artifacts directory.
 #[derive(Clone, Copy, Debug, Eq, PartialEq)]
@@ -20,7 +22,8 @@ pub(crate) enum ArtifactFile {
     ElectionManifestCanonical,
     ElectionParameters,
     ElectionPreVotingData,
-    EncryptedBallot(u128, HValue),
+    EncryptedBallot(u128, BallotId),
+    BallotChainClosing(u128),
     PreEncryptedBallotMetadata(u128),
     PreEncryptedBallot(u128, HValue),
     PreEncryptedBallotNonce(u128, HValue),
@@ -31,13 +34,11 @@ pub(crate) enum ArtifactFile {
     GuardianSecretKey(GuardianIndex),
     GuardianPublicKey(GuardianIndex),
     JointElectionPublicKey,
-}
-
-impl std::fmt::Display for ArtifactFile {
-    #[inline]
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> Result<(), std::fmt::Error> {
-        PathBuf::from(*self).as_path().display().fmt(f)
-    }
+    ShareDistribution,
+    DecryptionShare(GuardianIndex),
+    EncryptedTally,
+    DecryptedTally,
+    VerificationReport,
 }
 
 fn election_public_dir() -> PathBuf {
@@ -48,8 +49,20 @@ fn guardian_secret_dir(i: GuardianIndex) -> PathBuf {
     format!("SECRET_for_guardian_{i}").into()
 }
 
-impl From<ArtifactFile> for PathBuf {
-    fn from(artifact_file: ArtifactFile) -> PathBuf {
+/// A policy for naming the files in the artifacts directory.
+///
+/// This allows reading and writing records laid out by other implementations.
+pub(crate) trait ArtifactNaming {
+    /// Returns the path of the specified artifact file, relative to the artifacts directory.
+    fn relative_path(&self, artifact_file: ArtifactFile) -> PathBuf;
+}
+
+/// The naming convention used by this implementation.
+#[derive(Clone, Copy, Debug, Default)]
+pub(crate) struct DefaultArtifactNaming;
+
+impl ArtifactNaming for DefaultArtifactNaming {
+    fn relative_path(&self, artifact_file: ArtifactFile) -> PathBuf {
         use ArtifactFile::*;
         match artifact_file {
             PseudorandomSeedDefeatsAllSecrecy => {
@@ -70,14 +83,12 @@ impl From<ArtifactFile> for PathBuf {
             PreEncryptedBallotMetadata(ts) => Path::new("pre_encrypted/ballots/")
                 .join(format!("{ts}"))
                 .join(format!("metadata.{ts}.dat")),
-            EncryptedBallot(ts, i) => {
-                Path::new("record/ballots/")
-                    .join(format!("{ts}"))
-                    .join(format!(
-                        "ballot.{}.json",
-                        i.to_string_hex_no_prefix_suffix()
-                    ))
-            }
+            EncryptedBallot(ts, ballot_id) => Path::new("record/ballots/")
+                .join(format!("{ts}"))
+                .join(format!("ballot.{ballot_id}.json")),
+            BallotChainClosing(ts) => Path::new("record/ballots/")
+                .join(format!("{ts}"))
+                .join("chain_closing.json"),
             PreEncryptedBallot(ts, i) => Path::new("pre_encrypted/ballots/")
                 .join(format!("{ts}"))
                 .join(format!(
@@ -112,30 +123,84 @@ impl From<ArtifactFile> for PathBuf {
             }
             JointElectionPublicKey => election_public_dir().join("joint_election_public_key.json"),
             HashesExt => election_public_dir().join("hashes_ext.json"),
+            ShareDistribution => election_public_dir().join("share_distribution.json"),
+            DecryptionShare(i) => {
+                election_public_dir().join(format!("guardian_{i}.decryption_shares.json"))
+            }
+            EncryptedTally => Path::new("record").join("encrypted_tally.json"),
+            DecryptedTally => Path::new("record").join("decrypted_tally.json"),
+            VerificationReport => Path::new("record").join("verification_report.txt"),
+        }
+    }
+}
+
+/// Same as [`DefaultArtifactNaming`], except that guardian public keys are placed in a
+/// `guardians` directory, e.g. `guardians/guardian_1.public_key.json`.
+#[derive(Clone, Copy, Debug, Default)]
+pub(crate) struct GuardiansDirArtifactNaming;
+
+impl ArtifactNaming for GuardiansDirArtifactNaming {
+    fn relative_path(&self, artifact_file: ArtifactFile) -> PathBuf {
+        match artifact_file {
+            ArtifactFile::GuardianPublicKey(i) => {
+                Path::new("guardians").join(format!("guardian_{i}.public_key.json"))
+            }
+            _ => DefaultArtifactNaming.relative_path(artifact_file),
+        }
+    }
+}
+
+/// Selects an [`ArtifactNaming`] policy from the command line.
+#[derive(Clone, Copy, Debug, Default, clap::ValueEnum)]
+pub(crate) enum ArtifactNamingScheme {
+    /// The naming convention used by this implementation.
+    #[default]
+    Default,
+
+    /// Guardian public keys in a `guardians` directory.
+    GuardiansDir,
+}
+
+impl From<ArtifactNamingScheme> for Box<dyn ArtifactNaming> {
+    fn from(scheme: ArtifactNamingScheme) -> Self {
+        match scheme {
+            ArtifactNamingScheme::Default => Box::new(DefaultArtifactNaming),
+            ArtifactNamingScheme::GuardiansDir => Box::new(GuardiansDirArtifactNaming),
         }
     }
 }
 
 pub(crate) struct ArtifactsDir {
     pub dir_path: PathBuf,
+    naming: Box<dyn ArtifactNaming>,
 }
 
 impl ArtifactsDir {
-    /// Creates a new `ArtifactsDir` referring to the specified path.
+    /// Creates a new `ArtifactsDir` referring to the specified path, using the
+    /// [`DefaultArtifactNaming`].
     pub fn new<P>(path: P) -> Result<Self>
+    where
+        P: AsRef<Path>,
+    {
+        Self::new_with_naming(path, Box::new(DefaultArtifactNaming))
+    }
+
+    /// Creates a new `ArtifactsDir` referring to the specified path, using the specified
+    /// policy for naming the files within it.
+    pub fn new_with_naming<P>(path: P, naming: Box<dyn ArtifactNaming>) -> Result<Self>
     where
         P: AsRef<Path>,
     {
         Ok(ArtifactsDir {
             dir_path: path.as_ref().to_path_buf(),
+            naming,
         })
     }
 
     /// Returns the path to the specified artifact file.
     /// Does not check whether the file exists.
     pub fn path(&self, artifact_file: ArtifactFile) -> PathBuf {
-        let file_pb: PathBuf = artifact_file.into();
-        self.dir_path.join(file_pb)
+        self.dir_path.join(self.naming.relative_path(artifact_file))
     }
 
     /// Returns true if the file exists in the artifacts directory.
@@ -187,6 +252,8 @@ impl ArtifactsDir {
             bail!("Specify at least one of opt_path or opt_artifact_file");
         };
 
+        debug!("Reading: {}", stdioread_and_path.1.display());
+
         Ok(stdioread_and_path)
     }
 
@@ -222,4 +289,122 @@ impl ArtifactsDir {
 
         Ok(stdiowrite_and_path)
     }
+
+    /// Writes the specified artifact file with `f`, creating its parent directory if
+    /// necessary.
+    pub fn write_artifact<F>(&self, artifact_file: ArtifactFile, f: F) -> Result<()>
+    where
+        F: FnOnce(&mut dyn std::io::Write) -> Result<()>,
+    {
+        let path = self.path(artifact_file);
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)
+                .with_context(|| format!("Couldn't create directory: {}", parent.display()))?;
+        }
+
+        let (mut stdiowrite, path) = self.out_file_stdiowrite(&None, Some(artifact_file))?;
+        f(stdiowrite.as_mut()).with_context(|| format!("Writing {}", path.display()))?;
+
+        info!("Wrote: {}", path.display());
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod test {
+    use super::*;
+    use eg::{
+        example_election_parameters::example_election_parameters,
+        guardian_public_key::GuardianPublicKey, guardian_secret_key::GuardianSecretKey,
+    };
+    use util::csprng::Csprng;
+
+    use crate::test_artifacts::{run_subcommand, write_artifact};
+
+    #[test]
+    fn test_artifact_naming() {
+        let i = GuardianIndex::from_one_based_index(1).unwrap();
+
+        assert_eq!(
+            DefaultArtifactNaming.relative_path(ArtifactFile::GuardianPublicKey(i)),
+            Path::new("public/guardian_1.public_key.json")
+        );
+        assert_eq!(
+            GuardiansDirArtifactNaming.relative_path(ArtifactFile::GuardianPublicKey(i)),
+            Path::new("guardians/guardian_1.public_key.json")
+        );
+
+        // Every other file is named as by default.
+        for artifact_file in [
+            ArtifactFile::ElectionParameters,
+            ArtifactFile::GuardianSecretKey(i),
+            ArtifactFile::DecryptionShare(i),
+            ArtifactFile::EncryptedTally,
+        ] {
+            assert_eq!(
+                GuardiansDirArtifactNaming.relative_path(artifact_file),
+                DefaultArtifactNaming.relative_path(artifact_file),
+                "{artifact_file:?}"
+            );
+        }
+
+        // The artifacts dir consults its policy.
+        let dir = Path::new("artifacts");
+        let artifacts_dir =
+            ArtifactsDir::new_with_naming(dir, ArtifactNamingScheme::GuardiansDir.into()).unwrap();
+        assert_eq!(
+            artifacts_dir.path(ArtifactFile::GuardianPublicKey(i)),
+            dir.join("guardians/guardian_1.public_key.json")
+        );
+        assert_eq!(
+            ArtifactsDir::new(dir)
+                .unwrap()
+                .path(ArtifactFile::GuardianPublicKey(i)),
+            dir.join("public/guardian_1.public_key.json")
+        );
+    }
+
+    #[test]
+    fn test_artifacts_naming_arg() {
+        let mut csprng = Csprng::new(b"test_artifacts_naming_arg");
+
+        let election_parameters = example_election_parameters();
+        let i = GuardianIndex::from_one_based_index(1).unwrap();
+        let secret_key =
+            GuardianSecretKey::generate(&mut csprng, &election_parameters, i, None).unwrap();
+
+        let dir = tempfile::tempdir().unwrap();
+        let artifacts_dir = ArtifactsDir::new(dir.path()).unwrap();
+        write_artifact(&artifacts_dir, ArtifactFile::ElectionParameters, |w| {
+            election_parameters.to_stdiowrite(w)
+        });
+        write_artifact(&artifacts_dir, ArtifactFile::GuardianSecretKey(i), |w| {
+            secret_key.to_stdiowrite(w)
+        });
+        std::fs::create_dir_all(dir.path().join("guardians")).unwrap();
+
+        run_subcommand(
+            dir.path(),
+            &[
+                "--artifacts-naming",
+                "guardians-dir",
+                "--seed",
+                "01",
+                "guardian-secret-key-write-public-key",
+                "--i",
+                "1",
+            ],
+        )
+        .unwrap();
+
+        // The public key is written where the policy names it, and only there.
+        assert!(!artifacts_dir.exists(ArtifactFile::GuardianPublicKey(i)));
+        let public_key = GuardianPublicKey::from_stdioread(
+            &mut File::open(dir.path().join("guardians/guardian_1.public_key.json")).unwrap(),
+        )
+        .unwrap();
+        assert_eq!(public_key.to_json(), secret_key.make_public_key().to_json());
+    }
 }
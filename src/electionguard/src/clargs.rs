@@ -7,7 +7,10 @@
 
 use std::path::PathBuf;
 
-use crate::subcommands::Subcommands;
+use anyhow::{ensure, Context};
+use tracing::level_filters::LevelFilter;
+
+use crate::{artifacts_dir::ArtifactNamingScheme, subcommands::Subcommands};
 
 #[derive(Debug, clap::Parser)]
 pub(crate) struct Clargs {
@@ -15,12 +18,75 @@ pub(crate) struct Clargs {
     #[arg(long, env = "ELECTIONGUARD_ARTIFACTS_DIR")]
     pub artifacts_dir: PathBuf,
 
+    /// Naming convention for the files in the artifacts directory.
+    #[arg(long, value_enum, default_value_t)]
+    pub artifacts_naming: ArtifactNamingScheme,
+
     /// Make the entire operation deterministic by using the seed data from
     /// the `artifacts/pseudorandom_seed_defeats_all_secrecy.bin` file.
     /// This is completely insecure and should only be used for testing.
-    #[arg(long)]
+    #[arg(long, global = true)]
     pub insecure_deterministic: bool,
 
+    /// Make the entire operation deterministic by using this seed, given as hex digits.
+    /// This is completely insecure and should only be used for testing and rehearsals.
+    #[arg(long, global = true, conflicts_with = "insecure_deterministic")]
+    pub seed: Option<HexSeed>,
+
+    /// Report more detail about what is being done, including how long each step took.
+    /// Repeat for even more detail.
+    #[arg(short, long, global = true, action = clap::ArgAction::Count, conflicts_with = "quiet")]
+    pub verbose: u8,
+
+    /// Report only errors, and any output which the subcommand was asked to produce.
+    /// Warnings that randomness is insecurely deterministic are still shown.
+    #[arg(short, long, global = true)]
+    pub quiet: bool,
+
     #[command(subcommand)]
     pub subcommand: Subcommands,
 }
+
+impl Clargs {
+    /// Whether randomness comes from a seed rather than the operating system.
+    pub fn is_deterministic(&self) -> bool {
+        self.insecure_deterministic || self.seed.is_some()
+    }
+
+    /// The most detailed level of messages to report, from `--verbose` and `--quiet`.
+    pub fn max_level(&self) -> LevelFilter {
+        if self.quiet {
+            return LevelFilter::ERROR;
+        }
+        match self.verbose {
+            0 => LevelFilter::INFO,
+            1 => LevelFilter::DEBUG,
+            _ => LevelFilter::TRACE,
+        }
+    }
+}
+
+/// Seed data given on the command line as an even number of hex digits.
+#[derive(Debug, Clone)]
+pub(crate) struct HexSeed(pub Vec<u8>);
+
+impl std::str::FromStr for HexSeed {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        ensure!(!s.is_empty(), "Seed is empty");
+        ensure!(
+            s.len().is_multiple_of(2),
+            "Seed has an odd number of hex digits"
+        );
+        let bytes = (0..s.len())
+            .step_by(2)
+            .map(|ix| {
+                s.get(ix..ix + 2)
+                    .and_then(|pair| u8::from_str_radix(pair, 16).ok())
+                    .with_context(|| format!("Seed is not hex at position {ix}"))
+            })
+            .collect::<anyhow::Result<Vec<u8>>>()?;
+        Ok(HexSeed(bytes))
+    }
+}
@@ -5,16 +5,27 @@
 #![deny(clippy::panic)]
 #![deny(clippy::manual_assert)]
 
-use std::path::PathBuf;
+use std::{path::PathBuf, time::Instant};
 
 use anyhow::{ensure, Context, Result};
 use rand_core::{OsRng, RngCore};
+use tracing::{debug, info};
 
 use eg::{
-    election_manifest::ElectionManifest, election_parameters::ElectionParameters,
-    example_election_manifest::example_election_manifest, guardian::GuardianIndex,
-    guardian_public_key::GuardianPublicKey, guardian_secret_key::GuardianSecretKey, hashes::Hashes,
-    hashes_ext::HashesExt, joint_election_public_key::JointElectionPublicKey,
+    artifact_format::parse_json_artifact,
+    election_manifest::ElectionManifest,
+    election_parameters::ElectionParameters,
+    election_record::PreVotingData,
+    example_election_manifest::{example_election_manifest, example_election_manifest_seeded},
+    guardian::GuardianIndex,
+    guardian_key_share::GuardianPublicKeyShare,
+    guardian_public_key::GuardianPublicKey,
+    guardian_secret_key::GuardianSecretKey,
+    hashes::Hashes,
+    hashes_ext::HashesExt,
+    joint_election_public_key::JointElectionPublicKey,
+    share_distribution::ShareDistribution,
+    tally::{DecryptionShareSet, Tally},
 };
 use util::csprng::Csprng;
 
@@ -25,6 +36,13 @@ pub(crate) enum ElectionManifestSource {
     ArtifactFileElectionManifestCanonical,
     SpecificFile(PathBuf),
     Example,
+    ExampleSeeded {
+        seed: String,
+        num_contests: usize,
+        options_per_contest: usize,
+    },
+    #[cfg(feature = "net")]
+    Url(String),
 }
 
 impl ElectionManifestSource {
@@ -43,22 +61,79 @@ impl ElectionManifestSource {
             ElectionManifestSource::Example => {
                 return Ok(example_election_manifest()); //------- inner return
             }
+            ElectionManifestSource::ExampleSeeded {
+                seed,
+                num_contests,
+                options_per_contest,
+            } => {
+                return Ok(example_election_manifest_seeded(
+                    seed.as_bytes(),
+                    *num_contests,
+                    *options_per_contest,
+                )); //------- inner return
+            }
+            #[cfg(feature = "net")]
+            ElectionManifestSource::Url(url) => {
+                let election_manifest =
+                    fetch_url(url, "election manifest", MAX_MANIFEST_DOWNLOAD_BYTES)
+                        .and_then(|bytes| {
+                            parse_json_artifact(&mut bytes.as_slice(), "election manifest", |r| {
+                                ElectionManifest::from_stdioread_validated(r)
+                            })
+                        })
+                        .with_context(|| format!("Loading election manifest from: {url}"))?;
+
+                info!("Election manifest loaded from: {url}");
+
+                return Ok(election_manifest); //------- inner return
+            }
         };
 
         let (mut stdioread, actual_path) =
             artifacts_dir.in_file_stdioread(&opt_path, opt_artifact_file)?;
 
-        let election_manifest = ElectionManifest::from_stdioread_validated(&mut stdioread)
-            .with_context(|| {
-                format!("Loading election manifest from: {}", actual_path.display())
-            })?;
+        let election_manifest = parse_json_artifact(&mut stdioread, "election manifest", |r| {
+            ElectionManifest::from_stdioread_validated(r)
+        })
+        .with_context(|| format!("Loading election manifest from: {}", actual_path.display()))?;
 
-        eprintln!("Election manifest loaded from: {}", actual_path.display());
+        info!("Election manifest loaded from: {}", actual_path.display());
 
         Ok(election_manifest)
     }
 }
 
+/// The largest election manifest that will be downloaded.
+#[cfg(feature = "net")]
+const MAX_MANIFEST_DOWNLOAD_BYTES: u64 = 16 * 1024 * 1024;
+
+/// How long to wait for a download, including connecting and reading the whole body.
+#[cfg(feature = "net")]
+const DOWNLOAD_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(60);
+
+/// Downloads the body of an HTTPS URL, failing if it is longer than `max_bytes`.
+#[cfg(feature = "net")]
+fn fetch_url(url: &str, what: &str, max_bytes: u64) -> Result<Vec<u8>> {
+    ensure!(
+        url.get(..8)
+            .is_some_and(|scheme| scheme.eq_ignore_ascii_case("https://")),
+        "Refusing to download from non-HTTPS URL: {url}"
+    );
+
+    let agent = ureq::AgentBuilder::new()
+        .https_only(true)
+        .timeout(DOWNLOAD_TIMEOUT)
+        .build();
+
+    let response = agent
+        .get(url)
+        .call()
+        .with_context(|| format!("Downloading: {url}"))?;
+
+    eg::artifact_format::read_to_end_bounded(&mut response.into_reader(), what, max_bytes)
+        .with_context(|| format!("Downloading: {url}"))
+}
+
 pub(crate) fn load_election_parameters(
     artifacts_dir: &ArtifactsDir,
     csprng: &mut Csprng,
@@ -66,9 +141,17 @@ pub(crate) fn load_election_parameters(
     let (mut stdioread, path) =
         artifacts_dir.in_file_stdioread(&None, Some(ArtifactFile::ElectionParameters))?;
 
-    let election_parameters = ElectionParameters::from_stdioread_validated(&mut stdioread, csprng)?;
+    // Validation tests p and q for primality, which is by far the slowest part of loading.
+    let start = Instant::now();
+    let election_parameters = parse_json_artifact(&mut stdioread, "election parameters", |r| {
+        ElectionParameters::from_stdioread_validated(r, csprng)
+    })?;
+    debug!(
+        "Election parameters validated in {:.3}s",
+        start.elapsed().as_secs_f64()
+    );
 
-    eprintln!("Election parameters loaded from: {}", path.display());
+    info!("Election parameters loaded from: {}", path.display());
 
     Ok(election_parameters)
 }
@@ -89,8 +172,9 @@ pub(crate) fn load_guardian_secret_key(
         opt_i.map(ArtifactFile::GuardianSecretKey),
     )?;
 
-    let guardian_secret_key =
-        GuardianSecretKey::from_stdioread_validated(&mut stdioread, election_parameters)?;
+    let guardian_secret_key = parse_json_artifact(&mut stdioread, "guardian secret key", |r| {
+        GuardianSecretKey::from_stdioread_validated(r, election_parameters)
+    })?;
 
     if let Some(i) = opt_i {
         ensure!(i == guardian_secret_key.i,
@@ -101,14 +185,14 @@ pub(crate) fn load_guardian_secret_key(
     }
 
     if let Some(name) = &guardian_secret_key.opt_name {
-        eprintln!(
+        info!(
             "Secret key for guardian number {} {:?} loaded from: {}",
             guardian_secret_key.i,
             name,
             path.display()
         )
     } else {
-        eprintln!(
+        info!(
             "Secret key for guardian number {} loaded from: {}",
             guardian_secret_key.i,
             path.display()
@@ -134,8 +218,9 @@ pub(crate) fn load_guardian_public_key(
         opt_i.map(ArtifactFile::GuardianPublicKey),
     )?;
 
-    let guardian_public_key =
-        GuardianPublicKey::from_stdioread_validated(&mut stdioread, election_parameters)?;
+    let guardian_public_key = parse_json_artifact(&mut stdioread, "guardian public key", |r| {
+        GuardianPublicKey::from_stdioread_validated(r, election_parameters)
+    })?;
 
     if let Some(i) = opt_i {
         ensure!(i == guardian_public_key.i,
@@ -147,14 +232,14 @@ pub(crate) fn load_guardian_public_key(
     }
 
     if let Some(name) = &guardian_public_key.opt_name {
-        eprintln!(
+        info!(
             "Public key for guardian number {} {:?} loaded from: {}",
             guardian_public_key.i,
             name,
             path.display()
         )
     } else {
-        eprintln!(
+        info!(
             "Public key for guardian number {} loaded from: {}",
             guardian_public_key.i,
             path.display()
@@ -172,20 +257,68 @@ pub(crate) fn load_joint_election_public_key(
         artifacts_dir.in_file_stdioread(&None, Some(ArtifactFile::JointElectionPublicKey))?;
 
     let joint_election_public_key =
-        JointElectionPublicKey::from_stdioread_validated(&mut stdioread, election_parameters)?;
+        parse_json_artifact(&mut stdioread, "joint election public key", |r| {
+            JointElectionPublicKey::from_stdioread_validated(r, election_parameters)
+        })?;
 
-    eprintln!("Joint election public key loaded from: {}", path.display());
+    info!("Joint election public key loaded from: {}", path.display());
 
     Ok(joint_election_public_key)
 }
 
+/// Loads the record of the key ceremony's share exchange, validating that every guardian
+/// sent a share to every other guardian.
+pub(crate) fn load_share_distribution(
+    opt_path: &Option<PathBuf>,
+    artifacts_dir: &ArtifactsDir,
+    election_parameters: &ElectionParameters,
+) -> Result<ShareDistribution> {
+    let (mut stdioread, path) =
+        artifacts_dir.in_file_stdioread(opt_path, Some(ArtifactFile::ShareDistribution))?;
+
+    let share_distribution = parse_json_artifact(&mut stdioread, "share distribution", |r| {
+        ShareDistribution::from_stdioread_validated(r, election_parameters)
+    })?;
+
+    info!("Share distribution loaded from: {}", path.display());
+
+    Ok(share_distribution)
+}
+
 pub(crate) fn load_hashes(artifacts_dir: &ArtifactsDir) -> Result<Hashes> {
     let (mut stdioread, path) =
         artifacts_dir.in_file_stdioread(&None, Some(ArtifactFile::Hashes))?;
 
-    let hashes = Hashes::from_stdioread_validated(&mut stdioread)?;
+    let hashes = parse_json_artifact(&mut stdioread, "hashes", |r| {
+        Hashes::from_stdioread_validated(r)
+    })?;
+
+    info!("Hashes loaded from: {}", path.display());
 
-    eprintln!("Hashes loaded from: {}", path.display());
+    Ok(hashes)
+}
+
+/// Loads the hashes from the artifacts dir, checking that they are the ones computed from
+/// the specified parameters and manifest.
+///
+/// The hashes are published, and H_E is computed from them, so if either input has changed
+/// since they were written this is an error rather than something to fix up here. The
+/// artifact is never rewritten.
+pub(crate) fn load_hashes_current(
+    artifacts_dir: &ArtifactsDir,
+    election_parameters: &ElectionParameters,
+    election_manifest: &ElectionManifest,
+) -> Result<Hashes> {
+    let hashes = load_hashes(artifacts_dir)?;
+
+    hashes
+        .check_current(election_parameters, election_manifest)
+        .with_context(|| {
+            format!(
+                "The hashes in {} are out of date. Run write-hashes and write-hashes-ext again",
+                artifacts_dir.path(ArtifactFile::Hashes).display()
+            )
+        })?;
 
     Ok(hashes)
 }
@@ -194,13 +327,69 @@ pub(crate) fn load_hashes_ext(artifacts_dir: &ArtifactsDir) -> Result<HashesExt>
     let (mut stdioread, path) =
         artifacts_dir.in_file_stdioread(&None, Some(ArtifactFile::HashesExt))?;
 
-    let hashes = HashesExt::from_stdioread_validated(&mut stdioread)?;
+    let hashes = parse_json_artifact(&mut stdioread, "extended hashes", |r| {
+        HashesExt::from_stdioread_validated(r)
+    })?;
 
-    eprintln!("HashesExt loaded from: {}", path.display());
+    info!("HashesExt loaded from: {}", path.display());
 
     Ok(hashes)
 }
 
+/// Loads the encrypted tally, validating that it has the shape of the contests in the
+/// manifest and that its ciphertexts are valid under the election parameters.
+pub(crate) fn load_encrypted_tally(
+    opt_path: &Option<PathBuf>,
+    artifacts_dir: &ArtifactsDir,
+    election_parameters: &ElectionParameters,
+    election_manifest: &ElectionManifest,
+) -> Result<Tally> {
+    let (mut stdioread, path) =
+        artifacts_dir.in_file_stdioread(opt_path, Some(ArtifactFile::EncryptedTally))?;
+
+    let tally = parse_json_artifact(&mut stdioread, "encrypted tally", |r| {
+        Tally::from_stdioread_validated(r, &election_parameters.fixed_parameters, election_manifest)
+    })?;
+
+    info!(
+        "Encrypted tally of {} ballots loaded from: {}",
+        tally.ballot_count,
+        path.display()
+    );
+
+    Ok(tally)
+}
+
+/// Loads guardian `i`'s decryption shares of `tally`, verifying every share's proof against
+/// the guardian's public key share.
+pub(crate) fn load_decryption_share_set(
+    i: GuardianIndex,
+    artifacts_dir: &ArtifactsDir,
+    pvd: &PreVotingData,
+    guardian_public_key_share: &GuardianPublicKeyShare,
+    tally: &Tally,
+) -> Result<DecryptionShareSet> {
+    let (mut stdioread, path) =
+        artifacts_dir.in_file_stdioread(&None, Some(ArtifactFile::DecryptionShare(i)))?;
+
+    let decryption_share_set = parse_json_artifact(&mut stdioread, "decryption shares", |r| {
+        DecryptionShareSet::from_stdioread_validated(r, pvd, guardian_public_key_share, tally)
+    })?;
+    ensure!(
+        decryption_share_set.i == i,
+        "Decryption shares for guardian {i} are from guardian {}: {}",
+        decryption_share_set.i,
+        path.display()
+    );
+
+    info!(
+        "Decryption shares for guardian number {i} loaded from: {}",
+        path.display()
+    );
+
+    Ok(decryption_share_set)
+}
+
 /// Read the recommended amount of seed data from the OS RNG.
 ///
 /// `OsRng` is implemented by the `getrandom` crate, which describes itself as an "Interface to
@@ -232,3 +421,82 @@ pub(crate) fn load_all_guardian_public_keys(
 
     Ok(guardian_public_keys)
 }
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod test {
+    use super::*;
+    use eg::example_election::ExampleElection;
+
+    use crate::test_artifacts::write_example_election;
+
+    #[test]
+    fn test_load_hashes_current() {
+        let mut csprng = Csprng::new(b"test_load_hashes_current");
+
+        let example_election = ExampleElection::with_first_contests(&mut csprng, 1).unwrap();
+        let pvd = &example_election.pvd;
+
+        let dir = tempfile::tempdir().unwrap();
+        let artifacts_dir = ArtifactsDir::new(dir.path()).unwrap();
+        write_example_election(&artifacts_dir, &example_election);
+        let hashes_path = artifacts_dir.path(ArtifactFile::Hashes);
+        let hashes_bytes = std::fs::read(&hashes_path).unwrap();
+
+        let hashes = load_hashes_current(&artifacts_dir, &pvd.parameters, &pvd.manifest).unwrap();
+        assert!(hashes == pvd.hashes);
+
+        // Changing the manifest invalidates the published hashes, which are left as they are.
+        let mut amended_manifest = pvd.manifest.clone();
+        amended_manifest.label.push_str(" (amended)");
+        let err =
+            load_hashes_current(&artifacts_dir, &pvd.parameters, &amended_manifest).unwrap_err();
+        assert!(err.to_string().contains("out of date"), "{err:#}");
+        assert!(format!("{err:#}").contains("H_M"), "{err:#}");
+        assert_eq!(std::fs::read(&hashes_path).unwrap(), hashes_bytes);
+
+        // As does a missing file.
+        std::fs::remove_file(&hashes_path).unwrap();
+        load_hashes_current(&artifacts_dir, &pvd.parameters, &pvd.manifest).unwrap_err();
+        assert!(!hashes_path.exists());
+    }
+
+    #[cfg(feature = "net")]
+    #[test]
+    fn test_load_election_manifest_url() {
+        let dir = tempfile::tempdir().unwrap();
+        let artifacts_dir = ArtifactsDir::new(dir.path()).unwrap();
+        let load = |url: &str| {
+            ElectionManifestSource::Url(url.to_string())
+                .load_election_manifest(&artifacts_dir)
+                .unwrap_err()
+        };
+
+        // Refused before any connection is attempted.
+        for url in [
+            "http://example.com/election_manifest.json",
+            "ftp://example.com/election_manifest.json",
+            "example.com/election_manifest.json",
+            "https:/example.com",
+            "",
+        ] {
+            let err = load(url);
+            assert!(
+                format!("{err:#}").contains("Refusing to download from non-HTTPS URL"),
+                "{url}: {err:#}"
+            );
+        }
+
+        // The scheme isn't case sensitive. Nothing listens on port 1, so the download itself
+        // fails, and is reported as such.
+        let url = "HTTPS://127.0.0.1:1/election_manifest.json";
+        let err = load(url);
+        let err = format!("{err:#}");
+        assert!(!err.contains("Refusing"), "{err}");
+        assert!(
+            err.contains(&format!("Loading election manifest from: {url}")),
+            "{err}"
+        );
+        assert!(err.contains(&format!("Downloading: {url}")), "{err}");
+    }
+}
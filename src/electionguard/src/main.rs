@@ -10,11 +10,16 @@ mod clargs;
 mod common_utils;
 mod subcommand_helper;
 mod subcommands;
+#[cfg(test)]
+mod test_artifacts;
 
 //use std::path::PathBuf;
 
+use std::time::Instant;
+
 use anyhow::{ensure, Result};
 use clap::Parser;
+use tracing::debug;
 
 use artifacts_dir::{ArtifactFile, ArtifactsDir};
 use subcommand_helper::SubcommandHelper;
@@ -24,7 +29,10 @@ use crate::{clargs::Clargs, subcommands::Subcommand};
 fn main() -> Result<()> {
     let mut clargs = Clargs::parse();
 
-    let artifacts_dir = ArtifactsDir::new(&clargs.artifacts_dir)?;
+    init_tracing_subscriber(clargs.max_level());
+
+    let artifacts_dir =
+        ArtifactsDir::new_with_naming(&clargs.artifacts_dir, clargs.artifacts_naming.into())?;
 
     // Takes the `Subcommand` out of `clargs`, replacing it with the default `None`.
     // We need it for the `self` parameter to call `do_it()`.
@@ -38,8 +46,8 @@ fn main() -> Result<()> {
 
         //? TODO BUG TOCTOU
         ensure!(
-            no_seed_file || clargs.insecure_deterministic,
-            "Pseudorandom seed file ({}) exists, but the --insecure-deterministic command line argument was not specified",
+            no_seed_file || clargs.is_deterministic(),
+            "Pseudorandom seed file ({}) exists, but neither --insecure-deterministic nor --seed was specified",
             artifacts_dir
                 .path(ArtifactFile::PseudorandomSeedDefeatsAllSecrecy)
                 .display()
@@ -50,5 +58,20 @@ fn main() -> Result<()> {
     let mut subcommand_helper = SubcommandHelper::new(clargs, artifacts_dir, uses_csprng)?;
 
     // Perform the subcommand.
-    subcommand.do_it(&mut subcommand_helper)
+    let start = Instant::now();
+    let result = subcommand.do_it(&mut subcommand_helper);
+    debug!("Finished in {:.3}s", start.elapsed().as_secs_f64());
+
+    result
+}
+
+/// Reports messages on stderr as plain lines of text, as a person at a terminal would expect.
+fn init_tracing_subscriber(max_level: tracing::level_filters::LevelFilter) {
+    tracing_subscriber::fmt()
+        .with_writer(std::io::stderr)
+        .with_max_level(max_level)
+        .without_time()
+        .with_level(false)
+        .with_target(false)
+        .init();
 }
@@ -8,6 +8,7 @@
 use std::{fs::OpenOptions, io::Read};
 
 use anyhow::{bail, Result};
+use tracing::{debug, info};
 
 use util::csprng::Csprng;
 
@@ -42,7 +43,7 @@ impl SubcommandHelper {
         })
     }
 
-    /// Returns the csprng initialized from the entropy source or the seed file.
+    /// Returns the csprng initialized from the entropy source, the `--seed`, or the seed file.
     /// The csprng will be customized for the subcommand.
     /// But only once, ever, for this subcommand.
     /// We don't allow the Csprng to be initialized multiple times.
@@ -58,7 +59,15 @@ impl SubcommandHelper {
         self.csprng_initialized = true;
 
         let mut seed_data = Vec::new();
-        if self.clargs.insecure_deterministic {
+        if let Some(seed) = &self.clargs.seed {
+            seed_data.extend_from_slice(&seed.0);
+
+            // Not subject to `--quiet`.
+            eprintln!("!!! WARNING: Using INSECURE deterministic seed from --seed. !!!");
+            eprintln!(
+                "!!! Anyone with the seed can recover every secret. Not for real elections. !!!"
+            );
+        } else if self.clargs.insecure_deterministic {
             let (mut file, path) = self.artifacts_dir.open(
                 ArtifactFile::PseudorandomSeedDefeatsAllSecrecy,
                 OpenOptions::new().read(true),
@@ -66,8 +75,8 @@ impl SubcommandHelper {
 
             file.read_to_end(&mut seed_data)?;
 
-            eprintln!("!!! WARNING: Using INSECURE deterministic mode. !!!",);
-            eprintln!(
+            eprintln!("!!! WARNING: Using INSECURE deterministic mode. !!!");
+            info!(
                 "{} bytes of seed data read from: {}",
                 seed_data.len(),
                 path.display()
@@ -75,6 +84,8 @@ impl SubcommandHelper {
         } else {
             // Read true random bytes from the OS.
             seed_data.extend_from_slice(&osrng_seed_data_for_csprng());
+
+            debug!("Csprng seeded from the operating system's entropy source");
         };
 
         let mut seed = Vec::new();
@@ -87,3 +98,78 @@ impl SubcommandHelper {
         Ok(Csprng::new(&seed))
     }
 }
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod test {
+    use super::*;
+    use clap::Parser;
+    use eg::{example_election_parameters::example_election_parameters, guardian::GuardianIndex};
+
+    use crate::test_artifacts::{run_subcommand, write_artifact};
+
+    /// Runs `guardian-secret-key-generate` for guardian 1 with the specified command line
+    /// arguments, in a fresh artifacts dir, and returns the secret key file it wrote.
+    fn generate_key(args: &[&str]) -> Vec<u8> {
+        let dir = tempfile::tempdir().unwrap();
+        let artifacts_dir = ArtifactsDir::new(dir.path()).unwrap();
+        write_artifact(&artifacts_dir, ArtifactFile::ElectionParameters, |w| {
+            example_election_parameters().to_stdiowrite(w)
+        });
+
+        let i = GuardianIndex::from_one_based_index(1).unwrap();
+        let path = artifacts_dir.path(ArtifactFile::GuardianSecretKey(i));
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+
+        run_subcommand(dir.path(), args).unwrap();
+
+        std::fs::read(path).unwrap()
+    }
+
+    #[test]
+    fn test_seed() {
+        // Each run validates the election parameters, which is slow, so only three.
+        let key_1 = generate_key(&[
+            "--seed",
+            "00c0ffee",
+            "guardian-secret-key-generate",
+            "--i",
+            "1",
+        ]);
+        assert_ne!(
+            generate_key(&[
+                "--seed",
+                "00c0ffef",
+                "guardian-secret-key-generate",
+                "--i",
+                "1"
+            ]),
+            key_1
+        );
+
+        // `--seed` is global, so it may also follow the subcommand. Hex digits are not case
+        // sensitive.
+        assert_eq!(
+            generate_key(&[
+                "guardian-secret-key-generate",
+                "--i",
+                "1",
+                "--seed",
+                "00C0FFEE"
+            ]),
+            key_1
+        );
+
+        for bad_seed in ["", "abc", "0g"] {
+            assert!(Clargs::try_parse_from([
+                "electionguard",
+                "--artifacts-dir",
+                ".",
+                "--seed",
+                bad_seed,
+                "write-random-seed",
+            ])
+            .is_err());
+        }
+    }
+}
@@ -0,0 +1,289 @@
+// Copyright (C) Microsoft Corporation. All rights reserved.
+
+#![deny(clippy::unwrap_used)]
+#![deny(clippy::expect_used)]
+#![deny(clippy::panic)]
+#![deny(clippy::manual_assert)]
+
+use std::path::PathBuf;
+
+use anyhow::{bail, Context, Result};
+use tracing::info;
+
+use eg::{
+    election_record::PreVotingData,
+    guardian::GuardianIndex,
+    guardian_key_share::GuardianPublicKeyShare,
+    tally::{DecryptedTally, DecryptionShareSet, Tally},
+};
+
+use crate::{
+    artifacts_dir::ArtifactFile,
+    common_utils::{
+        load_all_guardian_public_keys, load_decryption_share_set, load_election_parameters,
+        load_encrypted_tally, load_hashes_current, load_hashes_ext, load_joint_election_public_key,
+        ElectionManifestSource,
+    },
+    subcommand_helper::SubcommandHelper,
+    subcommands::Subcommand,
+};
+
+/// Combine the guardians' decryption shares of the encrypted tally, and write the decrypted
+/// tally.
+#[derive(clap::Args, Debug, Default)]
+pub(crate) struct CombineDecryptionShares {
+    /// File containing the encrypted tally.
+    /// Default is the encrypted tally file in the artifacts dir.
+    #[arg(long)]
+    tally_in: Option<PathBuf>,
+
+    /// File to which to write the decrypted tally.
+    /// Default is in the artifacts dir.
+    /// If "-", write to stdout.
+    #[arg(long)]
+    out_file: Option<PathBuf>,
+}
+
+impl Subcommand for CombineDecryptionShares {
+    fn uses_csprng(&self) -> bool {
+        true
+    }
+
+    fn do_it(&mut self, subcommand_helper: &mut SubcommandHelper) -> Result<()> {
+        let mut csprng = subcommand_helper.get_csprng(b"CombineDecryptionShares")?;
+
+        let artifacts_dir = &subcommand_helper.artifacts_dir;
+
+        let election_parameters = load_election_parameters(artifacts_dir, &mut csprng)?;
+        let election_manifest = ElectionManifestSource::ArtifactFileElectionManifestCanonical
+            .load_election_manifest(artifacts_dir)?;
+        let hashes = load_hashes_current(artifacts_dir, &election_parameters, &election_manifest)?;
+        let hashes_ext = load_hashes_ext(artifacts_dir)?;
+        let jepk = load_joint_election_public_key(artifacts_dir, &election_parameters)?;
+        let guardian_public_keys =
+            load_all_guardian_public_keys(artifacts_dir, &election_parameters)?;
+
+        let tally = load_encrypted_tally(
+            &self.tally_in,
+            artifacts_dir,
+            &election_parameters,
+            &election_manifest,
+        )?;
+
+        let pvd = PreVotingData::new(
+            election_manifest,
+            election_parameters,
+            hashes,
+            hashes_ext,
+            jepk,
+        );
+
+        let public_key_shares =
+            GuardianPublicKeyShare::compute_all(&pvd.parameters, &guardian_public_keys)?;
+
+        let mut share_sets = Vec::new();
+        for public_key_share in &public_key_shares {
+            let i = public_key_share.i;
+            if artifacts_dir.exists(ArtifactFile::DecryptionShare(i)) {
+                share_sets.push(load_decryption_share_set(
+                    i,
+                    artifacts_dir,
+                    &pvd,
+                    public_key_share,
+                    &tally,
+                )?);
+            }
+        }
+
+        let decrypted_tally = combine_decryption_share_sets(&pvd, &tally, &share_sets)?;
+
+        let (mut stdiowrite, path) = artifacts_dir
+            .out_file_stdiowrite(&self.out_file, Some(ArtifactFile::DecryptedTally))?;
+
+        decrypted_tally
+            .to_stdiowrite(stdiowrite.as_mut())
+            .with_context(|| format!("Writing decrypted tally to: {}", path.display()))?;
+
+        drop(stdiowrite);
+
+        info!("Wrote decrypted tally to: {}", path.display());
+
+        // Unless the decrypted tally itself went to stdout.
+        if self.out_file != Some(PathBuf::from("-")) {
+            print!("{}", decrypted_tally.summary(&pvd.manifest)?);
+        }
+
+        Ok(())
+    }
+}
+
+/// Decrypts `tally` with the decryption share sets of the guardians who provided them.
+///
+/// Any `k` or more of the `n` guardians suffice. Errors, naming the guardians whose shares
+/// are missing, if fewer than `k` are present.
+fn combine_decryption_share_sets(
+    pvd: &PreVotingData,
+    tally: &Tally,
+    share_sets: &[DecryptionShareSet],
+) -> Result<DecryptedTally> {
+    let varying_parameters = &pvd.parameters.varying_parameters;
+
+    let missing: Vec<GuardianIndex> = varying_parameters
+        .each_guardian_i()
+        .filter(|&i| !share_sets.iter().any(|share_set| share_set.i == i))
+        .collect();
+
+    let cnt_present = varying_parameters.n.as_quantity() - missing.len();
+    if cnt_present < varying_parameters.k.as_quantity() {
+        bail!(
+            "Decryption requires the shares of at least k={} of the n={} guardians, but only {cnt_present} are present. Missing: {}",
+            varying_parameters.k,
+            varying_parameters.n,
+            guardian_list(&missing)
+        );
+    }
+
+    tally.decrypt(pvd, share_sets)
+}
+
+/// Lists the guardians, e.g. "guardians 2, 5".
+fn guardian_list(guardians: &[GuardianIndex]) -> String {
+    let numbers: Vec<String> = guardians.iter().map(|i| i.to_string()).collect();
+    match numbers.len() {
+        1 => format!("guardian {}", numbers[0]),
+        _ => format!("guardians {}", numbers.join(", ")),
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod test {
+    use super::*;
+    use eg::{example_election::ExampleElection, index::Index};
+    use util::csprng::Csprng;
+
+    use crate::{
+        artifacts_dir::ArtifactsDir,
+        test_artifacts::{run_subcommand, write_artifact, write_example_election},
+    };
+
+    /// Encrypts and tallies three ballots for the first contest of `example_election`'s
+    /// manifest, one for the first option and two for the second.
+    fn example_tally(csprng: &mut Csprng, example_election: &ExampleElection) -> Tally {
+        let ballots = example_election
+            .encrypt_ballots(
+                csprng,
+                &[
+                    r#"{ "contests": [ { "vote": [1, 0] } ] }"#,
+                    r#"{ "contests": [ { "vote": [0, 1] } ] }"#,
+                    r#"{ "contests": [ { "vote": [0, 1] } ] }"#,
+                ],
+            )
+            .unwrap();
+        let pvd = &example_election.pvd;
+        Tally::from_ballots(&pvd.parameters.fixed_parameters, &pvd.manifest, &ballots).unwrap()
+    }
+
+    #[test]
+    fn test_combine_decryption_share_sets() {
+        let mut csprng = Csprng::new(b"test_combine_decryption_share_sets");
+
+        let example_election = ExampleElection::with_first_contests(&mut csprng, 1).unwrap();
+
+        // Encrypt, tally, share, and combine, as the subcommands do in turn.
+        let tally = example_tally(&mut csprng, &example_election);
+        let ExampleElection {
+            guardian_key_shares,
+            pvd,
+            ..
+        } = example_election;
+
+        let share_sets = guardian_key_shares
+            .iter()
+            .map(|key_share| DecryptionShareSet::compute(&mut csprng, &pvd, key_share, &tally))
+            .collect::<Vec<_>>();
+
+        let decrypted_tally = combine_decryption_share_sets(&pvd, &tally, &share_sets).unwrap();
+        assert_eq!(decrypted_tally.ballot_count, 3);
+        let contest_result = decrypted_tally
+            .contests
+            .get(Index::from_one_based_index(1).unwrap())
+            .unwrap();
+        assert_eq!(contest_result.option_totals, [1, 2]);
+
+        // The order in which the shares are given doesn't matter.
+        let reversed = share_sets.iter().rev().cloned().collect::<Vec<_>>();
+        assert_eq!(
+            combine_decryption_share_sets(&pvd, &tally, &reversed).unwrap(),
+            decrypted_tally
+        );
+
+        // The example election has n=5 and k=3. With two shares missing, there is still a
+        // quorum.
+        assert_eq!(
+            combine_decryption_share_sets(&pvd, &tally, &share_sets[..3]).unwrap(),
+            decrypted_tally
+        );
+        assert_eq!(
+            combine_decryption_share_sets(&pvd, &tally, &share_sets[1..4]).unwrap(),
+            decrypted_tally
+        );
+
+        // With three missing, there is no quorum.
+        let err = combine_decryption_share_sets(&pvd, &tally, &share_sets[1..3]).unwrap_err();
+        assert!(err.to_string().contains("at least k=3"), "{err:#}");
+        assert!(
+            err.to_string().contains("Missing: guardians 1, 4, 5"),
+            "{err:#}"
+        );
+    }
+
+    #[test]
+    fn test_combine_decryption_shares_quorum() {
+        let mut csprng = Csprng::new(b"test_combine_decryption_shares_quorum");
+
+        let example_election = ExampleElection::with_first_contests(&mut csprng, 1).unwrap();
+        let pvd = &example_election.pvd;
+        let tally = example_tally(&mut csprng, &example_election);
+
+        let dir = tempfile::tempdir().unwrap();
+        let artifacts_dir = ArtifactsDir::new(dir.path()).unwrap();
+        write_example_election(&artifacts_dir, &example_election);
+        write_artifact(&artifacts_dir, ArtifactFile::EncryptedTally, |w| {
+            tally.to_stdiowrite(w)
+        });
+
+        // Only guardians 1, 3, and 5 of the n=5 share, which is exactly k=3.
+        for key_share in example_election.guardian_key_shares.iter().step_by(2) {
+            let share_set = DecryptionShareSet::compute(&mut csprng, pvd, key_share, &tally);
+            write_artifact(
+                &artifacts_dir,
+                ArtifactFile::DecryptionShare(key_share.i),
+                |w| share_set.to_stdiowrite(w),
+            );
+        }
+
+        run_subcommand(dir.path(), &["--seed", "01", "combine-decryption-shares"]).unwrap();
+
+        let decrypted_tally = DecryptedTally::from_stdioread(
+            &mut std::fs::File::open(artifacts_dir.path(ArtifactFile::DecryptedTally)).unwrap(),
+        )
+        .unwrap();
+        assert_eq!(decrypted_tally.ballot_count, 3);
+        let contest_result = decrypted_tally
+            .contests
+            .get(Index::from_one_based_index(1).unwrap())
+            .unwrap();
+        assert_eq!(contest_result.option_totals, [1, 2]);
+
+        // Without guardian 3's shares, there is no longer a quorum.
+        let i_3 = GuardianIndex::from_one_based_index(3).unwrap();
+        std::fs::remove_file(artifacts_dir.path(ArtifactFile::DecryptionShare(i_3))).unwrap();
+        let err =
+            run_subcommand(dir.path(), &["--seed", "01", "combine-decryption-shares"]).unwrap_err();
+        assert!(
+            err.to_string().contains("Missing: guardians 2, 3, 4"),
+            "{err:#}"
+        );
+    }
+}
@@ -0,0 +1,251 @@
+// Copyright (C) Microsoft Corporation. All rights reserved.
+
+#![deny(clippy::unwrap_used)]
+#![deny(clippy::expect_used)]
+#![deny(clippy::panic)]
+#![deny(clippy::manual_assert)]
+
+use std::path::PathBuf;
+
+use anyhow::{bail, Context, Result};
+use tracing::info;
+
+use eg::{
+    election_record::PreVotingData,
+    guardian::GuardianIndex,
+    guardian_key_share::GuardianKeyShare,
+    tally::{DecryptionShareSet, Tally},
+};
+use util::csprng::Csprng;
+
+use crate::{
+    artifacts_dir::ArtifactFile,
+    common_utils::{
+        load_all_guardian_public_keys, load_election_parameters, load_encrypted_tally,
+        load_guardian_secret_key, load_hashes_current, load_hashes_ext,
+        load_joint_election_public_key, load_share_distribution, ElectionManifestSource,
+    },
+    subcommand_helper::SubcommandHelper,
+    subcommands::Subcommand,
+};
+
+/// Compute a guardian's decryption shares of the encrypted tally, with proofs, and write them
+/// to a file.
+///
+/// The shares are computed with the guardian's key share, which it assembles from its secret
+/// key and the shares the other guardians sent it in the share distribution. So the shares
+/// of any `k` guardians suffice to decrypt.
+#[derive(clap::Args, Debug, Default)]
+pub(crate) struct ComputeDecryptionShare {
+    /// Guardian number, 1 <= i <= n.
+    #[arg(long)]
+    i: Option<GuardianIndex>,
+
+    /// File containing the guardian's secret key.
+    /// Default is to look in the artifacts dir, if --i is provided.
+    #[arg(long)]
+    secret_key_in: Option<PathBuf>,
+
+    /// File containing the share distribution from the key ceremony.
+    /// Default is the share distribution file in the artifacts dir.
+    #[arg(long)]
+    share_distribution_in: Option<PathBuf>,
+
+    /// File containing the encrypted tally.
+    /// Default is the encrypted tally file in the artifacts dir.
+    #[arg(long)]
+    tally_in: Option<PathBuf>,
+
+    /// File to which to write the decryption shares.
+    /// Default is in the artifacts dir, based on the guardian number from the secret key file.
+    /// If "-", write to stdout.
+    #[arg(long)]
+    out_file: Option<PathBuf>,
+}
+
+impl Subcommand for ComputeDecryptionShare {
+    fn uses_csprng(&self) -> bool {
+        true
+    }
+
+    fn do_it(&mut self, subcommand_helper: &mut SubcommandHelper) -> Result<()> {
+        let mut csprng = subcommand_helper
+            .get_csprng(format!("ComputeDecryptionShare({:?})", self.i).as_bytes())?;
+
+        if self.secret_key_in.is_none() && self.i.is_none() {
+            bail!("Specify at least one of --i or --secret-key-in");
+        }
+
+        let artifacts_dir = &subcommand_helper.artifacts_dir;
+
+        let election_parameters = load_election_parameters(artifacts_dir, &mut csprng)?;
+        let election_manifest = ElectionManifestSource::ArtifactFileElectionManifestCanonical
+            .load_election_manifest(artifacts_dir)?;
+        let hashes = load_hashes_current(artifacts_dir, &election_parameters, &election_manifest)?;
+        let hashes_ext = load_hashes_ext(artifacts_dir)?;
+        let jepk = load_joint_election_public_key(artifacts_dir, &election_parameters)?;
+        let guardian_public_keys =
+            load_all_guardian_public_keys(artifacts_dir, &election_parameters)?;
+
+        let guardian_secret_key = load_guardian_secret_key(
+            self.i,
+            &self.secret_key_in,
+            artifacts_dir,
+            &election_parameters,
+        )?;
+        guardian_secret_key
+            .i
+            .to_zero_based(election_parameters.varying_parameters.n)
+            .context("Computing decryption shares")?;
+
+        let share_distribution = load_share_distribution(
+            &self.share_distribution_in,
+            artifacts_dir,
+            &election_parameters,
+        )?;
+
+        let guardian_key_share = GuardianKeyShare::compute(
+            &election_parameters,
+            &guardian_secret_key,
+            &guardian_public_keys,
+            &share_distribution,
+        )
+        .with_context(|| {
+            format!(
+                "Computing the key share of guardian {}",
+                guardian_secret_key.i
+            )
+        })?;
+
+        let tally = load_encrypted_tally(
+            &self.tally_in,
+            artifacts_dir,
+            &election_parameters,
+            &election_manifest,
+        )?;
+
+        let pre_voting_data = PreVotingData::new(
+            election_manifest,
+            election_parameters,
+            hashes,
+            hashes_ext,
+            jepk,
+        );
+
+        let decryption_share_set = compute_decryption_share_set(
+            &mut csprng,
+            &pre_voting_data,
+            &guardian_key_share,
+            &tally,
+        )?;
+
+        let i = decryption_share_set.i;
+
+        let (mut stdiowrite, path) = artifacts_dir
+            .out_file_stdiowrite(&self.out_file, Some(ArtifactFile::DecryptionShare(i)))?;
+
+        decryption_share_set
+            .to_stdiowrite(stdiowrite.as_mut())
+            .with_context(|| {
+                format!(
+                    "Writing decryption shares for guardian {i} to: {}",
+                    path.display()
+                )
+            })?;
+
+        drop(stdiowrite);
+
+        info!(
+            "Wrote decryption shares for guardian {i} to: {}",
+            path.display()
+        );
+
+        Ok(())
+    }
+}
+
+/// Computes the guardian's decryption shares of every contest of `tally`.
+///
+/// Refuses a key share whose guardian number is not one of the `1..=n` guardians of the
+/// election, since nobody could use its shares.
+fn compute_decryption_share_set(
+    csprng: &mut Csprng,
+    pvd: &PreVotingData,
+    guardian_key_share: &GuardianKeyShare,
+    tally: &Tally,
+) -> Result<DecryptionShareSet> {
+    guardian_key_share
+        .i
+        .to_zero_based(pvd.parameters.varying_parameters.n)
+        .context("Computing decryption shares")?;
+
+    tally.validate(&pvd.parameters.fixed_parameters, &pvd.manifest)?;
+
+    Ok(DecryptionShareSet::compute(
+        csprng,
+        pvd,
+        guardian_key_share,
+        tally,
+    ))
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod test {
+    use super::*;
+    use eg::{example_election::ExampleElection, index::Index};
+
+    #[test]
+    fn test_compute_decryption_share_set() {
+        let mut csprng = Csprng::new(b"test_compute_decryption_share_set");
+
+        let example_election = ExampleElection::with_first_contests(&mut csprng, 1).unwrap();
+        let ballots = example_election
+            .encrypt_ballots(
+                &mut csprng,
+                &[
+                    r#"{ "contests": [ { "vote": [1, 0] } ] }"#,
+                    r#"{ "contests": [ { "vote": [0, 1] } ] }"#,
+                ],
+            )
+            .unwrap();
+        let ExampleElection {
+            guardian_key_shares,
+            pvd,
+            ..
+        } = example_election;
+        let tally =
+            Tally::from_ballots(&pvd.parameters.fixed_parameters, &pvd.manifest, &ballots).unwrap();
+
+        for key_share in &guardian_key_shares {
+            let decryption_share_set =
+                compute_decryption_share_set(&mut csprng, &pvd, key_share, &tally).unwrap();
+            assert_eq!(decryption_share_set.i, key_share.i);
+            decryption_share_set
+                .validate(&pvd, key_share.public_key_share(), &tally)
+                .unwrap();
+        }
+
+        // A key of a guardian which is not in the election.
+        let mut pvd_fewer_guardians = pvd.clone();
+        pvd_fewer_guardians.parameters.varying_parameters.n =
+            GuardianIndex::from_one_based_index(1).unwrap();
+        let last_key_share = guardian_key_shares.last().unwrap();
+        let err =
+            compute_decryption_share_set(&mut csprng, &pvd_fewer_guardians, last_key_share, &tally)
+                .unwrap_err();
+        assert!(format!("{err:#}").contains("not in the range"), "{err:#}");
+
+        // A tally of a different election.
+        let mut other_manifest = pvd.manifest.clone();
+        other_manifest
+            .contests
+            .get_mut(Index::from_one_based_index(1).unwrap())
+            .unwrap()
+            .selection_limit = 2;
+        let other_tally = Tally::new(&other_manifest);
+        assert!(
+            compute_decryption_share_set(&mut csprng, &pvd, last_key_share, &other_tally).is_err()
+        );
+    }
+}
@@ -0,0 +1,195 @@
+// Copyright (C) Microsoft Corporation. All rights reserved.
+
+#![deny(clippy::unwrap_used)]
+#![deny(clippy::expect_used)]
+#![deny(clippy::panic)]
+#![deny(clippy::manual_assert)]
+
+use std::path::PathBuf;
+
+use anyhow::{ensure, Context, Result};
+
+use eg::{
+    artifact_format::parse_json_artifact, election_parameters::ElectionParameters,
+    guardian::GuardianIndex, guardian_public_key::GuardianPublicKey,
+    guardian_public_key_set::GuardianPublicKeySet,
+};
+
+use crate::{
+    artifacts_dir::{ArtifactFile, ArtifactsDir},
+    common_utils::load_election_parameters,
+    subcommand_helper::SubcommandHelper,
+    subcommands::Subcommand,
+};
+
+/// Compares the guardian public keys in two directories, such as the copies of the key
+/// ceremony artifacts held by two participants.
+///
+/// It is an error if any guardian has different keys in the two directories. A key which is
+/// in only one of them is reported, but is not an error. The keys are not validated, so that
+/// a malformed copy can still be compared.
+#[derive(clap::Args, Debug)]
+pub(crate) struct DiffKeys {
+    /// First directory of guardian public keys, named as in the artifacts dir.
+    #[arg(long)]
+    dir_a: PathBuf,
+
+    /// Second directory of guardian public keys.
+    #[arg(long)]
+    dir_b: PathBuf,
+}
+
+/// Loads the public key of every guardian which has one in `dir_path`, without validating
+/// them.
+fn load_key_set(
+    dir_path: &PathBuf,
+    election_parameters: &ElectionParameters,
+) -> Result<GuardianPublicKeySet> {
+    let artifacts_dir = ArtifactsDir::new(dir_path)?;
+
+    let mut guardian_public_keys = Vec::new();
+    for i in election_parameters.varying_parameters.each_guardian_i() {
+        let artifact_file = ArtifactFile::GuardianPublicKey(i);
+        if !artifacts_dir.exists(artifact_file) {
+            continue;
+        }
+
+        let (mut stdioread, path) = artifacts_dir.in_file_stdioread(&None, Some(artifact_file))?;
+        let guardian_public_key = parse_json_artifact(
+            &mut stdioread,
+            "guardian public key",
+            GuardianPublicKey::from_stdioread,
+        )
+        .with_context(|| format!("Loading: {}", path.display()))?;
+        ensure!(
+            guardian_public_key.i == i,
+            "The public key for guardian {} is in the file for guardian {i}: {}",
+            guardian_public_key.i,
+            path.display()
+        );
+
+        guardian_public_keys.push(guardian_public_key);
+    }
+
+    GuardianPublicKeySet::new(guardian_public_keys)
+}
+
+fn format_indices(indices: &[GuardianIndex]) -> String {
+    if indices.is_empty() {
+        return "none".to_string();
+    }
+    indices
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+impl Subcommand for DiffKeys {
+    fn uses_csprng(&self) -> bool {
+        true
+    }
+
+    fn do_it(&mut self, subcommand_helper: &mut SubcommandHelper) -> Result<()> {
+        let mut csprng = subcommand_helper.get_csprng(b"DiffKeys")?;
+
+        let election_parameters =
+            load_election_parameters(&subcommand_helper.artifacts_dir, &mut csprng)?;
+
+        let key_set_a = load_key_set(&self.dir_a, &election_parameters)?;
+        let key_set_b = load_key_set(&self.dir_b, &election_parameters)?;
+
+        let diff = key_set_a.diff(&key_set_b);
+
+        println!(
+            "Only in {}: {}",
+            self.dir_a.display(),
+            format_indices(&diff.only_in_a)
+        );
+        println!(
+            "Only in {}: {}",
+            self.dir_b.display(),
+            format_indices(&diff.only_in_b)
+        );
+        println!("Differing: {}", format_indices(&diff.differing));
+        println!("Matching: {}", format_indices(&diff.matching));
+
+        ensure!(
+            diff.differing.is_empty(),
+            "Guardians with differing public keys: {}",
+            format_indices(&diff.differing)
+        );
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod test {
+    use super::*;
+    use eg::{
+        example_election::ExampleElection, example_election_parameters::example_election_parameters,
+    };
+    use util::csprng::Csprng;
+
+    use crate::test_artifacts::{run_subcommand, write_artifact};
+
+    #[test]
+    fn test_diff_keys() {
+        let mut csprng = Csprng::new(b"test_diff_keys");
+
+        let election_parameters = example_election_parameters();
+        let (_, mut guardian_public_keys) =
+            ExampleElection::generate_guardian_keys(&mut csprng, &election_parameters).unwrap();
+
+        let dir = tempfile::tempdir().unwrap();
+        let artifacts_dir = ArtifactsDir::new(dir.path()).unwrap();
+        write_artifact(&artifacts_dir, ArtifactFile::ElectionParameters, |w| {
+            election_parameters.to_stdiowrite(w)
+        });
+
+        let write_keys = |name: &str, keys: &[GuardianPublicKey]| {
+            let key_dir = dir.path().join(name);
+            std::fs::create_dir(&key_dir).unwrap();
+            let key_artifacts_dir = ArtifactsDir::new(&key_dir).unwrap();
+            for key in keys {
+                write_artifact(
+                    &key_artifacts_dir,
+                    ArtifactFile::GuardianPublicKey(key.i),
+                    |w| key.to_stdiowrite(w),
+                );
+            }
+            key_dir.to_str().unwrap().to_string()
+        };
+
+        // Guardian 1 is only in a and guardian 5 is only in b, which is not an error.
+        let dir_a = write_keys("a", &guardian_public_keys[..4]);
+        let dir_b = write_keys("b", &guardian_public_keys[1..]);
+        let diff_keys = |dir_b: &str| {
+            run_subcommand(
+                dir.path(),
+                &[
+                    "--seed",
+                    "01",
+                    "diff-keys",
+                    "--dir-a",
+                    &dir_a,
+                    "--dir-b",
+                    dir_b,
+                ],
+            )
+        };
+        diff_keys(&dir_b).unwrap();
+
+        // Guardian 3's key in c is malformed, with too few commitments, but is still loaded
+        // and compared.
+        guardian_public_keys[2].coefficient_commitments.0.pop();
+        assert!(guardian_public_keys[2]
+            .validate(&election_parameters)
+            .is_err());
+        let dir_c = write_keys("c", &guardian_public_keys[1..]);
+        let err = diff_keys(&dir_c).unwrap_err();
+        assert_eq!(err.to_string(), "Guardians with differing public keys: 3");
+    }
+}
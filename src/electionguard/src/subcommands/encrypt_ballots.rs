@@ -0,0 +1,296 @@
+// Copyright (C) Microsoft Corporation. All rights reserved.
+
+#![deny(clippy::unwrap_used)]
+#![deny(clippy::expect_used)]
+#![deny(clippy::panic)]
+#![deny(clippy::manual_assert)]
+
+use std::{
+    fs::File,
+    path::{Path, PathBuf},
+    time::{Instant, SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::{bail, Context, Result};
+use tracing::{info, warn};
+
+use eg::{
+    artifact_format::parse_json_artifact,
+    ballot::BallotEncrypted,
+    ballot_encryptor::{BallotEncryptor, BallotPlaintext},
+    device::Device,
+    election_record::PreVotingData,
+};
+use util::{csprng::Csprng, file::create_path};
+
+use crate::{
+    artifacts_dir::ArtifactFile,
+    common_utils::{
+        load_election_parameters, load_hashes_current, load_hashes_ext,
+        load_joint_election_public_key, ElectionManifestSource,
+    },
+    subcommand_helper::SubcommandHelper,
+    subcommands::Subcommand,
+};
+
+/// Encrypt every plaintext ballot file in a directory, and write the closing code of the
+/// chain of their confirmation codes.
+///
+/// The ballots are written as cast, so that `tally-ballots` counts them. Their nonces are not
+/// kept, so none of them could be challenged.
+#[derive(clap::Args, Debug)]
+pub(crate) struct EncryptBallots {
+    /// Directory of plaintext ballot JSON files. Every `*.json` file is encrypted, in order of
+    /// file name.
+    #[arg(long)]
+    in_dir: PathBuf,
+
+    /// Identifier of the encrypting device, which is recorded in each ballot and starts the
+    /// ballot chain.
+    #[arg(long, default_value = "EncryptBallots")]
+    device: String,
+
+    /// Stop at the first ballot which cannot be read or encrypted. By default, such ballots
+    /// are reported and skipped.
+    #[arg(long)]
+    stop_on_error: bool,
+}
+
+impl Subcommand for EncryptBallots {
+    fn uses_csprng(&self) -> bool {
+        true
+    }
+
+    fn do_it(&mut self, subcommand_helper: &mut SubcommandHelper) -> Result<()> {
+        let mut csprng = subcommand_helper.get_csprng(b"EncryptBallots")?;
+
+        let artifacts_dir = &subcommand_helper.artifacts_dir;
+
+        let election_parameters = load_election_parameters(artifacts_dir, &mut csprng)?;
+        let election_manifest = ElectionManifestSource::ArtifactFileElectionManifestCanonical
+            .load_election_manifest(artifacts_dir)?;
+        let hashes = load_hashes_current(artifacts_dir, &election_parameters, &election_manifest)?;
+        let hashes_ext = load_hashes_ext(artifacts_dir)?;
+        let jepk = load_joint_election_public_key(artifacts_dir, &election_parameters)?;
+
+        let pre_voting_data = PreVotingData::new(
+            election_manifest,
+            election_parameters,
+            hashes,
+            hashes_ext,
+            jepk,
+        );
+        let device = Device::new(&self.device, pre_voting_data);
+
+        let paths = plaintext_ballot_paths(&self.in_dir)?;
+
+        let label: u128 = SystemTime::now()
+            .duration_since(UNIX_EPOCH)?
+            .as_secs()
+            .into();
+        create_path(
+            &artifacts_dir
+                .dir_path
+                .join(format!("record/ballots/{label}")),
+        );
+
+        let mut encryptor = BallotEncryptor::new(&device);
+        let mut cnt_failed = 0_usize;
+        let start = Instant::now();
+
+        for path in &paths {
+            let result =
+                encrypt_ballot_file(&mut encryptor, &mut csprng, path).and_then(|ballot| {
+                    let (mut stdiowrite, out_path) = artifacts_dir.out_file_stdiowrite(
+                        &None,
+                        Some(ArtifactFile::EncryptedBallot(label, ballot.ballot_id())),
+                    )?;
+                    ballot.to_stdiowrite(stdiowrite.as_mut()).with_context(|| {
+                        format!("Writing encrypted ballot to: {}", out_path.display())
+                    })
+                });
+
+            if let Err(e) = result {
+                let e = e.context(format!("Encrypting ballot: {}", path.display()));
+                if self.stop_on_error {
+                    return Err(e);
+                }
+                warn!("{e:#}");
+                cnt_failed += 1;
+            }
+        }
+
+        let elapsed = start.elapsed().as_secs_f64();
+        let chain = encryptor.chain();
+        let cnt_encrypted = chain.ballot_count();
+
+        info!(
+            "Encrypted {cnt_encrypted} of {} ballots in {elapsed:.1}s ({:.2} ballots/sec).",
+            paths.len(),
+            if 0.0 < elapsed {
+                cnt_encrypted as f64 / elapsed
+            } else {
+                0.0
+            }
+        );
+        if 0 < cnt_failed {
+            warn!("{cnt_failed} ballots failed and were skipped.");
+        }
+
+        let closing = chain.closing();
+        let (mut stdiowrite, path) = artifacts_dir
+            .out_file_stdiowrite(&None, Some(ArtifactFile::BallotChainClosing(label)))?;
+        closing
+            .to_stdiowrite(stdiowrite.as_mut())
+            .with_context(|| format!("Writing ballot chain closing to: {}", path.display()))?;
+        drop(stdiowrite);
+        info!("Wrote ballot chain closing to: {}", path.display());
+
+        println!("Closing code: {}", closing.closing_code);
+
+        Ok(())
+    }
+}
+
+/// Returns the paths of the `*.json` files in the directory, sorted by file name.
+fn plaintext_ballot_paths(dir: &Path) -> Result<Vec<PathBuf>> {
+    let mut paths = Vec::new();
+    for entry in
+        std::fs::read_dir(dir).with_context(|| format!("Reading directory: {}", dir.display()))?
+    {
+        let path = entry?.path();
+        if path.is_file() && path.extension().is_some_and(|ext| ext == "json") {
+            paths.push(path);
+        }
+    }
+
+    if paths.is_empty() {
+        bail!("No plaintext ballot files found in: {}", dir.display());
+    }
+
+    paths.sort();
+    Ok(paths)
+}
+
+fn encrypt_ballot_file(
+    encryptor: &mut BallotEncryptor,
+    csprng: &mut Csprng,
+    path: &Path,
+) -> Result<BallotEncrypted> {
+    let mut file = File::open(path).with_context(|| format!("Opening: {}", path.display()))?;
+    let ballot_plaintext = parse_json_artifact(&mut file, "plaintext ballot", |r| {
+        BallotPlaintext::from_stdioread(r)
+    })
+    .with_context(|| format!("Reading: {}", path.display()))?;
+    let mut ballot = encryptor.encrypt(csprng, &ballot_plaintext)?;
+    ballot.cast()?;
+    Ok(ballot)
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod test {
+    use super::*;
+    use eg::{
+        ballot::BallotState,
+        ballot_chain::{BallotChain, BallotChainClosing},
+        example_election::ExampleElection,
+        verification::verify_ballot,
+    };
+
+    use crate::{
+        artifacts_dir::ArtifactsDir,
+        test_artifacts::{run_subcommand, write_example_election},
+    };
+
+    #[test]
+    fn test_encrypt_ballots() {
+        let mut csprng = Csprng::new(b"test_encrypt_ballots");
+
+        let example_election = ExampleElection::with_first_contests(&mut csprng, 1).unwrap();
+        let pvd = &example_election.pvd;
+
+        let dir = tempfile::tempdir().unwrap();
+        let artifacts_dir = ArtifactsDir::new(dir.path()).unwrap();
+        write_example_election(&artifacts_dir, &example_election);
+
+        let in_dir = tempfile::tempdir().unwrap();
+        for (name, json) in [
+            ("1.json", r#"{ "contests": [ { "vote": [1, 0] } ] }"#),
+            ("2.json", r#"{ "contests": [ { "vote": [1, 1, 0] } ] }"#),
+            ("3.json", r#"{ "contests": [ { "vote": [0, 1] } ] }"#),
+        ] {
+            std::fs::write(in_dir.path().join(name), json).unwrap();
+        }
+        let in_dir_arg = in_dir.path().to_str().unwrap();
+
+        // The ballot with too many options stops the batch if asked to.
+        let err = run_subcommand(
+            dir.path(),
+            &[
+                "--seed",
+                "01",
+                "encrypt-ballots",
+                "--in-dir",
+                in_dir_arg,
+                "--stop-on-error",
+            ],
+        )
+        .unwrap_err();
+        assert!(format!("{err:#}").contains("2.json"), "{err:#}");
+        std::fs::remove_dir_all(artifacts_dir.dir_path.join("record")).unwrap();
+
+        // Otherwise it is skipped.
+        run_subcommand(
+            dir.path(),
+            &["--seed", "01", "encrypt-ballots", "--in-dir", in_dir_arg],
+        )
+        .unwrap();
+
+        let batch_dirs = std::fs::read_dir(artifacts_dir.dir_path.join("record/ballots"))
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .collect::<Vec<_>>();
+        assert_eq!(batch_dirs.len(), 1);
+        let label: u128 = batch_dirs[0]
+            .file_name()
+            .unwrap()
+            .to_str()
+            .unwrap()
+            .parse()
+            .unwrap();
+
+        let closing = BallotChainClosing::from_stdioread(
+            &mut File::open(artifacts_dir.path(ArtifactFile::BallotChainClosing(label))).unwrap(),
+        )
+        .unwrap();
+        assert_eq!(closing.device, "EncryptBallots");
+        assert_eq!(closing.ballot_count, 2);
+
+        let ballots = std::fs::read_dir(&batch_dirs[0])
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .filter(|path| path != &artifacts_dir.path(ArtifactFile::BallotChainClosing(label)))
+            .map(|path| BallotEncrypted::from_stdioread(&mut File::open(path).unwrap()).unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(ballots.len(), 2);
+        for ballot in &ballots {
+            verify_ballot(pvd, ballot).unwrap();
+            assert_eq!(ballot.state, BallotState::Cast);
+        }
+
+        // The files are named by ballot id rather than in the order of encryption, but the
+        // published closing code is that of the chain over both ballots in one order.
+        let chain_of = |ballots: [&BallotEncrypted; 2]| {
+            let mut chain = BallotChain::new(&pvd.hashes_ext.h_e, "EncryptBallots");
+            for ballot in ballots {
+                chain.add(&ballot.confirmation_code);
+            }
+            chain.closing()
+        };
+        assert!(
+            chain_of([&ballots[0], &ballots[1]]) == closing
+                || chain_of([&ballots[1], &ballots[0]]) == closing
+        );
+    }
+}
@@ -8,6 +8,7 @@
 use std::path::PathBuf;
 
 use anyhow::{bail, Context, Result};
+use tracing::info;
 
 use eg::{guardian::GuardianIndex, guardian_secret_key::GuardianSecretKey};
 
@@ -62,7 +63,7 @@ impl Subcommand for GuardianSecretKeyGenerate {
             &election_parameters,
             self.i,
             self.name.clone(),
-        );
+        )?;
 
         let (mut stdiowrite, path) = subcommand_helper.artifacts_dir.out_file_stdiowrite(
             &self.secret_key_out_file,
@@ -77,7 +78,7 @@ impl Subcommand for GuardianSecretKeyGenerate {
 
         drop(stdiowrite);
 
-        eprintln!("Wrote {description}");
+        info!("Wrote {description}");
 
         Ok(())
     }
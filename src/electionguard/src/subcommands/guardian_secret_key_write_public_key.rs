@@ -8,6 +8,7 @@
 use std::path::PathBuf;
 
 use anyhow::{bail, Context, Result};
+use tracing::info;
 
 use eg::guardian::GuardianIndex;
 
@@ -77,7 +78,7 @@ impl Subcommand for GuardianSecretKeyWritePublicKey {
 
         drop(stdiowrite);
 
-        eprintln!("Wrote public key for guardian {i} to: {}", path.display());
+        info!("Wrote public key for guardian {i} to: {}", path.display());
 
         Ok(())
     }
@@ -0,0 +1,450 @@
+// Copyright (C) Microsoft Corporation. All rights reserved.
+
+#![deny(clippy::unwrap_used)]
+#![deny(clippy::expect_used)]
+#![deny(clippy::panic)]
+#![deny(clippy::manual_assert)]
+
+use std::{
+    sync::atomic::AtomicBool,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::{ensure, Context, Result};
+use tracing::{info, warn};
+
+use eg::{
+    ballot_encryptor::{BallotEncryptor, BallotPlaintext},
+    contest_selection::ContestSelection,
+    decryption_share::verify_decryption_end_to_end,
+    device::Device,
+    election_manifest::ElectionManifest,
+    example_election::ExampleElection,
+    example_election_manifest::example_election_manifest,
+    example_election_parameters::example_election_parameters,
+    guardian::GuardianIndex,
+    guardian_key_share::GuardianKeyShare,
+    share_distribution::ShareDistribution,
+    tally::{DecryptionShareSet, Tally},
+    vec1::Vec1,
+    verification::{verify_election_record, RecordContents, VerificationReport},
+};
+use util::csprng::Csprng;
+
+use crate::{
+    artifacts_dir::ArtifactFile, subcommand_helper::SubcommandHelper, subcommands::Subcommand,
+};
+
+/// Writes a complete, consistent example election record to the artifacts directory.
+///
+/// This uses the example election manifest and the standard parameters, with the number of
+/// guardians and quorum threshold taken from the command line. It writes the election
+/// parameters, the election manifest, every guardian's secret and public keys, the shares the
+/// guardians exchange, the joint election public key, the hashes, the pre-voting data, a
+/// number of cast ballots encrypted with random selections, their encrypted tally, every
+/// guardian's decryption shares of it, the decrypted tally, and a report of verifying the
+/// resulting election record.
+#[derive(clap::Args, Debug)]
+pub(crate) struct InitExampleElection {
+    /// Number of guardians.
+    #[arg(long, default_value = "5")]
+    n: GuardianIndex,
+
+    /// Decryption quorum threshold value.
+    #[arg(long, default_value = "3")]
+    k: GuardianIndex,
+
+    /// Number of ballots to encrypt.
+    #[arg(long, default_value_t = 10)]
+    ballots: usize,
+
+    /// Deprecated: use the global `--seed`, which takes hex digits.
+    /// Seed string from which to derive all randomness, as this subcommand's `--seed` did
+    /// before it became global. The same string derives the same randomness as it did then.
+    /// This is completely insecure and should only be used for testing.
+    #[arg(long)]
+    seed_string: Option<String>,
+}
+
+impl Subcommand for InitExampleElection {
+    fn uses_csprng(&self) -> bool {
+        true
+    }
+
+    fn do_it(&mut self, subcommand_helper: &mut SubcommandHelper) -> Result<()> {
+        let customization_data = b"InitExampleElection";
+        let mut csprng = if let Some(seed) = &self.seed_string {
+            ensure!(
+                subcommand_helper.clargs.seed.is_none(),
+                "--seed-string cannot be combined with --seed"
+            );
+
+            // Not subject to `--quiet`.
+            eprintln!("!!! WARNING: Using INSECURE deterministic seed from --seed-string. !!!");
+            warn!("--seed-string is deprecated, use the global --seed instead");
+
+            let mut seed_data = Vec::new();
+            seed_data.extend_from_slice(&(seed.len() as u64).to_be_bytes());
+            seed_data.extend_from_slice(seed.as_bytes());
+            seed_data.extend_from_slice(&(customization_data.len() as u64).to_be_bytes());
+            seed_data.extend_from_slice(customization_data);
+            Csprng::new(&seed_data)
+        } else {
+            subcommand_helper.get_csprng(customization_data)?
+        };
+
+        let artifacts_dir = &subcommand_helper.artifacts_dir;
+
+        ensure!(
+            self.k <= self.n,
+            "Quorum threshold k={} must be less than or equal to n={}",
+            self.k,
+            self.n
+        );
+
+        // Election parameters
+
+        let mut election_parameters = example_election_parameters();
+        election_parameters.varying_parameters.n = self.n;
+        election_parameters.varying_parameters.k = self.k;
+        election_parameters.validate(&mut csprng)?;
+
+        artifacts_dir.write_artifact(ArtifactFile::ElectionParameters, |w| {
+            election_parameters.to_stdiowrite(w)
+        })?;
+
+        // Election manifest
+
+        let election_manifest = example_election_manifest();
+        election_manifest.validate()?;
+
+        artifacts_dir.write_artifact(ArtifactFile::ElectionManifestCanonical, |w| {
+            election_manifest.to_stdiowrite_canonical(w)
+        })?;
+        artifacts_dir.write_artifact(ArtifactFile::ElectionManifestPretty, |w| {
+            election_manifest.to_stdiowrite_pretty(w)
+        })?;
+
+        // Guardian keys, and everything computed from them
+
+        let example_election =
+            ExampleElection::generate(&mut csprng, election_parameters, election_manifest)?;
+        let ExampleElection {
+            guardian_secret_keys,
+            guardian_public_keys,
+            pvd: pre_voting_data,
+            ..
+        } = &example_election;
+
+        for (secret_key, public_key) in guardian_secret_keys.iter().zip(guardian_public_keys) {
+            artifacts_dir.write_artifact(ArtifactFile::GuardianSecretKey(secret_key.i), |w| {
+                secret_key.to_stdiowrite(w)
+            })?;
+            artifacts_dir.write_artifact(ArtifactFile::GuardianPublicKey(public_key.i), |w| {
+                public_key.to_stdiowrite(w)
+            })?;
+        }
+
+        // Share distribution
+
+        let election_parameters = &pre_voting_data.parameters;
+
+        let mut share_distribution = ShareDistribution::new();
+        for secret_key in guardian_secret_keys {
+            share_distribution.add_shares_from(
+                &mut csprng,
+                election_parameters,
+                secret_key,
+                guardian_public_keys,
+            )?;
+        }
+
+        artifacts_dir.write_artifact(ArtifactFile::ShareDistribution, |w| {
+            share_distribution.to_stdiowrite(w)
+        })?;
+
+        // Joint election public key, hashes, and pre-voting data
+
+        artifacts_dir.write_artifact(ArtifactFile::JointElectionPublicKey, |w| {
+            pre_voting_data.public_key.to_stdiowrite(w)
+        })?;
+        artifacts_dir.write_artifact(ArtifactFile::Hashes, |w| {
+            pre_voting_data.hashes.to_stdiowrite(w)
+        })?;
+        artifacts_dir.write_artifact(ArtifactFile::HashesExt, |w| {
+            pre_voting_data.hashes_ext.to_stdiowrite(w)
+        })?;
+        artifacts_dir.write_artifact(ArtifactFile::ElectionPreVotingData, |w| {
+            pre_voting_data.to_stdiowrite(w)
+        })?;
+
+        // Check that the key material decrypts correctly before encrypting any ballots, with
+        // the key shares each guardian assembles from the share distribution.
+
+        let guardian_key_shares = guardian_secret_keys
+            .iter()
+            .map(|secret_key| {
+                GuardianKeyShare::compute(
+                    election_parameters,
+                    secret_key,
+                    guardian_public_keys,
+                    &share_distribution,
+                )
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        for (key_share, expected) in guardian_key_shares
+            .iter()
+            .zip(&example_election.guardian_key_shares)
+        {
+            ensure!(
+                key_share.public_key_share() == expected.public_key_share(),
+                "Guardian {}'s key share from the share distribution is not as expected",
+                key_share.i
+            );
+        }
+
+        // Every guardian, and just a quorum of the first k.
+        let k = election_parameters.varying_parameters.k.as_quantity();
+        for present in [&guardian_key_shares[..], &guardian_key_shares[..k]] {
+            verify_decryption_end_to_end(
+                &mut csprng,
+                pre_voting_data,
+                guardian_public_keys,
+                present,
+            )?;
+        }
+
+        info!("End-to-end decryption check passed.");
+
+        // Encrypted ballots
+
+        let label: u128 =
+            if subcommand_helper.clargs.is_deterministic() || self.seed_string.is_some() {
+                0
+            } else {
+                SystemTime::now()
+                    .duration_since(UNIX_EPOCH)?
+                    .as_secs()
+                    .into()
+            };
+
+        let device = Device::new("InitExampleElection", pre_voting_data.clone());
+        let mut encryptor = BallotEncryptor::new(&device);
+
+        let mut ballots = Vec::with_capacity(self.ballots);
+        for _ in 0..self.ballots {
+            let ballot_plaintext = random_ballot_plaintext(&pre_voting_data.manifest, &mut csprng)?;
+            let mut ballot = encryptor.encrypt(&mut csprng, &ballot_plaintext)?;
+            ballot.cast()?;
+
+            artifacts_dir.write_artifact(
+                ArtifactFile::EncryptedBallot(label, ballot.ballot_id()),
+                |w| ballot.to_stdiowrite(w),
+            )?;
+
+            ballots.push(ballot);
+        }
+
+        artifacts_dir.write_artifact(ArtifactFile::BallotChainClosing(label), |w| {
+            encryptor.chain().closing().to_stdiowrite(w)
+        })?;
+
+        // Tally
+
+        let tally = Tally::from_ballots(
+            &pre_voting_data.parameters.fixed_parameters,
+            &pre_voting_data.manifest,
+            &ballots,
+        )?;
+
+        artifacts_dir.write_artifact(ArtifactFile::EncryptedTally, |w| tally.to_stdiowrite(w))?;
+
+        // Decryption
+
+        let mut decryption_share_sets = Vec::with_capacity(guardian_key_shares.len());
+        for key_share in &guardian_key_shares {
+            let share_set =
+                DecryptionShareSet::compute(&mut csprng, pre_voting_data, key_share, &tally);
+
+            artifacts_dir.write_artifact(ArtifactFile::DecryptionShare(key_share.i), |w| {
+                share_set.to_stdiowrite(w)
+            })?;
+
+            decryption_share_sets.push(share_set);
+        }
+
+        let decrypted_tally = tally.decrypt(pre_voting_data, &decryption_share_sets)?;
+
+        artifacts_dir.write_artifact(ArtifactFile::DecryptedTally, |w| {
+            decrypted_tally.to_stdiowrite(w)
+        })?;
+
+        // Verification
+
+        let record_contents = RecordContents {
+            header: pre_voting_data,
+            opt_guardian_public_keys: Some(guardian_public_keys),
+            opt_ballots: Some(&ballots),
+            opt_tally: Some(&tally),
+            opt_decryption_shares: Some(&decryption_share_sets),
+            opt_decrypted_tally: Some(&decrypted_tally),
+        };
+        let report = verify_election_record(&record_contents, &AtomicBool::new(false))?;
+
+        artifacts_dir.write_artifact(ArtifactFile::VerificationReport, |w| {
+            write_verification_report(&report, w)
+        })?;
+
+        ensure!(
+            report.is_success(),
+            "The example election record failed verification: {}",
+            report.failures.join("; ")
+        );
+
+        info!(
+            "Wrote example election with n={}, k={}, and {} ballots to: {}",
+            self.n,
+            self.k,
+            self.ballots,
+            artifacts_dir.dir_path.display()
+        );
+
+        Ok(())
+    }
+}
+
+/// A plaintext ballot with random selections for every contest in the manifest.
+fn random_ballot_plaintext(
+    election_manifest: &ElectionManifest,
+    csprng: &mut Csprng,
+) -> Result<BallotPlaintext> {
+    let contests = &election_manifest.contests;
+
+    let mut contest_selections = Vec1::with_capacity(contests.len());
+    for contest_ix in contests.indices() {
+        let contest = contests
+            .get(contest_ix)
+            .with_context(|| format!("No contest {contest_ix}"))?;
+
+        contest_selections.try_push(ContestSelection::new_pick_random(
+            csprng,
+            contest.selection_limit,
+            contest.options.len(),
+        ))?;
+    }
+
+    Ok(BallotPlaintext {
+        opt_ballot_style: None,
+        contests: contest_selections,
+    })
+}
+
+/// Writes the status of each check, one per line, followed by any failures.
+fn write_verification_report(
+    report: &VerificationReport,
+    stdiowrite: &mut dyn std::io::Write,
+) -> Result<()> {
+    for (check, status) in &report.checks {
+        writeln!(stdiowrite, "{check}: {status:?}")?;
+    }
+    writeln!(
+        stdiowrite,
+        "Ballots checked: {} of {}",
+        report.ballots_processed, report.ballots_total
+    )?;
+    for failure in &report.failures {
+        writeln!(stdiowrite, "Failure: {failure}")?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod test {
+    use super::*;
+    use eg::tally::DecryptedTally;
+
+    use crate::{artifacts_dir::ArtifactsDir, test_artifacts::run_subcommand};
+
+    /// Runs `init-example-election` with a small number of guardians and ballots in a fresh
+    /// artifacts dir.
+    fn init_example_election() -> tempfile::TempDir {
+        let dir = tempfile::tempdir().unwrap();
+        run_subcommand(
+            dir.path(),
+            &[
+                "--seed",
+                "01",
+                "init-example-election",
+                "--n",
+                "3",
+                "--k",
+                "2",
+                "--ballots",
+                "2",
+            ],
+        )
+        .unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_init_example_election() {
+        let dir = init_example_election();
+        let artifacts_dir = ArtifactsDir::new(dir.path()).unwrap();
+
+        let mut artifact_files = vec![
+            ArtifactFile::ElectionParameters,
+            ArtifactFile::ElectionManifestCanonical,
+            ArtifactFile::ShareDistribution,
+            ArtifactFile::JointElectionPublicKey,
+            ArtifactFile::Hashes,
+            ArtifactFile::HashesExt,
+            ArtifactFile::ElectionPreVotingData,
+            ArtifactFile::BallotChainClosing(0),
+            ArtifactFile::EncryptedTally,
+            ArtifactFile::DecryptedTally,
+            ArtifactFile::VerificationReport,
+        ];
+        for i in 1..=3 {
+            let i = GuardianIndex::from_one_based_index(i).unwrap();
+            artifact_files.extend([
+                ArtifactFile::GuardianSecretKey(i),
+                ArtifactFile::GuardianPublicKey(i),
+                ArtifactFile::DecryptionShare(i),
+            ]);
+        }
+        for artifact_file in artifact_files {
+            assert!(artifacts_dir.exists(artifact_file), "{artifact_file:?}");
+        }
+
+        let decrypted_tally = DecryptedTally::from_stdioread(
+            &mut std::fs::File::open(artifacts_dir.path(ArtifactFile::DecryptedTally)).unwrap(),
+        )
+        .unwrap();
+        assert_eq!(decrypted_tally.ballot_count, 2);
+
+        let report =
+            std::fs::read_to_string(artifacts_dir.path(ArtifactFile::VerificationReport)).unwrap();
+        assert!(report.contains("Ballot correctness: Passed"), "{report}");
+        assert!(report.contains("Tally aggregation: Passed"), "{report}");
+        assert!(report.contains("Ballots checked: 2 of 2"), "{report}");
+
+        // The same seed gives the same record.
+        let other_dir = init_example_election();
+        let read = |dir: &tempfile::TempDir, artifact_file| {
+            std::fs::read(ArtifactsDir::new(dir.path()).unwrap().path(artifact_file)).unwrap()
+        };
+        for artifact_file in [
+            ArtifactFile::JointElectionPublicKey,
+            ArtifactFile::EncryptedTally,
+        ] {
+            assert_eq!(
+                read(&dir, artifact_file),
+                read(&other_dir, artifact_file),
+                "{artifact_file:?}"
+            );
+        }
+    }
+}
@@ -0,0 +1,596 @@
+// Copyright (C) Microsoft Corporation. All rights reserved.
+
+#![deny(clippy::unwrap_used)]
+#![deny(clippy::expect_used)]
+#![deny(clippy::panic)]
+#![deny(clippy::manual_assert)]
+
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Context, Result};
+
+use eg::{
+    artifact_format::{read_to_end_bounded, MAX_ARTIFACT_BYTES},
+    ballot::BallotEncrypted,
+    election_manifest::ElectionManifest,
+    election_parameters::ElectionParameters,
+    election_record::PreVotingData,
+    guardian_public_key::GuardianPublicKey,
+    guardian_secret_key::GuardianSecretKey,
+    hashes::Hashes,
+    hashes_ext::HashesExt,
+    joint_election_public_key::JointElectionPublicKey,
+};
+use util::csprng::Csprng;
+
+use crate::{
+    artifacts_dir::ArtifactFile, common_utils::load_election_parameters,
+    subcommand_helper::SubcommandHelper, subcommands::Subcommand,
+};
+
+/// The kinds of artifact which can be inspected.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, clap::ValueEnum)]
+pub(crate) enum ArtifactType {
+    ElectionParameters,
+    ElectionManifest,
+    GuardianSecretKey,
+    GuardianPublicKey,
+    JointElectionPublicKey,
+    Hashes,
+    HashesExt,
+    PreVotingData,
+    EncryptedBallot,
+}
+
+impl ArtifactType {
+    /// Guesses the artifact type from the file name, according to the default naming
+    /// convention of the artifacts directory.
+    fn from_path(path: &Path) -> Option<Self> {
+        let file_name = path.file_name()?.to_str()?;
+
+        let artifact_type = match file_name {
+            "election_parameters.json" => Self::ElectionParameters,
+            "election_manifest_pretty.json" | "election_manifest_canonical.bin" => {
+                Self::ElectionManifest
+            }
+            "joint_election_public_key.json" => Self::JointElectionPublicKey,
+            "hashes.json" => Self::Hashes,
+            "hashes_ext.json" => Self::HashesExt,
+            "election_record_header.json" => Self::PreVotingData,
+            _ if file_name.starts_with("guardian_") && file_name.ends_with(".SECRET_key.json") => {
+                Self::GuardianSecretKey
+            }
+            _ if file_name.starts_with("guardian_") && file_name.ends_with(".public_key.json") => {
+                Self::GuardianPublicKey
+            }
+            _ if file_name.starts_with("ballot.") && file_name.ends_with(".json") => {
+                Self::EncryptedBallot
+            }
+            _ => return None,
+        };
+
+        Some(artifact_type)
+    }
+
+    /// Returns true if validating this type of artifact requires the election parameters.
+    fn needs_election_parameters(self) -> bool {
+        matches!(
+            self,
+            Self::GuardianSecretKey | Self::GuardianPublicKey | Self::JointElectionPublicKey
+        )
+    }
+}
+
+/// Reads an artifact file, validates it as far as possible, and prints a summary of its
+/// contents.
+#[derive(clap::Args, Debug, Default)]
+pub(crate) struct Inspect {
+    /// The artifact file to inspect.
+    file: PathBuf,
+
+    /// The type of the artifact.
+    /// Default is to guess from the file name.
+    #[arg(long = "type", value_enum)]
+    opt_type: Option<ArtifactType>,
+}
+
+impl Subcommand for Inspect {
+    fn uses_csprng(&self) -> bool {
+        true
+    }
+
+    fn do_it(&mut self, subcommand_helper: &mut SubcommandHelper) -> Result<()> {
+        let artifact_type = match self.opt_type.or_else(|| ArtifactType::from_path(&self.file)) {
+            Some(artifact_type) => artifact_type,
+            None => bail!(
+                "Can't tell what kind of artifact this is from its name: {}\nSpecify it with --type",
+                self.file.display()
+            ),
+        };
+
+        let mut csprng = subcommand_helper.get_csprng(b"Inspect")?;
+
+        // Some artifacts can only be validated against the election parameters.
+        let opt_election_parameters = if artifact_type.needs_election_parameters()
+            && subcommand_helper
+                .artifacts_dir
+                .exists(ArtifactFile::ElectionParameters)
+        {
+            Some(load_election_parameters(
+                &subcommand_helper.artifacts_dir,
+                &mut csprng,
+            )?)
+        } else {
+            None
+        };
+
+        let (mut stdioread, path) = subcommand_helper
+            .artifacts_dir
+            .in_file_stdioread(&Some(self.file.clone()), None)?;
+
+        let context = || format!("Inspecting {artifact_type:?}: {}", path.display());
+
+        let bytes = read_to_end_bounded(
+            stdioread.as_mut(),
+            &format!("{artifact_type:?}"),
+            MAX_ARTIFACT_BYTES,
+        )
+        .with_context(context)?;
+
+        println!("{}: {artifact_type:?}", path.display());
+
+        inspect_artifact(
+            artifact_type,
+            &mut bytes.as_slice(),
+            &mut csprng,
+            &opt_election_parameters,
+            &mut std::io::stdout(),
+        )
+        .with_context(context)
+    }
+}
+
+/// Validates an artifact of the given type and writes a summary of it.
+fn inspect_artifact(
+    artifact_type: ArtifactType,
+    stdioread: &mut dyn std::io::Read,
+    csprng: &mut Csprng,
+    opt_election_parameters: &Option<ElectionParameters>,
+    stdiowrite: &mut dyn std::io::Write,
+) -> Result<()> {
+    use ArtifactType::*;
+
+    match artifact_type {
+        ElectionParameters => inspect_election_parameters(stdioread, stdiowrite, csprng)?,
+        ElectionManifest => inspect_election_manifest(stdioread, stdiowrite)?,
+        GuardianSecretKey => {
+            inspect_guardian_secret_key(stdioread, stdiowrite, opt_election_parameters)?
+        }
+        GuardianPublicKey => {
+            inspect_guardian_public_key(stdioread, stdiowrite, opt_election_parameters)?
+        }
+        JointElectionPublicKey => {
+            inspect_joint_election_public_key(stdioread, stdiowrite, opt_election_parameters)?
+        }
+        Hashes => inspect_hashes(stdioread, stdiowrite)?,
+        HashesExt => inspect_hashes_ext(stdioread, stdiowrite)?,
+        PreVotingData => inspect_pre_voting_data(stdioread, stdiowrite)?,
+        EncryptedBallot => inspect_encrypted_ballot(stdioread, stdiowrite)?,
+    }
+
+    if artifact_type.needs_election_parameters() && opt_election_parameters.is_none() {
+        writeln!(
+            stdiowrite,
+            "    (not validated, because the election parameters are not available)"
+        )?;
+    }
+
+    Ok(())
+}
+
+fn inspect_election_parameters(
+    stdioread: &mut dyn std::io::Read,
+    stdiowrite: &mut dyn std::io::Write,
+    csprng: &mut Csprng,
+) -> Result<()> {
+    let election_parameters = ElectionParameters::from_stdioread_validated(stdioread, csprng)?;
+
+    let fixed_parameters = &election_parameters.fixed_parameters;
+    let varying_parameters = &election_parameters.varying_parameters;
+
+    match &fixed_parameters.opt_ElectionGuard_Design_Specification {
+        Some(version) => writeln!(stdiowrite, "    spec version: {version}")?,
+        None => writeln!(stdiowrite, "    spec version: (none)")?,
+    }
+    writeln!(
+        stdiowrite,
+        "    matches standard parameters: {}",
+        yes_no(fixed_parameters.matches_standard().is_ok())
+    )?;
+    writeln!(
+        stdiowrite,
+        "    p: {} bits",
+        fixed_parameters.p.as_ref().bits()
+    )?;
+    writeln!(
+        stdiowrite,
+        "    q: {} bits",
+        fixed_parameters.q.as_ref().bits()
+    )?;
+    writeln!(stdiowrite, "    n: {}", varying_parameters.n)?;
+    writeln!(stdiowrite, "    k: {}", varying_parameters.k)?;
+    writeln!(stdiowrite, "    date: {:?}", varying_parameters.date)?;
+    writeln!(stdiowrite, "    info: {:?}", varying_parameters.info)?;
+
+    Ok(())
+}
+
+fn inspect_election_manifest(
+    stdioread: &mut dyn std::io::Read,
+    stdiowrite: &mut dyn std::io::Write,
+) -> Result<()> {
+    let election_manifest = ElectionManifest::from_stdioread_validated(stdioread)?;
+
+    writeln!(stdiowrite, "    label: {:?}", election_manifest.label)?;
+    writeln!(
+        stdiowrite,
+        "    contests: {}",
+        election_manifest.contests.len()
+    )?;
+    writeln!(
+        stdiowrite,
+        "    ballot styles: {}",
+        election_manifest.ballot_styles.len()
+    )?;
+    writeln!(stdiowrite)?;
+    for line in election_manifest.stats().to_string().lines() {
+        writeln!(stdiowrite, "    {line}")?;
+    }
+
+    Ok(())
+}
+
+fn inspect_guardian_secret_key(
+    stdioread: &mut dyn std::io::Read,
+    stdiowrite: &mut dyn std::io::Write,
+    opt_election_parameters: &Option<ElectionParameters>,
+) -> Result<()> {
+    let guardian_secret_key = GuardianSecretKey::from_stdioread(stdioread)?;
+
+    if let Some(election_parameters) = opt_election_parameters {
+        guardian_secret_key.validate(election_parameters)?;
+    }
+
+    // Don't print anything secret.
+    writeln!(stdiowrite, "    guardian number: {}", guardian_secret_key.i)?;
+    writeln!(stdiowrite, "    name: {:?}", guardian_secret_key.opt_name)?;
+    writeln!(
+        stdiowrite,
+        "    secret coefficients: {}",
+        guardian_secret_key.secret_coefficients.0.len()
+    )?;
+    writeln!(
+        stdiowrite,
+        "    coefficient commitments: {}",
+        guardian_secret_key.coefficient_commitments.0.len()
+    )?;
+
+    Ok(())
+}
+
+fn inspect_guardian_public_key(
+    stdioread: &mut dyn std::io::Read,
+    stdiowrite: &mut dyn std::io::Write,
+    opt_election_parameters: &Option<ElectionParameters>,
+) -> Result<()> {
+    let guardian_public_key = GuardianPublicKey::from_stdioread(stdioread)?;
+
+    if let Some(election_parameters) = opt_election_parameters {
+        guardian_public_key.validate(election_parameters)?;
+    }
+
+    writeln!(stdiowrite, "    guardian number: {}", guardian_public_key.i)?;
+    writeln!(stdiowrite, "    name: {:?}", guardian_public_key.opt_name)?;
+    writeln!(
+        stdiowrite,
+        "    coefficient commitments: {}",
+        guardian_public_key.coefficient_commitments.0.len()
+    )?;
+    writeln!(
+        stdiowrite,
+        "    coefficient proofs present: {}",
+        yes_no(!guardian_public_key.coefficient_proofs.is_empty())
+    )?;
+
+    Ok(())
+}
+
+fn inspect_joint_election_public_key(
+    stdioread: &mut dyn std::io::Read,
+    stdiowrite: &mut dyn std::io::Write,
+    opt_election_parameters: &Option<ElectionParameters>,
+) -> Result<()> {
+    let joint_election_public_key = JointElectionPublicKey::from_stdioread(stdioread)?;
+
+    if let Some(election_parameters) = opt_election_parameters {
+        joint_election_public_key.validate(election_parameters)?;
+    }
+
+    writeln!(
+        stdiowrite,
+        "    K: {} bits",
+        joint_election_public_key.joint_election_public_key.bits()
+    )?;
+
+    Ok(())
+}
+
+fn inspect_hashes(
+    stdioread: &mut dyn std::io::Read,
+    stdiowrite: &mut dyn std::io::Write,
+) -> Result<()> {
+    let hashes = Hashes::from_stdioread_validated(stdioread)?;
+
+    writeln!(stdiowrite, "    h_p: {}", hashes.h_p)?;
+    writeln!(stdiowrite, "    h_m: {}", hashes.h_m)?;
+    writeln!(stdiowrite, "    h_b: {}", hashes.h_b)?;
+
+    Ok(())
+}
+
+fn inspect_hashes_ext(
+    stdioread: &mut dyn std::io::Read,
+    stdiowrite: &mut dyn std::io::Write,
+) -> Result<()> {
+    let hashes_ext = HashesExt::from_stdioread_validated(stdioread)?;
+
+    writeln!(stdiowrite, "    h_e: {}", hashes_ext.h_e)?;
+
+    Ok(())
+}
+
+fn inspect_pre_voting_data(
+    stdioread: &mut dyn std::io::Read,
+    stdiowrite: &mut dyn std::io::Write,
+) -> Result<()> {
+    let mut json = String::new();
+    stdioread
+        .read_to_string(&mut json)
+        .context("Reading PreVotingData")?;
+    let pre_voting_data = PreVotingData::from_json_str(&json)?;
+
+    pre_voting_data.validate()?;
+
+    writeln!(
+        stdiowrite,
+        "    manifest: {:?}",
+        pre_voting_data.manifest.label
+    )?;
+    writeln!(
+        stdiowrite,
+        "    contests: {}",
+        pre_voting_data.manifest.contests.len()
+    )?;
+    writeln!(
+        stdiowrite,
+        "    n: {}",
+        pre_voting_data.parameters.varying_parameters.n
+    )?;
+    writeln!(
+        stdiowrite,
+        "    k: {}",
+        pre_voting_data.parameters.varying_parameters.k
+    )?;
+    writeln!(stdiowrite, "    h_b: {}", pre_voting_data.hashes.h_b)?;
+    writeln!(stdiowrite, "    h_e: {}", pre_voting_data.hashes_ext.h_e)?;
+
+    Ok(())
+}
+
+fn inspect_encrypted_ballot(
+    stdioread: &mut dyn std::io::Read,
+    stdiowrite: &mut dyn std::io::Write,
+) -> Result<()> {
+    let ballot = BallotEncrypted::from_stdioread(stdioread)?;
+
+    writeln!(
+        stdiowrite,
+        "    confirmation code: {}",
+        ballot.confirmation_code
+    )?;
+    writeln!(stdiowrite, "    state: {:?}", ballot.state)?;
+    writeln!(stdiowrite, "    date: {:?}", ballot.date)?;
+    writeln!(stdiowrite, "    device: {:?}", ballot.device)?;
+    match ballot.opt_ballot_style {
+        Some(ballot_style) => writeln!(stdiowrite, "    ballot style: {ballot_style}")?,
+        None => writeln!(stdiowrite, "    ballot style: (not recorded)")?,
+    }
+    writeln!(stdiowrite, "    contests: {}", ballot.contests.len())?;
+
+    Ok(())
+}
+
+fn yes_no(b: bool) -> &'static str {
+    if b {
+        "yes"
+    } else {
+        "no"
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod test {
+    use super::*;
+    use eg::{example_election::ExampleElection, guardian::GuardianIndex};
+
+    use crate::{
+        artifacts_dir::ArtifactsDir,
+        test_artifacts::{run_subcommand, write_artifact, write_example_election},
+    };
+
+    /// Inspects `artifact_file`, guessing its type from its name, and returns the type and
+    /// the summary.
+    fn inspect(
+        artifacts_dir: &ArtifactsDir,
+        artifact_file: ArtifactFile,
+        csprng: &mut Csprng,
+        opt_election_parameters: &Option<ElectionParameters>,
+    ) -> (ArtifactType, String) {
+        let path = artifacts_dir.path(artifact_file);
+        let artifact_type = ArtifactType::from_path(&path).unwrap();
+        let mut summary = Vec::new();
+        inspect_artifact(
+            artifact_type,
+            &mut std::fs::File::open(path).unwrap(),
+            csprng,
+            opt_election_parameters,
+            &mut summary,
+        )
+        .unwrap();
+        (artifact_type, String::from_utf8(summary).unwrap())
+    }
+
+    #[test]
+    fn test_inspect() {
+        let mut csprng = Csprng::new(b"test_inspect");
+
+        let example_election = ExampleElection::with_first_contests(&mut csprng, 1).unwrap();
+        let pvd = &example_election.pvd;
+        let ballot = example_election
+            .encrypt_ballots(&mut csprng, &[r#"{ "contests": [ { "vote": [1, 0] } ] }"#])
+            .unwrap()
+            .remove(0);
+
+        let dir = tempfile::tempdir().unwrap();
+        let artifacts_dir = ArtifactsDir::new(dir.path()).unwrap();
+        write_example_election(&artifacts_dir, &example_election);
+        let i_1 = GuardianIndex::from_one_based_index(1).unwrap();
+        write_artifact(&artifacts_dir, ArtifactFile::GuardianSecretKey(i_1), |w| {
+            example_election.guardian_secret_keys[0].to_stdiowrite(w)
+        });
+        write_artifact(&artifacts_dir, ArtifactFile::ElectionManifestPretty, |w| {
+            pvd.manifest.to_stdiowrite_pretty(w)
+        });
+        write_artifact(&artifacts_dir, ArtifactFile::ElectionPreVotingData, |w| {
+            pvd.to_stdiowrite(w)
+        });
+        let ballot_file = ArtifactFile::EncryptedBallot(0, ballot.ballot_id());
+        write_artifact(&artifacts_dir, ballot_file, |w| ballot.to_stdiowrite(w));
+
+        let opt_election_parameters = Some(pvd.parameters.clone());
+        let mut inspect = |artifact_file| {
+            inspect(
+                &artifacts_dir,
+                artifact_file,
+                &mut csprng,
+                &opt_election_parameters,
+            )
+        };
+
+        let (artifact_type, summary) = inspect(ArtifactFile::ElectionParameters);
+        assert_eq!(artifact_type, ArtifactType::ElectionParameters);
+        assert!(summary.contains("    n: 5\n    k: 3\n"), "{summary}");
+
+        for artifact_file in [
+            ArtifactFile::ElectionManifestCanonical,
+            ArtifactFile::ElectionManifestPretty,
+        ] {
+            let (artifact_type, summary) = inspect(artifact_file);
+            assert_eq!(artifact_type, ArtifactType::ElectionManifest);
+            assert!(summary.contains("    contests: 1\n"), "{summary}");
+        }
+
+        let (artifact_type, summary) = inspect(ArtifactFile::GuardianSecretKey(i_1));
+        assert_eq!(artifact_type, ArtifactType::GuardianSecretKey);
+        assert!(summary.starts_with("    guardian number: 1\n"), "{summary}");
+        assert!(
+            summary.contains("    secret coefficients: 3\n"),
+            "{summary}"
+        );
+
+        let i_2 = GuardianIndex::from_one_based_index(2).unwrap();
+        let (artifact_type, summary) = inspect(ArtifactFile::GuardianPublicKey(i_2));
+        assert_eq!(artifact_type, ArtifactType::GuardianPublicKey);
+        assert!(summary.starts_with("    guardian number: 2\n"), "{summary}");
+        assert!(
+            summary.contains("    coefficient proofs present: yes\n"),
+            "{summary}"
+        );
+
+        let (artifact_type, summary) = inspect(ArtifactFile::JointElectionPublicKey);
+        assert_eq!(artifact_type, ArtifactType::JointElectionPublicKey);
+        assert!(summary.starts_with("    K: "), "{summary}");
+
+        let (artifact_type, summary) = inspect(ArtifactFile::Hashes);
+        assert_eq!(artifact_type, ArtifactType::Hashes);
+        assert!(
+            summary.contains(&format!("    h_b: {}\n", pvd.hashes.h_b)),
+            "{summary}"
+        );
+
+        let (artifact_type, summary) = inspect(ArtifactFile::HashesExt);
+        assert_eq!(artifact_type, ArtifactType::HashesExt);
+        assert_eq!(summary, format!("    h_e: {}\n", pvd.hashes_ext.h_e));
+
+        let (artifact_type, summary) = inspect(ArtifactFile::ElectionPreVotingData);
+        assert_eq!(artifact_type, ArtifactType::PreVotingData);
+        assert!(
+            summary.contains(&format!("    h_e: {}\n", pvd.hashes_ext.h_e)),
+            "{summary}"
+        );
+
+        let (artifact_type, summary) = inspect(ballot_file);
+        assert_eq!(artifact_type, ArtifactType::EncryptedBallot);
+        assert!(
+            summary.starts_with(&format!(
+                "    confirmation code: {}\n",
+                ballot.confirmation_code
+            )),
+            "{summary}"
+        );
+
+        // A key without proofs, and without the election parameters to validate it against.
+        let mut public_key = example_election.guardian_public_keys[1].clone();
+        public_key.coefficient_proofs.0.clear();
+        let mut json = Vec::new();
+        public_key.to_stdiowrite(&mut json).unwrap();
+        let mut summary = Vec::new();
+        inspect_artifact(
+            ArtifactType::GuardianPublicKey,
+            &mut json.as_slice(),
+            &mut csprng,
+            &None,
+            &mut summary,
+        )
+        .unwrap();
+        let summary = String::from_utf8(summary).unwrap();
+        assert!(
+            summary.contains("    coefficient proofs present: no\n"),
+            "{summary}"
+        );
+        assert!(summary.contains("(not validated"), "{summary}");
+
+        // A file which isn't named as any artifact can only be inspected with its type given.
+        let share_distribution_path = dir.path().join("public/share_distribution.json");
+        assert_eq!(ArtifactType::from_path(&share_distribution_path), None);
+        let unrecognized = dir.path().join("public/copy_of_hashes.json");
+        std::fs::copy(artifacts_dir.path(ArtifactFile::Hashes), &unrecognized).unwrap();
+        let unrecognized = unrecognized.to_str().unwrap();
+
+        let err = run_subcommand(dir.path(), &["inspect", unrecognized]).unwrap_err();
+        assert!(
+            err.to_string()
+                .starts_with("Can't tell what kind of artifact this is from its name"),
+            "{err}"
+        );
+        run_subcommand(dir.path(), &["inspect", unrecognized, "--type", "hashes"]).unwrap();
+
+        // Given the wrong type, the file fails to validate.
+        run_subcommand(
+            dir.path(),
+            &["inspect", unrecognized, "--type", "hashes-ext"],
+        )
+        .unwrap_err();
+    }
+}
@@ -5,12 +5,21 @@
 #![deny(clippy::panic)]
 #![deny(clippy::manual_assert)]
 
+mod combine_decryption_shares;
+mod compute_decryption_share;
+mod diff_keys;
+mod encrypt_ballots;
 mod guardian_secret_key_generate;
 //? TODO mod guardian_secret_key_write_encrypted_share;
 mod guardian_secret_key_write_public_key;
+mod init_example_election;
+mod inspect;
 mod none;
 mod preencrypted_ballot_generate;
 mod preencrypted_ballot_record;
+mod tally_ballots;
+mod threshold_info;
+mod verify_guardian_keys;
 mod verify_standard_parameters;
 //? TODO mod voter_write_random_selections;
 mod voter_write_confirmation_code;
@@ -96,6 +105,35 @@ pub(crate) enum Subcommands {
 
     /// Write the extended hash to a file.
     WriteHashesExt(crate::subcommands::write_hashes_ext::WriteHashesExt),
+
+    /// Write a complete example election to the artifacts directory.
+    InitExampleElection(crate::subcommands::init_example_election::InitExampleElection),
+
+    /// Validate an artifact file and print a summary of its contents.
+    Inspect(crate::subcommands::inspect::Inspect),
+
+    /// Describe how many guardians may be absent, or must collude, for a given n and k.
+    ThresholdInfo(crate::subcommands::threshold_info::ThresholdInfo),
+
+    /// Encrypt every plaintext ballot file in a directory.
+    EncryptBallots(crate::subcommands::encrypt_ballots::EncryptBallots),
+
+    /// Tally the encrypted ballots in a directory, and write the encrypted tally.
+    TallyBallots(crate::subcommands::tally_ballots::TallyBallots),
+
+    /// Compute a guardian's decryption shares of the encrypted tally, with proofs, and write
+    /// them to a file.
+    ComputeDecryptionShare(crate::subcommands::compute_decryption_share::ComputeDecryptionShare),
+
+    /// Combine the guardians' decryption shares of the encrypted tally, and write the
+    /// decrypted tally.
+    CombineDecryptionShares(crate::subcommands::combine_decryption_shares::CombineDecryptionShares),
+
+    /// Compare the guardian public keys in two directories.
+    DiffKeys(crate::subcommands::diff_keys::DiffKeys),
+
+    /// Verify the proofs of knowledge of every guardian public key.
+    VerifyGuardianKeys(crate::subcommands::verify_guardian_keys::VerifyGuardianKeys),
 }
 
 impl Default for Subcommands {
@@ -123,6 +161,15 @@ impl<'a> From<&'a mut Subcommands> for &'a mut dyn Subcommand {
             VoterWriteConfirmationCode(a) => a,
             WriteJointElectionPublicKey(a) => a,
             WriteHashesExt(a) => a,
+            InitExampleElection(a) => a,
+            Inspect(a) => a,
+            ThresholdInfo(a) => a,
+            EncryptBallots(a) => a,
+            TallyBallots(a) => a,
+            ComputeDecryptionShare(a) => a,
+            CombineDecryptionShares(a) => a,
+            DiffKeys(a) => a,
+            VerifyGuardianKeys(a) => a,
         }
     }
 }
@@ -8,6 +8,7 @@
 use std::time::{SystemTime, UNIX_EPOCH};
 
 use anyhow::{bail, Context, Result};
+use tracing::info;
 
 use eg::{ballot_style::BallotStyleIndex, device::Device, election_record::PreVotingData};
 use preencrypted::ballot_encrypting_tool::BallotEncryptingTool;
@@ -16,8 +17,8 @@ use util::file::create_path;
 use crate::{
     artifacts_dir::ArtifactFile,
     common_utils::{
-        load_election_parameters, load_hashes, load_hashes_ext, load_joint_election_public_key,
-        ElectionManifestSource,
+        load_election_parameters, load_hashes_current, load_hashes_ext,
+        load_joint_election_public_key, ElectionManifestSource,
     },
     subcommand_helper::SubcommandHelper,
     subcommands::Subcommand,
@@ -65,7 +66,11 @@ impl Subcommand for PreEncryptedBallotGenerate {
         let ballot_style_index =
             BallotStyleIndex::from_one_based_index(self.ballot_style_index).unwrap();
 
-        let hashes = load_hashes(&subcommand_helper.artifacts_dir)?;
+        let hashes = load_hashes_current(
+            &subcommand_helper.artifacts_dir,
+            &election_parameters,
+            &election_manifest,
+        )?;
         let hashes_ext = load_hashes_ext(&subcommand_helper.artifacts_dir)?;
         let jepk =
             load_joint_election_public_key(&subcommand_helper.artifacts_dir, &election_parameters)?;
@@ -124,7 +129,7 @@ impl Subcommand for PreEncryptedBallotGenerate {
                 .to_stdiowrite(bx_write.as_mut())
                 .with_context(|| format!("Writing pre-encrypted ballot to: {}", path.display()))?;
 
-            eprintln!("Wrote pre-encrypted ballot to: {}", path.display());
+            info!("Wrote pre-encrypted ballot to: {}", path.display());
 
             drop(bx_write);
 
@@ -6,6 +6,7 @@
 #![deny(clippy::manual_assert)]
 
 use anyhow::{bail, Context, Result};
+use tracing::warn;
 
 use eg::{
     ballot_style::BallotStyleIndex, device::Device, election_record::PreVotingData, hash::HValue,
@@ -19,8 +20,8 @@ use util::file::create_path;
 use crate::{
     artifacts_dir::ArtifactFile,
     common_utils::{
-        load_election_parameters, load_hashes, load_hashes_ext, load_joint_election_public_key,
-        ElectionManifestSource,
+        load_election_parameters, load_hashes_current, load_hashes_ext,
+        load_joint_election_public_key, ElectionManifestSource,
     },
     subcommand_helper::SubcommandHelper,
     subcommands::Subcommand,
@@ -72,7 +73,11 @@ impl Subcommand for PreEncryptedBallotRecord {
         let ballot_style_index =
             BallotStyleIndex::from_one_based_index(self.ballot_style_index).unwrap();
 
-        let hashes = load_hashes(&subcommand_helper.artifacts_dir)?;
+        let hashes = load_hashes_current(
+            &subcommand_helper.artifacts_dir,
+            &election_parameters,
+            &election_manifest,
+        )?;
         let hashes_ext = load_hashes_ext(&subcommand_helper.artifacts_dir)?;
         let jepk =
             load_joint_election_public_key(&subcommand_helper.artifacts_dir, &election_parameters)?;
@@ -150,7 +155,7 @@ impl Subcommand for PreEncryptedBallotRecord {
                     &None,
                     Some(ArtifactFile::EncryptedBallot(
                         self.ballots_in,
-                        encrypted_ballot.confirmation_code,
+                        encrypted_ballot.ballot_id(),
                     )),
                 )?;
 
@@ -159,7 +164,7 @@ impl Subcommand for PreEncryptedBallotRecord {
                     .with_context(|| format!("Writing encrypted ballot to: {}", path.display()))?;
                 drop(bx_write);
             } else {
-                eprintln!(
+                warn!(
                     "Regenerated ballot with nonce {} does not match ballot {}",
                     nonce, b_idx
                 );
@@ -0,0 +1,370 @@
+// Copyright (C) Microsoft Corporation. All rights reserved.
+
+#![deny(clippy::unwrap_used)]
+#![deny(clippy::expect_used)]
+#![deny(clippy::panic)]
+#![deny(clippy::manual_assert)]
+
+use std::{
+    fs::File,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{bail, Context, Result};
+use tracing::{info, warn};
+
+use eg::{
+    artifact_format::parse_json_artifact,
+    ballot::{BallotEncrypted, BallotState},
+    election_record::PreVotingData,
+    tally::{BallotReplayGuard, Tally},
+    verification::verify_ballot,
+};
+use util::file::create_path;
+
+use crate::{
+    artifacts_dir::ArtifactFile,
+    common_utils::{
+        load_election_parameters, load_hashes_current, load_hashes_ext,
+        load_joint_election_public_key, ElectionManifestSource,
+    },
+    subcommand_helper::SubcommandHelper,
+    subcommands::Subcommand,
+};
+
+/// Tally the encrypted ballots in a directory, and write the encrypted tally.
+///
+/// Only cast ballots are counted. Challenged and uncast ballots are verified and checked for
+/// replays like the others, but are left out.
+#[derive(clap::Args, Debug, Default)]
+pub(crate) struct TallyBallots {
+    /// Directory of encrypted ballots. Every `ballot.*.json` file in it or its subdirectories
+    /// is tallied. Default is the ballots directory of the election record in the artifacts
+    /// dir.
+    #[arg(long)]
+    in_dir: Option<PathBuf>,
+
+    /// Include ballots without verifying their proofs first.
+    #[arg(long)]
+    skip_verify: bool,
+
+    /// File to which to write the encrypted tally.
+    /// Default is in the artifacts dir.
+    /// If "-", write to stdout.
+    #[arg(long)]
+    out_file: Option<PathBuf>,
+}
+
+impl Subcommand for TallyBallots {
+    fn uses_csprng(&self) -> bool {
+        true
+    }
+
+    fn do_it(&mut self, subcommand_helper: &mut SubcommandHelper) -> Result<()> {
+        let mut csprng = subcommand_helper.get_csprng(b"TallyBallots")?;
+
+        let artifacts_dir = &subcommand_helper.artifacts_dir;
+
+        let election_parameters = load_election_parameters(artifacts_dir, &mut csprng)?;
+        let election_manifest = ElectionManifestSource::ArtifactFileElectionManifestCanonical
+            .load_election_manifest(artifacts_dir)?;
+        let hashes = load_hashes_current(artifacts_dir, &election_parameters, &election_manifest)?;
+        let hashes_ext = load_hashes_ext(artifacts_dir)?;
+        let jepk = load_joint_election_public_key(artifacts_dir, &election_parameters)?;
+
+        let pre_voting_data = PreVotingData::new(
+            election_manifest,
+            election_parameters,
+            hashes,
+            hashes_ext,
+            jepk,
+        );
+
+        let in_dir = match &self.in_dir {
+            Some(in_dir) => in_dir.clone(),
+            None => artifacts_dir.dir_path.join("record/ballots"),
+        };
+        let paths = encrypted_ballot_paths(&in_dir)?;
+
+        if self.skip_verify {
+            warn!("Ballots are tallied without verifying their proofs.");
+        }
+
+        let (tally, left_out) = tally_ballot_files(&pre_voting_data, &paths, !self.skip_verify)?;
+
+        if self.out_file.is_none() {
+            if let Some(parent) = artifacts_dir.path(ArtifactFile::EncryptedTally).parent() {
+                create_path(&parent.to_path_buf());
+            }
+        }
+
+        let (mut stdiowrite, path) = artifacts_dir
+            .out_file_stdiowrite(&self.out_file, Some(ArtifactFile::EncryptedTally))?;
+
+        tally
+            .to_stdiowrite(stdiowrite.as_mut())
+            .with_context(|| format!("Writing encrypted tally to: {}", path.display()))?;
+
+        drop(stdiowrite);
+
+        info!("Wrote encrypted tally to: {}", path.display());
+
+        println!(
+            "Tallied {} ballots, excluded {} which were not cast, rejected {}.",
+            tally.ballot_count, left_out.cnt_not_cast, left_out.cnt_rejected
+        );
+
+        Ok(())
+    }
+}
+
+/// Returns the paths of the encrypted ballot files in the directory and its subdirectories,
+/// sorted by path.
+fn encrypted_ballot_paths(dir: &Path) -> Result<Vec<PathBuf>> {
+    let mut paths = Vec::new();
+    let mut dirs = vec![dir.to_path_buf()];
+    while let Some(dir) = dirs.pop() {
+        for entry in std::fs::read_dir(&dir)
+            .with_context(|| format!("Reading directory: {}", dir.display()))?
+        {
+            let path = entry?.path();
+            if path.is_dir() {
+                dirs.push(path);
+            } else if path
+                .file_name()
+                .and_then(|name| name.to_str())
+                .is_some_and(|name| name.starts_with("ballot.") && name.ends_with(".json"))
+            {
+                paths.push(path);
+            }
+        }
+    }
+
+    if paths.is_empty() {
+        bail!("No encrypted ballot files found in: {}", dir.display());
+    }
+
+    paths.sort();
+    Ok(paths)
+}
+
+/// The numbers of ballots left out of a tally.
+#[derive(Debug, Default, PartialEq, Eq)]
+struct LeftOut {
+    /// Ballots which are valid, but challenged or not yet cast.
+    cnt_not_cast: usize,
+
+    /// Ballots which can't be read, fail verification, or are replays.
+    cnt_rejected: usize,
+}
+
+/// Adds the ballot in each file to a tally, one at a time, so that only one ballot is in
+/// memory at once.
+///
+/// A ballot which can't be read, fails verification (if `verify` is set), or is a replay of
+/// an earlier one, is reported and rejected. A ballot which is not cast is reported and
+/// excluded. Returns the tally and the numbers of ballots left out.
+fn tally_ballot_files(
+    pvd: &PreVotingData,
+    paths: &[PathBuf],
+    verify: bool,
+) -> Result<(Tally, LeftOut)> {
+    let fixed_parameters = &pvd.parameters.fixed_parameters;
+
+    let mut tally = Tally::new(&pvd.manifest);
+    let mut replay_guard = BallotReplayGuard::new();
+    let mut left_out = LeftOut::default();
+
+    for path in paths {
+        let result = read_ballot_file(path).and_then(|ballot| {
+            if verify {
+                verify_ballot(pvd, &ballot)?;
+            }
+            replay_guard.check(fixed_parameters, &ballot)?;
+            if ballot.state != BallotState::Cast {
+                info!(
+                    "Excluded ballot: {}: it is {:?}",
+                    path.display(),
+                    ballot.state
+                );
+                left_out.cnt_not_cast += 1;
+                return Ok(());
+            }
+            tally.add_ballot(fixed_parameters, &ballot)
+        });
+
+        if let Err(e) = result {
+            warn!("Rejected ballot: {}: {e:#}", path.display());
+            left_out.cnt_rejected += 1;
+        }
+    }
+
+    Ok((tally, left_out))
+}
+
+fn read_ballot_file(path: &Path) -> Result<BallotEncrypted> {
+    let mut file = File::open(path).with_context(|| format!("Opening: {}", path.display()))?;
+    parse_json_artifact(
+        &mut file,
+        "encrypted ballot",
+        BallotEncrypted::from_stdioread,
+    )
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod test {
+    use super::*;
+    use eg::{
+        contest_selection::ContestSelection, device::Device, example_election::ExampleElection,
+        index::Index, vec1::Vec1,
+    };
+    use util::csprng::Csprng;
+
+    use crate::{
+        artifacts_dir::ArtifactsDir,
+        test_artifacts::{run_subcommand, write_artifact, write_example_election},
+    };
+
+    fn read_encrypted_tally(path: &Path) -> Tally {
+        Tally::from_stdioread(&mut File::open(path).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_tally_ballots() {
+        let mut csprng = Csprng::new(b"test_tally_ballots");
+
+        let example_election = ExampleElection::with_first_contests(&mut csprng, 1).unwrap();
+        let pvd = &example_election.pvd;
+        let ballots = example_election
+            .encrypt_ballots(
+                &mut csprng,
+                &[
+                    r#"{ "contests": [ { "vote": [1, 0] } ] }"#,
+                    r#"{ "contests": [ { "vote": [0, 1] } ] }"#,
+                    r#"{ "contests": [ { "vote": [0, 1] } ] }"#,
+                ],
+            )
+            .unwrap();
+
+        let dir = tempfile::tempdir().unwrap();
+        let artifacts_dir = ArtifactsDir::new(dir.path()).unwrap();
+        write_example_election(&artifacts_dir, &example_election);
+
+        // The first two ballots, written as encrypt-ballots would.
+        for ballot in &ballots[..2] {
+            write_artifact(
+                &artifacts_dir,
+                ArtifactFile::EncryptedBallot(1, ballot.ballot_id()),
+                |w| ballot.to_stdiowrite(w),
+            );
+        }
+
+        // The third ballot with two of its ciphertexts swapped, which fails verification.
+        let mut json = Vec::new();
+        ballots[2].to_stdiowrite(&mut json).unwrap();
+        let mut tampered = BallotEncrypted::from_stdioread(&mut json.as_slice()).unwrap();
+        tampered
+            .contests
+            .get_mut(Index::from_one_based_index(1).unwrap())
+            .unwrap()
+            .selection
+            .swap(0, 1);
+        write_artifact(
+            &artifacts_dir,
+            ArtifactFile::EncryptedBallot(2, tampered.ballot_id()),
+            |w| tampered.to_stdiowrite(w),
+        );
+
+        // A replay of the first ballot, in another subdirectory. Only one of the two is
+        // tallied.
+        write_artifact(
+            &artifacts_dir,
+            ArtifactFile::EncryptedBallot(3, ballots[0].ballot_id()),
+            |w| ballots[0].to_stdiowrite(w),
+        );
+
+        // A challenged ballot, which is valid but not counted.
+        let device = Device::new("test", pvd.clone());
+        let ctest_selections: Vec1<ContestSelection> =
+            [ContestSelection { vote: vec![1, 0] }].try_into().unwrap();
+        let mut challenged =
+            BallotEncrypted::new_from_selections(&device, &mut csprng, &[4; 32], &ctest_selections)
+                .unwrap();
+        challenged.challenge().unwrap();
+        write_artifact(
+            &artifacts_dir,
+            ArtifactFile::EncryptedBallot(4, challenged.ballot_id()),
+            |w| challenged.to_stdiowrite(w),
+        );
+
+        // Not a ballot.
+        let ballots_dir = dir.path().join("record/ballots");
+        std::fs::write(ballots_dir.join("2/ballot.5.json"), b"{").unwrap();
+        std::fs::write(ballots_dir.join("notes.txt"), b"").unwrap();
+
+        assert_eq!(encrypted_ballot_paths(&ballots_dir).unwrap().len(), 6);
+
+        let fixed_parameters = &pvd.parameters.fixed_parameters;
+
+        run_subcommand(dir.path(), &["--seed", "01", "tally-ballots"]).unwrap();
+        let tally = read_encrypted_tally(&artifacts_dir.path(ArtifactFile::EncryptedTally));
+        let mut expected =
+            Tally::from_ballots(fixed_parameters, &pvd.manifest, &ballots[..2]).unwrap();
+        assert_eq!(tally, expected);
+
+        // Without verification, the tampered ballot is included.
+        let out_file = dir.path().join("unverified_tally.json");
+        run_subcommand(
+            dir.path(),
+            &[
+                "--seed",
+                "01",
+                "tally-ballots",
+                "--skip-verify",
+                "--out-file",
+                out_file.to_str().unwrap(),
+            ],
+        )
+        .unwrap();
+        let tally = read_encrypted_tally(&out_file);
+        expected.add_ballot(fixed_parameters, &tampered).unwrap();
+        assert_eq!(tally, expected);
+        assert_eq!(tally.ballot_count, 3);
+
+        // The counts of ballots left out, and a directory with no ballots.
+        let paths = encrypted_ballot_paths(&ballots_dir).unwrap();
+        assert_eq!(
+            tally_ballot_files(pvd, &paths, true).unwrap().1,
+            LeftOut {
+                cnt_not_cast: 1,
+                cnt_rejected: 3,
+            }
+        );
+        assert_eq!(
+            tally_ballot_files(pvd, &paths, false).unwrap().1,
+            LeftOut {
+                cnt_not_cast: 1,
+                cnt_rejected: 2,
+            }
+        );
+
+        let empty_dir = dir.path().join("empty");
+        std::fs::create_dir_all(&empty_dir).unwrap();
+        let err = run_subcommand(
+            dir.path(),
+            &[
+                "--seed",
+                "01",
+                "tally-ballots",
+                "--in-dir",
+                empty_dir.to_str().unwrap(),
+            ],
+        )
+        .unwrap_err();
+        assert!(
+            err.to_string().contains("No encrypted ballot files found"),
+            "{err:#}"
+        );
+    }
+}
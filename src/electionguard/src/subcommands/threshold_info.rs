@@ -0,0 +1,113 @@
+// Copyright (C) Microsoft Corporation. All rights reserved.
+
+#![deny(clippy::unwrap_used)]
+#![deny(clippy::expect_used)]
+#![deny(clippy::panic)]
+#![deny(clippy::manual_assert)]
+
+use anyhow::{ensure, Result};
+
+use eg::{guardian::GuardianIndex, varying_parameters::threshold_warnings};
+
+use crate::{subcommand_helper::SubcommandHelper, subcommands::Subcommand};
+
+/// Describes the fault tolerance and collusion resistance of a given number of guardians
+/// and quorum threshold. This is a pure computation which does not read or write any
+/// artifacts.
+#[derive(clap::Args, Debug)]
+pub(crate) struct ThresholdInfo {
+    /// Number of guardians.
+    #[arg(long)]
+    n: GuardianIndex,
+
+    /// Decryption quorum threshold value.
+    #[arg(long)]
+    k: GuardianIndex,
+}
+
+impl Subcommand for ThresholdInfo {
+    fn uses_csprng(&self) -> bool {
+        false
+    }
+
+    fn do_it(&mut self, _subcommand_helper: &mut SubcommandHelper) -> Result<()> {
+        for line in describe_threshold(self.n, self.k)? {
+            println!("{line}");
+        }
+
+        Ok(())
+    }
+}
+
+/// Returns the lines of the description of `n` guardians with a quorum threshold of `k`.
+///
+/// Any `k` guardians can decrypt the tally without the others, see
+/// [`eg::tally::Tally::decrypt`].
+fn describe_threshold(n: GuardianIndex, k: GuardianIndex) -> Result<Vec<String>> {
+    ensure!(
+        k <= n,
+        "The quorum threshold k={k} must not exceed the number of guardians n={n}"
+    );
+
+    let mut lines = vec![
+        format!("Guardians (n): {n}"),
+        format!("Quorum threshold (k): {k}"),
+        format!(
+            "Guardians who may be absent while still decrypting the tally (n - k): {}",
+            n.get_one_based_u32() - k.get_one_based_u32()
+        ),
+        format!("Colluding guardians needed to decrypt improperly (k): {k}"),
+    ];
+
+    lines.extend(
+        threshold_warnings(n, k)
+            .into_iter()
+            .map(|warning| format!("Warning: {warning}")),
+    );
+
+    Ok(lines)
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod test {
+    use super::*;
+
+    fn describe(n: u32, k: u32) -> Result<Vec<String>> {
+        describe_threshold(
+            GuardianIndex::from_one_based_index(n).unwrap(),
+            GuardianIndex::from_one_based_index(k).unwrap(),
+        )
+    }
+
+    #[test]
+    fn test_describe_threshold() {
+        let lines = describe(5, 3).unwrap();
+        assert_eq!(
+            lines,
+            [
+                "Guardians (n): 5",
+                "Quorum threshold (k): 3",
+                "Guardians who may be absent while still decrypting the tally (n - k): 2",
+                "Colluding guardians needed to decrypt improperly (k): 3",
+            ]
+        );
+
+        // k = 1 and k = n are both warned about.
+        let lines = describe(4, 1).unwrap();
+        assert!(lines.contains(
+            &"Guardians who may be absent while still decrypting the tally (n - k): 3".to_string()
+        ));
+        assert!(lines
+            .iter()
+            .any(|line| line.contains("Any single guardian")));
+
+        let lines = describe(4, 4).unwrap();
+        assert!(lines.contains(
+            &"Guardians who may be absent while still decrypting the tally (n - k): 0".to_string()
+        ));
+        assert!(lines.iter().any(|line| line.contains("Every guardian")));
+
+        assert!(describe(3, 4).is_err());
+    }
+}
@@ -0,0 +1,132 @@
+// Copyright (C) Microsoft Corporation. All rights reserved.
+
+#![deny(clippy::unwrap_used)]
+#![deny(clippy::expect_used)]
+#![deny(clippy::panic)]
+#![deny(clippy::manual_assert)]
+
+use anyhow::{ensure, Result};
+
+use eg::{
+    election_parameters::ElectionParameters, guardian_key_proof::verify_guardian_key_proofs,
+    guardian_public_key::GuardianPublicKey,
+};
+
+use crate::{
+    common_utils::{load_all_guardian_public_keys, load_election_parameters},
+    subcommand_helper::SubcommandHelper,
+    subcommands::Subcommand,
+};
+
+/// Verifies the proofs of knowledge carried by every guardian public key, as a check on the
+/// key ceremony. Unlike full verification, this needs no manifest or ballots.
+#[derive(clap::Args, Debug, Default)]
+pub(crate) struct VerifyGuardianKeys {}
+
+impl Subcommand for VerifyGuardianKeys {
+    fn uses_csprng(&self) -> bool {
+        true
+    }
+
+    fn do_it(&mut self, subcommand_helper: &mut SubcommandHelper) -> Result<()> {
+        let mut csprng = subcommand_helper.get_csprng(b"VerifyGuardianKeys")?;
+
+        let election_parameters =
+            load_election_parameters(&subcommand_helper.artifacts_dir, &mut csprng)?;
+
+        let guardian_public_keys =
+            load_all_guardian_public_keys(&subcommand_helper.artifacts_dir, &election_parameters)?;
+
+        verify_guardian_keys(
+            &election_parameters,
+            &guardian_public_keys,
+            &mut std::io::stdout(),
+        )
+    }
+}
+
+/// Verifies the proofs of every guardian public key, writing a pass or FAIL line for each
+/// guardian. Errors if any of them fail.
+fn verify_guardian_keys(
+    election_parameters: &ElectionParameters,
+    guardian_public_keys: &[GuardianPublicKey],
+    stdiowrite: &mut dyn std::io::Write,
+) -> Result<()> {
+    let mut cnt_failed = 0;
+    for guardian_public_key in guardian_public_keys {
+        let i = guardian_public_key.i;
+        match verify_guardian_key_proofs(election_parameters, guardian_public_key) {
+            Ok(()) => writeln!(stdiowrite, "Guardian {i}: pass")?,
+            Err(e) => {
+                cnt_failed += 1;
+                writeln!(stdiowrite, "Guardian {i}: FAIL: {e}")?;
+            }
+        }
+    }
+
+    ensure!(
+        cnt_failed == 0,
+        "{cnt_failed} of {} guardian public keys failed verification",
+        guardian_public_keys.len()
+    );
+
+    Ok(())
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod test {
+    use super::*;
+    use eg::example_election::ExampleElection;
+    use util::csprng::Csprng;
+
+    use crate::{
+        artifacts_dir::{ArtifactFile, ArtifactsDir},
+        test_artifacts::{run_subcommand, write_artifact, write_example_election},
+    };
+
+    #[test]
+    fn test_verify_guardian_keys() {
+        let mut csprng = Csprng::new(b"test_verify_guardian_keys");
+
+        let example_election = ExampleElection::with_first_contests(&mut csprng, 1).unwrap();
+        let election_parameters = &example_election.pvd.parameters;
+
+        let dir = tempfile::tempdir().unwrap();
+        let artifacts_dir = ArtifactsDir::new(dir.path()).unwrap();
+        write_example_election(&artifacts_dir, &example_election);
+
+        run_subcommand(dir.path(), &["verify-guardian-keys"]).unwrap();
+
+        // Guardian 2's key, with the response of one of its proofs altered.
+        let mut guardian_public_keys = example_election.guardian_public_keys.clone();
+        let proof = &mut guardian_public_keys[1].coefficient_proofs.0[1];
+        proof.v = (&proof.v + 1u8) % election_parameters.fixed_parameters.q.as_ref();
+        let corrupted = &guardian_public_keys[1];
+        write_artifact(
+            &artifacts_dir,
+            ArtifactFile::GuardianPublicKey(corrupted.i),
+            |w| corrupted.to_stdiowrite(w),
+        );
+
+        let err = run_subcommand(dir.path(), &["verify-guardian-keys"]).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "1 of 5 guardian public keys failed verification"
+        );
+
+        // Each guardian gets its own line.
+        let mut report = Vec::new();
+        verify_guardian_keys(election_parameters, &guardian_public_keys, &mut report).unwrap_err();
+        let report = String::from_utf8(report).unwrap();
+        let lines = report.lines().collect::<Vec<_>>();
+        assert_eq!(lines.len(), 5, "{report}");
+        for (line, i) in lines.iter().zip(1..) {
+            if i == 2 {
+                assert!(line.starts_with("Guardian 2: FAIL: "), "{line}");
+            } else {
+                assert_eq!(*line, format!("Guardian {i}: pass"));
+            }
+        }
+    }
+}
@@ -6,8 +6,11 @@
 #![deny(clippy::manual_assert)]
 
 use anyhow::{Context, Result};
+use tracing::info;
 
-use eg::standard_parameters::STANDARD_PARAMETERS;
+use eg::{
+    fixed_parameters::DEFAULT_PRIMALITY_TEST_ROUNDS, standard_parameters::STANDARD_PARAMETERS,
+};
 
 use crate::{subcommand_helper::SubcommandHelper, subcommands::Subcommand};
 
@@ -16,6 +19,11 @@ use crate::{subcommand_helper::SubcommandHelper, subcommands::Subcommand};
 pub(crate) struct VerifyStandardParameters {
     #[arg(long, default_value_t = 1)]
     passes: usize,
+
+    /// Number of Miller-Rabin rounds with which to test p and q for primality in each pass.
+    /// A composite passes r rounds with probability at most 4^-r.
+    #[arg(long, default_value_t = DEFAULT_PRIMALITY_TEST_ROUNDS)]
+    primality_rounds: usize,
 }
 
 impl Subcommand for VerifyStandardParameters {
@@ -26,19 +34,19 @@ impl Subcommand for VerifyStandardParameters {
     fn do_it(&mut self, subcommand_helper: &mut SubcommandHelper) -> Result<()> {
         let mut csprng = subcommand_helper.get_csprng(b"VerifyStandardParameters")?;
 
-        eprint!("Initializing standard parameters...");
+        info!("Initializing standard parameters...");
         let fixed_parameters = &*STANDARD_PARAMETERS;
-        eprintln!("Done.");
+        info!("Done.");
 
-        eprintln!("Verifying standard parameters...");
+        info!("Verifying standard parameters...");
         for pass in 0..self.passes {
-            eprintln!("    Starting pass {pass}/{}...", self.passes);
+            info!("    Starting pass {pass}/{}...", self.passes);
             fixed_parameters
-                .validate(&mut csprng)
+                .validate_with_rounds(&mut csprng, self.primality_rounds)
                 .context("Parameter verification failed")?;
         }
 
-        eprintln!("Done.");
+        info!("Done.");
 
         Ok(())
     }
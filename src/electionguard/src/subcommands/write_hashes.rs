@@ -8,6 +8,7 @@
 use std::path::PathBuf;
 
 use anyhow::{Context, Result};
+use tracing::info;
 
 use eg::hashes::Hashes;
 
@@ -61,7 +62,7 @@ impl Subcommand for WriteHashes {
 
         drop(stdiowrite);
 
-        eprintln!("Wrote hashes to: {}", path.display());
+        info!("Wrote hashes to: {}", path.display());
 
         Ok(())
     }
@@ -8,6 +8,7 @@
 use std::path::PathBuf;
 
 use anyhow::{Context, Result};
+use tracing::info;
 
 use eg::hashes_ext::HashesExt;
 
@@ -70,7 +71,7 @@ impl Subcommand for WriteHashesExt {
 
         drop(stdiowrite);
 
-        eprintln!("Wrote hashes ext to: {}", path.display());
+        info!("Wrote hashes ext to: {}", path.display());
 
         Ok(())
     }
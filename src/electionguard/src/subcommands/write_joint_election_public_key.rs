@@ -8,6 +8,7 @@
 use std::path::PathBuf;
 
 use anyhow::{Context, Result};
+use tracing::info;
 
 use eg::joint_election_public_key::JointElectionPublicKey;
 
@@ -58,7 +59,7 @@ impl Subcommand for WriteJointElectionPublicKey {
 
         drop(stdiowrite);
 
-        eprintln!("Wrote joint election public key to: {}", path.display());
+        info!("Wrote joint election public key to: {}", path.display());
 
         Ok(())
     }
@@ -8,10 +8,15 @@
 use std::path::PathBuf;
 
 use anyhow::{bail, Context, Result};
+use tracing::info;
+
+use eg::{hash::HValue, hashes::Hashes};
 
 use crate::{
-    artifacts_dir::ArtifactFile, common_utils::ElectionManifestSource,
-    subcommand_helper::SubcommandHelper, subcommands::Subcommand,
+    artifacts_dir::ArtifactFile,
+    common_utils::{load_election_parameters, ElectionManifestSource},
+    subcommand_helper::SubcommandHelper,
+    subcommands::Subcommand,
 };
 
 #[derive(Clone, Copy, Debug, Default, clap::ValueEnum)]
@@ -39,6 +44,30 @@ pub(crate) struct WriteManifest {
     #[arg(long)]
     pub in_example: bool,
 
+    /// Generate an example election manifest from this seed. The same seed and sizes always
+    /// give the same manifest.
+    #[arg(long)]
+    pub in_example_seeded: Option<String>,
+
+    /// Number of contests in the generated example election manifest.
+    #[arg(long, default_value_t = 10, requires = "in_example_seeded")]
+    pub example_contests: usize,
+
+    /// Number of options in each contest of the generated example election manifest.
+    #[arg(long, default_value_t = 4, requires = "in_example_seeded")]
+    pub example_options: usize,
+
+    /// Download the input election manifest from an HTTPS URL.
+    #[cfg(feature = "net")]
+    #[arg(long)]
+    pub in_url: Option<String>,
+
+    /// Verify that the input election manifest has this manifest hash, e.g. as announced for
+    /// the election, under the election parameters in the artifacts dir. Nothing is written
+    /// if it doesn't.
+    #[arg(long)]
+    pub expected_manifest_hash: Option<HValue>,
+
     /// Output format. Default is canonical.
     /// Unless `--out-file` is specified, the output is written to the appropriate file in the
     /// artifacts dir.
@@ -52,34 +81,70 @@ pub(crate) struct WriteManifest {
     out_file: Option<PathBuf>,
 }
 
+impl WriteManifest {
+    /// Resolves the options to a ElectionManifestSource.
+    fn election_manifest_source(&self) -> ElectionManifestSource {
+        #[cfg(feature = "net")]
+        if let Some(url) = self.in_url.as_ref() {
+            return ElectionManifestSource::Url(url.clone());
+        }
+
+        if self.in_pretty {
+            ElectionManifestSource::ArtifactFileElectionManifestPretty
+        } else if self.in_example {
+            ElectionManifestSource::Example
+        } else if let Some(seed) = self.in_example_seeded.as_ref() {
+            ElectionManifestSource::ExampleSeeded {
+                seed: seed.clone(),
+                num_contests: self.example_contests,
+                options_per_contest: self.example_options,
+            }
+        } else if let Some(path) = self.in_file.as_ref() {
+            ElectionManifestSource::SpecificFile(path.clone())
+        } else {
+            ElectionManifestSource::ArtifactFileElectionManifestCanonical
+        }
+    }
+}
+
 impl Subcommand for WriteManifest {
     fn uses_csprng(&self) -> bool {
-        false
+        // Loading the election parameters to compute the manifest hash tests p and q for
+        // primality.
+        self.expected_manifest_hash.is_some()
     }
 
     fn do_it(&mut self, subcommand_helper: &mut SubcommandHelper) -> Result<()> {
-        let cnt_in_specified = self.in_pretty as usize
+        #[allow(unused_mut)]
+        let mut cnt_in_specified = self.in_pretty as usize
             + self.in_canonical as usize
             + self.in_file.is_some() as usize
-            + self.in_example as usize;
+            + self.in_example as usize
+            + self.in_example_seeded.is_some() as usize;
+        #[cfg(feature = "net")]
+        {
+            cnt_in_specified += self.in_url.is_some() as usize;
+        }
         if cnt_in_specified > 1 {
-            bail!("Specify at most one of `--in-pretty`, `--in-canonical`, `--in-file`, or `--in-example`");
+            bail!("Specify at most one of `--in-pretty`, `--in-canonical`, `--in-file`, `--in-example`, `--in-example-seeded`, or `--in-url`");
         }
 
-        // Resolve the options to a ElectionManifestSource.
-        let election_manifest_source = if self.in_pretty {
-            ElectionManifestSource::ArtifactFileElectionManifestPretty
-        } else if self.in_example {
-            ElectionManifestSource::Example
-        } else if let Some(path) = self.in_file.as_ref() {
-            ElectionManifestSource::SpecificFile(path.clone())
-        } else {
-            ElectionManifestSource::ArtifactFileElectionManifestCanonical
-        };
+        let election_manifest_source = self.election_manifest_source();
 
         let election_manifest =
             election_manifest_source.load_election_manifest(&subcommand_helper.artifacts_dir)?;
 
+        if let Some(expected_manifest_hash) = &self.expected_manifest_hash {
+            let mut csprng = subcommand_helper.get_csprng(b"WriteManifest")?;
+            let election_parameters =
+                load_election_parameters(&subcommand_helper.artifacts_dir, &mut csprng)?;
+            let h_p = Hashes::compute_h_p(&election_parameters);
+            election_manifest
+                .verify_hash(&h_p, expected_manifest_hash)
+                .context("Verifying the election manifest hash")?;
+            info!("Election manifest hash matches: {expected_manifest_hash}");
+        }
+
         use ElectionManifestFormat::*;
         let (artifact_file, description) = match self.out_format {
             Canonical => (ArtifactFile::ElectionManifestCanonical, "canonical bytes"),
@@ -102,7 +167,7 @@ impl Subcommand for WriteManifest {
             )
         })?;
 
-        eprintln!(
+        info!(
             "Wrote election manifest {description} to: {}",
             path.display()
         );
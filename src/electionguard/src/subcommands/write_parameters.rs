@@ -8,6 +8,7 @@
 use std::path::PathBuf;
 
 use anyhow::{Context, Result};
+use tracing::info;
 
 use eg::{
     election_parameters::ElectionParameters, guardian::GuardianIndex,
@@ -98,7 +99,7 @@ impl Subcommand for WriteParameters {
 
         drop(stdiowrite);
 
-        eprintln!("Wrote election parameters to: {}", path.display());
+        info!("Wrote election parameters to: {}", path.display());
 
         Ok(())
     }
@@ -8,6 +8,7 @@
 use std::io::Write;
 
 use anyhow::Result;
+use tracing::info;
 
 use util::hex_dump::HexDump;
 
@@ -52,7 +53,7 @@ impl Subcommand for WriteRandomSeed {
 
         let seed_data = osrng_seed_data_for_csprng();
 
-        eprintln!(
+        info!(
             "Random seed data:\n{}",
             HexDump::new()
                 .line_prefix("    ")
@@ -62,7 +63,7 @@ impl Subcommand for WriteRandomSeed {
         );
 
         file.write_all(&seed_data)?;
-        eprintln!("{} bytes written to: {}", seed_data.len(), path.display());
+        info!("{} bytes written to: {}", seed_data.len(), path.display());
 
         Ok(())
     }
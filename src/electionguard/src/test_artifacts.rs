@@ -0,0 +1,79 @@
+// Copyright (C) Microsoft Corporation. All rights reserved.
+
+//! Helpers for tests which run subcommands against an artifacts dir.
+
+#![allow(clippy::unwrap_used)]
+
+use std::path::Path;
+
+use anyhow::Result;
+use clap::Parser;
+
+use eg::example_election::ExampleElection;
+
+use crate::{
+    artifacts_dir::{ArtifactFile, ArtifactsDir},
+    clargs::Clargs,
+    subcommand_helper::SubcommandHelper,
+    subcommands::Subcommand,
+};
+
+/// [`ArtifactsDir::write_artifact`], panicking on error.
+pub(crate) fn write_artifact<F>(artifacts_dir: &ArtifactsDir, artifact_file: ArtifactFile, f: F)
+where
+    F: FnOnce(&mut dyn std::io::Write) -> Result<()>,
+{
+    artifacts_dir.write_artifact(artifact_file, f).unwrap();
+}
+
+/// Writes the public artifacts of `example_election` which exist before voting begins, as
+/// the key ceremony would leave them.
+pub(crate) fn write_example_election(
+    artifacts_dir: &ArtifactsDir,
+    example_election: &ExampleElection,
+) {
+    let pvd = &example_election.pvd;
+
+    write_artifact(artifacts_dir, ArtifactFile::ElectionParameters, |w| {
+        pvd.parameters.to_stdiowrite(w)
+    });
+    write_artifact(
+        artifacts_dir,
+        ArtifactFile::ElectionManifestCanonical,
+        |w| pvd.manifest.to_stdiowrite_canonical(w),
+    );
+    write_artifact(artifacts_dir, ArtifactFile::Hashes, |w| {
+        pvd.hashes.to_stdiowrite(w)
+    });
+    write_artifact(artifacts_dir, ArtifactFile::HashesExt, |w| {
+        pvd.hashes_ext.to_stdiowrite(w)
+    });
+    write_artifact(artifacts_dir, ArtifactFile::JointElectionPublicKey, |w| {
+        pvd.public_key.to_stdiowrite(w)
+    });
+    for public_key in &example_election.guardian_public_keys {
+        write_artifact(
+            artifacts_dir,
+            ArtifactFile::GuardianPublicKey(public_key.i),
+            |w| public_key.to_stdiowrite(w),
+        );
+    }
+}
+
+/// Runs the subcommand given by `args`, which are the command line arguments after
+/// `--artifacts-dir`, as `main` would with `dir` as the artifacts dir.
+pub(crate) fn run_subcommand(dir: &Path, args: &[&str]) -> Result<()> {
+    let mut clargs = Clargs::try_parse_from(
+        ["electionguard", "--artifacts-dir", dir.to_str().unwrap()]
+            .iter()
+            .chain(args),
+    )?;
+
+    let mut subcommand = std::mem::take(&mut clargs.subcommand);
+    let subcommand: &mut dyn Subcommand = (&mut subcommand).into();
+
+    let uses_csprng = subcommand.uses_csprng();
+    let artifacts_dir = ArtifactsDir::new_with_naming(dir, clargs.artifacts_naming.into())?;
+    let mut subcommand_helper = SubcommandHelper::new(clargs, artifacts_dir, uses_csprng)?;
+    subcommand.do_it(&mut subcommand_helper)
+}
@@ -234,6 +234,8 @@ impl ContestPreEncrypted {
             contest_hash: self.contest_hash,
             proof_ballot_correctness,
             proof_selection_limit,
+            opt_blank_indicator: None,
+            opt_undervote: None,
         }
     }
 }
@@ -9,7 +9,7 @@
 use base64::{engine::Config, Engine};
 
 use num_bigint::BigUint;
-use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use serde::{Deserializer, Serialize, Serializer};
 
 #[cfg(biguint_serialize_base64)]
 const BASE64_ENGINE: base64::engine::general_purpose::GeneralPurpose =
@@ -58,6 +58,54 @@ where
     }
 }
 
+/// Accepts only a string. A `BigUint` written as a JSON number is rejected with an explanation,
+/// rather than the generic "invalid type" error, since a number too large for the parser would
+/// otherwise be read as an `f64` and silently lose precision.
+struct BigUintStrVisitor;
+
+impl serde::de::Visitor<'_> for BigUintStrVisitor {
+    type Value = String;
+
+    fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        formatter.write_str("a BigUint encoded as a string")
+    }
+
+    fn visit_str<E: serde::de::Error>(self, v: &str) -> Result<String, E> {
+        Ok(v.to_owned())
+    }
+
+    fn visit_string<E: serde::de::Error>(self, v: String) -> Result<String, E> {
+        Ok(v)
+    }
+
+    fn visit_u64<E: serde::de::Error>(self, v: u64) -> Result<String, E> {
+        Err(number_error(v))
+    }
+
+    fn visit_i64<E: serde::de::Error>(self, v: i64) -> Result<String, E> {
+        Err(number_error(v))
+    }
+
+    fn visit_u128<E: serde::de::Error>(self, v: u128) -> Result<String, E> {
+        Err(number_error(v))
+    }
+
+    fn visit_i128<E: serde::de::Error>(self, v: i128) -> Result<String, E> {
+        Err(number_error(v))
+    }
+
+    fn visit_f64<E: serde::de::Error>(self, v: f64) -> Result<String, E> {
+        Err(number_error(v))
+    }
+}
+
+fn number_error<E: serde::de::Error>(v: impl std::fmt::Display) -> E {
+    E::custom(format!(
+        "Invalid BigUint: found the number {v}, but a BigUint must be written as a string, \
+        e.g. \"base16:1F\", so that it can't lose precision"
+    ))
+}
+
 pub fn biguint_deserialize<'de, D>(deserializer: D) -> Result<BigUint, D::Error>
 where
     D: Deserializer<'de>,
@@ -66,13 +114,13 @@ where
 
     #[cfg(not(biguint_serialize_base64))]
     {
-        let s = String::deserialize(deserializer)?;
+        let s = deserializer.deserialize_any(BigUintStrVisitor)?;
         biguint_from_str_with_prefix(&s).map_err(D::Error::custom)
     }
 
     #[cfg(biguint_serialize_base64)]
     {
-        let s = String::deserialize(deserializer)?;
+        let s = deserializer.deserialize_any(BigUintStrVisitor)?;
 
         if !s.starts_with(BASE64_PREFIX) {
             return Err(D::Error::custom(format!(
@@ -103,3 +151,44 @@ where
         Ok(BigUint::from_bytes_be(&bytes))
     }
 }
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod test {
+    use super::*;
+    use serde::Deserialize;
+
+    #[derive(Debug, Deserialize)]
+    struct Wrapper(
+        #[serde(deserialize_with = "biguint_deserialize")]
+        #[allow(dead_code)]
+        BigUint,
+    );
+
+    #[test]
+    fn test_biguint_deserialize_rejects_numbers() {
+        #[cfg(not(biguint_serialize_base64))]
+        {
+            let Wrapper(n) = serde_json::from_str("\"base16:1F\"").unwrap();
+            assert_eq!(n, BigUint::from(31u8));
+        }
+
+        for json in ["31", "-31", "3.1e1", "123456789012345678901234567890"] {
+            let e = serde_json::from_str::<Wrapper>(json).unwrap_err();
+            assert!(
+                e.to_string().contains("must be written as a string"),
+                "{json}: {e}"
+            );
+        }
+
+        // Numbers out of the range of an f64 are rejected by the parser itself.
+        serde_json::from_str::<Wrapper>("1e400").unwrap_err();
+
+        // Other types get the generic error.
+        let e = serde_json::from_str::<Wrapper>("[31]").unwrap_err();
+        assert!(
+            e.to_string().contains("a BigUint encoded as a string"),
+            "{e}"
+        );
+    }
+}
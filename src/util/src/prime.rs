@@ -13,6 +13,7 @@ use std::convert::TryInto;
 use std::fmt::Debug;
 use std::num::NonZeroUsize;
 
+use anyhow::{ensure, Result};
 use num_bigint::BigUint;
 use num_traits::{One, Zero};
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
@@ -38,7 +39,14 @@ pub const PRIMES_TABLE_U8_BITS_RANGE: std::ops::Range<usize> = 2..9;
 const EXHAUSTIVE_TRIAL_DIVISION_MAX_L2: usize = 20;
 const EXHAUSTIVE_TRIAL_DIVISION_MAX: usize = 1 << EXHAUSTIVE_TRIAL_DIVISION_MAX_L2;
 
-const MILLER_RABIN_ITERATIONS: usize = 50;
+/// The number of Miller-Rabin rounds [`is_prime`] performs on values too large for trial
+/// division.
+///
+/// Each round passes a composite with probability at most 1/4, so `r` rounds accept a
+/// composite with probability at most `4^-r`: 2^-32 for 16 rounds, 2^-64 for 32, and 2^-128
+/// for 64. The bound is loose for numbers as large as a 4096-bit `p`, for which the actual
+/// probability is far smaller.
+pub const DEFAULT_PRIMALITY_TEST_ROUNDS: usize = 64;
 
 //? TODO Would prefer to use AsRef instead of Borrow, but it doesn't have
 // an automatic `impl AsRef<T> for T`, and we can't `impl AsRef<BigUint> for BigUint`
@@ -47,9 +55,30 @@ const MILLER_RABIN_ITERATIONS: usize = 50;
 // the hash, ord, and eq traits work exactly the same between BigUintPrime and BigUint.
 
 pub fn is_prime<T: Borrow<BigUint>>(n: &T, csprng: &mut Csprng) -> bool {
-    //? OPT: Maybe somehow we could defer Csprng creation until we know that we need randomized primality testing.
-
     let n: &BigUint = n.borrow();
+    is_prime_impl(n, DEFAULT_PRIMALITY_TEST_ROUNDS, csprng)
+}
+
+/// Same as [`is_prime`], but performs the specified number of Miller-Rabin rounds on values
+/// too large for trial division. See [`DEFAULT_PRIMALITY_TEST_ROUNDS`] for how the rounds
+/// relate to the probability of accepting a composite.
+///
+/// Errors if `rounds` is zero.
+pub fn is_prime_with_rounds<T: Borrow<BigUint>>(
+    n: &T,
+    rounds: usize,
+    csprng: &mut Csprng,
+) -> Result<bool> {
+    ensure!(
+        0 < rounds,
+        "At least one round of primality testing is required"
+    );
+    Ok(is_prime_impl(n.borrow(), rounds, csprng))
+}
+
+/// Requires `0 < rounds`.
+fn is_prime_impl(n: &BigUint, rounds: usize, csprng: &mut Csprng) -> bool {
+    //? OPT: Maybe somehow we could defer Csprng creation until we know that we need randomized primality testing.
 
     use num_integer::Roots;
 
@@ -86,7 +115,7 @@ pub fn is_prime<T: Borrow<BigUint>>(n: &T, csprng: &mut Csprng) -> bool {
                     return false;
                 }
 
-                miller_rabin(n, MILLER_RABIN_ITERATIONS, csprng)
+                miller_rabin(n, rounds, csprng)
             }
         }
     }